@@ -3,7 +3,9 @@ use std::path::PathBuf;
 use clap::builder::BoolishValueParser;
 use clap::{ArgAction, ColorChoice, Parser};
 
-use super::options::{OutputFormat, UiPresetArg};
+use super::options::{
+	ColorDepthArg, HyperlinkArg, OutputFormat, PdfPreviewArg, PreviewLayoutArg, UiPresetArg,
+};
 use super::styles::{cli_styles, long_version};
 
 /// Command-line arguments accepted by the `frz` binary.
@@ -41,13 +43,107 @@ pub(crate) struct CliArgs {
 	pub(crate) root: Option<PathBuf>,
 	#[arg(
 		short = 'q',
-		long,
+		long = "query",
+		visible_alias = "initial-query",
 		value_name = "QUERY",
-		help = "Provide an initial search query"
+		help = "Start with QUERY in the search box"
 	)]
 	pub(crate) initial_query: Option<String>,
+	#[arg(
+		long = "case-sensitive",
+		conflicts_with = "ignore_case",
+		help = "Always match letter case exactly (default: smart case)"
+	)]
+	pub(crate) case_sensitive: bool,
+	#[arg(
+		short = 'i',
+		long = "ignore-case",
+		help = "Always ignore letter case (default: smart case)"
+	)]
+	pub(crate) ignore_case: bool,
 	#[arg(long, value_name = "THEME", help = "Select a theme by name")]
 	pub(crate) theme: Option<String>,
+	#[arg(
+		long = "theme-file",
+		value_name = "PATH",
+		conflicts_with = "theme",
+		help = "Load a TOML or JSON theme file and select it by its file name"
+	)]
+	pub(crate) theme_file: Option<PathBuf>,
+	#[arg(
+		long = "color-depth",
+		value_enum,
+		value_name = "DEPTH",
+		help = "Colors the terminal can display (default: auto)"
+	)]
+	pub(crate) color_depth: Option<ColorDepthArg>,
+	#[arg(
+		long = "editor",
+		value_name = "CMD",
+		help = "Command opening the highlighted file on Ctrl+O (default: $VISUAL or $EDITOR)"
+	)]
+	pub(crate) editor: Option<String>,
+	#[arg(
+		long = "copy-absolute",
+		help = "Copy the highlighted path resolved under the root on Ctrl+Y, leaving the relative form to Alt+Y"
+	)]
+	pub(crate) copy_absolute: bool,
+	#[arg(
+		long = "quick-select-labels",
+		help = "Number the first nine rows shown, which Alt+1 through Alt+9 accept"
+	)]
+	pub(crate) quick_select_labels: bool,
+	#[arg(
+		long = "footer",
+		help = "Show the number of matches and how long the last query took under the results"
+	)]
+	pub(crate) footer: bool,
+	#[arg(
+		long = "no-markdown-preview",
+		help = "Preview markdown files as highlighted source instead of formatted text (Alt+M switches)"
+	)]
+	pub(crate) no_markdown_preview: bool,
+	#[arg(
+		long = "blame",
+		help = "Show who last changed each previewed line, from git blame"
+	)]
+	pub(crate) blame: bool,
+	#[arg(
+		long = "no-line-numbers",
+		help = "Start previews without line numbers (Alt+L shows them)"
+	)]
+	pub(crate) no_line_numbers: bool,
+	#[arg(
+		long = "pdf-preview",
+		value_enum,
+		value_name = "MODE",
+		help = "Preview PDFs as a rendered page or as their text (default: auto, text without terminal graphics)"
+	)]
+	pub(crate) pdf_preview: Option<PdfPreviewArg>,
+	#[arg(
+		long = "preview-max-image-size",
+		value_name = "PIXELS",
+		help = "Shrink previewed images larger than this on either side (default: 4096)"
+	)]
+	pub(crate) preview_max_image_size: Option<u32>,
+	#[arg(
+		long = "fold-diacritics",
+		help = "Match accented letters by their base letter, so resume finds résumé"
+	)]
+	pub(crate) fold_diacritics: bool,
+	#[arg(
+		long = "preview-layout",
+		value_enum,
+		value_name = "LAYOUT",
+		help = "Place the preview beside or below the results (default: horizontal)"
+	)]
+	pub(crate) preview_layout: Option<PreviewLayoutArg>,
+	#[arg(
+		long = "preview-split",
+		value_name = "RATIO",
+		help = "Share of the space given to the results when previewing (default: 0.5)"
+	)]
+	pub(crate) preview_split: Option<f32>,
 	#[arg(
 		short = 'u',
 		long = "ui-preset",
@@ -119,6 +215,13 @@ pub(crate) struct CliArgs {
     )]
 	pub(crate) respect_ignore_files: Option<bool>,
 	#[arg(
+        long = "respect-gitignore",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with_all = ["git_ignore", "git_global", "git_exclude"],
+        help = "Apply git ignore rules (.gitignore, git exclude and global excludes); use --git-ignore, --git-global or --git-exclude to pick them one by one"
+    )]
+	pub(crate) respect_gitignore: Option<bool>,
+	#[arg(
         long = "git-ignore",
         value_parser = BoolishValueParser::new(),
         help = "Respect .gitignore files"
@@ -136,11 +239,29 @@ pub(crate) struct CliArgs {
         help = "Respect git exclude files"
     )]
 	pub(crate) git_exclude: Option<bool>,
+	#[arg(
+        long = "include-dirs",
+        value_parser = BoolishValueParser::new(),
+        help = "List directories as selectable results"
+    )]
+	pub(crate) include_dirs: Option<bool>,
+	#[arg(
+        long = "dedupe",
+        value_parser = BoolishValueParser::new(),
+        help = "List each file once when hard links or symlinks lead to it"
+    )]
+	pub(crate) dedupe: Option<bool>,
+	#[arg(
+        long = "mtime",
+        value_parser = BoolishValueParser::new(),
+        help = "Record modification times so Alt+S can order results by them"
+    )]
+	pub(crate) mtime: Option<bool>,
 	#[arg(
 		short = 'j',
 		long,
 		value_name = "NUM",
-		help = "Limit the number of indexing threads"
+		help = "Limit the number of indexing and matching threads"
 	)]
 	pub(crate) threads: Option<usize>,
 	#[arg(
@@ -157,6 +278,20 @@ pub(crate) struct CliArgs {
 		help = "Restrict search to specific file extensions"
 	)]
 	pub(crate) extensions: Option<Vec<String>>,
+	#[arg(
+		long = "include",
+		value_delimiter = ',',
+		value_name = "GLOB",
+		help = "Only index paths matching these globs"
+	)]
+	pub(crate) include_globs: Option<Vec<String>>,
+	#[arg(
+		long = "exclude",
+		value_delimiter = ',',
+		value_name = "GLOB",
+		help = "Skip paths matching these globs (wins over --include)"
+	)]
+	pub(crate) exclude_globs: Option<Vec<String>>,
 	#[arg(
 		long = "context-label",
 		value_name = "TEXT",
@@ -170,6 +305,49 @@ pub(crate) struct CliArgs {
 		help = "Comma-separated directory names to always ignore"
 	)]
 	pub(crate) global_ignores: Option<Vec<String>>,
+	#[arg(long = "no-history", help = "Neither load nor save the query history")]
+	pub(crate) no_history: bool,
+	#[arg(
+		long = "history-size",
+		value_name = "NUM",
+		conflicts_with = "no_history",
+		help = "Maximum number of queries kept in the history file and offered for recall"
+	)]
+	pub(crate) history_size: Option<usize>,
+	#[arg(
+		long = "no-frecency",
+		help = "Neither rank by nor record previously selected files"
+	)]
+	pub(crate) no_frecency: bool,
+	#[arg(
+		long = "no-restore-selection",
+		help = "Neither highlight nor record the file selected last under the root"
+	)]
+	pub(crate) no_restore_selection: bool,
+	#[arg(
+		long = "debounce",
+		value_name = "MS",
+		help = "Wait until typing has paused this long before searching"
+	)]
+	pub(crate) debounce_ms: Option<u64>,
+	#[arg(
+		long = "min-query-length",
+		value_name = "NUM",
+		help = "List rows unfiltered until the query is at least this long"
+	)]
+	pub(crate) min_query_length: Option<usize>,
+	#[arg(
+		long = "max-results",
+		value_name = "NUM",
+		help = "Show at most this many matches (default: 2000)"
+	)]
+	pub(crate) max_results: Option<usize>,
+	#[arg(
+		long = "min-score",
+		value_name = "SCORE",
+		help = "Hide matches scoring below SCORE, or below a percentage of the best match such as 40%"
+	)]
+	pub(crate) min_score: Option<String>,
 	#[arg(
 		short = 'p',
 		long = "print-config",
@@ -190,4 +368,91 @@ pub(crate) struct CliArgs {
         help = "Choose how to print the result"
     )]
 	pub(crate) output: OutputFormat,
+	#[arg(
+		long = "json-pretty",
+		help = "Indent --output json instead of printing it on one line"
+	)]
+	pub(crate) json_pretty: bool,
+	#[arg(
+		short = '0',
+		long = "print0",
+		conflicts_with = "output",
+		help = "Terminate each selected path with NUL instead of a newline, like --output null"
+	)]
+	pub(crate) print0: bool,
+	#[arg(
+		long = "hyperlinks",
+		value_enum,
+		value_name = "WHEN",
+		default_value_t = HyperlinkArg::Auto,
+		help = "Wrap plain output paths in clickable OSC 8 hyperlinks"
+	)]
+	pub(crate) hyperlinks: HyperlinkArg,
+	#[arg(
+		short = 'f',
+		long = "filter",
+		value_name = "QUERY",
+		conflicts_with = "expect",
+		help = "Print the rows matching QUERY, best first, without opening the finder; JSON Lines output streams the first matches of each indexed batch instead"
+	)]
+	pub(crate) filter: Option<String>,
+	#[arg(
+		long = "expect",
+		value_name = "KEYS",
+		value_delimiter = ',',
+		help = "Comma-separated keys that also accept, e.g. ctrl-v,alt-enter; the key pressed is printed before the selection (empty for Enter)"
+	)]
+	pub(crate) expect: Vec<String>,
+	#[arg(
+		long = "format",
+		value_name = "TEMPLATE",
+		conflicts_with = "output",
+		help = "Print each selection with TEMPLATE, e.g. '{path}\\t{score}'; placeholders: {path}, {abs_path}, {name}, {dir}, {score}, {tags}, {index}"
+	)]
+	pub(crate) format: Option<String>,
+	#[arg(
+		long = "exec",
+		value_name = "COMMAND",
+		conflicts_with_all = ["output", "format", "print0"],
+		help = "Run COMMAND on the accepted selection instead of printing it; {} is the highlighted path and {+} every selected path, both relative to the root the command runs in. frz exits with the command's exit code"
+	)]
+	pub(crate) exec: Option<String>,
+	#[arg(
+		long = "exec-shell",
+		requires = "exec",
+		help = "Run the --exec command through `sh -c`, quoting the substituted paths"
+	)]
+	pub(crate) exec_shell: bool,
+}
+
+impl CliArgs {
+	/// Output format chosen by `--output`, or [`OutputFormat::Null`] for
+	/// `--print0`.
+	pub(crate) fn output_format(&self) -> OutputFormat {
+		if self.print0 {
+			OutputFormat::Null
+		} else {
+			self.output
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn output_format(args: &[&str]) -> OutputFormat {
+		CliArgs::try_parse_from(std::iter::once("frz").chain(args.iter().copied()))
+			.expect("valid arguments")
+			.output_format()
+	}
+
+	#[test]
+	fn print0_selects_nul_terminated_output() {
+		assert_eq!(output_format(&[]), OutputFormat::Plain);
+		assert_eq!(output_format(&["--print0"]), OutputFormat::Null);
+		assert_eq!(output_format(&["-0"]), OutputFormat::Null);
+		assert_eq!(output_format(&["--output", "null"]), OutputFormat::Null);
+		assert!(CliArgs::try_parse_from(["frz", "--print0", "--output", "json"]).is_err());
+	}
 }
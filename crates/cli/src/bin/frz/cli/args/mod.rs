@@ -4,7 +4,7 @@ mod styles;
 
 use clap::Parser;
 pub(crate) use definitions::CliArgs;
-pub(crate) use options::OutputFormat;
+pub(crate) use options::{ColorDepthArg, OutputFormat};
 
 /// Parse command line arguments into the strongly typed [`CliArgs`] structure.
 /// Parse command line arguments into the strongly typed [`CliArgs`] structure.
@@ -1,4 +1,7 @@
+use std::env;
+
 use clap::ValueEnum;
+use frz_tui::{ColorDepth, PdfPreviewMode, PreviewLayout};
 
 /// Predefined UI presets selectable from the CLI.
 #[derive(Copy, Clone, Debug, ValueEnum)]
@@ -20,4 +23,165 @@ impl UiPresetArg {
 pub(crate) enum OutputFormat {
 	Plain,
 	Json,
+	/// `path,kind,tags,score` rows with a header line.
+	Csv,
+	/// NUL-terminated paths, like `find -print0`.
+	Null,
+	/// One JSON object per selection and line, for streaming consumers.
+	#[value(alias = "jsonl")]
+	JsonLines,
+}
+
+/// Terminal color depth selectable from the CLI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ColorDepthArg {
+	/// Detect from `COLORTERM` and `TERM`.
+	Auto,
+	/// 24-bit RGB colors.
+	Truecolor,
+	/// The 256-color palette.
+	#[value(name = "256")]
+	Ansi256,
+}
+
+impl ColorDepthArg {
+	/// Resolve to a concrete depth, probing the environment for `auto`.
+	pub(crate) fn resolve(self) -> ColorDepth {
+		match self {
+			ColorDepthArg::Auto => ColorDepth::detect(),
+			ColorDepthArg::Truecolor => ColorDepth::TrueColor,
+			ColorDepthArg::Ansi256 => ColorDepth::Ansi256,
+		}
+	}
+}
+
+/// When plain output wraps paths in OSC 8 hyperlinks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum HyperlinkArg {
+	/// Only when stdout is a terminal known to support them.
+	Auto,
+	/// Always, even when piped.
+	Always,
+	/// Never.
+	Never,
+}
+
+impl HyperlinkArg {
+	/// Whether to emit hyperlinks, probing the environment for `auto`.
+	pub(crate) fn resolve(self, stdout_is_terminal: bool) -> bool {
+		match self {
+			HyperlinkArg::Auto => {
+				stdout_is_terminal && terminal_supports_hyperlinks(|name| env::var(name).ok())
+			}
+			HyperlinkArg::Always => true,
+			HyperlinkArg::Never => false,
+		}
+	}
+}
+
+/// Whether the environment `var` describes a terminal known to render
+/// OSC 8 hyperlinks.
+///
+/// Terminals do not announce the feature, so this recognises the variables
+/// set by the emulators that implement it and by VTE 0.50 or later.
+fn terminal_supports_hyperlinks(var: impl Fn(&str) -> Option<String>) -> bool {
+	if var("TERM").as_deref() == Some("dumb") {
+		return false;
+	}
+	let known_program = var("TERM_PROGRAM").is_some_and(|program| {
+		["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"].contains(&program.as_str())
+	});
+	let known_term = var("TERM").is_some_and(|term| {
+		[
+			"xterm-kitty",
+			"alacritty",
+			"foot",
+			"xterm-ghostty",
+			"wezterm",
+		]
+		.iter()
+		.any(|known| term.starts_with(known))
+	});
+	let vte_version = var("VTE_VERSION").and_then(|version| version.parse::<u32>().ok());
+	known_program
+		|| known_term
+		|| vte_version.is_some_and(|version| version >= 5000)
+		|| var("WT_SESSION").is_some()
+		|| var("KONSOLE_VERSION").is_some()
+		|| var("DOMTERM").is_some()
+}
+
+/// Preview pane placement selectable from the CLI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PreviewLayoutArg {
+	/// Preview to the right of the results.
+	Horizontal,
+	/// Preview below the results.
+	Vertical,
+}
+
+impl From<PreviewLayoutArg> for PreviewLayout {
+	fn from(value: PreviewLayoutArg) -> Self {
+		match value {
+			PreviewLayoutArg::Horizontal => PreviewLayout::Horizontal,
+			PreviewLayoutArg::Vertical => PreviewLayout::Vertical,
+		}
+	}
+}
+
+/// How PDFs are previewed, selectable from the CLI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PdfPreviewArg {
+	/// The rendered first page when the terminal shows images, else the text.
+	Auto,
+	/// The rendered first page.
+	Image,
+	/// The text of every page.
+	Text,
+}
+
+impl From<PdfPreviewArg> for PdfPreviewMode {
+	fn from(value: PdfPreviewArg) -> Self {
+		match value {
+			PdfPreviewArg::Auto => PdfPreviewMode::Auto,
+			PdfPreviewArg::Image => PdfPreviewMode::Image,
+			PdfPreviewArg::Text => PdfPreviewMode::Text,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn supported(vars: &[(&str, &str)]) -> bool {
+		terminal_supports_hyperlinks(|name| {
+			vars.iter()
+				.find(|(key, _)| *key == name)
+				.map(|(_, value)| (*value).to_string())
+		})
+	}
+
+	#[test]
+	fn hyperlinks_are_detected_from_known_terminals() {
+		assert!(supported(&[("TERM", "xterm-kitty")]));
+		assert!(supported(&[
+			("TERM", "xterm-256color"),
+			("VTE_VERSION", "6003")
+		]));
+		assert!(supported(&[("TERM_PROGRAM", "WezTerm")]));
+		assert!(!supported(&[
+			("TERM", "xterm-256color"),
+			("VTE_VERSION", "4205")
+		]));
+		assert!(!supported(&[("TERM", "dumb"), ("WT_SESSION", "1")]));
+		assert!(!supported(&[]));
+	}
+
+	#[test]
+	fn auto_hyperlinks_need_a_terminal() {
+		assert!(!HyperlinkArg::Auto.resolve(false));
+		assert!(HyperlinkArg::Always.resolve(false));
+		assert!(!HyperlinkArg::Never.resolve(true));
+	}
 }
@@ -1,5 +1,10 @@
 mod args;
 mod output;
+mod template;
 
-pub(crate) use args::{CliArgs, OutputFormat, parse_cli};
-pub(crate) use output::{print_json, print_plain};
+pub(crate) use args::{CliArgs, ColorDepthArg, OutputFormat, parse_cli};
+pub(crate) use output::{
+	JsonLinesStream, print_csv, print_json, print_json_lines, print_null, print_plain,
+	print_template,
+};
+pub(crate) use template::OutputTemplate;
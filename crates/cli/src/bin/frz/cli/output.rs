@@ -1,49 +1,427 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
 use anyhow::Result;
-use frz_core::{SearchOutcome, SearchSelection};
-use serde_json::json;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use frz_core::filesystem::search::FILES_DATASET_KEY;
+use frz_core::{EntryKind, FileRow, SearchOutcome, SearchSelection};
+use serde::{Deserialize, Serialize};
+
+use super::template::{OutputTemplate, TemplateRow};
+
+/// Bytes of the path of `file` as it is named on disk, which differ from
+/// `file.path` when the name is not valid UTF-8.
+fn path_bytes(file: &FileRow) -> &[u8] {
+	file.os_path().as_os_str().as_encoded_bytes()
+}
 
 /// Print a plain-text representation of the search outcome.
-pub(crate) fn print_plain(outcome: &SearchOutcome) {
+///
+/// With `link_root`, each path is wrapped in an OSC 8 hyperlink to the file
+/// under that root; see [`write_plain`].
+pub(crate) fn print_plain(
+	outcome: &SearchOutcome,
+	link_root: Option<&Path>,
+	expect: bool,
+) -> Result<()> {
+	let mut stdout = io::stdout().lock();
+	write_plain(&mut stdout, outcome, link_root, expect)?;
+	stdout.flush()?;
+	Ok(())
+}
+
+/// Write the plain-text representation of the search outcome, one path per
+/// line.
+///
+/// With `link_root`, each path is shown as it is but wrapped in an OSC 8
+/// hyperlink whose `file://` URI points at the path resolved under
+/// `link_root`, so terminals that support them make the results clickable.
+///
+/// With `expect`, the accepted search starts with a line naming the
+/// `--expect` key that accepted it, left empty for the regular accept key,
+/// as fzf does.
+fn write_plain(
+	out: &mut impl Write,
+	outcome: &SearchOutcome,
+	link_root: Option<&Path>,
+	expect: bool,
+) -> Result<()> {
 	if !outcome.accepted {
-		println!("Search cancelled (query: '{}')", outcome.query);
-		return;
+		writeln!(out, "Search cancelled (query: '{}')", outcome.query)?;
+		return Ok(());
+	}
+
+	if expect {
+		writeln!(
+			out,
+			"{}",
+			outcome.accepted_with.as_deref().unwrap_or_default()
+		)?;
+	}
+
+	if outcome.selections.is_empty() {
+		writeln!(out, "No selection")?;
 	}
+	for file in outcome.selected_files() {
+		match link_root {
+			Some(root) => {
+				let uri = file_uri(&root.join(file.os_path()));
+				write!(out, "\x1b]8;;{uri}\x1b\\")?;
+				out.write_all(path_bytes(file))?;
+				out.write_all(b"\x1b]8;;\x1b\\\n")?;
+			}
+			None => {
+				out.write_all(path_bytes(file))?;
+				out.write_all(b"\n")?;
+			}
+		}
+	}
+	Ok(())
+}
 
-	match &outcome.selection {
-		Some(SearchSelection::File(file)) => println!("{}", file.path),
-		None => println!("No selection"),
+/// `file://` URI of the absolute `path`, percent-encoding every byte other
+/// than unreserved characters and `/`.
+fn file_uri(path: &Path) -> String {
+	let mut uri = String::from("file://");
+	for &byte in path.as_os_str().as_encoded_bytes() {
+		if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+			uri.push(char::from(byte));
+		} else {
+			uri.push_str(&format!("%{byte:02X}"));
+		}
 	}
+	uri
 }
 
-/// Format the search outcome as a JSON string.
-pub(crate) fn format_outcome_json(outcome: &SearchOutcome) -> Result<String> {
-	let selection = match &outcome.selection {
-		Some(SearchSelection::File(file)) => json!({
-			"type": "file",
-			"path": file.path,
-		}),
-		None => serde_json::Value::Null,
-	};
+/// The search outcome as printed by `--output json`.
+///
+/// Field names are part of frz's output format and must not change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct JsonOutcome {
+	/// `true` when the selection was accepted, `false` when the search was
+	/// aborted.
+	pub(crate) accepted: bool,
+	/// Key of the dataset that was searched, `files`.
+	pub(crate) dataset: String,
+	/// The query that was active when the search ended.
+	pub(crate) query: String,
+	/// The `--expect` key that accepted the search; absent for Enter.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(crate) accepted_with: Option<String>,
+	/// The highlighted row, `null` when there was none.
+	pub(crate) selection: Option<JsonSelection>,
+	/// Every selected row, in dataset order; empty when aborted.
+	pub(crate) selections: Vec<JsonSelection>,
+	/// 1-based source line at the top of the highlighted file's preview.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(crate) line: Option<usize>,
+	/// 1-based page shown by the highlighted document's preview.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(crate) page: Option<u32>,
+}
+
+/// One selected row in JSON and JSON Lines output.
+///
+/// Field names are part of frz's output format and must not change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct JsonSelection {
+	/// What was selected; always `file`.
+	#[serde(rename = "type")]
+	pub(crate) selection_type: String,
+	/// Kind of filesystem entry, e.g. `file` or `directory`.
+	pub(crate) kind: EntryKind,
+	/// Path relative to the search root, with replacement characters when
+	/// the name is not valid UTF-8.
+	pub(crate) path: String,
+	/// Exact bytes of a path that is not valid UTF-8, base64-encoded.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(crate) path_bytes: Option<String>,
+	/// Path resolved under the search root.
+	pub(crate) abs_path: String,
+	/// Match score, `null` for rows the final query did not match.
+	pub(crate) score: Option<u16>,
+	/// Tags of the row.
+	pub(crate) tags: Vec<String>,
+}
 
-	let payload = json!({
-		"accepted": outcome.accepted,
-		"query": outcome.query,
-		"selection": selection,
-	});
+impl JsonSelection {
+	pub(crate) fn new(selection: &SearchSelection, score: Option<u16>, root: &Path) -> Self {
+		let SearchSelection::File(file) = selection;
+		Self {
+			selection_type: "file".to_owned(),
+			kind: file.kind,
+			path: file.path.clone(),
+			path_bytes: file
+				.has_lossy_path()
+				.then(|| STANDARD.encode(path_bytes(file))),
+			abs_path: root.join(file.os_path()).to_string_lossy().into_owned(),
+			score,
+			tags: file.tags.clone(),
+		}
+	}
+}
+
+impl JsonOutcome {
+	/// Describe `outcome`, resolving absolute paths under `root`.
+	pub(crate) fn new(outcome: &SearchOutcome, root: &Path) -> Self {
+		let score_of = |index: usize| outcome.scores.get(index).copied().flatten();
+		let selections = outcome
+			.selections
+			.iter()
+			.enumerate()
+			.map(|(index, selection)| JsonSelection::new(selection, score_of(index), root))
+			.collect();
+		let selection = outcome.selection.as_ref().map(|selection| {
+			let SearchSelection::File(file) = selection;
+			let index = outcome
+				.selected_files()
+				.position(|selected| selected.stable_id() == file.stable_id());
+			JsonSelection::new(selection, index.and_then(score_of), root)
+		});
+		Self {
+			accepted: outcome.accepted,
+			dataset: FILES_DATASET_KEY.to_owned(),
+			query: outcome.query.clone(),
+			accepted_with: outcome.accepted_with.clone(),
+			selection,
+			selections,
+			line: outcome.position.and_then(|position| position.line),
+			page: outcome.position.and_then(|position| position.page),
+		}
+	}
+}
 
-	Ok(serde_json::to_string_pretty(&payload)?)
+/// Format the search outcome as JSON, indented when `pretty`.
+///
+/// Absolute paths are resolved under `root`.
+pub(crate) fn format_outcome_json(
+	outcome: &SearchOutcome,
+	root: &Path,
+	pretty: bool,
+) -> Result<String> {
+	let payload = JsonOutcome::new(outcome, root);
+	Ok(if pretty {
+		serde_json::to_string_pretty(&payload)?
+	} else {
+		serde_json::to_string(&payload)?
+	})
 }
 
 /// Print the JSON representation of the search outcome.
-pub(crate) fn print_json(outcome: &SearchOutcome) -> Result<()> {
-	println!("{}", format_outcome_json(outcome)?);
+pub(crate) fn print_json(outcome: &SearchOutcome, root: &Path, pretty: bool) -> Result<()> {
+	println!("{}", format_outcome_json(outcome, root, pretty)?);
+	Ok(())
+}
+
+/// Accepted files, empty when the search was cancelled.
+fn accepted_files(outcome: &SearchOutcome) -> impl Iterator<Item = &FileRow> {
+	outcome.selected_files().filter(|_| outcome.accepted)
+}
+
+/// Quote `field` for CSV when it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+	if field.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.to_string()
+	}
+}
+
+/// Format the accepted selections as CSV with a `path,kind,tags,score`
+/// header and one row per file.
+///
+/// Tags are joined with `;`, and the score is left empty for marked rows the
+/// final query does not match. Cancelled searches and empty selections
+/// produce only the header.
+pub(crate) fn format_outcome_csv(outcome: &SearchOutcome) -> Result<String> {
+	let mut csv = String::from("path,kind,tags,score\n");
+	for (index, file) in accepted_files(outcome).enumerate() {
+		let kind = serde_json::to_value(file.kind)?;
+		let kind = kind.as_str().unwrap_or_default();
+		let score = outcome
+			.scores
+			.get(index)
+			.copied()
+			.flatten()
+			.map(|score| score.to_string())
+			.unwrap_or_default();
+		csv.push_str(&format!(
+			"{},{},{},{}\n",
+			csv_field(&file.path),
+			csv_field(kind),
+			csv_field(&file.tags.join(";")),
+			score,
+		));
+	}
+	Ok(csv)
+}
+
+/// Format the accepted selections as NUL-terminated paths, byte for byte
+/// as they are named on disk.
+///
+/// Cancelled searches and empty selections produce no output at all.
+pub(crate) fn format_outcome_null(outcome: &SearchOutcome) -> Vec<u8> {
+	let mut output = Vec::new();
+	for file in accepted_files(outcome) {
+		output.extend_from_slice(path_bytes(file));
+		output.push(b'\0');
+	}
+	output
+}
+
+/// Write each accepted selection as a JSON object on a line of its own,
+/// flushing after every line.
+///
+/// Objects are the [`JsonSelection`]s of the JSON output, with absolute
+/// paths resolved under `root`. Cancelled searches and empty selections
+/// produce no output at all.
+fn write_json_lines(out: &mut impl Write, outcome: &SearchOutcome, root: &Path) -> Result<()> {
+	if !outcome.accepted {
+		return Ok(());
+	}
+	for (index, selection) in outcome.selections.iter().enumerate() {
+		let score = outcome.scores.get(index).copied().flatten();
+		serde_json::to_writer(&mut *out, &JsonSelection::new(selection, score, root))?;
+		out.write_all(b"\n")?;
+		out.flush()?;
+	}
+	Ok(())
+}
+
+/// Last line of streamed `--filter` JSON Lines output.
+///
+/// Field names are part of frz's output format and must not change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct JsonSummary {
+	/// Tells the summary apart from the selections before it; always
+	/// `summary`.
+	#[serde(rename = "type")]
+	pub(crate) summary_type: String,
+	/// The query that was matched.
+	pub(crate) query: String,
+	/// Number of matching rows printed.
+	pub(crate) matched: usize,
+	/// Number of rows searched.
+	pub(crate) total: usize,
+}
+
+/// Writes the matches of a `--filter` search as JSON Lines while the rows
+/// are still being indexed.
+pub(crate) struct JsonLinesStream<W> {
+	out: W,
+	root: PathBuf,
+	matched: usize,
+}
+
+impl<W: Write> JsonLinesStream<W> {
+	/// Stream to `out`, resolving absolute paths under `root`.
+	pub(crate) fn new(out: W, root: impl Into<PathBuf>) -> Self {
+		Self {
+			out,
+			root: root.into(),
+			matched: 0,
+		}
+	}
+
+	/// Write one [`JsonSelection`] line per scored row and flush them, so
+	/// consumers see the batch right away.
+	pub(crate) fn write_batch<'a>(
+		&mut self,
+		rows: impl IntoIterator<Item = (&'a FileRow, u16)>,
+	) -> Result<()> {
+		for (row, score) in rows {
+			let selection = SearchSelection::File(row.clone());
+			let value = JsonSelection::new(&selection, Some(score), &self.root);
+			serde_json::to_writer(&mut self.out, &value)?;
+			self.out.write_all(b"\n")?;
+			self.matched += 1;
+		}
+		self.out.flush()?;
+		Ok(())
+	}
+
+	/// End the stream with a [`JsonSummary`] of `total` searched rows and
+	/// return the number of matches written.
+	pub(crate) fn finish(mut self, query: &str, total: usize) -> Result<usize> {
+		let summary = JsonSummary {
+			summary_type: "summary".to_owned(),
+			query: query.to_owned(),
+			matched: self.matched,
+			total,
+		};
+		serde_json::to_writer(&mut self.out, &summary)?;
+		self.out.write_all(b"\n")?;
+		self.out.flush()?;
+		Ok(self.matched)
+	}
+}
+
+/// Print the accepted selections as JSON Lines.
+pub(crate) fn print_json_lines(outcome: &SearchOutcome, root: &Path) -> Result<()> {
+	write_json_lines(&mut io::stdout().lock(), outcome, root)
+}
+
+/// Print the CSV representation of the search outcome.
+pub(crate) fn print_csv(outcome: &SearchOutcome) -> Result<()> {
+	print!("{}", format_outcome_csv(outcome)?);
+	Ok(())
+}
+
+/// Print the selected paths, each terminated by NUL, without a trailing
+/// newline.
+pub(crate) fn print_null(outcome: &SearchOutcome) -> Result<()> {
+	let mut stdout = io::stdout().lock();
+	stdout.write_all(&format_outcome_null(outcome))?;
+	stdout.flush()?;
+	Ok(())
+}
+
+/// Format each accepted selection with `template`, followed by
+/// `terminator`.
+///
+/// Paths under `root` fill in `{abs_path}`. Cancelled searches and empty
+/// selections produce no output at all.
+pub(crate) fn format_outcome_template(
+	outcome: &SearchOutcome,
+	template: &OutputTemplate,
+	root: &Path,
+	terminator: u8,
+) -> Vec<u8> {
+	let mut output = Vec::new();
+	for (index, file) in accepted_files(outcome).enumerate() {
+		let row = TemplateRow {
+			file,
+			score: outcome.scores.get(index).copied().flatten(),
+			index,
+			root,
+		};
+		template.render(&row, &mut output);
+		output.push(terminator);
+	}
+	output
+}
+
+/// Print each accepted selection formatted with `template`, followed by
+/// `terminator`.
+pub(crate) fn print_template(
+	outcome: &SearchOutcome,
+	template: &OutputTemplate,
+	root: &Path,
+	terminator: u8,
+) -> Result<()> {
+	let mut stdout = io::stdout().lock();
+	stdout.write_all(&format_outcome_template(
+		outcome, template, root, terminator,
+	))?;
+	stdout.flush()?;
 	Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-	use frz_core::FileRow;
-	use serde_json::Value;
+	use frz_core::{FileRow, PreviewPosition};
+	use serde_json::{Value, json};
 
 	use super::*;
 
@@ -53,11 +431,407 @@ mod tests {
 			accepted: true,
 			query: "test".into(),
 			selection: Some(SearchSelection::File(FileRow::new("path"))),
+			selections: vec![SearchSelection::File(FileRow::new("path"))],
+			scores: vec![None],
+			position: None,
+			accepted_with: None,
 		};
 
-		let json = format_outcome_json(&outcome).expect("json");
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
 		let value: Value = serde_json::from_str(&json).expect("parse");
 		assert_eq!(value["selection"]["type"], "file");
 		assert_eq!(value["selection"]["path"], "path");
+		assert_eq!(value["selection"]["kind"], "file");
+		assert_eq!(value["selections"][0], value["selection"]);
+		assert!(value.get("line").is_none());
+		assert!(value.get("page").is_none());
+	}
+
+	#[test]
+	fn json_format_includes_preview_position() {
+		let outcome = SearchOutcome {
+			accepted: true,
+			query: String::new(),
+			selection: Some(SearchSelection::File(FileRow::new("app.log"))),
+			selections: vec![SearchSelection::File(FileRow::new("app.log"))],
+			scores: vec![None],
+			position: Some(PreviewPosition::line(340)),
+			accepted_with: None,
+		};
+
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
+		let value: Value = serde_json::from_str(&json).expect("parse");
+		assert_eq!(value["line"], 340);
+		assert!(value.get("page").is_none());
+	}
+
+	const AWKWARD_PATHS: [&str; 5] = [
+		"plain.txt",
+		"a,b.txt",
+		"say \"hi\".md",
+		"line\nbreak/and\r\nmore.rs",
+		" spaced , \"all\" ,\n",
+	];
+
+	fn accepted(path: &str) -> SearchOutcome {
+		SearchOutcome {
+			accepted: true,
+			query: String::new(),
+			selection: Some(SearchSelection::File(FileRow::new(path))),
+			selections: vec![SearchSelection::File(FileRow::new(path))],
+			scores: vec![None],
+			position: None,
+			accepted_with: None,
+		}
+	}
+
+	#[test]
+	fn csv_format_round_trips_awkward_paths() {
+		for path in AWKWARD_PATHS {
+			let csv = format_outcome_csv(&accepted(path)).expect("csv");
+			let mut reader = csv::Reader::from_reader(csv.as_bytes());
+			assert_eq!(
+				reader.headers().expect("headers"),
+				vec!["path", "kind", "tags", "score"]
+			);
+			let records: Vec<_> = reader
+				.records()
+				.map(|record| record.expect("record"))
+				.collect();
+			assert_eq!(records.len(), 1, "one row for {path:?}");
+			assert_eq!(&records[0][0], path);
+			assert_eq!(&records[0][1], "file");
+			assert_eq!(&records[0][2], "");
+			assert_eq!(&records[0][3], "", "no score without a match");
+		}
+
+		let file = FileRow::new("notes.md").with_tags(["a,b", "say \"hi\"", "plain"]);
+		let outcome = SearchOutcome {
+			selection: Some(SearchSelection::File(file.clone())),
+			selections: vec![SearchSelection::File(file)],
+			scores: vec![Some(87)],
+			..accepted("notes.md")
+		};
+		let csv = format_outcome_csv(&outcome).expect("csv");
+		let mut reader = csv::Reader::from_reader(csv.as_bytes());
+		let record = reader.records().next().expect("one row").expect("record");
+		assert_eq!(&record[2], "a,b;say \"hi\";plain");
+		assert_eq!(&record[3], "87");
+	}
+
+	#[test]
+	fn null_format_round_trips_awkward_paths() {
+		for path in AWKWARD_PATHS {
+			let output = String::from_utf8(format_outcome_null(&accepted(path))).expect("utf-8");
+			assert!(!output.ends_with('\n'));
+			let paths: Vec<_> = output.split_terminator('\0').collect();
+			assert_eq!(paths, vec![path]);
+		}
+	}
+
+	#[test]
+	fn cancelled_searches_emit_no_rows() {
+		let outcome = SearchOutcome {
+			accepted: false,
+			..accepted("ignored.txt")
+		};
+
+		assert_eq!(
+			format_outcome_csv(&outcome).expect("csv"),
+			"path,kind,tags,score\n"
+		);
+		assert!(format_outcome_null(&outcome).is_empty());
+	}
+
+	#[test]
+	fn every_marked_selection_is_emitted() {
+		let paths = ["a.rs", "b,c.rs"];
+		let outcome = SearchOutcome {
+			selections: paths
+				.iter()
+				.map(|path| SearchSelection::File(FileRow::new(*path)))
+				.collect(),
+			..accepted("a.rs")
+		};
+
+		assert_eq!(format_outcome_null(&outcome), b"a.rs\0b,c.rs\0");
+		let csv = format_outcome_csv(&outcome).expect("csv");
+		let mut reader = csv::Reader::from_reader(csv.as_bytes());
+		let rows: Vec<String> = reader
+			.records()
+			.map(|record| record.expect("record")[0].to_string())
+			.collect();
+		assert_eq!(rows, paths);
+
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
+		let value: Value = serde_json::from_str(&json).expect("parse");
+		assert_eq!(value["selections"][1]["path"], "b,c.rs");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_paths_are_emitted_exactly() {
+		use std::ffi::OsStr;
+		use std::os::unix::ffi::OsStrExt;
+		use std::path::Path;
+
+		let raw = b"caf\xe9.txt";
+		let row = FileRow::from_path(Path::new(OsStr::from_bytes(raw)));
+		let outcome = SearchOutcome {
+			selection: Some(SearchSelection::File(row.clone())),
+			selections: vec![SearchSelection::File(row)],
+			..accepted("ignored")
+		};
+
+		assert_eq!(format_outcome_null(&outcome), b"caf\xe9.txt\0");
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
+		let value: Value = serde_json::from_str(&json).expect("parse");
+		assert_eq!(value["selection"]["path"], "caf\u{FFFD}.txt");
+		let encoded = value["selection"]["path_bytes"]
+			.as_str()
+			.expect("path_bytes");
+		assert_eq!(STANDARD.decode(encoded).expect("base64"), raw);
+
+		let plain =
+			format_outcome_json(&accepted("plain.txt"), Path::new("/work"), false).expect("json");
+		let plain: Value = serde_json::from_str(&plain).expect("parse");
+		assert!(plain["selection"].get("path_bytes").is_none());
+	}
+
+	#[test]
+	fn null_format_keeps_marked_paths_with_newlines_apart() {
+		let paths = ["first\nhalf.rs", "second.rs"];
+		let outcome = SearchOutcome {
+			selections: paths
+				.iter()
+				.map(|path| SearchSelection::File(FileRow::new(*path)))
+				.collect(),
+			..accepted("second.rs")
+		};
+
+		let output = format_outcome_null(&outcome);
+		assert_eq!(output, b"first\nhalf.rs\0second.rs\0");
+		let split: Vec<_> = output.split(|&byte| byte == 0).collect();
+		assert_eq!(split, [&b"first\nhalf.rs"[..], b"second.rs", b""]);
+	}
+
+	fn plain(outcome: &SearchOutcome, link_root: Option<&Path>) -> String {
+		let mut output = Vec::new();
+		write_plain(&mut output, outcome, link_root, false).expect("plain");
+		String::from_utf8(output).expect("utf-8")
+	}
+
+	#[test]
+	fn plain_output_only_links_paths_when_asked() {
+		let outcome = accepted("src/main.rs");
+		assert_eq!(plain(&outcome, None), "src/main.rs\n");
+		assert_eq!(
+			plain(&outcome, Some(Path::new("/home/me/project"))),
+			"\x1b]8;;file:///home/me/project/src/main.rs\x1b\\src/main.rs\x1b]8;;\x1b\\\n"
+		);
+	}
+
+	#[test]
+	fn hyperlink_uris_percent_encode_awkward_paths() {
+		let outcome = SearchOutcome {
+			selections: ["a b/ü#1.rs", "100%.md"]
+				.iter()
+				.map(|path| SearchSelection::File(FileRow::new(*path)))
+				.collect(),
+			..accepted("a b/ü#1.rs")
+		};
+		let output = plain(&outcome, Some(Path::new("/tmp/x")));
+		let lines: Vec<_> = output.lines().collect();
+		assert_eq!(
+			lines,
+			[
+				"\x1b]8;;file:///tmp/x/a%20b/%C3%BC%231.rs\x1b\\a b/ü#1.rs\x1b]8;;\x1b\\",
+				"\x1b]8;;file:///tmp/x/100%25.md\x1b\\100%.md\x1b]8;;\x1b\\",
+			]
+		);
+
+		let cancelled = SearchOutcome {
+			accepted: false,
+			..accepted("a.rs")
+		};
+		assert!(!plain(&cancelled, Some(Path::new("/tmp/x"))).contains('\x1b'));
+	}
+
+	#[test]
+	fn templates_are_applied_to_every_selection() {
+		let outcome = SearchOutcome {
+			selections: ["src/a.rs", "docs/b.md"]
+				.iter()
+				.map(|path| SearchSelection::File(FileRow::new(*path)))
+				.collect(),
+			scores: vec![Some(42), None],
+			..accepted("src/a.rs")
+		};
+		let template = OutputTemplate::parse("{index}\\t{name}\\t{score}").expect("template");
+		let root = Path::new("/work");
+
+		assert_eq!(
+			format_outcome_template(&outcome, &template, root, b'\n'),
+			b"0\ta.rs\t42\n1\tb.md\t\n"
+		);
+		assert_eq!(
+			format_outcome_template(&outcome, &template, root, b'\0'),
+			b"0\ta.rs\t42\x001\tb.md\t\0"
+		);
+		let cancelled = SearchOutcome {
+			accepted: false,
+			..outcome
+		};
+		assert!(format_outcome_template(&cancelled, &template, root, b'\n').is_empty());
+	}
+
+	fn json_lines(outcome: &SearchOutcome) -> String {
+		let mut output = Vec::new();
+		write_json_lines(&mut output, outcome, Path::new("/work")).expect("json lines");
+		String::from_utf8(output).expect("utf-8")
+	}
+
+	#[test]
+	fn json_lines_parse_one_selection_at_a_time() {
+		let outcome = SearchOutcome {
+			selections: AWKWARD_PATHS
+				.iter()
+				.map(|path| SearchSelection::File(FileRow::new(*path).with_tags(["t\"1"])))
+				.collect(),
+			scores: vec![Some(7)],
+			..accepted("plain.txt")
+		};
+
+		let output = json_lines(&outcome);
+		assert!(output.ends_with("}\n"));
+		let lines: Vec<&str> = output.lines().collect();
+		assert_eq!(lines.len(), AWKWARD_PATHS.len());
+		for (line, path) in lines.iter().zip(AWKWARD_PATHS) {
+			let value: Value = serde_json::from_str(line).expect(line);
+			assert_eq!(value["path"], path);
+			assert_eq!(value["tags"], json!(["t\"1"]));
+		}
+		assert!(lines[3].contains(r#""line\nbreak/and\r\nmore.rs""#));
+		assert_eq!(serde_json::from_str::<Value>(lines[0]).unwrap()["score"], 7);
+		assert!(serde_json::from_str::<Value>(lines[1]).unwrap()["score"].is_null());
+
+		let cancelled = SearchOutcome {
+			accepted: false,
+			..outcome
+		};
+		assert!(json_lines(&cancelled).is_empty());
+	}
+
+	#[test]
+	fn expected_keys_are_reported_before_the_selection() {
+		let outcome = SearchOutcome {
+			accepted_with: Some("ctrl-v".into()),
+			..accepted("src/main.rs")
+		};
+		let mut output = Vec::new();
+		write_plain(&mut output, &outcome, None, true).expect("plain");
+		assert_eq!(output, b"ctrl-v\nsrc/main.rs\n");
+
+		let mut output = Vec::new();
+		write_plain(&mut output, &accepted("src/main.rs"), None, true).expect("plain");
+		assert_eq!(
+			output, b"\nsrc/main.rs\n",
+			"Enter leaves the key line empty"
+		);
+
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
+		let value: Value = serde_json::from_str(&json).expect("parse");
+		assert_eq!(value["accepted_with"], "ctrl-v");
+	}
+
+	#[test]
+	fn json_output_round_trips_through_its_documented_fields() {
+		let outcome = SearchOutcome {
+			selection: Some(SearchSelection::File(
+				FileRow::new("src/lib.rs").with_tags(["core"]),
+			)),
+			selections: vec![
+				SearchSelection::File(FileRow::new("src/lib.rs").with_tags(["core"])),
+				SearchSelection::File(FileRow::new("docs/old.md")),
+			],
+			scores: vec![Some(120), None],
+			query: "lib".into(),
+			position: Some(PreviewPosition::line(12)),
+			accepted_with: Some("ctrl-v".into()),
+			..accepted("src/lib.rs")
+		};
+
+		for pretty in [false, true] {
+			let json = format_outcome_json(&outcome, Path::new("/work"), pretty).expect("json");
+			assert_eq!(json.contains('\n'), pretty);
+			let parsed: JsonOutcome = serde_json::from_str(&json).expect("parse");
+			assert_eq!(parsed, JsonOutcome::new(&outcome, Path::new("/work")));
+		}
+
+		let parsed = JsonOutcome::new(&outcome, Path::new("/work"));
+		assert!(parsed.accepted);
+		assert_eq!(parsed.dataset, "files");
+		assert_eq!(parsed.query, "lib");
+		assert_eq!(parsed.line, Some(12));
+		let selection = parsed.selection.expect("highlighted row");
+		assert_eq!(selection.abs_path, "/work/src/lib.rs");
+		assert_eq!(selection.score, Some(120));
+		assert_eq!(selection.tags, ["core"]);
+		assert_eq!(parsed.selections[1].score, None);
+
+		let value: Value = serde_json::from_str(
+			&format_outcome_json(&outcome, Path::new("/work"), false).expect("json"),
+		)
+		.expect("parse");
+		let fields: Vec<_> = value["selections"][0]
+			.as_object()
+			.expect("object")
+			.keys()
+			.cloned()
+			.collect();
+		assert_eq!(
+			fields,
+			["abs_path", "kind", "path", "score", "tags", "type"],
+			"field names are stable"
+		);
+	}
+
+	#[test]
+	fn aborted_searches_are_reported_without_selections() {
+		let outcome = SearchOutcome {
+			accepted: false,
+			selection: None,
+			selections: Vec::new(),
+			scores: Vec::new(),
+			query: "nothing".into(),
+			..accepted("a.rs")
+		};
+		let json = format_outcome_json(&outcome, Path::new("/work"), false).expect("json");
+		let parsed: JsonOutcome = serde_json::from_str(&json).expect("parse");
+		assert!(!parsed.accepted);
+		assert_eq!(parsed.query, "nothing");
+		assert_eq!(parsed.selection, None);
+		assert!(parsed.selections.is_empty());
+	}
+
+	#[test]
+	fn streamed_json_lines_end_with_a_summary() {
+		let rows = [FileRow::new("src/lib.rs"), FileRow::new("src/main.rs")];
+		let mut output = Vec::new();
+		let mut stream = JsonLinesStream::new(&mut output, "/work");
+		stream.write_batch([(&rows[0], 90)]).expect("batch");
+		stream.write_batch([]).expect("empty batch");
+		stream.write_batch([(&rows[1], 40)]).expect("batch");
+		assert_eq!(stream.finish("src", 10).expect("summary"), 2);
+
+		let output = String::from_utf8(output).expect("utf-8");
+		let lines: Vec<&str> = output.lines().collect();
+		assert_eq!(lines.len(), 3);
+		let first: JsonSelection = serde_json::from_str(lines[0]).expect("selection");
+		assert_eq!(first.abs_path, "/work/src/lib.rs");
+		assert_eq!(first.score, Some(90));
+		let summary: JsonSummary = serde_json::from_str(lines[2]).expect("summary");
+		assert_eq!(summary.summary_type, "summary");
+		assert_eq!((summary.matched, summary.total), (2, 10));
 	}
 }
@@ -0,0 +1,228 @@
+//! `--format` templates for printing accepted selections.
+//!
+//! A template is literal text with `{placeholder}` fields filled in from each
+//! selection. `{{` and `}}` print a literal brace, and `\t`, `\n`, `\0` and
+//! `\\` are unescaped so shells need no special quoting for them. Templates
+//! are parsed once at startup, so a typo fails before the finder opens.
+
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use frz_core::FileRow;
+
+/// Names accepted between braces, in the order they are documented.
+const PLACEHOLDERS: [&str; 7] = ["path", "abs_path", "name", "dir", "score", "tags", "index"];
+
+/// A value filled in for every selection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+	/// The path relative to the root.
+	Path,
+	/// The path resolved under the root.
+	AbsPath,
+	/// The final path component.
+	Name,
+	/// Everything before the final component, or `.` when there is none.
+	Dir,
+	/// The match score, empty when the active query did not match the row.
+	Score,
+	/// The row's tags, separated by commas.
+	Tags,
+	/// 0-based position among the selections.
+	Index,
+}
+
+impl Field {
+	fn parse(name: &str) -> Option<Self> {
+		Some(match name {
+			"path" => Self::Path,
+			"abs_path" => Self::AbsPath,
+			"name" => Self::Name,
+			"dir" => Self::Dir,
+			"score" => Self::Score,
+			"tags" => Self::Tags,
+			"index" => Self::Index,
+			_ => return None,
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+	Text(String),
+	Field(Field),
+}
+
+/// A parsed `--format` template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OutputTemplate {
+	pieces: Vec<Piece>,
+}
+
+/// Everything a template can refer to for one selection.
+pub(crate) struct TemplateRow<'a> {
+	pub(crate) file: &'a FileRow,
+	pub(crate) score: Option<u16>,
+	pub(crate) index: usize,
+	pub(crate) root: &'a Path,
+}
+
+impl OutputTemplate {
+	/// Parse `template`, rejecting unknown placeholders and unbalanced braces.
+	pub(crate) fn parse(template: &str) -> Result<Self> {
+		let mut pieces = Vec::new();
+		let mut text = String::new();
+		let mut chars = template.chars().peekable();
+		while let Some(ch) = chars.next() {
+			match ch {
+				'{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+				'}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+				'{' => {
+					let mut name = String::new();
+					loop {
+						match chars.next() {
+							Some('}') => break,
+							Some(ch) => name.push(ch),
+							None => bail!("unclosed `{{{name}` in --format template"),
+						}
+					}
+					let Some(field) = Field::parse(&name) else {
+						bail!(
+							"unknown placeholder `{{{name}}}` in --format template (expected one of {})",
+							PLACEHOLDERS.map(|name| format!("{{{name}}}")).join(", ")
+						);
+					};
+					if !text.is_empty() {
+						pieces.push(Piece::Text(std::mem::take(&mut text)));
+					}
+					pieces.push(Piece::Field(field));
+				}
+				'}' => {
+					bail!("unmatched `}}` in --format template; write `}}}}` for a literal brace")
+				}
+				'\\' => match chars.next_if(|next| matches!(next, 't' | 'n' | '0' | '\\')) {
+					Some('t') => text.push('\t'),
+					Some('n') => text.push('\n'),
+					Some('0') => text.push('\0'),
+					Some(_) => text.push('\\'),
+					None => text.push('\\'),
+				},
+				_ => text.push(ch),
+			}
+		}
+		if !text.is_empty() {
+			pieces.push(Piece::Text(text));
+		}
+		Ok(Self { pieces })
+	}
+
+	/// Append the template filled in for `row` to `out`.
+	///
+	/// Paths are written byte for byte as they are named on disk.
+	pub(crate) fn render(&self, row: &TemplateRow<'_>, out: &mut Vec<u8>) {
+		let path = row.file.os_path();
+		for piece in &self.pieces {
+			match piece {
+				Piece::Text(text) => out.extend_from_slice(text.as_bytes()),
+				Piece::Field(Field::Path) => out.extend_from_slice(bytes(path)),
+				Piece::Field(Field::AbsPath) => {
+					out.extend_from_slice(bytes(&row.root.join(path)));
+				}
+				Piece::Field(Field::Name) => {
+					let name = path.file_name().map(Path::new).unwrap_or(path);
+					out.extend_from_slice(bytes(name));
+				}
+				Piece::Field(Field::Dir) => match path.parent() {
+					Some(dir) if !dir.as_os_str().is_empty() => out.extend_from_slice(bytes(dir)),
+					_ => out.push(b'.'),
+				},
+				Piece::Field(Field::Score) => {
+					if let Some(score) = row.score {
+						out.extend_from_slice(score.to_string().as_bytes());
+					}
+				}
+				Piece::Field(Field::Tags) => {
+					out.extend_from_slice(row.file.tags.join(",").as_bytes())
+				}
+				Piece::Field(Field::Index) => {
+					out.extend_from_slice(row.index.to_string().as_bytes())
+				}
+			}
+		}
+	}
+}
+
+fn bytes(path: &Path) -> &[u8] {
+	path.as_os_str().as_encoded_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn render(template: &str, row: &TemplateRow<'_>) -> String {
+		let mut out = Vec::new();
+		OutputTemplate::parse(template)
+			.expect("valid template")
+			.render(row, &mut out);
+		String::from_utf8(out).expect("utf-8")
+	}
+
+	#[test]
+	fn placeholders_are_filled_from_the_selection() {
+		let file = FileRow::new("src/app/main.rs").with_tags(["core", "entry"]);
+		let row = TemplateRow {
+			file: &file,
+			score: Some(87),
+			index: 2,
+			root: Path::new("/work"),
+		};
+		assert_eq!(
+			render("{path}\\t{score}\\t{tags}", &row),
+			"src/app/main.rs\t87\tcore,entry"
+		);
+		assert_eq!(
+			render("{index}: {dir} / {name} -> {abs_path}", &row),
+			"2: src/app / main.rs -> /work/src/app/main.rs"
+		);
+
+		let bare = FileRow::new("README");
+		let row = TemplateRow {
+			file: &bare,
+			score: None,
+			index: 0,
+			root: Path::new("/work"),
+		};
+		assert_eq!(
+			render("[{dir}|{name}|{score}|{tags}]", &row),
+			"[.|README||]"
+		);
+	}
+
+	#[test]
+	fn doubled_braces_and_backslashes_are_literal() {
+		let file = FileRow::new("a.rs");
+		let row = TemplateRow {
+			file: &file,
+			score: None,
+			index: 0,
+			root: Path::new("/"),
+		};
+		assert_eq!(render("{{path}} {{{path}}}", &row), "{path} {a.rs}");
+		assert_eq!(render("\\\\{path}\\0\\q", &row), "\\a.rs\0\\q");
+	}
+
+	#[test]
+	fn malformed_templates_are_rejected() {
+		for (template, needle) in [
+			("{pth}", "unknown placeholder `{pth}`"),
+			("{path", "unclosed `{path`"),
+			("path}", "unmatched `}`"),
+		] {
+			let message = OutputTemplate::parse(template)
+				.expect_err(template)
+				.to_string();
+			assert!(message.contains(needle), "{message}");
+		}
+	}
+}
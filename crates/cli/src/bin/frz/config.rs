@@ -1,11 +1,16 @@
 use std::path::PathBuf;
-use std::{env, fs};
+use std::time::Duration;
+use std::{env, fmt, fs};
 
-use anyhow::{Context, Result, ensure};
+use anyhow::{Context, Result, bail, ensure};
 use frz_core::filesystem::indexer::FilesystemOptions;
-use frz_tui::UiLabels;
+use frz_core::filesystem::search::{CaseMatching, MAX_RENDERED_RESULTS, ScoreCutoff};
+use frz_core::history::DEFAULT_HISTORY_SIZE;
+use frz_tui::{
+	ColorDepth, DEFAULT_MAX_IMAGE_DIMENSION, PdfPreviewMode, PreviewLayout, UiLabels, style,
+};
 
-use crate::cli::CliArgs;
+use crate::cli::{CliArgs, ColorDepthArg, OutputFormat};
 
 /// Simple application configuration derived from CLI arguments and defaults.
 #[derive(Debug)]
@@ -13,9 +18,34 @@ pub struct Config {
 	pub root: PathBuf,
 	pub filesystem: FilesystemOptions,
 	pub initial_query: String,
+	pub query_source: QuerySource,
+	pub case_matching: CaseMatching,
+	pub fold_diacritics: bool,
 	pub theme: Option<String>,
+	pub theme_file: Option<PathBuf>,
+	pub color_depth: ColorDepth,
+	pub markdown_preview: bool,
+	pub blame: bool,
+	pub line_numbers: bool,
+	pub pdf_preview: PdfPreviewMode,
+	pub max_image_dimension: u32,
+	pub preview_layout: PreviewLayout,
+	pub preview_split: f32,
 	pub ui: UiLabels,
 	pub file_headers: Option<Vec<String>>,
+	pub history_size: Option<usize>,
+	pub frecency: bool,
+	pub restore_selection: bool,
+	pub debounce: Duration,
+	pub min_query_length: usize,
+	pub max_results: usize,
+	pub score_cutoff: Option<ScoreCutoff>,
+	pub editor: Option<String>,
+	pub copy_absolute: bool,
+	pub quick_select_labels: bool,
+	pub footer: bool,
+	pub expect_keys: Vec<String>,
+	pub filter: Option<String>,
 }
 
 impl Config {
@@ -24,13 +54,43 @@ impl Config {
 		let root = resolve_root(cli)?;
 		let filesystem = build_filesystem_options(cli);
 
-		let initial_query = cli.initial_query.clone().unwrap_or_default();
+		let (initial_query, query_source) = match &cli.initial_query {
+			Some(query) => (query.clone(), QuerySource::Flag),
+			None => (String::new(), QuerySource::Default),
+		};
+		let case_matching = resolve_case_matching(cli);
+		let fold_diacritics = cli.fold_diacritics;
 		let theme = cli.theme.clone();
+		let theme_file = cli.theme_file.clone();
+		if let Some(path) = &theme_file {
+			style::load_theme_file(path)
+				.with_context(|| format!("failed to load theme file {}", path.display()))?;
+		}
+		let color_depth = cli.color_depth.unwrap_or(ColorDepthArg::Auto).resolve();
+		let markdown_preview = !cli.no_markdown_preview;
+		let blame = cli.blame;
+		let preview_layout = cli.preview_layout.map(Into::into).unwrap_or_default();
+		let preview_split = cli.preview_split.unwrap_or(0.5);
 		let ui = build_ui_config(cli)?;
 		let file_headers = cli
 			.file_headers
 			.as_ref()
 			.map(|headers| sanitize_headers(headers.clone()));
+		let history_size =
+			(!cli.no_history).then(|| cli.history_size.unwrap_or(DEFAULT_HISTORY_SIZE));
+		let frecency = !cli.no_frecency;
+		let debounce = Duration::from_millis(cli.debounce_ms.unwrap_or(0));
+		let min_query_length = cli.min_query_length.unwrap_or(0);
+		let max_results = cli.max_results.unwrap_or(MAX_RENDERED_RESULTS);
+		let score_cutoff = cli
+			.min_score
+			.as_deref()
+			.map(parse_score_cutoff)
+			.transpose()?;
+		let expect_keys = cli.expect.clone();
+		let max_image_dimension = cli
+			.preview_max_image_size
+			.unwrap_or(DEFAULT_MAX_IMAGE_DIMENSION);
 
 		// Validate
 		if let Some(threads) = filesystem.threads {
@@ -39,18 +99,113 @@ impl Config {
 		if let Some(max_depth) = filesystem.max_depth {
 			ensure!(max_depth > 0, "max-depth must be at least 1");
 		}
+		ensure!(max_results > 0, "max-results must be at least 1");
+		ensure!(
+			max_image_dimension > 0,
+			"preview-max-image-size must be at least 1"
+		);
+		ensure!(
+			preview_split > 0.0 && preview_split < 1.0,
+			"preview-split must be between 0 and 1"
+		);
+		for key in &expect_keys {
+			frz_tui::parse_key(key).with_context(|| format!("invalid --expect key `{key}`"))?;
+		}
+		// Only plain and JSON output have a place for the accepting key.
+		if !expect_keys.is_empty() {
+			ensure!(
+				cli.format.is_none()
+					&& cli.exec.is_none()
+					&& matches!(
+						cli.output_format(),
+						OutputFormat::Plain | OutputFormat::Json
+					),
+				"--expect only works with plain or JSON output"
+			);
+		}
 
 		Ok(Self {
 			root,
 			filesystem,
 			initial_query,
+			query_source,
+			case_matching,
+			fold_diacritics,
 			theme,
+			theme_file,
+			color_depth,
+			markdown_preview,
+			blame,
+			line_numbers: !cli.no_line_numbers,
+			pdf_preview: cli.pdf_preview.map(Into::into).unwrap_or_default(),
+			max_image_dimension,
+			preview_layout,
+			preview_split,
 			ui,
 			file_headers,
+			history_size,
+			frecency,
+			restore_selection: !cli.no_restore_selection,
+			debounce,
+			min_query_length,
+			max_results,
+			score_cutoff,
+			editor: cli.editor.clone(),
+			copy_absolute: cli.copy_absolute,
+			quick_select_labels: cli.quick_select_labels,
+			footer: cli.footer,
+			expect_keys,
+			filter: cli.filter.clone(),
+		})
+	}
+}
+
+/// Where the resolved initial query came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuerySource {
+	/// No query was given, so the search box starts empty.
+	Default,
+	/// Set with `--query`.
+	Flag,
+}
+
+impl fmt::Display for QuerySource {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Default => "default",
+			Self::Flag => "--query",
 		})
 	}
 }
 
+/// Parse `--min-score`: a plain score, or a percentage of the best match's
+/// score such as `40%`.
+fn parse_score_cutoff(value: &str) -> Result<ScoreCutoff> {
+	let value = value.trim();
+	if let Some(percent) = value.strip_suffix('%') {
+		let Ok(percent) = percent.trim().parse::<u8>() else {
+			bail!("min-score must be a score or a percentage, got {value:?}");
+		};
+		ensure!(percent <= 100, "min-score percentage must be at most 100%");
+		return Ok(ScoreCutoff::Percent(percent));
+	}
+	match value.parse() {
+		Ok(score) => Ok(ScoreCutoff::Absolute(score)),
+		Err(_) => bail!("min-score must be a score or a percentage, got {value:?}"),
+	}
+}
+
+/// Resolve the case matching mode from `--case-sensitive` and `--ignore-case`.
+fn resolve_case_matching(cli: &CliArgs) -> CaseMatching {
+	if cli.case_sensitive {
+		CaseMatching::Sensitive
+	} else if cli.ignore_case {
+		CaseMatching::Insensitive
+	} else {
+		CaseMatching::Smart
+	}
+}
+
 /// Resolve the filesystem root directory from CLI args, validating it exists and is a directory.
 fn resolve_root(cli: &CliArgs) -> Result<PathBuf> {
 	let mut root = match &cli.root {
@@ -89,13 +244,19 @@ fn build_filesystem_options(cli: &CliArgs) -> FilesystemOptions {
 	options.respect_ignore_files = cli
 		.respect_ignore_files
 		.unwrap_or(options.respect_ignore_files);
+	options.respect_gitignore = cli.respect_gitignore.unwrap_or(options.respect_gitignore);
 	options.git_ignore = cli.git_ignore.unwrap_or(options.git_ignore);
 	options.git_global = cli.git_global.unwrap_or(options.git_global);
 	options.git_exclude = cli.git_exclude.unwrap_or(options.git_exclude);
+	options.include_dirs = cli.include_dirs.unwrap_or(options.include_dirs);
+	options.dedupe_hardlinks = cli.dedupe.unwrap_or(options.dedupe_hardlinks);
 	options.threads = cli.threads;
 	options.max_depth = cli.max_depth;
 	options.allowed_extensions = allowed_extensions;
+	options.include_globs = cli.include_globs.clone().unwrap_or_default();
+	options.exclude_globs = cli.exclude_globs.clone().unwrap_or_default();
 	options.context_label = cli.context_label.clone();
+	options.collect_mtime = cli.mtime.unwrap_or(options.collect_mtime);
 
 	if let Some(extra_ignores) = cli.global_ignores.as_ref() {
 		for ignore in extra_ignores {
@@ -171,3 +332,221 @@ fn sanitize_headers(headers: Vec<String>) -> Vec<String> {
 		.filter(|h| !h.is_empty())
 		.collect()
 }
+
+#[cfg(test)]
+mod tests {
+	use clap::Parser;
+
+	use super::*;
+
+	fn config(args: &[&str]) -> Config {
+		let cli = CliArgs::try_parse_from(std::iter::once("frz").chain(args.iter().copied()))
+			.expect("valid arguments");
+		Config::from_cli(&cli).expect("valid config")
+	}
+
+	#[test]
+	fn query_flag_overrides_the_empty_default() {
+		let defaults = config(&[]);
+		assert_eq!(defaults.initial_query, "");
+		assert_eq!(defaults.query_source, QuerySource::Default);
+
+		for args in [
+			["--query", "src main"],
+			["-q", "src main"],
+			["--initial-query", "src main"],
+		] {
+			let config = config(&args);
+			assert_eq!(config.initial_query, "src main");
+			assert_eq!(config.query_source, QuerySource::Flag);
+		}
+	}
+
+	#[test]
+	fn debounce_defaults_to_zero() {
+		assert_eq!(config(&[]).debounce, Duration::ZERO);
+		assert_eq!(
+			config(&["--debounce", "120"]).debounce,
+			Duration::from_millis(120)
+		);
+	}
+
+	#[test]
+	fn min_query_length_defaults_to_zero() {
+		assert_eq!(config(&[]).min_query_length, 0);
+		assert_eq!(config(&["--min-query-length", "3"]).min_query_length, 3);
+	}
+
+	#[test]
+	fn frecency_is_on_unless_disabled() {
+		assert!(config(&[]).frecency);
+		assert!(!config(&["--no-frecency"]).frecency);
+	}
+
+	#[test]
+	fn selections_are_restored_unless_disabled() {
+		assert!(config(&[]).restore_selection);
+		assert!(!config(&["--no-restore-selection"]).restore_selection);
+	}
+
+	#[test]
+	fn malformed_theme_files_fail_settings_resolution() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("broken.toml");
+		fs::write(&path, "[styles.header]\nfg = \"not-a-colour\"\n").expect("write theme");
+
+		let cli = CliArgs::try_parse_from(["frz", "--theme-file", path.to_str().unwrap()])
+			.expect("valid arguments");
+		let err = Config::from_cli(&cli).expect_err("malformed theme");
+		let message = format!("{err:#}");
+		assert!(message.contains("broken.toml"), "{message}");
+	}
+
+	#[test]
+	fn color_depth_can_be_forced() {
+		assert_eq!(
+			config(&["--color-depth", "256"]).color_depth,
+			ColorDepth::Ansi256
+		);
+		assert_eq!(
+			config(&["--color-depth", "truecolor"]).color_depth,
+			ColorDepth::TrueColor
+		);
+	}
+
+	#[test]
+	fn markdown_previews_are_rendered_unless_disabled() {
+		assert!(config(&[]).markdown_preview);
+		assert!(!config(&["--no-markdown-preview"]).markdown_preview);
+	}
+
+	#[test]
+	fn case_flags_override_smart_case() {
+		assert_eq!(config(&[]).case_matching, CaseMatching::Smart);
+		assert_eq!(
+			config(&["--case-sensitive"]).case_matching,
+			CaseMatching::Sensitive
+		);
+		assert_eq!(config(&["-i"]).case_matching, CaseMatching::Insensitive);
+		assert!(CliArgs::try_parse_from(["frz", "--case-sensitive", "-i"]).is_err());
+	}
+
+	#[test]
+	fn diacritic_folding_is_opt_in() {
+		assert!(!config(&[]).fold_diacritics);
+		assert!(config(&["--fold-diacritics"]).fold_diacritics);
+	}
+
+	#[test]
+	fn git_ignore_rules_are_switched_together_or_one_by_one() {
+		assert!(
+			!config(&["--respect-gitignore", "false"])
+				.filesystem
+				.respect_gitignore
+		);
+		let options = config(&["--git-global", "false"]).filesystem;
+		assert!(options.respect_gitignore && options.git_ignore && !options.git_global);
+		for flag in ["--git-ignore", "--git-global", "--git-exclude"] {
+			let args = ["frz", "--respect-gitignore", "true", flag, "false"];
+			assert!(CliArgs::try_parse_from(args).is_err(), "{flag}");
+		}
+	}
+
+	#[test]
+	fn link_deduplication_is_opt_in() {
+		assert!(!config(&[]).filesystem.dedupe_hardlinks);
+		assert!(config(&["--dedupe", "true"]).filesystem.dedupe_hardlinks);
+	}
+
+	#[test]
+	fn modification_times_are_opt_in() {
+		assert!(!config(&[]).filesystem.collect_mtime);
+		assert!(config(&["--mtime", "true"]).filesystem.collect_mtime);
+	}
+
+	#[test]
+	fn blame_is_opt_in() {
+		assert!(!config(&[]).blame);
+		assert!(config(&["--blame"]).blame);
+	}
+
+	#[test]
+	fn max_results_default_to_the_render_cap() {
+		assert_eq!(config(&[]).max_results, MAX_RENDERED_RESULTS);
+		assert_eq!(config(&["--max-results", "500"]).max_results, 500);
+
+		let cli = CliArgs::try_parse_from(["frz", "--max-results", "0"]).expect("valid arguments");
+		assert!(Config::from_cli(&cli).is_err());
+	}
+
+	#[test]
+	fn min_score_is_a_score_or_a_percentage() {
+		assert_eq!(config(&[]).score_cutoff, None);
+		assert_eq!(
+			config(&["--min-score", "120"]).score_cutoff,
+			Some(ScoreCutoff::Absolute(120))
+		);
+		assert_eq!(
+			config(&["--min-score", "40%"]).score_cutoff,
+			Some(ScoreCutoff::Percent(40))
+		);
+
+		for value in ["101%", "3.5", "lots"] {
+			let cli =
+				CliArgs::try_parse_from(["frz", "--min-score", value]).expect("valid arguments");
+			assert!(Config::from_cli(&cli).is_err(), "{value}");
+		}
+	}
+
+	#[test]
+	fn preview_layout_and_split_are_configurable() {
+		let defaults = config(&[]);
+		assert_eq!(defaults.preview_layout, PreviewLayout::Horizontal);
+		assert_eq!(defaults.preview_split, 0.5);
+
+		let custom = config(&["--preview-layout", "vertical", "--preview-split", "0.6"]);
+		assert_eq!(custom.preview_layout, PreviewLayout::Vertical);
+		assert_eq!(custom.preview_split, 0.6);
+
+		assert_eq!(defaults.pdf_preview, PdfPreviewMode::Auto);
+		let text = config(&["--pdf-preview", "text"]);
+		assert_eq!(text.pdf_preview, PdfPreviewMode::Text);
+
+		assert_eq!(defaults.max_image_dimension, DEFAULT_MAX_IMAGE_DIMENSION);
+		let small = config(&["--preview-max-image-size", "1024"]);
+		assert_eq!(small.max_image_dimension, 1024);
+		let cli = CliArgs::try_parse_from(["frz", "--preview-max-image-size", "0"])
+			.expect("valid arguments");
+		assert!(Config::from_cli(&cli).is_err());
+
+		for ratio in ["0", "1", "1.5"] {
+			let cli = CliArgs::try_parse_from(["frz", "--preview-split", ratio])
+				.expect("valid arguments");
+			assert!(Config::from_cli(&cli).is_err(), "{ratio}");
+		}
+	}
+
+	#[test]
+	fn expect_keys_are_validated() {
+		assert_eq!(
+			config(&["--expect", "ctrl-v,alt-enter"]).expect_keys,
+			["ctrl-v", "alt-enter"]
+		);
+		let cli = CliArgs::try_parse_from(["frz", "--expect", "ctrl-nope"]).expect("parses");
+		let error = Config::from_cli(&cli).expect_err("unknown key");
+		assert!(error.to_string().contains("ctrl-nope"), "{error}");
+
+		config(&["--expect", "ctrl-v", "--output", "json"]);
+		for args in [
+			["--output", "csv"],
+			["--output", "json-lines"],
+			["--output", "null"],
+			["--format", "{path}"],
+			["--exec", "true"],
+		] {
+			let cli = CliArgs::try_parse_from(["frz", "--expect", "ctrl-v", args[0], args[1]])
+				.expect("parses");
+			assert!(Config::from_cli(&cli).is_err(), "{args:?}");
+		}
+	}
+}
@@ -0,0 +1,258 @@
+//! Non-interactive searches with `--filter`.
+//!
+//! The rows are indexed as for the finder, but the query is matched without
+//! opening it. Matches are printed best first once indexing finished, except
+//! for JSON Lines output, which matches each indexed batch as soon as it
+//! arrives so consumers can start before the walk is done.
+
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+
+use anyhow::Result;
+use frz_core::filesystem::indexer::{
+	IndexResult, IndexUpdate, IndexView, ProgressSnapshot, merge_update, spawn_line_index,
+	spawn_uncached_filesystem_index,
+};
+use frz_core::filesystem::search::{SearchOptions, collect_matches_with_options};
+use frz_core::{FileRow, SearchData, SearchOutcome, SearchSelection};
+
+use crate::cli::JsonLinesStream;
+use crate::config::Config;
+
+/// A `--filter` search of the rows [`Config`] describes.
+pub(crate) struct Filter {
+	query: String,
+	options: SearchOptions,
+	max_results: usize,
+	/// Root that absolute paths are resolved under, as for `--output json`.
+	root: PathBuf,
+	data: SearchData,
+	updates: Receiver<IndexResult>,
+}
+
+impl Filter {
+	/// Start indexing the rows to filter: the lines piped to stdin, or the
+	/// files under the root otherwise.
+	///
+	/// The filesystem walk bypasses the index cache, so every indexed row
+	/// is new and can be printed as soon as it matches.
+	pub(crate) fn from_config(query: String, config: Config) -> Result<Self> {
+		let threads = config.filesystem.thread_count();
+		let root = config.root.clone();
+		let stdin = io::stdin();
+		let (data, updates) = if stdin.is_terminal() {
+			spawn_uncached_filesystem_index(config.root, config.filesystem)?
+		} else {
+			spawn_line_index(stdin, "stdin")
+		};
+		let mut options = SearchOptions::default()
+			.with_case_matching(config.case_matching)
+			.with_fold_diacritics(config.fold_diacritics)
+			.with_threads(threads)
+			.with_max_results(usize::MAX);
+		if let Some(cutoff) = config.score_cutoff {
+			options = options.with_score_cutoff(cutoff);
+		}
+		Ok(Self::new(
+			query,
+			options,
+			config.max_results,
+			root,
+			data,
+			updates,
+		))
+	}
+
+	fn new(
+		query: String,
+		options: SearchOptions,
+		max_results: usize,
+		root: PathBuf,
+		data: SearchData,
+		updates: Receiver<IndexResult>,
+	) -> Self {
+		Self {
+			query,
+			options,
+			max_results,
+			root,
+			data,
+			updates,
+		}
+	}
+
+	/// Index every row, then match the query once and return up to
+	/// `--max-results` matches, best first, as an accepted outcome.
+	pub(crate) fn run(self) -> Result<SearchOutcome> {
+		let query = self.query.clone();
+		let options = self.options.clone();
+		let max_results = self.max_results;
+		let data = self.index(|_| Ok(()))?;
+
+		let batch = collect_matches_with_options(&data, &query, &options, max_results);
+		let selections: Vec<_> = batch
+			.indices
+			.iter()
+			.map(|&index| SearchSelection::File(data.files[index].clone()))
+			.collect();
+		Ok(SearchOutcome {
+			accepted: true,
+			selection: selections.first().cloned(),
+			scores: batch.scores.into_iter().map(Some).collect(),
+			selections,
+			query,
+			position: None,
+			accepted_with: None,
+		})
+	}
+
+	/// Match every indexed batch as it arrives and stream up to
+	/// `--max-results` matches to `out` as JSON Lines, ending with a summary.
+	///
+	/// Each batch is copied into a dataset of its own and ranked on its own,
+	/// so matches are only ordered within their batch, and once the budget
+	/// is spent later batches are skipped even if they hold better matches.
+	/// [`Filter::run`] ranks every row together instead.
+	///
+	/// Returns the number of matches printed.
+	pub(crate) fn stream_json_lines(self, out: impl Write) -> Result<usize> {
+		let query = self.query.clone();
+		let options = self.options.clone();
+		let mut budget = self.max_results;
+		let mut stream = JsonLinesStream::new(out, self.root.clone());
+		let data = self.index(|rows| {
+			if budget == 0 {
+				return Ok(());
+			}
+			let mut batch = SearchData::new();
+			batch.files = rows.to_vec();
+			let matches = collect_matches_with_options(&batch, &query, &options, budget);
+			budget -= matches.indices.len();
+			stream.write_batch(
+				matches
+					.indices
+					.iter()
+					.zip(matches.scores)
+					.map(|(&index, score)| (&rows[index], score)),
+			)
+		})?;
+		stream.finish(&query, data.files.len())
+	}
+
+	/// Apply index updates until the pass completes, handing the rows each
+	/// update added to `on_rows`.
+	fn index(self, mut on_rows: impl FnMut(&[FileRow]) -> Result<()>) -> Result<SearchData> {
+		let mut view = FilterView {
+			data: self.data,
+			complete: false,
+		};
+		for message in self.updates {
+			let indexed = view.data.files.len();
+			message.dispatch(&mut view);
+			on_rows(&view.data.files[indexed..])?;
+			if view.complete {
+				break;
+			}
+		}
+		Ok(view.data)
+	}
+}
+
+/// Rows indexed so far for a [`Filter`].
+struct FilterView {
+	data: SearchData,
+	complete: bool,
+}
+
+impl IndexView for FilterView {
+	fn forward_index_update(&self, _update: &IndexUpdate) {}
+
+	/// Append the update's rows; uncached walks and piped lines never
+	/// remove or replace earlier ones.
+	fn apply_index_update(&mut self, update: IndexUpdate) -> bool {
+		merge_update(&mut self.data, &update);
+		!update.files.is_empty()
+	}
+
+	fn record_index_progress(&mut self, progress: ProgressSnapshot) {
+		self.complete = progress.complete;
+	}
+
+	fn schedule_search_refresh_after_index_update(&mut self, _changed: bool) {}
+
+	fn is_complete(&self) -> bool {
+		self.complete
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use frz_core::filesystem::indexer::FilesystemOptions;
+
+	use super::*;
+
+	fn filter(root: &std::path::Path, query: &str, max_results: usize) -> Filter {
+		let (data, updates) =
+			spawn_uncached_filesystem_index(root.to_path_buf(), FilesystemOptions::default())
+				.expect("index");
+		let options = SearchOptions::default().with_max_results(usize::MAX);
+		let root = root.to_path_buf();
+		Filter::new(query.to_owned(), options, max_results, root, data, updates)
+	}
+
+	fn tree() -> tempfile::TempDir {
+		let dir = tempfile::tempdir().unwrap();
+		fs::create_dir_all(dir.path().join("src")).unwrap();
+		for path in ["src/lib.rs", "src/main.rs", "README.md", "notes.txt"] {
+			fs::write(dir.path().join(path), "").unwrap();
+		}
+		dir
+	}
+
+	#[test]
+	fn filters_print_every_match_best_first() {
+		let dir = tree();
+		let outcome = filter(dir.path(), "'.rs", usize::MAX)
+			.run()
+			.expect("filter");
+		assert!(outcome.accepted);
+		let mut paths: Vec<_> = outcome
+			.selected_files()
+			.map(|file| file.path.as_str())
+			.collect();
+		paths.sort_unstable();
+		assert_eq!(paths, ["src/lib.rs", "src/main.rs"]);
+		assert_eq!(outcome.scores.len(), 2);
+		assert!(outcome.scores.iter().all(Option::is_some));
+
+		let capped = filter(dir.path(), "", 1).run().expect("filter");
+		assert_eq!(capped.selections.len(), 1);
+	}
+
+	#[test]
+	fn streamed_filters_count_every_searched_row() {
+		let dir = tree();
+		let mut output = Vec::new();
+		let matched = filter(dir.path(), "'.rs", usize::MAX)
+			.stream_json_lines(&mut output)
+			.expect("filter");
+		assert_eq!(matched, 2);
+		let output = String::from_utf8(output).expect("utf-8");
+		let lines: Vec<serde_json::Value> = output
+			.lines()
+			.map(|line| serde_json::from_str(line).expect(line))
+			.collect();
+		assert_eq!(lines.len(), 3, "{output}");
+		assert!(
+			lines[..2]
+				.iter()
+				.all(|line| line["path"].as_str().unwrap().ends_with(".rs"))
+		);
+		assert_eq!(lines[2]["type"], "summary");
+		assert_eq!(lines[2]["matched"], 2);
+		assert_eq!(lines[2]["total"], 4);
+	}
+}
@@ -2,25 +2,58 @@
 
 mod cli;
 mod config;
+mod filter;
 mod workflow;
 
-use anyhow::Result;
-use cli::{OutputFormat, parse_cli, print_json, print_plain};
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use cli::{
+	OutputFormat, OutputTemplate, parse_cli, print_csv, print_json, print_json_lines, print_null,
+	print_plain, print_template,
+};
 use config::Config;
+use filter::Filter;
 use frz_tui::style;
-use workflow::SearchWorkflow;
+use workflow::{EXIT_ERROR, EXIT_NO_MATCH, ExecCommand, SearchWorkflow, outcome_exit_code};
 
 /// Entry point for the frz command-line application.
-fn main() -> Result<()> {
+///
+/// Exits with 0 when something was selected, 1 when nothing was, 130 when
+/// the search was aborted and 2 on errors; `--exec` passes on its command's
+/// exit code instead, even where it is one of these.
+fn main() -> ExitCode {
+	match run() {
+		Ok(code) => code,
+		Err(error) => {
+			eprintln!("Error: {error:?}");
+			ExitCode::from(EXIT_ERROR)
+		}
+	}
+}
+
+fn run() -> Result<ExitCode> {
 	let cli = parse_cli();
 
 	if cli.list_themes {
 		for name in style::names() {
 			println!("{name}");
 		}
-		return Ok(());
+		return Ok(ExitCode::SUCCESS);
 	}
 
+	let template = cli
+		.format
+		.as_deref()
+		.map(OutputTemplate::parse)
+		.transpose()
+		.context("invalid --format template")?;
+	let exec = cli
+		.exec
+		.as_deref()
+		.map(|command| ExecCommand::parse(command, cli.exec_shell))
+		.transpose()?;
 	let config = Config::from_cli(&cli)?;
 
 	if cli.print_config {
@@ -29,21 +62,105 @@ fn main() -> Result<()> {
 		println!("Max depth: {:?}", config.filesystem.max_depth);
 		println!("Hidden files: {}", config.filesystem.include_hidden);
 		println!("Follow symlinks: {}", config.filesystem.follow_symlinks);
+		println!("Dedupe links: {}", config.filesystem.dedupe_hardlinks);
+		println!(
+			"Query: {:?} ({})",
+			config.initial_query, config.query_source
+		);
+		println!("Case matching: {:?}", config.case_matching);
+		println!("Fold diacritics: {}", config.fold_diacritics);
+		println!("History size: {:?}", config.history_size);
+		println!("Frecency: {}", config.frecency);
+		println!("Restore selection: {}", config.restore_selection);
+		println!("Debounce: {:?}", config.debounce);
+		println!("Min query length: {}", config.min_query_length);
+		println!("Max results: {}", config.max_results);
+		println!("Min score: {:?}", config.score_cutoff);
 		println!("Theme: {:?}", config.theme);
+		println!("Theme file: {:?}", config.theme_file);
+		println!("Color depth: {:?}", config.color_depth);
+		println!("Markdown preview: {}", config.markdown_preview);
+		println!("Blame: {}", config.blame);
+		println!("Line numbers: {}", config.line_numbers);
+		println!("PDF preview: {:?}", config.pdf_preview);
+		println!("Max image size: {}", config.max_image_dimension);
+		println!(
+			"Preview layout: {:?} ({})",
+			config.preview_layout, config.preview_split
+		);
 	}
 
-	run_search(cli.output, config)
+	let hyperlinks = cli.hyperlinks.resolve(io::stdout().is_terminal());
+	run_search(
+		cli.output_format(),
+		template,
+		exec,
+		hyperlinks,
+		cli.json_pretty,
+		config,
+	)
 }
 
 /// Execute the search workflow and print output in the chosen format.
-fn run_search(format: OutputFormat, config: Config) -> Result<()> {
-	let workflow = SearchWorkflow::from_config(config)?;
-	let outcome = workflow.run()?;
+///
+/// A `template` replaces the format, ending each selection with NUL when
+/// the format is [`OutputFormat::Null`] and a newline otherwise. An `exec`
+/// command runs on the selection instead of printing it, and frz exits with
+/// the command's exit code.
+/// `hyperlinks` makes plain output link each path to the file under the
+/// search root. With `--expect` keys, plain output starts with the key that
+/// accepted the search. `json_pretty` indents JSON output.
+///
+/// With `--filter`, the matches are printed as if they had all been
+/// selected, and JSON Lines output is streamed while indexing.
+fn run_search(
+	format: OutputFormat,
+	template: Option<OutputTemplate>,
+	exec: Option<ExecCommand>,
+	hyperlinks: bool,
+	json_pretty: bool,
+	config: Config,
+) -> Result<ExitCode> {
+	let root = config.root.clone();
+	let expect = !config.expect_keys.is_empty();
+	let outcome = match config.filter.clone() {
+		Some(query) => {
+			let filter = Filter::from_config(query, config)?;
+			if format == OutputFormat::JsonLines && template.is_none() && exec.is_none() {
+				let matched = filter.stream_json_lines(io::stdout().lock())?;
+				return Ok(if matched == 0 {
+					ExitCode::from(EXIT_NO_MATCH)
+				} else {
+					ExitCode::SUCCESS
+				});
+			}
+			filter.run()?
+		}
+		None => SearchWorkflow::from_config(config)?.run()?,
+	};
 
+	if let Some(exec) = exec {
+		return Ok(exec
+			.run(&outcome, &root)?
+			.unwrap_or_else(|| outcome_exit_code(&outcome)));
+	}
+	if let Some(template) = template {
+		let terminator = if format == OutputFormat::Null {
+			b'\0'
+		} else {
+			b'\n'
+		};
+		print_template(&outcome, &template, &root, terminator)?;
+		return Ok(outcome_exit_code(&outcome));
+	}
+	let link_root = hyperlinks.then_some(root.as_path());
 	match format {
-		OutputFormat::Plain => print_plain(&outcome),
-		OutputFormat::Json => print_json(&outcome)?,
+		OutputFormat::Plain => print_plain(&outcome, link_root, expect)?,
+		OutputFormat::Json => print_json(&outcome, &root, json_pretty)?,
+		OutputFormat::Csv => print_csv(&outcome)?,
+		OutputFormat::Null => print_null(&outcome)?,
+		OutputFormat::JsonLines => print_json_lines(&outcome, &root)?,
 	}
 
-	Ok(())
+	Ok(outcome_exit_code(&outcome))
 }
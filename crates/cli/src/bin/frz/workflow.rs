@@ -1,6 +1,14 @@
-use anyhow::{Result, bail};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::{Command, ExitCode, ExitStatus};
+
+use anyhow::{Context, Result, ensure};
 use frz_core::SearchOutcome;
-use frz_tui::{Picker, style};
+use frz_core::frecency::FrecencyStore;
+use frz_core::history::HistoryStore;
+use frz_core::last_selection::LastSelectionStore;
+use frz_tui::Picker;
 
 use crate::config::Config;
 
@@ -16,27 +24,80 @@ impl SearchWorkflow {
 			root,
 			filesystem,
 			initial_query,
+			query_source: _,
+			case_matching,
+			fold_diacritics,
 			theme,
+			theme_file,
+			color_depth,
+			markdown_preview,
+			blame,
+			line_numbers,
+			pdf_preview,
+			max_image_dimension,
+			preview_layout,
+			preview_split,
 			ui,
 			file_headers,
+			history_size,
+			frecency,
+			restore_selection,
+			debounce,
+			min_query_length,
+			max_results,
+			score_cutoff,
+			editor,
+			copy_absolute,
+			quick_select_labels,
+			footer,
+			expect_keys,
+			filter: _,
 		} = config;
 
-		let mut search_ui = Picker::filesystem_with_options(root, filesystem)?;
+		// Piped input replaces the filesystem as the source of rows, as in
+		// `ls | frz`; the terminal is still used for the UI itself.
+		let stdin = io::stdin();
+		let mut search_ui = if stdin.is_terminal() {
+			Picker::filesystem_with_options(root, filesystem)?
+		} else {
+			Picker::lines(stdin)
+		};
 
 		search_ui = search_ui.with_ui_config(ui);
 		search_ui = search_ui.with_initial_query(initial_query);
+		search_ui = search_ui.with_case_matching(case_matching);
+		search_ui = search_ui.with_fold_diacritics(fold_diacritics);
+		search_ui = search_ui.with_debounce(debounce);
+		search_ui = search_ui.with_min_query_length(min_query_length);
+		search_ui = search_ui.with_max_results(max_results);
+		if let Some(cutoff) = score_cutoff {
+			search_ui = search_ui.with_score_cutoff(cutoff);
+		}
+		search_ui = search_ui.with_color_depth(color_depth);
+		if let Some(editor) = editor {
+			search_ui = search_ui.with_editor(editor);
+		}
+		search_ui = search_ui.with_absolute_copy(copy_absolute);
+		search_ui = search_ui.with_quick_select_labels(quick_select_labels);
+		search_ui = search_ui.with_match_footer(footer);
+		let expect_keys = expect_keys
+			.iter()
+			.map(|key| frz_tui::parse_key(key))
+			.collect::<Result<Vec<_>, _>>()?;
+		search_ui = search_ui.with_expect_keys(expect_keys);
+		search_ui = search_ui.with_markdown_preview(markdown_preview);
+		search_ui = search_ui.with_blame_preview(blame);
+		search_ui = search_ui.with_line_numbers(line_numbers);
+		search_ui = search_ui.with_pdf_preview(pdf_preview);
+		search_ui = search_ui.with_max_image_dimension(max_image_dimension);
+		search_ui = search_ui.with_preview_layout(preview_layout);
+		search_ui = search_ui.with_preview_split(preview_split);
 
 		if let Some(theme_name) = theme {
-			if style::by_name(&theme_name).is_none() {
-				let available_themes = style::names();
-				let themes_list = available_themes.join("\n  ");
-				bail!(
-					"Theme '{}' not found.\n\nAvailable themes:\n  {}",
-					theme_name,
-					themes_list
-				);
-			}
-			search_ui = search_ui.with_theme_name(&theme_name);
+			search_ui = search_ui.try_with_theme_name(&theme_name)?;
+		}
+		if let Some(path) = theme_file {
+			search_ui = search_ui.try_with_theme_file(path)?;
 		}
 
 		if let Some(headers) = file_headers {
@@ -44,11 +105,340 @@ impl SearchWorkflow {
 			search_ui = search_ui.with_headers(refs);
 		}
 
+		if let Some(limit) = history_size
+			&& let Ok(store) = HistoryStore::open_default()
+		{
+			search_ui = search_ui.with_history(store.with_limit(limit));
+		}
+
+		if frecency && let Ok(store) = FrecencyStore::open_default() {
+			search_ui = search_ui.with_frecency(store);
+		}
+
+		if restore_selection && let Ok(store) = LastSelectionStore::open_default() {
+			search_ui = search_ui.with_last_selection(store);
+		}
+
 		Ok(Self { search_ui })
 	}
 
 	/// Run the interactive search UI and return the final outcome.
 	pub(crate) fn run(self) -> Result<SearchOutcome> {
-		self.search_ui.run()
+		Ok(self.search_ui.run()?)
+	}
+}
+
+/// Command run on the accepted selection by `--exec`.
+///
+/// `{}` stands for the highlighted selection and `{+}` for every selected
+/// path. Without `--exec-shell` the command is split into words like a shell
+/// would and run directly, so paths need no quoting: a `{+}` word becomes
+/// one argument per path, and placeholders inside longer words are replaced
+/// in place, with `{+}` joining the paths by spaces. With `--exec-shell` the
+/// command runs through `sh -c` with every path quoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExecCommand {
+	/// Program and arguments, run directly.
+	Direct(Vec<String>),
+	/// Script for `sh -c`.
+	Shell(String),
+}
+
+impl ExecCommand {
+	/// Parse the `--exec` argument, splitting it into words unless it is run
+	/// through the shell.
+	pub(crate) fn parse(command: &str, shell: bool) -> Result<Self> {
+		if shell {
+			return Ok(Self::Shell(command.to_owned()));
+		}
+		let words = shlex::split(command).context("unbalanced quotes in --exec command")?;
+		ensure!(!words.is_empty(), "--exec needs a command to run");
+		Ok(Self::Direct(words))
+	}
+
+	/// The command to run for `outcome`, in `root` so relative paths resolve.
+	///
+	/// The working directory is `root` even when frz was started elsewhere,
+	/// so relative paths written in the command itself resolve under the root
+	/// too.
+	///
+	/// Returns `None` when the search was cancelled or nothing was selected.
+	pub(crate) fn command_for(
+		&self,
+		outcome: &SearchOutcome,
+		root: &Path,
+	) -> Result<Option<Command>> {
+		let Some(current) = outcome.selected_file().filter(|_| outcome.accepted) else {
+			return Ok(None);
+		};
+		let current = current.os_path().as_os_str();
+		let all: Vec<&OsStr> = outcome
+			.selected_files()
+			.map(|file| file.os_path().as_os_str())
+			.collect();
+
+		let mut command = match self {
+			Self::Direct(words) => {
+				let joined = join(all.iter().copied());
+				let mut args = Vec::new();
+				for word in words {
+					if word == "{+}" {
+						args.extend(all.iter().map(|path| path.to_os_string()));
+					} else {
+						args.push(expand(word, current, &joined));
+					}
+				}
+				let mut command = Command::new(&args[0]);
+				command.args(&args[1..]);
+				command
+			}
+			Self::Shell(script) => {
+				let current = shell_quote(current)?;
+				let all = all
+					.iter()
+					.map(|path| shell_quote(path))
+					.collect::<Result<Vec<_>>>()?;
+				let script = expand(script, OsStr::new(&current), &OsString::from(all.join(" ")));
+				let mut command = Command::new("sh");
+				command.arg("-c").arg(script);
+				command
+			}
+		};
+		command.current_dir(root);
+		Ok(Some(command))
+	}
+
+	/// Run the command for `outcome` and return its exit code, or `None`
+	/// when there was nothing to run it on.
+	pub(crate) fn run(&self, outcome: &SearchOutcome, root: &Path) -> Result<Option<ExitCode>> {
+		let Some(mut command) = self.command_for(outcome, root)? else {
+			return Ok(None);
+		};
+		let status = command.status().with_context(|| {
+			format!(
+				"failed to run --exec command {}",
+				command.get_program().display()
+			)
+		})?;
+		Ok(Some(exit_code(status)))
+	}
+}
+
+/// Exit code of a search accepted with nothing selected, such as a query
+/// that matched no row.
+pub(crate) const EXIT_NO_MATCH: u8 = 1;
+/// Exit code when frz itself failed, e.g. on an unreadable root.
+pub(crate) const EXIT_ERROR: u8 = 2;
+/// Exit code of an aborted search, as for a command interrupted with Ctrl+C.
+pub(crate) const EXIT_ABORTED: u8 = 130;
+
+/// How `outcome` ended, as an exit code: success when something was
+/// selected, [`EXIT_NO_MATCH`] when nothing was and [`EXIT_ABORTED`] when
+/// the search was cancelled.
+///
+/// `--exec` commands report their own exit code instead, so this only
+/// applies when no command ran. The codes are not reserved: a command
+/// exiting with 1, 2 or 130 is passed on as is, and frz only uses them
+/// itself when the command did not run or could not be started.
+pub(crate) fn outcome_exit_code(outcome: &SearchOutcome) -> ExitCode {
+	if !outcome.accepted {
+		ExitCode::from(EXIT_ABORTED)
+	} else if outcome.selected_files().next().is_none() {
+		ExitCode::from(EXIT_NO_MATCH)
+	} else {
+		ExitCode::SUCCESS
+	}
+}
+
+/// Replace `{}` with `current` and `{+}` with `all` in one pass, so
+/// placeholders inside substituted paths are left alone.
+fn expand(template: &str, current: &OsStr, all: &OsStr) -> OsString {
+	let mut expanded = OsString::new();
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		expanded.push(&rest[..start]);
+		let tail = &rest[start..];
+		if let Some(after) = tail.strip_prefix("{}") {
+			expanded.push(current);
+			rest = after;
+		} else if let Some(after) = tail.strip_prefix("{+}") {
+			expanded.push(all);
+			rest = after;
+		} else {
+			expanded.push("{");
+			rest = &tail[1..];
+		}
+	}
+	expanded.push(rest);
+	expanded
+}
+
+fn join<'a>(paths: impl Iterator<Item = &'a OsStr>) -> OsString {
+	let mut joined = OsString::new();
+	for (index, path) in paths.enumerate() {
+		if index > 0 {
+			joined.push(" ");
+		}
+		joined.push(path);
+	}
+	joined
+}
+
+/// `path` quoted for `sh`. Only UTF-8 paths can be embedded in a script.
+fn shell_quote(path: &OsStr) -> Result<String> {
+	let text = path.to_str().with_context(|| {
+		format!(
+			"--exec-shell cannot pass the non-UTF-8 path {}; use --exec without it",
+			path.display()
+		)
+	})?;
+	Ok(shlex::try_quote(text)?.into_owned())
+}
+
+/// The exit code frz should end with after a child exited with `status`.
+///
+/// Children killed by a signal report 128 plus the signal number, as shells
+/// do.
+fn exit_code(status: ExitStatus) -> ExitCode {
+	if let Some(code) = status.code() {
+		return ExitCode::from(u8::try_from(code).unwrap_or(1));
+	}
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::ExitStatusExt;
+		if let Some(signal) = status.signal() {
+			return ExitCode::from(u8::try_from(128 + signal).unwrap_or(1));
+		}
+	}
+	ExitCode::FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+	use frz_core::{FileRow, SearchSelection};
+
+	use super::*;
+
+	fn outcome(paths: &[&str]) -> SearchOutcome {
+		let selections: Vec<_> = paths
+			.iter()
+			.map(|path| SearchSelection::File(FileRow::new(*path)))
+			.collect();
+		SearchOutcome {
+			accepted: true,
+			selection: selections.last().cloned(),
+			scores: vec![None; selections.len()],
+			selections,
+			query: String::new(),
+			position: None,
+			accepted_with: None,
+		}
+	}
+
+	fn argv(command: &Command) -> Vec<String> {
+		std::iter::once(command.get_program())
+			.chain(command.get_args())
+			.map(|arg| arg.to_string_lossy().into_owned())
+			.collect()
+	}
+
+	#[test]
+	fn placeholders_expand_to_separate_arguments() {
+		let exec = ExecCommand::parse("nvim -- '{}' --all={+} {+}", false).expect("parse");
+		let command = exec
+			.command_for(&outcome(&["a b.rs", "it's {}.md"]), Path::new("/work"))
+			.expect("command")
+			.expect("accepted");
+		assert_eq!(
+			argv(&command),
+			[
+				"nvim",
+				"--",
+				"it's {}.md",
+				"--all=a b.rs it's {}.md",
+				"a b.rs",
+				"it's {}.md",
+			]
+		);
+		assert_eq!(command.get_current_dir(), Some(Path::new("/work")));
+	}
+
+	#[test]
+	fn shell_commands_quote_every_path() {
+		let exec = ExecCommand::parse("wc -l {+} && echo {}", true).expect("parse");
+		let command = exec
+			.command_for(&outcome(&["a b.rs", "it's.md"]), Path::new("/"))
+			.expect("command")
+			.expect("accepted");
+		assert_eq!(
+			argv(&command),
+			["sh", "-c", r#"wc -l 'a b.rs' "it's.md" && echo "it's.md""#]
+		);
+	}
+
+	#[test]
+	fn cancelled_or_empty_searches_run_nothing() {
+		let exec = ExecCommand::parse("rm {}", false).expect("parse");
+		let cancelled = SearchOutcome {
+			accepted: false,
+			..outcome(&["a.rs"])
+		};
+		assert!(
+			exec.command_for(&cancelled, Path::new("/"))
+				.unwrap()
+				.is_none()
+		);
+		assert!(
+			exec.command_for(&outcome(&[]), Path::new("/"))
+				.unwrap()
+				.is_none()
+		);
+		assert!(ExecCommand::parse("vim 'unclosed", false).is_err());
+		assert!(ExecCommand::parse("  ", false).is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn the_childs_exit_code_is_propagated() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		let exec = ExecCommand::parse("test -e {} && exit 3", true).expect("parse");
+		std::fs::write(dir.path().join("here.txt"), "").expect("write");
+
+		let code = exec.run(&outcome(&["here.txt"]), dir.path()).expect("run");
+		assert_eq!(code, Some(ExitCode::from(3)));
+		let code = exec.run(&outcome(&["gone.txt"]), dir.path()).expect("run");
+		assert_eq!(code, Some(ExitCode::from(1)));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn child_codes_are_passed_on_even_where_frz_uses_them() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		for code in [EXIT_NO_MATCH, EXIT_ERROR, EXIT_ABORTED, 255] {
+			let exec = ExecCommand::parse(&format!("exit {code}"), true).expect("parse");
+			let exit = exec.run(&outcome(&["a.rs"]), dir.path()).expect("run");
+			assert_eq!(exit, Some(ExitCode::from(code)));
+		}
+
+		let killed = ExecCommand::parse("kill -TERM $$", true).expect("parse");
+		let exit = killed.run(&outcome(&["a.rs"]), dir.path()).expect("run");
+		assert_eq!(exit, Some(ExitCode::from(128 + 15)));
+	}
+
+	#[test]
+	fn outcomes_map_to_distinct_exit_codes() {
+		assert_eq!(outcome_exit_code(&outcome(&["a.rs"])), ExitCode::SUCCESS);
+		assert_eq!(
+			outcome_exit_code(&outcome(&[])),
+			ExitCode::from(EXIT_NO_MATCH)
+		);
+		let cancelled = SearchOutcome {
+			accepted: false,
+			..outcome(&["a.rs"])
+		};
+		assert_eq!(outcome_exit_code(&cancelled), ExitCode::from(EXIT_ABORTED));
+
+		let exec = ExecCommand::parse("true", false).expect("parse");
+		assert_eq!(exec.run(&cancelled, Path::new("/")).expect("run"), None);
 	}
 }
@@ -3,11 +3,12 @@
 //! The helpers in this module respect environment overrides while falling back
 //! to platform-appropriate locations provided by the `dirs` crate.
 
-use dirs::{cache_dir, config_dir, data_dir};
 use std::env;
 use std::path::PathBuf;
 
-use anyhow::{Result, anyhow};
+use dirs::{cache_dir, config_dir, data_dir};
+
+use crate::error::{Error, Result};
 
 const APPLICATION: &str = "frz";
 
@@ -34,7 +35,7 @@ pub fn get_config_dir() -> Result<PathBuf> {
 		return Ok(dir);
 	}
 
-	let base = config_dir().ok_or_else(|| anyhow!("unable to determine config directory"))?;
+	let base = config_dir().ok_or_else(|| Error::config("unable to determine config directory"))?;
 	Ok(base.join(APPLICATION))
 }
 
@@ -44,7 +45,7 @@ pub fn get_data_dir() -> Result<PathBuf> {
 		return Ok(dir);
 	}
 
-	let base = data_dir().ok_or_else(|| anyhow!("unable to determine data directory"))?;
+	let base = data_dir().ok_or_else(|| Error::config("unable to determine data directory"))?;
 	Ok(base.join(APPLICATION))
 }
 
@@ -54,6 +55,6 @@ pub fn get_cache_dir() -> Result<PathBuf> {
 		return Ok(dir);
 	}
 
-	let base = cache_dir().ok_or_else(|| anyhow!("unable to determine cache directory"))?;
+	let base = cache_dir().ok_or_else(|| Error::config("unable to determine cache directory"))?;
 	Ok(base.join(APPLICATION))
 }
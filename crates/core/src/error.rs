@@ -0,0 +1,90 @@
+//! Error type shared by the public `frz` library API.
+//!
+//! Library functions return [`Error`] so embedders can branch on the kind of
+//! failure; binaries are free to convert it into `anyhow::Error` with `?`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Boxed error kept as the source of a [`Error::Config`] failure.
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Result alias using the library [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failure categories reported by the `frz` library crates.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+	/// Options or settings were invalid, e.g. a malformed glob or an unknown
+	/// theme name.
+	#[error("{message}")]
+	Config {
+		/// Human-readable description of the problem.
+		message: String,
+		/// Underlying error, when the problem was reported by another crate.
+		#[source]
+		source: Option<BoxError>,
+	},
+	/// A filesystem operation failed.
+	#[error("I/O error at {}", path.display())]
+	Io {
+		/// Path the operation was acting on.
+		path: PathBuf,
+		/// Underlying I/O error.
+		#[source]
+		source: io::Error,
+	},
+	/// The terminal could not be set up, read from or drawn to.
+	#[error("terminal error")]
+	Terminal(#[source] io::Error),
+}
+
+impl Error {
+	/// Build an [`Error::Config`] with no underlying source.
+	pub fn config(message: impl Into<String>) -> Self {
+		Self::Config {
+			message: message.into(),
+			source: None,
+		}
+	}
+
+	/// Build an [`Error::Config`] caused by `source`.
+	pub fn config_with(
+		message: impl Into<String>,
+		source: impl std::error::Error + Send + Sync + 'static,
+	) -> Self {
+		Self::Config {
+			message: message.into(),
+			source: Some(Box::new(source)),
+		}
+	}
+
+	/// Build an [`Error::Io`] for an operation on `path`.
+	pub fn io(path: impl AsRef<Path>, source: impl Into<io::Error>) -> Self {
+		Self::Io {
+			path: path.as_ref().to_path_buf(),
+			source: source.into(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::error::Error as _;
+
+	use super::*;
+
+	#[test]
+	fn sources_are_chained() {
+		let err = Error::io("/tmp/missing", io::Error::from(io::ErrorKind::NotFound));
+		assert_eq!(err.to_string(), "I/O error at /tmp/missing");
+		let source = err.source().expect("io source");
+		assert_eq!(
+			source.downcast_ref::<io::Error>().map(io::Error::kind),
+			Some(io::ErrorKind::NotFound)
+		);
+
+		assert!(Error::config("bad").source().is_none());
+	}
+}
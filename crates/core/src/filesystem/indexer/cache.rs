@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::hash::{Hash, Hasher};
@@ -5,24 +6,33 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use super::FilesystemOptions;
+use super::lock::CacheLock;
 use crate::app_dirs;
-use crate::filesystem::search::{FileRow, SearchData};
+use crate::error::{Error, Result};
+use crate::filesystem::search::{EntryKind, FileRow, SearchData};
 
 pub(super) const CACHE_TTL: Duration = Duration::from_secs(60);
-const CACHE_VERSION: u32 = 2;
+/// Bumped whenever the payload layout changes; caches written with another
+/// version are ignored and rebuilt by the next walk.
+const CACHE_VERSION: u32 = 6;
 const CACHE_NAMESPACE: &str = "filesystem";
 const CACHE_PREVIEW_LIMIT: usize = 512;
 const CACHE_PREVIEW_EXTENSION: &str = "preview.json";
+const CACHE_LOCK_EXTENSION: &str = "lock";
+
+/// Modification time of every cached entry by path, as recorded when the
+/// cache was written. `None` where the time could not be read.
+pub(super) type EntryStamps = HashMap<String, Option<SystemTime>>;
 
 /// Handle for persisting and retrieving indexed filesystem results.
 #[derive(Clone)]
 pub(super) struct CacheHandle {
 	path: PathBuf,
 	fingerprint: u64,
+	ttl: Duration,
 }
 
 /// Cached search data retrieved from disk storage.
@@ -30,13 +40,17 @@ pub(super) struct CachedEntry {
 	pub data: SearchData,
 	pub indexed_at: SystemTime,
 	pub complete: bool,
+	/// Modification times of the cached entries, used to re-emit only the
+	/// entries that changed since.
+	pub stamps: EntryStamps,
+	ttl: Duration,
 }
 
 impl CachedEntry {
 	/// Calculate time until cache should be reindexed.
 	pub fn reindex_delay(&self) -> Duration {
 		match SystemTime::now().duration_since(self.indexed_at) {
-			Ok(age) => CACHE_TTL.saturating_sub(age),
+			Ok(age) => self.ttl.saturating_sub(age),
 			Err(_) => Duration::ZERO,
 		}
 	}
@@ -51,30 +65,50 @@ impl CacheHandle {
 	/// Resolve a cache file location for the given root and options if cache directory exists.
 	pub fn resolve(root: &Path, options: &FilesystemOptions) -> Option<Self> {
 		let base = app_dirs::get_cache_dir().ok()?;
+		Some(Self::in_dir(&base.join(CACHE_NAMESPACE), root, options))
+	}
+
+	/// Cache file location for the given root and options inside `dir`.
+	pub fn in_dir(dir: &Path, root: &Path, options: &FilesystemOptions) -> Self {
 		let fingerprint = fingerprint_for(root, options);
 		let file_name = format!("{fingerprint:016x}.json");
-		let path = base.join(CACHE_NAMESPACE).join(file_name);
-		Some(Self { path, fingerprint })
+		Self {
+			path: dir.join(file_name),
+			fingerprint,
+			ttl: CACHE_TTL,
+		}
+	}
+
+	/// Reindex caches once they are older than `ttl` instead of [`CACHE_TTL`].
+	#[cfg(test)]
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = ttl;
+		self
 	}
 
 	/// Load cached entry from disk if it exists and is valid.
 	pub fn load(&self) -> Option<CachedEntry> {
-		load_payload(&self.path, self.fingerprint)
+		load_payload(&self.path, self.fingerprint, self.ttl)
 	}
 
 	/// Create a writer for accumulating and persisting cache data.
+	///
+	/// Returns `None` when another instance owns the cache refresh for this
+	/// root, in which case the existing cache is left untouched.
 	pub fn writer(&self, context_label: Option<String>) -> Option<CacheWriter> {
+		let lock = CacheLock::try_acquire(&self.path.with_extension(CACHE_LOCK_EXTENSION))?;
 		Some(CacheWriter::new(
 			self.path.clone(),
 			self.fingerprint,
 			context_label,
+			lock,
 		))
 	}
 
 	/// Load a preview of cached entries (limited subset for quick display).
 	pub fn load_preview(&self) -> Option<CachedEntry> {
 		let preview_path = self.preview_path();
-		load_payload(&preview_path, self.fingerprint)
+		load_payload(&preview_path, self.fingerprint, self.ttl)
 	}
 
 	fn preview_path(&self) -> PathBuf {
@@ -91,10 +125,17 @@ pub(super) struct CacheWriter {
 	context_label: Option<String>,
 	files: Vec<CacheFileEntry>,
 	preview_path: PathBuf,
+	/// Held until the writer is dropped so that only one instance refreshes the cache.
+	_lock: CacheLock,
 }
 
 impl CacheWriter {
-	fn new(path: PathBuf, fingerprint: u64, context_label: Option<String>) -> Self {
+	fn new(
+		path: PathBuf,
+		fingerprint: u64,
+		context_label: Option<String>,
+		lock: CacheLock,
+	) -> Self {
 		let mut preview_path = path.clone();
 		preview_path.set_extension(CACHE_PREVIEW_EXTENSION);
 		Self {
@@ -103,19 +144,30 @@ impl CacheWriter {
 			context_label,
 			files: Vec::new(),
 			preview_path,
+			_lock: lock,
 		}
 	}
 
-	pub fn record(&mut self, file: &FileRow) {
+	/// Record `file`, last modified at `stamp`.
+	pub fn record(&mut self, file: &FileRow, stamp: Option<SystemTime>) {
 		self.files.push(CacheFileEntry {
 			path: file.path.clone(),
+			size: file.size,
+			modified: file.modified,
+			kind: file.kind,
+			stamp,
+			path_bytes: lossy_path_bytes(file),
 		});
 	}
 
+	/// Drop the entry recorded for `path`.
+	pub fn forget(&mut self, path: &str) {
+		self.files.retain(|entry| entry.path != path);
+	}
+
 	pub fn finish(self) -> Result<()> {
 		if let Some(dir) = self.path.parent() {
-			fs::create_dir_all(dir)
-				.with_context(|| format!("failed to create cache directory: {}", dir.display()))?;
+			fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
 		}
 
 		let timestamp = SystemTime::now()
@@ -168,32 +220,66 @@ struct CachePayload {
 #[derive(Serialize, Deserialize, Clone)]
 struct CacheFileEntry {
 	path: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	size: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	modified: Option<SystemTime>,
+	#[serde(default, skip_serializing_if = "EntryKind::is_file")]
+	kind: EntryKind,
+	/// Modification time used to detect changes, recorded even when rows do
+	/// not show modification times.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	stamp: Option<SystemTime>,
+	/// Raw bytes of a path that is not valid UTF-8, whose `path` is lossy.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	path_bytes: Option<Vec<u8>>,
+}
+
+/// Raw bytes of the original path of `file` when its `path` is lossy.
+///
+/// Only unix paths are plain bytes; elsewhere just the lossy path is cached.
+#[cfg(unix)]
+fn lossy_path_bytes(file: &FileRow) -> Option<Vec<u8>> {
+	use std::os::unix::ffi::OsStrExt;
+
+	file.has_lossy_path()
+		.then(|| file.os_path().as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn lossy_path_bytes(_file: &FileRow) -> Option<Vec<u8>> {
+	None
+}
+
+#[cfg(unix)]
+fn row_from_bytes(bytes: Vec<u8>) -> Option<FileRow> {
+	use std::os::unix::ffi::OsStringExt;
+
+	Some(FileRow::from_path(Path::new(
+		&std::ffi::OsString::from_vec(bytes),
+	)))
+}
+
+#[cfg(not(unix))]
+fn row_from_bytes(_bytes: Vec<u8>) -> Option<FileRow> {
+	None
 }
 
 fn write_payload(path: &Path, payload: &CachePayload) -> Result<()> {
-	let data = serde_json::to_vec(payload).context("failed to serialize cache payload")?;
+	let data = serde_json::to_vec(payload).map_err(|err| Error::io(path, err))?;
 	let tmp_path = path.with_extension("tmp");
 	{
-		let mut file = fs::File::create(&tmp_path)
-			.with_context(|| format!("failed to create cache file: {}", tmp_path.display()))?;
+		let mut file = fs::File::create(&tmp_path).map_err(|err| Error::io(&tmp_path, err))?;
 		file.write_all(&data)
-			.with_context(|| format!("failed to write cache file: {}", tmp_path.display()))?;
+			.map_err(|err| Error::io(&tmp_path, err))?;
 		file.sync_all().ok();
 	}
 
 	let _ = fs::remove_file(path);
-	fs::rename(&tmp_path, path).with_context(|| {
-		format!(
-			"failed to move cache file from {} to {}",
-			tmp_path.display(),
-			path.display()
-		)
-	})?;
-
-	Ok(())
+	fs::rename(&tmp_path, path).map_err(|err| Error::io(path, err))
 }
 
-fn load_payload(path: &Path, fingerprint: u64) -> Option<CachedEntry> {
+fn load_payload(path: &Path, fingerprint: u64, ttl: Duration) -> Option<CachedEntry> {
 	let bytes = fs::read(path).ok()?;
 	let payload: CachePayload = serde_json::from_slice(&bytes).ok()?;
 	if payload.version != CACHE_VERSION || payload.fingerprint != fingerprint {
@@ -203,16 +289,26 @@ fn load_payload(path: &Path, fingerprint: u64) -> Option<CachedEntry> {
 	let indexed_at = UNIX_EPOCH + Duration::from_secs(payload.indexed_at);
 	let mut data = SearchData::new();
 	data.context_label = payload.context_label;
+	let mut stamps = EntryStamps::with_capacity(payload.files.len());
 	data.files = payload
 		.files
 		.into_iter()
-		.map(|entry| FileRow::filesystem(entry.path))
+		.map(|entry| {
+			stamps.insert(entry.path.clone(), entry.stamp);
+			let row = entry.path_bytes.and_then(row_from_bytes);
+			row.unwrap_or_else(|| FileRow::filesystem(entry.path))
+				.with_size(entry.size)
+				.with_modified(entry.modified)
+				.with_kind(entry.kind)
+		})
 		.collect();
 
 	Some(CachedEntry {
 		data,
 		indexed_at,
 		complete: payload.complete,
+		stamps,
+		ttl,
 	})
 }
 
@@ -222,11 +318,16 @@ fn fingerprint_for(root: &Path, options: &FilesystemOptions) -> u64 {
 	options.include_hidden.hash(&mut hasher);
 	options.follow_symlinks.hash(&mut hasher);
 	options.respect_ignore_files.hash(&mut hasher);
+	options.respect_gitignore.hash(&mut hasher);
 	options.git_ignore.hash(&mut hasher);
 	options.git_global.hash(&mut hasher);
 	options.git_exclude.hash(&mut hasher);
 	options.threads.hash(&mut hasher);
 	options.max_depth.hash(&mut hasher);
+	options.collect_sizes.hash(&mut hasher);
+	options.collect_mtime.hash(&mut hasher);
+	options.include_dirs.hash(&mut hasher);
+	options.dedupe_hardlinks.hash(&mut hasher);
 
 	match options.allowed_extensions.as_ref() {
 		Some(exts) => {
@@ -244,5 +345,81 @@ fn fingerprint_for(root: &Path, options: &FilesystemOptions) -> u64 {
 	ignores.sort();
 	ignores.hash(&mut hasher);
 
+	for globs in [&options.include_globs, &options.exclude_globs] {
+		let mut sorted = globs.clone();
+		sorted.sort();
+		sorted.hash(&mut hasher);
+	}
+
 	hasher.finish()
 }
+
+#[cfg(test)]
+mod tests {
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn cached_entries_keep_their_modification_stamps() {
+		let dir = tempdir().unwrap();
+		let handle = CacheHandle::in_dir(dir.path(), dir.path(), &FilesystemOptions::default());
+		let stamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+		let mut writer = handle.writer(None).expect("cache lock");
+		writer.record(&FileRow::filesystem("a.txt"), Some(stamp));
+		writer.record(&FileRow::filesystem("b.txt"), None);
+		writer.finish().unwrap();
+
+		let entry = handle.load().expect("cache entry");
+		assert!(entry.is_complete());
+		assert_eq!(entry.data.files.len(), 2);
+		assert_eq!(entry.stamps["a.txt"], Some(stamp));
+		assert_eq!(entry.stamps["b.txt"], None);
+		assert!(entry.data.files[0].modified.is_none());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_paths_survive_the_cache() {
+		use std::ffi::OsStr;
+		use std::os::unix::ffi::OsStrExt;
+
+		let dir = tempdir().unwrap();
+		let handle = CacheHandle::in_dir(dir.path(), dir.path(), &FilesystemOptions::default());
+		let original = Path::new(OsStr::from_bytes(b"caf\xe9.txt"));
+		let mut writer = handle.writer(None).expect("cache lock");
+		writer.record(&FileRow::from_path(original), None);
+		writer.record(&FileRow::filesystem("plain.txt"), None);
+		writer.finish().unwrap();
+
+		let entry = handle.load().expect("cache entry");
+		let [lossy, plain] = entry.data.files.as_slice() else {
+			panic!("expected two rows");
+		};
+		assert_eq!(lossy.path, "caf\u{FFFD}.txt");
+		assert_eq!(lossy.os_path(), original);
+		assert_eq!(lossy.id, FileRow::from_path(original).id);
+		assert!(!plain.has_lossy_path());
+	}
+
+	#[test]
+	fn caches_from_other_versions_are_ignored() {
+		let dir = tempdir().unwrap();
+		let handle = CacheHandle::in_dir(dir.path(), dir.path(), &FilesystemOptions::default());
+		let mut writer = handle.writer(None).expect("cache lock");
+		writer.record(&FileRow::filesystem("a.txt"), None);
+		writer.finish().unwrap();
+		assert!(handle.load().is_some());
+
+		for path in [handle.path.clone(), handle.preview_path()] {
+			let json = fs::read_to_string(&path).unwrap();
+			let current = format!("\"version\":{CACHE_VERSION}");
+			let previous = format!("\"version\":{}", CACHE_VERSION - 1);
+			assert!(json.contains(&current), "{json}");
+			fs::write(&path, json.replace(&current, &previous)).unwrap();
+		}
+		assert!(handle.load().is_none());
+		assert!(handle.load_preview().is_none());
+	}
+}
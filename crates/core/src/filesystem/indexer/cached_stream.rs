@@ -1,12 +1,19 @@
 use std::sync::Arc;
 
 use super::cache::CachedEntry;
-use super::{IndexKind, IndexResult, IndexStream, IndexUpdate, MAX_BATCH_SIZE, ProgressSnapshot};
+use super::{
+	CacheRole, IndexKind, IndexResult, IndexStream, IndexUpdate, MAX_BATCH_SIZE, ProgressSnapshot,
+};
 use crate::filesystem::search::FileRow;
 
+/// Stream the rows of a cached index after the `preview_len` already sent
+/// from its preview, completing the pass with the last batch when the cache
+/// holds a `complete` walk.
 pub(super) fn stream_cached_entry(
 	entry: CachedEntry,
 	preview_len: Option<usize>,
+	complete: bool,
+	cache_role: CacheRole,
 	tx: &std::sync::mpsc::Sender<IndexResult>,
 ) {
 	let stream = IndexStream::new(tx, 0, IndexKind::Preview);
@@ -28,17 +35,20 @@ pub(super) fn stream_cached_entry(
 		let progress = ProgressSnapshot {
 			indexed_files: total_files,
 			total_files: Some(total_files),
-			complete: false,
+			complete,
+			skipped_duplicates: 0,
+			cache_role,
 		};
 
 		let _ = stream.send_update(
 			IndexUpdate {
 				files: Arc::from(Vec::<FileRow>::new()),
+				removed: Arc::from([]),
 				progress,
 				reset: preview_len.is_none(),
 				cached_data: None,
 			},
-			false,
+			complete,
 		);
 		return;
 	}
@@ -51,20 +61,24 @@ pub(super) fn stream_cached_entry(
 		let chunk: Vec<FileRow> = files.drain(..chunk_len).collect();
 		dispatched += chunk_len;
 
+		let last = files.is_empty();
 		let progress = ProgressSnapshot {
 			indexed_files: dispatched,
 			total_files: Some(total_files),
-			complete: false,
+			complete: complete && last,
+			skipped_duplicates: 0,
+			cache_role,
 		};
 
 		let update = IndexUpdate {
 			files: chunk.into(),
+			removed: Arc::from([]),
 			progress,
 			reset: preview_len.is_none() && first_batch,
 			cached_data: None,
 		};
 
-		if !stream.send_update(update, false) {
+		if !stream.send_update(update, complete && last) {
 			break;
 		}
 
@@ -0,0 +1,105 @@
+//! Glob-based include and exclude filters for indexed paths.
+
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::error::{Error, Result};
+
+/// Compiled include and exclude globs matched against root-relative paths.
+///
+/// A path is kept when it matches no exclude glob and either the include list
+/// is empty or the path matches at least one include glob.
+#[derive(Debug, Clone)]
+pub(crate) struct GlobFilter {
+	include: Option<GlobSet>,
+	exclude: Option<GlobSet>,
+}
+
+impl GlobFilter {
+	/// Compile the patterns, returning `None` when both lists are empty.
+	pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Option<Self>> {
+		if include.is_empty() && exclude.is_empty() {
+			return Ok(None);
+		}
+
+		Ok(Some(Self {
+			include: compile(include)?,
+			exclude: compile(exclude)?,
+		}))
+	}
+
+	/// Whether the root-relative `path` should be indexed.
+	pub(crate) fn matches(&self, path: &Path) -> bool {
+		if self.exclude.as_ref().is_some_and(|set| set.is_match(path)) {
+			return false;
+		}
+		self.include.as_ref().is_none_or(|set| set.is_match(path))
+	}
+}
+
+fn compile(patterns: &[String]) -> Result<Option<GlobSet>> {
+	if patterns.is_empty() {
+		return Ok(None);
+	}
+
+	let mut builder = GlobSetBuilder::new();
+	for pattern in patterns {
+		let glob = Glob::new(pattern)
+			.map_err(|err| Error::config_with(format!("invalid glob pattern: {pattern}"), err))?;
+		builder.add(glob);
+	}
+	builder
+		.build()
+		.map(Some)
+		.map_err(|err| Error::config_with("failed to compile glob patterns", err))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn patterns(raw: &[&str]) -> Vec<String> {
+		raw.iter().map(ToString::to_string).collect()
+	}
+
+	fn filter(include: &[&str], exclude: &[&str]) -> GlobFilter {
+		GlobFilter::new(&patterns(include), &patterns(exclude))
+			.unwrap()
+			.expect("filter")
+	}
+
+	#[test]
+	fn empty_lists_compile_to_no_filter() {
+		assert!(GlobFilter::new(&[], &[]).unwrap().is_none());
+	}
+
+	#[test]
+	fn includes_restrict_and_empty_includes_match_everything() {
+		let only_sources = filter(&["*.rs", "*.toml"], &[]);
+		assert!(only_sources.matches(Path::new("src/main.rs")));
+		assert!(only_sources.matches(Path::new("Cargo.toml")));
+		assert!(!only_sources.matches(Path::new("README.md")));
+
+		let everything_but_target = filter(&[], &["target/**"]);
+		assert!(everything_but_target.matches(Path::new("README.md")));
+		assert!(!everything_but_target.matches(Path::new("target/debug/frz")));
+	}
+
+	#[test]
+	fn excludes_take_precedence_over_overlapping_includes() {
+		let sources = filter(&["*.rs"], &["target/**", "**/generated/*.rs"]);
+		assert!(sources.matches(Path::new("src/lib.rs")));
+		assert!(!sources.matches(Path::new("target/build/out.rs")));
+		assert!(!sources.matches(Path::new("src/generated/bindings.rs")));
+
+		let same_pattern = filter(&["*.rs"], &["*.rs"]);
+		assert!(!same_pattern.matches(Path::new("src/lib.rs")));
+	}
+
+	#[test]
+	fn invalid_patterns_are_errors() {
+		let err = GlobFilter::new(&patterns(&["src/[a"]), &[]).unwrap_err();
+		assert!(err.to_string().contains("src/[a"));
+	}
+}
@@ -0,0 +1,129 @@
+use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use super::IndexResult;
+use super::update_batcher::UpdateBatcher;
+use crate::filesystem::search::{FileRow, SearchData};
+
+/// Spawn a background thread that indexes one row per line of `reader`.
+///
+/// Lines are streamed as [`IndexResult`] updates in the same batches the
+/// filesystem indexer uses, so large inputs become searchable while they are
+/// still being read. Each line becomes a [`FileRow`] whose path is the line
+/// with its line ending removed; empty lines are skipped and invalid UTF-8 is
+/// replaced. Nothing on disk is touched, and reading stops early once the
+/// receiver is dropped.
+pub fn spawn_line_index(
+	reader: impl Read + Send + 'static,
+	context_label: impl Into<String>,
+) -> (SearchData, Receiver<IndexResult>) {
+	let (tx, rx) = mpsc::channel();
+	let data = SearchData::new().with_context(context_label);
+
+	thread::spawn(move || {
+		let mut reader = BufReader::new(reader);
+		let mut batcher = UpdateBatcher::new(false, None);
+		let mut line = Vec::new();
+		loop {
+			line.clear();
+			match reader.read_until(b'\n', &mut line) {
+				Ok(0) | Err(_) => break,
+				Ok(_) => {}
+			}
+			let text = String::from_utf8_lossy(&line);
+			let text = text.trim_end_matches(['\n', '\r']);
+			if text.is_empty() {
+				continue;
+			}
+
+			batcher.record_file(FileRow::new(text), None, None);
+			if batcher.should_flush() && !batcher.flush(&tx, false) {
+				return;
+			}
+		}
+		let _ = batcher.finalize(&tx);
+	});
+
+	(data, rx)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::time::Duration;
+
+	use super::*;
+	use crate::filesystem::indexer::{IndexUpdate, IndexView, ProgressSnapshot, merge_update};
+
+	#[derive(Default)]
+	struct Collected {
+		data: SearchData,
+		updates: usize,
+		complete: bool,
+	}
+
+	impl IndexView for Collected {
+		fn forward_index_update(&self, _update: &IndexUpdate) {}
+
+		fn apply_index_update(&mut self, update: IndexUpdate) -> bool {
+			self.updates += 1;
+			merge_update(&mut self.data, &update);
+			!update.files.is_empty()
+		}
+
+		fn record_index_progress(&mut self, progress: ProgressSnapshot) {
+			self.complete = progress.complete;
+		}
+
+		fn schedule_search_refresh_after_index_update(&mut self, _changed: bool) {}
+
+		fn is_complete(&self) -> bool {
+			self.complete
+		}
+	}
+
+	fn drain(rx: Receiver<IndexResult>) -> Collected {
+		let mut view = Collected::default();
+		while !view.is_complete() {
+			let envelope = rx
+				.recv_timeout(Duration::from_secs(5))
+				.expect("line index finished");
+			envelope.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn lines_become_rows_in_input_order() {
+		let input = "src/main.rs\r\n\nREADME.md\nno newline at end";
+		let (data, rx) = spawn_line_index(Cursor::new(input), "stdin");
+		assert_eq!(data.context_label.as_deref(), Some("stdin"));
+		assert!(data.files.is_empty());
+
+		let paths: Vec<_> = drain(rx)
+			.data
+			.files
+			.into_iter()
+			.map(|file| file.path)
+			.collect();
+		assert_eq!(paths, vec!["src/main.rs", "README.md", "no newline at end"]);
+	}
+
+	#[test]
+	fn large_inputs_are_streamed_in_batches() {
+		let input: String = (0..5_000).map(|i| format!("line {i}\n")).collect();
+		let (_, rx) = spawn_line_index(Cursor::new(input), "stdin");
+
+		let collected = drain(rx);
+		assert_eq!(collected.data.files.len(), 5_000);
+		assert!(collected.updates > 1, "expected several batches");
+		assert_eq!(collected.data.files[4_999].path, "line 4999");
+	}
+
+	#[test]
+	fn invalid_utf8_is_replaced() {
+		let (_, rx) = spawn_line_index(Cursor::new(b"caf\xe9\n".to_vec()), "stdin");
+		assert_eq!(drain(rx).data.files[0].path, "caf\u{fffd}");
+	}
+}
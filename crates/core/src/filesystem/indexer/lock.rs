@@ -0,0 +1,205 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Exclusive ownership of the cache refresh for a single index root.
+///
+/// The first `frz` instance to acquire the lock rewrites the cache; later
+/// instances keep reading the existing cache but skip writing it. The lock
+/// file records the owner's PID and acquisition time so that locks left by
+/// crashed processes can be reclaimed.
+///
+/// A lock is only reclaimed once its owner is known to have exited: however
+/// long a live owner takes to index, and whatever state its half-written or
+/// unreadable lock file is in, the lock stays its own.
+pub(super) struct CacheLock {
+	path: PathBuf,
+}
+
+impl CacheLock {
+	/// Try to take the lock at `path`, returning `None` if another live
+	/// instance already owns it.
+	pub fn try_acquire(path: &Path) -> Option<Self> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir).ok()?;
+		}
+
+		// One retry after clearing a stale lock; a second collision means
+		// another instance reclaimed it first.
+		for _ in 0..2 {
+			match OpenOptions::new().write(true).create_new(true).open(path) {
+				Ok(mut file) => {
+					let _ = write!(file, "{}\n{}\n", process::id(), unix_seconds());
+					return Some(Self {
+						path: path.to_path_buf(),
+					});
+				}
+				Err(err) if err.kind() == ErrorKind::AlreadyExists => match owner(path) {
+					Owner::Alive => return None,
+					// Released between our open and read; try again.
+					Owner::Gone => {}
+					Owner::Dead(contents) => {
+						// Only remove the lock we judged stale, not one a
+						// third instance took in the meantime.
+						if fs::read_to_string(path).is_ok_and(|current| current == contents) {
+							let _ = fs::remove_file(path);
+						}
+					}
+				},
+				Err(_) => return None,
+			}
+		}
+
+		None
+	}
+}
+
+impl Drop for CacheLock {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+fn unix_seconds() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+/// State of the instance holding an existing lock file.
+enum Owner {
+	/// Running, or not known to have exited.
+	Alive,
+	/// Exited without releasing the lock, whose contents are kept.
+	Dead(String),
+	/// The lock file no longer exists.
+	Gone,
+}
+
+fn owner(path: &Path) -> Owner {
+	let contents = match fs::read_to_string(path) {
+		Ok(contents) => contents,
+		Err(err) if err.kind() == ErrorKind::NotFound => return Owner::Gone,
+		// Unreadable locks are held until proven otherwise.
+		Err(_) => return Owner::Alive,
+	};
+
+	// A lock still being written has no PID yet and belongs to its writer.
+	let pid = contents
+		.lines()
+		.next()
+		.and_then(|line| line.trim().parse::<u32>().ok());
+	match pid.and_then(process_alive) {
+		Some(false) => Owner::Dead(contents),
+		_ => Owner::Alive,
+	}
+}
+
+/// Whether `pid` refers to a running process, when that can be determined.
+fn process_alive(pid: u32) -> Option<bool> {
+	if pid == process::id() {
+		return Some(true);
+	}
+
+	let proc_root = Path::new("/proc");
+	if proc_root.join("self").exists() {
+		return Some(proc_root.join(pid.to_string()).exists());
+	}
+
+	// Elsewhere on Unix, signal 0 checks for the process without touching it.
+	if cfg!(unix) {
+		return Command::new("kill")
+			.args(["-0", &pid.to_string()])
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status()
+			.ok()
+			.map(|status| status.success());
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Barrier};
+	use std::thread;
+
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn only_one_concurrent_writer_owns_the_lock() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("filesystem").join("root.lock");
+		let barrier = Arc::new(Barrier::new(2));
+
+		let handles: Vec<_> = (0..2)
+			.map(|_| {
+				let path = path.clone();
+				let barrier = Arc::clone(&barrier);
+				thread::spawn(move || {
+					barrier.wait();
+					let lock = CacheLock::try_acquire(&path);
+					let owned = lock.is_some();
+					// Hold the lock until both writers have tried to acquire it.
+					barrier.wait();
+					owned
+				})
+			})
+			.collect();
+
+		let owners = handles
+			.into_iter()
+			.map(|handle| handle.join().unwrap())
+			.filter(|owned| *owned)
+			.count();
+		assert_eq!(owners, 1);
+		assert!(!path.exists(), "lock should be released on drop");
+	}
+
+	#[test]
+	fn reclaims_stale_locks() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("root.lock");
+		fs::write(&path, format!("{}\n0\n", u32::MAX)).unwrap();
+
+		let lock = CacheLock::try_acquire(&path);
+		assert!(lock.is_some());
+		let contents = fs::read_to_string(&path).unwrap();
+		assert!(contents.starts_with(&process::id().to_string()));
+	}
+
+	#[test]
+	fn respects_live_locks() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("root.lock");
+		let _owner = CacheLock::try_acquire(&path).unwrap();
+
+		assert!(CacheLock::try_acquire(&path).is_none());
+	}
+
+	#[test]
+	fn old_locks_of_live_owners_are_kept() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("root.lock");
+		let held = format!("{}\n0\n", process::id());
+		fs::write(&path, &held).unwrap();
+
+		assert!(CacheLock::try_acquire(&path).is_none());
+		assert_eq!(fs::read_to_string(&path).unwrap(), held);
+	}
+
+	#[test]
+	fn half_written_locks_are_kept() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("root.lock");
+		fs::write(&path, "").unwrap();
+
+		assert!(CacheLock::try_acquire(&path).is_none());
+		assert!(path.exists());
+	}
+}
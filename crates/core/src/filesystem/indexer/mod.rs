@@ -5,19 +5,24 @@
 
 mod cache;
 mod cached_stream;
+mod globs;
+mod lines;
+mod lock;
 mod options;
 /// Streaming types for emitting index updates to the UI.
 pub mod stream;
 mod traversal;
 mod update_batcher;
 
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
+pub use lines::spawn_line_index;
 pub use options::FilesystemOptions;
 pub use stream::{IndexKind, IndexResult, IndexStream, IndexView};
 pub(crate) use traversal::build_walk;
-pub use traversal::spawn_filesystem_index;
+pub use traversal::{spawn_filesystem_index, spawn_uncached_filesystem_index};
 
 use crate::filesystem::search::{FileRow, SearchData};
 
@@ -30,9 +35,14 @@ pub(crate) const DISPATCH_INTERVAL: Duration = Duration::from_millis(120);
 pub struct IndexUpdate {
 	/// Batch of newly discovered files.
 	pub files: Arc<[FileRow]>,
+	/// Paths of rows to drop before `files` are added: entries deleted since
+	/// the cached index was written, and changed entries whose row is
+	/// replaced by one in `files`. Only set by incremental updates.
+	pub removed: Arc<[String]>,
 	/// Current indexing progress for UI display.
 	pub progress: ProgressSnapshot,
-	/// Whether the consumer should clear existing data before applying this update.
+	/// Whether the consumer should clear existing data before applying this
+	/// update. Incremental updates over a cached index leave it unset.
 	pub reset: bool,
 	/// Complete search data snapshot from cache, if available.
 	pub cached_data: Option<SearchData>,
@@ -43,16 +53,46 @@ pub struct IndexUpdate {
 pub struct ProgressSnapshot {
 	/// Number of files indexed so far.
 	pub indexed_files: usize,
-	/// Total number of files if known (e.g., from a completed cache).
+	/// Total number of files if known (e.g., from a completed cache). Always
+	/// set once the pass is complete.
 	pub total_files: Option<usize>,
-	/// Whether the indexing pass has finished.
+	/// Whether the indexing pass has finished. Set on exactly one update,
+	/// the last one of the pass, even when nothing was indexed. Streaming a
+	/// complete cache counts as a pass before the refresh walk.
 	pub complete: bool,
+	/// Number of entries left out because they lead to a file that is
+	/// already indexed, when [`FilesystemOptions::dedupe_hardlinks`] is set.
+	pub skipped_duplicates: usize,
+	/// How this instance uses the index cache of the root.
+	pub cache_role: CacheRole,
+}
+
+/// How an instance uses the index cache of its root when several index the
+/// same root at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheRole {
+	/// No cache is read or written, as for uncached walks and piped lines.
+	#[default]
+	Uncached,
+	/// This instance took the root's lock and rewrites the cache once its
+	/// walk completes.
+	Owner,
+	/// Another instance holds the root's lock; the cache is read but not
+	/// written.
+	Reader,
 }
 
 /// Merge an index update into the search data, resetting if indicated.
+///
+/// Rows named in [`IndexUpdate::removed`] are pruned first, which shifts the
+/// indices of the rows after them.
 pub fn merge_update(data: &mut SearchData, update: &IndexUpdate) {
 	if update.reset {
 		data.files.clear();
+	} else if !update.removed.is_empty() {
+		let removed: HashSet<&str> = update.removed.iter().map(String::as_str).collect();
+		data.files
+			.retain(|file| !removed.contains(file.path.as_str()));
 	}
 
 	if !update.files.is_empty() {
@@ -4,6 +4,9 @@ use std::num::NonZeroUsize;
 use std::path::Path;
 use std::thread;
 
+use super::globs::GlobFilter;
+use crate::error::Result;
+
 /// Configuration options for filesystem scanning and filtering.
 #[derive(Debug, Clone)]
 pub struct FilesystemOptions {
@@ -13,6 +16,9 @@ pub struct FilesystemOptions {
 	pub follow_symlinks: bool,
 	/// Respect .ignore files.
 	pub respect_ignore_files: bool,
+	/// Apply git's ignore rules at all. When disabled, `git_ignore`,
+	/// `git_global` and `git_exclude` are ignored.
+	pub respect_gitignore: bool,
 	/// Respect .gitignore files.
 	pub git_ignore: bool,
 	/// Respect global gitignore settings.
@@ -27,8 +33,23 @@ pub struct FilesystemOptions {
 	pub max_depth: Option<usize>,
 	/// File extensions to filter by.
 	pub allowed_extensions: Option<Vec<String>>,
+	/// Globs a root-relative path must match to be indexed; empty matches
+	/// everything.
+	pub include_globs: Vec<String>,
+	/// Globs excluding root-relative paths; these win over `include_globs`.
+	pub exclude_globs: Vec<String>,
 	/// Label describing the search context.
 	pub context_label: Option<String>,
+	/// Record each file's size while indexing.
+	pub collect_sizes: bool,
+	/// Record each entry's modification time while indexing.
+	pub collect_mtime: bool,
+	/// Also emit directories below the root as rows.
+	pub include_dirs: bool,
+	/// Index each file once even when several paths lead to it, such as
+	/// hard links or symlinks followed with `follow_symlinks`. The shortest
+	/// path is kept.
+	pub dedupe_hardlinks: bool,
 }
 
 impl Default for FilesystemOptions {
@@ -37,6 +58,7 @@ impl Default for FilesystemOptions {
 			include_hidden: true,
 			follow_symlinks: false,
 			respect_ignore_files: true,
+			respect_gitignore: true,
 			git_ignore: true,
 			git_global: true,
 			git_exclude: true,
@@ -60,7 +82,13 @@ impl Default for FilesystemOptions {
 			threads: None,
 			max_depth: None,
 			allowed_extensions: None,
+			include_globs: Vec::new(),
+			exclude_globs: Vec::new(),
 			context_label: None,
+			collect_sizes: false,
+			collect_mtime: false,
+			include_dirs: false,
+			dedupe_hardlinks: false,
 		}
 	}
 }
@@ -85,6 +113,11 @@ impl FilesystemOptions {
 		})
 	}
 
+	/// Compile the include and exclude globs, if any are configured.
+	pub(crate) fn glob_filter(&self) -> Result<Option<GlobFilter>> {
+		GlobFilter::new(&self.include_globs, &self.exclude_globs)
+	}
+
 	/// Create a set of directory names to globally ignore.
 	pub fn global_ignore_set(&self) -> HashSet<OsString> {
 		self.global_ignores
@@ -26,6 +26,16 @@ pub trait IndexView {
 
 	/// Schedule any follow-up work needed after ingesting an update.
 	fn schedule_search_refresh_after_index_update(&mut self, changed: bool);
+
+	/// Whether the final update of the indexing pass has been applied.
+	///
+	/// Every pass ends with exactly one update whose progress is complete and
+	/// carries the total count, so views can stop waiting once they see it.
+	/// Views that do not track it keep the default, which never reports
+	/// completion.
+	fn is_complete(&self) -> bool {
+		false
+	}
 }
 
 /// Marker type for index view actions in the streaming framework.
@@ -66,7 +76,8 @@ impl<'a> IndexStream<'a> {
 	/// Emit an update describing newly indexed data.
 	///
 	/// A `complete` flag of `true` indicates that the filesystem worker has
-	/// finished streaming updates for the current crawl pass.
+	/// finished streaming updates for the current crawl pass. It must match
+	/// `update.progress.complete`, and only the last update of a pass sets it.
 	pub fn send_update(&self, update: IndexUpdate, complete: bool) -> bool {
 		let progress = update.progress;
 		self.send_with(
@@ -1,19 +1,20 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::mpsc::{self, Receiver};
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use std::{fs, io, thread};
 
-use anyhow::Result;
 use ignore::{DirEntry, Error as IgnoreError, WalkBuilder, WalkState};
 
 use super::cache::{CacheHandle, CacheWriter};
 use super::cached_stream::stream_cached_entry;
-use super::update_batcher::UpdateBatcher;
+use super::update_batcher::{FileIdentity, UpdateBatcher};
 use super::{
-	FilesystemOptions, IndexKind, IndexResult, IndexStream, IndexUpdate, ProgressSnapshot,
+	CacheRole, FilesystemOptions, IndexKind, IndexResult, IndexStream, IndexUpdate,
+	ProgressSnapshot,
 };
-use crate::filesystem::search::{FileRow, SearchData};
+use crate::error::{Error, Result};
+use crate::filesystem::search::{EntryKind, FileRow, SearchData};
 
 /// Spawn a background thread that walks the filesystem and streams updates.
 ///
@@ -21,16 +22,57 @@ use crate::filesystem::search::{FileRow, SearchData};
 /// receiver for [`IndexResult`] messages. The caller should forward these
 /// messages to the UI event loop.
 ///
+/// When a cached index is streamed first, the walk that follows compares the
+/// modification time of every entry with the cached one and only emits the
+/// entries that were added or changed, plus the paths of deleted entries, as
+/// incremental updates.
+///
 /// # Errors
 ///
-/// Returns an error if the initial channel setup fails.
+/// Returns [`Error::Io`] if `root` is not an accessible directory and
+/// [`Error::Config`] if the include or exclude globs fail to compile.
 pub fn spawn_filesystem_index(
+	root: PathBuf,
+	options: FilesystemOptions,
+) -> Result<(SearchData, Receiver<IndexResult>)> {
+	let cache_handle = CacheHandle::resolve(&root, &options);
+	spawn_index(root, options, cache_handle)
+}
+
+/// Spawn a filesystem walk like [`spawn_filesystem_index`] that neither
+/// reads nor writes the index cache.
+///
+/// Every update carries only newly discovered rows and nothing is removed,
+/// so consumers that act on rows as they arrive never have to take one
+/// back. The walk starts right away instead of after the delay a fresh
+/// cache would impose.
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if `root` is not an accessible directory and
+/// [`Error::Config`] if the include or exclude globs fail to compile.
+pub fn spawn_uncached_filesystem_index(
+	root: PathBuf,
+	options: FilesystemOptions,
+) -> Result<(SearchData, Receiver<IndexResult>)> {
+	spawn_index(root, options, None)
+}
+
+fn spawn_index(
 	root: PathBuf,
 	mut options: FilesystemOptions,
+	cache_handle: Option<CacheHandle>,
 ) -> Result<(SearchData, Receiver<IndexResult>)> {
+	let metadata = fs::metadata(&root).map_err(|err| Error::io(&root, err))?;
+	if !metadata.is_dir() {
+		return Err(Error::io(
+			&root,
+			io::Error::from(io::ErrorKind::NotADirectory),
+		));
+	}
+	let glob_filter = options.glob_filter()?.map(Arc::new);
 	let (tx, rx) = mpsc::channel();
 
-	let cache_handle = CacheHandle::resolve(&root, &options);
 	let mut data = SearchData::new();
 	data.root = Some(root.clone());
 
@@ -41,9 +83,21 @@ pub fn spawn_filesystem_index(
 	let cache_handle_for_thread = cache_handle.clone();
 
 	thread::spawn(move || {
+		// Take the root's lock before anything is sent, so every update
+		// reports whether this instance refreshes the cache.
+		let cache_writer = cache_handle_for_thread
+			.as_ref()
+			.and_then(|handle| handle.writer(context_label.clone()));
+		let cache_role = match (&cache_handle_for_thread, &cache_writer) {
+			(None, _) => CacheRole::Uncached,
+			(Some(_), Some(_)) => CacheRole::Owner,
+			(Some(_), None) => CacheRole::Reader,
+		};
 		let mut reindex_delay = Duration::ZERO;
 		let mut preview_complete = false;
 		let mut preview_file_count = None;
+		// Entries the consumer holds once the cache has been streamed.
+		let mut baseline = None;
 		let root_for_data = root.clone();
 
 		if let Some(handle) = cache_handle_for_thread.as_ref() {
@@ -59,11 +113,20 @@ pub fn spawn_filesystem_index(
 					preview.data.root = Some(root_for_data.clone());
 				}
 
+				if preview_is_complete {
+					baseline = Some(std::mem::take(&mut preview.stamps));
+				}
+
 				let files: Arc<[FileRow]> = preview.data.files.clone().into();
 				let progress = ProgressSnapshot {
 					indexed_files: files.len(),
 					total_files: preview_is_complete.then_some(files.len()),
+					// A complete cache is a finished pass of its own, so warm
+					// starts are not shown as indexing until the refresh walk
+					// that follows ends with its own completion event.
 					complete: preview_is_complete,
+					skipped_duplicates: 0,
+					cache_role,
 				};
 
 				if !files.is_empty() {
@@ -71,6 +134,7 @@ pub fn spawn_filesystem_index(
 					let _ = stream.send_update(
 						IndexUpdate {
 							files,
+							removed: Arc::from([]),
 							progress,
 							reset: true,
 							cached_data: Some(preview.data),
@@ -91,8 +155,18 @@ pub fn spawn_filesystem_index(
 				if entry.data.root.is_none() {
 					entry.data.root = Some(root_for_data.clone());
 				}
+				let entry_is_complete = entry.is_complete();
+				if entry_is_complete {
+					baseline = Some(std::mem::take(&mut entry.stamps));
+				}
 
-				stream_cached_entry(entry, preview_file_count, &tx);
+				stream_cached_entry(
+					entry,
+					preview_file_count,
+					entry_is_complete,
+					cache_role,
+					&tx,
+				);
 			}
 		}
 
@@ -100,19 +174,31 @@ pub fn spawn_filesystem_index(
 			thread::sleep(reindex_delay);
 		}
 
-		let (file_tx, file_rx) = mpsc::channel::<FileRow>();
+		let (file_tx, file_rx) =
+			mpsc::channel::<(FileRow, Option<SystemTime>, Option<FileIdentity>)>();
 		let walker_root = Arc::new(root);
 		let extension_filter = options.extension_filter().map(Arc::new);
+		let collect_sizes = options.collect_sizes;
+		let collect_mtime = options.collect_mtime;
+		let include_dirs = options.include_dirs;
+		let dedupe = options.dedupe_hardlinks;
+		// Modification times are persisted so the next run can tell which
+		// entries changed.
+		let collect_stamps = cache_handle_for_thread.is_some();
 		let update_tx = tx;
 
-		let cache_writer = cache_handle_for_thread
-			.as_ref()
-			.and_then(|handle| handle.writer(context_label.clone()));
 		let aggregator = thread::spawn(move || {
-			let mut batcher = UpdateBatcher::new(should_reset, cache_writer);
+			let mut batcher =
+				UpdateBatcher::new(should_reset, cache_writer).with_cache_role(cache_role);
+			if let Some(baseline) = baseline {
+				batcher = batcher.with_baseline(baseline);
+			}
+			if dedupe {
+				batcher = batcher.with_dedupe();
+			}
 
-			while let Ok(file) = file_rx.recv() {
-				batcher.record_file(file);
+			while let Ok((file, stamp, identity)) = file_rx.recv() {
+				batcher.record_file(file, stamp, identity);
 
 				if batcher.should_flush() && !batcher.flush(&update_tx, false) {
 					return None::<CacheWriter>;
@@ -128,18 +214,28 @@ pub fn spawn_filesystem_index(
 				let sender = file_tx.clone();
 				let root = Arc::clone(&walker_root);
 				let extension_filter = extension_filter.clone();
+				let glob_filter = glob_filter.clone();
 				Box::new(move |entry: Result<DirEntry, IgnoreError>| {
 					if let Ok(entry) = entry {
 						let Some(file_type) = entry.file_type() else {
 							return WalkState::Continue;
 						};
-						if !file_type.is_file() {
+						let is_dir = file_type.is_dir();
+						let listed_dir = include_dirs && is_dir && entry.depth() > 0;
+						if !file_type.is_file() && !listed_dir {
 							return WalkState::Continue;
 						}
+						let kind = if entry.path_is_symlink() {
+							EntryKind::Symlink
+						} else if is_dir {
+							EntryKind::Dir
+						} else {
+							EntryKind::File
+						};
 
 						let path = entry.path();
 						let relative = path.strip_prefix(root.as_path()).unwrap_or(path);
-						if let Some(filter) = extension_filter.as_ref() {
+						if !is_dir && let Some(filter) = extension_filter.as_ref() {
 							let extension = relative
 								.extension()
 								.and_then(|ext| ext.to_str())
@@ -148,9 +244,31 @@ pub fn spawn_filesystem_index(
 								return WalkState::Continue;
 							}
 						}
-						let relative_display = relative.to_string_lossy().replace('\\', "/");
-						let file = FileRow::filesystem(relative_display);
-						if sender.send(file).is_err() {
+						if glob_filter
+							.as_ref()
+							.is_some_and(|filter| !filter.matches(relative))
+						{
+							return WalkState::Continue;
+						}
+						let metadata = if collect_sizes || collect_mtime || collect_stamps {
+							entry.metadata().ok()
+						} else {
+							None
+						};
+						let size = metadata
+							.as_ref()
+							.filter(|_| collect_sizes && !is_dir)
+							.map(|metadata| metadata.len());
+						let stamp = metadata
+							.as_ref()
+							.and_then(|metadata| metadata.modified().ok());
+						let modified = stamp.filter(|_| collect_mtime);
+						let identity = if dedupe { file_identity(path) } else { None };
+						let file = FileRow::from_path(relative)
+							.with_size(size)
+							.with_modified(modified)
+							.with_kind(kind);
+						if sender.send((file, stamp, identity)).is_err() {
 							return WalkState::Quit;
 						}
 					}
@@ -168,17 +286,37 @@ pub fn spawn_filesystem_index(
 	Ok((data, rx))
 }
 
+/// Identity of the file `path` leads to, following symlinks.
+#[cfg(unix)]
+fn file_identity(path: &Path) -> Option<FileIdentity> {
+	use std::os::unix::fs::MetadataExt;
+
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.dev(), metadata.ino()))
+}
+
+/// Identity of the file `path` leads to, following symlinks.
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> Option<FileIdentity> {
+	fs::canonicalize(path).ok()
+}
+
 /// Build a configured filesystem walker for the given root and options.
+///
+/// Git ignore rules follow git's semantics: nested `.gitignore` files,
+/// `.git/info/exclude` and the global excludes file only apply inside a git
+/// repository.
 pub(crate) fn build_walk(root: &Path, options: &FilesystemOptions) -> WalkBuilder {
 	let ignores = options.global_ignore_set();
+	let git = options.respect_gitignore;
 	let mut walker = WalkBuilder::new(root);
 
 	walker
 		.hidden(!options.include_hidden)
 		.follow_links(options.follow_symlinks)
-		.git_ignore(options.git_ignore)
-		.git_global(options.git_global)
-		.git_exclude(options.git_exclude)
+		.git_ignore(git && options.git_ignore)
+		.git_global(git && options.git_global)
+		.git_exclude(git && options.git_exclude)
 		.ignore(options.respect_ignore_files)
 		.parents(true)
 		.threads(options.thread_count())
@@ -191,3 +329,512 @@ pub(crate) fn build_walk(root: &Path, options: &FilesystemOptions) -> WalkBuilde
 
 	walker
 }
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use tempfile::tempdir;
+
+	use super::*;
+	use crate::filesystem::indexer::{IndexView, merge_update};
+	use crate::filesystem::search::SortKey;
+
+	fn walk(root: &Path, options: &FilesystemOptions) -> Vec<String> {
+		let mut files: Vec<String> = build_walk(root, options)
+			.build()
+			.filter_map(Result::ok)
+			.filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+			.map(|entry| {
+				let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+				relative.to_string_lossy().replace('\\', "/")
+			})
+			.collect();
+		files.sort();
+		files
+	}
+
+	fn repo() -> tempfile::TempDir {
+		let dir = tempdir().unwrap();
+		let root = dir.path();
+		fs::create_dir_all(root.join(".git/info")).unwrap();
+		fs::create_dir_all(root.join("build")).unwrap();
+		fs::create_dir_all(root.join("src/generated")).unwrap();
+		fs::write(root.join(".gitignore"), "build/\n").unwrap();
+		fs::write(root.join(".git/info/exclude"), "notes.txt\n").unwrap();
+		fs::write(root.join("src/.gitignore"), "generated/\n").unwrap();
+		fs::write(root.join(".env"), "").unwrap();
+		fs::write(root.join("notes.txt"), "").unwrap();
+		fs::write(root.join("build/out.o"), "").unwrap();
+		fs::write(root.join("src/main.rs"), "").unwrap();
+		fs::write(root.join("src/generated/bindings.rs"), "").unwrap();
+		dir
+	}
+
+	#[test]
+	fn gitignore_rules_exclude_ignored_paths() {
+		let dir = repo();
+		let options = FilesystemOptions {
+			include_hidden: false,
+			git_global: false,
+			..FilesystemOptions::default()
+		};
+
+		assert_eq!(walk(dir.path(), &options), vec!["src/main.rs"]);
+	}
+
+	#[test]
+	fn disabling_gitignore_restores_the_unfiltered_walk() {
+		let dir = repo();
+		let options = FilesystemOptions {
+			include_hidden: false,
+			respect_gitignore: false,
+			..FilesystemOptions::default()
+		};
+
+		assert_eq!(
+			walk(dir.path(), &options),
+			vec![
+				"build/out.o",
+				"notes.txt",
+				"src/generated/bindings.rs",
+				"src/main.rs"
+			]
+		);
+	}
+
+	#[test]
+	fn gitignore_composes_with_hidden_files() {
+		let dir = repo();
+		let options = FilesystemOptions {
+			include_hidden: true,
+			git_global: false,
+			..FilesystemOptions::default()
+		};
+
+		assert_eq!(
+			walk(dir.path(), &options),
+			vec![".env", ".gitignore", "src/.gitignore", "src/main.rs"]
+		);
+	}
+
+	#[test]
+	fn invalid_globs_fail_to_spawn() {
+		let dir = tempdir().unwrap();
+		let options = FilesystemOptions {
+			exclude_globs: vec!["target/[".to_string()],
+			..FilesystemOptions::default()
+		};
+
+		let Err(err) = spawn_filesystem_index(dir.path().to_path_buf(), options) else {
+			panic!("expected invalid glob to be rejected");
+		};
+		assert!(matches!(err, Error::Config { .. }), "{err:?}");
+		assert!(err.to_string().contains("target/["));
+		assert!(std::error::Error::source(&err).is_some());
+	}
+
+	#[test]
+	fn missing_root_is_an_io_error() {
+		let dir = tempdir().unwrap();
+		let missing = dir.path().join("missing");
+
+		let Err(err) = spawn_filesystem_index(missing.clone(), FilesystemOptions::default()) else {
+			panic!("expected a missing root to be rejected");
+		};
+		match err {
+			Error::Io { path, source } => {
+				assert_eq!(path, missing);
+				assert_eq!(source.kind(), io::ErrorKind::NotFound);
+			}
+			other => panic!("unexpected error: {other:?}"),
+		}
+	}
+
+	#[derive(Default)]
+	struct Collected {
+		rows: Vec<FileRow>,
+		complete: bool,
+	}
+
+	impl IndexView for Collected {
+		fn forward_index_update(&self, _update: &IndexUpdate) {}
+
+		fn apply_index_update(&mut self, update: IndexUpdate) -> bool {
+			self.rows.extend(update.files.iter().cloned());
+			true
+		}
+
+		fn record_index_progress(&mut self, progress: ProgressSnapshot) {
+			self.complete = progress.complete;
+		}
+
+		fn schedule_search_refresh_after_index_update(&mut self, _changed: bool) {}
+
+		fn is_complete(&self) -> bool {
+			self.complete
+		}
+	}
+
+	fn indexed_rows(root: &Path, options: FilesystemOptions) -> Vec<FileRow> {
+		let (_, rx) = spawn_filesystem_index(root.to_path_buf(), options).unwrap();
+		let mut collected = Collected::default();
+		for message in rx {
+			message.payload.apply(&mut collected);
+			if collected.is_complete() {
+				break;
+			}
+		}
+		collected.rows
+	}
+
+	fn indexed(root: &Path, options: FilesystemOptions) -> Vec<(String, EntryKind)> {
+		let mut rows: Vec<_> = indexed_rows(root, options)
+			.into_iter()
+			.map(|row| (row.path, row.kind))
+			.collect();
+		rows.sort_by(|a, b| a.0.cmp(&b.0));
+		rows.dedup();
+		rows
+	}
+
+	fn tree() -> tempfile::TempDir {
+		let dir = tempdir().unwrap();
+		fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+		fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+		fs::write(dir.path().join("README.md"), "").unwrap();
+		dir
+	}
+
+	#[test]
+	fn directories_are_only_indexed_when_requested() {
+		let dir = tree();
+
+		assert_eq!(
+			indexed(dir.path(), FilesystemOptions::default()),
+			vec![
+				("README.md".to_string(), EntryKind::File),
+				("src/lib.rs".to_string(), EntryKind::File),
+			]
+		);
+
+		let options = FilesystemOptions {
+			include_dirs: true,
+			..FilesystemOptions::default()
+		};
+		assert_eq!(
+			indexed(dir.path(), options),
+			vec![
+				("README.md".to_string(), EntryKind::File),
+				("src".to_string(), EntryKind::Dir),
+				("src/bin".to_string(), EntryKind::Dir),
+				("src/lib.rs".to_string(), EntryKind::File),
+			]
+		);
+	}
+
+	#[test]
+	fn extension_filter_keeps_directories() {
+		let dir = tree();
+		let options = FilesystemOptions {
+			include_dirs: true,
+			allowed_extensions: Some(vec!["rs".to_string()]),
+			..FilesystemOptions::default()
+		};
+
+		assert_eq!(
+			indexed(dir.path(), options),
+			vec![
+				("src".to_string(), EntryKind::Dir),
+				("src/bin".to_string(), EntryKind::Dir),
+				("src/lib.rs".to_string(), EntryKind::File),
+			]
+		);
+	}
+
+	#[test]
+	fn modification_times_are_collected_on_request() {
+		let dir = tempdir().unwrap();
+		let base = std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		for (name, age) in [("old.txt", 300), ("new.txt", 0), ("mid.txt", 100)] {
+			let path = dir.path().join(name);
+			fs::write(&path, "").unwrap();
+			fs::File::options()
+				.write(true)
+				.open(&path)
+				.unwrap()
+				.set_modified(base - Duration::from_secs(age))
+				.unwrap();
+		}
+
+		let rows = indexed_rows(dir.path(), FilesystemOptions::default());
+		assert!(rows.iter().all(|row| row.modified.is_none()));
+
+		let options = FilesystemOptions {
+			collect_mtime: true,
+			..FilesystemOptions::default()
+		};
+		let mut rows = indexed_rows(dir.path(), options);
+		rows.sort_by(|a, b| a.path.cmp(&b.path));
+		rows.dedup_by(|a, b| a.path == b.path);
+		rows.push(FileRow::filesystem("unknown.txt"));
+
+		let mut indices: Vec<usize> = (0..rows.len()).collect();
+		let mut scores = vec![0; rows.len()];
+		SortKey::ModifiedTime.apply(&rows, &mut indices, &mut scores);
+
+		let order: Vec<&str> = indices.iter().map(|&i| rows[i].path.as_str()).collect();
+		assert_eq!(order, vec!["new.txt", "mid.txt", "old.txt", "unknown.txt"]);
+	}
+
+	#[derive(Default)]
+	struct Updates(Vec<IndexUpdate>);
+
+	impl IndexView for Updates {
+		fn forward_index_update(&self, _update: &IndexUpdate) {}
+
+		fn apply_index_update(&mut self, update: IndexUpdate) -> bool {
+			self.0.push(update);
+			true
+		}
+
+		fn record_index_progress(&mut self, _progress: ProgressSnapshot) {}
+
+		fn schedule_search_refresh_after_index_update(&mut self, _changed: bool) {}
+
+		fn is_complete(&self) -> bool {
+			self.0.last().is_some_and(|update| update.progress.complete)
+		}
+	}
+
+	/// Walk updates of one indexing pass, leaving out the cached preview.
+	fn walk_updates(
+		root: &Path,
+		options: FilesystemOptions,
+		cache: Option<CacheHandle>,
+	) -> Vec<IndexUpdate> {
+		let (_, rx) = spawn_index(root.to_path_buf(), options, cache).unwrap();
+		let mut updates = Updates::default();
+		for message in rx {
+			if message.kind != IndexKind::Update {
+				continue;
+			}
+			message.payload.apply(&mut updates);
+			if updates.is_complete() {
+				break;
+			}
+		}
+		updates.0
+	}
+
+	/// Wait for the background cache write `written` checks for.
+	fn wait_until_cached(written: impl Fn() -> bool) {
+		let deadline = std::time::Instant::now() + Duration::from_secs(10);
+		while !written() {
+			assert!(
+				std::time::Instant::now() < deadline,
+				"cache was never written"
+			);
+			thread::sleep(Duration::from_millis(10));
+		}
+	}
+
+	fn touch(path: &Path, secs: u64) {
+		fs::File::options()
+			.write(true)
+			.open(path)
+			.unwrap()
+			.set_modified(std::time::UNIX_EPOCH + Duration::from_secs(secs))
+			.unwrap();
+	}
+
+	#[test]
+	fn reindexing_a_cached_tree_emits_only_changed_entries() {
+		let dir = tempdir().unwrap();
+		let cache_dir = tempdir().unwrap();
+		for name in ["a.txt", "b.txt", "c.txt"] {
+			fs::write(dir.path().join(name), name).unwrap();
+			touch(&dir.path().join(name), 1_700_000_000);
+		}
+		let options = FilesystemOptions::default();
+		let cache =
+			CacheHandle::in_dir(cache_dir.path(), dir.path(), &options).with_ttl(Duration::ZERO);
+
+		let first = walk_updates(dir.path(), options.clone(), Some(cache.clone()));
+		assert_eq!(
+			first.iter().map(|update| update.files.len()).sum::<usize>(),
+			3
+		);
+		wait_until_cached(|| cache.load().is_some());
+
+		touch(&dir.path().join("b.txt"), 1_700_000_100);
+		fs::remove_file(dir.path().join("c.txt")).unwrap();
+		fs::write(dir.path().join("d.txt"), "d").unwrap();
+
+		let second = walk_updates(dir.path(), options, Some(cache));
+		assert!(second.iter().all(|update| !update.reset));
+		let mut files: Vec<&str> = second
+			.iter()
+			.flat_map(|update| update.files.iter().map(|row| row.path.as_str()))
+			.collect();
+		files.sort_unstable();
+		assert_eq!(files, ["b.txt", "d.txt"]);
+		let mut removed: Vec<&str> = second
+			.iter()
+			.flat_map(|update| update.removed.iter().map(String::as_str))
+			.collect();
+		removed.sort_unstable();
+		assert_eq!(removed, ["b.txt", "c.txt"]);
+
+		let mut data = SearchData::new();
+		data.files = ["a.txt", "b.txt", "c.txt"].map(FileRow::filesystem).into();
+		for update in &second {
+			merge_update(&mut data, update);
+		}
+		let mut paths: Vec<&str> = data.files.iter().map(|row| row.path.as_str()).collect();
+		paths.sort_unstable();
+		assert_eq!(paths, ["a.txt", "b.txt", "d.txt"]);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn linked_duplicates_are_indexed_once_under_the_shortest_path() {
+		let dir = tempdir().unwrap();
+		let root = dir.path();
+		fs::create_dir_all(root.join("deep/nested")).unwrap();
+		fs::write(root.join("a.txt"), "a").unwrap();
+		fs::write(root.join("b.txt"), "b").unwrap();
+		fs::hard_link(root.join("a.txt"), root.join("deep/nested/a-link.txt")).unwrap();
+		std::os::unix::fs::symlink(root.join("deep/nested"), root.join("short")).unwrap();
+
+		let paths = |updates: &[IndexUpdate]| {
+			let mut data = SearchData::new();
+			for update in updates {
+				merge_update(&mut data, update);
+			}
+			let mut paths: Vec<String> = data.files.into_iter().map(|row| row.path).collect();
+			paths.sort_unstable();
+			paths
+		};
+		let options = FilesystemOptions {
+			follow_symlinks: true,
+			..FilesystemOptions::default()
+		};
+
+		let updates = walk_updates(root, options.clone(), None);
+		assert_eq!(
+			paths(&updates),
+			[
+				"a.txt",
+				"b.txt",
+				"deep/nested/a-link.txt",
+				"short/a-link.txt"
+			]
+		);
+		assert_eq!(updates.last().unwrap().progress.skipped_duplicates, 0);
+
+		let options = FilesystemOptions {
+			dedupe_hardlinks: true,
+			..options
+		};
+		let updates = walk_updates(root, options, None);
+		assert_eq!(paths(&updates), ["a.txt", "b.txt"]);
+		let progress = updates.last().unwrap().progress;
+		assert_eq!(progress.skipped_duplicates, 2);
+		assert_eq!(progress.total_files, Some(2));
+	}
+
+	#[test]
+	fn updates_report_whether_this_instance_owns_the_cache() {
+		let dir = tempdir().unwrap();
+		fs::write(dir.path().join("a.txt"), "a").unwrap();
+		let cache_dir = tempdir().unwrap();
+		let options = FilesystemOptions::default();
+		let cache = CacheHandle::in_dir(cache_dir.path(), dir.path(), &options);
+		let roles = |cache: Option<CacheHandle>| -> Vec<CacheRole> {
+			walk_updates(dir.path(), options.clone(), cache)
+				.iter()
+				.map(|update| update.progress.cache_role)
+				.collect()
+		};
+
+		assert_eq!(roles(None), [CacheRole::Uncached]);
+		let other_instance = cache.writer(None).expect("lock is free");
+		assert_eq!(roles(Some(cache.clone())), [CacheRole::Reader]);
+		drop(other_instance);
+		assert_eq!(roles(Some(cache)), [CacheRole::Owner]);
+	}
+
+	#[test]
+	fn every_pass_ends_with_one_completion_event() {
+		let dir = tempdir().unwrap();
+		let updates = walk_updates(dir.path(), FilesystemOptions::default(), None);
+		let [last] = updates.as_slice() else {
+			panic!("expected a single update, got {updates:?}");
+		};
+		assert!(last.progress.complete);
+		assert_eq!(last.progress.total_files, Some(0));
+
+		// A complete cached preview finishes a pass of its own, and the
+		// refresh walk that follows ends with a second one.
+		fs::write(dir.path().join("a.txt"), "a").unwrap();
+		let cache_dir = tempdir().unwrap();
+		let options = FilesystemOptions::default();
+		let cache =
+			CacheHandle::in_dir(cache_dir.path(), dir.path(), &options).with_ttl(Duration::ZERO);
+		walk_updates(dir.path(), options.clone(), Some(cache.clone()));
+		wait_until_cached(|| cache.load_preview().is_some());
+
+		let (_, rx) = spawn_index(dir.path().to_path_buf(), options, Some(cache)).unwrap();
+		let mut updates = Updates::default();
+		let mut envelopes = Vec::new();
+		for message in rx {
+			envelopes.push((message.kind, message.complete));
+			message.payload.apply(&mut updates);
+		}
+		assert_eq!(envelopes.first(), Some(&(IndexKind::Preview, true)));
+		let completions: Vec<_> = updates
+			.0
+			.iter()
+			.map(|update| update.progress.complete)
+			.collect();
+		assert_eq!(
+			completions,
+			envelopes
+				.iter()
+				.map(|(_, complete)| *complete)
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(completions.iter().filter(|complete| **complete).count(), 2);
+		assert_eq!(completions.first(), Some(&true));
+		assert_eq!(completions.last(), Some(&true));
+		assert_eq!(updates.0.last().unwrap().progress.total_files, Some(1));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_names_are_indexed_with_their_original_path() {
+		use std::ffi::OsStr;
+		use std::os::unix::ffi::OsStrExt;
+
+		let dir = tempdir().unwrap();
+		let name = Path::new(OsStr::from_bytes(b"caf\xe9.txt"));
+		fs::write(dir.path().join(name), "").unwrap();
+		fs::write(dir.path().join("cafe.txt"), "").unwrap();
+
+		let updates = walk_updates(dir.path(), FilesystemOptions::default(), None);
+		let mut rows: Vec<FileRow> = updates
+			.iter()
+			.flat_map(|update| update.files.iter().cloned())
+			.collect();
+		rows.sort_by(|a, b| a.path.cmp(&b.path));
+		let [plain, lossy] = rows.as_slice() else {
+			panic!("expected two rows, got {rows:?}");
+		};
+		assert_eq!(plain.path, "cafe.txt");
+		assert!(!plain.has_lossy_path());
+		assert_eq!(lossy.path, "caf\u{FFFD}.txt");
+		assert_eq!(lossy.os_path(), name);
+		assert!(dir.path().join(lossy.os_path()).exists());
+	}
+}
@@ -1,48 +1,146 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::sync::Arc;
 use std::sync::mpsc::Sender;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
-use super::cache::CacheWriter;
+use super::cache::{CacheWriter, EntryStamps};
 use super::{
-	DISPATCH_INTERVAL, IndexKind, IndexResult, IndexStream, IndexUpdate, MAX_BATCH_SIZE,
+	CacheRole, DISPATCH_INTERVAL, IndexKind, IndexResult, IndexStream, IndexUpdate, MAX_BATCH_SIZE,
 	MIN_BATCH_SIZE, ProgressSnapshot,
 };
 use crate::filesystem::search::FileRow;
 
+/// Identity of the file an entry leads to, shared by every link to it.
+#[cfg(unix)]
+pub(super) type FileIdentity = (u64, u64);
+/// Identity of the file an entry leads to, shared by every link to it.
+#[cfg(not(unix))]
+pub(super) type FileIdentity = std::path::PathBuf;
+
 pub(super) struct UpdateBatcher {
 	pending_files: Vec<FileRow>,
+	pending_removed: Vec<String>,
 	indexed_files: usize,
 	last_dispatch: Instant,
 	emit_reset: bool,
 	cache_writer: Option<CacheWriter>,
+	/// Entries the consumer already holds from the cache, with the stamps
+	/// they were cached with. Entries found unchanged are taken out, so what
+	/// remains when the walk ends was deleted.
+	baseline: Option<EntryStamps>,
+	/// Path kept for each file seen so far, when duplicates are dropped.
+	identities: Option<HashMap<FileIdentity, String>>,
+	skipped_duplicates: usize,
+	cache_role: CacheRole,
 }
 
 impl UpdateBatcher {
 	pub fn new(emit_reset: bool, cache_writer: Option<CacheWriter>) -> Self {
 		Self {
 			pending_files: Vec::new(),
+			pending_removed: Vec::new(),
 			indexed_files: 0,
 			last_dispatch: Instant::now(),
 			emit_reset,
 			cache_writer,
+			baseline: None,
+			identities: None,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
 		}
 	}
 
-	pub fn record_file(&mut self, file: FileRow) {
+	/// Report `role` as this instance's use of the index cache.
+	pub fn with_cache_role(mut self, role: CacheRole) -> Self {
+		self.cache_role = role;
+		self
+	}
+
+	/// Emit only the changes against `baseline`, the entries the consumer
+	/// already holds, instead of every walked entry.
+	pub fn with_baseline(mut self, baseline: EntryStamps) -> Self {
+		self.emit_reset = false;
+		self.baseline = Some(baseline);
+		self
+	}
+
+	/// Keep one row per file, preferring the shortest path, for entries
+	/// recorded with an identity.
+	pub fn with_dedupe(mut self) -> Self {
+		self.identities = Some(HashMap::new());
+		self
+	}
+
+	/// Record a walked entry that was last modified at `stamp` and leads to
+	/// the file `identity`.
+	pub fn record_file(
+		&mut self,
+		file: FileRow,
+		stamp: Option<SystemTime>,
+		identity: Option<FileIdentity>,
+	) {
+		if let (Some(identities), Some(identity)) = (&mut self.identities, identity) {
+			let replaced = match identities.entry(identity) {
+				Entry::Vacant(slot) => {
+					slot.insert(file.path.clone());
+					None
+				}
+				Entry::Occupied(mut slot) => {
+					self.skipped_duplicates += 1;
+					if !is_preferred(&file.path, slot.get()) {
+						return;
+					}
+					Some(slot.insert(file.path.clone()))
+				}
+			};
+			if let Some(replaced) = replaced {
+				self.drop_row(replaced);
+			}
+		}
+
 		if let Some(writer) = &mut self.cache_writer {
-			writer.record(&file);
+			writer.record(&file, stamp);
 		}
 
 		self.indexed_files += 1;
+		if let Some(baseline) = &mut self.baseline
+			&& let Some(cached) = baseline.remove(&file.path)
+		{
+			// Entries without a readable time are always refreshed.
+			if cached.is_some() && cached == stamp {
+				return;
+			}
+			self.pending_removed.push(file.path.clone());
+		}
 		self.pending_files.push(file);
 	}
 
+	/// Take back a row recorded earlier: pull it from the pending batch, or
+	/// have the consumer prune it when it was already sent.
+	fn drop_row(&mut self, path: String) {
+		if let Some(writer) = &mut self.cache_writer {
+			writer.forget(&path);
+		}
+		self.indexed_files -= 1;
+		match self.pending_files.iter().position(|file| file.path == path) {
+			Some(index) => {
+				self.pending_files.remove(index);
+			}
+			None => self.pending_removed.push(path),
+		}
+	}
+
+	fn pending_len(&self) -> usize {
+		self.pending_files.len() + self.pending_removed.len()
+	}
+
 	pub fn should_flush(&self) -> bool {
-		if self.pending_files.len() >= batch_size_for(self.indexed_files) {
+		if self.pending_len() >= batch_size_for(self.indexed_files) {
 			return true;
 		}
 
-		if !self.emit_reset && self.pending_files.is_empty() {
+		if !self.emit_reset && self.pending_len() == 0 {
 			return false;
 		}
 
@@ -50,17 +148,20 @@ impl UpdateBatcher {
 	}
 
 	pub fn flush(&mut self, tx: &Sender<IndexResult>, complete: bool) -> bool {
-		if !complete && !self.emit_reset && self.pending_files.is_empty() {
+		if !complete && !self.emit_reset && self.pending_len() == 0 {
 			return true;
 		}
 
 		let files_vec = std::mem::take(&mut self.pending_files);
 		let files: Arc<[FileRow]> = files_vec.into();
+		let removed: Arc<[String]> = std::mem::take(&mut self.pending_removed).into();
 
 		let progress = ProgressSnapshot {
 			indexed_files: self.indexed_files,
 			total_files: complete.then_some(self.indexed_files),
 			complete,
+			skipped_duplicates: self.skipped_duplicates,
+			cache_role: self.cache_role,
 		};
 
 		let reset = self.emit_reset;
@@ -72,6 +173,7 @@ impl UpdateBatcher {
 		if !stream.send_update(
 			IndexUpdate {
 				files,
+				removed,
 				progress,
 				reset,
 				cached_data: None,
@@ -87,6 +189,12 @@ impl UpdateBatcher {
 
 	pub fn finalize(self, tx: &Sender<IndexResult>) -> Option<CacheWriter> {
 		let mut this = self;
+		// Cached entries the walk did not find again were deleted.
+		if let Some(baseline) = this.baseline.take() {
+			let mut deleted: Vec<String> = baseline.into_keys().collect();
+			deleted.sort_unstable();
+			this.pending_removed.extend(deleted);
+		}
 		if !this.flush(tx, true) {
 			return None;
 		}
@@ -94,6 +202,13 @@ impl UpdateBatcher {
 	}
 }
 
+/// Whether `candidate` should replace `kept` as the row for their file:
+/// shorter paths win, and equal lengths are ordered so the choice does not
+/// depend on the walk order.
+fn is_preferred(candidate: &str, kept: &str) -> bool {
+	(candidate.len(), candidate) < (kept.len(), kept)
+}
+
 fn batch_size_for(indexed_files: usize) -> usize {
 	if indexed_files < 1_024 {
 		MIN_BATCH_SIZE
@@ -103,3 +218,73 @@ fn batch_size_for(indexed_files: usize) -> usize {
 		MAX_BATCH_SIZE
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::mpsc;
+
+	use super::*;
+	use crate::filesystem::indexer::{IndexView, merge_update};
+	use crate::filesystem::search::SearchData;
+
+	#[derive(Default)]
+	struct Merged {
+		data: SearchData,
+		complete: bool,
+	}
+
+	impl IndexView for Merged {
+		fn forward_index_update(&self, _update: &IndexUpdate) {}
+
+		fn apply_index_update(&mut self, update: IndexUpdate) -> bool {
+			merge_update(&mut self.data, &update);
+			true
+		}
+
+		fn record_index_progress(&mut self, progress: ProgressSnapshot) {
+			self.complete = progress.complete;
+		}
+
+		fn schedule_search_refresh_after_index_update(&mut self, _changed: bool) {}
+
+		fn is_complete(&self) -> bool {
+			self.complete
+		}
+	}
+
+	fn identity(id: u64) -> Option<FileIdentity> {
+		#[cfg(unix)]
+		return Some((0, id));
+		#[cfg(not(unix))]
+		return Some(id.to_string().into());
+	}
+
+	#[test]
+	fn shorter_duplicates_replace_rows_that_were_already_sent() {
+		let (tx, rx) = mpsc::channel();
+		let mut batcher = UpdateBatcher::new(true, None).with_dedupe();
+
+		batcher.record_file(FileRow::filesystem("very/long/one.txt"), None, identity(1));
+		batcher.record_file(FileRow::filesystem("long/two.txt"), None, identity(2));
+		assert!(batcher.flush(&tx, false));
+		// Replaces a sent row, then a pending one, then loses to the kept row.
+		batcher.record_file(FileRow::filesystem("one.txt"), None, identity(1));
+		batcher.record_file(FileRow::filesystem("b/two.txt"), None, identity(2));
+		batcher.record_file(FileRow::filesystem("a/two.txt"), None, identity(2));
+		batcher.record_file(FileRow::filesystem("deeper/two.txt"), None, identity(2));
+		batcher.finalize(&tx);
+
+		let mut view = Merged::default();
+		for message in rx.try_iter() {
+			message.payload.apply(&mut view);
+		}
+		assert!(view.is_complete());
+		let paths: Vec<&str> = view
+			.data
+			.files
+			.iter()
+			.map(|row| row.path.as_str())
+			.collect();
+		assert_eq!(paths, ["one.txt", "a/two.txt"]);
+	}
+}
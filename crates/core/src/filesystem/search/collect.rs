@@ -0,0 +1,369 @@
+//! Blocking search helpers for scripts and tests.
+//!
+//! These drive the same streaming pipeline as [`stream_files`] on the calling
+//! thread and keep only its final update, so the ranking is identical to what
+//! the UI would show.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, mpsc};
+
+use super::{
+	FileRow, MatchBatch, Query, SearchData, SearchOptions, SearchStream, SearchView, SearchViewV2,
+	SortKey, stream_files_by_tag, stream_files_with_options,
+};
+#[cfg(doc)]
+use super::{MAX_RENDERED_RESULTS, stream_files};
+
+/// Rows from the newest update of a drained search stream.
+#[derive(Default)]
+struct Collected {
+	indices: Vec<usize>,
+	ids: Option<Vec<u64>>,
+	scores: Vec<u16>,
+	positions: Option<Vec<Vec<usize>>>,
+}
+
+impl From<Collected> for MatchBatch {
+	fn from(collected: Collected) -> Self {
+		Self {
+			indices: collected.indices,
+			ids: collected.ids,
+			scores: collected.scores,
+			positions: collected.positions,
+		}
+	}
+}
+
+impl SearchView for Collected {
+	fn replace_matches(&mut self, indices: Vec<usize>, scores: Vec<u16>) {
+		*self = Self {
+			indices,
+			ids: None,
+			scores,
+			positions: None,
+		};
+	}
+
+	fn clear_matches(&mut self) {
+		*self = Self::default();
+	}
+
+	fn record_completion(&mut self, _complete: bool) {}
+
+	fn as_v2(&mut self) -> Option<&mut dyn SearchViewV2> {
+		Some(self)
+	}
+}
+
+impl SearchViewV2 for Collected {
+	fn replace_matches_v2(&mut self, batch: MatchBatch) {
+		*self = Self {
+			indices: batch.indices,
+			ids: batch.ids,
+			scores: batch.scores,
+			positions: batch.positions,
+		};
+	}
+}
+
+/// Run `query` against `data` and return the ranked matches, best first.
+///
+/// This blocks until the search, including any refinement pass, has
+/// finished. At most `limit` matches are returned, and never more than
+/// [`MAX_RENDERED_RESULTS`], the default [`SearchOptions::max_results`]. An
+/// empty query lists every row ordered by path,
+/// like [`stream_files`].
+#[must_use]
+pub fn collect_matches(data: &SearchData, query: &str, limit: usize) -> MatchBatch {
+	collect_matches_with_options(data, query, &SearchOptions::default(), limit)
+}
+
+/// Collect matches like [`collect_matches`] using custom [`SearchOptions`].
+///
+/// At most `limit` matches are returned, and never more than
+/// [`SearchOptions::max_results`].
+#[must_use]
+pub fn collect_matches_with_options(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	limit: usize,
+) -> MatchBatch {
+	collect(options, limit, |stream, latest_query_id| {
+		stream_files_with_options(data, query, options, stream, latest_query_id);
+	})
+}
+
+/// Run `query` against the tags of `data` like [`stream_files_by_tag`] and
+/// return the files carrying a matching tag, best first.
+///
+/// At most `limit` matches are returned, and never more than
+/// [`SearchOptions::max_results`].
+#[must_use]
+pub fn collect_tag_matches(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	limit: usize,
+) -> MatchBatch {
+	collect(options, limit, |stream, latest_query_id| {
+		stream_files_by_tag(data, query, options, stream, latest_query_id);
+	})
+}
+
+/// Drain the updates `search` streams and keep the newest `limit` rows.
+fn collect(
+	options: &SearchOptions,
+	limit: usize,
+	search: impl FnOnce(SearchStream<'_>, &Arc<AtomicU64>),
+) -> MatchBatch {
+	let (tx, rx) = mpsc::channel();
+	let latest_query_id = Arc::new(AtomicU64::new(0));
+	search(SearchStream::new(&tx, 0), &latest_query_id);
+	// A refinement pass keeps its own sender, so the channel stays open until
+	// it has sent the final update.
+	drop(tx);
+
+	let mut collected = Collected::default();
+	for envelope in rx {
+		let complete = envelope.complete;
+		envelope.dispatch(&mut collected);
+		if complete {
+			break;
+		}
+	}
+
+	let mut batch = MatchBatch::from(collected);
+	truncate(&mut batch, limit.min(options.max_results));
+	batch
+}
+
+/// Keep the first `limit` rows of `batch`.
+fn truncate(batch: &mut MatchBatch, limit: usize) {
+	batch.indices.truncate(limit);
+	batch.scores.truncate(limit);
+	if let Some(ids) = batch.ids.as_mut() {
+		ids.truncate(limit);
+	}
+	if let Some(positions) = batch.positions.as_mut() {
+		positions.truncate(limit);
+	}
+}
+
+/// Run `search` with every match kept, then send the first
+/// [`SearchOptions::max_results`] of them in `sort_key` order to `stream`.
+///
+/// Cutting the scored matches down first would keep the best-scoring rows
+/// and only order those, so a newer file outside the limit would never
+/// show up under [`SortKey::ModifiedTime`]. Ordering the whole match set
+/// costs a sort and match positions for every matching row, and the
+/// partial updates of the pass are dropped in favour of one final batch.
+/// Queries made only of filters already stream in `sort_key` order and are
+/// passed through.
+pub(super) fn stream_in_order(
+	files: &[FileRow],
+	query: &str,
+	options: &SearchOptions,
+	sort_key: SortKey,
+	stream: SearchStream<'_>,
+	search: impl FnOnce(&SearchOptions, SearchStream<'_>) -> bool,
+) -> bool {
+	if sort_key == SortKey::Score || Query::parse_with_options(query, options).is_filter_only() {
+		return search(options, stream);
+	}
+
+	let (tx, rx) = mpsc::channel();
+	let scored = match stream.cancellation() {
+		Some(token) => SearchStream::with_cancel(&tx, stream.id(), token.clone()),
+		None => SearchStream::new(&tx, stream.id()),
+	};
+	let unlimited = SearchOptions {
+		max_results: usize::MAX,
+		..options.clone()
+	};
+	let finished = search(&unlimited, scored);
+	drop(tx);
+
+	let mut collected = Collected::default();
+	let mut complete = false;
+	for envelope in rx {
+		complete = envelope.complete;
+		envelope.dispatch(&mut collected);
+		if complete {
+			break;
+		}
+	}
+	// A cancelled or superseded pass never completes, and its rows are stale.
+	if !complete {
+		return finished;
+	}
+
+	let mut batch = MatchBatch::from(collected);
+	sort_key.apply_to_batch(files, &mut batch);
+	truncate(&mut batch, options.max_results);
+	finished && stream.send_batch(batch, true)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::filesystem::search::{
+		FileRow, MAX_RENDERED_RESULTS, PREFILTER_ENABLE_THRESHOLD, stream_files,
+	};
+
+	fn dataset(len: usize) -> SearchData {
+		let mut data = SearchData::new();
+		data.files = (0..len)
+			.map(|i| FileRow::new(format!("src/module_{}/file_{i}.rs", i % 7)))
+			.chain([
+				FileRow::new("src/lib.rs"),
+				FileRow::new("src/main.rs"),
+				FileRow::new("tests/lib_test.rs"),
+				FileRow::new("README.md"),
+			])
+			.collect();
+		data
+	}
+
+	/// Drain [`stream_files`] the way the UI does, keeping every update.
+	fn streamed(data: &SearchData, query: &str) -> (Vec<usize>, Vec<u16>) {
+		struct Latest(Vec<usize>, Vec<u16>);
+
+		impl SearchView for Latest {
+			fn replace_matches(&mut self, indices: Vec<usize>, scores: Vec<u16>) {
+				self.0 = indices;
+				self.1 = scores;
+			}
+
+			fn clear_matches(&mut self) {
+				self.0.clear();
+				self.1.clear();
+			}
+
+			fn record_completion(&mut self, _complete: bool) {}
+		}
+
+		let (tx, rx) = mpsc::channel();
+		let latest_query_id = Arc::new(AtomicU64::new(0));
+		assert!(stream_files(
+			data,
+			query,
+			SearchStream::new(&tx, 0),
+			&latest_query_id
+		));
+		drop(tx);
+
+		let mut view = Latest(Vec::new(), Vec::new());
+		for envelope in rx {
+			let complete = envelope.complete;
+			envelope.dispatch(&mut view);
+			if complete {
+				break;
+			}
+		}
+		(view.0, view.1)
+	}
+
+	#[test]
+	fn matches_agree_with_the_streaming_path() {
+		for len in [16, PREFILTER_ENABLE_THRESHOLD + 50] {
+			let data = dataset(len);
+			for query in ["", "lib", "src rs", "'main", "^tests", "!module", "fl 3"] {
+				let batch = collect_matches(&data, query, usize::MAX);
+				let (indices, scores) = streamed(&data, query);
+				assert_eq!(batch.indices, indices, "indices for {query:?} over {len}");
+				assert_eq!(batch.scores, scores, "scores for {query:?} over {len}");
+			}
+		}
+	}
+
+	#[test]
+	fn results_are_truncated_to_the_limit() {
+		let data = dataset(MAX_RENDERED_RESULTS + 10);
+
+		let batch = collect_matches(&data, "rs", 3);
+		assert_eq!(batch.indices.len(), 3);
+		assert_eq!(batch.scores.len(), 3);
+		assert_eq!(batch.indices, streamed(&data, "rs").0[..3]);
+
+		let batch = collect_matches(&data, "", usize::MAX);
+		assert_eq!(batch.indices.len(), MAX_RENDERED_RESULTS);
+	}
+
+	#[test]
+	fn max_results_raise_or_lower_the_cap() {
+		let data = dataset(MAX_RENDERED_RESULTS + 10);
+		let total = data.files.len();
+
+		let fewer = SearchOptions::default().with_max_results(50);
+		for query in ["", "rs", "!lib"] {
+			let batch = collect_matches_with_options(&data, query, &fewer, usize::MAX);
+			assert_eq!(batch.indices.len(), 50, "{query:?}");
+		}
+
+		let more = SearchOptions::default().with_max_results(total * 2);
+		let batch = collect_matches_with_options(&data, "", &more, usize::MAX);
+		assert_eq!(batch.indices.len(), total);
+		let batch = collect_matches_with_options(&data, "rs", &more, usize::MAX);
+		assert_eq!(batch.indices.len(), total);
+	}
+
+	#[test]
+	fn tags_match_files_whose_paths_do_not() {
+		for len in [16, PREFILTER_ENABLE_THRESHOLD + 50] {
+			let mut data = dataset(len);
+			data.files[2] = FileRow::new("src/notes.txt").with_tags(["urgent", "work"]);
+			assert!(!data.files[2].path.contains("urgent"));
+
+			let batch = collect_matches(&data, "'urgent", usize::MAX);
+			assert_eq!(batch.indices, [2], "over {len}");
+			let batch = collect_matches(&data, "urgent", usize::MAX);
+			assert_eq!(batch.indices.first(), Some(&2), "over {len}");
+			let batch = collect_matches(&data, "'work !src", usize::MAX);
+			assert!(
+				batch.indices.is_empty(),
+				"negations check the path, over {len}"
+			);
+
+			let batch = collect_matches(&data, "tag:Work", usize::MAX);
+			assert_eq!(batch.indices, [2], "over {len}");
+			let batch = collect_matches(&data, "tag:work notes", usize::MAX);
+			assert_eq!(batch.indices, [2], "over {len}");
+			let batch = collect_matches(&data, "tag:work tag:home", usize::MAX);
+			assert!(batch.indices.is_empty(), "over {len}");
+		}
+	}
+
+	#[test]
+	fn tag_searches_return_the_tagged_files() {
+		for len in [16, PREFILTER_ENABLE_THRESHOLD + 50] {
+			let mut data = dataset(len);
+			data.files[1] = FileRow::new("docs/plan.md").with_tags(["work", "urgent"]);
+			data.files[4] = FileRow::new("src/urgent_fix.rs");
+			data.files[5] = FileRow::new("notes/todo.txt").with_tags(["urgent"]);
+			let options = SearchOptions::default();
+
+			let batch = collect_tag_matches(&data, "'urgent", &options, usize::MAX);
+			let mut indices = batch.indices.clone();
+			indices.sort_unstable();
+			assert_eq!(indices, [1, 5], "paths are not searched, over {len}");
+			let batch = collect_tag_matches(&data, "'work", &options, usize::MAX);
+			assert_eq!(batch.indices, [1], "over {len}");
+			let batch = collect_tag_matches(&data, "'urgent !work", &options, usize::MAX);
+			assert_eq!(batch.indices, [5], "over {len}");
+
+			data.files[1] = FileRow::new("docs/plan.md").with_tags(["urgent", "work"]);
+			let batch = collect_tag_matches(&data, "'urgent !work", &options, usize::MAX);
+			assert_eq!(batch.indices, [5], "every tag is negated, over {len}");
+			let batch = collect_tag_matches(&data, "'work", &options, usize::MAX);
+			assert_eq!(batch.indices, [1], "over {len}");
+
+			let batch = collect_tag_matches(&data, "tag:urgent", &options, usize::MAX);
+			let mut indices = batch.indices.clone();
+			indices.sort_unstable();
+			assert_eq!(indices, [1, 5], "single tags are tokens, over {len}");
+			let batch = collect_tag_matches(&data, "tag:urgent 'wor", &options, usize::MAX);
+			assert_eq!(batch.indices, [1], "over {len}");
+		}
+	}
+}
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-
 use super::file::FileRow;
 use super::iteration::{Fs, OsFs};
+use crate::error::{Error, Result};
 
 /// Dataset key for the files collection.
 pub const FILES_DATASET_KEY: &str = "files";
@@ -93,7 +92,7 @@ impl SearchData {
 	/// Build a [`SearchData`] by walking the filesystem under `root`.
 	///
 	/// # Errors
-	/// Returns an error if the underlying filesystem walker fails while
+	/// Returns [`Error::Io`] if the underlying filesystem walker fails while
 	/// enumerating files.
 	pub fn from_filesystem(root: impl AsRef<Path>) -> Result<Self> {
 		Self::from_filesystem_with(&OsFs, root)
@@ -106,7 +105,7 @@ impl SearchData {
 	///
 	/// # Errors
 	///
-	/// Returns an error if the underlying filesystem walker fails while
+	/// Returns [`Error::Io`] if the underlying filesystem walker fails while
 	/// enumerating files.
 	pub fn from_filesystem_with<F>(fs: &F, root: impl AsRef<Path>) -> Result<Self>
 	where
@@ -115,8 +114,9 @@ impl SearchData {
 		let root = root.as_ref();
 		let mut files = Vec::new();
 
-		for entry in fs.walk(root)? {
-			let relative = entry?;
+		let walk = fs.walk(root).map_err(|err| Error::io(root, err))?;
+		for entry in walk {
+			let relative = entry.map_err(|err| Error::io(root, err))?;
 			let display = relative.to_string_lossy().replace('\\', "/");
 			let file = FileRow::filesystem(display);
 
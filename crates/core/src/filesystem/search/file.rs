@@ -1,13 +1,32 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
 /// Represents a row in the file results table.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FileRow {
 	/// Stable identifier for this file row, derived from the path.
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub id: Option<u64>,
-	/// Filesystem path being represented.
+	/// Filesystem path being represented. Paths that are not valid UTF-8
+	/// hold a lossy rendering; see [`FileRow::os_path`].
 	pub path: String,
+	/// File size in bytes, when it was collected during indexing.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	/// Last modification time, when it was collected during indexing.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub modified: Option<SystemTime>,
+	/// Kind of filesystem entry the row represents.
+	#[serde(default, skip_serializing_if = "EntryKind::is_file")]
+	pub kind: EntryKind,
+	/// Labels searched alongside the path, each scored on its own.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
 	search_text: String,
 	truncate: TruncationStyle,
+	/// Original path when it is not valid UTF-8.
+	#[serde(skip)]
+	original_path: Option<PathBuf>,
 }
 
 impl FileRow {
@@ -23,11 +42,92 @@ impl FileRow {
 		Self::from_parts(path.into(), TruncationStyle::Left)
 	}
 
-	/// Return the searchable text composed of the path and display tags.
+	/// Build a row for a filesystem path that may not be valid UTF-8.
+	///
+	/// [`FileRow::path`] replaces invalid sequences with U+FFFD and uses `/`
+	/// separators, while the original path is kept for opening the file.
+	#[must_use]
+	pub fn from_path(path: &Path) -> Self {
+		let mut row = Self::filesystem(path.to_string_lossy().replace('\\', "/"));
+		if path.to_str().is_none() {
+			// Distinct invalid names can share a lossy rendering, so the id
+			// comes from the exact bytes.
+			row.id = Some(super::stable_hash64_bytes(
+				path.as_os_str().as_encoded_bytes(),
+			));
+			row.original_path = Some(path.to_path_buf());
+		}
+		row
+	}
+
+	/// Path to open the entry with: the original path for rows built from a
+	/// path that is not valid UTF-8, and [`FileRow::path`] otherwise.
+	#[must_use]
+	pub fn os_path(&self) -> &Path {
+		self.original_path
+			.as_deref()
+			.unwrap_or_else(|| Path::new(&self.path))
+	}
+
+	/// Whether [`FileRow::path`] is a lossy rendering of the original path.
+	#[must_use]
+	pub fn has_lossy_path(&self) -> bool {
+		self.original_path.is_some()
+	}
+
+	/// Identity of the row that survives re-filtering and re-indexing: its
+	/// [`FileRow::id`], or the hash of its path for rows without one.
+	#[must_use]
+	pub fn stable_id(&self) -> u64 {
+		self.id.unwrap_or_else(|| super::stable_hash64(&self.path))
+	}
+
+	/// Attach the file size in bytes.
+	#[must_use]
+	pub fn with_size(mut self, size: Option<u64>) -> Self {
+		self.size = size;
+		self
+	}
+
+	/// Attach the last modification time.
+	#[must_use]
+	pub fn with_modified(mut self, modified: Option<SystemTime>) -> Self {
+		self.modified = modified;
+		self
+	}
+
+	/// Attach the kind of filesystem entry the row represents.
+	#[must_use]
+	pub fn with_kind(mut self, kind: EntryKind) -> Self {
+		self.kind = kind;
+		self
+	}
+
+	/// Attach labels that queries match besides the path.
+	#[must_use]
+	pub fn with_tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+		self.tags = tags.into_iter().map(Into::into).collect();
+		self
+	}
+
+	/// Whether the row represents a directory.
+	#[must_use]
+	pub fn is_dir(&self) -> bool {
+		self.kind == EntryKind::Dir
+	}
+
+	/// Return the searchable text of the path; tags are searched separately.
 	pub(crate) fn search_text(&self) -> &str {
 		&self.search_text
 	}
 
+	/// Choose how the path is shortened when it does not fit its column.
+	#[must_use]
+	pub fn with_truncation_style(mut self, truncate: TruncationStyle) -> Self {
+		self.truncate = truncate;
+		self
+	}
+
 	/// Return the truncation style to use when rendering the path.
 	#[must_use]
 	pub fn truncation_style(&self) -> TruncationStyle {
@@ -40,12 +140,40 @@ impl FileRow {
 		Self {
 			id,
 			path,
+			size: None,
+			modified: None,
+			kind: EntryKind::File,
+			tags: Vec::new(),
 			search_text,
 			truncate,
+			original_path: None,
 		}
 	}
 }
 
+/// Kind of filesystem entry represented by a [`FileRow`].
+#[derive(
+	Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+	/// A regular file.
+	#[default]
+	File,
+	/// A directory.
+	Dir,
+	/// A symbolic link that was followed during indexing.
+	Symlink,
+}
+
+impl EntryKind {
+	/// Whether this is a regular file.
+	#[must_use]
+	pub fn is_file(&self) -> bool {
+		*self == Self::File
+	}
+}
+
 /// Controls how a path should be truncated before it is rendered.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum TruncationStyle {
@@ -53,6 +181,9 @@ pub enum TruncationStyle {
 	Left,
 	/// Truncate from the right side.
 	Right,
+	/// Truncate the interior, keeping the start of the path and its final
+	/// component, e.g. `src/very/lo…/file.rs`.
+	Middle,
 }
 
 /// Captures the outcome of a search interaction.
@@ -62,8 +193,56 @@ pub struct SearchOutcome {
 	pub accepted: bool,
 	/// The selected item, if any.
 	pub selection: Option<SearchSelection>,
+	/// Every item confirmed on accept, in dataset order.
+	///
+	/// Holds the explicitly marked rows, or just [`Self::selection`] when no
+	/// rows were marked. Empty when the search was cancelled.
+	pub selections: Vec<SearchSelection>,
+	/// Match score of each of [`Self::selections`], in the same order.
+	///
+	/// `None` for selections the active query did not match, such as marked
+	/// rows that were filtered out after being marked.
+	pub scores: Vec<Option<u16>>,
 	/// The query string that was active.
 	pub query: String,
+	/// Where the preview of the selected file was scrolled to on accept.
+	///
+	/// `None` when the selected file was not being previewed.
+	pub position: Option<PreviewPosition>,
+	/// Name of the expected key that accepted the search, such as `ctrl-v`.
+	///
+	/// `None` when the search was accepted with the regular accept key or
+	/// cancelled.
+	pub accepted_with: Option<String>,
+}
+
+/// Location shown at the top of a file's preview.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreviewPosition {
+	/// 1-based source line at the top of a text preview.
+	pub line: Option<usize>,
+	/// 1-based page shown by a document preview.
+	pub page: Option<u32>,
+}
+
+impl PreviewPosition {
+	/// Position at a 1-based source line.
+	#[must_use]
+	pub fn line(line: usize) -> Self {
+		Self {
+			line: Some(line),
+			page: None,
+		}
+	}
+
+	/// Position at a 1-based page.
+	#[must_use]
+	pub fn page(page: u32) -> Self {
+		Self {
+			line: None,
+			page: Some(page),
+		}
+	}
 }
 
 /// The active selection made by the user when a search ends.
@@ -82,6 +261,19 @@ impl SearchOutcome {
 			None => None,
 		}
 	}
+
+	/// Return every confirmed file, in the order of [`Self::selections`].
+	pub fn selected_files(&self) -> impl Iterator<Item = &FileRow> {
+		self.selections.iter().map(|selection| match selection {
+			SearchSelection::File(file) => file,
+		})
+	}
+
+	/// Return the kind of the selected entry, if any.
+	#[must_use]
+	pub fn selected_kind(&self) -> Option<EntryKind> {
+		self.selected_file().map(|file| file.kind)
+	}
 }
 
 #[cfg(test)]
@@ -100,4 +292,33 @@ mod tests {
 		assert!(row.id.is_some());
 		assert_eq!(row.search_text(), "file.txt");
 	}
+
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_paths_keep_their_original_bytes() {
+		use std::ffi::OsStr;
+		use std::os::unix::ffi::OsStrExt;
+
+		let latin1 = Path::new(OsStr::from_bytes(b"docs/caf\xe9.txt"));
+		let row = FileRow::from_path(latin1);
+		assert_eq!(row.path, "docs/caf\u{FFFD}.txt");
+		assert_eq!(row.search_text(), row.path);
+		assert!(row.has_lossy_path());
+		assert_eq!(row.os_path(), latin1);
+
+		let other = FileRow::from_path(Path::new(OsStr::from_bytes(b"docs/caf\xe8.txt")));
+		assert_eq!(other.path, row.path);
+		assert_ne!(other.id, row.id);
+		assert_eq!(
+			row.id,
+			Some(crate::filesystem::search::stable_hash64_bytes(
+				b"docs/caf\xe9.txt"
+			))
+		);
+
+		let plain = FileRow::from_path(Path::new("docs/cafe.txt"));
+		assert!(!plain.has_lossy_path());
+		assert_eq!(plain.id, FileRow::filesystem("docs/cafe.txt").id);
+		assert_eq!(plain.os_path(), Path::new("docs/cafe.txt"));
+	}
 }
@@ -3,46 +3,249 @@
 //! This feature module contains the fuzzy matching engine, scoring aggregation,
 //! and streaming infrastructure that powers the filesystem search experience.
 
-use std::sync::Arc;
-use std::sync::atomic::AtomicU64;
-
+mod collect;
 mod data;
 mod file;
 mod iteration;
 pub mod runtime;
+mod sort;
 
+pub use collect::{collect_matches, collect_matches_with_options, collect_tag_matches};
 pub use data::{FILES_DATASET_KEY, SearchData};
-pub use file::{FileRow, SearchOutcome, SearchSelection, TruncationStyle};
+pub use file::{
+	EntryKind, FileRow, PreviewPosition, SearchOutcome, SearchSelection, TruncationStyle,
+};
 pub use frz_stream::search::{
-	Dataset, EMPTY_QUERY_BATCH, MATCH_CHUNK_SIZE, MAX_RENDERED_RESULTS, MatchBatch,
-	PREFILTER_ENABLE_THRESHOLD, SearchMarker, SearchResult, SearchStream, SearchView, SearchViewV2,
-	config_for_query,
+	BASENAME_MATCH_BONUS, BoundaryBonus, CancellationToken, CandidateSources, CaseMatching,
+	Dataset, EMPTY_QUERY_BATCH, MATCH_CHUNK_SIZE, MAX_RENDERED_RESULTS, MatchBatch, MatchMode,
+	NarrowingCache, PREFILTER_ENABLE_THRESHOLD, PrefilterCache, PrefilterIndex, Query, QueryFilter,
+	QueryTerm, ScoreCutoff, SearchMarker, SearchOptions, SearchResult, SearchStream, SearchView,
+	SearchViewV2, TermGroup, TermKind, Tiebreak, config_for_options, config_for_query,
+	config_for_query_with_case, fold_diacritics,
 };
 pub use iteration::{Fs, FsIter, OsFs};
+pub use sort::SortKey;
+
+use self::collect::stream_in_order;
+use crate::frecency::Frecency;
 
 /// Streams file matches for the given query back to the UI thread.
 pub fn stream_files(
 	data: &SearchData,
 	query: &str,
 	stream: SearchStream<'_>,
-	latest_query_id: &Arc<AtomicU64>,
+	cancel: impl Into<CancellationToken>,
 ) -> bool {
-	struct FileDataset<'a>(&'a [FileRow]);
+	stream_files_with_options(data, query, &SearchOptions::default(), stream, cancel)
+}
 
-	impl<'a> Dataset for FileDataset<'a> {
-		fn len(&self) -> usize {
-			self.0.len()
-		}
+/// Streams file matches like [`stream_files`] using custom [`SearchOptions`].
+pub fn stream_files_with_options(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+) -> bool {
+	stream_files_sorted(data, query, options, SortKey::Score, stream, cancel)
+}
+
+/// Streams file matches like [`stream_files_with_options`], ordering them by
+/// `sort_key` instead of by score.
+///
+/// Every match is scored before the first [`SearchOptions::max_results`] in
+/// `sort_key` order are sent as one final batch, so the limit keeps the rows
+/// first by that key rather than the best-scoring ones. The rows streamed for
+/// an empty query are ordered by `sort_key` instead of by path.
+pub fn stream_files_sorted(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	sort_key: SortKey,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+) -> bool {
+	let files = FileDataset(data.files.as_slice(), None);
+	stream_in_order(
+		&data.files,
+		query,
+		options,
+		sort_key,
+		stream,
+		|options, stream| {
+			frz_stream::search::stream_dataset_with_options(
+				&files,
+				query,
+				options,
+				stream,
+				cancel,
+				move |index| sort_key.browse_key(&files.0[index]),
+			)
+		},
+	)
+}
+
+/// Streams the files carrying a tag that matches `query`, ignoring their
+/// paths.
+///
+/// Rows are scored by their best matching [tag](FileRow::tags), and the
+/// streamed indices refer to [`SearchData::files`] as for [`stream_files`],
+/// so a match is selected as the file itself. Negations, filters and match
+/// positions see every tag of a row, joined by spaces, so `'urgent !work`
+/// leaves out files tagged both. An empty query lists every file ordered
+/// by path, tagged or not.
+pub fn stream_files_by_tag(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+) -> bool {
+	stream_files_by_tag_sorted(data, query, options, SortKey::Score, stream, cancel)
+}
+
+/// Streams tag matches like [`stream_files_by_tag`], ordering them by
+/// `sort_key` like [`stream_files_sorted`].
+pub(crate) fn stream_files_by_tag_sorted(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	sort_key: SortKey,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+) -> bool {
+	let tags = TagDataset::new(data.files.as_slice());
+	stream_in_order(
+		&data.files,
+		query,
+		options,
+		sort_key,
+		stream,
+		|options, stream| {
+			frz_stream::search::stream_dataset_with_options(
+				&tags,
+				query,
+				options,
+				stream,
+				cancel,
+				move |index| sort_key.browse_key(&tags.files[index]),
+			)
+		},
+	)
+}
 
-		fn key_for(&self, index: usize) -> &str {
-			self.0[index].search_text()
+/// State a search worker carries from one query to the next.
+#[derive(Debug, Clone, Default)]
+pub struct SearchSession {
+	/// Bonuses added to the scores of previously accepted files.
+	pub frecency: Frecency,
+	/// Match sets of recent queries, reused by queries that narrow them.
+	///
+	/// Must be invalidated whenever the searched rows change.
+	pub narrowing: NarrowingCache,
+	/// Rows containing each character recent queries needed, used when no
+	/// earlier match set covers a query.
+	///
+	/// Must be invalidated whenever the searched rows change.
+	pub prefilter: PrefilterCache,
+}
+
+/// Streams file matches like [`stream_files_sorted`], adding each row's
+/// [`Frecency::bonus`] to its match score and narrowing queries that refine
+/// an earlier one to that query's matches. Other queries only score the rows
+/// containing the characters they need, per the session's
+/// [`PrefilterCache`].
+pub fn stream_files_in_session(
+	data: &SearchData,
+	query: &str,
+	options: &SearchOptions,
+	sort_key: SortKey,
+	session: &SearchSession,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+) -> bool {
+	let files = FileDataset(data.files.as_slice(), Some(&session.frecency));
+	let sources = CandidateSources {
+		narrowing: Some(&session.narrowing),
+		masks: Some(&session.prefilter),
+		..CandidateSources::default()
+	};
+	stream_in_order(
+		&data.files,
+		query,
+		options,
+		sort_key,
+		stream,
+		|options, stream| {
+			frz_stream::search::stream_dataset_with_sources(
+				&files,
+				query,
+				options,
+				sources,
+				stream,
+				cancel,
+				move |index| sort_key.browse_key(&files.0[index]),
+			)
+		},
+	)
+}
+
+/// Rows of a [`SearchData`] with optional frecency bonuses, searched by
+/// path and by [tags](FileRow::tags).
+struct FileDataset<'a>(&'a [FileRow], Option<&'a Frecency>);
+
+impl<'a> Dataset for FileDataset<'a> {
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn key_for(&self, index: usize) -> &str {
+		self.0[index].search_text()
+	}
+
+	fn score_bonus(&self, index: usize) -> u16 {
+		match self.1 {
+			Some(frecency) if !frecency.is_empty() => frecency.bonus(&self.0[index].path),
+			_ => 0,
 		}
 	}
 
-	let files = FileDataset(data.files.as_slice());
-	frz_stream::search::stream_dataset(&files, query, stream, latest_query_id, move |index| {
-		files.0[index].path.clone()
-	})
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		let row = &self.0[index];
+		std::iter::once(row.search_text()).chain(row.tags.iter().map(String::as_str))
+	}
+}
+
+/// Rows of a [`SearchData`] searched by their [tags](FileRow::tags) only.
+///
+/// The key of a row joins all of its tags, which negations and most filters
+/// check. Every tag also follows the key as a token of its own, which is
+/// scored on its own and checked by `tag:` filters.
+struct TagDataset<'a> {
+	files: &'a [FileRow],
+	keys: Vec<String>,
+}
+
+impl<'a> TagDataset<'a> {
+	fn new(files: &'a [FileRow]) -> Self {
+		let keys = files.iter().map(|file| file.tags.join(" ")).collect();
+		Self { files, keys }
+	}
+}
+
+impl<'a> Dataset for TagDataset<'a> {
+	fn len(&self) -> usize {
+		self.files.len()
+	}
+
+	fn key_for(&self, index: usize) -> &str {
+		&self.keys[index]
+	}
+
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		let tags = self.files[index].tags.iter().map(String::as_str);
+		std::iter::once(self.key_for(index)).chain(tags)
+	}
 }
 
 /// Compute a stable 64-bit hash for the provided value.
@@ -52,11 +255,17 @@ pub fn stream_files(
 /// processes and platforms.
 #[must_use]
 pub fn stable_hash64(value: &str) -> u64 {
+	stable_hash64_bytes(value.as_bytes())
+}
+
+/// [`stable_hash64`] of raw bytes, for names that are not valid UTF-8.
+#[must_use]
+pub(crate) fn stable_hash64_bytes(bytes: &[u8]) -> u64 {
 	const FNV_OFFSET: u64 = 0xcbf29ce484222325;
 	const FNV_PRIME: u64 = 0x00000100000001b3;
 
 	let mut hash = FNV_OFFSET;
-	for byte in value.as_bytes() {
+	for byte in bytes {
 		hash ^= u64::from(*byte);
 		hash = hash.wrapping_mul(FNV_PRIME);
 	}
@@ -7,7 +7,11 @@ use std::thread;
 
 use frz_stream::StreamAction;
 
-use super::{SearchData, SearchResult, SearchStream, stream_files};
+use super::{
+	SearchData, SearchOptions, SearchResult, SearchSession, SearchStream, SortKey,
+	stream_files_by_tag_sorted, stream_files_in_session,
+};
+use crate::frecency::Frecency;
 
 /// Commands understood by the background search worker.
 #[derive(Debug)]
@@ -21,6 +25,15 @@ pub enum SearchCommand {
 	},
 	/// Merge a fresh index update into the existing in-memory search data.
 	Update(StreamAction<SearchData>),
+	/// Replace the options applied to subsequent queries.
+	Configure(SearchOptions),
+	/// Change how the matches of subsequent queries are ordered.
+	Sort(SortKey),
+	/// Replace the frecency bonuses added to subsequent matches.
+	Frecency(Frecency),
+	/// Match subsequent queries against the rows' tags instead of their
+	/// paths, like [`stream_files_by_tag`](super::stream_files_by_tag), when set.
+	Tags(bool),
 	/// Stop the background worker thread.
 	Shutdown,
 }
@@ -38,19 +51,38 @@ pub fn spawn(
 	let latest_query_id = Arc::new(AtomicU64::new(0));
 	let thread_latest = Arc::clone(&latest_query_id);
 
-	thread::spawn(move || worker_loop(&mut data, command_rx, result_tx, thread_latest));
+	thread::spawn(move || {
+		let mut settings = WorkerSettings::default();
+		worker_loop(
+			&mut data,
+			&mut settings,
+			command_rx,
+			result_tx,
+			thread_latest,
+		);
+	});
 
 	(command_tx, result_rx, latest_query_id)
 }
 
+/// Settings applied to every query the worker runs.
+#[derive(Default)]
+struct WorkerSettings {
+	options: SearchOptions,
+	sort_key: SortKey,
+	session: SearchSession,
+	tags: bool,
+}
+
 fn worker_loop(
 	data: &mut SearchData,
+	settings: &mut WorkerSettings,
 	command_rx: Receiver<SearchCommand>,
 	result_tx: Sender<SearchResult>,
 	latest_query_id: Arc<AtomicU64>,
 ) {
 	while let Ok(command) = command_rx.recv() {
-		if !handle_command(data, &result_tx, &latest_query_id, command) {
+		if !handle_command(data, settings, &result_tx, &latest_query_id, command) {
 			break;
 		}
 	}
@@ -58,19 +90,200 @@ fn worker_loop(
 
 fn handle_command(
 	data: &mut SearchData,
+	settings: &mut WorkerSettings,
 	result_tx: &Sender<SearchResult>,
 	latest_query_id: &Arc<AtomicU64>,
 	command: SearchCommand,
 ) -> bool {
 	match command {
+		SearchCommand::Query { id, query } if settings.tags => {
+			let stream = SearchStream::new(result_tx, id);
+			stream_files_by_tag_sorted(
+				data,
+				&query,
+				&settings.options,
+				settings.sort_key,
+				stream,
+				latest_query_id,
+			)
+		}
 		SearchCommand::Query { id, query } => {
 			let stream = SearchStream::new(result_tx, id);
-			stream_files(data, &query, stream, latest_query_id)
+			stream_files_in_session(
+				data,
+				&query,
+				&settings.options,
+				settings.sort_key,
+				&settings.session,
+				stream,
+				latest_query_id,
+			)
 		}
 		SearchCommand::Update(action) => {
 			action.apply(data);
+			settings.session.narrowing.invalidate();
+			settings.session.prefilter.invalidate();
+			true
+		}
+		SearchCommand::Configure(new_options) => {
+			settings.options = new_options;
+			settings.session.narrowing.invalidate();
+			true
+		}
+		SearchCommand::Sort(sort_key) => {
+			settings.sort_key = sort_key;
+			true
+		}
+		SearchCommand::Frecency(frecency) => {
+			settings.session.frecency = frecency;
+			true
+		}
+		SearchCommand::Tags(tags) => {
+			settings.tags = tags;
 			true
 		}
 		SearchCommand::Shutdown => false,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::filesystem::search::{FileRow, SearchView};
+
+	#[derive(Default)]
+	struct Latest(Vec<usize>);
+
+	impl SearchView for Latest {
+		fn replace_matches(&mut self, indices: Vec<usize>, _scores: Vec<u16>) {
+			self.0 = indices;
+		}
+
+		fn clear_matches(&mut self) {
+			self.0.clear();
+		}
+
+		fn record_completion(&mut self, _complete: bool) {}
+	}
+
+	fn query(
+		tx: &Sender<SearchCommand>,
+		rx: &Receiver<SearchResult>,
+		id: u64,
+		text: &str,
+	) -> Vec<usize> {
+		tx.send(SearchCommand::Query {
+			id,
+			query: text.to_string(),
+		})
+		.unwrap();
+		let mut view = Latest::default();
+		loop {
+			let result = rx.recv_timeout(Duration::from_secs(2)).expect("results");
+			let complete = result.complete;
+			result.dispatch(&mut view);
+			if complete {
+				return view.0;
+			}
+		}
+	}
+
+	#[test]
+	fn index_updates_invalidate_narrowed_queries() {
+		let data = SearchData::new().with_files(vec![
+			FileRow::new("src/lib.rs"),
+			FileRow::new("docs/guide.md"),
+		]);
+		let (tx, rx, latest) = spawn(data);
+
+		latest.store(1, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 1, "'src"), [0]);
+
+		tx.send(SearchCommand::Update(StreamAction::new(
+			|data: &mut SearchData| {
+				data.files.push(FileRow::new("src/main.rs"));
+			},
+		)))
+		.unwrap();
+		latest.store(2, std::sync::atomic::Ordering::Release);
+		let mut matches = query(&tx, &rx, 2, "'src 'rs");
+		matches.sort_unstable();
+		assert_eq!(matches, [0, 2]);
+		tx.send(SearchCommand::Shutdown).unwrap();
+	}
+
+	#[test]
+	fn index_updates_invalidate_prefilter_bitmaps() {
+		let data = SearchData::new().with_files(vec![
+			FileRow::new("src/lib.rs"),
+			FileRow::new("docs/guide.md"),
+		]);
+		let (tx, rx, latest) = spawn(data);
+
+		latest.store(1, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 1, "'src"), [0]);
+
+		// Same number of rows, so only the invalidation reveals the change.
+		tx.send(SearchCommand::Update(StreamAction::new(
+			|data: &mut SearchData| {
+				data.files[1] = FileRow::new("src/main.rs");
+			},
+		)))
+		.unwrap();
+		latest.store(2, std::sync::atomic::Ordering::Release);
+		let mut matches = query(&tx, &rx, 2, "'src");
+		matches.sort_unstable();
+		assert_eq!(matches, [0, 1]);
+		tx.send(SearchCommand::Shutdown).unwrap();
+	}
+
+	#[test]
+	fn tag_mode_matches_tags_instead_of_paths() {
+		let data = SearchData::new().with_files(vec![
+			FileRow::new("src/urgent.rs"),
+			FileRow::new("docs/plan.md").with_tags(["urgent", "work"]),
+			FileRow::new("notes/todo.txt").with_tags(["urgent"]),
+		]);
+		let (tx, rx, latest) = spawn(data);
+
+		latest.store(1, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 1, "'src"), [0]);
+
+		tx.send(SearchCommand::Tags(true)).unwrap();
+		latest.store(2, std::sync::atomic::Ordering::Release);
+		assert!(query(&tx, &rx, 2, "'src").is_empty());
+		latest.store(3, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 3, "'urgent !work"), [2]);
+
+		tx.send(SearchCommand::Tags(false)).unwrap();
+		latest.store(4, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 4, "'src"), [0]);
+		tx.send(SearchCommand::Shutdown).unwrap();
+	}
+
+	#[test]
+	fn sorted_queries_keep_the_first_rows_by_the_sort_key() {
+		let at = |secs| Some(std::time::UNIX_EPOCH + Duration::from_secs(secs));
+		let data = SearchData::new().with_files(vec![
+			FileRow::new("main.rs").with_modified(at(100)),
+			FileRow::new("src/deeply/nested/main_old.rs").with_modified(at(200)),
+			FileRow::new("src/deeply/nested/main_new.rs").with_modified(at(300)),
+		]);
+		let (tx, rx, latest) = spawn(data);
+		tx.send(SearchCommand::Configure(SearchOptions {
+			max_results: 2,
+			..SearchOptions::default()
+		}))
+		.unwrap();
+
+		latest.store(1, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 1, "main")[0], 0);
+
+		tx.send(SearchCommand::Sort(SortKey::ModifiedTime)).unwrap();
+		latest.store(2, std::sync::atomic::Ordering::Release);
+		assert_eq!(query(&tx, &rx, 2, "main"), [2, 1]);
+		tx.send(SearchCommand::Shutdown).unwrap();
+	}
+}
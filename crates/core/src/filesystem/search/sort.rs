@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+use std::time::UNIX_EPOCH;
+
+use super::{FileRow, MatchBatch};
+
+/// Key ordering the rows streamed for an empty query: entries without the
+/// sort's field last, then the field descending, then the path.
+pub(crate) type BrowseKey = (bool, Reverse<u128>, String);
+
+/// Ordering applied to the filtered results before they are displayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortKey {
+	/// Keep the ranking produced by the matcher.
+	#[default]
+	Score,
+	/// Order by path.
+	Name,
+	/// Largest files first; files without a known size sort last.
+	Size,
+	/// Most recently modified first; entries without a known modification
+	/// time sort last.
+	ModifiedTime,
+}
+
+impl SortKey {
+	/// The next mode in the runtime cycle: score, name, then modification
+	/// time. [`SortKey::Size`] cycles back to score.
+	#[must_use]
+	pub fn next(self) -> Self {
+		match self {
+			Self::Score => Self::Name,
+			Self::Name => Self::ModifiedTime,
+			Self::ModifiedTime | Self::Size => Self::Score,
+		}
+	}
+
+	/// Key used to order `file` when the query is empty.
+	///
+	/// `Score` and `Name` both order by path, since every row scores the
+	/// same for an empty query.
+	pub(crate) fn browse_key(self, file: &FileRow) -> BrowseKey {
+		let field = match self {
+			Self::Score | Self::Name => Some(0),
+			Self::Size => file.size.map(u128::from),
+			Self::ModifiedTime => file.modified.map(|modified| {
+				modified
+					.duration_since(UNIX_EPOCH)
+					.map_or(0, |since| since.as_nanos())
+			}),
+		};
+		(
+			field.is_none(),
+			Reverse(field.unwrap_or(0)),
+			file.path.clone(),
+		)
+	}
+
+	/// Reorder `indices` into `files`, keeping `scores` aligned.
+	///
+	/// The sort is stable, so entries that compare equal keep their
+	/// score-based order.
+	pub fn apply(self, files: &[FileRow], indices: &mut Vec<usize>, scores: &mut Vec<u16>) {
+		if self == Self::Score {
+			return;
+		}
+
+		let order = self.order(files, indices);
+		let score = |slot: &usize| scores.get(*slot).copied().unwrap_or(0);
+		let sorted_scores = order.iter().map(score).take(scores.len()).collect();
+		*indices = order.iter().map(|&slot| indices[slot]).collect();
+		*scores = sorted_scores;
+	}
+
+	/// Reorder the rows of `batch` like [`SortKey::apply`], keeping its ids
+	/// and positions aligned.
+	pub(crate) fn apply_to_batch(self, files: &[FileRow], batch: &mut MatchBatch) {
+		if self == Self::Score {
+			return;
+		}
+
+		let order = self.order(files, &batch.indices);
+		fn permute<T: Clone>(values: &mut Vec<T>, order: &[usize]) {
+			*values = order
+				.iter()
+				.filter_map(|&slot| values.get(slot).cloned())
+				.collect();
+		}
+		permute(&mut batch.indices, &order);
+		permute(&mut batch.scores, &order);
+		if let Some(ids) = batch.ids.as_mut() {
+			permute(ids, &order);
+		}
+		if let Some(positions) = batch.positions.as_mut() {
+			permute(positions, &order);
+		}
+	}
+
+	/// Offsets into `indices`, stably sorted by this key.
+	fn order(self, files: &[FileRow], indices: &[usize]) -> Vec<usize> {
+		let file = |slot: &usize| files.get(indices[*slot]);
+		let mut order: Vec<usize> = (0..indices.len()).collect();
+		match self {
+			Self::Score => {}
+			Self::Name => order.sort_by(|a, b| {
+				let path = |slot| file(slot).map(|file| file.path.as_str());
+				path(a).cmp(&path(b))
+			}),
+			Self::Size => order.sort_by_key(|slot| {
+				let size = file(slot).and_then(|file| file.size);
+				(size.is_none(), Reverse(size))
+			}),
+			Self::ModifiedTime => order.sort_by_key(|slot| {
+				let modified = file(slot).and_then(|file| file.modified);
+				(modified.is_none(), Reverse(modified))
+			}),
+		}
+		order
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, UNIX_EPOCH};
+
+	use super::*;
+
+	#[test]
+	fn size_sorts_descending_with_unknown_last() {
+		let files = vec![
+			FileRow::new("small").with_size(Some(10)),
+			FileRow::new("unknown"),
+			FileRow::new("large").with_size(Some(4_096)),
+			FileRow::new("medium").with_size(Some(512)),
+		];
+		let mut indices = vec![0, 1, 2, 3];
+		let mut scores = vec![40, 30, 20, 10];
+
+		SortKey::Size.apply(&files, &mut indices, &mut scores);
+
+		assert_eq!(indices, vec![2, 3, 0, 1]);
+		assert_eq!(scores, vec![20, 10, 40, 30]);
+	}
+
+	#[test]
+	fn score_preserves_matcher_order() {
+		let files = vec![FileRow::new("a").with_size(Some(1)), FileRow::new("b")];
+		let mut indices = vec![1, 0];
+		let mut scores = vec![5, 3];
+
+		SortKey::Score.apply(&files, &mut indices, &mut scores);
+
+		assert_eq!(indices, vec![1, 0]);
+		assert_eq!(scores, vec![5, 3]);
+	}
+
+	#[test]
+	fn modified_time_sorts_newest_first_with_unknown_last() {
+		let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+		let files = vec![
+			FileRow::new("old").with_modified(at(100)),
+			FileRow::new("unknown"),
+			FileRow::new("new").with_modified(at(300)),
+			FileRow::new("middle").with_modified(at(200)),
+		];
+		let mut indices = vec![0, 1, 2, 3];
+		let mut scores = vec![40, 30, 20, 10];
+
+		SortKey::ModifiedTime.apply(&files, &mut indices, &mut scores);
+
+		assert_eq!(indices, vec![2, 3, 0, 1]);
+		assert_eq!(scores, vec![20, 10, 40, 30]);
+	}
+
+	#[test]
+	fn name_sorts_by_path() {
+		let files = vec![FileRow::new("b"), FileRow::new("c"), FileRow::new("a")];
+		let mut indices = vec![0, 1, 2];
+		let mut scores = vec![9, 8, 7];
+
+		SortKey::Name.apply(&files, &mut indices, &mut scores);
+
+		assert_eq!(indices, vec![2, 0, 1]);
+		assert_eq!(scores, vec![7, 9, 8]);
+	}
+
+	#[test]
+	fn browse_keys_put_newest_first() {
+		let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+		let files = [
+			FileRow::new("a").with_modified(at(1)),
+			FileRow::new("b"),
+			FileRow::new("c").with_modified(at(2)),
+		];
+
+		let mut by_mtime: Vec<_> = files.iter().collect();
+		by_mtime.sort_by_key(|file| SortKey::ModifiedTime.browse_key(file));
+		let paths: Vec<_> = by_mtime.iter().map(|file| file.path.as_str()).collect();
+		assert_eq!(paths, vec!["c", "a", "b"]);
+
+		let mut by_name: Vec<_> = files.iter().collect();
+		by_name.sort_by_key(|file| SortKey::Name.browse_key(file));
+		let paths: Vec<_> = by_name.iter().map(|file| file.path.as_str()).collect();
+		assert_eq!(paths, vec!["a", "b", "c"]);
+	}
+}
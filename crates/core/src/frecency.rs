@@ -0,0 +1,385 @@
+//! Frecency ranking shared between `frz` sessions.
+//!
+//! Every accepted file is appended to a [`Journal`] in the data directory
+//! together with the time it was picked. On startup the visits are folded
+//! into a small score bonus per path that fades with a half-life of
+//! [`FRECENCY_HALF_LIFE`], so files picked often and recently rank ahead of
+//! otherwise equal matches.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs;
+use crate::error::Result;
+use crate::journal::Journal;
+
+/// Number of visits kept on disk unless configured otherwise.
+pub const DEFAULT_FRECENCY_SIZE: usize = 1_000;
+
+/// Time after which a visit counts half as much.
+pub const FRECENCY_HALF_LIFE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Upper bound of the bonus added to a match's score.
+///
+/// Kept below [`BASENAME_MATCH_BONUS`] and no larger than the score of a
+/// single matched character, so a frequently picked file only wins among
+/// matches of similar quality.
+///
+/// [`BASENAME_MATCH_BONUS`]: crate::filesystem::search::BASENAME_MATCH_BONUS
+pub const MAX_FRECENCY_BONUS: u16 = 12;
+
+const FRECENCY_FILE: &str = "frecency.jsonl";
+
+/// A single accepted file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Visit {
+	path: PathBuf,
+	/// Seconds since the Unix epoch.
+	at: u64,
+}
+
+/// Accepted files persisted across sessions, oldest first.
+#[derive(Debug, Clone)]
+pub struct FrecencyStore {
+	journal: Journal,
+	limit: usize,
+}
+
+impl FrecencyStore {
+	/// Create a store backed by the journal at `path`.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self {
+			journal: Journal::new(path),
+			limit: DEFAULT_FRECENCY_SIZE,
+		}
+	}
+
+	/// Open the store in the directory resolved by [`app_dirs::get_data_dir`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the data directory cannot be determined.
+	pub fn open_default() -> Result<Self> {
+		Ok(Self::new(app_dirs::get_data_dir()?.join(FRECENCY_FILE)))
+	}
+
+	/// Keep at most `limit` visits on disk.
+	#[must_use]
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit;
+		self
+	}
+
+	/// Record that the file at `path` was accepted now.
+	///
+	/// Relative paths are skipped, since they cannot be matched up with later
+	/// sessions. Once the file holds twice the limit it is atomically
+	/// rewritten with only the newest visits.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the frecency file cannot be read or written.
+	pub fn record(&self, path: impl AsRef<Path>) -> Result<()> {
+		self.record_at(path.as_ref(), SystemTime::now())
+	}
+
+	fn record_at(&self, path: &Path, at: SystemTime) -> Result<()> {
+		if !path.is_absolute() {
+			return Ok(());
+		}
+		let visit = Visit {
+			path: path.to_path_buf(),
+			at: seconds_since_epoch(at),
+		};
+
+		self.journal
+			.append_within(&visit, self.limit, |visits| self.newest(visits))
+	}
+
+	/// The newest `limit` of `visits`.
+	fn newest(&self, mut visits: Vec<Visit>) -> Vec<Visit> {
+		let excess = visits.len().saturating_sub(self.limit);
+		visits.drain(..excess);
+		visits
+	}
+
+	/// Drop the visits of paths that no longer exist.
+	///
+	/// Returns the number of visits removed; the file is only rewritten when
+	/// there is something to remove.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the frecency file cannot be read or written.
+	pub fn prune_missing(&self) -> Result<usize> {
+		let visits: Vec<Visit> = self.journal.load()?;
+		if visits.iter().all(|visit| visit.path.exists()) {
+			return Ok(0);
+		}
+
+		let mut removed = 0;
+		self.journal.compact(|mut visits: Vec<Visit>| {
+			let before = visits.len();
+			visits.retain(|visit| visit.path.exists());
+			removed = before - visits.len();
+			visits
+		})?;
+		Ok(removed)
+	}
+
+	/// Load the bonuses for files under `root`, keyed by their path relative
+	/// to it, from the newest visits up to the limit.
+	///
+	/// Without a root the bonuses are keyed by absolute path, which suits
+	/// rows that are absolute paths themselves.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the frecency file exists but cannot be read.
+	pub fn load(&self, root: Option<&Path>) -> Result<Frecency> {
+		Ok(Frecency::from_visits(
+			self.newest(self.journal.load()?),
+			root,
+			SystemTime::now(),
+		))
+	}
+}
+
+/// Score bonuses for recently and frequently accepted files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frecency {
+	bonuses: HashMap<String, u16>,
+}
+
+impl Frecency {
+	fn from_visits(visits: Vec<Visit>, root: Option<&Path>, now: SystemTime) -> Self {
+		let now = seconds_since_epoch(now);
+		let half_life = FRECENCY_HALF_LIFE.as_secs_f64();
+		let mut weights: HashMap<String, f64> = HashMap::new();
+		for visit in visits {
+			let key = match root {
+				Some(root) => match visit.path.strip_prefix(root) {
+					Ok(relative) => relative.to_string_lossy().into_owned(),
+					Err(_) => continue,
+				},
+				None => visit.path.to_string_lossy().into_owned(),
+			};
+			let age = now.saturating_sub(visit.at) as f64;
+			*weights.entry(key).or_default() += 0.5_f64.powf(age / half_life);
+		}
+
+		// `weight / (weight + 1)` approaches one as visits pile up, so the
+		// bonus grows with use but never passes the maximum.
+		let bonuses = weights
+			.into_iter()
+			.map(|(path, weight)| {
+				let scale = weight / (weight + 1.0);
+				(path, (f64::from(MAX_FRECENCY_BONUS) * scale).round() as u16)
+			})
+			.filter(|(_, bonus)| *bonus > 0)
+			.collect();
+		Self { bonuses }
+	}
+
+	/// Bonus for the row whose path is `path`, or zero for unknown paths.
+	#[must_use]
+	pub fn bonus(&self, path: &str) -> u16 {
+		self.bonuses.get(path).copied().unwrap_or(0)
+	}
+
+	/// Returns true when no path has a bonus.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.bonuses.is_empty()
+	}
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH)
+		.map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use tempfile::tempdir;
+
+	use super::*;
+
+	fn visit(path: &str, days_ago: u64, now: SystemTime) -> Visit {
+		Visit {
+			path: PathBuf::from(path),
+			at: seconds_since_epoch(now) - days_ago * 24 * 60 * 60,
+		}
+	}
+
+	#[test]
+	fn bonuses_fade_with_age_and_stay_bounded() {
+		let now = SystemTime::now();
+		let mut visits = vec![
+			visit("/repo/old.rs", 28, now),
+			visit("/repo/new.rs", 0, now),
+			visit("/elsewhere/new.rs", 0, now),
+		];
+		visits.extend((0..100).map(|_| visit("/repo/hot.rs", 0, now)));
+
+		let frecency = Frecency::from_visits(visits, Some(Path::new("/repo")), now);
+		let (old, new, hot) = (
+			frecency.bonus("old.rs"),
+			frecency.bonus("new.rs"),
+			frecency.bonus("hot.rs"),
+		);
+		assert!(old < new && new < hot, "{old} {new} {hot}");
+		assert_eq!(hot, MAX_FRECENCY_BONUS);
+		assert_eq!(frecency.bonus("/elsewhere/new.rs"), 0);
+		assert_eq!(frecency.bonus("missing.rs"), 0);
+	}
+
+	#[test]
+	fn rootless_bonuses_use_absolute_paths() {
+		let now = SystemTime::now();
+		let frecency = Frecency::from_visits(vec![visit("/repo/a.rs", 0, now)], None, now);
+		assert!(frecency.bonus("/repo/a.rs") > 0);
+		assert_eq!(frecency.bonus("a.rs"), 0);
+	}
+
+	#[test]
+	fn recorded_files_are_loaded_relative_to_the_root() {
+		let dir = tempdir().unwrap();
+		let store = FrecencyStore::new(dir.path().join("frecency.jsonl"));
+		let root = dir.path().join("repo");
+
+		store.record(root.join("src/main.rs")).unwrap();
+		store.record("relative.rs").unwrap();
+
+		let frecency = store.load(Some(&root)).unwrap();
+		assert!(frecency.bonus(&Path::new("src").join("main.rs").to_string_lossy()) > 0);
+		assert_eq!(frecency.bonuses.len(), 1);
+	}
+
+	#[test]
+	fn file_is_compacted_to_the_limit() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("frecency.jsonl");
+		let store = FrecencyStore::new(&path).with_limit(2);
+		let names = || -> Vec<String> {
+			let visits: Vec<Visit> = Journal::new(&path).load().unwrap();
+			visits
+				.iter()
+				.map(|visit| {
+					visit
+						.path
+						.file_name()
+						.unwrap()
+						.to_string_lossy()
+						.into_owned()
+				})
+				.collect()
+		};
+
+		for name in ["a", "b", "c", "d"] {
+			store.record(dir.path().join(name)).unwrap();
+		}
+		assert_eq!(names(), ["a", "b", "c", "d"]);
+		let frecency = store.load(Some(dir.path())).unwrap();
+		assert_eq!((frecency.bonus("b"), frecency.bonus("c")), (0, 6));
+
+		store.record(dir.path().join("e")).unwrap();
+		assert_eq!(names(), ["d", "e"]);
+	}
+
+	#[test]
+	fn pruning_drops_missing_paths() {
+		let dir = tempdir().unwrap();
+		let store = FrecencyStore::new(dir.path().join("frecency.jsonl"));
+		let kept = dir.path().join("kept.rs");
+		fs::write(&kept, "").unwrap();
+
+		store.record(&kept).unwrap();
+		store.record(dir.path().join("gone.rs")).unwrap();
+		store.record(&kept).unwrap();
+
+		assert_eq!(store.prune_missing().unwrap(), 1);
+		assert_eq!(store.prune_missing().unwrap(), 0);
+		let frecency = store.load(Some(dir.path())).unwrap();
+		assert!(frecency.bonus("kept.rs") > 0);
+		assert_eq!(frecency.bonus("gone.rs"), 0);
+	}
+
+	fn ranked(paths: &[&str], query: &str, frecency: &Frecency) -> Vec<String> {
+		use std::sync::atomic::AtomicU64;
+		use std::sync::{Arc, mpsc};
+
+		use crate::filesystem::search::{
+			FileRow, SearchData, SearchOptions, SearchSession, SearchStream, SearchView, SortKey,
+			stream_files_in_session,
+		};
+
+		struct Latest(Vec<usize>);
+
+		impl SearchView for Latest {
+			fn replace_matches(&mut self, indices: Vec<usize>, _scores: Vec<u16>) {
+				self.0 = indices;
+			}
+
+			fn clear_matches(&mut self) {
+				self.0.clear();
+			}
+
+			fn record_completion(&mut self, _complete: bool) {}
+		}
+
+		let data = SearchData::new().with_files(paths.iter().map(|p| FileRow::new(*p)).collect());
+		let (tx, rx) = mpsc::channel();
+		let session = SearchSession {
+			frecency: frecency.clone(),
+			..SearchSession::default()
+		};
+		assert!(stream_files_in_session(
+			&data,
+			query,
+			&SearchOptions::default(),
+			SortKey::Score,
+			&session,
+			SearchStream::new(&tx, 0),
+			&Arc::new(AtomicU64::new(0)),
+		));
+		drop(tx);
+
+		let mut view = Latest(Vec::new());
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view.0
+			.into_iter()
+			.map(|index| paths[index].to_string())
+			.collect()
+	}
+
+	#[test]
+	fn frecent_files_win_ties_but_not_clearly_better_matches() {
+		let now = SystemTime::now();
+		let visits = (0..100).map(|_| visit("/repo/b/main.rs", 0, now)).collect();
+		let frecency = Frecency::from_visits(visits, Some(Path::new("/repo")), now);
+
+		let order = ranked(&["a/main.rs", "b/main.rs"], "main", &frecency);
+		assert_eq!(order, ["b/main.rs", "a/main.rs"]);
+		let order = ranked(&["a/main.rs", "b/main.rs"], "main", &Frecency::default());
+		assert_eq!(order[0], "a/main.rs");
+
+		let visits = (0..100)
+			.map(|_| visit("/repo/src/my_admin_notes.rs", 0, now))
+			.collect();
+		let frecency = Frecency::from_visits(visits, Some(Path::new("/repo")), now);
+		let order = ranked(
+			&["src/my_admin_notes.rs", "src/main.rs"],
+			"main.rs",
+			&frecency,
+		);
+		assert_eq!(order[0], "src/main.rs");
+	}
+}
@@ -0,0 +1,192 @@
+//! Query history shared between `frz` sessions.
+//!
+//! Accepted queries are appended to a [`Journal`] in the data directory and
+//! loaded into the prompt's history on startup. Loading keeps the newest
+//! entries up to the size limit, and the journal is compacted back down to
+//! it once it holds twice as many.
+
+use std::path::PathBuf;
+
+use crate::app_dirs;
+use crate::error::Result;
+use crate::journal::Journal;
+
+/// Number of queries kept on disk unless configured otherwise.
+pub const DEFAULT_HISTORY_SIZE: usize = 200;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// Accepted queries persisted across sessions, oldest first.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+	journal: Journal,
+	limit: usize,
+}
+
+impl HistoryStore {
+	/// Create a store backed by the journal at `path`.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self {
+			journal: Journal::new(path),
+			limit: DEFAULT_HISTORY_SIZE,
+		}
+	}
+
+	/// Open the store in the directory resolved by [`app_dirs::get_data_dir`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the data directory cannot be determined.
+	pub fn open_default() -> Result<Self> {
+		Ok(Self::new(app_dirs::get_data_dir()?.join(HISTORY_FILE)))
+	}
+
+	/// Keep at most `limit` queries on disk.
+	#[must_use]
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit;
+		self
+	}
+
+	/// Maximum number of queries kept on disk.
+	#[must_use]
+	pub fn limit(&self) -> usize {
+		self.limit
+	}
+
+	/// Load the newest queries, oldest first, with consecutive repeats
+	/// collapsed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the history file exists but cannot be read.
+	pub fn load(&self) -> Result<Vec<String>> {
+		Ok(self.compact(self.journal.load()?))
+	}
+
+	/// Record an accepted query.
+	///
+	/// Empty queries and repeats of the newest entry are skipped. Once the
+	/// file holds twice the limit it is atomically rewritten with only the
+	/// newest entries.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the history file cannot be read or written.
+	pub fn append(&self, query: &str) -> Result<()> {
+		let query = query.trim();
+		if query.is_empty() {
+			return Ok(());
+		}
+
+		let entries: Vec<String> = self.journal.load()?;
+		if entries.last().map(String::as_str) == Some(query) {
+			return Ok(());
+		}
+		self.journal
+			.append_within(&query.to_string(), self.limit, |entries| {
+				self.compact(entries)
+			})
+	}
+
+	fn compact(&self, mut entries: Vec<String>) -> Vec<String> {
+		entries.dedup();
+		let excess = entries.len().saturating_sub(self.limit);
+		entries.drain(..excess);
+		entries
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn appends_skip_empty_and_repeated_queries() {
+		let dir = tempdir().unwrap();
+		let store = HistoryStore::new(dir.path().join("history.jsonl"));
+
+		for query in ["src", "src", " ", "lib rs ", "src"] {
+			store.append(query).unwrap();
+		}
+
+		assert_eq!(store.load().unwrap(), vec!["src", "lib rs", "src"]);
+	}
+
+	#[test]
+	fn file_is_compacted_to_the_limit() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("history.jsonl");
+		let store = HistoryStore::new(&path).with_limit(3);
+
+		for query in ["a", "b", "c", "d", "e", "f"] {
+			store.append(query).unwrap();
+		}
+		assert_eq!(store.load().unwrap(), vec!["d", "e", "f"]);
+		let raw: Vec<String> = Journal::new(&path).load().unwrap();
+		assert_eq!(raw.len(), 6, "compaction waits for twice the limit");
+
+		store.append("g").unwrap();
+		assert_eq!(store.load().unwrap(), vec!["e", "f", "g"]);
+		let raw: Vec<String> = Journal::new(&path).load().unwrap();
+		assert_eq!(raw.len(), 3);
+	}
+
+	#[test]
+	fn concurrent_sessions_past_the_limit_keep_every_newer_entry() {
+		use std::sync::{Arc, Barrier};
+		use std::thread;
+
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("history.jsonl");
+		let barrier = Arc::new(Barrier::new(2));
+
+		let handles: Vec<_> = (0..2)
+			.map(|writer| {
+				let store = HistoryStore::new(&path).with_limit(10);
+				let barrier = Arc::clone(&barrier);
+				thread::spawn(move || {
+					barrier.wait();
+					for entry in 0..100 {
+						store.append(&format!("{writer}-{entry}")).unwrap();
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		// Compaction only drops the oldest entries, so each session's
+		// surviving entries must run without gaps up to its newest one.
+		let raw: Vec<String> = Journal::new(&path).load().unwrap();
+		assert!(raw.len() <= 20, "{} entries on disk", raw.len());
+		for writer in 0..2 {
+			let entries: Vec<u32> = raw
+				.iter()
+				.filter_map(|entry| entry.strip_prefix(&format!("{writer}-")))
+				.map(|entry| entry.parse().unwrap())
+				.collect();
+			if let Some(&first) = entries.first() {
+				assert_eq!(entries, (first..100).collect::<Vec<_>>());
+			}
+		}
+		let loaded = HistoryStore::new(&path).with_limit(10).load().unwrap();
+		assert_eq!(loaded.len(), 10);
+	}
+
+	#[test]
+	fn load_collapses_repeats_from_concurrent_sessions() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("history.jsonl");
+		let journal = Journal::new(&path);
+		for query in ["a", "b", "b", "c"] {
+			journal.append(&query).unwrap();
+		}
+
+		let store = HistoryStore::new(&path).with_limit(2);
+		assert_eq!(store.load().unwrap(), vec!["b", "c"]);
+	}
+}
@@ -0,0 +1,250 @@
+//! Append-only journals for state shared between concurrent `frz` sessions.
+//!
+//! Rewriting a whole state file lets two sessions clobber each other's
+//! changes. Journals instead append one JSON record per line and let readers
+//! merge every record on load, so concurrent writers only ever add data.
+//!
+//! A journal is only rewritten to compact it once it has grown well past
+//! its limit. Appends hold a shared lock on a `.lock` file next to the
+//! journal and compaction holds it exclusively, so no record appended by
+//! another session is lost to a rewrite.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::{Error, Result};
+
+/// A newline-delimited JSON journal stored at a fixed path.
+#[derive(Debug, Clone)]
+pub struct Journal {
+	path: PathBuf,
+}
+
+impl Journal {
+	/// Create a journal backed by the file at `path`.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into() }
+	}
+
+	/// Location of the journal on disk.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Append a single record to the journal.
+	///
+	/// Each record is written with one `write` call on a file opened in
+	/// append mode, so records from concurrent writers never interleave.
+	pub fn append<T: Serialize>(&self, record: &T) -> Result<()> {
+		let mut line = serde_json::to_vec(record).map_err(|err| Error::io(&self.path, err))?;
+		line.push(b'\n');
+
+		let _lock = self.lock(false)?;
+		let mut file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.path)
+			.map_err(|err| Error::io(&self.path, err))?;
+		file.write_all(&line)
+			.map_err(|err| Error::io(&self.path, err))
+	}
+
+	/// Append `record`, compacting the journal once it holds more than
+	/// twice `limit` records.
+	///
+	/// The slack keeps compaction rare: most calls only append.
+	pub fn append_within<T, F>(&self, record: &T, limit: usize, compact: F) -> Result<()>
+	where
+		T: Serialize + DeserializeOwned,
+		F: FnOnce(Vec<T>) -> Vec<T>,
+	{
+		self.append(record)?;
+		if self.record_count()? > limit.saturating_mul(2) {
+			self.compact(compact)?;
+		}
+		Ok(())
+	}
+
+	/// Replace the journal's records with the result of `compact`.
+	///
+	/// The records are loaded and written back while holding the journal's
+	/// lock exclusively, so appends from other sessions wait for the rewrite
+	/// instead of being lost to it. The new records go to a sibling temporary
+	/// file that is then renamed over the journal, so readers see either the
+	/// old or the new contents.
+	pub fn compact<T, F>(&self, compact: F) -> Result<()>
+	where
+		T: Serialize + DeserializeOwned,
+		F: FnOnce(Vec<T>) -> Vec<T>,
+	{
+		let _lock = self.lock(true)?;
+		let records = compact(self.load()?);
+
+		let mut contents = Vec::new();
+		for record in &records {
+			serde_json::to_writer(&mut contents, record)
+				.map_err(|err| Error::io(&self.path, err))?;
+			contents.push(b'\n');
+		}
+
+		// Only the holder of the exclusive lock writes the temporary file.
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, contents).map_err(|err| Error::io(&tmp_path, err))?;
+		fs::rename(&tmp_path, &self.path).map_err(|err| Error::io(&self.path, err))
+	}
+
+	/// Number of records in the journal, counting lines that fail to parse.
+	fn record_count(&self) -> Result<usize> {
+		match fs::read(&self.path) {
+			Ok(contents) => Ok(contents
+				.split(|&byte| byte == b'\n')
+				.filter(|line| !line.trim_ascii().is_empty())
+				.count()),
+			Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+			Err(err) => Err(Error::io(&self.path, err)),
+		}
+	}
+
+	/// Lock the `.lock` file next to the journal, shared for appends or
+	/// exclusively for compaction, until the returned file is dropped.
+	fn lock(&self, exclusive: bool) -> Result<File> {
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+		}
+
+		let mut name = OsString::from(self.path.as_os_str());
+		name.push(".lock");
+		let path = PathBuf::from(name);
+		let file = OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.write(true)
+			.open(&path)
+			.map_err(|err| Error::io(&path, err))?;
+		let locked = if exclusive {
+			file.lock()
+		} else {
+			file.lock_shared()
+		};
+		locked.map_err(|err| Error::io(&path, err))?;
+		Ok(file)
+	}
+
+	/// Load every record in the journal in the order it was written.
+	///
+	/// Missing journals load as empty, and lines that fail to parse (for
+	/// example a record torn by a crash mid-write) are skipped.
+	pub fn load<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+		let contents = match fs::read_to_string(&self.path) {
+			Ok(contents) => contents,
+			Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(err) => return Err(Error::io(&self.path, err)),
+		};
+
+		Ok(contents
+			.lines()
+			.filter(|line| !line.trim().is_empty())
+			.filter_map(|line| serde_json::from_str(line).ok())
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Barrier};
+	use std::thread;
+
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn concurrent_writers_do_not_clobber_each_other() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("state").join("history.jsonl");
+		let barrier = Arc::new(Barrier::new(2));
+
+		let handles: Vec<_> = (0..2u32)
+			.map(|writer| {
+				let journal = Journal::new(&path);
+				let barrier = Arc::clone(&barrier);
+				thread::spawn(move || {
+					barrier.wait();
+					for entry in 0..200u32 {
+						journal.append(&(writer, entry)).unwrap();
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		let records: Vec<(u32, u32)> = Journal::new(&path).load().unwrap();
+		assert_eq!(records.len(), 400);
+		for writer in 0..2 {
+			let entries: Vec<u32> = records
+				.iter()
+				.filter(|(owner, _)| *owner == writer)
+				.map(|(_, entry)| *entry)
+				.collect();
+			assert_eq!(entries, (0..200).collect::<Vec<_>>());
+		}
+	}
+
+	#[test]
+	fn skips_torn_records() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("history.jsonl");
+		fs::write(&path, "\"first\"\n\"sec").unwrap();
+
+		let records: Vec<String> = Journal::new(&path).load().unwrap();
+		assert_eq!(records, vec!["first".to_string()]);
+	}
+
+	#[test]
+	fn missing_journal_loads_empty() {
+		let dir = tempdir().unwrap();
+		let records: Vec<String> = Journal::new(dir.path().join("none")).load().unwrap();
+		assert!(records.is_empty());
+	}
+
+	#[test]
+	fn compaction_replaces_all_records() {
+		let dir = tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("history.jsonl"));
+		for entry in ["a", "b", "c"] {
+			journal.append(&entry).unwrap();
+		}
+
+		journal
+			.compact(|records: Vec<String>| records[2..].to_vec())
+			.unwrap();
+		journal.append(&"d").unwrap();
+
+		let records: Vec<String> = journal.load().unwrap();
+		assert_eq!(records, vec!["c".to_string(), "d".to_string()]);
+		assert!(!journal.path().with_extension("tmp").exists());
+	}
+
+	#[test]
+	fn appends_only_compact_past_twice_the_limit() {
+		let dir = tempdir().unwrap();
+		let journal = Journal::new(dir.path().join("history.jsonl"));
+		let newest = |records: Vec<u32>| records[records.len() - 2..].to_vec();
+
+		for entry in 0..4u32 {
+			journal.append_within(&entry, 2, newest).unwrap();
+		}
+		assert_eq!(journal.load::<u32>().unwrap(), [0, 1, 2, 3]);
+
+		journal.append_within(&4u32, 2, newest).unwrap();
+		assert_eq!(journal.load::<u32>().unwrap(), [3, 4]);
+	}
+}
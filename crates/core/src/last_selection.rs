@@ -0,0 +1,157 @@
+//! The file accepted last under each root, shared between `frz` sessions.
+//!
+//! Every accepted file is appended to a [`Journal`] in the data directory
+//! together with its root, and the newest record for a root wins on load, so
+//! the next session under that root can start with the file highlighted.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs;
+use crate::error::Result;
+use crate::journal::Journal;
+
+/// Number of records kept on disk unless configured otherwise.
+pub const DEFAULT_LAST_SELECTION_SIZE: usize = 200;
+
+const LAST_SELECTION_FILE: &str = "last-selection.jsonl";
+
+/// A file accepted under a root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Pick {
+	/// Canonical root the file was found under.
+	root: PathBuf,
+	/// The file's path relative to the root, as shown in the table.
+	path: String,
+}
+
+/// Files accepted last under each root, persisted across sessions.
+#[derive(Debug, Clone)]
+pub struct LastSelectionStore {
+	journal: Journal,
+	limit: usize,
+}
+
+impl LastSelectionStore {
+	/// Create a store backed by the journal at `path`.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self {
+			journal: Journal::new(path),
+			limit: DEFAULT_LAST_SELECTION_SIZE,
+		}
+	}
+
+	/// Open the store in the directory resolved by [`app_dirs::get_data_dir`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the data directory cannot be determined.
+	pub fn open_default() -> Result<Self> {
+		Ok(Self::new(
+			app_dirs::get_data_dir()?.join(LAST_SELECTION_FILE),
+		))
+	}
+
+	/// Keep at most `limit` records on disk.
+	#[must_use]
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit;
+		self
+	}
+
+	/// Record that the row at `path` under `root` was accepted.
+	///
+	/// Once the file holds twice the limit it is atomically rewritten with
+	/// only the newest record of each root, dropping the roots used least
+	/// recently if that is still too many.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the file cannot be read or written.
+	pub fn record(&self, root: &Path, path: &str) -> Result<()> {
+		let pick = Pick {
+			root: canonical(root),
+			path: path.to_owned(),
+		};
+
+		self.journal.append_within(&pick, self.limit, |picks| {
+			let mut newest: Vec<Pick> = Vec::new();
+			for pick in picks.into_iter().rev() {
+				if !newest.iter().any(|kept| kept.root == pick.root) {
+					newest.push(pick);
+				}
+			}
+			newest.truncate(self.limit);
+			newest.reverse();
+			newest
+		})
+	}
+
+	/// The path of the row accepted last under `root`, if any.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the file exists but cannot be read.
+	pub fn load(&self, root: &Path) -> Result<Option<String>> {
+		let root = canonical(root);
+		let picks: Vec<Pick> = self.journal.load()?;
+		Ok(picks
+			.into_iter()
+			.rev()
+			.find(|pick| pick.root == root)
+			.map(|pick| pick.path))
+	}
+}
+
+/// `root` with symlinks and relative components resolved, so every spelling
+/// of a directory shares its record.
+fn canonical(root: &Path) -> PathBuf {
+	root.canonicalize().unwrap_or_else(|_| root.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn the_newest_pick_of_each_root_is_loaded() {
+		let dir = tempdir().unwrap();
+		let store = LastSelectionStore::new(dir.path().join("last-selection.jsonl"));
+		let (one, two) = (dir.path().join("one"), dir.path().join("two"));
+		std::fs::create_dir_all(&one).unwrap();
+
+		assert_eq!(store.load(&one).unwrap(), None);
+		store.record(&one, "src/main.rs").unwrap();
+		store.record(&two, "README.md").unwrap();
+		store.record(&one.join("."), "src/lib.rs").unwrap();
+
+		assert_eq!(store.load(&one).unwrap().as_deref(), Some("src/lib.rs"));
+		assert_eq!(store.load(&two).unwrap().as_deref(), Some("README.md"));
+		assert_eq!(store.load(dir.path()).unwrap(), None);
+	}
+
+	#[test]
+	fn file_is_compacted_to_the_newest_pick_per_root() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("last-selection.jsonl");
+		let store = LastSelectionStore::new(&path).with_limit(2);
+
+		let picks = [
+			("/a", "1"),
+			("/a", "2"),
+			("/b", "3"),
+			("/a", "4"),
+			("/c", "5"),
+		];
+		for (root, file) in picks {
+			store.record(Path::new(root), file).unwrap();
+		}
+
+		let picks: Vec<Pick> = Journal::new(&path).load().unwrap();
+		let files: Vec<_> = picks.iter().map(|pick| pick.path.as_str()).collect();
+		assert_eq!(files, ["4", "5"]);
+	}
+}
@@ -5,8 +5,15 @@
 //! through the module hierarchy.
 
 pub mod app_dirs;
+pub mod error;
 pub mod filesystem;
+pub mod frecency;
+pub mod history;
+pub mod journal;
+pub mod last_selection;
 
+pub use crate::error::{Error, Result};
 pub use crate::filesystem::search::{
-	FileRow, SearchData, SearchOutcome, SearchSelection, TruncationStyle,
+	EntryKind, FileRow, PreviewPosition, SearchData, SearchOutcome, SearchSelection,
+	TruncationStyle,
 };
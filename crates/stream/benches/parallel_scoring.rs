@@ -0,0 +1,64 @@
+//! Query latency scoring on one thread and on a scoped pool.
+//!
+//! Run with `cargo bench -p frz-stream --bench parallel_scoring`. Each query
+//! is matched against synthetic paths until its final results arrive, once
+//! with the default single thread and once per thread count, and the time
+//! per query is reported.
+#![allow(unused_crate_dependencies)]
+
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant};
+
+use frz_stream::search::{Dataset, SearchOptions, SearchStream, stream_dataset_with_options};
+
+const ROWS: usize = 100_000;
+const QUERIES: [&str; 3] = ["md12 rs", "'module_7 !item_9", "pkg src item"];
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+struct Paths(Vec<String>);
+
+impl Dataset for Paths {
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn key_for(&self, index: usize) -> &str {
+		&self.0[index]
+	}
+}
+
+/// Run `query` until its final results arrive and return how long it took.
+fn search(paths: &Paths, query: &str, options: &SearchOptions) -> Duration {
+	let (tx, rx) = mpsc::channel();
+	let latest = Arc::new(AtomicU64::new(1));
+	let start = Instant::now();
+	stream_dataset_with_options(
+		paths,
+		query,
+		options,
+		SearchStream::new(&tx, 1),
+		&latest,
+		|index| index,
+	);
+	drop(tx);
+	let _ = rx.iter().count();
+	start.elapsed()
+}
+
+fn main() {
+	let paths = Paths(
+		(0..ROWS)
+			.map(|i| format!("pkg_{}/src/module_{}/item_{i}.rs", i % 97, i % 13))
+			.collect(),
+	);
+	for query in QUERIES {
+		for threads in THREADS {
+			let options = SearchOptions::default().with_threads(threads);
+			// One warm-up run so the first measurement is not an outlier.
+			search(&paths, query, &options);
+			let elapsed = search(&paths, query, &options);
+			println!("{query:>20} on {threads} thread(s): {elapsed:>9.2?}");
+		}
+	}
+}
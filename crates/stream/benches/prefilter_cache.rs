@@ -0,0 +1,88 @@
+//! Keystroke latency with and without a [`PrefilterCache`].
+//!
+//! Run with `cargo bench -p frz-stream --bench prefilter_cache`. A query is
+//! typed one character at a time over a million synthetic paths and then
+//! deleted again, once scanning every row per keystroke and once through a
+//! warm session cache, and the time per keystroke is reported.
+#![allow(unused_crate_dependencies)]
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use frz_stream::search::{
+	CandidateSources, CancellationToken, Dataset, PrefilterCache, SearchOptions, SearchStream,
+	stream_dataset_with_sources,
+};
+
+const ROWS: usize = 1_000_000;
+const TYPED: &str = "'component_42/ Mod";
+
+struct Paths(Vec<String>);
+
+impl Dataset for Paths {
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn key_for(&self, index: usize) -> &str {
+		&self.0[index]
+	}
+}
+
+/// Run `query` until its final results arrive and return how long it took.
+fn search(paths: &Paths, query: &str, sources: CandidateSources<'_>) -> Duration {
+	let (tx, rx) = mpsc::channel();
+	let start = Instant::now();
+	stream_dataset_with_sources(
+		paths,
+		query,
+		&SearchOptions::default(),
+		sources,
+		SearchStream::new(&tx, 1),
+		CancellationToken::new(),
+		|index| index,
+	);
+	drop(tx);
+	// Refinement passes run on their own thread and hold their own sender.
+	let _ = rx.iter().count();
+	start.elapsed()
+}
+
+fn measure(label: &str, paths: &Paths, queries: &[String], sources: CandidateSources<'_>) {
+	let total: Duration = queries
+		.iter()
+		.map(|query| search(paths, query, sources))
+		.sum();
+	println!(
+		"{label:>8}: {:>9.2?}/keystroke over {} keystrokes",
+		total / queries.len() as u32,
+		queries.len()
+	);
+}
+
+fn main() {
+	let paths = Paths(
+		(0..ROWS)
+			.map(|i| format!("src/package_{}/component_{}/Mod_{i}.rs", i % 311, i % 97))
+			.collect(),
+	);
+	let mut queries = Vec::new();
+	let mut typed = String::new();
+	for ch in TYPED.chars() {
+		typed.push(ch);
+		queries.push(typed.clone());
+	}
+	while typed.pop().is_some() && !typed.is_empty() {
+		queries.push(typed.clone());
+	}
+
+	measure("uncached", &paths, &queries, CandidateSources::default());
+
+	let cache = PrefilterCache::new();
+	let sources = CandidateSources {
+		masks: Some(&cache),
+		..CandidateSources::default()
+	};
+	measure("cold", &paths, &queries, sources);
+	measure("warm", &paths, &queries, sources);
+}
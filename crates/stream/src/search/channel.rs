@@ -1,5 +1,9 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
+use super::matcher::should_abort;
 use crate::{DataStream, StreamEnvelope, ViewAction, ViewTarget};
 
 /// Batch of search matches emitted by a producer.
@@ -11,6 +15,14 @@ pub struct MatchBatch {
 	pub ids: Option<Vec<u64>>,
 	/// Relevance scores for each match.
 	pub scores: Vec<u16>,
+	/// Positions of the matched characters in each row's key, computed with
+	/// the same [`Query`] and configuration that scored the row.
+	///
+	/// Only scored matches carry positions; consumers should compute
+	/// highlights themselves when this is `None`.
+	///
+	/// [`Query`]: super::Query
+	pub positions: Option<Vec<Vec<usize>>>,
 }
 
 impl MatchBatch {
@@ -40,6 +52,14 @@ pub trait SearchView {
 	/// pending.
 	fn record_completion(&mut self, complete: bool);
 
+	/// Observe how long the query took, from the creation of its
+	/// [`SearchStream`] until the final batch was sent.
+	///
+	/// Called right before [`record_completion`](Self::record_completion)
+	/// with `complete` set, so the time is wall-clock scoring time and leaves
+	/// out how long the consumer takes to apply and render the results.
+	fn record_elapsed(&mut self, _elapsed: Duration) {}
+
 	/// Attempt to upgrade to the V2 search view if supported.
 	fn as_v2(&mut self) -> Option<&mut dyn SearchViewV2> {
 		None
@@ -81,9 +101,115 @@ fn apply_batch(view: &mut dyn SearchView, batch: MatchBatch) {
 	}
 }
 
+/// Shared signal that stops a search stream from outside the matcher.
+///
+/// A token is tripped with [`CancellationToken::cancel`], which makes the
+/// streaming functions return `false`. It can also watch a latest-query-id
+/// counter, as built with [`CancellationToken::for_query`] or converted from
+/// `&Arc<AtomicU64>`: once the counter moves past the query's id, the query
+/// counts as superseded and streaming ends quietly, returning `true`. Clones
+/// share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+	cancelled: Arc<AtomicBool>,
+	query: Option<QueryWatch>,
+}
+
+/// Latest-query-id counter watched by a [`CancellationToken`].
+#[derive(Debug, Clone)]
+struct QueryWatch {
+	latest: Arc<AtomicU64>,
+	/// Id of the watched query; `None` until the token is attached to a
+	/// stream, whose id is used.
+	id: Option<u64>,
+}
+
+impl CancellationToken {
+	/// Create a token that has not been cancelled.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a token for query `id` that counts as cancelled once `latest`
+	/// holds a different id, i.e. a newer query has started.
+	#[must_use]
+	pub fn for_query(latest: &Arc<AtomicU64>, id: u64) -> Self {
+		Self {
+			cancelled: Arc::default(),
+			query: Some(QueryWatch {
+				latest: Arc::clone(latest),
+				id: Some(id),
+			}),
+		}
+	}
+
+	/// Trip the token, stopping every stream that holds a clone of it.
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Release);
+	}
+
+	/// Whether [`CancellationToken::cancel`] has been called or the watched
+	/// query has been superseded.
+	#[must_use]
+	pub fn is_cancelled(&self) -> bool {
+		self.is_tripped() || self.is_superseded()
+	}
+
+	/// Whether a newer query has replaced the watched one.
+	#[must_use]
+	pub fn is_superseded(&self) -> bool {
+		self.query
+			.as_ref()
+			.is_some_and(|query| query.id.is_some_and(|id| should_abort(id, &query.latest)))
+	}
+
+	/// Whether [`CancellationToken::cancel`] has been called.
+	pub(crate) fn is_tripped(&self) -> bool {
+		self.cancelled.load(Ordering::Acquire)
+	}
+
+	/// Watch the query `id` unless the token already names one.
+	pub(crate) fn for_stream(mut self, id: u64) -> Self {
+		if let Some(query) = self.query.as_mut() {
+			query.id.get_or_insert(id);
+		}
+		self
+	}
+}
+
+impl From<&CancellationToken> for CancellationToken {
+	fn from(token: &CancellationToken) -> Self {
+		token.clone()
+	}
+}
+
+/// Watch the counter for a query newer than the stream the token is used
+/// with.
+impl From<&Arc<AtomicU64>> for CancellationToken {
+	fn from(latest: &Arc<AtomicU64>) -> Self {
+		Self {
+			cancelled: Arc::default(),
+			query: Some(QueryWatch {
+				latest: Arc::clone(latest),
+				id: None,
+			}),
+		}
+	}
+}
+
+impl From<(&Arc<AtomicU64>, u64)> for CancellationToken {
+	fn from((latest, id): (&Arc<AtomicU64>, u64)) -> Self {
+		Self::for_query(latest, id)
+	}
+}
+
 /// Handle used to stream search results back to the UI.
 pub struct SearchStream<'a> {
 	inner: DataStream<'a, SearchMarker, SearchAction>,
+	cancel: Option<CancellationToken>,
+	/// When the stream was created, which completing batches are timed from.
+	started: Instant,
 }
 
 impl<'a> SearchStream<'a> {
@@ -92,9 +218,45 @@ impl<'a> SearchStream<'a> {
 	pub fn new(tx: &'a Sender<SearchResult>, id: u64) -> Self {
 		Self {
 			inner: DataStream::new(tx, id, SearchMarker),
+			cancel: None,
+			started: Instant::now(),
+		}
+	}
+
+	/// Create a stream that stops matching once `token` is cancelled.
+	///
+	/// The token is polled between [`MATCH_CHUNK_SIZE`] chunks, and nothing
+	/// more is sent after it trips. A token converted from a latest-query-id
+	/// counter watches `id`.
+	///
+	/// [`MATCH_CHUNK_SIZE`]: super::MATCH_CHUNK_SIZE
+	#[must_use]
+	pub fn with_cancel(
+		tx: &'a Sender<SearchResult>,
+		id: u64,
+		token: impl Into<CancellationToken>,
+	) -> Self {
+		Self {
+			inner: DataStream::new(tx, id, SearchMarker),
+			cancel: Some(token.into().for_stream(id)),
+			started: Instant::now(),
 		}
 	}
 
+	/// Cancellation token attached with [`SearchStream::with_cancel`].
+	#[must_use]
+	pub fn cancellation(&self) -> Option<&CancellationToken> {
+		self.cancel.as_ref()
+	}
+
+	/// Whether the stream's cancellation token has been tripped.
+	#[must_use]
+	pub fn is_cancelled(&self) -> bool {
+		self.cancel
+			.as_ref()
+			.is_some_and(CancellationToken::is_cancelled)
+	}
+
 	/// Identifier for the active query.
 	#[must_use]
 	pub fn id(&self) -> u64 {
@@ -111,16 +273,24 @@ impl<'a> SearchStream<'a> {
 			indices,
 			ids: None,
 			scores,
+			positions: None,
 		};
 		self.send_batch(batch, complete)
 	}
 
 	/// Send a batch of search results to the UI thread using the new
 	/// identifier-aware path when available.
+	///
+	/// A completing batch also reports the time since the stream was created
+	/// through [`SearchView::record_elapsed`].
 	pub fn send_batch(&self, batch: MatchBatch, complete: bool) -> bool {
+		let elapsed = complete.then(|| self.started.elapsed());
 		self.send_with(
 			move |view| {
 				apply_batch(view, batch);
+				if let Some(elapsed) = elapsed {
+					view.record_elapsed(elapsed);
+				}
 				view.record_completion(complete);
 			},
 			complete,
@@ -147,6 +317,8 @@ impl<'a> Clone for SearchStream<'a> {
 	fn clone(&self) -> Self {
 		Self {
 			inner: self.inner.clone(),
+			cancel: self.cancel.clone(),
+			started: self.started,
 		}
 	}
 }
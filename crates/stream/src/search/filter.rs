@@ -0,0 +1,192 @@
+//! Structured filters embedded in queries.
+//!
+//! A filter restricts candidates before any scoring happens and never
+//! contributes to the score or the highlights. Only keys listed in
+//! [`FILTER_KEYS`] are recognised; any other `key:value` token is matched as
+//! ordinary query text.
+//!
+//! - `ext:rs` keeps candidates whose file name has the `rs` extension.
+//! - `ext:toml,md` keeps candidates with any of the listed extensions.
+//! - `tag:urgent` keeps candidates tagged `urgent`, ignoring case. The tags
+//!   of a candidate are the [tokens](crate::search::Dataset::tokens_for)
+//!   after its key, such as the tags of file rows.
+//! - `src/`, or any other token ending in `/`, keeps candidates under that
+//!   directory of the index root.
+
+/// Builds a filter from the value after `key:`, or rejects the value.
+type ParseValue = fn(&str) -> Option<QueryFilter>;
+
+/// Recognised filter keys and the parser building a filter from each value.
+///
+/// New filters are added by extending this table.
+const FILTER_KEYS: &[(&str, ParseValue)] = &[("ext", parse_extensions), ("tag", parse_tag)];
+
+/// A structured filter parsed from a `key:value` query token.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum QueryFilter {
+	/// Keep candidates whose final path component has one of these
+	/// extensions, compared ASCII case-insensitively and stored without a
+	/// leading dot.
+	Extension(Vec<String>),
+	/// Keep candidates whose path starts with this prefix, compared
+	/// case-sensitively. Stored relative to the index root, without a
+	/// leading `./`.
+	PathPrefix(String),
+	/// Keep candidates with this tag, compared case-insensitively.
+	Tag(String),
+}
+
+impl QueryFilter {
+	/// Parse a `key:value` token whose key is a known filter.
+	///
+	/// Returns `None` for unknown keys and for values the filter rejects, so
+	/// the token can be treated as plain text instead.
+	#[must_use]
+	pub fn parse(token: &str) -> Option<Self> {
+		let (key, value) = token.split_once(':')?;
+		FILTER_KEYS
+			.iter()
+			.find(|(name, _)| *name == key)
+			.and_then(|(_, parse)| parse(value))
+	}
+
+	/// Parse a directory token such as `src/` or `./src/` into a
+	/// [`QueryFilter::PathPrefix`].
+	///
+	/// Only tokens ending in `/` qualify, and tokens starting with a match
+	/// operator (`'`, `!` or `^`) keep their operator meaning. A lone `/` is
+	/// not a prefix, since indexed paths are relative to the root.
+	#[must_use]
+	pub fn path_prefix(token: &str) -> Option<Self> {
+		if !token.ends_with('/') || token == "/" || token.starts_with(['\'', '!', '^']) {
+			return None;
+		}
+		let mut prefix = token;
+		while let Some(rest) = prefix.strip_prefix("./") {
+			prefix = rest.trim_start_matches('/');
+		}
+		Some(Self::PathPrefix(prefix.to_string()))
+	}
+
+	/// Whether `haystack` passes the filter.
+	///
+	/// A bare haystack has no tags, so it never passes a
+	/// [`QueryFilter::Tag`]; see [`QueryFilter::accepts_entry`].
+	#[must_use]
+	pub fn accepts(&self, haystack: &str) -> bool {
+		self.accepts_entry(haystack, &[])
+	}
+
+	/// Whether the candidate with `key` and the `tags` after it passes the
+	/// filter. Only [`QueryFilter::Tag`] looks at the tags.
+	#[must_use]
+	pub fn accepts_entry(&self, key: &str, tags: &[&str]) -> bool {
+		match self {
+			Self::Extension(extensions) => {
+				let name = key.trim_end_matches('/');
+				let name = name.rsplit('/').next().unwrap_or(name);
+				name.rsplit_once('.')
+					.filter(|(stem, _)| !stem.is_empty())
+					.is_some_and(|(_, extension)| {
+						extensions
+							.iter()
+							.any(|wanted| wanted.eq_ignore_ascii_case(extension))
+					})
+			}
+			Self::PathPrefix(prefix) => key.starts_with(prefix.as_str()),
+			Self::Tag(_) => tags.iter().any(|tag| self.matches_tag(tag)),
+		}
+	}
+
+	/// Whether this is a [`QueryFilter::Tag`] for `tag`.
+	#[must_use]
+	pub fn matches_tag(&self, tag: &str) -> bool {
+		let Self::Tag(wanted) = self else {
+			return false;
+		};
+		wanted
+			.chars()
+			.flat_map(char::to_lowercase)
+			.eq(tag.chars().flat_map(char::to_lowercase))
+	}
+}
+
+fn parse_extensions(value: &str) -> Option<QueryFilter> {
+	let extensions: Vec<String> = value
+		.split(',')
+		.map(|extension| extension.trim_start_matches('.'))
+		.filter(|extension| !extension.is_empty())
+		.map(str::to_string)
+		.collect();
+	(!extensions.is_empty()).then_some(QueryFilter::Extension(extensions))
+}
+
+fn parse_tag(value: &str) -> Option<QueryFilter> {
+	(!value.is_empty()).then(|| QueryFilter::Tag(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn extension_filters_parse_comma_separated_lists() {
+		assert_eq!(
+			QueryFilter::parse("ext:toml,.md"),
+			Some(QueryFilter::Extension(vec!["toml".into(), "md".into()]))
+		);
+		assert_eq!(QueryFilter::parse("ext:"), None);
+		assert_eq!(QueryFilter::parse("ext:,"), None);
+		assert_eq!(QueryFilter::parse("lang:rs"), None);
+		assert_eq!(QueryFilter::parse("plain"), None);
+	}
+
+	#[test]
+	fn extension_filters_check_the_final_component() {
+		let filter = QueryFilter::parse("ext:rs,md").expect("filter");
+		assert!(filter.accepts("src/main.rs"));
+		assert!(filter.accepts("README.MD"));
+		assert!(!filter.accepts("src.rs/mod"));
+		assert!(!filter.accepts("docs/.md"));
+		assert!(!filter.accepts("Cargo.toml"));
+		assert!(!filter.accepts("rs"));
+	}
+
+	#[test]
+	fn directory_tokens_become_root_relative_path_prefixes() {
+		let prefix = |token| match QueryFilter::path_prefix(token) {
+			Some(QueryFilter::PathPrefix(prefix)) => Some(prefix),
+			_ => None,
+		};
+		assert_eq!(prefix("src/").as_deref(), Some("src/"));
+		assert_eq!(prefix("./src/").as_deref(), Some("src/"));
+		assert_eq!(prefix("././/src/bin/").as_deref(), Some("src/bin/"));
+		assert_eq!(prefix("./").as_deref(), Some(""));
+		assert_eq!(prefix("/"), None);
+		assert_eq!(prefix("src"), None);
+		assert_eq!(prefix("'src/"), None);
+		assert_eq!(prefix("!target/"), None);
+
+		let filter = QueryFilter::path_prefix("./src/").expect("prefix");
+		assert!(filter.accepts("src/main.rs"));
+		assert!(filter.accepts("src/"));
+		assert!(!filter.accepts("Src/main.rs"));
+		assert!(!filter.accepts("tests/src/main.rs"));
+		assert!(!filter.accepts("src"));
+	}
+
+	#[test]
+	fn tag_filters_match_whole_tags_ignoring_case() {
+		let filter = QueryFilter::parse("tag:Urgent").expect("filter");
+		assert_eq!(filter, QueryFilter::Tag("Urgent".into()));
+		assert_eq!(QueryFilter::parse("tag:"), None);
+
+		assert!(filter.accepts_entry("notes/todo.txt", &["work", "URGENT"]));
+		assert!(!filter.accepts_entry("notes/todo.txt", &["urgently"]));
+		assert!(!filter.accepts_entry("notes/urgent", &[]));
+		assert!(!filter.accepts("urgent"), "a bare key has no tags");
+		assert!(filter.matches_tag("urgent"));
+		assert!(!QueryFilter::parse("ext:md").unwrap().matches_tag("md"));
+	}
+}
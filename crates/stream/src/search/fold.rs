@@ -0,0 +1,253 @@
+//! Diacritic folding for accent-insensitive matching.
+//!
+//! Folding replaces accented Latin letters with their base letters and drops
+//! combining marks, so `resume` matches `résumé` whether the key is stored
+//! precomposed or decomposed. Match positions found in a folded key are
+//! mapped back to the characters of the original key for highlighting.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::matcher::Dataset;
+
+/// Fold the diacritics of `text`, borrowing it when nothing changes.
+#[must_use]
+pub fn fold_diacritics(text: &str) -> Cow<'_, str> {
+	if text.is_ascii() || !text.chars().any(|ch| fold_char(ch).is_some()) {
+		return Cow::Borrowed(text);
+	}
+	let mut folded = String::with_capacity(text.len());
+	for ch in text.chars() {
+		match fold_char(ch) {
+			Some(replacement) => folded.push_str(replacement),
+			None => folded.push(ch),
+		}
+	}
+	Cow::Owned(folded)
+}
+
+/// Map character positions in the folded form of `original` back to
+/// character positions in `original`.
+///
+/// Positions inside a letter that folded to several, such as the `s`s of
+/// `ß`, map to that letter once, and combining marks that were dropped are
+/// included with the letter they decorate.
+pub(crate) fn unfold_positions(original: &str, positions: Vec<usize>) -> Vec<usize> {
+	// Original characters covered by each folded character.
+	let mut origins: Vec<Range<usize>> = Vec::with_capacity(original.len());
+	for (index, ch) in original.chars().enumerate() {
+		match fold_char(ch).map(|replacement| replacement.chars().count()) {
+			// A dropped mark joins the characters folded from the letter
+			// before it.
+			Some(0) => {
+				let start = origins.last().map(|range| range.start);
+				for range in origins.iter_mut().rev() {
+					if Some(range.start) != start {
+						break;
+					}
+					range.end = index + 1;
+				}
+			}
+			width => origins.extend(std::iter::repeat_n(index..index + 1, width.unwrap_or(1))),
+		}
+	}
+	let mut unfolded: Vec<usize> = positions
+		.into_iter()
+		.filter_map(|position| origins.get(position).cloned())
+		.flatten()
+		.collect();
+	unfolded.dedup();
+	unfolded
+}
+
+/// Replacement for a character that folds, or `None` if it is kept.
+fn fold_char(ch: char) -> Option<&'static str> {
+	let folded = match ch {
+		// Combining diacritical marks, as left by NFD-normalized text.
+		'\u{0300}'..='\u{036F}' => "",
+		'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+		'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+		'Æ' => "AE",
+		'æ' => "ae",
+		'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+		'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+		'Ð' | 'Ď' | 'Đ' => "D",
+		'ð' | 'ď' | 'đ' => "d",
+		'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+		'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+		'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+		'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+		'Ĥ' | 'Ħ' => "H",
+		'ĥ' | 'ħ' => "h",
+		'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+		'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+		'Ĵ' => "J",
+		'ĵ' => "j",
+		'Ķ' => "K",
+		'ķ' => "k",
+		'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+		'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+		'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+		'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+		'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+		'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+		'Œ' => "OE",
+		'œ' => "oe",
+		'Ŕ' | 'Ŗ' | 'Ř' => "R",
+		'ŕ' | 'ŗ' | 'ř' => "r",
+		'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+		'ś' | 'ŝ' | 'ş' | 'š' => "s",
+		'ß' => "ss",
+		'Ţ' | 'Ť' | 'Ŧ' => "T",
+		'ţ' | 'ť' | 'ŧ' => "t",
+		'Þ' => "Th",
+		'þ' => "th",
+		'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+		'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+		'Ŵ' => "W",
+		'ŵ' => "w",
+		'Ý' | 'Ŷ' | 'Ÿ' => "Y",
+		'ý' | 'ÿ' | 'ŷ' => "y",
+		'Ź' | 'Ż' | 'Ž' => "Z",
+		'ź' | 'ż' | 'ž' => "z",
+		_ => return None,
+	};
+	Some(folded)
+}
+
+/// Dataset whose keys are folded when folding is enabled.
+///
+/// Keys and the tokens after them are folded once when the pass starts;
+/// only keys and tokens that change are stored, so ASCII datasets cost a
+/// scan and no allocations.
+pub(crate) struct FoldedDataset<D> {
+	inner: D,
+	/// Folded key by index, `None` where folding leaves the key unchanged.
+	/// Empty when folding is disabled.
+	folded: Vec<Option<Box<str>>>,
+	/// Every token after the key of the entries where folding changes any
+	/// of them, by ascending index.
+	folded_tokens: Vec<(usize, Box<str>)>,
+}
+
+impl<D: Dataset> FoldedDataset<D> {
+	pub(crate) fn new(inner: D, enabled: bool) -> Self {
+		let mut folded_tokens = Vec::new();
+		let folded = if enabled {
+			(0..inner.len())
+				.map(|index| {
+					let tokens: Vec<Cow<'_, str>> = inner
+						.tokens_for(index)
+						.skip(1)
+						.map(fold_diacritics)
+						.collect();
+					if tokens.iter().any(|token| matches!(token, Cow::Owned(_))) {
+						folded_tokens.extend(
+							tokens
+								.into_iter()
+								.map(|token| (index, token.into_owned().into_boxed_str())),
+						);
+					}
+					match fold_diacritics(inner.key_for(index)) {
+						Cow::Borrowed(_) => None,
+						Cow::Owned(folded) => Some(folded.into_boxed_str()),
+					}
+				})
+				.collect()
+		} else {
+			Vec::new()
+		};
+		Self {
+			inner,
+			folded,
+			folded_tokens,
+		}
+	}
+
+	/// Tokens of the entry at `index` as the dataset provides them, unfolded.
+	pub(crate) fn raw_tokens(&self, index: usize) -> impl Iterator<Item = &str> {
+		self.inner.tokens_for(index)
+	}
+
+	/// The unfolded key at `index` if folding changed it.
+	pub(crate) fn original(&self, index: usize) -> Option<&str> {
+		self.folded
+			.get(index)?
+			.as_ref()
+			.map(|_| self.inner.key_for(index))
+	}
+
+	/// Unfolded keys of every entry that folding changed, by index.
+	pub(crate) fn originals(&self) -> Vec<Option<String>> {
+		(0..self.folded.len())
+			.map(|index| self.original(index).map(str::to_owned))
+			.collect()
+	}
+}
+
+impl<D: Dataset> Dataset for FoldedDataset<D> {
+	fn len(&self) -> usize {
+		self.inner.len()
+	}
+
+	fn key_for(&self, index: usize) -> &str {
+		match self.folded.get(index) {
+			Some(Some(folded)) => folded,
+			_ => self.inner.key_for(index),
+		}
+	}
+
+	fn score_bonus(&self, index: usize) -> u16 {
+		self.inner.score_bonus(index)
+	}
+
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		let start = self
+			.folded_tokens
+			.partition_point(|(entry, _)| *entry < index);
+		let folded = self.folded_tokens[start..]
+			.iter()
+			.take_while(move |(entry, _)| *entry == index)
+			.map(|(_, token)| &**token);
+		let changed = self
+			.folded_tokens
+			.get(start)
+			.is_some_and(|(entry, _)| *entry == index);
+		let mut inner = self.inner.tokens_for(index);
+		let key = match self.folded.get(index) {
+			Some(Some(folded)) => inner.next().map(|_| &**folded),
+			_ => inner.next(),
+		};
+		let unchanged = inner.take(if changed { 0 } else { usize::MAX });
+		key.into_iter().chain(folded).chain(unchanged)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accented_letters_and_combining_marks_fold() {
+		assert_eq!(fold_diacritics("résumé.pdf"), "resume.pdf");
+		assert_eq!(fold_diacritics("re\u{301}sume\u{301}.pdf"), "resume.pdf");
+		assert_eq!(fold_diacritics("Straße/Œuvre"), "Strasse/OEuvre");
+		assert!(matches!(fold_diacritics("src/main.rs"), Cow::Borrowed(_)));
+		assert!(matches!(fold_diacritics("日本/notes"), Cow::Borrowed(_)));
+	}
+
+	#[test]
+	fn positions_map_back_to_original_characters() {
+		// "résumé" folds to "resume": positions are one to one.
+		assert_eq!(
+			unfold_positions("résumé", vec![0, 1, 2, 3, 4, 5]),
+			[0, 1, 2, 3, 4, 5]
+		);
+		// Combining marks disappear from the folded key, shifting later
+		// characters, and are highlighted with their letter.
+		let decomposed = "re\u{301}sume\u{301}";
+		assert_eq!(unfold_positions(decomposed, vec![1, 2, 5]), [1, 2, 3, 6, 7]);
+		// Both `s`s of `ß` point at the `ß`.
+		assert_eq!(unfold_positions("aßb", vec![1, 2, 3]), [1, 2]);
+	}
+}
@@ -0,0 +1,315 @@
+//! Per-character row bitmaps reused from one keystroke to the next.
+//!
+//! [`PrefilterCache`] answers the same question as
+//! [`PrefilterIndex`](super::PrefilterIndex), which rows could match a
+//! query, without indexing the dataset up front. The first query needing a
+//! character, or a pair of adjacent characters, scans the keys once for it,
+//! and the resulting bitmap is reused by every later query. Combined bitmaps
+//! of recent queries are kept too, so typing another character only
+//! intersects (or, for fuzzy terms, extends) the previous query's bitmap,
+//! and deleting one finds the earlier bitmap again.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use super::fold::{FoldedDataset, fold_diacritics};
+use super::matcher::Dataset;
+use super::prefilter::{Constraint, normalize};
+use super::query::Query;
+
+/// Number of combined bitmaps of recent queries kept by a
+/// [`PrefilterCache`].
+const RECENT_MASKS: usize = 16;
+
+/// Bitmaps of the rows containing each character and character pair that a
+/// query needed so far.
+///
+/// The cache is tied to the dataset's contents: call
+/// [`PrefilterCache::invalidate`] whenever rows are added, removed or
+/// changed. A dataset of a different length also clears it. Clones share
+/// the same bitmaps.
+#[derive(Debug, Clone, Default)]
+pub struct PrefilterCache {
+	state: Arc<Mutex<MaskState>>,
+}
+
+#[derive(Debug, Default)]
+struct MaskState {
+	/// Rows of the dataset the bitmaps were built from.
+	len: usize,
+	/// Bitmap of each [`Constraint::Char`] and [`Constraint::Pair`].
+	grams: HashMap<Constraint, Arc<RowMask>>,
+	/// Combined bitmaps of recent constraints, newest last.
+	recent: VecDeque<(Constraint, Arc<RowMask>)>,
+}
+
+impl PrefilterCache {
+	/// Create an empty cache.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Forget every bitmap, e.g. after the dataset changed.
+	pub fn invalidate(&self) {
+		*self.lock() = MaskState::default();
+	}
+
+	/// Whether no character bitmap is cached.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.lock().grams.is_empty()
+	}
+
+	/// Ascending rows of `dataset` that may match `query`, building the
+	/// bitmaps of characters and pairs seen for the first time.
+	///
+	/// Returns `None` when no row can be ruled out.
+	pub(crate) fn candidates_for<D: Dataset>(
+		&self,
+		query: &Query,
+		dataset: &FoldedDataset<D>,
+	) -> Option<Arc<[usize]>> {
+		let constraint = Constraint::for_query(query)?;
+		let len = dataset.len();
+		let mut state = self.lock();
+		if state.len != len {
+			*state = MaskState {
+				len,
+				..MaskState::default()
+			};
+		}
+		state.scan_missing_grams(&constraint, dataset);
+		let mask = state.mask(&constraint);
+		(mask.count() < len).then(|| mask.rows().collect())
+	}
+
+	fn lock(&self) -> MutexGuard<'_, MaskState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl MaskState {
+	/// Build the bitmaps of the characters and pairs of `constraint` that
+	/// have none yet, in one scan over the tokens of every row.
+	///
+	/// Tokens are scanned as the dataset provides them and folded, so the
+	/// bitmaps serve searches with and without folding.
+	fn scan_missing_grams<D: Dataset>(
+		&mut self,
+		constraint: &Constraint,
+		dataset: &FoldedDataset<D>,
+	) {
+		let mut wanted = Vec::new();
+		constraint.grams(&mut wanted);
+		wanted.sort_unstable();
+		wanted.dedup();
+		wanted.retain(|gram| !self.grams.contains_key(gram));
+		if wanted.is_empty() {
+			return;
+		}
+
+		let mut masks = vec![RowMask::new(self.len); wanted.len()];
+		let mut mark = |row: usize, key: &str| {
+			let mut previous = None;
+			for ch in key.chars().map(normalize) {
+				if let Some(ch) = ch {
+					let pair = previous.map(|previous| Constraint::Pair(previous, ch));
+					for gram in [Some(Constraint::Char(ch)), pair].into_iter().flatten() {
+						if let Ok(slot) = wanted.binary_search(&gram) {
+							masks[slot].insert(row);
+						}
+					}
+				}
+				previous = ch;
+			}
+		};
+		for row in 0..self.len {
+			for token in dataset.raw_tokens(row) {
+				mark(row, token);
+				let folded = fold_diacritics(token);
+				if folded != token {
+					mark(row, &folded);
+				}
+			}
+		}
+		for (gram, mask) in wanted.into_iter().zip(masks) {
+			self.grams.insert(gram, Arc::new(mask));
+		}
+	}
+
+	/// Rows satisfying `constraint`, starting from the largest recent
+	/// bitmap it refines.
+	fn mask(&mut self, constraint: &Constraint) -> Arc<RowMask> {
+		let (children, intersect) = match constraint {
+			Constraint::Char(_) | Constraint::Pair(..) => {
+				return Arc::clone(&self.grams[constraint]);
+			}
+			Constraint::All(children) => (children, true),
+			Constraint::Any(children) => (children, false),
+		};
+		if let Some((_, mask)) = self.recent.iter().find(|(recent, _)| recent == constraint) {
+			return Arc::clone(mask);
+		}
+
+		let base = self
+			.recent
+			.iter()
+			.filter_map(|(recent, mask)| {
+				let covered = match (recent, intersect) {
+					(Constraint::All(covered), true) | (Constraint::Any(covered), false) => covered,
+					_ => return None,
+				};
+				covered
+					.iter()
+					.all(|child| children.binary_search(child).is_ok())
+					.then_some((covered, mask))
+			})
+			.max_by_key(|(covered, _)| covered.len())
+			.map(|(covered, mask)| (covered.clone(), RowMask::clone(mask)));
+		let (covered, mut mask) = match base {
+			Some(base) => base,
+			None => {
+				let first = self.mask(&children[0]);
+				(vec![children[0].clone()], RowMask::clone(&first))
+			}
+		};
+		for child in children {
+			if covered.binary_search(child).is_err() {
+				let other = self.mask(child);
+				if intersect {
+					mask.intersect(&other);
+				} else {
+					mask.extend(&other);
+				}
+			}
+		}
+
+		let mask = Arc::new(mask);
+		if self.recent.len() == RECENT_MASKS {
+			self.recent.pop_front();
+		}
+		self.recent
+			.push_back((constraint.clone(), Arc::clone(&mask)));
+		mask
+	}
+}
+
+/// Set of rows, one bit per row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RowMask {
+	words: Vec<u64>,
+}
+
+impl RowMask {
+	fn new(len: usize) -> Self {
+		Self {
+			words: vec![0; len.div_ceil(64)],
+		}
+	}
+
+	fn insert(&mut self, row: usize) {
+		self.words[row / 64] |= 1 << (row % 64);
+	}
+
+	fn intersect(&mut self, other: &Self) {
+		for (word, other) in self.words.iter_mut().zip(&other.words) {
+			*word &= other;
+		}
+	}
+
+	fn extend(&mut self, other: &Self) {
+		for (word, other) in self.words.iter_mut().zip(&other.words) {
+			*word |= other;
+		}
+	}
+
+	fn count(&self) -> usize {
+		self.words
+			.iter()
+			.map(|word| word.count_ones() as usize)
+			.sum()
+	}
+
+	/// Rows in the set, ascending.
+	fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+		self.words.iter().enumerate().flat_map(|(index, &word)| {
+			let mut word = word;
+			std::iter::from_fn(move || {
+				if word == 0 {
+					return None;
+				}
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				Some(index * 64 + bit)
+			})
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Keys<'a>(&'a [&'a str]);
+
+	impl Dataset for Keys<'_> {
+		fn len(&self) -> usize {
+			self.0.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			self.0[index]
+		}
+	}
+
+	fn candidates(cache: &PrefilterCache, keys: &[&str], query: &str) -> Option<Vec<usize>> {
+		let dataset = FoldedDataset::new(Keys(keys), false);
+		cache
+			.candidates_for(&Query::parse(query), &dataset)
+			.map(|rows| rows.to_vec())
+	}
+
+	#[test]
+	fn bitmaps_are_built_once_per_character_and_reused() {
+		let keys = ["src/main.rs", "src/lib.rs", "docs/guide.md", "Résumé.pdf"];
+		let cache = PrefilterCache::new();
+		assert!(cache.is_empty());
+		assert_eq!(candidates(&cache, &keys, "'li"), Some(vec![1]));
+		assert_eq!(cache.lock().grams.len(), 1);
+
+		assert_eq!(candidates(&cache, &keys, "'lib"), Some(vec![1]));
+		assert_eq!(candidates(&cache, &keys, "'l"), Some(vec![1]));
+		assert_eq!(cache.lock().grams.len(), 3, "`ib` and `l` were scanned for");
+		assert_eq!(candidates(&cache, &keys, "'resu"), Some(vec![3]), "folded");
+		assert_eq!(candidates(&cache, &keys, "'s"), None, "every row has an s");
+	}
+
+	#[test]
+	fn added_characters_refine_the_previous_bitmap() {
+		let keys = ["ab", "abc", "abcd", "xyz"];
+		let cache = PrefilterCache::new();
+		assert_eq!(candidates(&cache, &keys, "'ab"), Some(vec![0, 1, 2]));
+		assert_eq!(candidates(&cache, &keys, "'ab 'c"), Some(vec![1, 2]));
+		assert_eq!(candidates(&cache, &keys, "'ab 'c 'd"), Some(vec![2]));
+		// Fuzzy terms extend their bitmap with every added character.
+		assert_eq!(candidates(&cache, &keys, "'ab | 'x"), None);
+		assert_eq!(candidates(&cache, &keys, "D"), Some(vec![2]));
+		assert_eq!(candidates(&cache, &keys, "dz"), Some(vec![2, 3]));
+		assert_eq!(candidates(&cache, &keys, "'ab 'c"), Some(vec![1, 2]));
+	}
+
+	#[test]
+	fn invalidated_caches_see_new_rows() {
+		let cache = PrefilterCache::new();
+		assert_eq!(candidates(&cache, &["ab", "cd"], "'ab"), Some(vec![0]));
+		cache.invalidate();
+		assert!(cache.is_empty());
+		assert_eq!(candidates(&cache, &["cd", "ab"], "'ab"), Some(vec![1]));
+		assert_eq!(
+			candidates(&cache, &["cd", "ab", "abc"], "'ab"),
+			Some(vec![1, 2]),
+			"datasets of another length clear the cache"
+		);
+	}
+}
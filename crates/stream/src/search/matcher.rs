@@ -1,16 +1,25 @@
+use std::borrow::Cow;
 use std::cmp::{Ordering as CmpOrdering, Reverse};
-use std::collections::BinaryHeap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::thread;
 
 use frizbee::{Config, match_list};
 
-use super::channel::{MatchBatch, SearchStream};
+use super::channel::{CancellationToken, MatchBatch, SearchStream};
+use super::fold::{FoldedDataset, fold_diacritics, unfold_positions};
+use super::mask::PrefilterCache;
+use super::narrow::NarrowingCache;
+use super::options::{BoundaryBonus, CaseMatching, ScoreCutoff, SearchOptions, Tiebreak};
+use super::prefilter::PrefilterIndex;
+use super::query::{Query, QueryTerm, TermGroup, config_for_alternative};
 
 /// Tunable thresholds shared across the search pipeline.
 pub const PREFILTER_ENABLE_THRESHOLD: usize = 1_000;
 
-/// Maximum number of rows rendered in the result table.
+/// Default maximum number of rows rendered in the result table, see
+/// [`SearchOptions::max_results`].
 pub const MAX_RENDERED_RESULTS: usize = 2_000;
 
 /// Number of matches processed per scoring chunk.
@@ -19,14 +28,63 @@ pub const MATCH_CHUNK_SIZE: usize = 512;
 /// Number of rows processed before emitting a heartbeat for empty queries.
 pub const EMPTY_QUERY_BATCH: usize = 128;
 
+/// Score added for each fuzzy term whose characters all appear, in order, in
+/// the final path component of a key, so `main` ranks `src/main.rs` above
+/// `main_helpers/util.rs`.
+///
+/// Larger than frizbee's prefix bonus, which would otherwise favour paths
+/// whose first directory starts with the term.
+pub const BASENAME_MATCH_BONUS: u16 = 16;
+
+/// Most haystacks handed to frizbee at once on AVX-512 machines.
+///
+/// frizbee 0.6 scores up to 32 haystacks of similar length together, but
+/// its AVX-512 interleave swaps lanes 8..16 with 16..24, so scores land on
+/// the wrong haystacks. Batches of 16 always use the 16-lane path, which
+/// keeps them in place.
+const AVX512_FRIZBEE_BATCH: usize = 16;
+
+/// Number of haystacks to hand to frizbee per call, out of `len`.
+fn frizbee_batch(len: usize) -> usize {
+	#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+	if std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512bw") {
+		return AVX512_FRIZBEE_BATCH;
+	}
+	len.max(1)
+}
+
 /// Builds fuzzy matching options for the provided query and dataset size.
+///
+/// The query is split on whitespace and the typo budget sized for its longest
+/// term, since each term is matched on its own. Letter case follows
+/// [`CaseMatching::Smart`] and word boundaries get the default
+/// [`BoundaryBonus`].
 pub fn config_for_query(query: &str, dataset_len: usize) -> Config {
+	config_for_query_with_case(query, dataset_len, CaseMatching::Smart)
+}
+
+/// Builds fuzzy matching options like [`config_for_query`] with an explicit
+/// [`CaseMatching`] mode.
+///
+/// frizbee always compares case-insensitively, so case-sensitive queries
+/// instead raise its matching-case bonus; this makes frizbee align on
+/// same-case characters, keeping highlights on the characters that satisfy
+/// the case-sensitive check.
+pub fn config_for_query_with_case(
+	query: &str,
+	dataset_len: usize,
+	case_matching: CaseMatching,
+) -> Config {
 	let mut config = Config {
 		prefilter: false,
 		..Config::default()
 	};
 
-	let length = query.chars().count();
+	let length = query
+		.split_whitespace()
+		.map(|term| term.chars().count())
+		.max()
+		.unwrap_or(0);
 	let mut allowed_typos: u16 = match length {
 		0 => 0,
 		1 => 0,
@@ -48,31 +106,143 @@ pub fn config_for_query(query: &str, dataset_len: usize) -> Config {
 	}
 
 	config.sort = false;
+	apply_boundary_bonus(&mut config, BoundaryBonus::default());
+	if case_matching.is_sensitive(query) {
+		prefer_matching_case(&mut config);
+	}
 
 	config
 }
 
+/// Builds fuzzy matching options like [`config_for_query`] with the case
+/// matching and boundary bonuses of `options`.
+pub fn config_for_options(query: &str, dataset_len: usize, options: &SearchOptions) -> Config {
+	let mut config = config_for_query_with_case(query, dataset_len, options.case_matching);
+	apply_boundary_bonus(&mut config, options.boundary_bonus);
+	config
+}
+
+fn apply_boundary_bonus(config: &mut Config, bonus: BoundaryBonus) {
+	config.scoring.delimiter_bonus = bonus.separator;
+	config.scoring.capitalization_bonus = bonus.camel_case;
+}
+
+/// Make frizbee favour characters whose case matches the needle.
+fn prefer_matching_case(config: &mut Config) {
+	config.scoring.matching_case_bonus = config.scoring.match_score;
+}
+
 #[derive(Clone, Eq, PartialEq)]
 struct RankedMatch {
 	index: usize,
 	score: u16,
+	/// Key length used by [`Tiebreak::PathLength`]; zero when unused.
+	length: usize,
+	/// Key used for lexical tiebreaks; empty when unused.
+	key: Box<str>,
+	/// Compare `key` with [`natural_cmp`] instead of lexically.
+	natural: bool,
+}
+
+impl RankedMatch {
+	fn new(index: usize, score: u16) -> Self {
+		Self {
+			index,
+			score,
+			length: 0,
+			key: Box::default(),
+			natural: false,
+		}
+	}
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum StreamPassResult {
 	Completed,
 	Aborted,
+	Cancelled,
 	HungUp,
 }
 
+/// Early-exit conditions polled between chunks: the pass's token and the
+/// stream's own [`CancellationToken`], if it has one.
+struct AbortCheck {
+	cancel: CancellationToken,
+	stream_cancel: Option<CancellationToken>,
+}
+
+impl AbortCheck {
+	fn new(stream: &SearchStream<'_>, cancel: CancellationToken) -> Self {
+		Self {
+			cancel: cancel.for_stream(stream.id()),
+			stream_cancel: stream.cancellation().cloned(),
+		}
+	}
+
+	fn tokens(&self) -> impl Iterator<Item = &CancellationToken> {
+		std::iter::once(&self.cancel).chain(self.stream_cancel.as_ref())
+	}
+
+	/// Reason to stop the current pass, if any.
+	fn poll(&self) -> Option<StreamPassResult> {
+		if self.tokens().any(CancellationToken::is_tripped) {
+			Some(StreamPassResult::Cancelled)
+		} else if self.tokens().any(CancellationToken::is_superseded) {
+			Some(StreamPassResult::Aborted)
+		} else {
+			None
+		}
+	}
+}
+
 impl Ord for RankedMatch {
 	fn cmp(&self, other: &Self) -> CmpOrdering {
 		self.score
 			.cmp(&other.score)
+			.then_with(|| other.length.cmp(&self.length))
+			.then_with(|| {
+				if self.natural {
+					natural_cmp(&other.key, &self.key)
+				} else {
+					other.key.cmp(&self.key)
+				}
+			})
 			.then_with(|| other.index.cmp(&self.index))
 	}
 }
 
+/// Compare `a` and `b` character by character, except that runs of ASCII
+/// digits are compared by numeric value. Equal values with different leading
+/// zeros fall back to comparing the digits as text, keeping the order total.
+fn natural_cmp(a: &str, b: &str) -> CmpOrdering {
+	let (mut a, mut b) = (a, b);
+	loop {
+		let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+			return a.len().cmp(&b.len());
+		};
+		if ca.is_ascii_digit() && cb.is_ascii_digit() {
+			let split = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+			let (run_a, rest_a) = a.split_at(split(a));
+			let (run_b, rest_b) = b.split_at(split(b));
+			let (value_a, value_b) = (run_a.trim_start_matches('0'), run_b.trim_start_matches('0'));
+			let ordering = value_a
+				.len()
+				.cmp(&value_b.len())
+				.then_with(|| value_a.cmp(value_b))
+				.then_with(|| run_a.cmp(run_b));
+			if ordering != CmpOrdering::Equal {
+				return ordering;
+			}
+			(a, b) = (rest_a, rest_b);
+		} else {
+			if ca != cb {
+				return ca.cmp(&cb);
+			}
+			(a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+		}
+	}
+}
+
 impl PartialOrd for RankedMatch {
 	fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
 		Some(self.cmp(other))
@@ -82,31 +252,110 @@ impl PartialOrd for RankedMatch {
 /// Maintains the highest scoring matches for a particular query.
 pub struct ScoreAggregator<'a> {
 	stream: SearchStream<'a>,
+	tiebreak: Tiebreak,
+	limit: usize,
+	cutoff: Option<ScoreCutoff>,
 	heap: BinaryHeap<Reverse<RankedMatch>>,
 	scratch: Vec<RankedMatch>,
 	dirty: bool,
+	/// Computes the matched character positions of the row at an index.
+	positions: Option<&'a dyn Fn(usize) -> Vec<usize>>,
+	/// Positions of the rows sent by the last emit, keyed by index.
+	sent_positions: HashMap<usize, Vec<usize>>,
 }
 
 impl<'a> ScoreAggregator<'a> {
 	/// Creates a new aggregator that will stream results through `stream`.
 	pub fn new(stream: SearchStream<'a>) -> Self {
+		Self::with_tiebreak(stream, Tiebreak::default())
+	}
+
+	/// Creates an aggregator that orders equal scores using `tiebreak`.
+	pub fn with_tiebreak(stream: SearchStream<'a>, tiebreak: Tiebreak) -> Self {
 		Self {
 			stream,
+			tiebreak,
+			limit: MAX_RENDERED_RESULTS,
+			cutoff: None,
 			heap: BinaryHeap::new(),
 			scratch: Vec::new(),
 			dirty: false,
+			positions: None,
+			sent_positions: HashMap::new(),
 		}
 	}
 
+	/// Keep at most `limit` matches instead of [`MAX_RENDERED_RESULTS`].
+	#[must_use]
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit;
+		self
+	}
+
+	/// Leave matches scoring below `cutoff` out of every emitted batch.
+	///
+	/// A relative cutoff follows the best match seen so far, so rows can
+	/// disappear from later batches as better matches arrive.
+	#[must_use]
+	pub fn with_cutoff(mut self, cutoff: Option<ScoreCutoff>) -> Self {
+		self.cutoff = cutoff;
+		self
+	}
+
+	/// Attach the matched character positions returned by `positions` to
+	/// every emitted row, see [`MatchBatch::positions`].
+	///
+	/// Positions are only computed for rows that are emitted and are reused
+	/// while a row stays among the best matches, so no more than the limit of
+	/// rows have positions at any time.
+	#[must_use]
+	pub fn with_positions(mut self, positions: &'a dyn Fn(usize) -> Vec<usize>) -> Self {
+		self.positions = Some(positions);
+		self
+	}
+
 	/// Inserts a scored match and marks the aggregator as dirty when the result set changes.
+	///
+	/// Matches pushed without a key keep dataset order among equal scores.
 	pub fn push(&mut self, index: usize, score: u16) {
-		if self.insert(RankedMatch { index, score }) {
+		if self.insert(RankedMatch::new(index, score)) {
+			self.dirty = true;
+		}
+	}
+
+	/// Inserts a scored match, ordering equal scores by `key` according to the
+	/// configured [`Tiebreak`].
+	pub fn push_keyed(&mut self, index: usize, score: u16, key: &str) {
+		// Skip building the tiebreak key for matches that cannot enter the heap.
+		if self.heap.len() >= self.limit && self.heap.peek().is_some_and(|min| score < min.0.score)
+		{
+			return;
+		}
+
+		let entry = match self.tiebreak {
+			Tiebreak::None => RankedMatch::new(index, score),
+			Tiebreak::Lexical => RankedMatch {
+				key: key.into(),
+				..RankedMatch::new(index, score)
+			},
+			Tiebreak::Natural => RankedMatch {
+				key: key.into(),
+				natural: true,
+				..RankedMatch::new(index, score)
+			},
+			Tiebreak::PathLength => RankedMatch {
+				length: key.chars().count(),
+				key: key.into(),
+				..RankedMatch::new(index, score)
+			},
+		};
+		if self.insert(entry) {
 			self.dirty = true;
 		}
 	}
 
 	fn insert(&mut self, entry: RankedMatch) -> bool {
-		if self.heap.len() < MAX_RENDERED_RESULTS {
+		if self.heap.len() < self.limit {
 			self.heap.push(Reverse(entry));
 			true
 		} else if let Some(mut current_min) = self.heap.peek_mut() {
@@ -143,8 +392,15 @@ impl<'a> ScoreAggregator<'a> {
 		self.scratch.clear();
 		self.scratch
 			.extend(self.heap.iter().map(|entry| entry.0.clone()));
-		self.scratch
-			.sort_unstable_by(|a, b| b.score.cmp(&a.score).then_with(|| a.index.cmp(&b.index)));
+		self.scratch.sort_unstable_by(|a, b| b.cmp(a));
+		if let (Some(cutoff), Some(best)) = (self.cutoff, self.scratch.first()) {
+			let threshold = cutoff.threshold(best.score);
+			// Sorted by score first, so the weak matches are at the end.
+			let kept = self
+				.scratch
+				.partition_point(|entry| entry.score >= threshold);
+			self.scratch.truncate(kept);
+		}
 
 		let mut indices = Vec::with_capacity(self.scratch.len());
 		let mut scores = Vec::with_capacity(self.scratch.len());
@@ -152,6 +408,7 @@ impl<'a> ScoreAggregator<'a> {
 			indices.push(entry.index);
 			scores.push(entry.score);
 		}
+		let positions = self.emitted_positions(&indices);
 
 		self.dirty = false;
 		self.stream.send_batch(
@@ -159,19 +416,36 @@ impl<'a> ScoreAggregator<'a> {
 				indices,
 				ids: None,
 				scores,
+				positions,
 			},
 			complete,
 		)
 	}
+
+	/// Positions for the emitted `indices`, computing only those of rows
+	/// that were not part of the previous emit.
+	fn emitted_positions(&mut self, indices: &[usize]) -> Option<Vec<Vec<usize>>> {
+		let source = self.positions?;
+		let mut previous = std::mem::take(&mut self.sent_positions);
+		let positions = indices
+			.iter()
+			.map(|&index| {
+				let positions = previous.remove(&index).unwrap_or_else(|| source(index));
+				self.sent_positions.insert(index, positions.clone());
+				positions
+			})
+			.collect();
+		Some(positions)
+	}
 }
 
 #[derive(Clone, Eq, PartialEq)]
-struct AlphabeticalEntry {
+struct AlphabeticalEntry<K> {
 	index: usize,
-	key: String,
+	key: K,
 }
 
-impl Ord for AlphabeticalEntry {
+impl<K: Ord> Ord for AlphabeticalEntry<K> {
 	fn cmp(&self, other: &Self) -> CmpOrdering {
 		self.key
 			.cmp(&other.key)
@@ -179,33 +453,40 @@ impl Ord for AlphabeticalEntry {
 	}
 }
 
-impl PartialOrd for AlphabeticalEntry {
+impl<K: Ord> PartialOrd for AlphabeticalEntry<K> {
 	fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
 		Some(self.cmp(other))
 	}
 }
 
-/// Collects the lexicographically smallest entries for an empty query.
-pub struct AlphabeticalCollector<'a, F>
+/// Collects the entries with the smallest keys for an empty query.
+///
+/// Keys are usually paths, giving alphabetical order, but any [`Ord`] key
+/// works, e.g. one that sorts recently modified files first.
+pub struct AlphabeticalCollector<'a, F, K = String>
 where
-	F: FnMut(usize) -> String,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
 {
 	stream: SearchStream<'a>,
+	total: usize,
 	limit: usize,
 	key_for_index: F,
-	heap: BinaryHeap<AlphabeticalEntry>,
-	scratch: Vec<AlphabeticalEntry>,
+	heap: BinaryHeap<AlphabeticalEntry<K>>,
+	scratch: Vec<AlphabeticalEntry<K>>,
 	dirty: bool,
 }
 
-impl<'a, F> AlphabeticalCollector<'a, F>
+impl<'a, F, K> AlphabeticalCollector<'a, F, K>
 where
-	F: FnMut(usize) -> String,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
 {
 	/// Creates a collector that will emit at most [`MAX_RENDERED_RESULTS`] entries.
 	pub fn new(stream: SearchStream<'a>, total: usize, key_for_index: F) -> Self {
 		Self {
 			stream,
+			total,
 			limit: MAX_RENDERED_RESULTS.min(total),
 			key_for_index,
 			heap: BinaryHeap::new(),
@@ -214,6 +495,13 @@ where
 		}
 	}
 
+	/// Emit at most `limit` entries instead of [`MAX_RENDERED_RESULTS`].
+	#[must_use]
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit.min(self.total);
+		self
+	}
+
 	/// Inserts a candidate index when the collector still has capacity.
 	pub fn insert(&mut self, index: usize) {
 		if self.limit == 0 {
@@ -255,6 +543,7 @@ where
 					indices: Vec::new(),
 					ids: None,
 					scores: Vec::new(),
+					positions: None,
 				},
 				complete,
 			);
@@ -277,6 +566,7 @@ where
 				indices,
 				ids: None,
 				scores,
+				positions: None,
 			},
 			complete,
 		)
@@ -295,6 +585,27 @@ pub trait Dataset {
 
 	/// Return the searchable key associated with `index`.
 	fn key_for(&self, index: usize) -> &str;
+
+	/// Extra score added to every match of the entry at `index`, for example
+	/// to favour recently used entries. Defaults to no bonus.
+	///
+	/// The bonus only applies to scored queries; empty queries keep their
+	/// alphabetical order.
+	fn score_bonus(&self, index: usize) -> u16 {
+		let _ = index;
+		0
+	}
+
+	/// Every scorable field of the entry at `index`, starting with its
+	/// [`Dataset::key_for`]. Defaults to the key alone.
+	///
+	/// Each further token, such as a tag, is scored on its own and a match
+	/// keeps its best score across the tokens. `tag:` filters compare the
+	/// further tokens, while negations, other filters, sorting and
+	/// highlighting only look at the key.
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		std::iter::once(self.key_for(index))
+	}
 }
 
 impl<T> Dataset for &T
@@ -308,16 +619,50 @@ where
 	fn key_for(&self, index: usize) -> &str {
 		<T as Dataset>::key_for(*self, index)
 	}
+
+	fn score_bonus(&self, index: usize) -> u16 {
+		<T as Dataset>::score_bonus(*self, index)
+	}
+
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		<T as Dataset>::tokens_for(*self, index)
+	}
 }
 
 /// Owned dataset that can be sent across threads for background refinement.
 struct OwnedDataset {
 	entries: Vec<String>,
+	bonuses: Vec<u16>,
+	/// Tokens after the key, by ascending entry index.
+	tokens: Vec<(usize, String)>,
+	/// Unfolded keys of entries whose diacritics were folded; see
+	/// [`FoldedDataset::originals`].
+	originals: Vec<Option<String>>,
 }
 
 impl OwnedDataset {
-	fn new(entries: Vec<String>) -> Self {
-		Self { entries }
+	fn with_capacity(capacity: usize) -> Self {
+		Self {
+			entries: Vec::with_capacity(capacity),
+			bonuses: Vec::with_capacity(capacity),
+			tokens: Vec::new(),
+			originals: Vec::new(),
+		}
+	}
+
+	fn original(&self, index: usize) -> Option<&str> {
+		self.originals.get(index)?.as_deref()
+	}
+
+	fn push(&mut self, key: &str, bonus: u16) {
+		self.entries.push(key.to_owned());
+		self.bonuses.push(bonus);
+	}
+
+	/// Add a token after the key of the entry pushed last.
+	fn push_token(&mut self, token: &str) {
+		let index = self.entries.len() - 1;
+		self.tokens.push((index, token.to_owned()));
 	}
 }
 
@@ -329,98 +674,591 @@ impl Dataset for OwnedDataset {
 	fn key_for(&self, index: usize) -> &str {
 		&self.entries[index]
 	}
+
+	fn score_bonus(&self, index: usize) -> u16 {
+		self.bonuses[index]
+	}
+
+	fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+		let start = self.tokens.partition_point(|(entry, _)| *entry < index);
+		let tokens = self.tokens[start..]
+			.iter()
+			.take_while(move |(entry, _)| *entry == index)
+			.map(|(_, token)| token.as_str());
+		std::iter::once(self.key_for(index)).chain(tokens)
+	}
+}
+
+/// Score a chunk of haystacks against every term in `query`.
+///
+/// Candidates excluded by negation terms or filters are dropped before
+/// scoring. Returns chunk-local indices paired with their combined score,
+/// the best of their key and their `tokens`, which pair chunk-local indices
+/// with the tokens after each key.
+fn match_chunk(
+	query: &Query,
+	haystacks: &[&str],
+	tokens: &[(usize, &str)],
+	config: &Config,
+) -> Vec<(usize, u16)> {
+	let excluded = excluded_in_chunk(query, haystacks, tokens);
+	let kept_local = |local: usize| excluded.get(local) != Some(&true);
+	let matches = if excluded.is_empty() {
+		score_candidates(query, haystacks, config)
+	} else {
+		let (indices, kept): (Vec<usize>, Vec<&str>) = haystacks
+			.iter()
+			.enumerate()
+			.filter(|(local, _)| kept_local(*local))
+			.map(|(index, haystack)| (index, *haystack))
+			.unzip();
+		score_candidates(query, &kept, config)
+			.into_iter()
+			.map(|(local, score)| (indices[local], score))
+			.collect()
+	};
+	if tokens.is_empty() {
+		return matches;
+	}
+
+	let (owners, kept): (Vec<usize>, Vec<&str>) = tokens
+		.iter()
+		.filter(|(local, _)| kept_local(*local))
+		.copied()
+		.unzip();
+	let mut best = vec![None; haystacks.len()];
+	for (local, score) in matches {
+		best[local] = Some(score);
+	}
+	for (token, score) in score_candidates(query, &kept, config) {
+		let current = &mut best[owners[token]];
+		*current = (*current).max(Some(score));
+	}
+	best.into_iter()
+		.enumerate()
+		.filter_map(|(local, score)| Some((local, score?)))
+		.collect()
+}
+
+/// Whether `query` excludes each haystack of a chunk, checking its filters
+/// against the `tokens` after each key. Empty when the query excludes
+/// nothing.
+fn excluded_in_chunk(query: &Query, haystacks: &[&str], tokens: &[(usize, &str)]) -> Vec<bool> {
+	if !query.has_exclusions() {
+		return Vec::new();
+	}
+
+	let mut rest = tokens;
+	let mut tags = Vec::new();
+	haystacks
+		.iter()
+		.enumerate()
+		.map(|(local, haystack)| {
+			let count = rest.iter().take_while(|(owner, _)| *owner == local).count();
+			let (own, after) = rest.split_at(count);
+			rest = after;
+			tags.clear();
+			tags.extend(own.iter().map(|(_, token)| *token));
+			query.excludes_entry(haystack, &tags)
+		})
+		.collect()
+}
+
+fn score_candidates(query: &Query, haystacks: &[&str], config: &Config) -> Vec<(usize, u16)> {
+	let mut scores: Vec<Option<u16>> = haystacks
+		.iter()
+		.map(|haystack| query.score_literal_terms(haystack, &config.scoring))
+		.collect();
+
+	for term in query.fuzzy_terms() {
+		let config = config_for_alternative(&term.text, config);
+		require_fuzzy_term(&mut scores, term, haystacks, &config);
+	}
+
+	for group in query.alternative_groups() {
+		let best = score_alternatives(group, haystacks, config);
+		for (score, alternative) in scores.iter_mut().zip(best) {
+			*score = score.zip(alternative).map(|(a, b)| a.saturating_add(b));
+		}
+	}
+
+	scores
+		.into_iter()
+		.enumerate()
+		.filter_map(|(index, score)| Some((index, score?)))
+		.collect()
+}
+
+/// Add the score of `term` to every surviving candidate, dropping those it
+/// does not match. Only candidates still in the running are handed to frizbee.
+fn require_fuzzy_term(
+	scores: &mut [Option<u16>],
+	term: &QueryTerm,
+	haystacks: &[&str],
+	config: &Config,
+) {
+	let (alive, subset): (Vec<usize>, Vec<&str>) = scores
+		.iter()
+		.enumerate()
+		.filter(|(_, score)| score.is_some())
+		.map(|(index, _)| (index, haystacks[index]))
+		.unzip();
+	if alive.is_empty() {
+		return;
+	}
+
+	let term_scores = fuzzy_scores(term, &subset, config);
+	for (index, term_score) in alive.into_iter().zip(term_scores) {
+		scores[index] = scores[index]
+			.zip(term_score)
+			.map(|(a, b)| a.saturating_add(b));
+	}
+}
+
+fn fuzzy_scores(term: &QueryTerm, haystacks: &[&str], config: &Config) -> Vec<Option<u16>> {
+	let mut scores = vec![None; haystacks.len()];
+	let batch_len = frizbee_batch(haystacks.len());
+	for (batch, offset) in haystacks.chunks(batch_len).zip((0..).step_by(batch_len)) {
+		for entry in match_list(&term.text, batch, config) {
+			let index = offset + entry.index as usize;
+			if entry.score > 0 && term.matches_case(haystacks[index], config) {
+				scores[index] = Some(entry.score);
+			}
+		}
+	}
+	apply_basename_bonus(&mut scores, term, haystacks);
+	scores
+}
+
+/// Final component of a `/`-separated key, ignoring trailing separators.
+fn final_component(key: &str) -> &str {
+	let trimmed = key.trim_end_matches('/');
+	trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+/// Add [`BASENAME_MATCH_BONUS`] to matches whose final path component
+/// contains every character of `term` in order.
+///
+/// Keys without a separator are all basename, so a clean match anywhere in
+/// them is boosted; matches that needed typos never are.
+fn apply_basename_bonus(scores: &mut [Option<u16>], term: &QueryTerm, haystacks: &[&str]) {
+	for (score, haystack) in scores.iter_mut().zip(haystacks) {
+		if let Some(score) = score
+			&& term.is_subsequence_of(final_component(haystack))
+		{
+			*score = score.saturating_add(BASENAME_MATCH_BONUS);
+		}
+	}
+}
+
+/// Score each haystack by the best matching alternative in `group`.
+fn score_alternatives(group: &TermGroup, haystacks: &[&str], config: &Config) -> Vec<Option<u16>> {
+	let mut best = vec![None; haystacks.len()];
+	for term in &group.alternatives {
+		let scores: Vec<Option<u16>> = if term.is_fuzzy() {
+			let config = config_for_alternative(&term.text, config);
+			fuzzy_scores(term, haystacks, &config)
+		} else if term.is_negation() {
+			haystacks
+				.iter()
+				.map(|haystack| (!term.occurs_in(haystack)).then_some(0))
+				.collect()
+		} else {
+			haystacks
+				.iter()
+				.map(|haystack| term.score(haystack, &config.scoring))
+				.collect()
+		};
+		for (current, candidate) in best.iter_mut().zip(scores) {
+			*current = (*current).max(candidate);
+		}
+	}
+	best
+}
+
+/// Rows visited by a matching pass.
+#[derive(Clone, Copy)]
+enum Rows<'a> {
+	/// Every row of the dataset.
+	All(usize),
+	/// Only these rows, in ascending order.
+	Subset(&'a [usize]),
+}
+
+impl Rows<'_> {
+	fn len(&self) -> usize {
+		match self {
+			Self::All(total) => *total,
+			Self::Subset(indices) => indices.len(),
+		}
+	}
+
+	fn index(&self, position: usize) -> usize {
+		match self {
+			Self::All(_) => position,
+			Self::Subset(indices) => indices[position],
+		}
+	}
+}
+
+/// Optional outputs collected by a matching pass besides the ranked stream.
+#[derive(Default)]
+struct PassSink<'a> {
+	/// Copy of every visited key, for a background refinement pass.
+	owned: Option<&'a mut OwnedDataset>,
+	/// Every matched index, for narrowing later queries.
+	matched: Option<&'a mut Vec<usize>>,
+}
+
+/// What a matching pass scores and how.
+#[derive(Clone, Copy)]
+struct MatchPass<'a> {
+	rows: Rows<'a>,
+	query: &'a Query,
+	config: &'a Config,
+	/// Threads scoring chunks; one scores on the calling thread.
+	threads: usize,
 }
 
+/// Chunks scored ahead of the one being merged, per worker thread.
+const CHUNKS_IN_FLIGHT_PER_WORKER: usize = 2;
+
 fn stream_matches_with_config<D>(
 	dataset: D,
-	trimmed: &str,
-	config: &Config,
+	pass: MatchPass<'_>,
 	aggregator: &mut ScoreAggregator<'_>,
-	latest_query_id: &AtomicU64,
-	stream_id: u64,
-	mut owned_keys: Option<&mut Vec<String>>,
+	abort: &AbortCheck,
+	sink: PassSink<'_>,
 ) -> StreamPassResult
 where
 	D: Dataset,
 {
-	let total = dataset.len();
+	let chunks = pass.rows.len().div_ceil(MATCH_CHUNK_SIZE);
+	let workers = pass.threads.min(chunks);
+	if workers > 1 {
+		stream_matches_in_parallel(&dataset, pass, workers, aggregator, abort, sink)
+	} else {
+		stream_matches_sequentially(&dataset, pass, aggregator, abort, sink)
+	}
+}
+
+fn stream_matches_sequentially<D>(
+	dataset: &D,
+	pass: MatchPass<'_>,
+	aggregator: &mut ScoreAggregator<'_>,
+	abort: &AbortCheck,
+	mut sink: PassSink<'_>,
+) -> StreamPassResult
+where
+	D: Dataset,
+{
+	let chunks = pass.rows.len().div_ceil(MATCH_CHUNK_SIZE);
 	let mut haystacks = Vec::with_capacity(MATCH_CHUNK_SIZE);
-	let mut offset = 0;
-	while offset < total {
-		if should_abort(stream_id, latest_query_id) {
-			return StreamPassResult::Aborted;
+	let mut indices = Vec::with_capacity(MATCH_CHUNK_SIZE);
+	let mut tokens = Vec::new();
+	for chunk in 0..chunks {
+		if let Some(result) = abort.poll() {
+			return result;
 		}
 
-		let end = (offset + MATCH_CHUNK_SIZE).min(total);
 		haystacks.clear();
-		for index in offset..end {
-			let key = dataset.key_for(index);
-			haystacks.push(key);
-			if let Some(keys) = owned_keys.as_deref_mut() {
-				keys.push(key.to_owned());
+		indices.clear();
+		tokens.clear();
+		gather_chunk(
+			dataset,
+			pass.rows,
+			chunk,
+			ChunkKeys {
+				indices: &mut indices,
+				haystacks: &mut haystacks,
+				tokens: &mut tokens,
+			},
+			&mut sink,
+		);
+		let matches = match_chunk(pass.query, &haystacks, &tokens, pass.config);
+		merge_chunk(
+			dataset, &indices, &haystacks, matches, aggregator, &mut sink,
+		);
+
+		if let Some(result) = abort.poll() {
+			return result;
+		}
+		if !aggregator.flush_partial() {
+			return StreamPassResult::HungUp;
+		}
+	}
+
+	abort.poll().unwrap_or(StreamPassResult::Completed)
+}
+
+/// Score chunks on `workers` threads while this thread reads keys and merges
+/// the scored chunks in dataset order, so the stream sees the same batches
+/// as a sequential pass.
+///
+/// Keys are only read on the calling thread, so the dataset does not need to
+/// be shared. Workers poll `abort` before every chunk and skip the work once
+/// the query is superseded or cancelled.
+fn stream_matches_in_parallel<'d, D>(
+	dataset: &'d D,
+	pass: MatchPass<'_>,
+	workers: usize,
+	aggregator: &mut ScoreAggregator<'_>,
+	abort: &AbortCheck,
+	mut sink: PassSink<'_>,
+) -> StreamPassResult
+where
+	D: Dataset,
+{
+	type Job<'d> = (usize, Vec<&'d str>, Vec<(usize, &'d str)>);
+	type Scored<'d> = (usize, Vec<&'d str>, Option<Vec<(usize, u16)>>);
+
+	let chunks = pass.rows.len().div_ceil(MATCH_CHUNK_SIZE);
+	let window = workers * CHUNKS_IN_FLIGHT_PER_WORKER;
+	let (job_tx, job_rx) = mpsc::channel::<Job<'d>>();
+	let job_rx = Mutex::new(job_rx);
+	let (scored_tx, scored_rx) = mpsc::channel::<Scored<'d>>();
+
+	thread::scope(|scope| {
+		for _ in 0..workers {
+			let (jobs, scored) = (&job_rx, scored_tx.clone());
+			scope.spawn(move || {
+				loop {
+					let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+					let Ok((chunk, haystacks, tokens)) = job else {
+						break;
+					};
+					let matches = abort
+						.poll()
+						.is_none()
+						.then(|| match_chunk(pass.query, &haystacks, &tokens, pass.config));
+					if scored.send((chunk, haystacks, matches)).is_err() {
+						break;
+					}
+				}
+			});
+		}
+
+		let mut indices_in_flight = VecDeque::with_capacity(window);
+		let mut pending = BTreeMap::new();
+		let (mut next_chunk, mut merged) = (0, 0);
+		let outcome = 'pass: loop {
+			if let Some(result) = abort.poll() {
+				break result;
+			}
+			while next_chunk < chunks && next_chunk - merged < window {
+				let mut indices = Vec::with_capacity(MATCH_CHUNK_SIZE);
+				let mut haystacks = Vec::with_capacity(MATCH_CHUNK_SIZE);
+				let mut tokens = Vec::new();
+				gather_chunk(
+					dataset,
+					pass.rows,
+					next_chunk,
+					ChunkKeys {
+						indices: &mut indices,
+						haystacks: &mut haystacks,
+						tokens: &mut tokens,
+					},
+					&mut sink,
+				);
+				indices_in_flight.push_back(indices);
+				// The receiver outlives the workers, so sending cannot fail.
+				let _ = job_tx.send((next_chunk, haystacks, tokens));
+				next_chunk += 1;
+			}
+			if merged == chunks {
+				break abort.poll().unwrap_or(StreamPassResult::Completed);
 			}
+
+			// Workers only stop once the job channel closes, so a result
+			// always arrives.
+			let Ok((chunk, haystacks, matches)) = scored_rx.recv() else {
+				break StreamPassResult::Aborted;
+			};
+			pending.insert(chunk, (haystacks, matches));
+			while let Some((haystacks, matches)) = pending.remove(&merged) {
+				let indices = indices_in_flight.pop_front().unwrap_or_default();
+				let Some(matches) = matches else {
+					break 'pass abort.poll().unwrap_or(StreamPassResult::Aborted);
+				};
+				merge_chunk(
+					dataset, &indices, &haystacks, matches, aggregator, &mut sink,
+				);
+				merged += 1;
+
+				if let Some(result) = abort.poll() {
+					break 'pass result;
+				}
+				if !aggregator.flush_partial() {
+					break 'pass StreamPassResult::HungUp;
+				}
+			}
+		};
+		drop(job_tx);
+		outcome
+	})
+}
+
+/// Buffers a chunk's rows are read into.
+struct ChunkKeys<'a, 'd> {
+	/// Dataset index of each row.
+	indices: &'a mut Vec<usize>,
+	/// Key of each row.
+	haystacks: &'a mut Vec<&'d str>,
+	/// Chunk-local row of each token after a key, with the token.
+	tokens: &'a mut Vec<(usize, &'d str)>,
+}
+
+/// Read the keys and tokens of the rows in `chunk`, copying them into the
+/// sink's owned dataset when one is being collected.
+fn gather_chunk<'d, D>(
+	dataset: &'d D,
+	rows: Rows<'_>,
+	chunk: usize,
+	keys: ChunkKeys<'_, 'd>,
+	sink: &mut PassSink<'_>,
+) where
+	D: Dataset,
+{
+	let start = chunk * MATCH_CHUNK_SIZE;
+	let end = (start + MATCH_CHUNK_SIZE).min(rows.len());
+	for (local, position) in (start..end).enumerate() {
+		let index = rows.index(position);
+		let mut tokens = dataset.tokens_for(index);
+		let key = tokens.next().unwrap_or_default();
+		keys.haystacks.push(key);
+		keys.indices.push(index);
+		let mut owned = sink.owned.as_deref_mut();
+		if let Some(owned) = owned.as_deref_mut() {
+			owned.push(key, dataset.score_bonus(index));
 		}
-		let matches = match_list(trimmed, &haystacks, config);
-		for entry in matches {
-			if entry.score == 0 {
-				continue;
+		for token in tokens {
+			keys.tokens.push((local, token));
+			if let Some(owned) = owned.as_deref_mut() {
+				owned.push_token(token);
 			}
-			let index = offset + entry.index as usize;
-			aggregator.push(index, entry.score);
 		}
+	}
+}
 
-		if should_abort(stream_id, latest_query_id) {
-			return StreamPassResult::Aborted;
-		}
-		if !aggregator.flush_partial() {
-			return StreamPassResult::HungUp;
+/// Add a scored chunk's matches, with their dataset bonuses, to the
+/// aggregator and the sink.
+fn merge_chunk<D>(
+	dataset: &D,
+	indices: &[usize],
+	haystacks: &[&str],
+	matches: Vec<(usize, u16)>,
+	aggregator: &mut ScoreAggregator<'_>,
+	sink: &mut PassSink<'_>,
+) where
+	D: Dataset,
+{
+	for (local, score) in matches {
+		let index = indices[local];
+		let score = score.saturating_add(dataset.score_bonus(index));
+		aggregator.push_keyed(index, score, haystacks[local]);
+		if let Some(matched) = sink.matched.as_deref_mut() {
+			matched.push(index);
 		}
+	}
+}
 
-		offset = end;
+/// Positions of `query` in the key of the entry at `index`, mapped back to
+/// the characters of `original` when the key is its folded form.
+///
+/// Entries that only matched through a [token](Dataset::tokens_for) after
+/// their key have no positions, rather than highlights of a key the scorer
+/// did not choose.
+fn match_positions<D: Dataset>(
+	query: &Query,
+	config: &Config,
+	dataset: &D,
+	index: usize,
+	original: Option<&str>,
+) -> Vec<usize> {
+	let key = dataset.key_for(index);
+	if dataset.tokens_for(index).nth(1).is_some()
+		&& score_candidates(query, &[key], config).is_empty()
+	{
+		return Vec::new();
+	}
+	let positions = query.match_indices(key, config);
+	match original {
+		Some(original) => unfold_positions(original, positions),
+		None => positions,
 	}
+}
 
-	if should_abort(stream_id, latest_query_id) {
-		return StreamPassResult::Aborted;
+/// Matching options for a pass that considers every candidate, without the
+/// prefilter's typo budget.
+fn unbounded_config(query: &Query, dataset_len: usize, options: &SearchOptions) -> Config {
+	let mut config = config_for_options("", dataset_len, options);
+	config.prefilter = false;
+	config.max_typos = None;
+	if query.is_case_sensitive() {
+		prefer_matching_case(&mut config);
 	}
+	config
+}
 
-	StreamPassResult::Completed
+fn aggregator_for<'a>(
+	stream: SearchStream<'a>,
+	options: &SearchOptions,
+	positions: &'a dyn Fn(usize) -> Vec<usize>,
+) -> ScoreAggregator<'a> {
+	ScoreAggregator::with_tiebreak(stream, options.tiebreak)
+		.with_limit(options.max_results)
+		.with_cutoff(options.score_cutoff)
+		.with_positions(positions)
 }
 
+/// Cache that a pass records its complete match set into, with the
+/// generation it was read at.
+type NarrowingTarget = (NarrowingCache, u64);
+
 fn spawn_refined_search(
-	query: String,
-	haystacks: Vec<String>,
+	query: Query,
+	options: SearchOptions,
+	dataset: OwnedDataset,
 	stream: SearchStream<'_>,
-	latest_query_id: Arc<AtomicU64>,
+	cancel: CancellationToken,
+	narrowing: Option<NarrowingTarget>,
 ) {
-	if haystacks.is_empty() {
+	if dataset.is_empty() {
 		let _ = stream.send(Vec::new(), Vec::new(), true);
 		return;
 	}
 
 	let tx = stream.clone_sender();
 	let stream_id = stream.id();
+	let stream_cancel = stream.cancellation().cloned();
 	std::thread::spawn(move || {
-		let stream = SearchStream::new(&tx, stream_id);
-		let dataset = OwnedDataset::new(haystacks);
-
-		let mut config = config_for_query(&query, dataset.len());
-		config.prefilter = false;
-		config.max_typos = None;
-
-		let mut aggregator = ScoreAggregator::new(stream);
-		let outcome = stream_matches_with_config(
-			dataset,
-			&query,
-			&config,
-			&mut aggregator,
-			latest_query_id.as_ref(),
-			stream_id,
-			None,
-		);
+		let stream = match stream_cancel {
+			Some(token) => SearchStream::with_cancel(&tx, stream_id, token),
+			None => SearchStream::new(&tx, stream_id),
+		};
+		let abort = AbortCheck::new(&stream, cancel);
+		let config = unbounded_config(&query, dataset.len(), &options);
+		let pass = MatchPass {
+			rows: Rows::All(dataset.len()),
+			query: &query,
+			config: &config,
+			threads: options.threads,
+		};
 
-		if matches!(outcome, StreamPassResult::Completed)
-			&& !should_abort(stream_id, latest_query_id.as_ref())
-		{
+		let mut matched = Vec::new();
+		let sink = PassSink {
+			owned: None,
+			matched: narrowing.is_some().then_some(&mut matched),
+		};
+		let positions =
+			|index| match_positions(&query, &config, &dataset, index, dataset.original(index));
+		let mut aggregator = aggregator_for(stream, &options, &positions);
+		let outcome = stream_matches_with_config(&dataset, pass, &mut aggregator, &abort, sink);
+
+		if outcome == StreamPassResult::Completed && abort.poll().is_none() {
+			if let Some((cache, generation)) = narrowing {
+				cache.store(generation, query.clone(), matched);
+			}
 			let _ = aggregator.finish();
 		}
 	});
@@ -428,99 +1266,387 @@ fn spawn_refined_search(
 
 /// Perform fuzzy matching on a dataset, emitting batches of ranked matches to the stream.
 ///
-/// Returns `true` if streaming completed successfully, `false` if the receiver hung up.
-pub fn stream_dataset<D, F>(
+/// The query is parsed with [`Query::parse`] and every whitespace-separated
+/// term must match. Operator terms (`'exact`, `^prefix`, `suffix$`) use
+/// literal matching while the rest are fuzzy, and each match's score is the
+/// sum of its per-term scores, where `a | b` groups
+/// contribute their best-scoring alternative, plus the entry's
+/// [`Dataset::score_bonus`]. Candidates containing
+/// a `!negated` term are dropped, and empty queries or queries made only of
+/// negations stream the remaining dataset ordered by `alphabetical_key`.
+///
+/// Matching stops early once `cancel` is cancelled or superseded. Passing the
+/// latest-query-id counter as `&Arc<AtomicU64>` watches for a query newer than
+/// the stream's id; see [`CancellationToken`] for the other conversions.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_dataset<D, F, K>(
 	dataset: D,
 	query: &str,
 	stream: SearchStream<'_>,
-	latest_query_id: &Arc<AtomicU64>,
+	cancel: impl Into<CancellationToken>,
 	alphabetical_key: F,
 ) -> bool
 where
 	D: Dataset,
-	F: FnMut(usize) -> String,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
 {
-	let id = stream.id();
-	let trimmed = query.trim();
-	if trimmed.is_empty() {
-		return stream_alphabetical(dataset.len(), stream, latest_query_id, alphabetical_key);
-	}
-
-	let total = dataset.len();
-	let config = config_for_query(trimmed, total);
-	if !config.prefilter {
-		let mut aggregator = ScoreAggregator::new(stream);
-		match stream_matches_with_config(
-			dataset,
-			trimmed,
-			&config,
-			&mut aggregator,
-			latest_query_id.as_ref(),
-			id,
-			None,
-		) {
-			StreamPassResult::HungUp => return false,
-			StreamPassResult::Aborted => return true,
-			StreamPassResult::Completed => {}
-		}
-
-		return aggregator.finish();
-	}
-
-	let mut owned_keys = Vec::with_capacity(total);
-	let mut aggregator = ScoreAggregator::new(stream.clone());
-	match stream_matches_with_config(
+	stream_dataset_with_options(
 		dataset,
-		trimmed,
-		&config,
-		&mut aggregator,
-		latest_query_id.as_ref(),
-		id,
-		Some(&mut owned_keys),
-	) {
-		StreamPassResult::HungUp => return false,
-		StreamPassResult::Aborted => return true,
+		query,
+		&SearchOptions::default(),
+		stream,
+		cancel,
+		alphabetical_key,
+	)
+}
+
+/// Perform matching like [`stream_dataset`] using custom [`SearchOptions`].
+///
+/// With [`SearchOptions::threads`] above one, chunks of the dataset are
+/// scored on that many scoped threads and merged in dataset order, so the
+/// streamed batches match those of a single-threaded pass.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_dataset_with_options<D, F, K>(
+	dataset: D,
+	query: &str,
+	options: &SearchOptions,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	alphabetical_key: F,
+) -> bool
+where
+	D: Dataset,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	stream_dataset_inner(
+		dataset,
+		query,
+		options,
+		CandidateSources::default(),
+		stream,
+		cancel,
+		alphabetical_key,
+	)
+}
+
+/// Perform matching like [`stream_dataset_with_options`], reusing the match
+/// sets of earlier queries in `narrowing`.
+///
+/// When the query [narrows](Query::narrows) a query whose complete match set
+/// is cached, only that set is scored, in a single pass without the
+/// prefilter. Complete match sets are recorded in `narrowing` as passes
+/// finish. The results are identical to a full scan as long as the cache is
+/// [invalidated](NarrowingCache::invalidate) whenever the dataset changes.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_dataset_narrowed<D, F, K>(
+	dataset: D,
+	query: &str,
+	options: &SearchOptions,
+	narrowing: &NarrowingCache,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	alphabetical_key: F,
+) -> bool
+where
+	D: Dataset,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	let sources = CandidateSources {
+		narrowing: Some(narrowing),
+		..CandidateSources::default()
+	};
+	stream_dataset_inner(
+		dataset,
+		query,
+		options,
+		sources,
+		stream,
+		cancel,
+		alphabetical_key,
+	)
+}
+
+/// Perform matching like [`stream_dataset_with_options`], only scoring the
+/// rows that `index` cannot rule out.
+///
+/// When the index rejects any row, the remaining rows are scored in a
+/// single pass without the prefilter; otherwise, or when `index` was built
+/// for a dataset of another length, the regular passes run. The results are
+/// identical to a full scan as long as the index was built from the current
+/// keys.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_dataset_indexed<D, F, K>(
+	dataset: D,
+	query: &str,
+	options: &SearchOptions,
+	index: &PrefilterIndex,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	alphabetical_key: F,
+) -> bool
+where
+	D: Dataset,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	let sources = CandidateSources {
+		index: Some(index),
+		..CandidateSources::default()
+	};
+	stream_dataset_inner(
+		dataset,
+		query,
+		options,
+		sources,
+		stream,
+		cancel,
+		alphabetical_key,
+	)
+}
+
+/// Where a pass may find the subset of rows worth scoring instead of the
+/// whole dataset.
+///
+/// Sources are asked in field order and the first to rule out any row
+/// wins; without one, the regular passes run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CandidateSources<'a> {
+	/// Match sets of earlier queries that the query narrows, which also
+	/// records the match set of this query.
+	pub narrowing: Option<&'a NarrowingCache>,
+	/// Character index built up front from the dataset's keys.
+	pub index: Option<&'a PrefilterIndex>,
+	/// Character bitmaps built as queries need them.
+	pub masks: Option<&'a PrefilterCache>,
+}
+
+/// Perform matching like [`stream_dataset_with_options`], scoring only the
+/// rows that the first of `sources` able to rule out any row keeps.
+///
+/// See [`stream_dataset_narrowed`] and [`stream_dataset_indexed`] for how
+/// each source is used; bitmaps from [`PrefilterCache`] are used like an
+/// index. The results are identical to a full scan as long as every source
+/// matches the dataset's current keys.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_dataset_with_sources<D, F, K>(
+	dataset: D,
+	query: &str,
+	options: &SearchOptions,
+	sources: CandidateSources<'_>,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	alphabetical_key: F,
+) -> bool
+where
+	D: Dataset,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	stream_dataset_inner(
+		dataset,
+		query,
+		options,
+		sources,
+		stream,
+		cancel,
+		alphabetical_key,
+	)
+}
+
+fn stream_dataset_inner<D, F, K>(
+	dataset: D,
+	query: &str,
+	options: &SearchOptions,
+	sources: CandidateSources<'_>,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	alphabetical_key: F,
+) -> bool
+where
+	D: Dataset,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	let dataset = FoldedDataset::new(dataset, options.fold_diacritics);
+	let raw_query = if options.fold_diacritics {
+		fold_diacritics(query)
+	} else {
+		Cow::Borrowed(query)
+	};
+	let raw_query = raw_query.as_ref();
+	let query = Query::parse_with_options(raw_query, options);
+	if query.is_filter_only() {
+		let mut tags = Vec::new();
+		return stream_alphabetical_filtered(
+			dataset.len(),
+			options.max_results,
+			stream,
+			cancel,
+			alphabetical_key,
+			|index| {
+				let mut tokens = dataset.tokens_for(index);
+				let key = tokens.next().unwrap_or_default();
+				tags.clear();
+				tags.extend(tokens);
+				!query.excludes_entry(key, &tags)
+			},
+		);
+	}
+
+	let total = dataset.len();
+	let cancel = cancel.into();
+	let abort = AbortCheck::new(&stream, cancel.clone());
+	let narrowing = sources
+		.narrowing
+		.map(|cache| (cache.clone(), cache.generation()));
+	let candidates = narrowing
+		.as_ref()
+		.and_then(|(cache, _)| cache.candidates_for(&query))
+		.or_else(|| {
+			sources
+				.index
+				.and_then(|index| index.candidates_for(&query, total))
+		})
+		.or_else(|| {
+			sources
+				.masks
+				.and_then(|masks| masks.candidates_for(&query, &dataset))
+		});
+
+	let (rows, config) = match &candidates {
+		Some(candidates) => (
+			Rows::Subset(candidates),
+			unbounded_config(&query, candidates.len(), options),
+		),
+		None => (
+			Rows::All(total),
+			config_for_options(raw_query, total, options),
+		),
+	};
+	let pass = MatchPass {
+		rows,
+		query: &query,
+		config: &config,
+		threads: options.threads,
+	};
+	if !config.prefilter || !query.has_fuzzy_terms() {
+		let mut matched = Vec::new();
+		let sink = PassSink {
+			owned: None,
+			matched: narrowing.is_some().then_some(&mut matched),
+		};
+		let positions =
+			|index| match_positions(&query, &config, &dataset, index, dataset.original(index));
+		let mut aggregator = aggregator_for(stream, options, &positions);
+		match stream_matches_with_config(&dataset, pass, &mut aggregator, &abort, sink) {
+			StreamPassResult::HungUp | StreamPassResult::Cancelled => return false,
+			StreamPassResult::Aborted => return true,
+			StreamPassResult::Completed => {}
+		}
+
+		if let Some((cache, generation)) = narrowing {
+			cache.store(generation, query.clone(), matched);
+		}
+		return aggregator.finish();
+	}
+
+	let mut owned = OwnedDataset::with_capacity(total);
+	let positions =
+		|index| match_positions(&query, &config, &dataset, index, dataset.original(index));
+	let mut aggregator = aggregator_for(stream.clone(), options, &positions);
+	let sink = PassSink {
+		owned: Some(&mut owned),
+		matched: None,
+	};
+	match stream_matches_with_config(&dataset, pass, &mut aggregator, &abort, sink) {
+		StreamPassResult::HungUp | StreamPassResult::Cancelled => return false,
+		StreamPassResult::Aborted => return true,
 		StreamPassResult::Completed => {}
 	}
 
 	if !aggregator.finish_with_completion(false) {
 		return false;
 	}
+	owned.originals = dataset.originals();
 
-	spawn_refined_search(
-		trimmed.to_owned(),
-		owned_keys,
-		stream,
-		Arc::clone(latest_query_id),
-	);
+	spawn_refined_search(query, options.clone(), owned, stream, cancel, narrowing);
 	true
 }
 
 /// Stream results in alphabetical order when no query is provided.
 ///
-/// Returns `true` if streaming completed successfully, `false` if the receiver hung up.
-pub fn stream_alphabetical<F>(
+/// The order follows the keys returned by `key_for_index`, so callers can
+/// substitute any [`Ord`] key for the path.
+///
+/// Returns `true` if streaming completed successfully or was superseded by a
+/// newer query, and `false` if the receiver hung up or `cancel` or the
+/// stream's [`CancellationToken`] was cancelled.
+pub fn stream_alphabetical<F, K>(
+	total: usize,
+	stream: SearchStream<'_>,
+	cancel: impl Into<CancellationToken>,
+	key_for_index: F,
+) -> bool
+where
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+{
+	stream_alphabetical_filtered(
+		total,
+		MAX_RENDERED_RESULTS,
+		stream,
+		cancel,
+		key_for_index,
+		|_| true,
+	)
+}
+
+fn stream_alphabetical_filtered<F, K, P>(
 	total: usize,
+	limit: usize,
 	stream: SearchStream<'_>,
-	latest_query_id: &Arc<AtomicU64>,
+	cancel: impl Into<CancellationToken>,
 	key_for_index: F,
+	mut include: P,
 ) -> bool
 where
-	F: FnMut(usize) -> String,
+	F: FnMut(usize) -> K,
+	K: Ord + Clone,
+	P: FnMut(usize) -> bool,
 {
-	let id = stream.id();
-	let mut collector = AlphabeticalCollector::new(stream, total, key_for_index);
+	let abort = AbortCheck::new(&stream, cancel.into());
+	let mut collector = AlphabeticalCollector::new(stream, total, key_for_index).with_limit(limit);
 
 	let mut processed = 0;
 	for index in 0..total {
-		if should_abort(id, latest_query_id.as_ref()) {
-			return true;
+		if let Some(result) = abort.poll() {
+			return result == StreamPassResult::Aborted;
+		}
+		if include(index) {
+			collector.insert(index);
 		}
-		collector.insert(index);
 		processed += 1;
 		if processed % EMPTY_QUERY_BATCH == 0 {
-			if should_abort(id, latest_query_id.as_ref()) {
-				return true;
+			if let Some(result) = abort.poll() {
+				return result == StreamPassResult::Aborted;
 			}
 			if !collector.flush_partial() {
 				return false;
@@ -528,8 +1654,8 @@ where
 		}
 	}
 
-	if should_abort(id, latest_query_id.as_ref()) {
-		return true;
+	if let Some(result) = abort.poll() {
+		return result == StreamPassResult::Aborted;
 	}
 
 	collector.finish()
@@ -542,6 +1668,8 @@ pub fn should_abort(id: u64, latest_query_id: &AtomicU64) -> bool {
 
 #[cfg(test)]
 mod tests {
+	use std::sync::Arc;
+
 	use super::*;
 	use crate::search::SearchView;
 
@@ -552,6 +1680,7 @@ mod tests {
 		indices: Vec<usize>,
 		scores: Vec<u16>,
 		completions: Vec<bool>,
+		timings: usize,
 	}
 
 	impl SearchView for StubView {
@@ -568,6 +1697,10 @@ mod tests {
 		fn record_completion(&mut self, complete: bool) {
 			self.completions.push(complete);
 		}
+
+		fn record_elapsed(&mut self, _elapsed: std::time::Duration) {
+			self.timings += 1;
+		}
 	}
 
 	impl Dataset for TestDataset {
@@ -607,16 +1740,13 @@ mod tests {
 
 		let envelope = rx.recv().unwrap();
 		assert!(envelope.complete);
-		let mut view = StubView {
-			indices: Vec::new(),
-			scores: Vec::new(),
-			completions: Vec::new(),
-		};
+		let mut view = StubView::default();
 		envelope.dispatch(&mut view);
 
 		assert_eq!(view.indices, vec![1, 0]); // alphabetical order
 		assert_eq!(view.scores, vec![0, 0]);
 		assert_eq!(view.completions, vec![true]);
+		assert_eq!(view.timings, 1, "the completing batch reports its time");
 	}
 
 	#[test]
@@ -658,5 +1788,1294 @@ mod tests {
 			view.completions.iter().any(|complete| *complete),
 			"refined pass should eventually mark the stream complete"
 		);
+		assert_eq!(view.timings, 1, "only the completing batch is timed");
+	}
+
+	fn collect_complete(dataset: &TestDataset, query: &str) -> StubView {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset(dataset, query, stream, &latest, |idx| {
+			dataset.0[idx].clone()
+		});
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn quoted_terms_require_substring_matches() {
+		let dataset = TestDataset(vec![
+			"src/lib.rs".into(),
+			"src/main.rs".into(),
+			"docs/library.md".into(),
+		]);
+
+		let view = collect_complete(&dataset, "'lib");
+		let mut indices = view.indices.clone();
+		indices.sort_unstable();
+		assert_eq!(indices, vec![0, 2]);
+		assert!(view.scores.iter().all(|score| *score > 0));
+
+		let view = collect_complete(&dataset, "src 'main");
+		assert_eq!(view.indices, vec![1]);
+	}
+
+	#[test]
+	fn anchored_and_exact_terms_filter_candidates() {
+		let dataset = TestDataset(vec![
+			"src/lib.rs".into(),
+			"src/main.rs".into(),
+			"docs/exact.md".into(),
+			"tests/src.rs".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert_eq!(sorted("^src"), vec![0, 1]);
+		assert_eq!(sorted(".rs$"), vec![0, 1, 3]);
+		assert_eq!(sorted("'exact"), vec![2]);
+		assert_eq!(sorted("^src .rs$"), vec![0, 1]);
+		assert_eq!(
+			collect_complete(&dataset, "^src main").indices.first(),
+			Some(&1)
+		);
+	}
+
+	#[test]
+	fn negations_drop_candidates_from_multi_term_queries() {
+		let dataset = TestDataset(vec![
+			"src/config.rs".into(),
+			"tests/config_test.rs".into(),
+			"docs/config.md".into(),
+			"src/main.rs".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert!(!sorted("config !test").contains(&1));
+		assert_eq!(sorted("'config !test !.md"), vec![0]);
+		assert_eq!(sorted(".rs$ !test"), vec![0, 3]);
+	}
+
+	#[test]
+	fn extension_filters_restrict_candidates_before_scoring() {
+		let dataset = TestDataset(vec![
+			"src/main.rs".into(),
+			"docs/main.md".into(),
+			"Cargo.toml".into(),
+			"main.rs.bak".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert_eq!(sorted("main ext:rs"), vec![0]);
+		assert_eq!(sorted("ext:toml,md"), vec![1, 2]);
+		// Filters stand alone, so a `|` next to one joins nothing.
+		assert_eq!(sorted("ext:md | main"), vec![1]);
+		assert!(sorted("ext:rs !src").is_empty());
+	}
+
+	#[test]
+	fn path_prefixes_restrict_fuzzy_matching_to_a_directory() {
+		let dataset = TestDataset(vec![
+			"src/parser.rs".into(),
+			"tests/parser.rs".into(),
+			"docs/parser.md".into(),
+			"src/bin/parser.rs".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert_eq!(sorted("parser"), vec![0, 1, 2, 3]);
+		assert_eq!(sorted("src/ parser"), vec![0, 3]);
+		assert_eq!(sorted("./src/ parser"), vec![0, 3]);
+		assert_eq!(sorted("src/bin/"), vec![3]);
+		assert_eq!(sorted("tests/ !parser"), Vec::<usize>::new());
+		let view = collect_complete(&dataset, "lib/ parser");
+		assert!(view.indices.is_empty());
+		assert_eq!(view.completions.last(), Some(&true));
+	}
+
+	#[test]
+	fn extension_filters_apply_to_prefiltered_passes() {
+		let mut keys: Vec<String> = (0..PREFILTER_ENABLE_THRESHOLD)
+			.map(|i| format!("pkg/module_{i}.md"))
+			.collect();
+		keys.push("pkg/module_7.rs".into());
+		let dataset = TestDataset(keys);
+
+		let view = collect_complete(&dataset, "module ext:rs");
+		assert_eq!(view.indices, vec![PREFILTER_ENABLE_THRESHOLD]);
+		assert_eq!(view.completions.last(), Some(&true));
+	}
+
+	#[test]
+	fn negation_only_queries_stream_the_remaining_dataset() {
+		let dataset = TestDataset(vec!["b.rs".into(), "a_test.rs".into(), "a.rs".into()]);
+
+		let view = collect_complete(&dataset, "!test");
+		assert_eq!(view.indices, vec![2, 0]);
+		assert_eq!(view.completions.last(), Some(&true));
+
+		let view = collect_complete(&dataset, "   ");
+		assert_eq!(view.indices, vec![2, 1, 0]);
+	}
+
+	fn ranked_indices(tiebreak: Tiebreak, candidates: &[(&str, u16)]) -> Vec<usize> {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let mut aggregator = ScoreAggregator::with_tiebreak(SearchStream::new(&tx, 1), tiebreak);
+		for (index, (key, score)) in candidates.iter().enumerate() {
+			aggregator.push_keyed(index, *score, key);
+		}
+		assert!(aggregator.finish());
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view.indices
+	}
+
+	#[test]
+	fn tiebreaks_order_equal_scores_deterministically() {
+		let candidates = [
+			("src/zeta.rs", 10),
+			("src/app/mod.rs", 10),
+			("src/alpha.rs", 10),
+			("lib.rs", 5),
+			("a.rs", 10),
+		];
+
+		assert_eq!(
+			ranked_indices(Tiebreak::PathLength, &candidates),
+			vec![4, 0, 2, 1, 3]
+		);
+		assert_eq!(
+			ranked_indices(Tiebreak::Lexical, &candidates),
+			vec![4, 2, 1, 0, 3]
+		);
+		assert_eq!(
+			ranked_indices(Tiebreak::None, &candidates),
+			vec![0, 1, 2, 4, 3]
+		);
+	}
+
+	#[test]
+	fn natural_tiebreak_compares_digit_runs_numerically() {
+		let candidates = [
+			("file10.rs", 10),
+			("file2.rs", 10),
+			("file02.rs", 10),
+			("file1.rs", 10),
+			("File3.rs", 10),
+		];
+
+		assert_eq!(
+			ranked_indices(Tiebreak::Natural, &candidates),
+			vec![4, 3, 2, 1, 0]
+		);
+		assert_eq!(
+			ranked_indices(Tiebreak::Lexical, &candidates),
+			vec![4, 2, 3, 0, 1]
+		);
+	}
+
+	#[test]
+	fn natural_tiebreak_ignores_arrival_order() {
+		let names = [
+			"dir/page9.md",
+			"dir/page10.md",
+			"dir/page1.md",
+			"dir/page100.md",
+		];
+		let sorted = |candidates: Vec<(&'static str, u16)>| {
+			let indices = ranked_indices(Tiebreak::Natural, &candidates);
+			indices
+				.into_iter()
+				.map(|index| candidates[index].0)
+				.collect::<Vec<_>>()
+		};
+
+		let expected = vec![
+			"dir/page1.md",
+			"dir/page9.md",
+			"dir/page10.md",
+			"dir/page100.md",
+		];
+		assert_eq!(
+			sorted(names.iter().map(|name| (*name, 7)).collect()),
+			expected
+		);
+		assert_eq!(
+			sorted(names.iter().rev().map(|name| (*name, 7)).collect()),
+			expected
+		);
+	}
+
+	#[test]
+	fn dataset_streams_apply_the_configured_tiebreak() {
+		use std::sync::mpsc::channel;
+
+		let dataset = TestDataset(vec!["b/x.rs".into(), "x.rs".into(), "a/x.rs".into()]);
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let options = SearchOptions::default().with_tiebreak(Tiebreak::PathLength);
+		stream_dataset_with_options(
+			&dataset,
+			"'x.rs",
+			&options,
+			SearchStream::new(&tx, 1),
+			&latest,
+			|idx| dataset.0[idx].clone(),
+		);
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		assert_eq!(view.indices, vec![1, 2, 0]);
+	}
+
+	#[test]
+	fn or_groups_match_any_alternative() {
+		let dataset = TestDataset(vec![
+			"src/core.go".into(),
+			"src/app.rb".into(),
+			"src/main.py".into(),
+			"docs/core.md".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert_eq!(sorted(".go$ | .rb$"), vec![0, 1]);
+		assert_eq!(sorted("^src .py$ | 'core"), vec![0, 2]);
+		assert_eq!(sorted("'core | 'main !docs"), vec![0, 2]);
+	}
+
+	#[test]
+	fn or_groups_rank_by_the_best_alternative() {
+		let dataset = TestDataset(vec!["library/x".into(), "lib".into()]);
+		let view = collect_complete(&dataset, "'zzz | ^lib$ | 'lib");
+		assert_eq!(view.indices, vec![1, 0]);
+		assert!(view.scores[0] > view.scores[1]);
+	}
+
+	#[test]
+	fn multi_term_queries_rank_by_combined_term_scores() {
+		let dataset = TestDataset(vec![
+			"library/sources.txt".into(),
+			"docs/readme.md".into(),
+			"src/lib.rs".into(),
+		]);
+
+		let view = collect_complete(&dataset, "src lib");
+		assert_eq!(view.indices.first(), Some(&2));
+	}
+
+	#[test]
+	fn multi_term_queries_require_every_term() {
+		let mut keys: Vec<String> = (0..PREFILTER_ENABLE_THRESHOLD)
+			.map(|i| format!("src/noise-{i}.txt"))
+			.collect();
+		keys.push("src/lib.rs".into());
+		let dataset = TestDataset(keys);
+
+		let view = collect_complete(&dataset, "'src ^src 'lib");
+		assert_eq!(view.indices, vec![PREFILTER_ENABLE_THRESHOLD]);
+	}
+
+	#[test]
+	fn uppercase_queries_match_case_sensitively() {
+		let dataset = TestDataset(vec![
+			"src/Lib.rs".into(),
+			"src/lib.rs".into(),
+			"LICENSE".into(),
+		]);
+		let sorted = |query: &str| {
+			let mut indices = collect_complete(&dataset, query).indices;
+			indices.sort_unstable();
+			indices
+		};
+
+		assert_eq!(sorted("Lib"), vec![0]);
+		assert_eq!(sorted("'Lib"), vec![0]);
+		assert!(sorted("lib").starts_with(&[0, 1]));
+	}
+
+	/// Dataset that trips a cancellation token when `trip_at` is scored,
+	/// recording how many batches had been sent by then.
+	struct TrippingDataset<'a> {
+		keys: Vec<String>,
+		trip_at: usize,
+		token: CancellationToken,
+		rx: &'a std::sync::mpsc::Receiver<crate::search::SearchResult>,
+		sent_before_cancel: std::cell::Cell<usize>,
+	}
+
+	impl Dataset for TrippingDataset<'_> {
+		fn len(&self) -> usize {
+			self.keys.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			if index == self.trip_at {
+				self.token.cancel();
+				self.sent_before_cancel.set(self.rx.try_iter().count());
+			}
+			&self.keys[index]
+		}
+	}
+
+	#[test]
+	fn cancelled_streams_stop_sending_batches() {
+		use std::sync::mpsc::channel;
+
+		for len in [MATCH_CHUNK_SIZE * 4, PREFILTER_ENABLE_THRESHOLD * 4] {
+			let (tx, rx) = channel();
+			let token = CancellationToken::new();
+			let dataset = TrippingDataset {
+				keys: (0..len).map(|i| format!("file-{i}")).collect(),
+				trip_at: MATCH_CHUNK_SIZE * 2 + 10,
+				token: token.clone(),
+				rx: &rx,
+				sent_before_cancel: std::cell::Cell::new(0),
+			};
+			let latest = Arc::new(AtomicU64::new(1));
+			let stream = SearchStream::with_cancel(&tx, 1, token.clone());
+
+			let completed = stream_dataset(&dataset, "file", stream, &latest, |idx| {
+				dataset.keys[idx].clone()
+			});
+
+			assert!(!completed, "cancellation is reported as an early stop");
+			assert!(token.is_cancelled());
+			assert_eq!(
+				dataset.sent_before_cancel.get(),
+				2,
+				"batches before the cancel"
+			);
+			assert_eq!(rx.try_iter().count(), 0, "no batches after the cancel");
+		}
+	}
+
+	#[test]
+	fn cancelled_token_stops_alphabetical_streams() {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let token = CancellationToken::new();
+		token.cancel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::with_cancel(&tx, 1, token);
+
+		assert!(!stream_alphabetical(10, stream, &latest, |idx| idx.to_string()));
+		assert_eq!(rx.try_iter().count(), 0);
+	}
+
+	#[test]
+	fn tokens_passed_to_streams_stop_them() {
+		use std::sync::mpsc::channel;
+
+		let dataset = TestDataset((0..10).map(|i| format!("file-{i}")).collect());
+		let (tx, rx) = channel();
+		let token = CancellationToken::new();
+		token.cancel();
+
+		let stream = SearchStream::new(&tx, 1);
+		assert!(!stream_dataset(&dataset, "file", stream, &token, |idx| idx));
+		assert_eq!(rx.try_iter().count(), 0);
+	}
+
+	#[test]
+	fn superseded_query_tokens_end_streams_quietly() {
+		use std::sync::mpsc::channel;
+
+		let dataset = TestDataset((0..10).map(|i| format!("file-{i}")).collect());
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let token = CancellationToken::from((&latest, 1));
+		assert!(!token.is_cancelled());
+
+		latest.store(2, AtomicOrdering::Release);
+		assert!(token.is_superseded() && token.is_cancelled());
+		let stream = SearchStream::new(&tx, 1);
+		assert!(stream_dataset(&dataset, "file", stream, &token, |idx| idx));
+		let stream = SearchStream::new(&tx, 1);
+		assert!(stream_alphabetical(10, stream, &latest, |idx| idx));
+		assert_eq!(rx.try_iter().count(), 0);
+
+		let stream = SearchStream::new(&tx, 2);
+		assert!(stream_alphabetical(10, stream, &latest, |idx| idx));
+		assert_eq!(rx.try_iter().count(), 1, "the current query streams");
+	}
+
+	#[test]
+	fn final_components_ignore_trailing_separators() {
+		assert_eq!(final_component("src/main.rs"), "main.rs");
+		assert_eq!(final_component("main.rs"), "main.rs");
+		assert_eq!(final_component("src/main/"), "main");
+		assert_eq!(final_component("src//"), "src");
+		assert_eq!(final_component("/"), "");
+	}
+
+	#[test]
+	fn basename_matches_outrank_directory_matches() {
+		let dataset = TestDataset(vec![
+			"main_helpers/util.rs".into(),
+			"src/main.rs".into(),
+			"src/main/".into(),
+		]);
+
+		let view = collect_complete(&dataset, "main");
+		assert_eq!(view.indices.len(), 3);
+		assert_eq!(view.indices[2], 0, "directory-only match ranks last");
+	}
+
+	#[test]
+	fn basename_bonus_applies_to_keys_without_separators() {
+		let query = Query::parse("main");
+		let term = query.fuzzy_terms().next().expect("fuzzy term");
+		let config = config_for_query("main", 4);
+		let haystacks = ["main.rs", "x/main.rs", "main/", "mian.rs"];
+
+		let mut plain = vec![None; haystacks.len()];
+		for entry in match_list(&term.text, &haystacks, &config) {
+			plain[entry.index as usize] = Some(entry.score);
+		}
+		let boosted = fuzzy_scores(term, &haystacks, &config);
+
+		for index in 0..3 {
+			let expected = plain[index].map(|score| score + BASENAME_MATCH_BONUS);
+			assert_eq!(boosted[index], expected, "{}", haystacks[index]);
+		}
+		assert_eq!(boosted[3], plain[3], "typo matches are not boosted");
+	}
+
+	struct BoostedDataset {
+		keys: Vec<String>,
+		boosted: usize,
+	}
+
+	impl Dataset for BoostedDataset {
+		fn len(&self) -> usize {
+			self.keys.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			&self.keys[index]
+		}
+
+		fn score_bonus(&self, index: usize) -> u16 {
+			if index == self.boosted { 8 } else { 0 }
+		}
+	}
+
+	fn first_complete(dataset: &BoostedDataset, query: &str) -> StubView {
+		use std::sync::mpsc::channel;
+		use std::time::Duration;
+
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset(dataset, query, stream, &latest, |idx| {
+			dataset.keys[idx].clone()
+		});
+
+		let mut view = StubView::default();
+		while view.completions.last() != Some(&true) {
+			rx.recv_timeout(Duration::from_secs(2))
+				.expect("complete batch")
+				.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn score_bonuses_break_otherwise_equal_matches() {
+		let dataset = BoostedDataset {
+			keys: vec!["a/main.rs".into(), "b/main.rs".into()],
+			boosted: 1,
+		};
+
+		let view = first_complete(&dataset, "main");
+		assert_eq!(view.indices, vec![1, 0]);
+		assert_eq!(view.scores[0], view.scores[1] + 8);
+
+		let view = first_complete(&dataset, "");
+		assert_eq!(view.indices, vec![0, 1], "empty queries ignore bonuses");
+	}
+
+	#[test]
+	fn score_bonuses_survive_the_refined_pass() {
+		let boosted = PREFILTER_ENABLE_THRESHOLD / 2;
+		let dataset = BoostedDataset {
+			keys: vec!["src/main.rs".into(); PREFILTER_ENABLE_THRESHOLD + 1],
+			boosted,
+		};
+
+		let view = first_complete(&dataset, "main");
+		assert_eq!(view.indices[0], boosted);
+	}
+
+	fn collect_narrowed(dataset: &TestDataset, query: &str, cache: &NarrowingCache) -> StubView {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset_narrowed(
+			dataset,
+			query,
+			&SearchOptions::default(),
+			cache,
+			stream,
+			&latest,
+			|idx| dataset.0[idx].clone(),
+		);
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn narrowed_queries_match_a_full_scan() {
+		for len in [40, PREFILTER_ENABLE_THRESHOLD + 40] {
+			let dataset = TestDataset(
+				(0..len)
+					.map(|i| format!("src/module_{}/file_{i}.rs", i % 7))
+					.chain(["docs/main.md".into(), "src/main.rs".into()])
+					.collect(),
+			);
+			let cache = NarrowingCache::new();
+			for query in [
+				"src",
+				"src m",
+				"src ma",
+				"src main",
+				"srcm",
+				"'mod",
+				"'module_3",
+				"'module_3 !file_1",
+				"!file_1",
+				"^src",
+				"^src/main",
+			] {
+				let narrowed = collect_narrowed(&dataset, query, &cache);
+				let full = collect_complete(&dataset, query);
+				assert_eq!(narrowed.indices, full.indices, "{query:?} over {len}");
+				assert_eq!(narrowed.scores, full.scores, "{query:?} over {len}");
+			}
+			assert!(
+				cache
+					.candidates_for(&Query::parse("^src/main x"))
+					.is_some_and(|candidates| candidates.len() == 1),
+				"refined passes are cached too"
+			);
+		}
+	}
+
+	#[test]
+	fn invalidated_caches_do_not_hide_new_rows() {
+		let cache = NarrowingCache::new();
+		let mut dataset = TestDataset(vec!["src/lib.rs".into(), "docs/guide.md".into()]);
+		assert_eq!(collect_narrowed(&dataset, "'src", &cache).indices, [0]);
+
+		dataset.0.push("src/main.rs".into());
+		cache.invalidate();
+		let view = collect_narrowed(&dataset, "'src 'rs", &cache);
+		assert_eq!(view.indices.len(), 2);
+	}
+
+	fn collect_indexed(
+		dataset: &TestDataset,
+		query: &str,
+		options: &SearchOptions,
+		index: &PrefilterIndex,
+	) -> StubView {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset_indexed(dataset, query, options, index, stream, &latest, |idx| {
+			dataset.0[idx].clone()
+		});
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn indexed_queries_match_a_full_scan() {
+		for len in [40, PREFILTER_ENABLE_THRESHOLD + 40] {
+			let dataset = TestDataset(
+				(0..len)
+					.map(|i| match i % 5 {
+						0 => format!("src/Módulo_{}/Résumé_{i}.md", i % 7),
+						1 => format!("docs/İstanbul/straße_{i}.txt"),
+						2 => format!("lib/ÆON/{i}.RS"),
+						_ => format!("src/module_{}/file_{i}.rs", i % 7),
+					})
+					.chain(["docs/main.md".into(), "src/main.rs".into(), "xyz".into()])
+					.collect(),
+			);
+			let index = PrefilterIndex::build(&dataset);
+			for fold in [false, true] {
+				let options = SearchOptions::default().with_fold_diacritics(fold);
+				for query in [
+					"src",
+					"main",
+					"xq",
+					"Main",
+					"'modu",
+					"'résumé",
+					"'RESUME",
+					"'ße !file_1",
+					"^lib/æon",
+					"rs$",
+					"ext:md",
+					"docs/ stra",
+					"'aeon | 'module_3",
+					"é",
+					"İst",
+				] {
+					let indexed = collect_indexed(&dataset, query, &options, &index);
+					let full = collect_with_options(&dataset, query, &options);
+					let context = format!("{query:?} over {len}, folding {fold}");
+					assert_eq!(indexed.indices, full.indices, "{context}");
+					assert_eq!(indexed.scores, full.scores, "{context}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn cached_prefilter_bitmaps_match_a_full_scan_while_typing() {
+		for len in [40, PREFILTER_ENABLE_THRESHOLD + 40] {
+			let dataset = TestDataset(
+				(0..len)
+					.map(|i| match i % 4 {
+						0 => format!("src/Módulo_{}/Résumé_{i}.md", i % 7),
+						1 => format!("lib/ÆON/{i}.RS"),
+						_ => format!("src/module_{}/file_{i}.rs", i % 7),
+					})
+					.chain(["docs/main.md".into(), "src/main.rs".into()])
+					.collect(),
+			);
+			let cache = PrefilterCache::new();
+			let options = SearchOptions::default().with_fold_diacritics(true);
+			let sources = CandidateSources {
+				masks: Some(&cache),
+				..CandidateSources::default()
+			};
+			let mut typed = String::new();
+			let mut queries = Vec::new();
+			for ch in "'módulo_3 F".chars() {
+				typed.push(ch);
+				queries.push(typed.clone());
+			}
+			while typed.pop().is_some() {
+				queries.push(typed.clone());
+			}
+			for query in queries.iter().chain(&["ae".into(), "aeZ".into()]) {
+				let (tx, rx) = std::sync::mpsc::channel();
+				let latest = Arc::new(AtomicU64::new(1));
+				let stream = SearchStream::new(&tx, 1);
+				stream_dataset_with_sources(
+					&dataset,
+					query,
+					&options,
+					sources,
+					stream,
+					&latest,
+					|idx| dataset.0[idx].clone(),
+				);
+				drop(tx);
+				let mut cached = StubView::default();
+				for envelope in rx {
+					envelope.dispatch(&mut cached);
+				}
+
+				let full = collect_with_options(&dataset, query, &options);
+				assert_eq!(cached.indices, full.indices, "{query:?} over {len}");
+				assert_eq!(cached.scores, full.scores, "{query:?} over {len}");
+			}
+			assert!(!cache.is_empty());
+		}
+	}
+
+	#[test]
+	fn indexes_only_reject_rows_that_cannot_match() {
+		let dataset = TestDataset(vec![
+			"src/lib.rs".into(),
+			"docs/guide.md".into(),
+			"build.zig".into(),
+		]);
+		let index = PrefilterIndex::build(&dataset);
+		let query = Query::parse_with_options("'lib", &SearchOptions::default());
+		assert_eq!(index.candidates_for(&query, 3).as_deref(), Some(&[0][..]));
+
+		let view = collect_indexed(&dataset, "'lib", &SearchOptions::default(), &index);
+		assert_eq!(view.indices, [0]);
+
+		let stale = PrefilterIndex::build(TestDataset(vec!["src/lib.rs".into()]));
+		let view = collect_indexed(&dataset, "'.", &SearchOptions::default(), &stale);
+		assert_eq!(
+			view.indices.len(),
+			3,
+			"indexes of other datasets are ignored"
+		);
+	}
+
+	fn collect_with_options(
+		dataset: &TestDataset,
+		query: &str,
+		options: &SearchOptions,
+	) -> StubView {
+		use std::sync::mpsc::channel;
+
+		let (tx, rx) = channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset_with_options(dataset, query, options, stream, &latest, |idx| {
+			dataset.0[idx].clone()
+		});
+		drop(tx);
+
+		let mut view = StubView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view
+	}
+
+	#[test]
+	fn parallel_passes_match_a_sequential_scan() {
+		let options = SearchOptions::default().with_threads(4);
+		for len in [
+			MATCH_CHUNK_SIZE * 3 + 7,
+			PREFILTER_ENABLE_THRESHOLD * 5 + 37,
+		] {
+			let dataset = TestDataset(
+				(0..len)
+					.map(|i| format!("src/module_{}/file_{i}.rs", i % 7))
+					.collect(),
+			);
+			for query in ["file", "src mod", "'module_3 !file_1", "mdl 12", "zzz"] {
+				let parallel = collect_with_options(&dataset, query, &options);
+				let sequential = collect_complete(&dataset, query);
+				assert_eq!(parallel.indices, sequential.indices, "{query:?} over {len}");
+				assert_eq!(parallel.scores, sequential.scores, "{query:?} over {len}");
+				assert_eq!(parallel.completions.last(), Some(&true));
+			}
+		}
+	}
+
+	/// Parity at a realistic size; slow in debug builds, so run it with
+	/// `cargo test -- --ignored`. `benches/parallel_scoring.rs` times it.
+	#[test]
+	#[ignore = "scores 100k rows twice per query"]
+	fn parallel_scoring_matches_sequential_ranking_on_a_large_dataset() {
+		let dataset = TestDataset(
+			(0..100_000)
+				.map(|i| format!("pkg_{}/src/module_{}/item_{i}.rs", i % 97, i % 13))
+				.collect(),
+		);
+		let sequential = SearchOptions::default();
+		let parallel = SearchOptions::default().with_threads(4);
+		for query in ["md12 rs", "'module_7 !item_9"] {
+			let single = collect_with_options(&dataset, query, &sequential);
+			let fanned = collect_with_options(&dataset, query, &parallel);
+
+			assert!(!single.indices.is_empty(), "{query:?} matches something");
+			assert_eq!(fanned.indices, single.indices, "{query:?}");
+			assert_eq!(fanned.scores, single.scores, "{query:?}");
+			assert_eq!(fanned.completions.last(), Some(&true));
+		}
+	}
+
+	/// Dataset that supersedes the running query when `supersede_at` is read,
+	/// remembering the furthest row read.
+	struct SupersedingDataset {
+		keys: Vec<String>,
+		supersede_at: usize,
+		latest: Arc<AtomicU64>,
+		furthest_read: std::cell::Cell<usize>,
+	}
+
+	impl Dataset for SupersedingDataset {
+		fn len(&self) -> usize {
+			self.keys.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			if index == self.supersede_at {
+				self.latest.store(2, AtomicOrdering::Release);
+			}
+			self.furthest_read.set(self.furthest_read.get().max(index));
+			&self.keys[index]
+		}
+	}
+
+	#[test]
+	fn superseded_parallel_passes_stop_promptly() {
+		use std::sync::mpsc::channel;
+
+		let threads = 4;
+		let len = MATCH_CHUNK_SIZE * 64;
+		let dataset = SupersedingDataset {
+			keys: (0..len).map(|i| format!("file-{i}")).collect(),
+			supersede_at: MATCH_CHUNK_SIZE * 4,
+			latest: Arc::new(AtomicU64::new(1)),
+			furthest_read: std::cell::Cell::new(0),
+		};
+		let (tx, rx) = channel();
+		let stream = SearchStream::new(&tx, 1);
+		let options = SearchOptions::default().with_threads(threads);
+
+		let completed = stream_dataset_with_options(
+			&dataset,
+			"file",
+			&options,
+			stream,
+			&dataset.latest,
+			|idx| dataset.keys[idx].clone(),
+		);
+		drop(tx);
+
+		assert!(completed, "superseded passes are not failures");
+		assert!(rx.iter().all(|envelope| !envelope.complete));
+		let window = threads * CHUNKS_IN_FLIGHT_PER_WORKER * MATCH_CHUNK_SIZE;
+		assert!(dataset.furthest_read.get() < dataset.supersede_at + window);
+	}
+
+	/// View that keeps every batch it receives.
+	#[derive(Default)]
+	struct BatchView(Vec<MatchBatch>);
+
+	impl SearchView for BatchView {
+		fn replace_matches(&mut self, _indices: Vec<usize>, _scores: Vec<u16>) {
+			unreachable!("batches arrive through the V2 view");
+		}
+
+		fn clear_matches(&mut self) {}
+
+		fn record_completion(&mut self, _complete: bool) {}
+
+		fn as_v2(&mut self) -> Option<&mut dyn crate::search::SearchViewV2> {
+			Some(self)
+		}
+	}
+
+	impl crate::search::SearchViewV2 for BatchView {
+		fn replace_matches_v2(&mut self, batch: MatchBatch) {
+			self.0.push(batch);
+		}
+	}
+
+	fn received_batches(
+		dataset: &TestDataset,
+		query: &str,
+		options: &SearchOptions,
+	) -> Vec<MatchBatch> {
+		let (tx, rx) = mpsc::channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset_with_options(dataset, query, options, stream, &latest, |idx| idx);
+		drop(tx);
+
+		let mut view = BatchView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		view.0
+	}
+
+	#[test]
+	fn streamed_results_honor_a_custom_cap() {
+		let total = MAX_RENDERED_RESULTS + 500;
+		let dataset = TestDataset((0..total).map(|i| format!("dir/file{i}.rs")).collect());
+		// Zero is clamped to one; the largest cap keeps every row.
+		for (max_results, expected) in [(0, 1), (10, 10), (total * 2, total)] {
+			for threads in [1, 4] {
+				let options = SearchOptions::default()
+					.with_max_results(max_results)
+					.with_threads(threads);
+				for query in ["", "rs", "'file", "!nothing"] {
+					let batches = received_batches(&dataset, query, &options);
+					let last = batches.last().expect("final batch");
+					assert_eq!(
+						last.indices.len(),
+						expected,
+						"{query:?} with cap {max_results} on {threads} threads"
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn score_cutoffs_hide_weak_matches_but_not_the_listing() {
+		let dataset = TestDataset(vec![
+			"src/main.rs".into(),
+			"docs/manual.md".into(),
+			"tests/mock_amazon_index.txt".into(),
+			"notes.txt".into(),
+		]);
+		let scores = |options: &SearchOptions, query: &str| {
+			// A query without matches clears the view instead of sending a batch.
+			let batches = received_batches(&dataset, query, options);
+			batches
+				.last()
+				.map_or_else(Vec::new, |batch| batch.scores.clone())
+		};
+		let all = scores(&SearchOptions::default(), "main");
+		assert!(all.len() >= 2, "{all:?}");
+		let best = all[0];
+
+		let relative = SearchOptions::default().with_score_cutoff(ScoreCutoff::Percent(100));
+		assert!(scores(&relative, "main").iter().all(|&score| score == best));
+
+		let absolute = SearchOptions::default().with_score_cutoff(ScoreCutoff::Absolute(best + 1));
+		assert!(scores(&absolute, "main").is_empty());
+
+		for options in [relative, absolute] {
+			let listing = received_batches(&dataset, "", &options);
+			assert_eq!(listing.last().expect("final batch").indices.len(), 4);
+		}
+	}
+
+	#[test]
+	fn scored_batches_carry_the_scorers_match_positions() {
+		let dataset = TestDataset(vec![
+			"docs/manual.md".into(),
+			"src/main.rs".into(),
+			"lib.rs".into(),
+			"src/domain.rs".into(),
+		]);
+		let options = SearchOptions::default().with_max_results(2);
+		let batches = received_batches(&dataset, "main", &options);
+		let last = batches.last().expect("final batch");
+		let positions = last.positions.as_ref().expect("positions");
+		assert_eq!(last.indices.len(), 2);
+		assert_eq!(positions.len(), 2, "only emitted rows carry positions");
+
+		let query = Query::parse("main");
+		let config = config_for_query("main", dataset.len());
+		for (&index, positions) in last.indices.iter().zip(positions) {
+			assert_eq!(*positions, query.match_indices(&dataset.0[index], &config));
+		}
+		assert_eq!(positions[0], [4, 5, 6, 7], "`src/main.rs` ranks first");
+
+		let batches = received_batches(&dataset, "", &options);
+		assert!(batches.iter().all(|batch| batch.positions.is_none()));
+	}
+
+	fn boundary_score(query: &str, key: &str, bonus: BoundaryBonus) -> u16 {
+		let options = SearchOptions::default().with_boundary_bonus(bonus);
+		let config = config_for_options(query, 1, &options);
+		match_list(query, &[key], &config)
+			.first()
+			.map(|matched| matched.score)
+			.unwrap_or_else(|| panic!("{query:?} does not match {key:?}"))
+	}
+
+	#[test]
+	fn word_boundaries_outrank_mid_word_matches() {
+		let dataset = TestDataset(
+			["raspberry", "search_pipeline", "wasp", "SearchPipeline"]
+				.into_iter()
+				.map(String::from)
+				.collect(),
+		);
+		let view = collect_with_options(&dataset, "sp", &SearchOptions::default());
+		assert_eq!(&view.indices[..2], [1, 3]);
+
+		// The bonuses are what lift the boundary matches.
+		let bonus = BoundaryBonus::default();
+		for key in ["search_pipeline", "SearchPipeline"] {
+			let with = boundary_score("sp", key, bonus);
+			let without = boundary_score("sp", key, BoundaryBonus::NONE);
+			assert!(with > without, "{key}: {with} <= {without}");
+		}
+		assert_eq!(
+			boundary_score("sp", "raspberry", bonus),
+			boundary_score("sp", "raspberry", BoundaryBonus::NONE)
+		);
+	}
+
+	#[test]
+	fn separator_bonuses_follow_runs_of_separators() {
+		let bonus = BoundaryBonus {
+			separator: 10,
+			camel_case: 0,
+		};
+		for key in ["a_spec", "a__spec", "a/-spec", "a/./spec", "a spec"] {
+			let gained = boundary_score("spec", key, bonus)
+				- boundary_score("spec", key, BoundaryBonus::NONE);
+			assert_eq!(gained, bonus.separator, "{key}");
+		}
+
+		// Leading separators are not word boundaries on their own; the
+		// prefix bonuses already cover matches near the start.
+		for key in ["_spec", "/spec", "__spec"] {
+			assert_eq!(
+				boundary_score("spec", key, bonus),
+				boundary_score("spec", key, BoundaryBonus::NONE),
+				"{key}"
+			);
+		}
+	}
+
+	#[test]
+	fn boundaries_after_unicode_text_earn_bonuses() {
+		let bonus = BoundaryBonus {
+			separator: 10,
+			camel_case: 20,
+		};
+		let gained = |key: &str| {
+			boundary_score("spec", key, bonus) - boundary_score("spec", key, BoundaryBonus::NONE)
+		};
+		assert_eq!(gained("naïve_spec"), bonus.separator);
+		assert_eq!(gained("über/spec"), bonus.separator);
+		assert_eq!(gained("größeSpec"), bonus.camel_case);
+		assert_eq!(gained("größespec"), 0);
+	}
+
+	#[test]
+	fn folded_diacritics_match_and_highlight_the_original_characters() {
+		let mut keys = vec![
+			"cv/résumé.pdf".to_owned(),
+			"cv/re\u{301}sume\u{301}.md".to_owned(),
+			"notes.txt".to_owned(),
+		];
+		let folding = SearchOptions::default().with_fold_diacritics(true);
+
+		let mut exact = collect_with_options(&TestDataset(keys.clone()), "'résumé", &folding);
+		exact.indices.sort_unstable();
+		assert_eq!(exact.indices, [0, 1]);
+		let unfolded = collect_with_options(
+			&TestDataset(keys.clone()),
+			"'resume",
+			&SearchOptions::default(),
+		);
+		assert!(unfolded.indices.is_empty());
+
+		// Small datasets are scored in one pass, large ones are refined on a
+		// background thread; both report positions in the original keys.
+		for len in [keys.len(), PREFILTER_ENABLE_THRESHOLD + 10] {
+			keys.resize_with(len, || "filler/notes.txt".to_owned());
+			let batches = received_batches(&TestDataset(keys.clone()), "resume", &folding);
+			let last = batches.last().expect("final batch");
+			let positions: HashMap<usize, &Vec<usize>> = last
+				.indices
+				.iter()
+				.copied()
+				.zip(last.positions.as_ref().expect("positions"))
+				.collect();
+			assert_eq!(positions[&0], &[3, 4, 5, 6, 7, 8], "over {len}");
+			assert_eq!(positions[&1], &[3, 4, 5, 6, 7, 8, 9, 10], "over {len}");
+		}
+	}
+
+	#[test]
+	fn fuzzy_scores_land_on_their_own_haystacks() {
+		let query = Query::parse("x");
+		let term = query.fuzzy_terms().next().expect("fuzzy term");
+		let config = unbounded_config(&query, 32, &SearchOptions::default());
+		for target in 0..32 {
+			let mut haystacks = vec!["aaaaaaaaaaaaaaaaaaaaaaaa"; 32];
+			haystacks[target] = "aaaaaaaaaaaaaaaaaaaaaaax";
+			let matched: Vec<usize> = fuzzy_scores(term, &haystacks, &config)
+				.iter()
+				.enumerate()
+				.filter_map(|(index, score)| score.map(|_| index))
+				.collect();
+			assert_eq!(matched, [target]);
+		}
+	}
+
+	/// Paths with tags scored as separate tokens.
+	struct TaggedDataset(Vec<(String, Vec<&'static str>)>);
+
+	impl Dataset for TaggedDataset {
+		fn len(&self) -> usize {
+			self.0.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			&self.0[index].0
+		}
+
+		fn tokens_for(&self, index: usize) -> impl Iterator<Item = &str> {
+			let (key, tags) = &self.0[index];
+			std::iter::once(key.as_str()).chain(tags.iter().copied())
+		}
+	}
+
+	fn tagged_matches(
+		dataset: &TaggedDataset,
+		query: &str,
+		options: &SearchOptions,
+		sources: CandidateSources<'_>,
+	) -> MatchBatch {
+		let (tx, rx) = mpsc::channel();
+		let latest = Arc::new(AtomicU64::new(1));
+		let stream = SearchStream::new(&tx, 1);
+		stream_dataset_with_sources(dataset, query, options, sources, stream, &latest, |idx| {
+			dataset.0[idx].0.clone()
+		});
+		drop(tx);
+
+		let mut view = BatchView::default();
+		for envelope in rx {
+			envelope.dispatch(&mut view);
+		}
+		// Queries without matches only clear the view.
+		view.0.pop().unwrap_or(MatchBatch {
+			indices: Vec::new(),
+			ids: None,
+			scores: Vec::new(),
+			positions: None,
+		})
+	}
+
+	#[test]
+	fn tokens_after_the_key_are_scored_on_their_own() {
+		for len in [10, PREFILTER_ENABLE_THRESHOLD + 10] {
+			let mut rows: Vec<(String, Vec<&'static str>)> = (0..len)
+				.map(|i| (format!("src/module_{i}.rs"), Vec::new()))
+				.collect();
+			rows[3].1 = vec!["urgent", "café"];
+			rows[5] = ("docs/urgent.md".into(), Vec::new());
+			rows[7] = ("docs/guide.md".into(), vec!["urgent"]);
+			rows[9] = ("docs/notes.md".into(), vec!["urgent docs"]);
+			let dataset = TaggedDataset(rows);
+			let index = PrefilterIndex::build(&dataset);
+			let cache = PrefilterCache::new();
+			let options = SearchOptions::default()
+				.with_fold_diacritics(true)
+				.with_threads(4);
+			let all_sources = [
+				CandidateSources::default(),
+				CandidateSources {
+					index: Some(&index),
+					..CandidateSources::default()
+				},
+				CandidateSources {
+					masks: Some(&cache),
+					..CandidateSources::default()
+				},
+			];
+			for sources in all_sources {
+				let top = |query: &str, count: usize| {
+					let mut indices = tagged_matches(&dataset, query, &options, sources).indices;
+					indices.truncate(count);
+					indices.sort_unstable();
+					indices
+				};
+				let sorted = |query: &str| top(query, usize::MAX);
+				assert_eq!(top("urgent", 4), [3, 5, 7, 9], "{len} rows");
+				assert_eq!(sorted("'urgent"), [3, 5, 7, 9], "{len} rows");
+				assert_eq!(sorted("'cafe"), [3], "folded tags, {len} rows");
+				// Negations and filters only look at the key.
+				assert_eq!(sorted("'urgent !docs"), [3], "{len} rows");
+				assert_eq!(sorted("'urgent ext:md"), [5, 7, 9], "{len} rows");
+				// Every term must match the same token.
+				assert!(sorted("'urgent 'module_3.rs").is_empty(), "{len} rows");
+
+				// Only rows whose key matched highlight it, even where the key
+				// contains some of the terms.
+				let last = tagged_matches(&dataset, "'urgent 'docs", &options, sources);
+				let positions = last.positions.as_ref().expect("positions");
+				let by_row: HashMap<usize, &Vec<usize>> =
+					last.indices.iter().copied().zip(positions).collect();
+				assert_eq!(by_row.len(), 2, "{len} rows");
+				assert_eq!(*by_row[&5], [0, 1, 2, 3, 5, 6, 7, 8, 9, 10], "{len} rows");
+				assert!(by_row[&9].is_empty(), "{len} rows");
+			}
+		}
+	}
+
+	#[test]
+	fn tag_filters_keep_rows_with_every_tag() {
+		for len in [10, PREFILTER_ENABLE_THRESHOLD + 10] {
+			let mut rows: Vec<(String, Vec<&'static str>)> = (0..len)
+				.map(|i| (format!("src/module_{i}.rs"), Vec::new()))
+				.collect();
+			rows[3].1 = vec!["Urgent", "café"];
+			rows[5] = ("docs/urgent.md".into(), Vec::new());
+			rows[7] = ("docs/guide.md".into(), vec!["urgent"]);
+			rows[9] = ("docs/notes.md".into(), vec!["urgently"]);
+			let dataset = TaggedDataset(rows);
+			let index = PrefilterIndex::build(&dataset);
+			let cache = PrefilterCache::new();
+			let options = SearchOptions::default()
+				.with_fold_diacritics(true)
+				.with_threads(4);
+			let all_sources = [
+				CandidateSources::default(),
+				CandidateSources {
+					index: Some(&index),
+					..CandidateSources::default()
+				},
+				CandidateSources {
+					masks: Some(&cache),
+					..CandidateSources::default()
+				},
+			];
+			for sources in all_sources {
+				let sorted = |query: &str| {
+					let mut indices = tagged_matches(&dataset, query, &options, sources).indices;
+					indices.sort_unstable();
+					indices
+				};
+				assert_eq!(sorted("tag:urgent"), [3, 7], "{len} rows");
+				assert_eq!(sorted("tag:urgent .md$"), [7], "{len} rows");
+				assert_eq!(sorted("'module tag:urgent"), [3], "{len} rows");
+				assert_eq!(sorted("tag:urgent tag:cafe"), [3], "{len} rows");
+				assert!(sorted("tag:urgent tag:work").is_empty(), "{len} rows");
+
+				// The filter does not count towards the highlights.
+				let last = tagged_matches(&dataset, "tag:urgent 'guide", &options, sources);
+				assert_eq!(last.indices, [7], "{len} rows");
+				let positions = last.positions.as_ref().expect("positions");
+				assert_eq!(positions[0], [5, 6, 7, 8, 9], "{len} rows");
+			}
+		}
 	}
 }
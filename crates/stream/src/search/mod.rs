@@ -2,13 +2,30 @@
 //! primitives.
 
 mod channel;
+mod filter;
+mod fold;
+mod mask;
 mod matcher;
+mod narrow;
+mod options;
+mod prefilter;
+mod query;
 
 pub use channel::{
-	MatchBatch, SearchAction, SearchMarker, SearchResult, SearchStream, SearchView, SearchViewV2,
+	CancellationToken, MatchBatch, SearchAction, SearchMarker, SearchResult, SearchStream,
+	SearchView, SearchViewV2,
 };
+pub use filter::QueryFilter;
+pub use fold::fold_diacritics;
+pub use mask::PrefilterCache;
 pub use matcher::{
-	AlphabeticalCollector, Dataset, EMPTY_QUERY_BATCH, MATCH_CHUNK_SIZE, MAX_RENDERED_RESULTS,
-	PREFILTER_ENABLE_THRESHOLD, ScoreAggregator, config_for_query, stream_alphabetical,
-	stream_dataset,
+	AlphabeticalCollector, BASENAME_MATCH_BONUS, CandidateSources, Dataset, EMPTY_QUERY_BATCH,
+	MATCH_CHUNK_SIZE, MAX_RENDERED_RESULTS, PREFILTER_ENABLE_THRESHOLD, ScoreAggregator,
+	config_for_options, config_for_query, config_for_query_with_case, stream_alphabetical,
+	stream_dataset, stream_dataset_indexed, stream_dataset_narrowed, stream_dataset_with_options,
+	stream_dataset_with_sources,
 };
+pub use narrow::{NARROWING_CACHE_SIZE, NarrowingCache};
+pub use options::{BoundaryBonus, CaseMatching, MatchMode, ScoreCutoff, SearchOptions, Tiebreak};
+pub use prefilter::PrefilterIndex;
+pub use query::{Query, QueryTerm, TermGroup, TermKind};
@@ -0,0 +1,143 @@
+//! Reuse of earlier result sets for queries that refine them.
+//!
+//! While typing, most queries only add to the previous one, and every
+//! candidate they match was already matched before. [`NarrowingCache`]
+//! remembers the complete match sets of recent queries so such a query only
+//! has to score those candidates instead of the whole dataset. Whether a
+//! query is covered by an earlier one is decided by [`Query::narrows`].
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::query::Query;
+
+/// Number of earlier match sets kept by a [`NarrowingCache`].
+pub const NARROWING_CACHE_SIZE: usize = 4;
+
+/// Complete match sets of recent queries over one dataset.
+///
+/// The cache is tied to the dataset's contents: call
+/// [`NarrowingCache::invalidate`] whenever rows are added, removed or
+/// reordered. Clones share the same entries, so background passes can
+/// record their results once they finish.
+#[derive(Debug, Clone, Default)]
+pub struct NarrowingCache {
+	state: Arc<Mutex<CacheState>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+	/// Bumped on every invalidation so passes started before it cannot
+	/// record stale indices.
+	generation: u64,
+	/// Newest last.
+	entries: VecDeque<CachedMatches>,
+}
+
+#[derive(Debug)]
+struct CachedMatches {
+	query: Query,
+	indices: Arc<[usize]>,
+}
+
+impl NarrowingCache {
+	/// Create an empty cache.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Forget every match set, e.g. after the dataset changed.
+	pub fn invalidate(&self) {
+		let mut state = self.lock();
+		state.generation += 1;
+		state.entries.clear();
+	}
+
+	/// Whether no match set is cached.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.lock().entries.is_empty()
+	}
+
+	pub(crate) fn generation(&self) -> u64 {
+		self.lock().generation
+	}
+
+	/// The smallest cached match set of a query that `query` narrows.
+	pub(crate) fn candidates_for(&self, query: &Query) -> Option<Arc<[usize]>> {
+		self.lock()
+			.entries
+			.iter()
+			.filter(|entry| query.narrows(&entry.query))
+			.min_by_key(|entry| entry.indices.len())
+			.map(|entry| Arc::clone(&entry.indices))
+	}
+
+	/// Remember the complete, ascending match set of `query`, unless the
+	/// cache was invalidated since `generation` was read.
+	pub(crate) fn store(&self, generation: u64, query: Query, indices: Vec<usize>) {
+		let mut state = self.lock();
+		if state.generation != generation {
+			return;
+		}
+		state.entries.retain(|entry| entry.query != query);
+		if state.entries.len() == NARROWING_CACHE_SIZE {
+			state.entries.pop_front();
+		}
+		state.entries.push_back(CachedMatches {
+			query,
+			indices: indices.into(),
+		});
+	}
+
+	fn lock(&self) -> MutexGuard<'_, CacheState> {
+		self.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn picks_the_smallest_set_of_a_narrowed_query() {
+		let cache = NarrowingCache::new();
+		let generation = cache.generation();
+		cache.store(generation, Query::parse("src"), vec![1, 2, 3]);
+		cache.store(generation, Query::parse("src lib"), vec![2]);
+		cache.store(generation, Query::parse("main"), vec![4]);
+
+		let candidates = cache.candidates_for(&Query::parse("src lib rs"));
+		assert_eq!(candidates.as_deref(), Some(&[2][..]));
+		let candidates = cache.candidates_for(&Query::parse("src x"));
+		assert_eq!(candidates.as_deref(), Some(&[1, 2, 3][..]));
+		assert!(cache.candidates_for(&Query::parse("srcm")).is_none());
+	}
+
+	#[test]
+	fn invalidation_drops_entries_and_late_results() {
+		let cache = NarrowingCache::new();
+		let stale = cache.generation();
+		cache.store(stale, Query::parse("src"), vec![1]);
+		cache.invalidate();
+		assert!(cache.is_empty());
+
+		cache.store(stale, Query::parse("src"), vec![1]);
+		assert!(cache.is_empty(), "passes started before invalidation");
+	}
+
+	#[test]
+	fn keeps_only_the_newest_sets() {
+		let cache = NarrowingCache::new();
+		let generation = cache.generation();
+		for term in ["a", "b", "c", "d", "e"] {
+			cache.store(generation, Query::parse(term), vec![0]);
+		}
+		assert!(cache.candidates_for(&Query::parse("a")).is_none());
+		assert!(cache.candidates_for(&Query::parse("e")).is_some());
+		assert_eq!(cache.lock().entries.len(), NARROWING_CACHE_SIZE);
+	}
+}
@@ -0,0 +1,241 @@
+//! Tunable behaviour shared by every search pass.
+
+use super::matcher::MAX_RENDERED_RESULTS;
+
+/// Secondary ordering applied to matches that share the same score.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Tiebreak {
+	/// Prefer shorter keys, then fall back to lexical order.
+	#[default]
+	PathLength,
+	/// Order equal scores lexically by key.
+	Lexical,
+	/// Order equal scores by key, comparing runs of digits by their numeric
+	/// value so `file2` sorts before `file10`.
+	Natural,
+	/// Keep equal scores in dataset order.
+	None,
+}
+
+/// How letter case is compared between a query and candidate keys.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum CaseMatching {
+	/// Ignore case unless the query contains an uppercase letter.
+	#[default]
+	Smart,
+	/// Always ignore case.
+	Insensitive,
+	/// Always match case exactly.
+	Sensitive,
+}
+
+impl CaseMatching {
+	/// Whether `query` should be matched case-sensitively.
+	#[must_use]
+	pub fn is_sensitive(self, query: &str) -> bool {
+		match self {
+			Self::Smart => query.chars().any(char::is_uppercase),
+			Self::Insensitive => false,
+			Self::Sensitive => true,
+		}
+	}
+}
+
+/// How query terms without a match operator are matched.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum MatchMode {
+	/// Typo-tolerant fuzzy matching.
+	#[default]
+	Fuzzy,
+	/// Plain substring matching, as if every term started with `'`.
+	Substring,
+}
+
+impl MatchMode {
+	/// The other mode.
+	#[must_use]
+	pub fn toggled(self) -> Self {
+		match self {
+			Self::Fuzzy => Self::Substring,
+			Self::Substring => Self::Fuzzy,
+		}
+	}
+}
+
+/// Score added to characters matched at the start of a word.
+///
+/// Words start after a separator such as `/`, `_`, `-`, `.` or a space, and
+/// at a lowercase-to-uppercase camelCase boundary, so `sp` ranks
+/// `search_pipeline` above `raspberry`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct BoundaryBonus {
+	/// Bonus for the first character after a separator.
+	pub separator: u16,
+	/// Bonus for an uppercase character following a lowercase one.
+	pub camel_case: u16,
+}
+
+impl Default for BoundaryBonus {
+	fn default() -> Self {
+		Self {
+			separator: 6,
+			camel_case: 6,
+		}
+	}
+}
+
+impl BoundaryBonus {
+	/// No boundary bonuses; only the match itself is scored.
+	pub const NONE: Self = Self {
+		separator: 0,
+		camel_case: 0,
+	};
+}
+
+/// Lowest score a match needs to be shown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ScoreCutoff {
+	/// Hide matches scoring below this value.
+	Absolute(u16),
+	/// Hide matches scoring below this percentage of the best match's
+	/// score. Values above 100 are treated as 100.
+	Percent(u8),
+}
+
+impl ScoreCutoff {
+	/// Lowest score kept when the best match scores `top`.
+	#[must_use]
+	pub fn threshold(self, top: u16) -> u16 {
+		match self {
+			Self::Absolute(score) => score,
+			Self::Percent(percent) => {
+				let percent = u32::from(percent.min(100));
+				// Round up so a cutoff of 50% of 5 keeps 3, not 2.
+				u16::try_from((u32::from(top) * percent).div_ceil(100)).unwrap_or(u16::MAX)
+			}
+		}
+	}
+}
+
+/// Options controlling how queries are matched and ranked.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchOptions {
+	/// Ordering applied to matches with equal scores.
+	pub tiebreak: Tiebreak,
+	/// How letter case is compared while matching.
+	pub case_matching: CaseMatching,
+	/// Whether plain terms are matched fuzzily or as substrings.
+	pub match_mode: MatchMode,
+	/// Number of threads that score candidates. With one thread, the
+	/// default, scoring happens on the calling thread.
+	pub threads: usize,
+	/// Most matches kept per query; lower-ranked matches are dropped.
+	/// Defaults to [`MAX_RENDERED_RESULTS`].
+	pub max_results: usize,
+	/// Hide scored matches below this cutoff. Queries without scored terms,
+	/// such as the empty query, list every row regardless.
+	pub score_cutoff: Option<ScoreCutoff>,
+	/// Bonuses for matches at word boundaries.
+	pub boundary_bonus: BoundaryBonus,
+	/// Match accented letters by their base letter, so `resume` finds
+	/// `résumé`. Off by default since every key with diacritics is copied
+	/// when a query starts.
+	pub fold_diacritics: bool,
+}
+
+impl Default for SearchOptions {
+	fn default() -> Self {
+		Self {
+			tiebreak: Tiebreak::default(),
+			case_matching: CaseMatching::default(),
+			match_mode: MatchMode::default(),
+			threads: 1,
+			max_results: MAX_RENDERED_RESULTS,
+			score_cutoff: None,
+			boundary_bonus: BoundaryBonus::default(),
+			fold_diacritics: false,
+		}
+	}
+}
+
+impl SearchOptions {
+	/// Set the ordering applied to matches with equal scores.
+	#[must_use]
+	pub fn with_tiebreak(mut self, tiebreak: Tiebreak) -> Self {
+		self.tiebreak = tiebreak;
+		self
+	}
+
+	/// Set how letter case is compared while matching.
+	#[must_use]
+	pub fn with_case_matching(mut self, case_matching: CaseMatching) -> Self {
+		self.case_matching = case_matching;
+		self
+	}
+
+	/// Set whether plain terms are matched fuzzily or as substrings.
+	#[must_use]
+	pub fn with_match_mode(mut self, match_mode: MatchMode) -> Self {
+		self.match_mode = match_mode;
+		self
+	}
+
+	/// Hide matches scoring below `cutoff`.
+	#[must_use]
+	pub fn with_score_cutoff(mut self, cutoff: ScoreCutoff) -> Self {
+		self.score_cutoff = Some(cutoff);
+		self
+	}
+
+	/// Set how many threads score candidates. Zero is treated as one.
+	#[must_use]
+	pub fn with_threads(mut self, threads: usize) -> Self {
+		self.threads = threads.max(1);
+		self
+	}
+
+	/// Set the most matches kept per query. Zero is treated as one.
+	#[must_use]
+	pub fn with_max_results(mut self, max_results: usize) -> Self {
+		self.max_results = max_results.max(1);
+		self
+	}
+
+	/// Set the bonuses for matches at word boundaries.
+	#[must_use]
+	pub fn with_boundary_bonus(mut self, boundary_bonus: BoundaryBonus) -> Self {
+		self.boundary_bonus = boundary_bonus;
+		self
+	}
+
+	/// Set whether accented letters match their base letter.
+	#[must_use]
+	pub fn with_fold_diacritics(mut self, fold: bool) -> Self {
+		self.fold_diacritics = fold;
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn smart_case_turns_sensitive_on_uppercase() {
+		assert!(!CaseMatching::Smart.is_sensitive("readme"));
+		assert!(CaseMatching::Smart.is_sensitive("README"));
+		assert!(CaseMatching::Smart.is_sensitive("src Lib"));
+		assert!(!CaseMatching::Insensitive.is_sensitive("README"));
+		assert!(CaseMatching::Sensitive.is_sensitive("readme"));
+	}
+
+	#[test]
+	fn score_cutoffs_are_absolute_or_relative_to_the_best_match() {
+		assert_eq!(ScoreCutoff::Absolute(40).threshold(100), 40);
+		assert_eq!(ScoreCutoff::Absolute(40).threshold(10), 40);
+		assert_eq!(ScoreCutoff::Percent(50).threshold(100), 50);
+		assert_eq!(ScoreCutoff::Percent(50).threshold(5), 3);
+		assert_eq!(ScoreCutoff::Percent(0).threshold(100), 0);
+		assert_eq!(ScoreCutoff::Percent(250).threshold(u16::MAX), u16::MAX);
+	}
+}
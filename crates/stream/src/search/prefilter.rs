@@ -0,0 +1,362 @@
+//! Precomputed character index for rejecting candidates before scoring.
+//!
+//! frizbee's prefilter looks at every key again for every query. For large
+//! datasets that are searched many times, [`PrefilterIndex`] records once
+//! which rows contain each character and each pair of adjacent characters,
+//! so a query only has to score the rows that could possibly match it.
+//!
+//! The index only rejects rows no pass would ever match: literal terms and
+//! path prefixes need all of their character pairs, case-sensitive fuzzy
+//! terms all of their characters, and other fuzzy terms, which tolerate
+//! any number of typos, at least one of their characters. Characters are
+//! compared by their lowercase form, and characters whose lowercase form is
+//! longer than one character never constrain a query.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::filter::QueryFilter;
+use super::fold::fold_diacritics;
+use super::matcher::Dataset;
+use super::query::{Query, QueryTerm, TermGroup, TermKind};
+
+/// Rows containing each character and each pair of adjacent characters of
+/// a dataset's keys and other [tokens](Dataset::tokens_for).
+///
+/// The index is tied to the keys it was built from: build a new one
+/// whenever rows are added, removed or reordered. An index built for a
+/// dataset of a different length is ignored. Keys are indexed both as they
+/// are and with their diacritics folded, so one index serves searches with
+/// and without [`SearchOptions::fold_diacritics`](super::SearchOptions).
+///
+/// Every row costs a `u32` in the list of each distinct character and
+/// each distinct pair of adjacent characters in its tokens. A path of
+/// around 100 characters has about 120 of those, so the index holds
+/// roughly half a kilobyte per row, or half a gigabyte per million rows,
+/// on top of the dataset itself. Build it only for datasets searched
+/// often enough to repay that.
+#[derive(Debug, Clone, Default)]
+pub struct PrefilterIndex {
+	len: usize,
+	chars: HashMap<char, Vec<u32>>,
+	pairs: HashMap<(char, char), Vec<u32>>,
+}
+
+impl PrefilterIndex {
+	/// Index the tokens of every row of `dataset`.
+	///
+	/// Datasets with more rows than fit in a `u32` get an empty index that
+	/// is never used.
+	#[must_use]
+	pub fn build<D: Dataset>(dataset: D) -> Self {
+		let len = dataset.len();
+		let mut index = Self::default();
+		if u32::try_from(len).is_err() {
+			return index;
+		}
+		index.len = len;
+		for (row, position) in (0..len).zip(0u32..) {
+			for token in dataset.tokens_for(row) {
+				index.insert(position, token);
+				let folded = fold_diacritics(token);
+				if folded != token {
+					index.insert(position, &folded);
+				}
+			}
+		}
+		index
+	}
+
+	/// Number of rows the index was built from.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Whether the index covers no rows.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	fn insert(&mut self, row: u32, key: &str) {
+		let mut previous = None;
+		for ch in key.chars().map(normalize) {
+			if let Some(ch) = ch {
+				push_row(self.chars.entry(ch).or_default(), row);
+				if let Some(previous) = previous {
+					push_row(self.pairs.entry((previous, ch)).or_default(), row);
+				}
+			}
+			previous = ch;
+		}
+	}
+
+	/// Ascending rows of a dataset of `len` rows that may match `query`.
+	///
+	/// Returns `None` when the index was built for another dataset or
+	/// cannot rule out any row, so the regular passes run instead.
+	pub(crate) fn candidates_for(&self, query: &Query, len: usize) -> Option<Arc<[usize]>> {
+		if len != self.len || self.is_empty() {
+			return None;
+		}
+		let rows = self.rows(&Constraint::for_query(query)?);
+		(rows.len() < len).then(|| rows.into_iter().map(|row| row as usize).collect())
+	}
+
+	/// Ascending rows satisfying `constraint`.
+	fn rows(&self, constraint: &Constraint) -> Vec<u32> {
+		match constraint {
+			Constraint::Char(ch) => self.chars.get(ch).cloned().unwrap_or_default(),
+			Constraint::Pair(first, second) => self
+				.pairs
+				.get(&(*first, *second))
+				.cloned()
+				.unwrap_or_default(),
+			Constraint::All(all) => {
+				let mut lists: Vec<Vec<u32>> = all.iter().map(|c| self.rows(c)).collect();
+				lists.sort_unstable_by_key(Vec::len);
+				let mut lists = lists.into_iter();
+				let first = lists.next().unwrap_or_default();
+				lists.fold(first, |rows, other| intersect(&rows, &other))
+			}
+			Constraint::Any(any) => union(any.iter().map(|c| self.rows(c)).collect()),
+		}
+	}
+}
+
+/// Characters a row must contain to possibly match a query.
+///
+/// Built by [`Constraint::for_query`], which leaves out whatever cannot
+/// rule out a row, so a row failing the constraint never matches.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum Constraint {
+	/// The row contains this lowercase character.
+	Char(char),
+	/// The row contains these lowercase characters next to each other.
+	Pair(char, char),
+	/// Every constraint holds; sorted and without duplicates.
+	All(Vec<Constraint>),
+	/// At least one constraint holds; sorted and without duplicates.
+	Any(Vec<Constraint>),
+}
+
+impl Constraint {
+	/// What every match of `query` contains, following the rules in the
+	/// [module docs](self), or `None` when any row may match it.
+	pub(crate) fn for_query(query: &Query) -> Option<Self> {
+		let constraints = query
+			.groups()
+			.iter()
+			.map(Self::for_group)
+			.chain(query.filters().iter().map(Self::for_filter))
+			.flatten()
+			.collect();
+		Self::all(constraints)
+	}
+
+	fn for_group(group: &TermGroup) -> Option<Self> {
+		let alternatives = group
+			.alternatives
+			.iter()
+			.map(Self::for_term)
+			.collect::<Option<Vec<_>>>()?;
+		Self::any(alternatives)
+	}
+
+	fn for_term(term: &QueryTerm) -> Option<Self> {
+		match term.kind {
+			TermKind::Exact | TermKind::Prefix | TermKind::Suffix | TermKind::Full => {
+				Self::substring(&term.text)
+			}
+			// Case-sensitive terms must match every character, since the
+			// unbounded passes allow them no typos.
+			TermKind::Fuzzy if term.case_sensitive => Self::all(
+				term.text
+					.chars()
+					.filter_map(normalize)
+					.map(Self::Char)
+					.collect(),
+			),
+			// frizbee compares bytes, so any non-ASCII character can be
+			// matched in part; otherwise one matching character is enough.
+			TermKind::Fuzzy if term.text.is_ascii() => Self::any(
+				term.text
+					.chars()
+					.filter_map(normalize)
+					.map(Self::Char)
+					.collect(),
+			),
+			TermKind::Fuzzy | TermKind::Negate => None,
+		}
+	}
+
+	fn for_filter(filter: &QueryFilter) -> Option<Self> {
+		match filter {
+			QueryFilter::Extension(extensions) => {
+				let extensions = extensions
+					.iter()
+					.map(|extension| Self::substring(&format!(".{extension}")))
+					.collect::<Option<Vec<_>>>()?;
+				Self::any(extensions)
+			}
+			QueryFilter::PathPrefix(prefix) => Self::substring(prefix),
+			// Tags are indexed with the rest of a row's tokens.
+			QueryFilter::Tag(tag) => Self::substring(tag),
+		}
+	}
+
+	/// Every indexable character pair of `text`, or every character of runs
+	/// too short to form one.
+	fn substring(text: &str) -> Option<Self> {
+		let chars: Vec<Option<char>> = text.chars().map(normalize).collect();
+		let mut constraints = Vec::new();
+		for run in chars.split(Option::is_none) {
+			match run {
+				[] => {}
+				[Some(ch)] => constraints.push(Self::Char(*ch)),
+				_ => constraints.extend(
+					run.windows(2)
+						.filter_map(|pair| Some(Self::Pair(pair[0]?, pair[1]?))),
+				),
+			}
+		}
+		Self::all(constraints)
+	}
+
+	fn all(constraints: Vec<Self>) -> Option<Self> {
+		Self::combine(constraints, Self::All)
+	}
+
+	fn any(constraints: Vec<Self>) -> Option<Self> {
+		Self::combine(constraints, Self::Any)
+	}
+
+	fn combine(mut constraints: Vec<Self>, wrap: fn(Vec<Self>) -> Self) -> Option<Self> {
+		constraints.sort_unstable();
+		constraints.dedup();
+		match constraints.len() {
+			0 => None,
+			1 => constraints.pop(),
+			_ => Some(wrap(constraints)),
+		}
+	}
+
+	/// Every character and pair the constraint refers to.
+	pub(crate) fn grams(&self, grams: &mut Vec<Self>) {
+		match self {
+			Self::Char(_) | Self::Pair(..) => grams.push(self.clone()),
+			Self::All(constraints) | Self::Any(constraints) => {
+				for constraint in constraints {
+					constraint.grams(grams);
+				}
+			}
+		}
+	}
+}
+
+/// The lowercase form of `ch` when it is a single character.
+pub(crate) fn normalize(ch: char) -> Option<char> {
+	let mut lower = ch.to_lowercase();
+	match (lower.next(), lower.next()) {
+		(Some(lower), None) => Some(lower),
+		_ => None,
+	}
+}
+
+/// Add `row` to an ascending posting list, once.
+fn push_row(rows: &mut Vec<u32>, row: u32) {
+	if rows.last() != Some(&row) {
+		rows.push(row);
+	}
+}
+
+fn intersect(left: &[u32], right: &[u32]) -> Vec<u32> {
+	let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+	let mut rows = Vec::new();
+	while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+		match a.cmp(b) {
+			std::cmp::Ordering::Less => {
+				left.next();
+			}
+			std::cmp::Ordering::Greater => {
+				right.next();
+			}
+			std::cmp::Ordering::Equal => {
+				rows.push(**a);
+				left.next();
+				right.next();
+			}
+		}
+	}
+	rows
+}
+
+fn union(lists: Vec<Vec<u32>>) -> Vec<u32> {
+	let mut rows: Vec<u32> = lists.into_iter().flatten().collect();
+	rows.sort_unstable();
+	rows.dedup();
+	rows
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Keys<'a>(&'a [&'a str]);
+
+	impl Dataset for Keys<'_> {
+		fn len(&self) -> usize {
+			self.0.len()
+		}
+
+		fn key_for(&self, index: usize) -> &str {
+			self.0[index]
+		}
+	}
+
+	fn candidates(keys: &[&str], query: &str) -> Option<Vec<usize>> {
+		PrefilterIndex::build(Keys(keys))
+			.candidates_for(&Query::parse(query), keys.len())
+			.map(|rows| rows.to_vec())
+	}
+
+	#[test]
+	fn literal_terms_need_every_character_pair() {
+		let keys = ["src/main.rs", "src/mani.rs", "docs/domain.md"];
+		assert_eq!(candidates(&keys, "'main"), Some(vec![0, 2]));
+		assert_eq!(candidates(&keys, "^src 'ani"), Some(vec![1]));
+		assert_eq!(candidates(&keys, "'m"), None, "every row has an m");
+		assert_eq!(candidates(&keys, "ext:md"), Some(vec![2]));
+		assert_eq!(candidates(&keys, "docs/"), Some(vec![2]));
+	}
+
+	#[test]
+	fn fuzzy_terms_only_reject_rows_without_any_of_their_characters() {
+		let keys = ["src/main.rs", "docs/guide.md", "build"];
+		assert_eq!(candidates(&keys, "xyz"), Some(vec![]));
+		assert_eq!(candidates(&keys, "qm"), Some(vec![0, 1]));
+		assert_eq!(candidates(&keys, "Mn"), Some(vec![0]));
+		assert_eq!(candidates(&keys, "qm | 'uil"), None, "covers every row");
+		assert_eq!(candidates(&keys, "!main"), None);
+	}
+
+	#[test]
+	fn unicode_keys_compare_by_lowercase_and_folded_forms() {
+		let keys = ["Résumé.pdf", "resume.txt", "İstanbul/map"];
+		assert_eq!(candidates(&keys, "'RÉSU"), Some(vec![0]));
+		assert_eq!(candidates(&keys, "'resu"), Some(vec![0, 1]), "folded keys");
+		// `İ` lowercases to two characters, so it never constrains a query.
+		assert_eq!(candidates(&keys, "'İst"), Some(vec![2]));
+		assert_eq!(candidates(&keys, "'İ"), None);
+		// Non-ASCII fuzzy terms may match part of any multi-byte character.
+		assert_eq!(candidates(&keys, "é"), None);
+	}
+
+	#[test]
+	fn indexes_of_other_datasets_are_ignored() {
+		let index = PrefilterIndex::build(Keys(&["src/main.rs", "docs"]));
+		assert_eq!(index.len(), 2);
+		assert!(index.candidates_for(&Query::parse("'main"), 3).is_none());
+	}
+}
@@ -0,0 +1,993 @@
+//! Query parsing for the search pipeline.
+//!
+//! Raw queries are split on whitespace into typed terms before they reach the
+//! matcher. Every term must match a candidate: plain terms are scored
+//! individually with frizbee, while operator-prefixed terms select a literal
+//! match strategy:
+//!
+//! - `'term` matches `term` as a substring.
+//! - `^term` requires the candidate to start with `term`.
+//! - `term$` requires the candidate to end with `term`.
+//! - `^term$` requires the candidate to equal `term`.
+//! - `!term` drops every candidate containing `term` before scoring.
+//! - `a | b` matches candidates matching either `a` or `b`.
+//! - `key:value` applies a [`QueryFilter`] such as `ext:rs` or `tag:urgent`
+//!   when `key` is a known filter, and is plain text otherwise. Every filter
+//!   must pass, so `tag:a tag:b` keeps candidates tagged both.
+//! - `dir/` keeps candidates whose path starts with `dir/`, matching case
+//!   exactly; `./dir/` is the same prefix.
+//!
+//! Letter case is compared according to a [`CaseMatching`] mode, smart case
+//! by default: queries are case-insensitive unless they contain an uppercase
+//! letter. In [`MatchMode::Substring`], plain terms are matched as if they
+//! started with `'`.
+
+use std::ops::Range;
+
+use frizbee::{Config, Scoring};
+
+use super::filter::QueryFilter;
+use super::matcher::{PREFILTER_ENABLE_THRESHOLD, config_for_query};
+use super::options::{CaseMatching, MatchMode, SearchOptions};
+
+/// Strategy used to match a single query term against a candidate key.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TermKind {
+	/// Typo-tolerant fuzzy matching via frizbee.
+	Fuzzy,
+	/// Substring matching, selected with a leading `'`.
+	Exact,
+	/// Match anchored to the start, selected with a leading `^`.
+	Prefix,
+	/// Match anchored to the end, selected with a trailing `$`.
+	Suffix,
+	/// Match of the whole candidate, selected with `^term$`.
+	Full,
+	/// Excludes candidates containing the term, selected with a leading `!`.
+	Negate,
+}
+
+/// A single whitespace-delimited term of a parsed query.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct QueryTerm {
+	/// Term text with any operator prefix or suffix removed.
+	pub text: String,
+	/// Match strategy for the term.
+	pub kind: TermKind,
+	/// Whether letter case must match exactly.
+	pub case_sensitive: bool,
+}
+
+impl QueryTerm {
+	/// Parse a single raw term, stripping any match operators.
+	///
+	/// The term is case-sensitive when it contains an uppercase letter.
+	#[must_use]
+	pub fn parse(raw: &str) -> Self {
+		Self::parse_with_case(raw, CaseMatching::Smart.is_sensitive(raw))
+	}
+
+	fn parse_with_case(raw: &str, case_sensitive: bool) -> Self {
+		let mut term = Self::parse_operators(raw);
+		term.case_sensitive = case_sensitive;
+		term
+	}
+
+	fn parse_operators(raw: &str) -> Self {
+		if let Some(rest) = raw.strip_prefix('\'')
+			&& !rest.is_empty()
+		{
+			return Self::new(rest, TermKind::Exact);
+		}
+		if let Some(rest) = raw.strip_prefix('!')
+			&& !rest.is_empty()
+		{
+			return Self::new(rest, TermKind::Negate);
+		}
+
+		let (body, prefix) = match raw.strip_prefix('^') {
+			Some(rest) => (rest, true),
+			None => (raw, false),
+		};
+		let (body, suffix) = match body.strip_suffix('$') {
+			Some(rest) => (rest, true),
+			None => (body, false),
+		};
+		if body.is_empty() {
+			return Self::new(raw, TermKind::Fuzzy);
+		}
+
+		let kind = match (prefix, suffix) {
+			(true, true) => TermKind::Full,
+			(true, false) => TermKind::Prefix,
+			(false, true) => TermKind::Suffix,
+			(false, false) => TermKind::Fuzzy,
+		};
+		Self::new(body, kind)
+	}
+
+	fn new(text: &str, kind: TermKind) -> Self {
+		Self {
+			text: text.to_string(),
+			kind,
+			case_sensitive: false,
+		}
+	}
+
+	/// Whether the term is handled by frizbee rather than a literal strategy.
+	#[must_use]
+	pub fn is_fuzzy(&self) -> bool {
+		self.kind == TermKind::Fuzzy
+	}
+
+	/// Whether the term excludes candidates rather than matching them.
+	#[must_use]
+	pub fn is_negation(&self) -> bool {
+		self.kind == TermKind::Negate
+	}
+
+	/// Whether every character of the term appears in `haystack` in order,
+	/// following the term's case sensitivity.
+	#[must_use]
+	pub fn is_subsequence_of(&self, haystack: &str) -> bool {
+		let mut haystack = haystack.chars();
+		self.text.chars().all(|needle| {
+			haystack.any(|candidate| {
+				if self.case_sensitive {
+					needle == candidate
+				} else {
+					chars_eq_ignore_case(needle, candidate)
+				}
+			})
+		})
+	}
+
+	/// Whether `haystack` contains the term, ignoring anchors.
+	#[must_use]
+	pub fn occurs_in(&self, haystack: &str) -> bool {
+		self.find_substring(haystack).is_some()
+	}
+
+	/// Character range of `haystack` matched by a literal term.
+	///
+	/// Always returns `None` for fuzzy and negation terms.
+	#[must_use]
+	pub fn literal_range(&self, haystack: &str) -> Option<Range<usize>> {
+		let (start, _) = self.locate(haystack)?;
+		Some(start..start + self.text.chars().count())
+	}
+
+	/// Score a literal match using frizbee's scoring weights.
+	///
+	/// Each matched character earns the regular match score, and the same
+	/// prefix, delimiter, capitalization, and exact-match bonuses frizbee
+	/// applies are added so that literal and fuzzy terms produce comparable
+	/// totals. Returns `None` when the term does not match or is fuzzy.
+	#[must_use]
+	pub fn score(&self, haystack: &str, scoring: &Scoring) -> Option<u16> {
+		let (start, byte_start) = self.locate(haystack)?;
+		let length = self.text.chars().count();
+		let mut score = scoring
+			.match_score
+			.saturating_mul(u16::try_from(length).unwrap_or(u16::MAX));
+
+		let previous = haystack[..byte_start].chars().next_back();
+		if start == 0 {
+			score = score.saturating_add(scoring.prefix_bonus);
+		} else if previous.is_some_and(|ch| scoring.delimiters.contains(ch)) {
+			score = score.saturating_add(scoring.delimiter_bonus);
+		} else if previous.is_some_and(char::is_lowercase)
+			&& haystack[byte_start..].starts_with(char::is_uppercase)
+		{
+			score = score.saturating_add(scoring.capitalization_bonus);
+		}
+		if haystack.chars().count() == length {
+			score = score.saturating_add(scoring.exact_match_bonus);
+		}
+
+		Some(score)
+	}
+
+	/// Score the term against `haystack` with the indices it matched.
+	///
+	/// Fuzzy terms are matched with frizbee, literal terms with their anchor
+	/// strategy, and negations match (with no indices) when the term is absent.
+	#[must_use]
+	pub fn match_indices(&self, haystack: &str, config: &Config) -> Option<(u16, Vec<usize>)> {
+		match self.kind {
+			TermKind::Fuzzy => {
+				let config = config_for_alternative(&self.text, config);
+				let matched = frizbee::match_indices(&self.text, haystack, &config)
+					.filter(|matched| matched.score > 0)?;
+				if !self.matches_case(haystack, &config) {
+					return None;
+				}
+				let indices = if self.case_sensitive
+					&& !self.indices_match_case(haystack, &matched.indices)
+				{
+					self.case_sensitive_positions(haystack)
+				} else {
+					matched.indices
+				};
+				Some((matched.score, indices))
+			}
+			TermKind::Negate => (!self.occurs_in(haystack)).then(|| (0, Vec::new())),
+			_ => {
+				let score = self.score(haystack, &config.scoring)?;
+				let range = self.literal_range(haystack)?;
+				Some((score, range.collect()))
+			}
+		}
+	}
+
+	/// Whether a fuzzy match of the term against `haystack` respects case.
+	///
+	/// frizbee ignores case, so case-sensitive terms additionally require
+	/// their characters to appear in order with the exact case, missing at
+	/// most the typo budget of `config` (none when the budget is unlimited).
+	/// Always true for case-insensitive terms.
+	#[must_use]
+	pub fn matches_case(&self, haystack: &str, config: &Config) -> bool {
+		if !self.case_sensitive {
+			return true;
+		}
+
+		let needle: Vec<char> = self.text.chars().collect();
+		let mut row = vec![0usize; needle.len() + 1];
+		for ch in haystack.chars() {
+			let mut diagonal = 0;
+			for (position, wanted) in needle.iter().enumerate() {
+				let above = row[position + 1];
+				row[position + 1] = if ch == *wanted {
+					diagonal + 1
+				} else {
+					above.max(row[position])
+				};
+				diagonal = above;
+			}
+		}
+
+		let typos = usize::from(config.max_typos.unwrap_or(0));
+		row[needle.len()] + typos >= needle.len()
+	}
+
+	/// Whether the characters at `indices` appear in the term with the same case.
+	fn indices_match_case(&self, haystack: &str, indices: &[usize]) -> bool {
+		let mut needle = self.text.chars();
+		haystack
+			.chars()
+			.enumerate()
+			.filter(|(index, _)| indices.contains(index))
+			.all(|(_, ch)| needle.any(|wanted| wanted == ch))
+	}
+
+	/// Leftmost positions matching the term's characters with exact case.
+	fn case_sensitive_positions(&self, haystack: &str) -> Vec<usize> {
+		let mut needle = self.text.chars().peekable();
+		let mut positions = Vec::new();
+		for (index, ch) in haystack.chars().enumerate() {
+			if needle.peek() == Some(&ch) {
+				positions.push(index);
+				needle.next();
+			}
+		}
+		positions
+	}
+
+	/// Locate the term in `haystack`, returning its character and byte offsets.
+	fn locate(&self, haystack: &str) -> Option<(usize, usize)> {
+		self.find(haystack, self.kind)
+	}
+
+	fn find_substring(&self, haystack: &str) -> Option<(usize, usize)> {
+		self.find(haystack, TermKind::Exact)
+	}
+
+	fn find(&self, haystack: &str, kind: TermKind) -> Option<(usize, usize)> {
+		let needle: Vec<char> = self.text.chars().collect();
+		let chars: Vec<(usize, char)> = haystack.char_indices().collect();
+		if needle.len() > chars.len() {
+			return None;
+		}
+
+		let matches_at = |start: usize| {
+			chars[start..start + needle.len()]
+				.iter()
+				.zip(&needle)
+				.all(|((_, actual), wanted)| {
+					if self.case_sensitive {
+						actual == wanted
+					} else {
+						chars_eq_ignore_case(*actual, *wanted)
+					}
+				})
+		};
+		let byte_offset = |start: usize| chars.get(start).map_or(haystack.len(), |(byte, _)| *byte);
+		let last = chars.len() - needle.len();
+
+		let start = match kind {
+			TermKind::Fuzzy | TermKind::Negate => return None,
+			TermKind::Exact => (0..=last).find(|start| matches_at(*start))?,
+			TermKind::Prefix => matches_at(0).then_some(0)?,
+			TermKind::Suffix => matches_at(last).then_some(last)?,
+			TermKind::Full => (last == 0 && matches_at(0)).then_some(0)?,
+		};
+		Some((start, byte_offset(start)))
+	}
+}
+
+/// Alternatives joined with `|`; a candidate matches when any of them does.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TermGroup {
+	/// Terms in the group, in the order they appeared in the query.
+	pub alternatives: Vec<QueryTerm>,
+}
+
+impl TermGroup {
+	fn single(&self) -> Option<&QueryTerm> {
+		match self.alternatives.as_slice() {
+			[term] => Some(term),
+			_ => None,
+		}
+	}
+
+	/// Score the best matching alternative against `haystack`.
+	///
+	/// Returns the winning score together with the character indices it
+	/// matched, or `None` when no alternative matches.
+	#[must_use]
+	pub fn best_match(&self, haystack: &str, config: &Config) -> Option<(u16, Vec<usize>)> {
+		self.alternatives
+			.iter()
+			.filter_map(|term| term.match_indices(haystack, config))
+			.max_by_key(|(score, _)| *score)
+	}
+}
+
+/// Parsed representation of a raw query string.
+///
+/// A query is the conjunction of its groups: every group must match a
+/// candidate for it to be included in the results.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Query {
+	groups: Vec<TermGroup>,
+	filters: Vec<QueryFilter>,
+	case_sensitive: bool,
+}
+
+impl Query {
+	/// Parse a raw query string into groups of typed terms using smart case.
+	///
+	/// Whitespace separates groups, except around a standalone `|` which
+	/// joins its neighbours into a single group of alternatives.
+	#[must_use]
+	pub fn parse(raw: &str) -> Self {
+		Self::parse_with_case(raw, CaseMatching::Smart)
+	}
+
+	/// Parse a raw query string, comparing case according to `case_matching`.
+	///
+	/// Smart case looks at the whole query, so one uppercase letter makes
+	/// every term case-sensitive.
+	#[must_use]
+	pub fn parse_with_case(raw: &str, case_matching: CaseMatching) -> Self {
+		let case_sensitive = case_matching.is_sensitive(raw);
+		let mut groups: Vec<TermGroup> = Vec::new();
+		let mut filters = Vec::new();
+		let mut join_next = false;
+		for token in raw.split_whitespace() {
+			if token == "|" {
+				join_next = !groups.is_empty();
+				continue;
+			}
+			if let Some(filter) =
+				QueryFilter::parse(token).or_else(|| QueryFilter::path_prefix(token))
+			{
+				filters.push(filter);
+				join_next = false;
+				continue;
+			}
+
+			let term = QueryTerm::parse_with_case(token, case_sensitive);
+			match groups.last_mut() {
+				Some(group) if join_next => group.alternatives.push(term),
+				_ => groups.push(TermGroup {
+					alternatives: vec![term],
+				}),
+			}
+			join_next = false;
+		}
+
+		Self {
+			groups,
+			filters,
+			case_sensitive,
+		}
+	}
+
+	/// Parse a raw query string with the case matching and match mode of
+	/// `options`.
+	#[must_use]
+	pub fn parse_with_options(raw: &str, options: &SearchOptions) -> Self {
+		Self::parse_with_case(raw, options.case_matching).with_match_mode(options.match_mode)
+	}
+
+	/// Match plain terms according to `mode`.
+	///
+	/// [`MatchMode::Substring`] turns every fuzzy term into a substring term
+	/// so it is located and scored like `'term`; operator terms are kept.
+	#[must_use]
+	pub fn with_match_mode(mut self, mode: MatchMode) -> Self {
+		if mode == MatchMode::Substring {
+			for term in self
+				.groups
+				.iter_mut()
+				.flat_map(|group| group.alternatives.iter_mut())
+				.filter(|term| term.kind == TermKind::Fuzzy)
+			{
+				term.kind = TermKind::Exact;
+			}
+		}
+		self
+	}
+
+	/// Whether the query compares letter case exactly.
+	#[must_use]
+	pub fn is_case_sensitive(&self) -> bool {
+		self.case_sensitive
+	}
+
+	/// Return every group in the order it appeared in the query.
+	#[must_use]
+	pub fn groups(&self) -> &[TermGroup] {
+		&self.groups
+	}
+
+	/// Return every `key:value` filter in the order it appeared in the query.
+	#[must_use]
+	pub fn filters(&self) -> &[QueryFilter] {
+		&self.filters
+	}
+
+	/// Iterate over every parsed term, across all groups.
+	pub fn terms(&self) -> impl Iterator<Item = &QueryTerm> {
+		self.groups.iter().flat_map(|group| &group.alternatives)
+	}
+
+	/// Whether the query contains no terms at all.
+	///
+	/// Filters are not terms, so a query made only of filters is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.groups.is_empty()
+	}
+
+	/// Iterate over the standalone fuzzy terms, each of which must match.
+	///
+	/// Fuzzy terms inside `|` groups are not included.
+	pub fn fuzzy_terms(&self) -> impl Iterator<Item = &QueryTerm> {
+		self.groups
+			.iter()
+			.filter_map(TermGroup::single)
+			.filter(|term| term.is_fuzzy())
+	}
+
+	/// Whether any term, including `|` alternatives, is matched fuzzily.
+	#[must_use]
+	pub fn has_fuzzy_terms(&self) -> bool {
+		self.terms().any(QueryTerm::is_fuzzy)
+	}
+
+	/// Whether the query only excludes candidates and matches nothing itself.
+	///
+	/// Such queries (including empty ones) select the whole dataset minus any
+	/// negated or filtered-out candidates.
+	#[must_use]
+	pub fn is_filter_only(&self) -> bool {
+		self.groups
+			.iter()
+			.all(|group| group.single().is_some_and(QueryTerm::is_negation))
+	}
+
+	/// Whether the query contains any standalone negation terms.
+	#[must_use]
+	pub fn has_negations(&self) -> bool {
+		self.negations().next().is_some()
+	}
+
+	/// Whether [`Query::excludes`] can drop any candidate, i.e. the query has
+	/// negations or filters.
+	#[must_use]
+	pub fn has_exclusions(&self) -> bool {
+		self.has_negations() || !self.filters.is_empty()
+	}
+
+	/// Whether a negation term or a failed filter excludes `haystack` from
+	/// the results.
+	///
+	/// The haystack is taken to have no tags; see [`Query::excludes_entry`].
+	#[must_use]
+	pub fn excludes(&self, haystack: &str) -> bool {
+		self.excludes_entry(haystack, &[])
+	}
+
+	/// Whether a negation term or a failed filter excludes the candidate with
+	/// `key` and the `tags` after it from the results.
+	///
+	/// Negations only look at the key; `tag:` filters check the tags.
+	#[must_use]
+	pub fn excludes_entry(&self, key: &str, tags: &[&str]) -> bool {
+		self.negations().any(|term| term.occurs_in(key))
+			|| !self
+				.filters
+				.iter()
+				.all(|filter| filter.accepts_entry(key, tags))
+	}
+
+	fn negations(&self) -> impl Iterator<Item = &QueryTerm> {
+		self.groups
+			.iter()
+			.filter_map(TermGroup::single)
+			.filter(|term| term.is_negation())
+	}
+
+	/// Iterate over the standalone terms matched with a literal strategy.
+	pub fn literal_terms(&self) -> impl Iterator<Item = &QueryTerm> {
+		self.groups
+			.iter()
+			.filter_map(TermGroup::single)
+			.filter(|term| !term.is_fuzzy() && !term.is_negation())
+	}
+
+	/// Iterate over the groups containing more than one `|` alternative.
+	pub fn alternative_groups(&self) -> impl Iterator<Item = &TermGroup> {
+		self.groups
+			.iter()
+			.filter(|group| group.alternatives.len() > 1)
+	}
+
+	/// Score every literal term against `haystack`.
+	///
+	/// Returns `None` when any term fails to match and the combined score
+	/// otherwise (zero when the query has no literal terms).
+	#[must_use]
+	pub fn score_literal_terms(&self, haystack: &str, scoring: &Scoring) -> Option<u16> {
+		self.literal_terms().try_fold(0u16, |total, term| {
+			let score = term.score(haystack, scoring)?;
+			Some(total.saturating_add(score))
+		})
+	}
+
+	/// Character indices in `haystack` covered by literal terms.
+	#[must_use]
+	pub fn literal_indices(&self, haystack: &str) -> Vec<usize> {
+		self.literal_terms()
+			.filter_map(|term| term.literal_range(haystack))
+			.flatten()
+			.collect()
+	}
+
+	/// Whether every candidate matching this query is guaranteed to match
+	/// `broader` as well, so `broader`'s matches can stand in for the
+	/// dataset.
+	///
+	/// This holds when both compare case the same way, this query keeps all
+	/// of `broader`'s filters, and each of `broader`'s groups is implied by
+	/// one of this query's groups. A group implies an identical group, and a
+	/// literal term implies a looser term of the same kind: `'abc` implies
+	/// `'b`, `^abc` implies `^ab`, `abc$` implies `bc$` and `!b` implies
+	/// `!abc`. Fuzzy terms only imply themselves, since a longer fuzzy term
+	/// tolerates typos the shorter one does not.
+	#[must_use]
+	pub fn narrows(&self, broader: &Query) -> bool {
+		self.case_sensitive == broader.case_sensitive
+			&& broader
+				.filters
+				.iter()
+				.all(|filter| self.filters.contains(filter))
+			&& broader
+				.groups
+				.iter()
+				.all(|looser| self.groups.iter().any(|group| group_implies(group, looser)))
+	}
+
+	/// Sorted character indices in `haystack` to highlight for this query.
+	///
+	/// Fuzzy terms contribute frizbee's match positions, literal terms the
+	/// text they matched, and each `|` group the positions of whichever
+	/// alternative scored best. Positions matched by several terms are only
+	/// reported once.
+	#[must_use]
+	pub fn match_indices(&self, haystack: &str, config: &Config) -> Vec<usize> {
+		let mut indices = self.literal_indices(haystack);
+		for term in self.fuzzy_terms() {
+			if let Some((_, matched)) = term.match_indices(haystack, config) {
+				indices.extend(matched);
+			}
+		}
+		for group in self.alternative_groups() {
+			if let Some((_, matched)) = group.best_match(haystack, config) {
+				indices.extend(matched);
+			}
+		}
+		indices.sort_unstable();
+		indices.dedup();
+		indices
+	}
+}
+
+/// Whether every candidate matching `group` also matches `looser`.
+fn group_implies(group: &TermGroup, looser: &TermGroup) -> bool {
+	if group == looser {
+		return true;
+	}
+	let (Some(term), Some(looser)) = (group.single(), looser.single()) else {
+		return false;
+	};
+	if term.kind != looser.kind {
+		return false;
+	}
+	match term.kind {
+		TermKind::Exact => term.text.contains(&looser.text),
+		TermKind::Prefix => term.text.starts_with(&looser.text),
+		TermKind::Suffix => term.text.ends_with(&looser.text),
+		TermKind::Negate => looser.text.contains(&term.text),
+		TermKind::Fuzzy | TermKind::Full => false,
+	}
+}
+
+/// Derive the frizbee config for a single fuzzy alternative from the config
+/// of the whole pass, rescaling the typo budget to the alternative's length.
+pub(crate) fn config_for_alternative(term: &str, base: &Config) -> Config {
+	let mut config = base.clone();
+	if config.max_typos.is_some() {
+		config.max_typos = config_for_query(term, PREFILTER_ENABLE_THRESHOLD).max_typos;
+	}
+	config
+}
+
+fn chars_eq_ignore_case(left: char, right: char) -> bool {
+	left == right || left.to_lowercase().eq(right.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(raw: &str) -> Vec<(String, TermKind)> {
+		Query::parse(raw)
+			.terms()
+			.map(|term| (term.text.clone(), term.kind))
+			.collect()
+	}
+
+	#[test]
+	fn plain_terms_are_separate_fuzzy_terms() {
+		let query = Query::parse("  src   lib ");
+		let terms: Vec<&str> = query.fuzzy_terms().map(|term| term.text.as_str()).collect();
+		assert_eq!(terms, vec!["src", "lib"]);
+		assert_eq!(query.literal_terms().count(), 0);
+	}
+
+	#[test]
+	fn operators_select_term_kinds() {
+		assert_eq!(
+			kinds("main 'Lib ^src .rs$ ^Cargo.toml$"),
+			vec![
+				("main".to_string(), TermKind::Fuzzy),
+				("Lib".to_string(), TermKind::Exact),
+				("src".to_string(), TermKind::Prefix),
+				(".rs".to_string(), TermKind::Suffix),
+				("Cargo.toml".to_string(), TermKind::Full),
+			]
+		);
+	}
+
+	#[test]
+	fn negations_exclude_matching_candidates() {
+		let query = Query::parse_with_case("config !TEST", CaseMatching::Insensitive);
+		assert_eq!(query.fuzzy_terms().count(), 1);
+		assert!(query.has_negations());
+		assert!(!query.is_filter_only());
+		assert!(query.excludes("tests/config.rs"));
+		assert!(!query.excludes("src/config.rs"));
+		assert!(Query::parse("!test").is_filter_only());
+		assert!(!Query::parse("config !TEST").excludes("tests/config.rs"));
+	}
+
+	#[test]
+	fn known_filter_keys_are_stripped_from_the_needle() {
+		let query = Query::parse("ext:rs,toml main");
+		assert_eq!(
+			query.filters(),
+			&[QueryFilter::Extension(vec!["rs".into(), "toml".into()])]
+		);
+		let terms: Vec<_> = query.terms().map(|term| term.text.as_str()).collect();
+		assert_eq!(terms, vec!["main"]);
+		assert!(query.has_exclusions());
+		assert!(query.excludes("src/main.md"));
+		assert!(!query.excludes("src/main.rs"));
+
+		let filters_only = Query::parse("ext:md");
+		assert!(filters_only.is_empty());
+		assert!(filters_only.is_filter_only());
+	}
+
+	#[test]
+	fn tag_filters_combine_and_keep_the_remaining_terms() {
+		let query = Query::parse("tag:urgent plan tag:work");
+		assert_eq!(
+			query.filters(),
+			&[
+				QueryFilter::Tag("urgent".into()),
+				QueryFilter::Tag("work".into())
+			]
+		);
+		let terms: Vec<_> = query.terms().map(|term| term.text.as_str()).collect();
+		assert_eq!(terms, vec!["plan"]);
+
+		assert!(!query.excludes_entry("docs/plan.md", &["Work", "urgent"]));
+		assert!(query.excludes_entry("docs/plan.md", &["urgent"]));
+		assert!(query.excludes_entry("docs/plan.md", &[]));
+		assert!(query.excludes("docs/plan.md"));
+
+		let filters_only = Query::parse("tag:urgent");
+		assert!(filters_only.is_empty());
+		assert!(filters_only.is_filter_only());
+	}
+
+	#[test]
+	fn directory_tokens_filter_the_remaining_terms() {
+		let query = Query::parse("./src/ parser");
+		assert_eq!(query.filters(), &[QueryFilter::PathPrefix("src/".into())]);
+		let terms: Vec<_> = query.terms().map(|term| term.text.as_str()).collect();
+		assert_eq!(terms, vec!["parser"]);
+		// The prefix is case-sensitive without making the terms so.
+		assert!(!query.is_case_sensitive());
+		assert!(query.excludes("tests/parser.rs"));
+		assert!(query.excludes("SRC/parser.rs"));
+		assert!(!query.excludes("src/parser.rs"));
+	}
+
+	#[test]
+	fn unknown_filter_keys_are_plain_text() {
+		let query = Query::parse("lang:rs ext:");
+		assert!(query.filters().is_empty());
+		let terms: Vec<_> = query
+			.terms()
+			.map(|term| (term.text.as_str(), term.kind))
+			.collect();
+		assert_eq!(
+			terms,
+			vec![("lang:rs", TermKind::Fuzzy), ("ext:", TermKind::Fuzzy)]
+		);
+	}
+
+	#[test]
+	fn bare_operators_are_literal_fuzzy_text() {
+		assert_eq!(
+			kinds("' ! ^ $ ^$"),
+			vec![
+				("'".to_string(), TermKind::Fuzzy),
+				("!".to_string(), TermKind::Fuzzy),
+				("^".to_string(), TermKind::Fuzzy),
+				("$".to_string(), TermKind::Fuzzy),
+				("^$".to_string(), TermKind::Fuzzy),
+			]
+		);
+	}
+
+	#[test]
+	fn literal_terms_follow_smart_case() {
+		let scoring = Scoring::default();
+		assert!(
+			QueryTerm::parse("'lib")
+				.score("src/LIB.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			QueryTerm::parse("'LIB")
+				.score("src/lib.rs", &scoring)
+				.is_none()
+		);
+		assert!(
+			QueryTerm::parse("'LIB")
+				.score("src/LIB.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			Query::parse_with_case("'LIB", CaseMatching::Insensitive)
+				.score_literal_terms("src/lib.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			QueryTerm::parse("'lbi")
+				.score("src/lib.rs", &scoring)
+				.is_none()
+		);
+	}
+
+	#[test]
+	fn anchors_constrain_the_match_position() {
+		let scoring = Scoring::default();
+		assert!(
+			QueryTerm::parse("^src")
+				.score("src/lib.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			QueryTerm::parse("^lib")
+				.score("src/lib.rs", &scoring)
+				.is_none()
+		);
+		assert!(
+			QueryTerm::parse(".rs$")
+				.score("src/lib.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			QueryTerm::parse("src$")
+				.score("src/lib.rs", &scoring)
+				.is_none()
+		);
+		assert!(
+			QueryTerm::parse("^lib.rs$")
+				.score("lib.rs", &scoring)
+				.is_some()
+		);
+		assert!(
+			QueryTerm::parse("^lib$")
+				.score("lib.rs", &scoring)
+				.is_none()
+		);
+	}
+
+	#[test]
+	fn literal_scores_reward_boundaries() {
+		let scoring = Scoring::default();
+		let score = |raw: &str| QueryTerm::parse(raw).score("src/lib.rs", &scoring).unwrap();
+		assert!(score("'src") > score("'rc/"));
+		assert!(score("'lib") > score("'ib."));
+
+		let camel = |raw: &str| {
+			QueryTerm::parse(raw)
+				.score("searchPipeline", &scoring)
+				.unwrap()
+		};
+		assert_eq!(
+			camel("'pipe") - camel("'ipel"),
+			scoring.capitalization_bonus
+		);
+	}
+
+	#[test]
+	fn literal_indices_are_character_positions() {
+		let query = Query::parse("'lib .rs$");
+		assert_eq!(query.literal_indices("é/lib.rs"), vec![2, 3, 4, 5, 6, 7]);
+	}
+
+	#[test]
+	fn pipes_join_terms_into_alternative_groups() {
+		let query = Query::parse("src ^core | .rs$ | py$ !test | ");
+		let groups: Vec<usize> = query
+			.groups()
+			.iter()
+			.map(|group| group.alternatives.len())
+			.collect();
+		assert_eq!(groups, vec![1, 3, 1]);
+		assert_eq!(query.fuzzy_terms().count(), 1);
+		assert!(query.has_negations());
+		assert_eq!(query.literal_terms().count(), 0);
+	}
+
+	#[test]
+	fn leading_and_repeated_pipes_are_ignored() {
+		let query = Query::parse("| foo | | bar");
+		assert_eq!(query.groups().len(), 1);
+		assert_eq!(query.groups()[0].alternatives.len(), 2);
+	}
+
+	#[test]
+	fn highlights_come_from_the_winning_alternative() {
+		let query = Query::parse("'zzz | 'lib");
+		let config = Config::default();
+		assert_eq!(query.match_indices("src/lib.rs", &config), vec![4, 5, 6]);
+	}
+
+	#[test]
+	fn highlights_merge_every_term_once() {
+		let config = Config::default();
+		let query = Query::parse("src lib");
+		assert_eq!(
+			query.match_indices("src/lib.rs", &config),
+			vec![0, 1, 2, 4, 5, 6]
+		);
+
+		let overlapping = Query::parse("src 'sr");
+		assert_eq!(
+			overlapping.match_indices("src/lib.rs", &config),
+			vec![0, 1, 2]
+		);
+	}
+
+	#[test]
+	fn case_sensitive_fuzzy_terms_require_matching_case() {
+		let config = Config::default();
+		let term = QueryTerm::parse("Foo");
+		assert!(term.case_sensitive);
+		assert!(term.matches_case("src/Foo.rs", &config));
+		assert!(!term.matches_case("src/foo.rs", &config));
+
+		let typos = Config {
+			max_typos: Some(1),
+			..Config::default()
+		};
+		assert!(term.matches_case("src/Fo.rs", &typos));
+		assert!(QueryTerm::parse("foo").matches_case("src/FOO.rs", &config));
+	}
+
+	#[test]
+	fn case_sensitive_highlights_land_on_matching_case() {
+		let config = config_for_query("Foo", 10);
+		let query = Query::parse("Foo");
+		assert_eq!(query.match_indices("foo/Foo.rs", &config), vec![4, 5, 6]);
+		assert!(query.match_indices("foo/foo.rs", &config).is_empty());
+
+		let forced = Query::parse_with_case("foo", CaseMatching::Sensitive);
+		assert_eq!(forced.match_indices("Foo/foo.rs", &config), vec![4, 5, 6]);
+	}
+
+	#[test]
+	fn added_terms_and_longer_literals_narrow_a_query() {
+		let narrows =
+			|query: &str, broader: &str| Query::parse(query).narrows(&Query::parse(broader));
+
+		assert!(narrows("src", "src"));
+		assert!(narrows("src main", "src"));
+		assert!(narrows("main src", "src"));
+		assert!(narrows("'srcm", "'src"));
+		assert!(narrows("'xsrc", "'src"));
+		assert!(narrows("^srcm", "^src"));
+		assert!(narrows("main.rs$", "rs$"));
+		assert!(narrows("x !ma", "x !main"));
+		assert!(narrows("x ext:rs", "x ext:rs"));
+		assert!(narrows("x ext:rs", "x"));
+		assert!(narrows("a | b c", "a | b"));
+
+		assert!(!narrows("srcm", "src"), "fuzzy terms tolerate typos");
+		assert!(!narrows("src", "src main"));
+		assert!(!narrows("^srcm$", "^src$"));
+		assert!(!narrows("x !main", "x !ma"));
+		assert!(!narrows("x", "x ext:rs"));
+		assert!(!narrows("a | bc", "a | b"));
+		assert!(!narrows("'srcM", "'src"), "smart case changes every term");
+	}
+
+	#[test]
+	fn substring_mode_matches_plain_terms_literally() {
+		let options = SearchOptions::default().with_match_mode(MatchMode::Substring);
+		let query = Query::parse_with_options("mn ^src rs$ !test a | b", &options);
+		let kinds: Vec<_> = query.terms().map(|term| term.kind).collect();
+		assert_eq!(
+			kinds,
+			[
+				TermKind::Exact,
+				TermKind::Prefix,
+				TermKind::Suffix,
+				TermKind::Negate,
+				TermKind::Exact,
+				TermKind::Exact,
+			]
+		);
+		assert!(!query.has_fuzzy_terms());
+
+		let query = Query::parse_with_options("ma", &options);
+		assert_eq!(query.literal_indices("src/main.rs"), [4, 5]);
+		let scoring = Scoring::default();
+		assert!(query.score_literal_terms("src/main.rs", &scoring).is_some());
+		assert!(query.score_literal_terms("src/mxa.rs", &scoring).is_none());
+
+		let fuzzy = Query::parse_with_options("mn", &SearchOptions::default());
+		assert!(fuzzy.has_fuzzy_terms());
+	}
+}
@@ -0,0 +1,107 @@
+//! Allocations per frame when redrawing a static result set.
+//!
+//! Run with `cargo bench -p frz-tui --bench row_cache`. Each frame builds the
+//! rows for 5,000 highlighted results, once from scratch and once through a
+//! warm [`RowCache`], and reports the heap allocations and time per frame.
+#![allow(unused_crate_dependencies)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use frizbee::Config;
+use frz_core::filesystem::search::{FileRow, Query};
+use frz_tui::components::rows::{RowCache, RowLayout, build_file_rows};
+use ratatui::style::Style;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		unsafe { System.alloc(layout) }
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		unsafe { System.dealloc(ptr, layout) }
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		unsafe { System.realloc(ptr, layout, new_size) }
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ROWS: usize = 5_000;
+const FRAMES: usize = 50;
+
+fn measure(label: &str, mut frame: impl FnMut()) {
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+	for _ in 0..FRAMES {
+		frame();
+	}
+	let elapsed = start.elapsed();
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+	println!(
+		"{label:>8}: {:>9} allocations/frame, {:>8.2?}/frame",
+		allocations / FRAMES,
+		elapsed / FRAMES as u32,
+	);
+}
+
+fn main() {
+	let files: Vec<FileRow> = (0..ROWS)
+		.map(|i| FileRow::new(format!("src/module_{}/component_{i}/mod.rs", i % 97)))
+		.collect();
+	let filtered: Vec<usize> = (0..ROWS).collect();
+	let scores: Vec<u16> = (0..ROWS).map(|i| (ROWS - i) as u16).collect();
+	let query = Query::parse("comp mod");
+	let config = Config::default();
+	let widths = [48, 8];
+	let style = Style::default();
+
+	measure("fresh", || {
+		black_box(build_file_rows(
+			&filtered,
+			&scores,
+			&files,
+			Some((&query, config.clone())),
+			style,
+			Some(&widths),
+			false,
+		));
+	});
+
+	let mut cache = RowCache::default();
+	let layout = RowLayout {
+		highlight_style: style,
+		column_widths: Some(&widths),
+		show_sizes: false,
+		show_tags: false,
+		marked: None,
+		positions: None,
+	};
+	let _ = cache.build_file_rows(
+		&filtered,
+		&scores,
+		&files,
+		Some((&query, config.clone())),
+		layout,
+	);
+	measure("cached", || {
+		black_box(cache.build_file_rows(
+			&filtered,
+			&scores,
+			&files,
+			Some((&query, config.clone())),
+			layout,
+		));
+	});
+}
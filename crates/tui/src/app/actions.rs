@@ -1,74 +1,239 @@
-use anyhow::Result;
-use frz_core::filesystem::search::SearchOutcome;
+use std::collections::HashSet;
+
+use frz_core::error::Result;
+use frz_core::filesystem::search::{FileRow, SearchOutcome, SearchSelection};
 use ratatui::crossterm::event::{
 	KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
 use ratatui::layout::Rect;
 
 use super::App;
-use crate::components::{copy_to_clipboard, extract_selected_text, point_in_rect};
+use super::results::ResultsState;
+use crate::components::{extract_selected_text, point_in_rect};
+use crate::keys::{Action, key_name, same_key};
+
+/// A selected path inserted into the query with Alt+.
+///
+/// Pressing Alt+. again on the same row replaces the inserted basename with
+/// the row's full path.
+#[derive(Clone, Debug)]
+pub(crate) struct PathInsertion {
+	path: String,
+	expanded: bool,
+}
 
 impl<'a> App<'a> {
 	/// Process a keyboard event and return a result if the user exits.
 	pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Result<Option<SearchOutcome>> {
+		let previous_insertion = self.last_path_insertion.take();
+		self.restore_selection = None;
+		if self.handle_preview_find_key(key) {
+			return Ok(None);
+		}
+		// Expected keys win over bindings, like fzf's --expect
+		if let Some(expected) = self
+			.expect_keys
+			.iter()
+			.find(|expected| same_key(expected, &key))
+		{
+			let name = key_name(expected);
+			return Ok(Some(self.accept(Some(name))));
+		}
+		if let Some(action) = self.key_bindings.action_for(&key) {
+			return Ok(self.perform(action));
+		}
 		match key.code {
-			KeyCode::Esc => {
-				return Ok(Some(SearchOutcome {
+			// Ctrl+Q to copy the query to the clipboard
+			KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				if let Err(error) = (self.clipboard)(self.search_input.text()) {
+					self.show_status(format!("Copy failed: {error}"));
+				}
+			}
+			// Alt+C to cycle between smart, case-sensitive and case-insensitive matching
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::ALT) => {
+				self.cycle_case_matching();
+			}
+			// Ctrl+F to switch between fuzzy and substring matching
+			KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				self.toggle_match_mode();
+			}
+			// Ctrl+T to switch between matching paths and matching tags
+			KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				self.toggle_tag_search();
+			}
+			// Alt+S to cycle between score, name and modification time ordering
+			KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+				self.cycle_sort_key();
+			}
+			// Alt+. to insert the selected row's basename (full path on repeat)
+			KeyCode::Char('.') if key.modifiers.contains(KeyModifiers::ALT) => {
+				self.insert_selected_path(previous_insertion);
+			}
+			// Alt+1..Alt+9 to accept the Nth row shown in the table, alone even
+			// when other rows are marked
+			KeyCode::Char(digit @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+				let position = digit as usize - '1' as usize;
+				if self.results.select_visible(position) {
+					self.results.marked.clear();
+					return Ok(Some(self.accept(None)));
+				}
+			}
+			// Shift+PageUp/PageDown to scroll preview
+			KeyCode::PageUp
+				if self.preview.enabled && key.modifiers.contains(KeyModifiers::SHIFT) =>
+			{
+				self.scroll_preview_up(10);
+			}
+			KeyCode::PageDown
+				if self.preview.enabled && key.modifiers.contains(KeyModifiers::SHIFT) =>
+			{
+				self.scroll_preview_down(10);
+			}
+			_ => {
+				if self.search_input.input(key) {
+					self.mark_query_dirty_from_user_input();
+					self.request_search_debounced();
+				}
+			}
+		}
+		Ok(None)
+	}
+
+	/// Accept the current selection, recording the expected key that did so.
+	fn accept(&mut self, accepted_with: Option<String>) -> SearchOutcome {
+		// Skip the rest of the debounce interval so the selection
+		// reflects the query as typed.
+		if self.flush_debounced_search(true) {
+			self.wait_for_search_results();
+		}
+		self.search_input.commit_to_history();
+		let selection = self.current_selection();
+		let selections = self.current_selections();
+		let scores = self.selection_scores(&selections);
+		let position = selection
+			.as_ref()
+			.and_then(|selection| self.preview_position(selection));
+		SearchOutcome {
+			accepted: true,
+			selection,
+			selections,
+			scores,
+			query: self.search_input.text().to_string(),
+			position,
+			accepted_with,
+		}
+	}
+
+	/// Run a bound [`Action`], returning the outcome if it ends the search.
+	fn perform(&mut self, action: Action) -> Option<SearchOutcome> {
+		match action {
+			Action::Cancel => {
+				return Some(SearchOutcome {
 					accepted: false,
 					selection: None,
+					selections: Vec::new(),
+					scores: Vec::new(),
 					query: self.search_input.text().to_string(),
-				}));
+					position: None,
+					accepted_with: None,
+				});
 			}
-			KeyCode::Enter => {
-				let selection = self.current_selection();
-				return Ok(Some(SearchOutcome {
-					accepted: true,
-					selection,
-					query: self.search_input.text().to_string(),
-				}));
+			Action::Accept => return Some(self.accept(None)),
+			// Moving on an empty or recalled prompt browses query history
+			Action::MoveUp if self.search_input.history_previous() => {
+				self.mark_query_dirty_from_user_input();
+				self.request_search_debounced();
 			}
-			KeyCode::Tab => {
-				self.mark_query_dirty();
-				self.switch_mode();
+			Action::MoveDown if self.search_input.history_next() => {
+				self.mark_query_dirty_from_user_input();
+				self.request_search_debounced();
 			}
-			// Ctrl+P to toggle preview pane
-			KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-				self.toggle_preview();
+			Action::MoveUp => {
+				self.move_selection_up();
+				self.refresh_preview_if_enabled();
 			}
-			_ => match key.code {
-				KeyCode::Up => {
-					self.move_selection_up();
-					if self.preview.enabled {
-						self.update_preview();
-					}
-				}
-				KeyCode::Down => {
-					self.move_selection_down();
-					if self.preview.enabled {
-						self.update_preview();
-					}
-				}
-				// Ctrl+Up/Down or Shift+Up/Down to scroll preview
-				KeyCode::PageUp if self.preview.enabled => {
-					self.scroll_preview_up(10);
-				}
-				KeyCode::PageDown if self.preview.enabled => {
-					self.scroll_preview_down(10);
-				}
-				_ => {
-					if self.search_input.input(key) {
-						self.mark_query_dirty_from_user_input();
-						self.request_search();
+			Action::MoveDown => {
+				self.move_selection_down();
+				self.refresh_preview_if_enabled();
+			}
+			Action::MoveToTop => {
+				self.results.select_clamped(0);
+				self.refresh_preview_if_enabled();
+			}
+			Action::MoveToBottom => {
+				self.results.select_clamped(usize::MAX);
+				self.load_more_results();
+				self.refresh_preview_if_enabled();
+			}
+			Action::PageUp => {
+				let rows = self.results.viewport_rows().max(1) as isize;
+				self.results.move_selection_by(-rows);
+				self.refresh_preview_if_enabled();
+			}
+			Action::PageDown => {
+				let rows = self.results.viewport_rows().max(1) as isize;
+				self.results.move_selection_by(rows);
+				self.load_more_results();
+				self.refresh_preview_if_enabled();
+			}
+			Action::TogglePreview => self.toggle_preview(),
+			Action::ToggleLineNumbers => self.toggle_line_numbers(),
+			Action::ToggleMarkdown => self.toggle_markdown_preview(),
+			Action::ToggleDebugOverlay => self.debug_overlay = !self.debug_overlay,
+			Action::FindInPreview => self.open_preview_find(),
+			Action::MarkNext => {
+				self.toggle_mark();
+				self.move_selection_down();
+				self.refresh_preview_if_enabled();
+			}
+			Action::MarkPrevious => {
+				self.toggle_mark();
+				self.move_selection_up();
+				self.refresh_preview_if_enabled();
+			}
+			Action::MarkAll => {
+				let ids = self.filtered_ids();
+				self.results.marked.extend(ids);
+			}
+			Action::UnmarkAll => self.results.marked.clear(),
+			Action::InvertMarks => {
+				for id in self.filtered_ids() {
+					if !self.results.marked.remove(&id) {
+						self.results.marked.insert(id);
 					}
 				}
-			},
+			}
+			Action::CopyPath => self.copy_selected_path(false),
+			Action::CopyAbsolutePath => self.copy_selected_path(true),
+			Action::OpenInEditor => self.request_edit(),
+		}
+		None
+	}
+
+	/// Stable ids of every row matching the query, not just the rendered
+	/// ones.
+	fn filtered_ids(&self) -> Vec<u64> {
+		self.results
+			.buffers
+			.filtered
+			.iter()
+			.filter_map(|&index| self.data.files.get(index))
+			.map(FileRow::stable_id)
+			.collect()
+	}
+
+	pub(crate) fn refresh_preview_if_enabled(&mut self) {
+		if self.preview.enabled {
+			self.update_preview();
 		}
-		Ok(None)
 	}
 
 	pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent) {
 		self.update_preview_hover(mouse.column, mouse.row);
 		self.update_results_hover(mouse.column, mouse.row);
+		if mouse.kind != MouseEventKind::Moved {
+			self.restore_selection = None;
+		}
 
 		match mouse.kind {
 			MouseEventKind::ScrollUp if self.preview.enabled && self.preview.hovered => {
@@ -121,10 +286,16 @@ impl<'a> App<'a> {
 				self.preview.selection.clear();
 			}
 			MouseEventKind::Down(MouseButton::Left) if self.results.hovered => {
-				if self.select_result_at(mouse.column, mouse.row) && self.preview.enabled {
-					self.update_preview();
+				self.results.range_anchor = None;
+				if self.select_result_at(mouse.column, mouse.row) {
+					self.results.range_anchor = self.selected_row_id();
+					self.load_more_results();
+					if self.preview.enabled {
+						self.update_preview();
+					}
 				}
 				self.results.dragging = true;
+				self.results.range_marked.clear();
 				self.preview.selection.clear();
 			}
 			MouseEventKind::Up(MouseButton::Left) => {
@@ -136,6 +307,8 @@ impl<'a> App<'a> {
 				self.results.dragging = false;
 				self.results.dragging_scrollbar = false;
 				self.results.drag_anchor = None;
+				self.results.range_anchor = None;
+				self.results.range_marked.clear();
 				self.preview.drag_anchor = None;
 				self.preview.dragging = false;
 			}
@@ -151,9 +324,13 @@ impl<'a> App<'a> {
 			MouseEventKind::Drag(MouseButton::Left) if self.results.dragging_scrollbar => {
 				self.drag_results_scrollbar_to(mouse.row);
 			}
+			// Dragging over the rows marks the range since the pressed row
 			MouseEventKind::Drag(MouseButton::Left) if self.results.dragging => {
-				if self.select_result_at(mouse.column, mouse.row) && self.preview.enabled {
-					self.update_preview();
+				if self.select_result_at(mouse.column, mouse.row) {
+					self.mark_dragged_range();
+					if self.preview.enabled {
+						self.update_preview();
+					}
 				}
 			}
 			_ => {}
@@ -185,15 +362,124 @@ impl<'a> App<'a> {
 		// Get the inner area (excluding border)
 		let inner = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
 
-		if let Some(text) =
-			extract_selected_text(&self.preview.wrapped_lines, &self.preview.selection, inner)
-		{
-			let _ = copy_to_clipboard(&text);
+		if let Some(text) = extract_selected_text(
+			&self.preview.wrapped_lines,
+			&self.preview.selection,
+			inner,
+			self.preview.content.gutter_width,
+		) {
+			let _ = (self.clipboard)(&text);
 		}
 	}
 
-	fn switch_mode(&mut self) {
-		// No-op now that we only have one mode
+	/// Copy the highlighted row's path to the clipboard, resolved under the
+	/// search root when `absolute`, and confirm it in the status line.
+	fn copy_selected_path(&mut self, absolute: bool) {
+		let Some(SearchSelection::File(file)) = self.current_selection() else {
+			return;
+		};
+		let path = if absolute {
+			self.data.resolve_file_path(&file).display().to_string()
+		} else {
+			file.path
+		};
+		match (self.clipboard)(&path) {
+			Ok(()) => self.show_status(format!("Copied {path}")),
+			Err(error) => self.show_status(format!("Copy failed: {error}")),
+		}
+	}
+
+	/// Insert the selected row's basename into the query at the cursor.
+	///
+	/// A repeated press on the same row undoes the basename and inserts the
+	/// full path instead, so either form can be reverted with a single undo.
+	fn insert_selected_path(&mut self, previous: Option<PathInsertion>) {
+		let Some(SearchSelection::File(file)) = self.current_selection() else {
+			return;
+		};
+
+		let expand =
+			previous.is_some_and(|insertion| !insertion.expanded && insertion.path == file.path);
+		let mut changed = expand && self.search_input.revert_insertion(basename(&file.path));
+		let text = if expand {
+			file.path.as_str()
+		} else {
+			basename(&file.path)
+		};
+		changed |= self.search_input.insert_str(text);
+
+		self.last_path_insertion = Some(PathInsertion {
+			path: file.path.clone(),
+			expanded: expand,
+		});
+		if changed {
+			self.mark_query_dirty_from_user_input();
+			self.request_search_debounced();
+		}
+	}
+
+	/// [Stable id](FileRow::stable_id) of the highlighted row.
+	fn selected_row_id(&self) -> Option<u64> {
+		self.results
+			.table_state
+			.selected()
+			.and_then(|selected| self.results.buffers.filtered.get(selected))
+			.and_then(|&index| self.data.files.get(index))
+			.map(FileRow::stable_id)
+	}
+
+	/// Mark the highlighted row for a multi-selection, or unmark it.
+	fn toggle_mark(&mut self) {
+		let Some(id) = self.selected_row_id() else {
+			return;
+		};
+		if !self.results.marked.remove(&id) {
+			self.results.marked.insert(id);
+		}
+	}
+
+	/// Mark every row between the one a drag started on and the highlighted
+	/// one, unmarking rows the drag marked before that fell out of the range.
+	///
+	/// Rows marked before the drag stay marked. Nothing is marked once the
+	/// row the drag started on is no longer among the results.
+	fn mark_dragged_range(&mut self) {
+		let (Some(anchor), Some(selected)) = (
+			self.results.range_anchor,
+			self.results.table_state.selected(),
+		) else {
+			return;
+		};
+		let Some(anchor) = self.results.buffers.filtered.iter().position(|&index| {
+			self.data
+				.files
+				.get(index)
+				.is_some_and(|file| file.stable_id() == anchor)
+		}) else {
+			return;
+		};
+		let range = anchor.min(selected)..=anchor.max(selected);
+		let ids: HashSet<u64> = self
+			.results
+			.buffers
+			.filtered
+			.get(range)
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|&index| self.data.files.get(index))
+			.map(FileRow::stable_id)
+			.collect();
+		let ResultsState {
+			marked,
+			range_marked,
+			..
+		} = &mut self.results;
+		range_marked.retain(|id| ids.contains(id) || !marked.remove(id));
+		for id in ids {
+			if marked.insert(id) {
+				range_marked.insert(id);
+			}
+		}
 	}
 
 	fn move_selection_up(&mut self) {
@@ -211,6 +497,7 @@ impl<'a> App<'a> {
 				self.results.table_state.select(Some(selected + 1));
 			}
 		}
+		self.load_more_results();
 	}
 
 	fn preview_scrollbar_contains(&self, column: u16, row: u16) -> bool {
@@ -262,6 +549,7 @@ impl<'a> App<'a> {
 		};
 
 		self.preview.scroll = new_scroll.min(metrics.max_scroll);
+		self.preview.scrolled_by_user = true;
 		self.update_scrollbar_state();
 		true
 	}
@@ -317,6 +605,7 @@ impl<'a> App<'a> {
 		// Keep selection in range without resetting offset.
 		let new_selection = new_offset.min(content_length.saturating_sub(1));
 		*self.results.table_state.selected_mut() = Some(new_selection);
+		self.load_more_results();
 
 		// Update preview if enabled
 		if self.preview.enabled {
@@ -327,6 +616,11 @@ impl<'a> App<'a> {
 	}
 }
 
+/// Final component of a `/`-separated row path.
+fn basename(path: &str) -> &str {
+	path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
 fn scrollbar_thumb_height(track_height: usize, viewport_len: usize, content_len: usize) -> usize {
 	if track_height == 0 || content_len == 0 {
 		return 0;
@@ -387,3 +681,689 @@ fn drag_with_anchor(
 	let desired_top = relative.saturating_sub(anchor_val).min(track_span);
 	Some(max_position.saturating_mul(desired_top) / track_span)
 }
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use std::time::{Duration, UNIX_EPOCH};
+
+	use frz_core::filesystem::search::{CaseMatching, FileRow, MatchMode, SearchData, SortKey};
+
+	use super::*;
+	use crate::keys::KeyBindings;
+
+	fn app_with_selection(path: &str) -> App<'static> {
+		let mut data = SearchData::new();
+		data.files = vec![FileRow::new(path)];
+		let mut app = App::new(data);
+		app.mark_query_dirty();
+		app.request_search();
+		app.pump_search_results_until(|app| app.current_selection().is_some());
+		app
+	}
+
+	fn press(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+		app.handle_key(KeyEvent::new(code, modifiers)).unwrap();
+	}
+
+	#[test]
+	fn basename_handles_multibyte_components() {
+		assert_eq!(basename("ドキュメント/日本語.md"), "日本語.md");
+		assert_eq!(basename("naïve.txt"), "naïve.txt");
+	}
+
+	#[test]
+	fn alt_dot_inserts_basename_then_full_path() {
+		let mut app = app_with_selection("ドキュメント/日本語.md");
+
+		press(&mut app, KeyCode::Char('.'), KeyModifiers::ALT);
+		assert_eq!(app.search_input.text(), "日本語.md");
+
+		press(&mut app, KeyCode::Char('.'), KeyModifiers::ALT);
+		assert_eq!(app.search_input.text(), "ドキュメント/日本語.md");
+
+		press(&mut app, KeyCode::Char('u'), KeyModifiers::CONTROL);
+		assert_eq!(app.search_input.text(), "");
+	}
+
+	#[test]
+	fn alt_dot_expands_the_basename_without_its_undo_history() {
+		let mut app = app_with_selection("src/main.rs");
+		app.search_input.set_text("x ");
+		app.search_input
+			.textarea_mut()
+			.move_cursor(tui_textarea::CursorMove::End);
+
+		press(&mut app, KeyCode::Char('.'), KeyModifiers::ALT);
+		assert_eq!(app.search_input.text(), "x main.rs");
+		// Nothing is left to undo, so the basename is deleted instead.
+		app.search_input.textarea_mut().set_max_histories(0);
+		press(&mut app, KeyCode::Char('.'), KeyModifiers::ALT);
+		assert_eq!(app.search_input.text(), "x src/main.rs");
+	}
+
+	#[test]
+	fn failed_query_copies_are_reported() {
+		let mut app = app_with_selection("src/main.rs");
+		app.clipboard = |_| Err("no clipboard".into());
+
+		press(&mut app, KeyCode::Char('q'), KeyModifiers::CONTROL);
+		assert_eq!(app.current_status(), Some("Copy failed: no clipboard"));
+	}
+
+	#[test]
+	fn alt_dot_inserts_at_cursor() {
+		let mut app = app_with_selection("src/ß.rs");
+		app.search_input.set_text("x");
+		app.search_input
+			.textarea_mut()
+			.move_cursor(tui_textarea::CursorMove::Head);
+
+		press(&mut app, KeyCode::Char('.'), KeyModifiers::ALT);
+		assert_eq!(app.search_input.text(), "ß.rsx");
+	}
+
+	#[test]
+	fn alt_c_cycles_case_matching_and_reruns_the_query() {
+		let mut app = app_with_selection("src/Lib.rs");
+		app.search_input.set_text("lib");
+		app.mark_query_dirty_from_user_input();
+		app.request_search();
+		let wait_for = |app: &mut App, len: usize| {
+			app.pump_search_results_until(|app| app.filtered_len() == len);
+			assert_eq!(app.filtered_len(), len);
+		};
+		wait_for(&mut app, 1);
+
+		press(&mut app, KeyCode::Char('c'), KeyModifiers::ALT);
+		assert_eq!(app.search_options.case_matching, CaseMatching::Sensitive);
+		wait_for(&mut app, 0);
+
+		press(&mut app, KeyCode::Char('c'), KeyModifiers::ALT);
+		assert_eq!(app.search_options.case_matching, CaseMatching::Insensitive);
+		wait_for(&mut app, 1);
+
+		press(&mut app, KeyCode::Char('c'), KeyModifiers::ALT);
+		assert_eq!(app.search_options.case_matching, CaseMatching::Smart);
+	}
+
+	#[test]
+	fn ctrl_f_toggles_substring_matching_and_reruns_the_query() {
+		let mut app = app_with_selection("src/main.rs");
+		app.search_input.set_text("smn");
+		app.mark_query_dirty_from_user_input();
+		app.request_search();
+		let wait_for = |app: &mut App, len: usize| {
+			app.pump_search_results_until(|app| app.filtered_len() == len);
+			assert_eq!(app.filtered_len(), len);
+		};
+		wait_for(&mut app, 1);
+		assert_eq!(app.match_mode_label(), None);
+
+		press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL);
+		assert_eq!(app.search_options.match_mode, MatchMode::Substring);
+		assert_eq!(app.match_mode_label(), Some("Substring"));
+		wait_for(&mut app, 0);
+
+		app.search_input.set_text("main");
+		app.mark_query_dirty_from_user_input();
+		app.request_search();
+		wait_for(&mut app, 1);
+
+		press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL);
+		assert_eq!(app.search_options.match_mode, MatchMode::Fuzzy);
+	}
+
+	#[test]
+	fn ctrl_t_matches_tags_instead_of_paths() {
+		let mut data = SearchData::new();
+		data.files = vec![
+			FileRow::new("src/urgent.rs"),
+			FileRow::new("docs/plan.md").with_tags(["urgent", "work"]),
+			FileRow::new("notes/todo.txt").with_tags(["urgent"]),
+		];
+		let mut app = App::new(data);
+		app.search_input.set_text("'urgent !work");
+		app.mark_query_dirty_from_user_input();
+		app.request_search();
+		app.wait_for_search_results();
+		assert_eq!(app.filtered_len(), 3);
+
+		press(&mut app, KeyCode::Char('t'), KeyModifiers::CONTROL);
+		app.wait_for_search_results();
+		let Some(SearchSelection::File(file)) = app.current_selection() else {
+			panic!("expected a file");
+		};
+		assert_eq!(file.path, "notes/todo.txt");
+		assert_eq!(app.filtered_len(), 1);
+		assert!(app.progress_status().0.starts_with("Tags"));
+
+		press(&mut app, KeyCode::Char('t'), KeyModifiers::CONTROL);
+		app.wait_for_search_results();
+		assert_eq!(app.filtered_len(), 3);
+	}
+
+	#[test]
+	fn alt_s_orders_the_empty_query_by_modification_time() {
+		let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+		let mut data = SearchData::new();
+		data.files = vec![
+			FileRow::new("a.rs").with_modified(at(1)),
+			FileRow::new("b.rs").with_modified(at(3)),
+			FileRow::new("c.rs").with_modified(at(2)),
+		];
+		let mut app = App::new(data);
+		let wait_for = |app: &mut App, first: &str| {
+			app.mark_query_dirty();
+			app.request_search();
+			app.pump_search_results_until(|app| selected_path(app).as_deref() == Some(first));
+			assert_eq!(selected_path(app).as_deref(), Some(first));
+		};
+		wait_for(&mut app, "a.rs");
+
+		press(&mut app, KeyCode::Char('s'), KeyModifiers::ALT);
+		assert_eq!(app.sort_key, SortKey::Name);
+		press(&mut app, KeyCode::Char('s'), KeyModifiers::ALT);
+		assert_eq!(app.sort_key, SortKey::ModifiedTime);
+		wait_for(&mut app, "b.rs");
+
+		press(&mut app, KeyCode::Char('s'), KeyModifiers::ALT);
+		assert_eq!(app.sort_key, SortKey::Score);
+		wait_for(&mut app, "a.rs");
+	}
+
+	fn selected_path(app: &App) -> Option<String> {
+		match app.current_selection() {
+			Some(SearchSelection::File(file)) => Some(file.path),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn debounced_typing_waits_for_idle_input() {
+		let mut app = app_with_selection("src/lib.rs");
+		app.set_search_debounce(Duration::from_millis(50));
+
+		press(&mut app, KeyCode::Char('l'), KeyModifiers::NONE);
+		assert_eq!(app.search_input.text(), "l");
+		assert!(
+			!app.search.is_in_flight(),
+			"query issued before the interval"
+		);
+		assert!(!app.flush_debounced_search(false));
+
+		std::thread::sleep(Duration::from_millis(60));
+		assert!(app.flush_debounced_search(false));
+		assert!(!app.flush_debounced_search(false));
+	}
+
+	#[test]
+	fn zero_debounce_searches_on_every_change() {
+		let mut app = app_with_selection("src/lib.rs");
+		press(&mut app, KeyCode::Char('l'), KeyModifiers::NONE);
+		assert!(app.search.is_in_flight());
+		assert!(!app.flush_debounced_search(true));
+	}
+
+	#[test]
+	fn enter_flushes_a_pending_debounced_query() {
+		let mut data = SearchData::new();
+		data.files = vec![FileRow::new("alpha.rs"), FileRow::new("beta.rs")];
+		let mut app = App::new(data);
+		app.mark_query_dirty();
+		app.request_search();
+		app.wait_for_search_results();
+		assert_eq!(selected_path(&app).as_deref(), Some("alpha.rs"));
+
+		app.set_search_debounce(Duration::from_secs(3600));
+		press(&mut app, KeyCode::Char('b'), KeyModifiers::NONE);
+		press(&mut app, KeyCode::Char('e'), KeyModifiers::NONE);
+		let outcome = app
+			.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+			.unwrap()
+			.expect("enter accepts");
+		assert_eq!(outcome.query, "be");
+		assert!(matches!(
+			outcome.selection,
+			Some(SearchSelection::File(file)) if file.path == "beta.rs"
+		));
+	}
+
+	fn app_with_rows(paths: &[&str]) -> App<'static> {
+		let mut data = SearchData::new();
+		data.files = paths.iter().map(|path| FileRow::new(*path)).collect();
+		let mut app = App::new(data);
+		app.mark_query_dirty();
+		app.request_search();
+		app.wait_for_search_results();
+		app
+	}
+
+	fn accept(app: &mut App) -> SearchOutcome {
+		app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+			.unwrap()
+			.expect("enter accepts")
+	}
+
+	fn paths(outcome: &SearchOutcome) -> Vec<&str> {
+		outcome
+			.selected_files()
+			.map(|file| file.path.as_str())
+			.collect()
+	}
+
+	fn marked_paths<'a>(app: &'a App) -> Vec<&'a str> {
+		app.data
+			.files
+			.iter()
+			.filter(|file| app.results.marked.contains(&file.stable_id()))
+			.map(|file| file.path.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn tab_toggles_marks_and_advances() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs"]);
+
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		assert_eq!(selected_path(&app).as_deref(), Some("b.rs"));
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		assert_eq!(app.results.marked.len(), 2);
+
+		// Shift+Tab marks "c.rs", then unmarks "b.rs" on the way back up.
+		press(&mut app, KeyCode::BackTab, KeyModifiers::SHIFT);
+		press(&mut app, KeyCode::BackTab, KeyModifiers::SHIFT);
+		assert_eq!(marked_paths(&app), ["a.rs", "c.rs"]);
+		assert_eq!(selected_path(&app).as_deref(), Some("a.rs"));
+	}
+
+	#[test]
+	fn marks_survive_refiltering_and_match_mode_switches() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "lib.rs"]);
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		let search = |app: &mut App, query: &str, len: usize| {
+			app.search_input.set_text(query);
+			app.mark_query_dirty_from_user_input();
+			app.request_search();
+			app.pump_search_results_until(|app| app.filtered_len() == len);
+			assert_eq!(app.filtered_len(), len, "{query:?}");
+		};
+
+		search(&mut app, "'lib", 1);
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL);
+		search(&mut app, "b.rs", 1);
+		assert_eq!(marked_paths(&app), ["a.rs", "lib.rs"]);
+
+		// Hidden marked rows are still confirmed.
+		let outcome = accept(&mut app);
+		assert_eq!(paths(&outcome), vec!["a.rs", "lib.rs"]);
+		assert!(outcome.scores[0].is_none(), "a.rs does not match `b.rs`");
+		assert!(outcome.scores[1].is_some());
+	}
+
+	#[test]
+	fn enter_confirms_every_marked_row() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs"]);
+		press(&mut app, KeyCode::Down, KeyModifiers::NONE);
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		app.results.table_state.select(Some(0));
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+
+		let outcome = accept(&mut app);
+		assert_eq!(paths(&outcome), vec!["a.rs", "b.rs"]);
+		assert!(outcome.scores.iter().all(Option::is_some));
+		assert!(matches!(
+			outcome.selection,
+			Some(SearchSelection::File(file)) if file.path == "b.rs"
+		));
+	}
+
+	#[test]
+	fn cleared_marks_fall_back_to_the_highlighted_row() {
+		let mut app = app_with_rows(&["a.rs", "b.rs"]);
+		press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+		press(&mut app, KeyCode::Char('u'), KeyModifiers::ALT);
+		assert!(app.results.marked.is_empty());
+
+		let outcome = accept(&mut app);
+		assert_eq!(paths(&outcome), vec!["b.rs"]);
+
+		let outcome = app
+			.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+			.unwrap()
+			.expect("esc cancels");
+		assert!(outcome.selections.is_empty());
+	}
+
+	#[test]
+	fn remapped_accept_fires_on_its_new_key() {
+		let mut app = app_with_rows(&["a.rs", "b.rs"]);
+		let ctrl_y = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+		app.key_bindings = KeyBindings::default().bind(Action::Accept, [ctrl_y]);
+
+		let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+		assert!(app.handle_key(enter).unwrap().is_none());
+
+		let outcome = app.handle_key(ctrl_y).unwrap().expect("ctrl-y accepts");
+		assert!(outcome.accepted);
+		assert_eq!(paths(&outcome), vec!["a.rs"]);
+	}
+
+	thread_local! {
+		static COPIED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+	}
+
+	fn record_copy(text: &str) -> std::result::Result<(), String> {
+		COPIED.with_borrow_mut(|copied| copied.push(text.to_owned()));
+		Ok(())
+	}
+
+	#[test]
+	fn ctrl_y_copies_the_highlighted_path() {
+		let mut app = app_with_rows(&["src/a.rs", "docs/b.md"]);
+		app.clipboard = record_copy;
+		app.results.table_state.select(Some(1));
+		let expected = selected_path(&app).expect("highlighted row");
+
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+		assert_eq!(COPIED.take(), vec![expected.clone()]);
+		assert_eq!(
+			app.current_status(),
+			Some(format!("Copied {expected}").as_str())
+		);
+
+		app.search_input.set_text("'nothing-matches");
+		app.mark_query_dirty_from_user_input();
+		app.request_search();
+		app.pump_search_results_until(|app| app.current_selection().is_none());
+		app.status = None;
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+		assert!(COPIED.take().is_empty(), "nothing highlighted");
+		assert_eq!(app.current_status(), None);
+	}
+
+	#[test]
+	fn alt_y_copies_the_path_under_the_root() {
+		let mut data = SearchData::new().with_root("/work/project");
+		data.files = vec![FileRow::new("src/main.rs")];
+		let mut app = App::new(data);
+		app.clipboard = record_copy;
+		app.mark_query_dirty();
+		app.request_search();
+		app.wait_for_search_results();
+
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+		let absolute = Path::new("/work/project").join("src/main.rs");
+		assert_eq!(
+			COPIED.take(),
+			vec![absolute.display().to_string(), "src/main.rs".to_owned()]
+		);
+		assert_eq!(app.current_status(), Some("Copied src/main.rs"));
+	}
+
+	#[test]
+	fn alt_m_switches_markdown_between_rendered_and_source() {
+		let mut app = app_with_rows(&["README.md"]);
+		assert!(app.preview.runtime.render_markdown());
+		app.preview.path = "README.md".to_owned();
+
+		press(&mut app, KeyCode::Char('m'), KeyModifiers::ALT);
+		assert!(!app.preview.runtime.render_markdown());
+		assert!(app.preview.path.is_empty(), "previewed again");
+
+		press(&mut app, KeyCode::Char('m'), KeyModifiers::ALT);
+		assert!(app.preview.runtime.render_markdown());
+	}
+
+	#[test]
+	fn expected_keys_accept_and_are_reported() {
+		let mut app = app_with_rows(&["src/main.rs", "src/lib.rs"]);
+		// Ctrl+Y copies the path unless it is expected.
+		app.expect_keys = vec![KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL)];
+		let outcome = app
+			.handle_key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL))
+			.unwrap()
+			.expect("expected keys accept");
+		assert!(outcome.accepted);
+		assert_eq!(outcome.accepted_with.as_deref(), Some("ctrl-y"));
+		assert_eq!(outcome.selections.len(), 1);
+
+		assert_eq!(accept(&mut app).accepted_with, None);
+	}
+
+	#[test]
+	fn ctrl_home_and_end_still_move_the_prompt_cursor() {
+		let mut app = app_with_rows(&["src/main.rs"]);
+		app.search_input.set_text("main");
+
+		press(&mut app, KeyCode::Home, KeyModifiers::CONTROL);
+		press(&mut app, KeyCode::Char('x'), KeyModifiers::NONE);
+		press(&mut app, KeyCode::End, KeyModifiers::CONTROL);
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::NONE);
+		assert_eq!(app.search_input.text(), "xmainy");
+	}
+
+	#[test]
+	fn expected_keys_match_whatever_the_terminal_reports() {
+		use ratatui::crossterm::event::{KeyEventKind, KeyEventState};
+
+		let mut app = app_with_rows(&["src/main.rs"]);
+		app.expect_keys = vec![crate::parse_key("shift-a").unwrap()];
+		// Terminals send Shift+A as `A`, some with the Shift modifier and
+		// with keyboard enhancements as a repeat or with lock state.
+		let mut key = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT);
+		key.kind = KeyEventKind::Repeat;
+		key.state = KeyEventState::CAPS_LOCK;
+		let outcome = app.handle_key(key).unwrap().expect("expected keys accept");
+		assert_eq!(outcome.accepted_with.as_deref(), Some("A"));
+	}
+
+	#[test]
+	fn absolute_copies_can_be_the_default() {
+		let mut data = SearchData::new().with_root("/work/project");
+		data.files = vec![FileRow::new("src/app/main.rs")];
+		let mut app = App::new(data);
+		app.clipboard = record_copy;
+		app.set_absolute_copy(true);
+		app.set_absolute_copy(true);
+		let ctrl_y = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+		assert_eq!(
+			app.key_bindings.action_for(&ctrl_y),
+			Some(Action::CopyAbsolutePath)
+		);
+		app.mark_query_dirty();
+		app.request_search();
+		app.wait_for_search_results();
+
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+		press(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+		let absolute = Path::new("/work/project").join("src/app/main.rs");
+		assert_eq!(
+			COPIED.take(),
+			vec![absolute.display().to_string(), "src/app/main.rs".to_owned()]
+		);
+	}
+
+	#[test]
+	fn alt_digits_accept_rows_counted_from_the_scroll_offset() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"]);
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		*app.results.table_state.offset_mut() = 2;
+		let expected = app.data.files[app.results.buffers.filtered[3]].path.clone();
+
+		let outcome = app
+			.handle_key(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::ALT))
+			.unwrap()
+			.expect("Alt+2 accepts");
+		assert!(outcome.accepted);
+		let Some(SearchSelection::File(file)) = outcome.selection else {
+			panic!("a file was accepted");
+		};
+		assert_eq!(file.path, expected);
+
+		// Only three rows are shown below the offset.
+		let none = app
+			.handle_key(KeyEvent::new(KeyCode::Char('4'), KeyModifiers::ALT))
+			.unwrap();
+		assert!(none.is_none());
+		assert_eq!(app.results.table_state.selected(), Some(3));
+	}
+
+	#[test]
+	fn alt_digits_only_reach_rows_in_view() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"]);
+		// Borders and header leave room for two rows.
+		app.results.area = Some(Rect::new(0, 0, 40, 6));
+		app.results.table_state.select(Some(0));
+
+		let hidden = app
+			.handle_key(KeyEvent::new(KeyCode::Char('3'), KeyModifiers::ALT))
+			.unwrap();
+		assert!(hidden.is_none());
+		assert_eq!(app.results.table_state.selected(), Some(0));
+		assert!(
+			app.handle_key(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::ALT))
+				.unwrap()
+				.is_some()
+		);
+	}
+
+	#[test]
+	fn alt_digits_accept_their_row_alone_over_marks() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs"]);
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		let path = |app: &App, row: usize| {
+			app.data.files[app.results.buffers.filtered[row]]
+				.path
+				.clone()
+		};
+		app.results.table_state.select(Some(0));
+		app.toggle_mark();
+		app.results.table_state.select(Some(1));
+		app.toggle_mark();
+		let expected = path(&app, 2);
+
+		let outcome = app
+			.handle_key(KeyEvent::new(KeyCode::Char('3'), KeyModifiers::ALT))
+			.unwrap()
+			.expect("Alt+3 accepts");
+		let paths: Vec<_> = outcome
+			.selections
+			.iter()
+			.map(|SearchSelection::File(file)| file.path.clone())
+			.collect();
+		assert_eq!(paths, vec![expected]);
+	}
+
+	#[test]
+	fn dragging_over_rows_marks_the_range() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs", "d.rs"]);
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		let row_path = |app: &App, row: usize| {
+			app.data.files[app.results.buffers.filtered[row]]
+				.path
+				.clone()
+		};
+		let paths = |app: &App, rows: &[usize]| {
+			let mut paths: Vec<_> = rows.iter().map(|&row| row_path(app, row)).collect();
+			paths.sort_unstable();
+			paths
+		};
+		let mouse = |app: &mut App, kind, row| {
+			app.handle_mouse(MouseEvent {
+				kind,
+				column: 5,
+				row,
+				modifiers: KeyModifiers::NONE,
+			});
+		};
+		// Rows start below the border, header and separator.
+		let body = 3;
+		app.results.table_state.select(Some(3));
+		app.toggle_mark();
+
+		mouse(&mut app, MouseEventKind::Down(MouseButton::Left), body);
+		assert_eq!(app.results.marked.len(), 1, "clicking alone marks nothing");
+		mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), body + 3);
+		assert_eq!(marked_paths(&app), paths(&app, &[0, 1, 2, 3]));
+		mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), body + 1);
+		assert_eq!(
+			marked_paths(&app),
+			paths(&app, &[0, 1, 3]),
+			"rows marked before the drag stay marked"
+		);
+		mouse(&mut app, MouseEventKind::Up(MouseButton::Left), body + 1);
+		assert_eq!(app.results.table_state.selected(), Some(1));
+		assert!(app.results.range_anchor.is_none());
+
+		// Pressing below the last row starts no range.
+		app.results.marked.clear();
+		mouse(&mut app, MouseEventKind::Down(MouseButton::Left), body + 6);
+		assert!(app.results.range_anchor.is_none());
+		mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), body);
+		assert!(app.results.marked.is_empty());
+	}
+
+	#[test]
+	fn dragged_ranges_start_at_their_row_after_results_reorder() {
+		let mut app = app_with_rows(&["a.rs", "b.rs", "c.rs", "d.rs"]);
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		let mouse = |app: &mut App, kind, row| {
+			app.handle_mouse(MouseEvent {
+				kind,
+				column: 5,
+				row,
+				modifiers: KeyModifiers::NONE,
+			});
+		};
+		let body = 3;
+		mouse(&mut app, MouseEventKind::Down(MouseButton::Left), body + 1);
+		let anchor = app.results.buffers.filtered[1];
+
+		// New results move the pressed row to the top.
+		app.results
+			.buffers
+			.filtered
+			.retain(|&index| index != anchor);
+		app.results.buffers.filtered.insert(0, anchor);
+		mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), body + 1);
+		let expected: HashSet<u64> = app.results.buffers.filtered[..2]
+			.iter()
+			.map(|&index| app.data.files[index].stable_id())
+			.collect();
+		assert_eq!(app.results.marked, expected);
+	}
+
+	#[test]
+	fn paging_moves_by_the_rows_in_view_and_clamps_at_the_ends() {
+		let paths: Vec<String> = (0..30).map(|i| format!("file_{i:02}.rs")).collect();
+		let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+		let mut app = app_with_rows(&paths);
+		// Borders and header leave 8 of the 12 rows for results.
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		let selected = |app: &App| app.results.table_state.selected();
+
+		press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(8));
+		press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(16));
+		assert_eq!(app.results.table_state.offset(), 16);
+		press(&mut app, KeyCode::PageUp, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(8));
+
+		press(&mut app, KeyCode::End, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(29));
+		assert_eq!(app.results.table_state.offset(), 22);
+		press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(29));
+
+		press(&mut app, KeyCode::Home, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(0));
+		assert_eq!(app.results.table_state.offset(), 0);
+		press(&mut app, KeyCode::PageUp, KeyModifiers::NONE);
+		assert_eq!(selected(&app), Some(0));
+	}
+}
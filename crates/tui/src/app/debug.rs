@@ -0,0 +1,112 @@
+//! Overlay describing the picker's internal state, for diagnosing slow or
+//! surprising sessions.
+//!
+//! [`Action::ToggleDebugOverlay`](crate::keys::Action::ToggleDebugOverlay)
+//! shows it over the top right of the results. Besides the search figures
+//! it tells whether this instance refreshes the index cache of its root or
+//! only reads it because another `frz` owns the refresh.
+
+use frz_core::filesystem::indexer::CacheRole;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::symbols::border;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+use super::App;
+use crate::components::progress::{format_count, format_elapsed};
+
+impl App<'_> {
+	/// Lines shown in the debug overlay.
+	pub(crate) fn debug_lines(&self) -> Vec<String> {
+		let cache = match self.index_progress.cache_role() {
+			CacheRole::Owner => "owner, refreshes it for this root",
+			CacheRole::Reader => "reader, another frz refreshes it",
+			CacheRole::Uncached => "not used",
+		};
+		let indexing = if self.index_progress.is_complete() {
+			"complete"
+		} else {
+			"indexing"
+		};
+		let last_search = self
+			.search
+			.last_elapsed()
+			.map_or_else(|| "-".to_owned(), format_elapsed);
+		vec![
+			format!("Index cache: {cache}"),
+			format!("Rows: {} ({indexing})", format_count(self.data.files.len())),
+			format!(
+				"Matches: {} kept of at most {}",
+				format_count(self.filtered_len()),
+				format_count(self.result_limit())
+			),
+			format!("Last search: {last_search}"),
+			format!("Search threads: {}", self.search_options.threads),
+		]
+	}
+
+	/// Draw the debug overlay in the top right corner of `area`.
+	pub(crate) fn render_debug_overlay(&self, frame: &mut Frame, area: Rect) {
+		let lines = self.debug_lines();
+		let width = lines
+			.iter()
+			.map(|line| line.chars().count() as u16 + 4)
+			.max()
+			.unwrap_or(0)
+			.min(area.width);
+		let height = (lines.len() as u16 + 2).min(area.height);
+		let overlay = Rect {
+			x: area.right().saturating_sub(width),
+			y: area.y,
+			width,
+			height,
+		};
+
+		let theme = &self.style.theme;
+		let block = Block::default()
+			.borders(Borders::ALL)
+			.border_set(border::ROUNDED)
+			.border_style(Style::default().fg(theme.header.fg.unwrap_or_default()))
+			.title(" Debug ");
+		let text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+		frame.render_widget(Clear, overlay);
+		frame.render_widget(Paragraph::new(text).block(block), overlay);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use frz_core::filesystem::indexer::{IndexView, ProgressSnapshot};
+	use frz_core::filesystem::search::{FileRow, SearchData};
+	use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+	use super::*;
+
+	#[test]
+	fn f12_shows_the_cache_role_reported_by_the_indexer() {
+		let mut data = SearchData::new();
+		data.files = vec![FileRow::new("a.rs"), FileRow::new("b.rs")];
+		let mut app = App::new(data);
+		assert!(app.debug_lines()[0].ends_with("not used"));
+
+		app.record_index_progress(ProgressSnapshot {
+			indexed_files: 2,
+			total_files: None,
+			complete: false,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Reader,
+		});
+		let lines = app.debug_lines();
+		assert_eq!(lines[0], "Index cache: reader, another frz refreshes it");
+		assert_eq!(lines[1], "Rows: 2 (indexing)");
+
+		app.handle_key(KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE))
+			.unwrap();
+		assert!(app.debug_overlay);
+		app.handle_key(KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE))
+			.unwrap();
+		assert!(!app.debug_overlay);
+	}
+}
@@ -0,0 +1,178 @@
+//! Opening the highlighted file in an editor without leaving the picker.
+//!
+//! [`Action::OpenInEditor`](crate::keys::Action::OpenInEditor) only records
+//! which file to open; the runtime then hands the terminal to the editor and
+//! takes it back once the editor exits, see [`App::run`].
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use frz_core::filesystem::search::SearchSelection;
+
+use super::App;
+
+/// Editor used when neither a command nor `$VISUAL`/`$EDITOR` is set.
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Controlling terminal the editor is attached to when frz's own stdin or
+/// stdout is a pipe.
+const TTY: &str = "/dev/tty";
+
+impl<'a> App<'a> {
+	/// Ask the runtime to open the highlighted file in the editor.
+	pub(crate) fn request_edit(&mut self) {
+		if let Some(SearchSelection::File(file)) = self.current_selection() {
+			self.pending_edit = Some(self.data.resolve_file_path(&file));
+		}
+	}
+
+	/// The file waiting to be opened in the editor, if any.
+	pub(crate) fn take_pending_edit(&mut self) -> Option<PathBuf> {
+		self.pending_edit.take()
+	}
+
+	/// The command opening `path`: the configured editor, `$VISUAL`,
+	/// `$EDITOR` or `vi`, split into words, with the path appended.
+	///
+	/// When frz reads candidates from a pipe or its output is captured, as
+	/// in `find | frz` or `$(frz)`, the editor would inherit the pipe, so
+	/// that side is connected to [`TTY`] instead. Without a controlling
+	/// terminal the editor inherits frz's stdio.
+	pub(crate) fn editor_command(&self, path: &Path) -> Result<Command, String> {
+		let editor = self
+			.editor
+			.clone()
+			.or_else(|| std::env::var("VISUAL").ok())
+			.or_else(|| std::env::var("EDITOR").ok())
+			.filter(|editor| !editor.trim().is_empty())
+			.unwrap_or_else(|| FALLBACK_EDITOR.to_owned());
+		let words = shlex::split(&editor)
+			.filter(|words| !words.is_empty())
+			.ok_or_else(|| format!("Invalid editor command: {editor}"))?;
+		let mut command = Command::new(&words[0]);
+		command.args(&words[1..]).arg(path);
+		if let Some(root) = self.data.root.as_deref().filter(|root| root.is_dir()) {
+			command.current_dir(root);
+		}
+		if !io::stdin().is_terminal()
+			&& let Ok(tty) = File::open(TTY)
+		{
+			command.stdin(tty);
+		}
+		if !io::stdout().is_terminal()
+			&& let Ok(tty) = OpenOptions::new().write(true).open(TTY)
+		{
+			command.stdout(tty);
+		}
+		Ok(command)
+	}
+
+	/// Pick up after the editor returned the terminal with `status`.
+	///
+	/// Reports editors that failed to start or exited unsuccessfully and
+	/// previews the file again, since it may have changed and image previews
+	/// were encoded for the graphics backend probed before.
+	pub(crate) fn resume_after_edit(&mut self, status: io::Result<ExitStatus>) {
+		match status {
+			Ok(status) if status.success() => {}
+			Ok(status) => self.show_status(format!("Editor exited with {status}")),
+			Err(error) => self.show_status(format!("Could not start editor: {error}")),
+		}
+		self.preview.runtime.clear_cache();
+		self.preview.path.clear();
+		self.preview.pending_path = None;
+		self.update_preview();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use frz_core::filesystem::search::{FileRow, SearchData};
+	use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+	use super::*;
+
+	fn app(root: &Path) -> App<'static> {
+		let mut data = SearchData::new().with_root(root);
+		data.files = vec![FileRow::new("notes.md"), FileRow::new("src/main.rs")];
+		let mut app = App::new(data);
+		app.mark_query_dirty();
+		app.request_search();
+		app.wait_for_search_results();
+		app
+	}
+
+	#[test]
+	fn ctrl_o_queues_the_highlighted_file_for_the_editor() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut app = app(dir.path());
+		app.editor = Some("code --wait".to_owned());
+		let Some(SearchSelection::File(file)) = app.current_selection() else {
+			panic!("a row is highlighted");
+		};
+
+		app.handle_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL))
+			.unwrap();
+		let path = app.take_pending_edit().expect("edit requested");
+		assert_eq!(path, dir.path().join(&file.path));
+		assert!(app.take_pending_edit().is_none());
+
+		let command = app.editor_command(&path).expect("command");
+		assert_eq!(command.get_program(), "code");
+		let args: Vec<_> = command.get_args().collect();
+		assert_eq!(args, ["--wait".as_ref(), path.as_os_str()]);
+		assert_eq!(command.get_current_dir(), Some(dir.path()));
+	}
+
+	#[test]
+	fn failed_editors_are_reported() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut app = app(dir.path());
+		app.editor = Some("'unclosed".to_owned());
+		assert!(app.editor_command(Path::new("a")).is_err());
+
+		app.resume_after_edit(Err(io::Error::from(io::ErrorKind::NotFound)));
+		assert!(
+			app.current_status()
+				.is_some_and(|status| status.starts_with("Could not start editor")),
+			"{:?}",
+			app.current_status()
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn the_preview_is_refreshed_after_the_editor_exits() {
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::write(dir.path().join("notes.md"), "draft").unwrap();
+		let mut app = app(dir.path());
+		app.preview.enabled = true;
+		app.editor = Some(r#"sh -c 'printf edited > "$0"'"#.to_owned());
+		let path = dir.path().join("notes.md");
+		app.preview.path = path.display().to_string();
+
+		// What the runtime does between leaving and re-entering the terminal.
+		let status = app.editor_command(&path).expect("command").status();
+		app.resume_after_edit(status);
+
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), "edited");
+		assert_eq!(app.current_status(), None);
+		assert_eq!(
+			app.preview.pending_path.as_deref(),
+			Some(path.display().to_string().as_str()),
+			"previewed again"
+		);
+
+		app.editor = Some("false".to_owned());
+		let status = app.editor_command(&path).expect("command").status();
+		app.resume_after_edit(status);
+		assert!(
+			app.current_status()
+				.is_some_and(|status| status.starts_with("Editor exited with")),
+			"{:?}",
+			app.current_status()
+		);
+	}
+}
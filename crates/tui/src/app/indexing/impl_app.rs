@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::mpsc::{Receiver, TryRecvError};
 use std::time::{Duration, Instant};
 
@@ -7,7 +8,7 @@ use std::time::{Duration, Instant};
 // `MAX_INDEX_PROCESSING_TIME` caps the wall-clock time spent applying updates before we
 // yield back to drawing and input handling.
 use frz_core::filesystem::indexer::{IndexResult, IndexUpdate, ProgressSnapshot, merge_update};
-use frz_core::filesystem::search::FILES_DATASET_KEY;
+use frz_core::filesystem::search::{FILES_DATASET_KEY, FileRow};
 
 use crate::app::App;
 use crate::components::IndexProgress;
@@ -67,6 +68,7 @@ impl<'a> App<'a> {
 				self.data = data;
 				self.results.buffers.filtered.clear();
 				self.results.buffers.scores.clear();
+				self.results.buffers.positions.clear();
 				self.results.table_state.select(None);
 				self.index_progress
 					.refresh_from_data(&self.data, self.dataset_totals());
@@ -79,12 +81,31 @@ impl<'a> App<'a> {
 					self.index_progress = IndexProgress::with_unknown_totals();
 					self.results.buffers.filtered.clear();
 					self.results.buffers.scores.clear();
+					self.results.buffers.positions.clear();
 					self.results.table_state.select(None);
 				}
 
-				let update_changed = update.reset || !update.files.is_empty();
+				// Pruned rows shift the indices after them: drop the results
+				// until the search reruns.
+				let pruned = !update.reset && !update.removed.is_empty();
+				if pruned {
+					self.results.buffers.filtered.clear();
+					self.results.buffers.scores.clear();
+					self.results.buffers.positions.clear();
+					self.results.table_state.select(None);
+				}
+
+				let update_changed =
+					update.reset || !update.files.is_empty() || !update.removed.is_empty();
 				if update_changed {
 					merge_update(&mut self.data, &update);
+					// Marks are kept by stable id, so they only need to
+					// forget rows that are gone.
+					if pruned && !self.results.marked.is_empty() {
+						let present: HashSet<u64> =
+							self.data.files.iter().map(FileRow::stable_id).collect();
+						self.results.marked.retain(|id| present.contains(id));
+					}
 					self.rebuild_row_id_maps();
 					self.mark_query_dirty();
 					changed = true;
@@ -102,6 +123,9 @@ impl<'a> App<'a> {
 			.record_indexed(&[(FILES_DATASET_KEY, progress.indexed_files)]);
 		self.index_progress
 			.set_totals(&[(FILES_DATASET_KEY, progress.total_files)]);
+		self.index_progress
+			.record_skipped_duplicates(progress.skipped_duplicates);
+		self.index_progress.record_cache_role(progress.cache_role);
 		if progress.complete {
 			self.index_progress.mark_complete();
 		}
@@ -18,4 +18,8 @@ impl<'a> IndexView for App<'a> {
 	fn schedule_search_refresh_after_index_update(&mut self, changed: bool) {
 		App::schedule_search_refresh_after_index_update(self, changed);
 	}
+
+	fn is_complete(&self) -> bool {
+		self.index_progress.is_complete()
+	}
 }
@@ -1,8 +1,10 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use frz_core::filesystem::indexer::{IndexUpdate, IndexView, ProgressSnapshot};
-use frz_core::filesystem::search::{FileRow, MatchBatch, SearchData, SearchViewV2};
+use frz_core::filesystem::indexer::{CacheRole, IndexUpdate, IndexView, ProgressSnapshot};
+use frz_core::filesystem::search::{
+	FileRow, MatchBatch, SearchData, SearchSelection, SearchViewV2,
+};
 
 use crate::app::App;
 
@@ -31,8 +33,11 @@ fn index_updates_refresh_results_without_input_changes() {
 			indexed_files: 1,
 			total_files: Some(1),
 			complete: true,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
 		},
 		reset: false,
+		removed: Arc::from([]),
 		cached_data: None,
 	};
 
@@ -88,8 +93,11 @@ fn row_id_map_tracks_incremental_index_updates() {
 			indexed_files: 0,
 			total_files: None,
 			complete: false,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
 		},
 		reset: false,
+		removed: Arc::from([]),
 		cached_data: None,
 	};
 
@@ -110,6 +118,7 @@ fn row_id_map_tracks_incremental_index_updates() {
 		indices: vec![0],
 		ids: Some(vec![second_id]),
 		scores: vec![10],
+		positions: None,
 	};
 	<App as SearchViewV2>::replace_matches_v2(&mut app, batch);
 	let filtered = app.results.buffers.filtered.clone();
@@ -147,8 +156,11 @@ fn row_id_map_rebuilds_when_cached_data_applied() {
 			indexed_files: 0,
 			total_files: None,
 			complete: false,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
 		},
 		reset: false,
+		removed: Arc::from([]),
 		cached_data: Some(cached_data.clone()),
 	};
 
@@ -162,6 +174,7 @@ fn row_id_map_rebuilds_when_cached_data_applied() {
 		indices: vec![1],
 		ids: Some(vec![first_id]),
 		scores: vec![5],
+		positions: None,
 	};
 	<App as SearchViewV2>::replace_matches_v2(&mut app, batch);
 	let filtered = app.results.buffers.filtered.clone();
@@ -171,3 +184,80 @@ fn row_id_map_rebuilds_when_cached_data_applied() {
 		"stable ids should resolve to indices from the cached dataset",
 	);
 }
+
+#[test]
+fn removed_rows_are_pruned_and_marks_follow_their_rows() {
+	let mut data = SearchData::new();
+	data.files = vec![
+		FileRow::filesystem("a.txt"),
+		FileRow::filesystem("b.txt"),
+		FileRow::filesystem("c.txt"),
+	];
+	let mut app = App::new(data);
+	wait_for_results(&mut app);
+	app.results.marked = [0, 2].map(|index| app.data.files[index].stable_id()).into();
+
+	// `b.txt` changed and `a.txt` was deleted.
+	let update = IndexUpdate {
+		files: Arc::from(vec![FileRow::filesystem("b.txt")]),
+		progress: ProgressSnapshot {
+			indexed_files: 2,
+			total_files: Some(2),
+			complete: true,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
+		},
+		reset: false,
+		removed: Arc::from(["b.txt".to_owned(), "a.txt".to_owned()]),
+		cached_data: None,
+	};
+
+	let changed = <App as IndexView>::apply_index_update(&mut app, update);
+	assert!(changed, "removals should modify the data set");
+	let paths: Vec<_> = app
+		.data
+		.files
+		.iter()
+		.map(|file| file.path.as_str())
+		.collect();
+	assert_eq!(paths, ["c.txt", "b.txt"]);
+	assert_eq!(
+		app.results.marked,
+		[FileRow::filesystem("c.txt").stable_id()].into(),
+		"the mark on c.txt stays and the one on a.txt is dropped"
+	);
+	assert!(app.results.buffers.filtered.is_empty());
+}
+
+#[test]
+fn marks_survive_cached_data_that_reorders_rows() {
+	let first = FileRow::filesystem("src/lib.rs");
+	let second = FileRow::filesystem("src/main.rs");
+	let mut data = SearchData::new();
+	data.files = vec![first.clone(), second.clone()];
+	let mut app = App::new(data);
+	wait_for_results(&mut app);
+	app.results.marked = [first.stable_id()].into();
+
+	let update = IndexUpdate {
+		files: Arc::from(Vec::<FileRow>::new()),
+		progress: ProgressSnapshot {
+			indexed_files: 0,
+			total_files: None,
+			complete: false,
+			skipped_duplicates: 0,
+			cache_role: CacheRole::Uncached,
+		},
+		reset: false,
+		removed: Arc::from([]),
+		cached_data: Some(SearchData::new().with_files(vec![second, first])),
+	};
+	assert!(<App as IndexView>::apply_index_update(&mut app, update));
+
+	let selected: Vec<String> = app
+		.current_selections()
+		.into_iter()
+		.map(|SearchSelection::File(file)| file.path)
+		.collect();
+	assert_eq!(selected, ["src/lib.rs"]);
+}
@@ -5,6 +5,8 @@
 //! actions (input handling), rendering, search coordination, and indexing.
 
 mod actions;
+mod debug;
+mod editor;
 mod indexing;
 pub(crate) mod preview;
 mod render;
@@ -0,0 +1,331 @@
+//! Searching the previewed text from a prompt under the preview.
+//!
+//! [`Action::FindInPreview`](crate::keys::Action::FindInPreview) opens the
+//! prompt. Every typed character searches again and scrolls to the first
+//! occurrence from where the prompt was opened; Enter closes the prompt and
+//! leaves `n` and `N` jumping between occurrences. Esc ends the search, and
+//! so does any other key, which the picker then handles as usual. The search
+//! ends as well when another file is previewed. Line numbers and other
+//! gutters are not searched.
+
+use std::ops::Range;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::text::Line;
+
+use crate::app::state::App;
+use crate::components::preview::{PreviewTerms, highlight_wrapped, wrapped_row};
+use crate::components::selection_style;
+
+/// A search of the previewed text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PreviewFind {
+	/// Text searched for.
+	pub pattern: String,
+	/// Whether keys edit the pattern rather than jump between occurrences.
+	pub editing: bool,
+	/// Occurrences of the pattern as the source line and the character range
+	/// on it, in order.
+	pub matches: Vec<(usize, usize, usize)>,
+	/// Source lines with occurrences, with the range of `matches` on each.
+	lines: Vec<(usize, Range<usize>)>,
+	/// Index into `matches` of the occurrence scrolled to.
+	pub current: usize,
+	/// Wrapped row at the top of the preview when the prompt was opened.
+	origin: usize,
+}
+
+impl PreviewFind {
+	/// Text shown in the prompt: the pattern and which occurrence is shown.
+	pub(crate) fn prompt(&self) -> String {
+		match self.matches.len() {
+			0 if self.pattern.is_empty() => "/".to_owned(),
+			0 => format!("/{} (no matches)", self.pattern),
+			total => format!("/{} ({}/{total})", self.pattern, self.current + 1),
+		}
+	}
+}
+
+impl<'a> App<'a> {
+	/// Open the preview's search prompt, keeping the previous pattern if a
+	/// search is under way.
+	pub(crate) fn open_preview_find(&mut self) {
+		if !self.preview.enabled || self.preview.content.lines().is_none() {
+			return;
+		}
+		let origin = self.preview.scroll;
+		self.preview
+			.find
+			.get_or_insert_with(|| PreviewFind {
+				origin,
+				..PreviewFind::default()
+			})
+			.editing = true;
+	}
+
+	/// Let the search of the preview handle `key`, returning `false` for keys
+	/// it leaves to the picker.
+	pub(crate) fn handle_preview_find_key(&mut self, key: KeyEvent) -> bool {
+		let Some(find) = self.preview.find.as_mut() else {
+			return false;
+		};
+		let plain = !key
+			.modifiers
+			.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+		match key.code {
+			KeyCode::Esc => self.preview.find = None,
+			KeyCode::Enter if find.editing => {
+				find.editing = false;
+				if find.matches.is_empty() {
+					self.preview.find = None;
+				}
+			}
+			KeyCode::Backspace if find.editing => {
+				find.pattern.pop();
+				self.search_preview();
+			}
+			KeyCode::Char(ch) if find.editing && plain => {
+				find.pattern.push(ch);
+				self.search_preview();
+			}
+			KeyCode::Char('n') if !find.editing && plain => self.jump_in_preview(1),
+			KeyCode::Char('N') if !find.editing && plain => self.jump_in_preview(-1),
+			_ if !find.editing => {
+				self.preview.find = None;
+				return false;
+			}
+			_ => return false,
+		}
+		true
+	}
+
+	/// Find the pattern again after it changed and scroll to the first
+	/// occurrence at or below the row the prompt was opened on.
+	fn search_preview(&mut self) {
+		let Some(find) = self.preview.find.as_ref() else {
+			return;
+		};
+		let terms = PreviewTerms::literal(&find.pattern);
+		let gutter_width = self.preview.content.gutter_width;
+		let matches = self
+			.preview
+			.content
+			.lines()
+			.map(|lines| terms.occurrences(lines, gutter_width))
+			.unwrap_or_default();
+		let mut lines: Vec<(usize, Range<usize>)> = Vec::new();
+		for (index, &(line, ..)) in matches.iter().enumerate() {
+			match lines.last_mut() {
+				Some((last, range)) if *last == line => range.end = index + 1,
+				_ => lines.push((line, index..index + 1)),
+			}
+		}
+		let origin = find.origin;
+		let current = matches
+			.iter()
+			.position(|&(line, start, _)| {
+				self.preview_row(line, start)
+					.is_some_and(|row| row >= origin)
+			})
+			.unwrap_or(0);
+		if let Some(find) = self.preview.find.as_mut() {
+			find.matches = matches;
+			find.lines = lines;
+			find.current = current;
+		}
+		self.scroll_to_preview_match();
+	}
+
+	/// Move `step` occurrences forward or back, wrapping around at the ends.
+	fn jump_in_preview(&mut self, step: isize) {
+		let Some(find) = self.preview.find.as_mut() else {
+			return;
+		};
+		let total = find.matches.len();
+		if total == 0 {
+			return;
+		}
+		find.current = (find.current as isize + step).rem_euclid(total as isize) as usize;
+		self.scroll_to_preview_match();
+	}
+
+	/// Scroll the current occurrence into view, unless it is already shown.
+	fn scroll_to_preview_match(&mut self) {
+		let Some(&(line, start, _)) = self
+			.preview
+			.find
+			.as_ref()
+			.and_then(|find| find.matches.get(find.current))
+		else {
+			return;
+		};
+		let Some(row) = self.preview_row(line, start) else {
+			return;
+		};
+		let shown = self.preview.viewport_height.max(1);
+		if row < self.preview.scroll || row >= self.preview.scroll + shown {
+			let max_scroll = self.preview.max_scroll(self.preview.wrapped_lines.len());
+			self.preview.scroll = row.min(max_scroll);
+		}
+		self.preview.scrolled_by_user = true;
+		self.preview.update_scrollbar();
+	}
+
+	/// Restyle every occurrence of `find` in `rows`, the wrapped preview, with
+	/// the theme's match style and the current one like selected text.
+	pub(crate) fn mark_preview_matches(&self, find: &PreviewFind, rows: &mut [Line<'static>]) {
+		let Some(lines) = self.preview.content.lines() else {
+			return;
+		};
+		let matched = self.style.theme.highlight;
+		let current = selection_style(&self.style.theme);
+		for (line, indices) in &find.lines {
+			let Some(source) = lines.get(*line) else {
+				continue;
+			};
+			let ranges: Vec<_> = indices
+				.clone()
+				.map(|index| {
+					let (_, start, end) = find.matches[index];
+					let style = if index == find.current {
+						current
+					} else {
+						matched
+					};
+					(start, end, style)
+				})
+				.collect();
+			let Some(wrapped) = rows.get_mut(self.preview.wrapped_rows_of(*line)) else {
+				continue;
+			};
+			highlight_wrapped(source, wrapped, self.preview.content.gutter_width, &ranges);
+		}
+	}
+
+	/// The wrapped row showing the character at `offset` on source `line`.
+	fn preview_row(&self, line: usize, offset: usize) -> Option<usize> {
+		let source = self.preview.content.lines()?.get(line)?;
+		let range = self.preview.wrapped_rows_of(line);
+		let rows = self.preview.wrapped_lines.get(range.clone())?;
+		if rows.is_empty() {
+			return None;
+		}
+		let gutter_width = self.preview.content.gutter_width;
+		Some(range.start + wrapped_row(source, rows, gutter_width, offset))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use frz_core::filesystem::search::{FileRow, SearchData};
+	use ratatui::text::Line;
+
+	use super::*;
+	use crate::components::PreviewContent;
+
+	fn app() -> App<'static> {
+		let mut data = SearchData::new();
+		data.files = vec![FileRow::new("main.rs")];
+		let mut app = App::new(data);
+		let lines = [
+			"fn main() {",
+			"    let needle = 1;",
+			"    // a comment long enough to wrap before the needle",
+			"}",
+			"needle",
+		]
+		.map(Line::from)
+		.to_vec();
+		app.preview.enabled = true;
+		app.preview.viewport_height = 2;
+		app.preview.content = PreviewContent::text("main.rs", lines);
+		app.rebuild_preview_wrap(24);
+		app
+	}
+
+	fn press(app: &mut App<'_>, code: KeyCode) {
+		app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+			.unwrap();
+	}
+
+	fn shown_line(app: &App<'_>) -> usize {
+		let find = app.preview.find.as_ref().expect("search open");
+		let (line, start, _) = find.matches[find.current];
+		assert!(
+			app.preview.scroll <= app.preview_row(line, start).unwrap(),
+			"occurrence scrolled past"
+		);
+		line
+	}
+
+	#[test]
+	fn typing_searches_and_n_jumps_between_occurrences() {
+		let mut app = app();
+		app.handle_key(KeyEvent::new(KeyCode::Char('/'), KeyModifiers::ALT))
+			.unwrap();
+		for ch in "needle".chars() {
+			press(&mut app, KeyCode::Char(ch));
+		}
+		assert_eq!(app.search_input.text(), "");
+		let find = app.preview.find.as_ref().unwrap();
+		assert_eq!(find.matches.len(), 3);
+		assert_eq!(find.prompt(), "/needle (1/3)");
+		assert_eq!(shown_line(&app), 1);
+
+		press(&mut app, KeyCode::Enter);
+		press(&mut app, KeyCode::Char('n'));
+		assert_eq!(shown_line(&app), 2);
+		let (line, start, _) = app.preview.find.as_ref().unwrap().matches[1];
+		let row = app.preview_row(line, start).unwrap();
+		assert!(
+			row > app
+				.preview
+				.wrapped_sources
+				.iter()
+				.position(|&s| s == 2)
+				.unwrap()
+		);
+		assert_eq!(app.preview.scroll, row);
+
+		press(&mut app, KeyCode::Char('n'));
+		press(&mut app, KeyCode::Char('n'));
+		assert_eq!(shown_line(&app), 1);
+		press(&mut app, KeyCode::Char('N'));
+		assert_eq!(shown_line(&app), 4);
+
+		press(&mut app, KeyCode::Esc);
+		assert!(app.preview.find.is_none());
+		assert_eq!(app.search_input.text(), "");
+	}
+
+	#[test]
+	fn unmatched_patterns_close_on_enter() {
+		let mut app = app();
+		app.open_preview_find();
+		press(&mut app, KeyCode::Char('x'));
+		assert_eq!(
+			app.preview.find.as_ref().unwrap().prompt(),
+			"/x (no matches)"
+		);
+		press(&mut app, KeyCode::Enter);
+		assert!(app.preview.find.is_none());
+	}
+
+	#[test]
+	fn other_keys_end_the_search_and_reach_the_picker() {
+		let mut app = app();
+		app.open_preview_find();
+		for ch in "needle".chars() {
+			press(&mut app, KeyCode::Char(ch));
+		}
+		press(&mut app, KeyCode::Enter);
+		press(&mut app, KeyCode::Char('n'));
+		assert!(app.preview.find.is_some());
+
+		press(&mut app, KeyCode::Char('x'));
+		assert!(app.preview.find.is_none());
+		assert_eq!(app.search_input.text(), "x");
+		press(&mut app, KeyCode::Char('n'));
+		assert_eq!(app.search_input.text(), "xn");
+	}
+}
@@ -3,6 +3,8 @@
 //! This module manages the preview pane functionality including content loading,
 //! scrolling, wrapping, and mouse interaction.
 
+mod find;
 mod state;
 
-pub(crate) use state::PreviewState;
+pub(crate) use find::PreviewFind;
+pub(crate) use state::{DEFAULT_SPLIT_RATIO, FollowedQuery, PreviewState};
@@ -1,21 +1,37 @@
 //! Preview pane state management.
 
+use std::ops::Range;
+
+use frz_core::filesystem::search::PreviewPosition;
 use ratatui::layout::Rect;
 use ratatui::text::Line;
 use ratatui::widgets::ScrollbarState;
 
+use super::PreviewFind;
+use crate::components::preview::PreviewTerms;
 use crate::components::{
-	PreviewContent, PreviewRuntime, ScrollMetrics, TextSelection, point_in_rect,
+	PreviewContent, PreviewKind, PreviewRuntime, ScrollMetrics, TextSelection, point_in_rect,
 };
+use crate::config::PreviewLayout;
+
+/// Share of the split given to the results by default.
+pub(crate) const DEFAULT_SPLIT_RATIO: f32 = 0.5;
 
 /// State for the preview pane.
 pub(crate) struct PreviewState {
 	/// Whether the preview pane is visible.
 	pub enabled: bool,
+	/// Orientation of the split between results and preview.
+	pub layout: PreviewLayout,
+	/// Share of the split given to the results, between 0 and 1.
+	pub split_ratio: f32,
 	/// Cached preview content for the currently selected file.
 	pub content: PreviewContent,
 	/// Scroll offset within the preview pane.
 	pub scroll: usize,
+	/// Whether the user scrolled since the preview was loaded, which stops
+	/// it from following the query.
+	pub scrolled_by_user: bool,
 	/// Scrollbar state for the preview pane.
 	pub scrollbar_state: ScrollbarState,
 	/// Last known viewport height for scroll bounds.
@@ -24,6 +40,8 @@ pub(crate) struct PreviewState {
 	pub wrap_width: usize,
 	/// Wrapped preview lines sized to the current viewport width.
 	pub wrapped_lines: Vec<Line<'static>>,
+	/// Index of the source line each wrapped line was produced from.
+	pub wrapped_sources: Vec<usize>,
 	/// Last known preview area on screen.
 	pub area: Option<Rect>,
 	/// Screen area of the preview scrollbar if rendered.
@@ -44,18 +62,38 @@ pub(crate) struct PreviewState {
 	pub scroll_metrics: Option<ScrollMetrics>,
 	/// Text selection state for copy functionality.
 	pub selection: TextSelection,
+	/// Search of the previewed text, while its prompt is open.
+	pub find: Option<PreviewFind>,
+	/// Where the query was last found in the previewed text, kept until the
+	/// query or the preview changes.
+	pub followed: Option<FollowedQuery>,
+}
+
+/// The query found in the previewed text.
+#[derive(Debug, Clone)]
+pub(crate) struct FollowedQuery {
+	/// Query text the terms were parsed from.
+	pub query: String,
+	/// The words of the query.
+	pub terms: PreviewTerms,
+	/// Source line the words were first found on.
+	pub line: Option<usize>,
 }
 
 impl Default for PreviewState {
 	fn default() -> Self {
 		Self {
 			enabled: false,
+			layout: PreviewLayout::default(),
+			split_ratio: DEFAULT_SPLIT_RATIO,
 			content: PreviewContent::empty(),
 			scroll: 0,
+			scrolled_by_user: false,
 			scrollbar_state: ScrollbarState::default(),
 			viewport_height: 0,
 			wrap_width: 0,
 			wrapped_lines: Vec::new(),
+			wrapped_sources: Vec::new(),
 			area: None,
 			scrollbar_area: None,
 			hovered: false,
@@ -66,6 +104,8 @@ impl Default for PreviewState {
 			runtime: PreviewRuntime::default(),
 			scroll_metrics: None,
 			selection: TextSelection::new(),
+			find: None,
+			followed: None,
 		}
 	}
 }
@@ -83,6 +123,38 @@ impl PreviewState {
 		}
 	}
 
+	/// Location at the top of the preview for the file at `path`.
+	///
+	/// Returns `None` when the pane is hidden or showing a different file.
+	pub fn position_for(&self, path: &str) -> Option<PreviewPosition> {
+		if !self.enabled || self.path != path {
+			return None;
+		}
+
+		match &self.content.kind {
+			PreviewKind::Text { .. } => self
+				.wrapped_sources
+				.get(self.scroll)
+				.map(|source| PreviewPosition::line(source + 1)),
+			// Only the first page of a PDF is rendered.
+			#[cfg(feature = "media-preview")]
+			PreviewKind::Pdf { .. } => Some(PreviewPosition::page(1)),
+			// Rendered markdown lines do not map back to source lines.
+			_ => None,
+		}
+	}
+
+	/// The wrapped rows produced from source line `line`.
+	pub fn wrapped_rows_of(&self, line: usize) -> Range<usize> {
+		let start = self
+			.wrapped_sources
+			.partition_point(|&source| source < line);
+		let end = self
+			.wrapped_sources
+			.partition_point(|&source| source <= line);
+		start..end
+	}
+
 	pub fn max_scroll(&self, content_length: usize) -> usize {
 		let viewport_len = self.viewport_len(content_length);
 		content_length.saturating_sub(viewport_len)
@@ -3,6 +3,25 @@ use ratatui::widgets::HighlightSpacing;
 use unicode_width::UnicodeWidthStr;
 
 use crate::components::tables::{HIGHLIGHT_SYMBOL, TABLE_COLUMN_SPACING, TABLE_HIGHLIGHT_SPACING};
+use crate::config::PreviewLayout;
+
+/// Split `area` into the results and preview panes.
+///
+/// The results get `ratio` of the width for [`PreviewLayout::Horizontal`] or
+/// of the height for [`PreviewLayout::Vertical`], and the preview the rest.
+pub(crate) fn split_panes(area: Rect, layout: PreviewLayout, ratio: f32) -> (Rect, Rect) {
+	let total = match layout {
+		PreviewLayout::Horizontal => area.width,
+		PreviewLayout::Vertical => area.height,
+	};
+	let results = (f32::from(total) * ratio.clamp(0.0, 1.0)).round() as u16;
+	let constraints = [Constraint::Length(results), Constraint::Fill(1)];
+	let [results_area, preview_area] = match layout {
+		PreviewLayout::Horizontal => Layout::horizontal(constraints).areas(area),
+		PreviewLayout::Vertical => Layout::vertical(constraints).areas(area),
+	};
+	(results_area, preview_area)
+}
 
 pub(crate) fn resolve_column_widths(
 	area: Rect,
@@ -94,4 +113,40 @@ mod tests {
 		assert!(truncated.starts_with('…'));
 		assert!(truncated.ends_with("file_name.ext"));
 	}
+
+	#[test]
+	fn horizontal_splits_divide_the_width() {
+		let area = Rect::new(2, 1, 100, 30);
+		for (ratio, results_width) in [(0.5, 50), (0.6, 60), (0.25, 25)] {
+			let (results, preview) = split_panes(area, PreviewLayout::Horizontal, ratio);
+			assert_eq!(results, Rect::new(2, 1, results_width, 30));
+			assert_eq!(
+				preview,
+				Rect::new(2 + results_width, 1, 100 - results_width, 30)
+			);
+		}
+	}
+
+	#[test]
+	fn vertical_splits_divide_the_height() {
+		let area = Rect::new(2, 1, 100, 30);
+		for (ratio, results_height) in [(0.5, 15), (0.6, 18), (0.3, 9)] {
+			let (results, preview) = split_panes(area, PreviewLayout::Vertical, ratio);
+			assert_eq!(results, Rect::new(2, 1, 100, results_height));
+			assert_eq!(
+				preview,
+				Rect::new(2, 1 + results_height, 100, 30 - results_height)
+			);
+		}
+	}
+
+	#[test]
+	fn odd_sizes_round_and_ratios_are_clamped() {
+		let area = Rect::new(0, 0, 81, 11);
+		let (results, preview) = split_panes(area, PreviewLayout::Vertical, 0.5);
+		assert_eq!((results.height, preview.height), (6, 5));
+
+		let (results, preview) = split_panes(area, PreviewLayout::Horizontal, 1.5);
+		assert_eq!((results.width, preview.width), (81, 0));
+	}
 }
@@ -4,19 +4,24 @@ use std::sync::OnceLock;
 
 use frizbee::Config;
 use frz_core::filesystem::search;
-use layout::resolve_column_widths;
+use layout::{resolve_column_widths, split_panes};
 use ratatui::Frame;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Margin, Rect};
-use ratatui::widgets::Paragraph;
+use ratatui::widgets::{Paragraph, TableState};
 
 use super::App;
+use super::preview::PreviewFind;
 use crate::components::preview::selection::apply_selection_to_lines;
-use crate::components::rows::build_file_rows;
-use crate::components::tables::{TABLE_HIGHLIGHT_SPACING, TableSpec};
+use crate::components::rows::RowLayout;
+use crate::components::tables::{TABLE_HEADER_ROWS, TABLE_HIGHLIGHT_SPACING, TableSpec};
 use crate::components::{
 	InputContext, PreviewContext, ProgressState, render_input, render_preview, render_table,
 };
 
+/// Terminal rows needed before the match footer is shown, so short
+/// terminals keep every row for the results.
+const FOOTER_MIN_HEIGHT: u16 = 10;
+
 impl App<'_> {
 	pub(crate) fn draw(&mut self, frame: &mut Frame) {
 		let area = clamp_area(frame.area());
@@ -25,10 +30,19 @@ impl App<'_> {
 			horizontal: 1,
 		});
 
+		let footer = self.match_footer && area.height >= FOOTER_MIN_HEIGHT;
 		let layout = Layout::default()
 			.direction(Direction::Vertical)
-			.constraints([Constraint::Length(1), Constraint::Min(1)])
+			.constraints([
+				Constraint::Length(1),
+				Constraint::Min(1),
+				Constraint::Length(u16::from(footer)),
+			])
 			.split(area);
+		if footer {
+			let label = Paragraph::new(self.match_footer_label()).style(self.style.theme.empty);
+			frame.render_widget(label, layout[2]);
+		}
 
 		let (progress_text, progress_complete) = self.progress_status();
 		let placeholder = self.ui.tabs().first().map(|tab| tab.tab_label.as_str());
@@ -47,17 +61,16 @@ impl App<'_> {
 
 		let results_area = layout[1];
 
-		// Split horizontally if preview is enabled
-		if self.preview.enabled {
-			let split = Layout::default()
-				.direction(Direction::Horizontal)
-				.constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-				.split(results_area);
-
-			self.results.area = Some(split[0]);
-			self.preview.area = Some(split[1]);
-			self.render_results(frame, split[0]);
-			self.render_preview_pane(frame, split[1]);
+		// Split between results and preview if preview is enabled
+		let panes = self
+			.preview
+			.enabled
+			.then(|| split_panes(results_area, self.preview.layout, self.preview.split_ratio));
+		if let Some((list_area, preview_area)) = panes {
+			self.results.area = Some(list_area);
+			self.preview.area = Some(preview_area);
+			self.render_results(frame, list_area);
+			self.render_preview_pane(frame, preview_area);
 		} else {
 			self.preview.area = None;
 			self.preview.hovered = false;
@@ -67,15 +80,7 @@ impl App<'_> {
 		}
 
 		if self.filtered_len() == 0 {
-			let mut message_area = if self.preview.enabled {
-				let split = Layout::default()
-					.direction(Direction::Horizontal)
-					.constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-					.split(results_area);
-				split[0]
-			} else {
-				results_area
-			};
+			let mut message_area = panes.map_or(results_area, |(list_area, _)| list_area);
 			// Account for border (1 top + 1 bottom) and header + divider (2)
 			const BORDER_AND_HEADER_HEIGHT: u16 = 4;
 			if message_area.height > BORDER_AND_HEADER_HEIGHT {
@@ -96,26 +101,58 @@ impl App<'_> {
 				}
 			}
 		}
+
+		if self.debug_overlay {
+			self.render_debug_overlay(frame, results_area);
+		}
 	}
 
-	fn progress_status(&mut self) -> (String, bool) {
+	pub(crate) fn progress_status(&mut self) -> (String, bool) {
+		if let Some(status) = self.current_status() {
+			return (status.to_string(), true);
+		}
 		let labels = vec![("files", "Files".to_string())];
-		self.index_progress.status(&labels)
+		let (progress, complete) = self.index_progress.status(&labels);
+		let parts: Vec<String> = [
+			self.tag_search.then(|| "Tags".to_owned()),
+			self.match_mode_label().map(str::to_owned),
+			self.selection_label()
+				.or_else(|| self.capped_results_label()),
+			Some(progress).filter(|progress| !progress.is_empty()),
+		]
+		.into_iter()
+		.flatten()
+		.collect();
+		(parts.join(" • "), complete)
 	}
 
 	fn render_results(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
-		// Update scrollbar state based on current viewport
+		// Only the rows in view are built, so loading more matches does not
+		// grow the work done per frame.
 		let inner_height = area.height.saturating_sub(2) as usize;
+		let window = self
+			.results
+			.scroll_to_selection(inner_height.saturating_sub(TABLE_HEADER_ROWS));
 		self.results.update_scrollbar(inner_height);
 
 		let highlight_owned = self.highlight_for_query(self.data.files.len());
 		let highlight_state = highlight_owned
 			.as_ref()
-			.map(|(text, config)| (text.as_str(), config.clone()));
+			.map(|(query, config)| (query, config.clone()));
 
 		// Default headers and widths if not set
-		let default_headers = vec!["Path".into(), "Score".into()];
-		let default_widths = vec![Constraint::Min(20), Constraint::Length(8)];
+		let mut default_headers = vec!["Path".into()];
+		let mut default_widths = vec![Constraint::Min(20)];
+		if self.show_sizes {
+			default_headers.push("Size".into());
+			default_widths.push(Constraint::Length(10));
+		}
+		if self.show_tags {
+			default_headers.push("Tags".into());
+			default_widths.push(Constraint::Length(20));
+		}
+		default_headers.push("Score".into());
+		default_widths.push(Constraint::Length(8));
 
 		let widths = self
 			.results
@@ -132,27 +169,48 @@ impl App<'_> {
 		let has_selection = self.results.table_state.selected().is_some();
 		let column_widths = resolve_column_widths(area, widths, has_selection);
 
-		let rows = build_file_rows(
-			&self.results.buffers.filtered,
-			&self.results.buffers.scores,
+		let scores = self
+			.results
+			.buffers
+			.scores
+			.get(window.clone())
+			.unwrap_or_default();
+		let rows = self.results.row_cache.build_file_rows(
+			&self.results.buffers.filtered[window.clone()],
+			scores,
 			&self.data.files,
 			highlight_state,
-			self.style.theme.highlight,
-			Some(&column_widths),
+			RowLayout {
+				highlight_style: self.style.theme.highlight,
+				column_widths: Some(&column_widths),
+				show_sizes: self.show_sizes,
+				show_tags: self.show_tags,
+				marked: Some(&self.results.marked),
+				positions: Some(&self.results.buffers.positions),
+			},
 		);
 
 		let spec = TableSpec {
 			headers: headers.clone(),
 			widths: widths.clone(),
 			rows,
+			total_rows: self.results.buffers.filtered.len(),
 			title: None,
 			highlight_spacing: TABLE_HIGHLIGHT_SPACING,
+			row_labels: self.quick_select_labels,
 		};
 
+		let mut window_state = TableState::default().with_selected(
+			self.results
+				.table_state
+				.selected()
+				.and_then(|selected| selected.checked_sub(window.start))
+				.filter(|&selected| selected < window.len()),
+		);
 		render_table(
 			frame,
 			area,
-			&mut self.results.table_state,
+			&mut window_state,
 			&mut self.results.scrollbar_state,
 			&mut self.results.scrollbar_area,
 			spec,
@@ -169,19 +227,40 @@ impl App<'_> {
 		let inner_width = area.width.saturating_sub(2) as usize;
 		let wrap_width = inner_width.saturating_sub(1);
 		self.rebuild_preview_wrap(wrap_width);
+		let found = if self.preview.find.is_none() {
+			self.follow_query_in_preview()
+		} else {
+			None
+		};
 		self.update_scrollbar_state();
 
+		// Mark the query on the line it was found on, or every occurrence of
+		// the preview's own search
+		let mut lines = self.preview.wrapped_lines.clone();
+		if let Some((terms, found)) = found {
+			let style = self.style.theme.highlight;
+			let gutter_width = self.preview.content.gutter_width;
+			let rows = self.preview.wrapped_rows_of(found);
+			for line in lines.get_mut(rows).into_iter().flatten() {
+				*line = terms.highlight(line, gutter_width, style);
+			}
+		}
+		if let Some(find) = &self.preview.find {
+			self.mark_preview_matches(find, &mut lines);
+		}
+
 		// Apply selection highlighting if active
 		let inner = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
 		let lines_with_selection = if self.preview.selection.has_selection() {
 			apply_selection_to_lines(
-				&self.preview.wrapped_lines,
+				&lines,
 				&self.preview.selection,
 				inner,
 				&self.style.theme,
+				self.preview.content.gutter_width,
 			)
 		} else {
-			self.preview.wrapped_lines.clone()
+			lines
 		};
 
 		let ctx = PreviewContext {
@@ -191,18 +270,21 @@ impl App<'_> {
 			scrollbar_state: &mut self.preview.scrollbar_state,
 			scrollbar_area: &mut self.preview.scrollbar_area,
 			scroll_metrics: self.preview.scroll_metrics,
+			prompt: self.preview.find.as_ref().map(PreviewFind::prompt),
 			theme: &self.style.theme,
 		};
 		render_preview(frame, area, ctx);
 	}
 
-	fn highlight_for_query(&self, dataset_len: usize) -> Option<(String, Config)> {
-		let query = self.search_input.text().trim();
-		if query.is_empty() {
+	fn highlight_for_query(&self, dataset_len: usize) -> Option<(search::Query, Config)> {
+		let text = self.matching_query();
+		let query = search::Query::parse_with_options(text, &self.search_options);
+		// Filters alone still mark the tags they ask for.
+		if query.is_empty() && query.filters().is_empty() {
 			return None;
 		}
-		let config = search::config_for_query(query, dataset_len);
-		Some((query.to_string(), config))
+		let config = search::config_for_options(text, dataset_len, &self.search_options);
+		Some((query, config))
 	}
 }
 
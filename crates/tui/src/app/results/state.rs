@@ -1,10 +1,12 @@
 //! State management for the results table.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use ratatui::layout::Rect;
 use ratatui::widgets::{ScrollbarState, TableState};
 
+use crate::components::rows::RowCache;
 use crate::components::tables::TABLE_HEADER_ROWS;
 use crate::components::{ScrollMetrics, point_in_rect};
 
@@ -13,6 +15,8 @@ use crate::components::{ScrollMetrics, point_in_rect};
 pub(crate) struct TabBuffers {
 	pub filtered: Vec<usize>,
 	pub scores: Vec<u16>,
+	/// Match positions computed while scoring, keyed by dataset index.
+	pub positions: HashMap<usize, Vec<usize>>,
 	pub headers: Option<Vec<String>>,
 	pub widths: Option<Vec<ratatui::layout::Constraint>>,
 }
@@ -41,6 +45,26 @@ pub(crate) struct ResultsState {
 	pub row_id_map: HashMap<u64, usize>,
 	/// Cached scroll metrics based on the last rendered viewport.
 	pub scroll_metrics: Option<ScrollMetrics>,
+	/// Rows built for earlier frames, reused while their inputs are unchanged.
+	pub row_cache: RowCache,
+	/// [Stable ids](frz_core::filesystem::search::FileRow::stable_id) of the
+	/// rows marked for a multi-selection, so marks follow their rows through
+	/// re-filtering and index updates.
+	pub marked: HashSet<u64>,
+	/// Stable id of the row a drag over the rows started on, so the range
+	/// keeps its start when results arrive during the drag.
+	pub range_anchor: Option<u64>,
+	/// Rows the current drag marked, unmarked again when the dragged range
+	/// shrinks away from them.
+	pub range_marked: HashSet<u64>,
+	/// Pages of [`SearchOptions::max_results`] matches requested for the
+	/// current query.
+	///
+	/// [`SearchOptions::max_results`]: frz_core::filesystem::search::SearchOptions::max_results
+	pub pages: usize,
+	/// Selection to hold while another page of matches streams in, so
+	/// partial results do not pull it back.
+	pub loading_more: Option<usize>,
 }
 
 impl Default for ResultsState {
@@ -59,6 +83,12 @@ impl Default for ResultsState {
 			buffers: TabBuffers::default(),
 			row_id_map: HashMap::new(),
 			scroll_metrics: None,
+			row_cache: RowCache::default(),
+			marked: HashSet::new(),
+			range_anchor: None,
+			range_marked: HashSet::new(),
+			pages: 1,
+			loading_more: None,
 		}
 	}
 }
@@ -130,6 +160,85 @@ impl ResultsState {
 		true
 	}
 
+	/// Select the `position`th row shown in the table, counting from 0 at
+	/// the first row below the scroll offset.
+	///
+	/// Returns `false` when fewer rows are shown, because the table ends
+	/// or is too short to show the row.
+	pub fn select_visible(&mut self, position: usize) -> bool {
+		let index = self.table_state.offset().saturating_add(position);
+		if position >= self.viewport_rows() || index >= self.filtered_len() {
+			return false;
+		}
+		self.table_state.select(Some(index));
+		true
+	}
+
+	/// Rows of the table in view, below its borders and header.
+	pub fn viewport_rows(&self) -> usize {
+		self.area.map_or(0, |area| {
+			(area.height.saturating_sub(2) as usize).saturating_sub(TABLE_HEADER_ROWS)
+		})
+	}
+
+	/// Move the selection by `delta` rows, clamped to the filtered rows, and
+	/// scroll the table by as much so the selection keeps its place in view.
+	pub fn move_selection_by(&mut self, delta: isize) {
+		let len = self.filtered_len();
+		let Some(selected) = self.table_state.selected().filter(|_| len > 0) else {
+			return;
+		};
+		let target = selected.saturating_add_signed(delta).min(len - 1);
+		let offset = self.table_state.offset().saturating_add_signed(delta);
+		self.table_state.select(Some(target));
+		*self.table_state.offset_mut() = offset;
+		self.follow_selection();
+	}
+
+	/// Select the filtered row at `index`, clamped to the last one.
+	pub fn select_clamped(&mut self, index: usize) {
+		let len = self.filtered_len();
+		if len == 0 {
+			return;
+		}
+		self.table_state.select(Some(index.min(len - 1)));
+		self.follow_selection();
+	}
+
+	/// Scroll the selection into view and update the scrollbar to match,
+	/// without waiting for the next frame.
+	fn follow_selection(&mut self) {
+		let rows = self.viewport_rows();
+		self.scroll_to_selection(rows);
+		if let Some(area) = self.area {
+			self.update_scrollbar(area.height.saturating_sub(2) as usize);
+		}
+	}
+
+	/// Scroll just far enough to keep the selection among the
+	/// `viewport_rows` rows shown, and return the filtered rows in view.
+	///
+	/// Only these rows are built for a frame, however many matches were
+	/// loaded.
+	pub fn scroll_to_selection(&mut self, viewport_rows: usize) -> Range<usize> {
+		let len = self.filtered_len();
+		let mut offset = self
+			.table_state
+			.offset()
+			.min(len.saturating_sub(viewport_rows));
+		if viewport_rows > 0
+			&& let Some(selected) = self.table_state.selected()
+		{
+			if selected < offset {
+				offset = selected;
+			} else if selected >= offset + viewport_rows {
+				offset = selected + 1 - viewport_rows;
+			}
+		}
+		*self.table_state.offset_mut() = offset;
+		offset..(offset + viewport_rows).min(len)
+	}
+
 	/// Compute scroll/offset metrics for the results viewport.
 	///
 	/// Uses `ScrollMetrics` but accounts for table header rows.
@@ -184,3 +293,28 @@ impl ResultsState {
 			.position(position);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn windows_follow_the_selection() {
+		let mut results = ResultsState::default();
+		results.buffers.filtered = (0..100).collect();
+		assert_eq!(results.scroll_to_selection(10), 0..10);
+
+		results.table_state.select(Some(42));
+		assert_eq!(results.scroll_to_selection(10), 33..43);
+		results.table_state.select(Some(35));
+		assert_eq!(results.scroll_to_selection(10), 33..43, "still in view");
+		results.table_state.select(Some(20));
+		assert_eq!(results.scroll_to_selection(10), 20..30);
+
+		results.table_state.select(Some(99));
+		assert_eq!(results.scroll_to_selection(10), 90..100);
+		results.buffers.filtered.truncate(5);
+		results.table_state.select(Some(4));
+		assert_eq!(results.scroll_to_selection(10), 0..5);
+	}
+}
@@ -1,4 +1,5 @@
 use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
 
 use frz_core::filesystem::indexer::IndexUpdate;
 use frz_core::filesystem::search::{MatchBatch, SearchResult, SearchView, SearchViewV2};
@@ -11,6 +12,52 @@ impl<'a> App<'a> {
 		self.issue_search();
 	}
 
+	/// Request a search for text the user just typed, waiting for the
+	/// configured debounce interval of idle input before issuing it.
+	pub(crate) fn request_search_debounced(&mut self) {
+		if !self.search.defer_search() {
+			self.issue_search();
+		}
+	}
+
+	/// Issue the debounced search once its interval has elapsed, or right
+	/// away when `force` is set. Returns whether a search was issued.
+	pub(crate) fn flush_debounced_search(&mut self, force: bool) -> bool {
+		if !self.search.take_deferred_search(force) {
+			return false;
+		}
+		self.issue_search();
+		true
+	}
+
+	/// Block until the in-flight search has delivered its final results.
+	pub(crate) fn wait_for_search_results(&mut self) {
+		while self.search.is_in_flight() {
+			match self.search.recv() {
+				Ok(result) => self.handle_search_result(result),
+				Err(_) => break,
+			}
+		}
+	}
+
+	/// Call `pump` until `done` holds, giving up after a few seconds.
+	/// Returns whether `done` held in the end.
+	#[cfg(test)]
+	pub(crate) fn pump_until(&mut self, pump: fn(&mut Self), done: impl Fn(&Self) -> bool) -> bool {
+		let deadline = std::time::Instant::now() + Duration::from_secs(5);
+		while !done(self) && std::time::Instant::now() < deadline {
+			std::thread::sleep(Duration::from_millis(10));
+			pump(self);
+		}
+		done(self)
+	}
+
+	/// Pump search results until `done` holds, see [`App::pump_until`].
+	#[cfg(test)]
+	pub(crate) fn pump_search_results_until(&mut self, done: impl Fn(&Self) -> bool) -> bool {
+		self.pump_until(Self::pump_search_results, done)
+	}
+
 	/// Schedule a search refresh due to new index data while respecting the
 	/// currently running query.
 	pub(crate) fn request_search_after_index_update(&mut self) {
@@ -51,24 +98,37 @@ impl<'a> App<'a> {
 	}
 
 	fn issue_search(&mut self) {
-		let query = self.search_input.text().to_string();
+		let query = self.matching_query().to_string();
 		self.search.issue_search(query);
 	}
 }
 
 impl<'a> SearchView for App<'a> {
 	fn replace_matches(&mut self, indices: Vec<usize>, scores: Vec<u16>) {
-		self.apply_match_batch(indices, None, scores);
+		self.apply_match_batch(MatchBatch {
+			indices,
+			ids: None,
+			scores,
+			positions: None,
+		});
 	}
 
 	fn clear_matches(&mut self) {
 		self.results.buffers.filtered.clear();
 		self.results.buffers.scores.clear();
+		self.results.buffers.positions.clear();
 		self.ensure_selection();
 	}
 
 	fn record_completion(&mut self, complete: bool) {
 		self.search.record_result_completion(complete);
+		if complete {
+			self.results.loading_more = None;
+		}
+	}
+
+	fn record_elapsed(&mut self, elapsed: Duration) {
+		self.search.record_elapsed(elapsed);
 	}
 
 	fn as_v2(&mut self) -> Option<&mut dyn SearchViewV2> {
@@ -78,11 +138,6 @@ impl<'a> SearchView for App<'a> {
 
 impl<'a> SearchViewV2 for App<'a> {
 	fn replace_matches_v2(&mut self, batch: MatchBatch) {
-		let MatchBatch {
-			indices,
-			ids,
-			scores,
-		} = batch;
-		self.apply_match_batch(indices, ids, scores);
+		self.apply_match_batch(batch);
 	}
 }
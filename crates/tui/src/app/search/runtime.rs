@@ -6,11 +6,13 @@
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
-use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::mpsc::{Receiver, RecvError, Sender, TryRecvError};
+use std::time::{Duration, Instant};
 
 use frz_core::filesystem::indexer::{IndexUpdate, merge_update};
 use frz_core::filesystem::search::runtime::SearchCommand;
-use frz_core::filesystem::search::{SearchData, SearchResult};
+use frz_core::filesystem::search::{SearchData, SearchOptions, SearchResult, SortKey};
+use frz_core::frecency::Frecency;
 use frz_stream::StreamAction;
 
 /// Tracks the revision counters used to determine when data has changed.
@@ -32,6 +34,12 @@ pub(crate) struct SearchRuntime {
 	in_flight: bool,
 	user_has_typed: bool,
 	revisions: RevisionState,
+	/// Idle time required after typing before a query is issued.
+	debounce: Duration,
+	/// When the debounced query should be issued, if one is waiting.
+	debounce_deadline: Option<Instant>,
+	/// How long the last finished query took to score.
+	elapsed: Option<Duration>,
 }
 
 impl SearchRuntime {
@@ -50,9 +58,54 @@ impl SearchRuntime {
 			in_flight: false,
 			user_has_typed: false,
 			revisions: RevisionState::default(),
+			debounce: Duration::ZERO,
+			debounce_deadline: None,
+			elapsed: None,
 		}
 	}
 
+	pub(crate) fn set_debounce(&mut self, debounce: Duration) {
+		self.debounce = debounce;
+	}
+
+	/// Restart the debounce timer. Returns `false` when debouncing is
+	/// disabled and the query should be issued right away.
+	pub(crate) fn defer_search(&mut self) -> bool {
+		if self.debounce.is_zero() {
+			return false;
+		}
+		self.debounce_deadline = Some(Instant::now() + self.debounce);
+		true
+	}
+
+	/// Clear the debounce timer, returning whether a query was waiting on it
+	/// and, unless `force` is set, the interval has elapsed.
+	pub(crate) fn take_deferred_search(&mut self, force: bool) -> bool {
+		match self.debounce_deadline {
+			Some(deadline) if force || Instant::now() >= deadline => {
+				self.debounce_deadline = None;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	pub(crate) fn configure(&self, options: SearchOptions) {
+		let _ = self.tx.send(SearchCommand::Configure(options));
+	}
+
+	pub(crate) fn configure_sort(&self, sort_key: SortKey) {
+		let _ = self.tx.send(SearchCommand::Sort(sort_key));
+	}
+
+	pub(crate) fn configure_frecency(&self, frecency: Frecency) {
+		let _ = self.tx.send(SearchCommand::Frecency(frecency));
+	}
+
+	pub(crate) fn configure_tags(&self, tags: bool) {
+		let _ = self.tx.send(SearchCommand::Tags(tags));
+	}
+
 	pub(crate) fn shutdown(&self) {
 		let _ = self.tx.send(SearchCommand::Shutdown);
 	}
@@ -68,6 +121,7 @@ impl SearchRuntime {
 	}
 
 	pub(crate) fn issue_search(&mut self, query: String) {
+		self.debounce_deadline = None;
 		self.next_query_id = self.next_query_id.saturating_add(1);
 		let id = self.next_query_id;
 		self.current_query_id = Some(id);
@@ -79,6 +133,7 @@ impl SearchRuntime {
 
 	pub(crate) fn should_refresh_after_index_update(&self) -> bool {
 		!self.in_flight
+			&& self.debounce_deadline.is_none()
 			&& self.revisions.input != self.revisions.last_applied
 			&& self.revisions.input == self.revisions.last_user_input
 	}
@@ -95,6 +150,15 @@ impl SearchRuntime {
 		}
 	}
 
+	pub(crate) fn record_elapsed(&mut self, elapsed: Duration) {
+		self.elapsed = Some(elapsed);
+	}
+
+	/// How long the last finished query took to score, if one finished.
+	pub(crate) fn last_elapsed(&self) -> Option<Duration> {
+		self.elapsed
+	}
+
 	pub(crate) fn has_issued_query(&self) -> bool {
 		self.current_query_id.is_some()
 	}
@@ -103,7 +167,6 @@ impl SearchRuntime {
 		self.user_has_typed
 	}
 
-	#[cfg(test)]
 	pub(crate) fn is_in_flight(&self) -> bool {
 		self.in_flight
 	}
@@ -117,6 +180,10 @@ impl SearchRuntime {
 		self.rx.try_recv()
 	}
 
+	pub(crate) fn recv(&mut self) -> Result<SearchResult, RecvError> {
+		self.rx.recv()
+	}
+
 	pub(crate) fn notify_of_update(&self, update: &IndexUpdate) {
 		let action = StreamAction::new({
 			let update = update.clone();
@@ -3,21 +3,39 @@
 //! The `app` module exposes the [`App`] struct which bundles together search
 //! data, extension metadata, and UI-specific caches.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 
 use frz_core::filesystem::indexer::IndexResult;
 use frz_core::filesystem::search::{
-	FILES_DATASET_KEY, SearchData, SearchSelection, runtime as search,
+	CaseMatching, FILES_DATASET_KEY, MatchBatch, MatchMode, PreviewPosition, SearchData,
+	SearchOptions, SearchSelection, SortKey, runtime as search,
 };
+use frz_core::frecency::Frecency;
+use ratatui::crossterm::event::KeyEvent;
 use throbber_widgets_tui::ThrobberState;
 
 use super::SearchRuntime;
-use super::preview::PreviewState;
+use super::actions::PathInsertion;
+use super::preview::{FollowedQuery, PreviewState};
 use super::results::ResultsState;
-use crate::components::{IndexProgress, PreviewContent, PreviewKind, wrap_highlighted_lines};
-use crate::config::UiLabels;
+use crate::components::preview::PreviewTerms;
+use crate::components::progress::{format_count, format_match_footer};
+use crate::components::{
+	Gutter, IndexProgress, PreviewContent, SharedPreviewProvider, copy_to_clipboard,
+	wrap_highlighted_lines,
+};
+use crate::config::{PdfPreviewMode, PreviewLayout, UiLabels};
 use crate::input::QueryInput;
-use crate::style::{StyleConfig, Theme};
+use crate::keys::{Action, KeyBindings};
+use crate::style::{ColorDepth, StyleConfig, Theme};
+#[cfg(feature = "theme-watch")]
+use crate::style::{ThemeReload, ThemeWatcher};
+
+/// How long a message posted with [`App::show_status`] stays visible.
+pub(crate) const STATUS_DURATION: Duration = Duration::from_secs(4);
 
 impl<'a> Drop for App<'a> {
 	fn drop(&mut self) {
@@ -42,6 +60,42 @@ pub struct App<'a> {
 	pub(crate) index_progress: IndexProgress,
 	pub(crate) index_updates: Option<Receiver<IndexResult>>,
 	pub(crate) search: SearchRuntime,
+	pub(crate) search_options: SearchOptions,
+	pub(crate) last_path_insertion: Option<PathInsertion>,
+	pub(crate) sort_key: SortKey,
+	/// Whether queries match the rows' tags instead of their paths.
+	pub(crate) tag_search: bool,
+	pub(crate) show_sizes: bool,
+	pub(crate) show_tags: bool,
+	/// Whether the rows that Alt+1..Alt+9 accept are numbered.
+	pub(crate) quick_select_labels: bool,
+	/// Whether a footer under the results counts the matches and shows how
+	/// long the last query took.
+	pub(crate) match_footer: bool,
+	/// Whether the overlay describing the picker's internal state is shown.
+	pub(crate) debug_overlay: bool,
+	pub(crate) key_bindings: KeyBindings,
+	/// Keys that accept the search and are reported in the outcome.
+	pub(crate) expect_keys: Vec<KeyEvent>,
+	pub(crate) min_query_length: usize,
+	/// Transient message shown in place of the index progress, and when it
+	/// was posted.
+	pub(crate) status: Option<(String, Instant)>,
+	/// Command opening files for editing, instead of `$VISUAL`/`$EDITOR`.
+	pub(crate) editor: Option<String>,
+	/// File to open in the editor once the runtime suspends the UI.
+	pub(crate) pending_edit: Option<PathBuf>,
+	/// Whether the keys of [`Action::CopyPath`](crate::keys::Action::CopyPath)
+	/// and [`Action::CopyAbsolutePath`](crate::keys::Action::CopyAbsolutePath)
+	/// are swapped.
+	pub(crate) copy_absolute: bool,
+	/// Path of a row to highlight once it is among the matches, unless the
+	/// user moved the selection first.
+	pub(crate) restore_selection: Option<String>,
+	/// Copies text to the system clipboard; replaced in tests.
+	pub(crate) clipboard: fn(&str) -> Result<(), String>,
+	#[cfg(feature = "theme-watch")]
+	pub(crate) theme_watcher: Option<ThemeWatcher>,
 	pub(crate) preview: PreviewState,
 	pub(crate) results: ResultsState,
 }
@@ -64,6 +118,26 @@ impl<'a> App<'a> {
 			index_progress,
 			index_updates: None,
 			search,
+			search_options: SearchOptions::default(),
+			last_path_insertion: None,
+			sort_key: SortKey::default(),
+			tag_search: false,
+			show_sizes: false,
+			show_tags: false,
+			quick_select_labels: false,
+			match_footer: false,
+			debug_overlay: false,
+			key_bindings: KeyBindings::default(),
+			expect_keys: Vec::new(),
+			min_query_length: 0,
+			status: None,
+			editor: None,
+			pending_edit: None,
+			copy_absolute: false,
+			restore_selection: None,
+			clipboard: copy_to_clipboard,
+			#[cfg(feature = "theme-watch")]
+			theme_watcher: None,
 			preview,
 			results,
 		}
@@ -98,6 +172,139 @@ impl<'a> App<'a> {
 		(search, index_progress)
 	}
 
+	/// Replace the matching options and rerun the current query with them.
+	pub fn set_search_options(&mut self, options: SearchOptions) {
+		if self.search_options == options {
+			return;
+		}
+		self.search.configure(options.clone());
+		self.search_options = options;
+		self.results.pages = 1;
+		self.results.loading_more = None;
+		self.mark_query_dirty();
+		if self.search.has_issued_query() {
+			self.request_search();
+		}
+	}
+
+	/// Switch to the next case matching mode (smart, sensitive, insensitive)
+	/// and rerun the current query with it.
+	pub(crate) fn cycle_case_matching(&mut self) {
+		let case_matching = match self.search_options.case_matching {
+			CaseMatching::Smart => CaseMatching::Sensitive,
+			CaseMatching::Sensitive => CaseMatching::Insensitive,
+			CaseMatching::Insensitive => CaseMatching::Smart,
+		};
+		self.set_search_options(
+			self.search_options
+				.clone()
+				.with_case_matching(case_matching),
+		);
+	}
+
+	/// Switch between fuzzy and substring matching and rerun the current
+	/// query with the new mode.
+	pub(crate) fn toggle_match_mode(&mut self) {
+		let match_mode = self.search_options.match_mode.toggled();
+		self.set_search_options(self.search_options.clone().with_match_mode(match_mode));
+	}
+
+	/// Switch between matching the rows' paths and their
+	/// [tags](frz_core::filesystem::search::FileRow::tags), and rerun the
+	/// current query.
+	pub(crate) fn toggle_tag_search(&mut self) {
+		self.tag_search = !self.tag_search;
+		self.search.configure_tags(self.tag_search);
+		self.mark_query_dirty();
+		self.request_search();
+	}
+
+	/// Switch to the next sort mode (score, name, modification time).
+	pub(crate) fn cycle_sort_key(&mut self) {
+		self.set_sort_key(self.sort_key.next());
+	}
+
+	/// Change how filtered results are ordered and refresh the results.
+	///
+	/// The sort key also orders the rows browsed with an empty query.
+	pub fn set_sort_key(&mut self, sort_key: SortKey) {
+		if self.sort_key == sort_key {
+			return;
+		}
+		self.search.configure_sort(sort_key);
+		self.sort_key = sort_key;
+		self.mark_query_dirty();
+		if self.search.has_issued_query() {
+			self.request_search();
+		}
+	}
+
+	/// Rank recently and frequently accepted files ahead of otherwise equal
+	/// matches.
+	pub fn set_frecency(&mut self, frecency: Frecency) {
+		self.search.configure_frecency(frecency);
+		self.mark_query_dirty();
+		if self.search.has_issued_query() {
+			self.request_search();
+		}
+	}
+
+	/// Show a column with human-readable file sizes in the results table.
+	pub fn show_size_column(&mut self, show: bool) {
+		self.show_sizes = show;
+	}
+
+	/// Show a column with each row's tags in the results table.
+	pub fn show_tag_column(&mut self, show: bool) {
+		self.show_tags = show;
+	}
+
+	/// Number the rows that Alt+1..Alt+9 accept in the results table.
+	pub fn show_quick_select_labels(&mut self, show: bool) {
+		self.quick_select_labels = show;
+	}
+
+	/// Show a footer under the results with the number of matches out of
+	/// every row and how long the last query took to score.
+	pub fn show_match_footer(&mut self, show: bool) {
+		self.match_footer = show;
+	}
+
+	/// Only start matching once the query has at least `length` characters.
+	///
+	/// Shorter queries list rows as if the query were empty, which avoids
+	/// matching huge datasets against one or two characters. Leading and
+	/// trailing whitespace is not counted.
+	pub fn set_min_query_length(&mut self, length: usize) {
+		if self.min_query_length == length {
+			return;
+		}
+		self.min_query_length = length;
+		self.mark_query_dirty();
+		if self.search.has_issued_query() {
+			self.request_search();
+		}
+	}
+
+	/// The query text to match, or an empty query while the input is shorter
+	/// than the minimum length.
+	pub(crate) fn matching_query(&self) -> &str {
+		let text = self.search_input.text();
+		if text.trim().chars().count() < self.min_query_length {
+			""
+		} else {
+			text
+		}
+	}
+
+	/// Wait until typing has been idle for `debounce` before issuing a query.
+	///
+	/// The prompt still updates on every key and Enter skips the wait. A zero
+	/// interval, the default, issues a query on every change.
+	pub fn set_search_debounce(&mut self, debounce: Duration) {
+		self.search.set_debounce(debounce);
+	}
+
 	/// Apply a new theme without changing the associated bat configuration.
 	pub fn set_theme(&mut self, theme: Theme) {
 		self.set_theme_with_bat(theme, None);
@@ -105,10 +312,93 @@ impl<'a> App<'a> {
 
 	/// Apply a new theme and optional bat theme name.
 	pub fn set_theme_with_bat(&mut self, theme: Theme, bat_theme: Option<String>) {
-		self.style.theme = theme;
+		self.style.set_theme(theme);
 		self.bat_theme = bat_theme;
 	}
 
+	/// Render at `depth`, downgrading the current and any later theme when
+	/// truecolor is unavailable.
+	pub fn set_color_depth(&mut self, depth: ColorDepth) {
+		self.style = self.style.clone().with_color_depth(depth);
+	}
+
+	/// Open files for editing with `command`, split into words like a shell
+	/// would, instead of `$VISUAL` or `$EDITOR`. The file's path is appended.
+	pub fn set_editor(&mut self, command: impl Into<String>) {
+		self.editor = Some(command.into());
+	}
+
+	/// Swap the keys bound to [`Action::CopyPath`](crate::keys::Action::CopyPath)
+	/// and [`Action::CopyAbsolutePath`](crate::keys::Action::CopyAbsolutePath)
+	/// when `absolute`, so Ctrl+Y copies resolved paths and Alt+Y relative
+	/// ones.
+	pub fn set_absolute_copy(&mut self, absolute: bool) {
+		if self.copy_absolute == absolute {
+			return;
+		}
+		let relative_keys = self.key_bindings.keys(Action::CopyPath).to_vec();
+		let absolute_keys = self.key_bindings.keys(Action::CopyAbsolutePath).to_vec();
+		self.key_bindings = std::mem::take(&mut self.key_bindings)
+			.bind(Action::CopyPath, absolute_keys)
+			.bind(Action::CopyAbsolutePath, relative_keys);
+		self.copy_absolute = absolute;
+	}
+
+	/// Highlight the row at `path` as soon as it is among the matches, e.g.
+	/// the file accepted last time.
+	///
+	/// This happens at most once, and not at all once the user moved the
+	/// selection, typed or clicked.
+	pub fn restore_selection(&mut self, path: impl Into<String>) {
+		self.restore_selection = Some(path.into());
+	}
+
+	/// Show `message` next to the prompt for a few seconds, in place of the
+	/// indexing progress.
+	pub fn show_status(&mut self, message: impl Into<String>) {
+		self.status = Some((message.into(), Instant::now()));
+	}
+
+	/// The status message, while it has not yet expired.
+	pub(crate) fn current_status(&self) -> Option<&str> {
+		self.status
+			.as_ref()
+			.filter(|(_, posted)| posted.elapsed() < STATUS_DURATION)
+			.map(|(message, _)| message.as_str())
+	}
+
+	/// Reload the active theme whenever `watcher` reports that its file
+	/// changed.
+	#[cfg(feature = "theme-watch")]
+	pub fn set_theme_watcher(&mut self, watcher: ThemeWatcher) {
+		self.theme_watcher = Some(watcher);
+	}
+
+	/// Apply theme reloads reported by the watcher. Failed reloads keep the
+	/// current theme and are reported as a status message.
+	#[cfg(feature = "theme-watch")]
+	pub(crate) fn pump_theme_reloads(&mut self) {
+		let Some(watcher) = self.theme_watcher.as_ref() else {
+			return;
+		};
+		let mut reloads = Vec::new();
+		while let Some(reload) = watcher.try_recv() {
+			reloads.push(reload);
+		}
+		for reload in reloads {
+			match reload {
+				ThemeReload::Reloaded { name } => {
+					if let Some(theme) = crate::style::by_name(&name) {
+						self.set_theme_with_bat(theme, crate::style::bat_theme(&name));
+					}
+				}
+				ThemeReload::Failed { message } => {
+					self.show_status(format!("Theme not reloaded: {message}"));
+				}
+			}
+		}
+	}
+
 	/// Ensure the row selection remains valid for the currently filtered list.
 	pub(crate) fn ensure_selection(&mut self) {
 		self.results.ensure_selection();
@@ -119,6 +409,99 @@ impl<'a> App<'a> {
 		self.results.filtered_len()
 	}
 
+	/// Label such as `2,000+ of 134,551 files` when the results were cut off
+	/// at [`SearchOptions::max_results`].
+	///
+	/// A query matching exactly the maximum is labelled as capped too, since
+	/// the search does not report how many matches it dropped.
+	pub(crate) fn capped_results_label(&self) -> Option<String> {
+		let shown = self.filtered_len();
+		let total = self.data.files.len();
+		self.results_capped()
+			.then(|| format!("{}+ of {} files", format_count(shown), format_count(total)))
+	}
+
+	/// Text of the footer enabled with [`App::show_match_footer`].
+	pub(crate) fn match_footer_label(&self) -> String {
+		format_match_footer(
+			self.filtered_len(),
+			self.data.files.len(),
+			self.results_capped(),
+			self.search.last_elapsed(),
+		)
+	}
+
+	/// Whether the current query matched more rows than were kept.
+	fn results_capped(&self) -> bool {
+		let shown = self.filtered_len();
+		shown >= self.result_limit() && self.data.files.len() > shown
+	}
+
+	/// Most matches kept for the current query: a
+	/// [`SearchOptions::max_results`] page for every page loaded so far.
+	pub(crate) fn result_limit(&self) -> usize {
+		self.search_options
+			.max_results
+			.saturating_mul(self.results.pages)
+	}
+
+	/// Search for another page of matches once the last loaded match of a
+	/// capped query is selected or scrolled into view, keeping the
+	/// selection where it is.
+	///
+	/// A page is loaded by running the query again with a limit one page
+	/// larger, so every page scores the whole dataset once more and sorts
+	/// all matches kept so far. Paging far into a huge result set costs a
+	/// full search per page rather than just the rows added.
+	pub(crate) fn load_more_results(&mut self) {
+		let Some(selected) = self.results.table_state.selected() else {
+			return;
+		};
+		let len = self.filtered_len();
+		let last_in_view = self.results.table_state.offset() + self.results.viewport_rows() >= len;
+		if (selected + 1 < len && !last_in_view)
+			|| !self.results_capped()
+			|| self.results.loading_more.is_some()
+		{
+			return;
+		}
+		self.results.pages += 1;
+		self.results.loading_more = Some(selected);
+		self.search.configure(
+			self.search_options
+				.clone()
+				.with_max_results(self.result_limit()),
+		);
+		self.mark_query_dirty();
+		self.request_search();
+	}
+
+	/// Label such as `3 selected / 120 matched / 4,512 files` while rows are
+	/// marked, with a `+` after the matches when they were capped like in
+	/// [`Self::capped_results_label`].
+	pub(crate) fn selection_label(&self) -> Option<String> {
+		let selected = self.results.marked.len();
+		if selected == 0 {
+			return None;
+		}
+		let shown = self.filtered_len();
+		let total = self.data.files.len();
+		let capped = self.results_capped();
+		Some(format!(
+			"{} selected / {}{} matched / {} files",
+			format_count(selected),
+			format_count(shown),
+			if capped { "+" } else { "" },
+			format_count(total)
+		))
+	}
+
+	/// Prompt indicator for the match mode, shown while plain terms are
+	/// matched as substrings.
+	pub(crate) fn match_mode_label(&self) -> Option<&'static str> {
+		(self.search_options.match_mode == MatchMode::Substring).then_some("Substring")
+	}
+
 	/// Flag the in-memory query so the next search run refreshes it.
 	pub(crate) fn mark_query_dirty(&mut self) {
 		self.search.mark_query_dirty();
@@ -128,6 +511,19 @@ impl<'a> App<'a> {
 	/// indexing updates are pending.
 	pub(crate) fn mark_query_dirty_from_user_input(&mut self) {
 		self.search.mark_query_dirty_from_user_input();
+		// A new query starts over from the first page of matches.
+		if self.results.pages > 1 {
+			self.results.pages = 1;
+			self.results.loading_more = None;
+			self.search.configure(self.search_options.clone());
+		}
+	}
+
+	/// Preview location of `selection`, if it is the file being previewed.
+	pub(crate) fn preview_position(&self, selection: &SearchSelection) -> Option<PreviewPosition> {
+		let SearchSelection::File(file) = selection;
+		let path = self.data.resolve_file_path(file);
+		self.preview.position_for(&path.display().to_string())
 	}
 
 	/// Compute the currently selected row using extension-specific logic.
@@ -141,6 +537,48 @@ impl<'a> App<'a> {
 			.map(SearchSelection::File)
 	}
 
+	/// Rows confirmed by Enter: the marked rows in dataset order, or the
+	/// highlighted row when no marked row is present.
+	pub(crate) fn current_selections(&self) -> Vec<SearchSelection> {
+		let marked: Vec<SearchSelection> = if self.results.marked.is_empty() {
+			Vec::new()
+		} else {
+			self.data
+				.files
+				.iter()
+				.filter(|file| self.results.marked.contains(&file.stable_id()))
+				.cloned()
+				.map(SearchSelection::File)
+				.collect()
+		};
+		if marked.is_empty() {
+			return self.current_selection().into_iter().collect();
+		}
+		marked
+	}
+
+	/// Match score of each of `selections` under the active query, or `None`
+	/// for rows it does not match.
+	pub(crate) fn selection_scores(&self, selections: &[SearchSelection]) -> Vec<Option<u16>> {
+		let ids: Vec<u64> = selections
+			.iter()
+			.map(|SearchSelection::File(file)| file.stable_id())
+			.collect();
+		let mut scores: HashMap<u64, Option<u16>> = ids.iter().map(|&id| (id, None)).collect();
+		let buffers = &self.results.buffers;
+		for (&index, &score) in buffers.filtered.iter().zip(&buffers.scores) {
+			if let Some(slot) = self
+				.data
+				.files
+				.get(index)
+				.and_then(|file| scores.get_mut(&file.stable_id()))
+			{
+				*slot = Some(score);
+			}
+		}
+		ids.iter().map(|id| scores[id]).collect()
+	}
+
 	/// Ensure that every known search mode has backing buffers.
 	pub(crate) fn ensure_tab_buffers(&mut self) {
 		// No-op now that we have a single tab buffer
@@ -152,16 +590,18 @@ impl<'a> App<'a> {
 			.data
 			.id_map_for_dataset(FILES_DATASET_KEY)
 			.unwrap_or_default();
+		self.results.row_cache.clear();
 	}
 
 	/// Apply a batch of matches, reconciling stable ids with indices when
-	/// available.
-	pub(crate) fn apply_match_batch(
-		&mut self,
-		indices: Vec<usize>,
-		ids: Option<Vec<u64>>,
-		scores: Vec<u16>,
-	) {
+	/// available and keeping the match positions the batch carries.
+	pub(crate) fn apply_match_batch(&mut self, batch: MatchBatch) {
+		let MatchBatch {
+			indices,
+			ids,
+			scores,
+			positions,
+		} = batch;
 		// Track the path of the currently selected item before updating results
 		let old_selected_path = if self.preview.enabled {
 			self.current_selection().map(|sel| match sel {
@@ -173,29 +613,46 @@ impl<'a> App<'a> {
 			None
 		};
 
-		let filtered = if let Some(ids) = ids {
-			let ids_len = ids.len();
-			let mut resolved: Vec<usize> = ids
-				.into_iter()
-				.enumerate()
-				.filter_map(|(offset, id)| {
-					self.results
-						.row_id_map
-						.get(&id)
-						.copied()
+		// Resolve every offset of the batch so positions stay with their row.
+		let resolved: Vec<Option<usize>> = match ids {
+			Some(ids) => (0..ids.len().max(indices.len()))
+				.map(|offset| {
+					ids.get(offset)
+						.and_then(|id| self.results.row_id_map.get(id).copied())
 						.or_else(|| indices.get(offset).copied())
 				})
-				.collect();
-			if ids_len < indices.len() {
-				resolved.extend(indices.into_iter().skip(ids_len));
-			}
-			resolved
-		} else {
-			indices
+				.collect(),
+			None => indices.into_iter().map(Some).collect(),
 		};
-		self.results.buffers.filtered = filtered;
+		self.results.buffers.positions = positions
+			.map(|positions| {
+				resolved
+					.iter()
+					.zip(positions)
+					.filter_map(|(index, positions)| Some(((*index)?, positions)))
+					.collect()
+			})
+			.unwrap_or_default();
+		// The search worker orders the matches by the sort key before
+		// limiting them, so the rows arrive in display order.
+		self.results.buffers.filtered = resolved.into_iter().flatten().collect();
 		self.results.buffers.scores = scores;
 		self.ensure_selection();
+		if let Some(selected) = self.results.loading_more
+			&& selected < self.filtered_len()
+		{
+			self.results.table_state.select(Some(selected));
+		}
+		if let Some(path) = self.restore_selection.as_deref()
+			&& let Some(position) = self.results.buffers.filtered.iter().position(|&index| {
+				self.data
+					.files
+					.get(index)
+					.is_some_and(|file| file.path == path)
+			}) {
+			self.results.table_state.select(Some(position));
+			self.restore_selection = None;
+		}
 
 		// Update preview if enabled and the selected item changed
 		if self.preview.enabled {
@@ -241,6 +698,70 @@ impl<'a> App<'a> {
 		self.update_preview();
 	}
 
+	/// Choose whether markdown files are previewed as formatted text, the
+	/// default, or as highlighted source.
+	pub fn set_markdown_preview(&mut self, rendered: bool) {
+		self.preview.runtime.set_render_markdown(rendered);
+	}
+
+	/// Choose whether highlighted previews show who last changed each line,
+	/// from `git blame`.
+	pub fn set_blame_preview(&mut self, enabled: bool) {
+		self.preview.runtime.set_blame(enabled);
+	}
+
+	/// Choose whether highlighted previews number their lines, the default.
+	pub fn set_line_numbers(&mut self, enabled: bool) {
+		self.preview.runtime.set_line_numbers(enabled);
+	}
+
+	/// Choose whether PDFs are previewed as a rendered page or as text.
+	pub fn set_pdf_preview(&mut self, mode: PdfPreviewMode) {
+		self.preview.runtime.set_pdf_mode(mode);
+	}
+
+	/// Shrink previewed images larger than `max` pixels on either side.
+	pub fn set_max_image_dimension(&mut self, max: u32) {
+		self.preview.runtime.set_max_image_dimension(max);
+	}
+
+	/// Show or hide the line numbers of highlighted previews and preview the
+	/// highlighted file again with the change.
+	pub(crate) fn toggle_line_numbers(&mut self) {
+		let enabled = !self.preview.runtime.line_numbers();
+		self.preview.runtime.set_line_numbers(enabled);
+		self.preview.path.clear();
+		self.preview.pending_path = None;
+		self.refresh_preview_if_enabled();
+	}
+
+	/// Switch markdown previews between formatted text and highlighted source
+	/// and preview the highlighted file again with the change.
+	pub(crate) fn toggle_markdown_preview(&mut self) {
+		let rendered = !self.preview.runtime.render_markdown();
+		self.preview.runtime.set_render_markdown(rendered);
+		self.preview.path.clear();
+		self.preview.pending_path = None;
+		self.refresh_preview_if_enabled();
+	}
+
+	/// Ask `provider` for preview content before the built-in file previews.
+	pub fn add_preview_provider(&mut self, provider: SharedPreviewProvider) {
+		self.preview.runtime.add_provider(provider);
+	}
+
+	/// Place the preview beside the results or below them.
+	pub fn set_preview_layout(&mut self, layout: PreviewLayout) {
+		self.preview.layout = layout;
+	}
+
+	/// Give the results `ratio` of the space shared with the preview.
+	///
+	/// The ratio is clamped to between 0 and 1.
+	pub fn set_preview_split(&mut self, ratio: f32) {
+		self.preview.split_ratio = ratio.clamp(0.0, 1.0);
+	}
+
 	/// Disable the preview pane.
 	pub fn disable_preview(&mut self) {
 		self.preview.enabled = false;
@@ -280,6 +801,9 @@ impl<'a> App<'a> {
 				self.preview.path.clear();
 				self.preview.pending_path = None;
 				self.preview.scroll = 0;
+				self.preview.scrolled_by_user = false;
+				self.preview.find = None;
+				self.preview.followed = None;
 				return;
 			}
 		};
@@ -300,9 +824,12 @@ impl<'a> App<'a> {
 		self.preview.pending_path = Some(path_str);
 
 		// Request preview generation in background
-		self.preview
-			.runtime
-			.request(path, self.bat_theme.clone(), 500);
+		self.preview.runtime.request(
+			SearchSelection::File(selection),
+			self.data.root.clone(),
+			self.bat_theme.clone(),
+			500,
+		);
 	}
 
 	/// Poll for completed preview results from the background worker.
@@ -319,6 +846,9 @@ impl<'a> App<'a> {
 						self.preview.content = result.content;
 						self.preview.pending_path = None;
 						self.preview.scroll = 0;
+						self.preview.scrolled_by_user = false;
+						self.preview.find = None;
+						self.preview.followed = None;
 						self.rebuild_preview_wrap(self.preview.wrap_width);
 						self.preview.update_scrollbar();
 					}
@@ -331,14 +861,50 @@ impl<'a> App<'a> {
 
 	/// Scroll the preview pane up.
 	pub(crate) fn scroll_preview_up(&mut self, lines: usize) {
+		self.preview.scrolled_by_user = true;
 		self.preview.scroll_up(lines);
 	}
 
 	/// Scroll the preview pane down.
 	pub(crate) fn scroll_preview_down(&mut self, lines: usize) {
+		self.preview.scrolled_by_user = true;
 		self.preview.scroll_down(lines);
 	}
 
+	/// Find the query in the wrapped preview and scroll to the first line
+	/// mentioning it, unless the user scrolled since the preview was loaded.
+	///
+	/// Returns the query's words and the source line they were found on, so
+	/// the caller can mark them. The search skips the preview's gutter and
+	/// only runs again once the query or the preview changed.
+	pub(crate) fn follow_query_in_preview(&mut self) -> Option<(PreviewTerms, usize)> {
+		let stale = self
+			.preview
+			.followed
+			.as_ref()
+			.is_none_or(|followed| followed.query != self.matching_query());
+		if stale {
+			let query = self.matching_query().to_owned();
+			let terms = PreviewTerms::parse(&query);
+			let gutter_width = self.preview.content.gutter_width;
+			let line = self
+				.preview
+				.content
+				.lines()
+				.and_then(|lines| terms.first_line(lines, gutter_width));
+			self.preview.followed = Some(FollowedQuery { query, terms, line });
+		}
+		let followed = self.preview.followed.as_ref()?;
+		let found = followed.line.map(|line| (followed.terms.clone(), line));
+		if !self.preview.scrolled_by_user {
+			self.preview.scroll = found
+				.as_ref()
+				.map_or(0, |&(_, line)| self.preview.wrapped_rows_of(line).start);
+			self.preview.update_scrollbar();
+		}
+		found
+	}
+
 	pub(crate) fn update_preview_hover(&mut self, column: u16, row: u16) {
 		self.preview.update_hover(column, row);
 	}
@@ -359,10 +925,22 @@ impl<'a> App<'a> {
 	pub(crate) fn rebuild_preview_wrap(&mut self, available_width: usize) {
 		self.preview.wrap_width = available_width;
 
-		self.preview.wrapped_lines = match &self.preview.content.kind {
-			PreviewKind::Text { lines } => wrap_highlighted_lines(lines, available_width),
-			_ => Vec::new(),
+		self.preview.wrapped_lines.clear();
+		self.preview.wrapped_sources.clear();
+		let gutter = Gutter {
+			width: self.preview.content.gutter_width,
+			style: self.style.theme.empty,
 		};
+		if let Some(lines) = self.preview.content.lines() {
+			for (source, line) in lines.iter().enumerate() {
+				let wrapped =
+					wrap_highlighted_lines(std::slice::from_ref(line), available_width, gutter);
+				self.preview
+					.wrapped_sources
+					.extend(std::iter::repeat_n(source, wrapped.len()));
+				self.preview.wrapped_lines.extend(wrapped);
+			}
+		}
 
 		let content_length = self.preview.wrapped_lines.len();
 		let max_scroll = self.preview.max_scroll(content_length);
@@ -411,6 +989,17 @@ mod tests {
 		assert!(files_ready, "expected initial search results to populate");
 	}
 
+	#[test]
+	fn initial_query_filters_the_first_results() {
+		let mut data = sample_data();
+		data.initial_query = "readme".into();
+		let mut app = App::new(data);
+		assert_eq!(app.search_input.text(), "readme");
+
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.results.buffers.filtered.first(), Some(&2));
+	}
+
 	#[test]
 	fn stable_ids_survive_reordering() {
 		let mut data = sample_data();
@@ -437,6 +1026,7 @@ mod tests {
 			indices: vec![1],
 			ids: Some(vec![original_id]),
 			scores: vec![42],
+			positions: None,
 		};
 		app.replace_matches_v2(batch);
 		assert_eq!(app.results.buffers.filtered, vec![0]);
@@ -463,4 +1053,300 @@ mod tests {
 			"dragging to the bottom should reach max scroll based on wrapped lines"
 		);
 	}
+
+	#[test]
+	fn preview_follows_the_query_until_scrolled() {
+		let mut app = App::new(sample_data());
+		let lines = ["intro", "a line long enough to wrap", "fn main() {}", "end"]
+			.map(Line::from)
+			.to_vec();
+		app.preview.enabled = true;
+		app.preview.viewport_height = 2;
+		app.preview.content = PreviewContent::text("main.rs", lines);
+		app.rebuild_preview_wrap(10);
+
+		app.search_input.set_text("MAIN");
+		assert_eq!(app.follow_query_in_preview(), None);
+		assert_eq!(app.preview.scroll, 0);
+
+		app.search_input.set_text("'main");
+		let (_, line) = app.follow_query_in_preview().expect("query found");
+		assert_eq!(line, 2);
+		assert_eq!(app.preview.wrapped_sources[app.preview.scroll], 2);
+
+		app.scroll_preview_up(1);
+		let scrolled = app.preview.scroll;
+		app.search_input.set_text("intro");
+		assert!(app.follow_query_in_preview().is_some());
+		assert_eq!(app.preview.scroll, scrolled);
+	}
+
+	#[test]
+	fn preview_position_maps_wrapped_scroll_to_source_line() {
+		let mut app = App::new(sample_data());
+		let selection = SearchSelection::File(FileRow::new("src/main.rs"));
+		let path = app.data.resolve_file_path(&FileRow::new("src/main.rs"));
+		let lines = vec![
+			Line::from("a long first line that wraps"),
+			Line::from("short"),
+			Line::from("third"),
+		];
+		app.preview.enabled = true;
+		app.preview.viewport_height = 1;
+		app.preview.path = path.display().to_string();
+		app.preview.content = PreviewContent::text(app.preview.path.clone(), lines);
+		app.rebuild_preview_wrap(10);
+		assert!(app.preview.wrapped_lines.len() > 3);
+
+		app.preview.scroll = app.preview.wrapped_lines.len() - 2;
+		assert_eq!(
+			app.preview_position(&selection),
+			Some(PreviewPosition::line(2))
+		);
+
+		let other = SearchSelection::File(FileRow::new("README.md"));
+		assert_eq!(app.preview_position(&other), None);
+
+		app.preview.enabled = false;
+		assert_eq!(app.preview_position(&selection), None);
+	}
+
+	#[test]
+	fn short_queries_list_every_row() {
+		let mut app = App::new(sample_data());
+		app.set_min_query_length(3);
+		app.search_input.set_text(" md ");
+		assert_eq!(app.matching_query(), "");
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.results.buffers.filtered, vec![2, 1, 0]);
+
+		app.search_input.set_text("read");
+		assert_eq!(app.matching_query(), "read");
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.results.buffers.filtered.first(), Some(&2));
+	}
+
+	#[test]
+	fn themes_are_downgraded_without_truecolor() {
+		use ratatui::style::{Color, Style};
+
+		let mut app = App::new(sample_data());
+		let mut theme = app.style.theme;
+		theme.highlight = Style::new().fg(Color::Rgb(255, 0, 0)).bg(Color::Blue);
+		app.set_theme(theme);
+		assert_eq!(app.style.theme.highlight.fg, Some(Color::Rgb(255, 0, 0)));
+
+		app.set_color_depth(ColorDepth::Ansi256);
+		assert_eq!(app.style.theme.highlight.fg, Some(Color::Indexed(196)));
+		assert_eq!(app.style.theme.highlight.bg, Some(Color::Blue));
+
+		theme.highlight = Style::new().fg(Color::Rgb(128, 128, 128));
+		app.set_theme(theme);
+		assert_eq!(app.style.theme.highlight.fg, Some(Color::Indexed(244)));
+	}
+
+	#[test]
+	fn capped_results_are_labelled() {
+		let mut data = SearchData::new();
+		data.files = (0..1_200)
+			.map(|i| FileRow::new(format!("src/file_{i}.rs")))
+			.collect();
+		let mut app = App::new(data);
+		app.set_search_options(SearchOptions::default().with_max_results(1_000));
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.filtered_len(), 1_000);
+		assert_eq!(
+			app.capped_results_label().as_deref(),
+			Some("1,000+ of 1,200 files")
+		);
+
+		app.search_input.set_text("'file_11");
+		prime_and_wait_for_results(&mut app);
+		assert!(app.filtered_len() < 1_000);
+		assert_eq!(app.capped_results_label(), None);
+	}
+
+	#[test]
+	fn marking_every_match_covers_rows_beyond_the_viewport() {
+		use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+		let mut data = SearchData::new();
+		data.files = (0..1_200)
+			.map(|i| FileRow::new(format!("src/file_{i}.rs")))
+			.collect();
+		let mut app = App::new(data);
+		app.set_search_options(SearchOptions::default().with_max_results(1_000));
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.selection_label(), None);
+
+		let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+		app.handle_key(ctrl('a')).unwrap();
+		assert_eq!(app.results.marked.len(), 1_000);
+		assert_eq!(
+			app.selection_label().as_deref(),
+			Some("1,000 selected / 1,000+ matched / 1,200 files")
+		);
+
+		app.search_input.set_text("'file_11");
+		prime_and_wait_for_results(&mut app);
+		let matched = app.filtered_len();
+		app.handle_key(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::ALT))
+			.unwrap();
+		let selected = app.results.marked.len();
+		assert!(selected < 1_000, "marked matches were unmarked");
+		assert_eq!(
+			app.selection_label(),
+			Some(format!(
+				"{selected} selected / {matched} matched / 1,200 files"
+			))
+		);
+
+		app.handle_key(ctrl('d')).unwrap();
+		assert!(app.results.marked.is_empty());
+		assert_eq!(app.selection_label(), None);
+	}
+
+	#[cfg(feature = "theme-watch")]
+	#[test]
+	fn watched_theme_changes_apply_in_place() {
+		use ratatui::style::Color;
+
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("live.toml");
+		let source = |fg: &str| {
+			format!(
+				"name = \"app-live-test\"\n\
+				 [styles.header]\n[styles.row_highlight]\n[styles.prompt]\n[styles.empty]\n\
+				 [styles.highlight]\nfg = \"{fg}\"\n"
+			)
+		};
+		std::fs::write(&path, source("#010101")).expect("write");
+
+		let mut app = App::new(sample_data());
+		app.set_theme_watcher(ThemeWatcher::with_intervals(
+			&path,
+			Duration::from_millis(5),
+			Duration::from_millis(20),
+		));
+		std::thread::sleep(Duration::from_millis(20));
+		let wait_until = |app: &mut App, done: &dyn Fn(&App) -> bool| {
+			assert!(app.pump_until(App::pump_theme_reloads, done));
+		};
+
+		std::fs::write(&path, source("#020202")).expect("write");
+		wait_until(&mut app, &|app| {
+			app.style.theme.highlight.fg == Some(Color::Rgb(2, 2, 2))
+		});
+
+		std::fs::write(&path, "broken").expect("write");
+		wait_until(&mut app, &|app| app.current_status().is_some());
+		assert_eq!(app.style.theme.highlight.fg, Some(Color::Rgb(2, 2, 2)));
+	}
+
+	#[test]
+	fn scrolling_past_the_cap_loads_later_matches() {
+		use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+		let mut data = SearchData::new();
+		data.files = (0..250)
+			.map(|i| FileRow::new(format!("src/file_{i}.rs")))
+			.collect();
+		let mut app = App::new(data);
+		app.set_search_options(SearchOptions::default().with_max_results(100));
+		app.search_input.set_text("'file");
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.filtered_len(), 100);
+		let first_page = app.results.buffers.filtered.clone();
+
+		let down = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+		for _ in 0..99 {
+			app.handle_key(down).unwrap();
+		}
+		app.wait_for_search_results();
+		assert_eq!(app.filtered_len(), 200);
+		assert_eq!(app.results.table_state.selected(), Some(99));
+		assert_eq!(app.results.buffers.filtered[..100], first_page[..]);
+		assert_eq!(
+			app.capped_results_label().as_deref(),
+			Some("200+ of 250 files")
+		);
+
+		app.handle_key(down).unwrap();
+		let Some(SearchSelection::File(file)) = app.current_selection() else {
+			panic!("a row is selected");
+		};
+		assert!(
+			!first_page
+				.iter()
+				.any(|&index| app.data.files[index].path == file.path)
+		);
+
+		// A new query starts over from the first page.
+		app.handle_key(KeyEvent::new(KeyCode::Char('_'), KeyModifiers::NONE))
+			.unwrap();
+		app.wait_for_search_results();
+		assert_eq!(app.filtered_len(), 100);
+	}
+
+	#[test]
+	fn clicking_with_the_last_match_in_view_loads_later_matches() {
+		use ratatui::crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+		use ratatui::layout::Rect;
+
+		let mut data = SearchData::new();
+		data.files = (0..250)
+			.map(|i| FileRow::new(format!("src/file_{i}.rs")))
+			.collect();
+		let mut app = App::new(data);
+		app.set_search_options(SearchOptions::default().with_max_results(100));
+		app.search_input.set_text("'file");
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(app.filtered_len(), 100);
+
+		// Eight rows fit below the borders and header; the last of them is
+		// the last loaded match.
+		app.results.area = Some(Rect::new(0, 0, 40, 12));
+		*app.results.table_state.offset_mut() = 92;
+		app.handle_mouse(MouseEvent {
+			kind: MouseEventKind::Down(MouseButton::Left),
+			column: 5,
+			row: 3,
+			modifiers: KeyModifiers::NONE,
+		});
+		app.wait_for_search_results();
+		assert_eq!(app.filtered_len(), 200);
+		assert_eq!(app.results.table_state.selected(), Some(92));
+	}
+
+	#[test]
+	fn restored_selections_wait_for_their_row_and_yield_to_the_user() {
+		use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+		let files = || {
+			(0..20)
+				.map(|i| FileRow::new(format!("src/file_{i}.rs")))
+				.collect::<Vec<_>>()
+		};
+		let selected_path = |app: &App| match app.current_selection() {
+			Some(SearchSelection::File(file)) => file.path,
+			None => panic!("a row is selected"),
+		};
+
+		let mut data = SearchData::new();
+		data.files = files();
+		let mut app = App::new(data);
+		app.restore_selection("src/file_7.rs");
+		prime_and_wait_for_results(&mut app);
+		assert_eq!(selected_path(&app), "src/file_7.rs");
+		assert!(app.restore_selection.is_none(), "restored only once");
+
+		let mut data = SearchData::new();
+		data.files = files();
+		let mut app = App::new(data);
+		app.restore_selection("src/file_7.rs");
+		app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+			.unwrap();
+		prime_and_wait_for_results(&mut app);
+		assert_ne!(selected_path(&app), "src/file_7.rs");
+	}
 }
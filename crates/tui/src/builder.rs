@@ -1,13 +1,28 @@
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::mpsc::Receiver;
+use std::time::Duration;
 
-use anyhow::Result;
-use frz_core::filesystem::indexer::{FilesystemOptions, IndexResult, spawn_filesystem_index};
-use frz_core::filesystem::search::{SearchData, SearchOutcome};
+use frz_core::error::{Error, Result};
+use frz_core::filesystem::indexer::{
+	FilesystemOptions, IndexResult, spawn_filesystem_index, spawn_line_index,
+};
+use frz_core::filesystem::search::{
+	CaseMatching, MatchMode, ScoreCutoff, SearchData, SearchOptions, SearchOutcome,
+	SearchSelection, SortKey, Tiebreak,
+};
+use frz_core::frecency::FrecencyStore;
+use frz_core::history::HistoryStore;
+use frz_core::last_selection::LastSelectionStore;
+use ratatui::crossterm::event::KeyEvent;
 use ratatui::layout::Constraint;
 
 use super::App;
-use super::config::UiLabels;
-use super::style::Theme;
+use super::app::preview::DEFAULT_SPLIT_RATIO;
+use super::components::{PreviewProvider, SharedPreviewProvider};
+use super::config::{DEFAULT_MAX_IMAGE_DIMENSION, PdfPreviewMode, PreviewLayout, UiLabels};
+use super::keys::KeyBindings;
+use super::style::{ColorDepth, Theme};
 
 /// A builder for configuring an interactive fuzzy picker.
 pub struct Picker {
@@ -19,11 +34,38 @@ pub struct Picker {
 	bat_theme: Option<String>,
 	index_updates: Option<Receiver<IndexResult>>,
 	preview_enabled: bool,
+	render_markdown: bool,
+	blame: bool,
+	line_numbers: bool,
+	pdf_preview: PdfPreviewMode,
+	max_image_dimension: u32,
+	preview_providers: Vec<SharedPreviewProvider>,
+	preview_layout: PreviewLayout,
+	preview_split: f32,
+	search_options: SearchOptions,
+	sort_key: SortKey,
+	show_sizes: bool,
+	show_tags: bool,
+	history: Option<HistoryStore>,
+	frecency: Option<FrecencyStore>,
+	last_selection: Option<LastSelectionStore>,
+	debounce: Duration,
+	key_bindings: KeyBindings,
+	expect_keys: Vec<KeyEvent>,
+	min_query_length: usize,
+	color_depth: ColorDepth,
+	editor: Option<String>,
+	absolute_copy: bool,
+	quick_select_labels: bool,
+	match_footer: bool,
+	#[cfg(feature = "theme-watch")]
+	theme_file: Option<std::path::PathBuf>,
 }
 
 impl Picker {
 	/// Create a new search UI for the provided data.
 	pub fn new(data: SearchData) -> Self {
+		let show_tags = data.files.iter().any(|file| !file.tags.is_empty());
 		Self {
 			data,
 			headers: None,
@@ -33,26 +75,80 @@ impl Picker {
 			bat_theme: None,
 			index_updates: None,
 			preview_enabled: false,
+			render_markdown: true,
+			blame: false,
+			line_numbers: true,
+			pdf_preview: PdfPreviewMode::default(),
+			max_image_dimension: DEFAULT_MAX_IMAGE_DIMENSION,
+			preview_providers: Vec::new(),
+			preview_layout: PreviewLayout::default(),
+			preview_split: DEFAULT_SPLIT_RATIO,
+			search_options: SearchOptions::default(),
+			sort_key: SortKey::default(),
+			show_sizes: false,
+			show_tags,
+			history: None,
+			frecency: None,
+			last_selection: None,
+			debounce: Duration::ZERO,
+			key_bindings: KeyBindings::default(),
+			expect_keys: Vec::new(),
+			min_query_length: 0,
+			color_depth: ColorDepth::default(),
+			editor: None,
+			absolute_copy: false,
+			quick_select_labels: false,
+			match_footer: false,
+			#[cfg(feature = "theme-watch")]
+			theme_file: None,
 		}
 	}
 
 	/// Create a search UI pre-populated with files from the filesystem rooted at `path`.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Io`] if `path` is not an accessible directory.
 	pub fn filesystem(path: impl AsRef<std::path::Path>) -> Result<Self> {
 		Self::filesystem_with_options(path.as_ref().to_path_buf(), FilesystemOptions::default())
 	}
 
 	/// Create a search UI with custom filesystem scanning options.
+	///
+	/// Queries are scored on as many threads as the scan uses, see
+	/// [`FilesystemOptions::threads`].
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Io`] if `path` is not an accessible directory and
+	/// [`Error::Config`] if the options are invalid.
 	pub fn filesystem_with_options(
 		path: impl Into<std::path::PathBuf>,
 		options: FilesystemOptions,
 	) -> Result<Self> {
 		let root = path.into();
+		let show_sizes = options.collect_sizes;
+		let threads = options.thread_count();
 		let (data, updates) = spawn_filesystem_index(root, options)?;
 		let mut ui = Self::new(data);
 		ui.index_updates = Some(updates);
+		ui.show_sizes = show_sizes;
+		ui.search_options = ui.search_options.with_threads(threads);
 		Ok(ui)
 	}
 
+	/// Create a search UI over the lines read from `reader`, e.g. standard
+	/// input.
+	///
+	/// Each non-empty line becomes a row and the rows are streamed in while
+	/// the UI is running; the filesystem is never scanned.
+	pub fn lines(reader: impl std::io::Read + Send + 'static) -> Self {
+		let (data, updates) = spawn_line_index(reader, "stdin");
+		let mut ui = Self::new(data);
+		ui.index_updates = Some(updates);
+		ui
+	}
+
 	/// Set column headers for the results table.
 	pub fn with_headers(mut self, headers: Vec<&str>) -> Self {
 		self.headers = Some(headers.into_iter().map(|s| s.to_string()).collect());
@@ -78,6 +174,9 @@ impl Picker {
 	}
 
 	/// Select a theme by name.
+	///
+	/// Unknown names are ignored; use [`Picker::try_with_theme_name`] to
+	/// reject them instead.
 	pub fn with_theme_name(mut self, name: &str) -> Self {
 		if let Some(theme) = super::style::by_name(name) {
 			self.theme = Some(theme);
@@ -86,6 +185,47 @@ impl Picker {
 		self
 	}
 
+	/// Select a theme by name, rejecting unknown names.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Config`] listing the available themes when no theme
+	/// is called `name`.
+	pub fn try_with_theme_name(self, name: &str) -> Result<Self> {
+		if super::style::by_name(name).is_none() {
+			let themes = super::style::names().join("\n  ");
+			return Err(Error::config(format!(
+				"Theme '{name}' not found.\n\nAvailable themes:\n  {themes}"
+			)));
+		}
+		Ok(self.with_theme_name(name))
+	}
+
+	/// Load a theme from a TOML or JSON file in the built-in theme format,
+	/// register it under the file stem and select it.
+	///
+	/// With the `theme-watch` feature the file is watched while the UI runs
+	/// and the theme is reloaded in place whenever it changes. Edits that do
+	/// not parse keep the last good theme and show a short status message.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Io`] if the file cannot be read and [`Error::Config`]
+	/// if it is not a valid theme definition.
+	pub fn try_with_theme_file(self, path: impl AsRef<std::path::Path>) -> Result<Self> {
+		let path = path.as_ref();
+		let registration = super::style::load_theme_file(path)?;
+		let name = registration.name.clone();
+		let _ = super::style::register_additional([registration]);
+		#[cfg_attr(not(feature = "theme-watch"), allow(unused_mut))]
+		let mut ui = self.with_theme_name(&name);
+		#[cfg(feature = "theme-watch")]
+		{
+			ui.theme_file = Some(path.to_path_buf());
+		}
+		Ok(ui)
+	}
+
 	/// Set a custom theme.
 	pub fn with_theme(mut self, theme: Theme) -> Self {
 		self.theme = Some(theme);
@@ -99,9 +239,295 @@ impl Picker {
 		self
 	}
 
+	/// Choose whether markdown files are previewed as formatted text, the
+	/// default, or highlighted as source like other files.
+	/// [`Action::ToggleMarkdown`](crate::keys::Action::ToggleMarkdown)
+	/// switches between them at runtime.
+	pub fn with_markdown_preview(mut self, rendered: bool) -> Self {
+		self.render_markdown = rendered;
+		self
+	}
+
+	/// Show the commit and author that last changed each line beside
+	/// highlighted previews, from `git blame`.
+	///
+	/// Files outside a git repository, or systems without git, are previewed
+	/// as usual with a note saying why no blame is shown.
+	pub fn with_blame_preview(mut self, enabled: bool) -> Self {
+		self.blame = enabled;
+		self
+	}
+
+	/// Number the lines of highlighted previews. Defaults to `true`;
+	/// [`Action::ToggleLineNumbers`](crate::keys::Action::ToggleLineNumbers)
+	/// switches them at runtime.
+	pub fn with_line_numbers(mut self, enabled: bool) -> Self {
+		self.line_numbers = enabled;
+		self
+	}
+
+	/// Choose whether PDFs are previewed as a rendered first page or as the
+	/// text of every page. The default renders the page when the terminal
+	/// can show images and falls back to the text otherwise. Only takes
+	/// effect with the `media-preview` feature.
+	pub fn with_pdf_preview(mut self, mode: PdfPreviewMode) -> Self {
+		self.pdf_preview = mode;
+		self
+	}
+
+	/// Shrink previewed images larger than `max` pixels on either side to
+	/// that size, keeping their aspect ratio, to bound the memory a preview
+	/// holds. Defaults to [`DEFAULT_MAX_IMAGE_DIMENSION`]. Only takes effect
+	/// with the `media-preview` feature.
+	pub fn with_max_image_dimension(mut self, max: u32) -> Self {
+		self.max_image_dimension = max.max(1);
+		self
+	}
+
+	/// Preview selections with `provider` before the built-in file previews.
+	///
+	/// Providers are asked in the order they were added and the first to
+	/// return content is shown, so data that only exists in memory can be
+	/// previewed without touching the filesystem.
+	pub fn with_preview_provider(
+		mut self,
+		provider: impl PreviewProvider + Send + Sync + 'static,
+	) -> Self {
+		self.preview_providers.push(Arc::new(provider));
+		self
+	}
+
+	/// Place the preview beside the results, the default, or below them.
+	pub fn with_preview_layout(mut self, layout: PreviewLayout) -> Self {
+		self.preview_layout = layout;
+		self
+	}
+
+	/// Give the results `ratio` of the space shared with the preview.
+	///
+	/// Defaults to an even split. The ratio is clamped to between 0 and 1.
+	pub fn with_preview_split(mut self, ratio: f32) -> Self {
+		self.preview_split = ratio;
+		self
+	}
+
+	/// Choose how results with equal scores are ordered.
+	///
+	/// [`Tiebreak::None`] keeps the order rows were indexed in, while
+	/// [`Tiebreak::Natural`] orders them by path with numbers compared by
+	/// value, which stays stable as more rows are indexed mid-query.
+	pub fn with_tiebreak(mut self, tiebreak: Tiebreak) -> Self {
+		self.search_options.tiebreak = tiebreak;
+		self
+	}
+
+	/// Choose how letter case is compared while matching.
+	///
+	/// Defaults to [`CaseMatching::Smart`].
+	pub fn with_case_matching(mut self, case_matching: CaseMatching) -> Self {
+		self.search_options.case_matching = case_matching;
+		self
+	}
+
+	/// Choose whether plain query terms are matched fuzzily or as
+	/// substrings. Ctrl+F switches between the two while the picker runs.
+	///
+	/// Defaults to [`MatchMode::Fuzzy`].
+	pub fn with_match_mode(mut self, match_mode: MatchMode) -> Self {
+		self.search_options.match_mode = match_mode;
+		self
+	}
+
+	/// Match accented letters by their base letter, so `resume` finds
+	/// `résumé`.
+	///
+	/// Off by default, since keys with diacritics are copied for every
+	/// query.
+	pub fn with_fold_diacritics(mut self, fold: bool) -> Self {
+		self.search_options.fold_diacritics = fold;
+		self
+	}
+
+	/// Choose how filtered results are ordered.
+	pub fn with_sort_key(mut self, sort_key: SortKey) -> Self {
+		self.sort_key = sort_key;
+		self
+	}
+
+	/// Show a column with human-readable file sizes.
+	///
+	/// Enabled automatically for filesystem pickers that collect sizes.
+	pub fn with_size_column(mut self) -> Self {
+		self.show_sizes = true;
+		self
+	}
+
+	/// Show a column with each row's
+	/// [tags](frz_core::filesystem::search::FileRow::tags), marking the
+	/// tags a `tag:` filter in the query asks for.
+	///
+	/// Enabled automatically when any of the initial rows has tags.
+	pub fn with_tag_column(mut self) -> Self {
+		self.show_tags = true;
+		self
+	}
+
+	/// Persist accepted queries in `store` and offer them as history.
+	///
+	/// The stored queries are loaded when the UI starts and the accepted
+	/// query is appended when it exits. The prompt remembers as many queries
+	/// as the store keeps on disk. Failures to read or write the store
+	/// are ignored so that history never prevents a search.
+	pub fn with_history(mut self, store: HistoryStore) -> Self {
+		self.history = Some(store);
+		self
+	}
+
+	/// Rank files accepted in earlier sessions ahead of otherwise equal
+	/// matches, and record the files accepted in this one in `store`.
+	///
+	/// Visits of files that no longer exist are pruned when the UI starts.
+	/// Pickers built without a store rank purely by match quality. Failures
+	/// to read or write the store are ignored.
+	pub fn with_frecency(mut self, store: FrecencyStore) -> Self {
+		self.frecency = Some(store);
+		self
+	}
+
+	/// Start with the file accepted last under the same root highlighted, and
+	/// record the file accepted in this session in `store`.
+	///
+	/// The file is highlighted once it shows up among the matches, unless
+	/// the user moved the selection or typed by then. Pickers without a root
+	/// neither restore nor record anything, and failures to read or write
+	/// the store are ignored.
+	pub fn with_last_selection(mut self, store: LastSelectionStore) -> Self {
+		self.last_selection = Some(store);
+		self
+	}
+
+	/// Delay queries until typing has been idle for `debounce`.
+	///
+	/// Useful for large datasets where every keystroke would otherwise start
+	/// a search. Defaults to zero, which searches on every change.
+	pub fn with_debounce(mut self, debounce: Duration) -> Self {
+		self.debounce = debounce;
+		self
+	}
+
+	/// Load `max_results` matches per query at a time.
+	///
+	/// Defaults to [`MAX_RENDERED_RESULTS`]. When results are cut off, the
+	/// prompt shows a label such as `2,000+ of 134,551 files`, and selecting
+	/// the last loaded match searches again for another page of them.
+	///
+	/// [`MAX_RENDERED_RESULTS`]: frz_core::filesystem::search::MAX_RENDERED_RESULTS
+	pub fn with_max_results(mut self, max_results: usize) -> Self {
+		self.search_options = self.search_options.with_max_results(max_results);
+		self
+	}
+
+	/// Hide matches scoring below `cutoff`, either an absolute score or a
+	/// percentage of the best match's score.
+	///
+	/// Only scored queries are affected; the rows listed for an empty query
+	/// are always shown.
+	pub fn with_score_cutoff(mut self, cutoff: ScoreCutoff) -> Self {
+		self.search_options = self.search_options.with_score_cutoff(cutoff);
+		self
+	}
+
+	/// Only start matching once the query has at least `length` characters.
+	///
+	/// Shorter queries show the same listing as an empty query. Defaults to
+	/// zero, which matches from the first character.
+	pub fn with_min_query_length(mut self, length: usize) -> Self {
+		self.min_query_length = length;
+		self
+	}
+
+	/// Render colors at `depth`.
+	///
+	/// With [`ColorDepth::Ansi256`], RGB theme colors are mapped to the
+	/// nearest entry of the 256-color palette. Defaults to truecolor; use
+	/// [`ColorDepth::detect`] to follow the terminal.
+	pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+		self.color_depth = depth;
+		self
+	}
+
+	/// Open files with `command` on
+	/// [`Action::OpenInEditor`](crate::keys::Action::OpenInEditor) instead of
+	/// `$VISUAL` or `$EDITOR`.
+	///
+	/// The command is split into words like a shell would and the file's
+	/// path is appended, e.g. `code --wait`.
+	pub fn with_editor(mut self, command: impl Into<String>) -> Self {
+		self.editor = Some(command.into());
+		self
+	}
+
+	/// Swap the keys bound to [`Action::CopyPath`](crate::keys::Action::CopyPath)
+	/// and [`Action::CopyAbsolutePath`](crate::keys::Action::CopyAbsolutePath)
+	/// when `absolute`, so Ctrl+Y copies paths resolved under the search root
+	/// and Alt+Y copies them as shown in the table. Defaults to `false`.
+	pub fn with_absolute_copy(mut self, absolute: bool) -> Self {
+		self.absolute_copy = absolute;
+		self
+	}
+
+	/// Number the first nine rows shown in the results table, which Alt+1
+	/// through Alt+9 accept.
+	///
+	/// Terminals do not report Alt being held on its own, so the numbers are
+	/// either always shown or never. Defaults to `false`.
+	pub fn with_quick_select_labels(mut self, show: bool) -> Self {
+		self.quick_select_labels = show;
+		self
+	}
+
+	/// Show a footer under the results such as `123/4,567 matches • 12 ms`:
+	/// the matches out of every row and how long the last query took to
+	/// score.
+	///
+	/// The footer is left out on terminals too short to spare the row.
+	/// Defaults to `false`.
+	pub fn with_match_footer(mut self, show: bool) -> Self {
+		self.match_footer = show;
+		self
+	}
+
+	/// Replace the keys bound to the picker's actions.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Config`] when a key is bound more than once.
+	pub fn try_with_key_bindings(mut self, key_bindings: KeyBindings) -> Result<Self> {
+		key_bindings.validate()?;
+		self.key_bindings = key_bindings;
+		Ok(self)
+	}
+
+	/// Accept the search with any of `keys` as well as the accept key, and
+	/// report which one was pressed in
+	/// [`SearchOutcome::accepted_with`], like fzf's `--expect`.
+	///
+	/// Expected keys take precedence over the keys bound to actions; see
+	/// [`parse_key`](crate::parse_key) for reading them from fzf's key names.
+	pub fn with_expect_keys(mut self, keys: impl IntoIterator<Item = KeyEvent>) -> Self {
+		self.expect_keys = keys.into_iter().collect();
+		self
+	}
+
 	/// Run the interactive search UI with the configured options.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Terminal`] if the terminal cannot be driven.
 	pub fn run(mut self) -> Result<SearchOutcome> {
 		// Build an App and apply optional customizations, then run it.
+		pin_root(&mut self.data.root);
+		let root = self.data.root.clone();
 		let mut app = App::new(self.data);
 		if let Some(headers) = self.headers {
 			app.set_headers(headers);
@@ -113,16 +539,171 @@ impl Picker {
 			app.ui = ui;
 			app.ensure_tab_buffers();
 		}
+		app.set_color_depth(self.color_depth);
 		if let Some(theme) = self.theme {
 			app.set_theme_with_bat(theme, self.bat_theme.clone());
 		}
 		if let Some(updates) = self.index_updates.take() {
 			app.set_index_updates(updates);
 		}
+		app.set_markdown_preview(self.render_markdown);
+		app.set_blame_preview(self.blame);
+		app.set_line_numbers(self.line_numbers);
+		app.set_pdf_preview(self.pdf_preview);
+		app.set_max_image_dimension(self.max_image_dimension);
+		for provider in self.preview_providers {
+			app.add_preview_provider(provider);
+		}
+		app.set_preview_layout(self.preview_layout);
+		app.set_preview_split(self.preview_split);
 		if self.preview_enabled {
 			app.enable_preview();
 		}
+		app.set_search_options(self.search_options);
+		app.set_sort_key(self.sort_key);
+		app.show_size_column(self.show_sizes);
+		app.show_tag_column(self.show_tags);
+		app.set_search_debounce(self.debounce);
+		app.key_bindings = self.key_bindings;
+		app.expect_keys = self.expect_keys;
+		app.set_min_query_length(self.min_query_length);
+		if let Some(editor) = self.editor {
+			app.set_editor(editor);
+		}
+		app.set_absolute_copy(self.absolute_copy);
+		app.show_quick_select_labels(self.quick_select_labels);
+		app.show_match_footer(self.match_footer);
+		#[cfg(feature = "theme-watch")]
+		if let Some(path) = self.theme_file.take() {
+			app.set_theme_watcher(super::style::ThemeWatcher::spawn(path));
+		}
+		if let Some(store) = self.history.as_ref() {
+			app.search_input.set_history_limit(store.limit());
+			if let Ok(entries) = store.load() {
+				app.search_input.load_history(entries);
+			}
+		}
+		if let Some(store) = self.frecency.as_ref() {
+			let _ = store.prune_missing();
+			if let Ok(frecency) = store.load(root.as_deref()) {
+				app.set_frecency(frecency);
+			}
+		}
+		if let (Some(store), Some(root)) = (self.last_selection.as_ref(), root.as_deref())
+			&& let Ok(Some(path)) = store.load(root)
+		{
+			app.restore_selection(path);
+		}
+
+		let outcome = app.run()?;
+		if outcome.accepted
+			&& let Some(store) = self.history.as_ref()
+		{
+			let _ = store.append(&outcome.query);
+		}
+		if outcome.accepted
+			&& let Some(store) = self.frecency.as_ref()
+		{
+			for file in outcome.selected_files() {
+				let path = file.os_path();
+				let _ = match &root {
+					Some(root) => store.record(root.join(path)),
+					None => store.record(path),
+				};
+			}
+		}
+		if outcome.accepted
+			&& let (Some(store), Some(root)) = (self.last_selection.as_ref(), root.as_deref())
+			&& let Some(SearchSelection::File(file)) = outcome.selection.as_ref()
+		{
+			let _ = store.record(root, &file.path);
+		}
+		Ok(outcome)
+	}
+}
+
+/// Make a relative `root` absolute, once, so paths copied, opened and
+/// recorded later do not depend on the working directory of whoever uses
+/// them.
+///
+/// Roots that cannot be canonicalized, such as missing directories, are
+/// left as they are.
+fn pin_root(root: &mut Option<PathBuf>) {
+	if let Some(root) = root.as_mut().filter(|root| root.is_relative())
+		&& let Ok(absolute) = root.canonicalize()
+	{
+		*root = absolute;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unknown_theme_names_are_config_errors() {
+		let Err(err) = Picker::new(SearchData::new()).try_with_theme_name("no-such-theme") else {
+			panic!("expected an unknown theme to be rejected");
+		};
+		assert!(matches!(err, Error::Config { .. }), "{err:?}");
+		assert!(err.to_string().contains("no-such-theme"));
+	}
+
+	#[test]
+	fn missing_roots_are_io_errors() {
+		let Err(err) = Picker::filesystem("/nonexistent/frz/root") else {
+			panic!("expected a missing root to be rejected");
+		};
+		assert!(matches!(err, Error::Io { .. }), "{err:?}");
+	}
+
+	#[test]
+	fn conflicting_key_bindings_are_config_errors() {
+		use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+		use crate::Action;
+
+		let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+		let bindings = KeyBindings::default().bind(Action::Cancel, [enter]);
+		let Err(err) = Picker::new(SearchData::new()).try_with_key_bindings(bindings) else {
+			panic!("expected Enter bound twice to be rejected");
+		};
+		assert!(matches!(err, Error::Config { .. }), "{err:?}");
+	}
+
+	#[test]
+	fn theme_files_are_registered_and_selected() {
+		use ratatui::style::Color;
+
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("picker-file-theme.toml");
+		std::fs::write(
+			&path,
+			"[styles.header]\n[styles.row_highlight]\n[styles.prompt]\n[styles.empty]\n\
+			 [styles.highlight]\nfg = \"#0a0b0c\"\n",
+		)
+		.expect("write theme");
+
+		let picker = Picker::new(SearchData::new())
+			.try_with_theme_file(&path)
+			.expect("valid theme file");
+		let highlight = Some(Color::Rgb(10, 11, 12));
+		assert_eq!(picker.theme.expect("selected").highlight.fg, highlight);
+		assert_eq!(
+			crate::style::by_name("picker-file-theme").map(|theme| theme.highlight.fg),
+			Some(highlight)
+		);
+	}
+
+	#[test]
+	fn relative_roots_are_pinned_to_the_working_directory() {
+		let mut root = Some(PathBuf::from("."));
+		pin_root(&mut root);
+		let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+		assert_eq!(root, Some(cwd));
 
-		app.run()
+		let mut missing = Some(PathBuf::from("no/such/frz/root"));
+		pin_root(&mut missing);
+		assert_eq!(missing, Some(PathBuf::from("no/such/frz/root")));
 	}
 }
@@ -16,12 +16,12 @@ pub use preview::selection::{
 	TextSelection, apply_selection_to_lines, copy_to_clipboard, extract_selected_text,
 	selection_style,
 };
-#[cfg(feature = "media-preview")]
-pub use preview::{ImagePreview, PdfPreview, is_image_available, is_pdf_file, protocol_name};
 pub use preview::{
-	PreviewContent, PreviewContext, PreviewKind, PreviewRuntime, render_preview,
-	wrap_highlighted_lines,
+	Gutter, PreviewContent, PreviewContext, PreviewKind, PreviewProvider, PreviewRuntime,
+	SharedPreviewProvider, render_preview, wrap_highlighted_lines,
 };
+#[cfg(feature = "media-preview")]
+pub use preview::{ImagePreview, PdfPreview, is_image_available, is_pdf_file, protocol_name};
 pub use progress::IndexProgress;
 pub use prompt::{InputContext, ProgressState, render_input};
 pub use scrollbar::{ScrollMetrics, point_in_rect, render_scrollbar};
@@ -0,0 +1,311 @@
+//! Listing of zip and tar archives.
+//!
+//! Only the entry headers are read, nothing is extracted. Each entry is shown
+//! with its size and, for zip archives, its compressed size in aligned columns
+//! that scroll like any text preview.
+//!
+//! Tarballs have no index, so reaching a header means reading, and for
+//! `.tar.gz` decompressing, every entry before it. Listing them stops after
+//! [`MAX_TAR_BYTES`] to keep huge tarballs from stalling the preview.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use zip::ZipArchive;
+
+use crate::components::rows::format_size;
+
+/// Width of the size columns, enough for `1023.9 KiB`.
+const SIZE_WIDTH: usize = 10;
+
+/// Bytes of a tarball, after decompression, read at most while listing it.
+const MAX_TAR_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Archive formats that can be listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveFormat {
+	/// Zip archive, including `.jar` files.
+	Zip,
+	/// Uncompressed tarball.
+	Tar,
+	/// Gzip-compressed tarball.
+	TarGz,
+}
+
+/// Detect an archive format from the file name of `path`.
+pub(crate) fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+	let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+	if name.ends_with(".zip") || name.ends_with(".jar") {
+		Some(ArchiveFormat::Zip)
+	} else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+		Some(ArchiveFormat::TarGz)
+	} else if name.ends_with(".tar") {
+		Some(ArchiveFormat::Tar)
+	} else {
+		None
+	}
+}
+
+/// A file or directory stored in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ArchiveEntry {
+	/// Path inside the archive; directories end with `/`.
+	name: String,
+	/// Uncompressed size in bytes.
+	size: u64,
+	/// Stored size in bytes, when the format compresses entries one by one.
+	compressed_size: Option<u64>,
+}
+
+impl ArchiveEntry {
+	fn is_dir(&self) -> bool {
+		self.name.ends_with('/')
+	}
+}
+
+/// Entries read from an archive.
+#[derive(Debug, Default)]
+struct Listing {
+	entries: Vec<ArchiveEntry>,
+	/// Whether reading stopped at the entry or byte limit.
+	truncated: bool,
+}
+
+/// List the archive at `path` as table lines, keeping at most `max_lines`
+/// entries.
+///
+/// Returns a description of the problem if the archive cannot be read.
+pub(crate) fn list_archive(
+	path: &Path,
+	format: ArchiveFormat,
+	max_lines: usize,
+) -> Result<Vec<Line<'static>>, String> {
+	let file = File::open(path).map_err(|e| format!("Cannot read: {e}"))?;
+	let reader = BufReader::new(file);
+	let listing = match format {
+		ArchiveFormat::Zip => list_zip(reader, max_lines),
+		ArchiveFormat::Tar => list_tar(reader, max_lines, MAX_TAR_BYTES),
+		ArchiveFormat::TarGz => list_tar(GzDecoder::new(reader), max_lines, MAX_TAR_BYTES),
+	}?;
+	Ok(render_listing(&listing))
+}
+
+fn list_zip<R: Read + Seek>(reader: R, limit: usize) -> Result<Listing, String> {
+	let mut archive = ZipArchive::new(reader).map_err(|e| format!("Archive error: {e}"))?;
+	let mut listing = Listing {
+		truncated: archive.len() > limit,
+		..Listing::default()
+	};
+	for index in 0..archive.len().min(limit) {
+		// Raw access reads the header without decompressing the entry.
+		let file = archive
+			.by_index_raw(index)
+			.map_err(|e| format!("Archive error: {e}"))?;
+		listing.entries.push(ArchiveEntry {
+			name: file.name().to_owned(),
+			size: file.size(),
+			compressed_size: Some(file.compressed_size()),
+		});
+	}
+	Ok(listing)
+}
+
+/// List the entries of a tarball, stopping after `limit` entries or once
+/// the next header lies past `max_bytes` into the stream.
+fn list_tar<R: Read>(reader: R, limit: usize, max_bytes: u64) -> Result<Listing, String> {
+	let mut archive = tar::Archive::new(reader);
+	let mut entries = archive
+		.entries()
+		.map_err(|e| format!("Archive error: {e}"))?;
+	let mut listing = Listing::default();
+	let mut end = 0;
+	loop {
+		if end > max_bytes {
+			listing.truncated = true;
+			break;
+		}
+		let Some(entry) = entries.next() else {
+			break;
+		};
+		if listing.entries.len() == limit {
+			listing.truncated = true;
+			break;
+		}
+		let entry = entry.map_err(|e| format!("Archive error: {e}"))?;
+		let mut name = entry
+			.path()
+			.map_err(|e| format!("Archive error: {e}"))?
+			.to_string_lossy()
+			.into_owned();
+		if entry.header().entry_type().is_dir() && !name.ends_with('/') {
+			name.push('/');
+		}
+		end = entry.raw_file_position() + entry.size();
+		listing.entries.push(ArchiveEntry {
+			name,
+			size: entry.size(),
+			compressed_size: None,
+		});
+	}
+	Ok(listing)
+}
+
+fn render_listing(listing: &Listing) -> Vec<Line<'static>> {
+	let muted = Style::new().add_modifier(Modifier::DIM);
+	let mut lines = Vec::with_capacity(listing.entries.len() + 3);
+	lines.push(Line::styled(
+		format!(
+			"{:>SIZE_WIDTH$}  {:>SIZE_WIDTH$}  Name",
+			"Size", "Compressed"
+		),
+		Style::new().add_modifier(Modifier::BOLD),
+	));
+
+	let mut total = 0;
+	for entry in &listing.entries {
+		let (size, compressed) = if entry.is_dir() {
+			("-".to_owned(), "-".to_owned())
+		} else {
+			total += entry.size;
+			let compressed = entry.compressed_size.map_or("-".to_owned(), format_size);
+			(format_size(entry.size), compressed)
+		};
+		lines.push(Line::from(format!(
+			"{size:>SIZE_WIDTH$}  {compressed:>SIZE_WIDTH$}  {}",
+			entry.name
+		)));
+	}
+
+	lines.push(Line::default());
+	let summary = if listing.truncated {
+		"... (truncated)".to_owned()
+	} else {
+		let count = listing.entries.len();
+		let noun = if count == 1 { "entry" } else { "entries" };
+		format!("{count} {noun}, {} uncompressed", format_size(total))
+	};
+	lines.push(Line::styled(summary, muted));
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Cursor, Write};
+
+	use zip::write::SimpleFileOptions;
+	use zip::{CompressionMethod, ZipWriter};
+
+	use super::*;
+
+	fn texts(lines: &[Line<'_>]) -> Vec<String> {
+		lines.iter().map(|line| line.to_string()).collect()
+	}
+
+	fn zip_bytes() -> Vec<u8> {
+		let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+		let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+		let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+		writer.add_directory("src/", stored).unwrap();
+		writer.start_file("src/main.rs", stored).unwrap();
+		writer.write_all(b"fn main() {}\n").unwrap();
+		writer.start_file("notes.txt", deflated).unwrap();
+		writer.write_all(&[b'a'; 4096]).unwrap();
+		writer.finish().unwrap().into_inner()
+	}
+
+	#[test]
+	fn zip_entries_are_listed_with_their_sizes() {
+		let listing = list_zip(Cursor::new(zip_bytes()), 10).unwrap();
+		let lines = texts(&render_listing(&listing));
+		let compressed = listing.entries[2].compressed_size.unwrap();
+		assert!(compressed < 4096, "{compressed}");
+
+		assert_eq!(
+			lines,
+			[
+				"      Size  Compressed  Name".to_owned(),
+				"         -           -  src/".to_owned(),
+				"      13 B        13 B  src/main.rs".to_owned(),
+				format!("   4.0 KiB  {:>10}  notes.txt", format_size(compressed)),
+				String::new(),
+				"3 entries, 4.0 KiB uncompressed".to_owned(),
+			]
+		);
+	}
+
+	#[test]
+	fn tar_entries_are_listed_up_to_the_limit() {
+		let mut builder = tar::Builder::new(Vec::new());
+		for (name, contents) in [("a.txt", &b"hello"[..]), ("b.txt", b""), ("c.txt", b"c")] {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(contents.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+			builder.append_data(&mut header, name, contents).unwrap();
+		}
+		let bytes = builder.into_inner().unwrap();
+
+		let listing = list_tar(Cursor::new(&bytes), 10, MAX_TAR_BYTES).unwrap();
+		assert_eq!(
+			texts(&render_listing(&listing))[1..],
+			[
+				"       5 B           -  a.txt",
+				"       0 B           -  b.txt",
+				"       1 B           -  c.txt",
+				"",
+				"3 entries, 6 B uncompressed",
+			]
+		);
+
+		let listing = list_tar(Cursor::new(&bytes), 2, MAX_TAR_BYTES).unwrap();
+		let lines = texts(&render_listing(&listing));
+		assert_eq!(lines.len(), 5);
+		assert_eq!(lines[4], "... (truncated)");
+
+		// Each entry takes a 512 byte header and its data padded to 512 bytes,
+		// so `b.txt` starts within the first kilobyte and `c.txt` past it.
+		let listing = list_tar(Cursor::new(&bytes), 10, 1024).unwrap();
+		let lines = texts(&render_listing(&listing));
+		assert_eq!(
+			lines[1..3],
+			[
+				"       5 B           -  a.txt",
+				"       0 B           -  b.txt"
+			]
+		);
+		assert_eq!(lines[4], "... (truncated)");
+	}
+
+	#[test]
+	fn corrupt_archives_are_reported() {
+		let err = list_zip(Cursor::new(b"not a zip".to_vec()), 10).unwrap_err();
+		assert!(err.starts_with("Archive error"), "{err}");
+
+		let mut bytes = zip_bytes();
+		bytes.truncate(bytes.len() / 2);
+		assert!(list_zip(Cursor::new(bytes), 10).is_err());
+	}
+
+	#[test]
+	fn formats_are_detected_from_the_file_name() {
+		assert_eq!(
+			archive_format(Path::new("a/b.ZIP")),
+			Some(ArchiveFormat::Zip)
+		);
+		assert_eq!(
+			archive_format(Path::new("release.tar.gz")),
+			Some(ArchiveFormat::TarGz)
+		);
+		assert_eq!(
+			archive_format(Path::new("x.tgz")),
+			Some(ArchiveFormat::TarGz)
+		);
+		assert_eq!(archive_format(Path::new("x.tar")), Some(ArchiveFormat::Tar));
+		assert_eq!(archive_format(Path::new("x.gz")), None);
+		assert_eq!(archive_format(Path::new("zip")), None);
+	}
+}
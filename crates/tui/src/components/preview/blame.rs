@@ -0,0 +1,288 @@
+//! `git blame` gutter for text previews.
+//!
+//! The previewed file is blamed with `git blame --line-porcelain` on the
+//! preview worker, and each line gets the abbreviated commit and author
+//! that last touched it in an aligned column in front of the text. Files
+//! outside a repository, or machines without git, keep the plain preview
+//! together with a short note explaining why no blame is shown.
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthStr;
+
+/// Number of commit hash characters shown in the gutter.
+const COMMIT_WIDTH: usize = 8;
+
+/// Widest author column; longer names are cut with an ellipsis.
+const MAX_AUTHOR_WIDTH: usize = 16;
+
+/// Hash git reports for lines that have not been committed yet.
+const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+/// Who last changed one line of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlameLine {
+	/// Full hash of the commit.
+	pub commit: String,
+	/// Author name of the commit.
+	pub author: String,
+	/// One-based line number in the current file.
+	pub line: usize,
+}
+
+impl BlameLine {
+	fn is_committed(&self) -> bool {
+		self.commit != UNCOMMITTED
+	}
+}
+
+/// Blame the first `lines` lines of the file at `path`, which must have at
+/// least that many.
+///
+/// Only the lines the preview shows are blamed, and git runs in the C
+/// locale so its errors can be recognised. Returns a short explanation
+/// instead when git is missing or the file is not tracked in a repository.
+pub(crate) fn blame_file(path: &Path, lines: usize) -> Result<Vec<BlameLine>, String> {
+	if lines == 0 {
+		return Ok(Vec::new());
+	}
+	let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+		return Err("No git blame for this path".to_owned());
+	};
+	let dir = if dir.as_os_str().is_empty() {
+		Path::new(".")
+	} else {
+		dir
+	};
+	let output = Command::new("git")
+		.arg("-C")
+		.arg(dir)
+		.args(["blame", "--line-porcelain", "-L"])
+		.arg(format!("1,{lines}"))
+		.arg("--")
+		.arg(name)
+		.env("LC_ALL", "C")
+		.output()
+		.map_err(|e| match e.kind() {
+			ErrorKind::NotFound => "git is not installed".to_owned(),
+			_ => format!("Cannot run git: {e}"),
+		})?;
+
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(if stderr.contains("not a git repository") {
+			"Not in a git repository".to_owned()
+		} else if stderr.contains("no such path") {
+			"Not tracked by git".to_owned()
+		} else {
+			let reason = stderr.lines().next().unwrap_or("unknown error");
+			format!("git blame failed: {}", reason.trim_start_matches("fatal: "))
+		});
+	}
+	Ok(parse_line_porcelain(&String::from_utf8_lossy(
+		&output.stdout,
+	)))
+}
+
+/// Parse the output of `git blame --line-porcelain`.
+///
+/// Every line of the file is described by a header naming the commit and
+/// line numbers, then `key value` lines about the commit, then the line's
+/// text prefixed with a tab.
+pub(crate) fn parse_line_porcelain(output: &str) -> Vec<BlameLine> {
+	let mut lines = Vec::new();
+	let mut current: Option<BlameLine> = None;
+	for row in output.lines() {
+		if row.starts_with('\t') {
+			lines.extend(current.take());
+			continue;
+		}
+		match &mut current {
+			Some(entry) => {
+				if let Some(author) = row.strip_prefix("author ") {
+					entry.author = author.to_owned();
+				}
+			}
+			None => {
+				let mut fields = row.split(' ');
+				let commit = fields.next().unwrap_or_default();
+				let line = fields.nth(1).and_then(|line| line.parse().ok());
+				if let Some(line) = line
+					&& !commit.is_empty()
+				{
+					current = Some(BlameLine {
+						commit: commit.to_owned(),
+						author: String::new(),
+						line,
+					});
+				}
+			}
+		}
+	}
+	lines
+}
+
+/// Gutter text for each of the first `line_count` lines of the file.
+///
+/// Columns are padded to the longest author so the text after the gutter
+/// stays aligned; lines missing from the blame get a blank gutter.
+pub(crate) fn gutter_rows(blame: &[BlameLine], line_count: usize) -> Vec<String> {
+	let author_width = blame
+		.iter()
+		.map(|entry| entry.author.chars().count())
+		.max()
+		.unwrap_or(0)
+		.min(MAX_AUTHOR_WIDTH);
+	let blank = format!("{:COMMIT_WIDTH$} {:author_width$} │ ", "", "");
+	let mut rows = vec![blank; line_count];
+	for entry in blame {
+		let Some(row) = entry
+			.line
+			.checked_sub(1)
+			.and_then(|index| rows.get_mut(index))
+		else {
+			continue;
+		};
+		let commit = if entry.is_committed() {
+			&entry.commit[..COMMIT_WIDTH.min(entry.commit.len())]
+		} else {
+			""
+		};
+		let author = truncate(&entry.author, author_width);
+		*row = format!("{commit:COMMIT_WIDTH$} {author:author_width$} │ ");
+	}
+	rows
+}
+
+/// Prefix each preview line with its blame gutter, returning the lines and
+/// the gutter's width in columns.
+///
+/// Lines past the end of the file, such as the truncation marker, get a
+/// blank gutter.
+pub(crate) fn with_blame_gutter(
+	lines: Vec<Line<'static>>,
+	blame: &[BlameLine],
+) -> (Vec<Line<'static>>, usize) {
+	let style = Style::new().add_modifier(Modifier::DIM);
+	let rows = gutter_rows(blame, lines.len());
+	let width = rows.first().map_or(0, |row| row.width());
+	let lines = lines
+		.into_iter()
+		.zip(rows)
+		.map(|(line, row)| {
+			let mut spans = Vec::with_capacity(line.spans.len() + 1);
+			spans.push(Span::styled(row, style));
+			spans.extend(line.spans);
+			Line::from(spans).style(line.style)
+		})
+		.collect();
+	(lines, width)
+}
+
+fn truncate(text: &str, width: usize) -> String {
+	if text.chars().count() <= width {
+		return text.to_owned();
+	}
+	let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+	truncated.push('…');
+	truncated
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FIXTURE: &str = include_str!("fixtures/blame.porcelain");
+
+	#[test]
+	fn porcelain_output_is_parsed_into_gutter_rows() {
+		let blame = parse_line_porcelain(FIXTURE);
+		assert_eq!(
+			blame
+				.iter()
+				.map(|entry| (&entry.commit[..7], entry.author.as_str(), entry.line))
+				.collect::<Vec<_>>(),
+			[
+				("3f1c2a9", "Ada Lovelace", 1),
+				("3f1c2a9", "Ada Lovelace", 2),
+				("9b8a7c6", "Charles Xavier Babbage-Smith", 3),
+				("0000000", "Not Committed Yet", 4),
+			]
+		);
+
+		assert_eq!(
+			gutter_rows(&blame, 5),
+			[
+				"3f1c2a9e Ada Lovelace     │ ",
+				"3f1c2a9e Ada Lovelace     │ ",
+				"9b8a7c6d Charles Xavier … │ ",
+				"         Not Committed Y… │ ",
+				"                          │ ",
+			]
+		);
+	}
+
+	#[test]
+	fn gutters_are_prefixed_to_preview_lines() {
+		let blame = parse_line_porcelain(FIXTURE);
+		let lines = vec![
+			Line::from(vec![Span::raw("   1 │ "), Span::raw("fn main() {")]),
+			Line::from("... (truncated)"),
+		];
+		let (lines, width) = with_blame_gutter(lines, &blame[..1]);
+		assert_eq!(width, "3f1c2a9e Ada Lovelace │ ".width());
+		assert_eq!(
+			lines[0].to_string(),
+			"3f1c2a9e Ada Lovelace │    1 │ fn main() {"
+		);
+		assert_eq!(
+			lines[1].to_string(),
+			"                      │ ... (truncated)"
+		);
+		assert_eq!(
+			lines[0].spans[0].style,
+			Style::new().add_modifier(Modifier::DIM)
+		);
+	}
+
+	#[test]
+	fn files_outside_repositories_explain_the_missing_blame() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("notes.txt");
+		std::fs::write(&path, "hello\n").unwrap();
+		let err = blame_file(&path, 1).unwrap_err();
+		assert!(
+			err == "Not in a git repository" || err == "git is not installed",
+			"{err}"
+		);
+	}
+
+	#[test]
+	fn only_the_previewed_lines_are_blamed() {
+		let dir = tempfile::tempdir().unwrap();
+		let git = |args: &[&str]| {
+			Command::new("git")
+				.arg("-C")
+				.arg(dir.path())
+				.args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+				.args(args)
+				.output()
+				.is_ok_and(|output| output.status.success())
+		};
+		if !git(&["init", "--quiet"]) {
+			return;
+		}
+		let path = dir.path().join("notes.txt");
+		std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+		assert!(git(&["add", "notes.txt"]));
+		assert!(git(&["commit", "--quiet", "-m", "notes"]));
+
+		let blame = blame_file(&path, 2).expect("blame");
+		assert_eq!(blame.len(), 2);
+		assert!(blame.iter().all(|line| line.author == "Ada"), "{blame:?}");
+	}
+}
@@ -15,6 +15,16 @@ pub enum PreviewKind {
 		/// Highlighted lines.
 		lines: Vec<Line<'static>>,
 	},
+	/// Markdown rendered as formatted text.
+	Markdown {
+		/// Rendered lines.
+		lines: Vec<Line<'static>>,
+	},
+	/// Entries of a zip or tar archive, one table row per line.
+	Archive {
+		/// Table lines.
+		lines: Vec<Line<'static>>,
+	},
 	/// Image content (requires `media-preview` feature).
 	#[cfg(feature = "media-preview")]
 	Image {
@@ -41,6 +51,11 @@ pub struct PreviewContent {
 	pub path: String,
 	/// Content kind.
 	pub kind: PreviewKind,
+	/// Short note shown under the preview, such as why no blame is shown.
+	pub notice: Option<String>,
+	/// Columns at the start of every line taken by line numbers or blame.
+	/// Wrapped continuations leave them blank and text selection skips them.
+	pub gutter_width: usize,
 }
 
 impl PreviewContent {
@@ -52,6 +67,8 @@ impl PreviewContent {
 			kind: PreviewKind::Placeholder {
 				message: String::new(),
 			},
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -63,6 +80,8 @@ impl PreviewContent {
 			kind: PreviewKind::Placeholder {
 				message: "Empty file".into(),
 			},
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -74,6 +93,8 @@ impl PreviewContent {
 			kind: PreviewKind::Placeholder {
 				message: message.into(),
 			},
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -85,6 +106,8 @@ impl PreviewContent {
 			kind: PreviewKind::Placeholder {
 				message: "Loading...".into(),
 			},
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -94,6 +117,30 @@ impl PreviewContent {
 		Self {
 			path: path.into(),
 			kind: PreviewKind::Text { lines },
+			notice: None,
+			gutter_width: 0,
+		}
+	}
+
+	/// Markdown preview with rendered lines.
+	#[must_use]
+	pub fn markdown(path: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+		Self {
+			path: path.into(),
+			kind: PreviewKind::Markdown { lines },
+			notice: None,
+			gutter_width: 0,
+		}
+	}
+
+	/// Archive preview listing its entries.
+	#[must_use]
+	pub fn archive(path: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+		Self {
+			path: path.into(),
+			kind: PreviewKind::Archive { lines },
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -104,6 +151,8 @@ impl PreviewContent {
 		Self {
 			path: path.into(),
 			kind: PreviewKind::Image { image },
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
@@ -114,9 +163,25 @@ impl PreviewContent {
 		Self {
 			path: path.into(),
 			kind: PreviewKind::Pdf { pdf },
+			notice: None,
+			gutter_width: 0,
 		}
 	}
 
+	/// Mark the first `width` columns of every line as a gutter.
+	#[must_use]
+	pub fn with_gutter_width(mut self, width: usize) -> Self {
+		self.gutter_width = width;
+		self
+	}
+
+	/// Attach a note shown under the preview.
+	#[must_use]
+	pub fn with_notice(mut self, notice: impl Into<String>) -> Self {
+		self.notice = Some(notice.into());
+		self
+	}
+
 	/// Check if this preview matches a path.
 	#[must_use]
 	pub fn matches(&self, path: &str) -> bool {
@@ -138,11 +203,13 @@ impl PreviewContent {
 		}
 	}
 
-	/// Get text lines if this is a text preview.
+	/// Get text lines if this is a text, markdown or archive preview.
 	#[must_use]
 	pub fn lines(&self) -> Option<&[Line<'static>]> {
 		match &self.kind {
-			PreviewKind::Text { lines } => Some(lines),
+			PreviewKind::Text { lines }
+			| PreviewKind::Markdown { lines }
+			| PreviewKind::Archive { lines } => Some(lines),
 			_ => None,
 		}
 	}
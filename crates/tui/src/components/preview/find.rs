@@ -0,0 +1,297 @@
+//! Finding the query, or text typed into the preview's own search prompt, in
+//! previewed text.
+//!
+//! Words are searched for as plain substrings, so the preview can open at the
+//! first line mentioning them and mark where they occur. Words with an
+//! uppercase letter match case-sensitively, like the smart case of the
+//! results.
+
+use ratatui::style::Style;
+use ratatui::text::Line;
+
+use super::selection::highlight::apply_selection_to_line;
+use super::wrap::{continuation_prefix_len, split_gutter};
+
+/// A word of the query to find, as characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+	chars: Vec<char>,
+	case_sensitive: bool,
+}
+
+/// The words of a query searched for in the preview.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PreviewTerms {
+	terms: Vec<Term>,
+}
+
+impl PreviewTerms {
+	/// The words of `query`, without the operators of the query syntax.
+	///
+	/// Negated words and `|` are left out, since text lacking a word or
+	/// either of two words is no place to scroll to.
+	pub(crate) fn parse(query: &str) -> Self {
+		let terms = query
+			.split_whitespace()
+			.filter(|word| *word != "|" && !word.starts_with('!'))
+			.map(|word| word.trim_start_matches(['\'', '^']).trim_end_matches('$'))
+			.filter(|word| !word.is_empty())
+			.map(|word| Term {
+				chars: word.chars().collect(),
+				case_sensitive: word.chars().any(char::is_uppercase),
+			})
+			.collect();
+		Self { terms }
+	}
+
+	/// `text` as a single word, spaces and operators included.
+	pub(crate) fn literal(text: &str) -> Self {
+		let terms = (!text.is_empty())
+			.then(|| Term {
+				chars: text.chars().collect(),
+				case_sensitive: text.chars().any(char::is_uppercase),
+			})
+			.into_iter()
+			.collect();
+		Self { terms }
+	}
+
+	/// Every occurrence of the words in `lines` after their `gutter_width`
+	/// column gutter, as the line index and the character range on the
+	/// whole line, in order.
+	pub(crate) fn occurrences(
+		&self,
+		lines: &[Line<'static>],
+		gutter_width: usize,
+	) -> Vec<(usize, usize, usize)> {
+		if self.terms.is_empty() {
+			return Vec::new();
+		}
+		lines
+			.iter()
+			.enumerate()
+			.flat_map(|(index, line)| {
+				let (skipped, body) = body_chars(line, gutter_width);
+				self.ranges(&body)
+					.into_iter()
+					.map(move |(start, end)| (index, skipped + start, skipped + end))
+			})
+			.collect()
+	}
+
+	/// Index of the first of `lines` containing any of the words after its
+	/// `gutter_width` column gutter.
+	pub(crate) fn first_line(&self, lines: &[Line<'static>], gutter_width: usize) -> Option<usize> {
+		if self.terms.is_empty() {
+			return None;
+		}
+		lines
+			.iter()
+			.position(|line| !self.ranges(&body_chars(line, gutter_width).1).is_empty())
+	}
+
+	/// `line` with every occurrence of the words after its `gutter_width`
+	/// column gutter restyled with `style`.
+	///
+	/// Rows a line was wrapped onto can be passed as well, since their
+	/// continuation padding is at least as wide as the gutter.
+	pub(crate) fn highlight(
+		&self,
+		line: &Line<'static>,
+		gutter_width: usize,
+		style: Style,
+	) -> Line<'static> {
+		let (skipped, body) = body_chars(line, gutter_width);
+		self.ranges(&body)
+			.into_iter()
+			.fold(line.clone(), |line, (start, end)| {
+				apply_selection_to_line(&line, skipped + start, skipped + end, style)
+			})
+	}
+
+	/// Character ranges of the words in `text`, in order and merged where
+	/// they overlap.
+	fn ranges(&self, text: &[char]) -> Vec<(usize, usize)> {
+		let mut ranges = Vec::new();
+		for term in &self.terms {
+			let len = term.chars.len();
+			for start in 0..text.len().saturating_sub(len - 1) {
+				let matches = text[start..start + len]
+					.iter()
+					.zip(&term.chars)
+					.all(|(&found, &wanted)| chars_match(found, wanted, term.case_sensitive));
+				if matches {
+					ranges.push((start, start + len));
+				}
+			}
+		}
+		ranges.sort_unstable();
+		let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+		for (start, end) in ranges {
+			match merged.last_mut() {
+				Some(last) if start <= last.1 => last.1 = last.1.max(end),
+				_ => merged.push((start, end)),
+			}
+		}
+		merged
+	}
+}
+
+/// The row of `rows`, the wrapped rows of `line` with its `gutter_width`
+/// column gutter, showing the character at `offset` into `line`.
+pub(crate) fn wrapped_row(
+	line: &Line<'static>,
+	rows: &[Line<'_>],
+	gutter_width: usize,
+	offset: usize,
+) -> usize {
+	row_starts(line, rows, gutter_width)
+		.iter()
+		.rposition(|&(start, _)| start <= offset)
+		.unwrap_or(0)
+}
+
+/// Restyle the character ranges of `line` given with their style in `ranges`
+/// in `rows`, the rows it was wrapped into with its `gutter_width` column
+/// gutter.
+pub(crate) fn highlight_wrapped(
+	line: &Line<'static>,
+	rows: &mut [Line<'static>],
+	gutter_width: usize,
+	ranges: &[(usize, usize, Style)],
+) {
+	let starts = row_starts(line, rows, gutter_width);
+	for (row, &(row_start, prefix)) in rows.iter_mut().zip(&starts) {
+		let row_end = row_start + line_chars(row).len().saturating_sub(prefix);
+		for &(start, end, style) in ranges {
+			let (start, end) = (start.max(row_start), end.min(row_end));
+			if start < end {
+				let (start, end) = (start - row_start + prefix, end - row_start + prefix);
+				*row = apply_selection_to_line(row, start, end, style);
+			}
+		}
+	}
+}
+
+/// Offset into `line` at which each of its wrapped `rows` continues it,
+/// with the number of padding characters the row starts with.
+fn row_starts(line: &Line<'static>, rows: &[Line<'_>], gutter_width: usize) -> Vec<(usize, usize)> {
+	let padding = continuation_prefix_len(line, gutter_width);
+	let mut start = 0;
+	rows.iter()
+		.enumerate()
+		.map(|(index, row)| {
+			let prefix = if index == 0 { 0 } else { padding };
+			let row_start = (start, prefix);
+			start += line_chars(row).len().saturating_sub(prefix);
+			row_start
+		})
+		.collect()
+}
+
+/// The characters of `line` after its `gutter_width` column gutter, with
+/// the number of characters the gutter takes.
+fn body_chars(line: &Line<'static>, gutter_width: usize) -> (usize, Vec<char>) {
+	let (gutter, body) = split_gutter(line, gutter_width);
+	let skipped = gutter.iter().map(|span| span.content.chars().count()).sum();
+	let body = body.iter().flat_map(|span| span.content.chars()).collect();
+	(skipped, body)
+}
+
+fn line_chars(line: &Line<'_>) -> Vec<char> {
+	line.spans
+		.iter()
+		.flat_map(|span| span.content.chars())
+		.collect()
+}
+
+fn chars_match(found: char, wanted: char, case_sensitive: bool) -> bool {
+	found == wanted || (!case_sensitive && found.to_lowercase().eq(wanted.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+	use ratatui::style::Color;
+	use ratatui::text::Span;
+
+	use super::*;
+	use crate::components::preview::wrap::{Gutter, wrap_highlighted_lines};
+
+	fn text(line: &Line<'_>) -> String {
+		line.spans
+			.iter()
+			.map(|span| span.content.as_ref())
+			.collect()
+	}
+
+	#[test]
+	fn query_operators_are_not_searched_for() {
+		let terms = PreviewTerms::parse("'main ^src !test | rs$");
+		let lines = [
+			Line::from("mod test;"),
+			Line::from("fn MAIN() {}"),
+			Line::from("use rs;"),
+		];
+		assert_eq!(terms.first_line(&lines, 0), Some(1));
+		assert_eq!(PreviewTerms::parse("!test |").first_line(&lines, 0), None);
+		assert_eq!(PreviewTerms::parse("Main").first_line(&lines, 0), None);
+	}
+
+	#[test]
+	fn every_occurrence_is_restyled_across_spans() {
+		let style = Style::default().fg(Color::Red);
+		let line = Line::from(vec![Span::raw("let foo"), Span::raw("bar = Foo;")]);
+		let highlighted = PreviewTerms::parse("foo").highlight(&line, 0, style);
+
+		assert_eq!(text(&highlighted), text(&line));
+		let marked: Vec<_> = highlighted
+			.spans
+			.iter()
+			.filter(|span| span.style == style)
+			.map(|span| span.content.as_ref())
+			.collect();
+		assert_eq!(marked, ["foo", "Foo"]);
+	}
+
+	#[test]
+	fn occurrences_are_mapped_to_the_rows_they_wrapped_onto() {
+		let style = Style::default().fg(Color::Red);
+		let line = Line::from("   7 │     alpha beta gamma delta");
+		let gutter = Gutter {
+			width: 7,
+			style: Style::default(),
+		};
+		let mut rows = wrap_highlighted_lines(std::slice::from_ref(&line), 20, gutter);
+		assert_eq!(rows.len(), 4, "{rows:?}");
+
+		let occurrences =
+			PreviewTerms::literal("gamma").occurrences(std::slice::from_ref(&line), 7);
+		let [(0, start, end)] = occurrences[..] else {
+			panic!("{occurrences:?}");
+		};
+		assert_eq!(wrapped_row(&line, &rows, 7, start), 2);
+
+		highlight_wrapped(&line, &mut rows, 7, &[(start, end, style)]);
+		let marked: Vec<_> = rows
+			.iter()
+			.flat_map(|row| &row.spans)
+			.filter(|span| span.style == style)
+			.map(|span| span.content.as_ref())
+			.collect();
+		assert_eq!(marked, ["gamma"]);
+	}
+
+	#[test]
+	fn line_numbers_are_not_searched() {
+		let lines = [Line::from("  12 │ let x = 12;")];
+		let occurrences = PreviewTerms::literal("12").occurrences(&lines, 7);
+		assert_eq!(occurrences, [(0, 15, 17)]);
+		assert_eq!(PreviewTerms::literal("│").occurrences(&lines, 7), []);
+
+		let terms = PreviewTerms::parse("12");
+		assert_eq!(terms.first_line(&lines, 7), Some(0));
+		let numbered = [Line::from("  12 │ let x = 1;")];
+		assert_eq!(terms.first_line(&numbered, 7), None);
+		assert_eq!(terms.first_line(&numbered, 0), Some(0));
+	}
+}
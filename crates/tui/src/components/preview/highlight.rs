@@ -8,47 +8,69 @@ use bat::config::{Config, VisibleLines};
 use bat::controller::Controller;
 use bat::input::Input;
 use bat::line_range::LineRanges;
-use bat::style::{StyleComponent, StyleComponents};
+use bat::style::StyleComponents;
 use ratatui::style::Style;
 use ratatui::text::{Line, Span};
 
+/// Width of the gutter [`highlight_with_bat`] numbers up to `max_lines`
+/// lines with.
+#[must_use]
+pub fn line_number_width(max_lines: usize) -> usize {
+	// Right-aligned digits followed by " │ ".
+	max_lines.to_string().len().max(4) + 3
+}
+
 /// Highlight file content using bat's Controller API.
+///
+/// With `line_numbers` each line starts with a right-aligned number and a
+/// `│` separator, [`line_number_width`] columns in all, which the preview
+/// passes to wrapping and text selection as its gutter. The truncation
+/// marker gets the separator without a number.
 pub fn highlight_with_bat(
 	path: &Path,
 	content: &str,
 	bat_theme: Option<&str>,
 	max_lines: usize,
+	line_numbers: bool,
 	assets: &HighlightingAssets,
 ) -> Vec<Line<'static>> {
+	let digits = line_number_width(max_lines) - 3;
+	let number = |line: Line<'static>, number: Option<usize>| -> Line<'static> {
+		if !line_numbers {
+			return line;
+		}
+		let gutter = match number {
+			Some(number) => format!("{number:>digits$} │ "),
+			None => format!("{:digits$} │ ", ""),
+		};
+		let mut spans = Vec::with_capacity(line.spans.len() + 1);
+		spans.push(Span::raw(gutter));
+		spans.extend(line.spans);
+		Line::from(spans).style(line.style)
+	};
+	let truncated = || number(Line::from("... (truncated)"), None);
+
 	let render_plain = |text: &str| -> Vec<Line<'static>> {
 		let mut output = Vec::new();
 		for (i, line) in text.lines().enumerate() {
 			if i >= max_lines {
-				output.push(Line::from(Span::styled(
-					"... (truncated)",
-					Style::default(),
-				)));
+				output.push(truncated());
 				break;
 			}
-			let line_num = format!("{:>4} │ ", i + 1);
-			output.push(Line::from(vec![
-				Span::styled(line_num, Style::default()),
-				Span::raw(line.to_string()),
-			]));
+			output.push(number(Line::from(line.to_string()), Some(i + 1)));
 		}
 		output
 	};
 
 	let highlight_attempt = panic::catch_unwind(AssertUnwindSafe(|| {
-		// Build bat Config
+		// Build bat Config. Line numbers are added afterwards so their width
+		// is known to wrapping and selection.
 		let theme = bat_theme.unwrap_or("Monokai Extended").to_string();
-		let mut style_components = StyleComponents::default();
-		style_components.insert(StyleComponent::LineNumbers);
 
 		let config = Config {
 			colored_output: true,
 			true_color: true,
-			style_components,
+			style_components: StyleComponents::default(),
 			theme,
 			visible_lines: VisibleLines::Ranges(LineRanges::all()),
 			term_width: 120,
@@ -66,13 +88,10 @@ pub fn highlight_with_bat(
 			let mut output = Vec::new();
 			for (i, line) in buffer.lines().enumerate() {
 				if i >= max_lines {
-					output.push(Line::from(Span::styled(
-						"... (truncated)",
-						Style::default(),
-					)));
+					output.push(truncated());
 					break;
 				}
-				output.push(parse_ansi_line(line));
+				output.push(number(parse_ansi_line(line), Some(i + 1)));
 			}
 			output
 		} else {
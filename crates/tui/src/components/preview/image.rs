@@ -1,12 +1,17 @@
 //! Image preview via terminal graphics protocols (Kitty, Sixel, iTerm2, halfblocks).
 //!
-//! SVG files are rasterized with `resvg` before display.
+//! SVG files are rasterized with `resvg` before display, at the size the
+//! preview is encoded for so small drawings stay sharp.
 //!
 //! Images are pre-encoded in the background worker thread using `Picker::new_protocol()`
-//! so that rendering is instant and doesn't block the UI thread.
+//! so that rendering is instant and doesn't block the UI thread. Images larger
+//! than the preview's maximum dimension on either side are shrunk first, so
+//! huge photos are not kept and encoded at full size. Their size is read from
+//! the header before decoding, and images whose pixels would not fit in
+//! [`MAX_DECODE_ALLOC`] are refused without being decoded.
 
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 
 use image::{DynamicImage, RgbaImage};
 use ratatui::layout::Rect;
@@ -16,10 +21,14 @@ use ratatui_image::{Image, Resize};
 
 use super::media::{center_rect, is_svg_file};
 
-static PICKER: OnceLock<Option<Picker>> = OnceLock::new();
+/// The graphics backend, `None` until the terminal was first probed.
+static PICKER: RwLock<Option<Option<Arc<Picker>>>> = RwLock::new(None);
 
 const MAX_SVG_DIMENSION: u32 = 2048;
 
+/// Most memory the decoder may allocate for one image, in bytes.
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
 const DEFAULT_ENCODE_SIZE: Rect = Rect {
 	x: 0,
 	y: 0,
@@ -27,21 +36,21 @@ const DEFAULT_ENCODE_SIZE: Rect = Rect {
 	height: 40,
 };
 
-fn render_svg(path: &Path) -> Option<DynamicImage> {
-	let data = std::fs::read(path).ok()?;
-	let tree = resvg::usvg::Tree::from_data(&data, &resvg::usvg::Options::default()).ok()?;
+/// Rasterize the SVG document `data` as large as fits within `bounds`
+/// pixels, keeping its aspect ratio.
+fn render_svg(data: &[u8], bounds: (u32, u32)) -> Option<DynamicImage> {
+	let tree = resvg::usvg::Tree::from_data(data, &resvg::usvg::Options::default()).ok()?;
 	let size = tree.size();
 
-	let scale =
-		if size.width() > MAX_SVG_DIMENSION as f32 || size.height() > MAX_SVG_DIMENSION as f32 {
-			(MAX_SVG_DIMENSION as f32 / size.width()).min(MAX_SVG_DIMENSION as f32 / size.height())
-		} else {
-			1.0
-		};
+	let (max_width, max_height) = (
+		bounds.0.clamp(1, MAX_SVG_DIMENSION) as f32,
+		bounds.1.clamp(1, MAX_SVG_DIMENSION) as f32,
+	);
+	let scale = (max_width / size.width()).min(max_height / size.height());
 
 	let (width, height) = (
-		(size.width() * scale).ceil() as u32,
-		(size.height() * scale).ceil() as u32,
+		(size.width() * scale).ceil().max(1.0) as u32,
+		(size.height() * scale).ceil().max(1.0) as u32,
 	);
 
 	let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)?;
@@ -54,11 +63,73 @@ fn render_svg(path: &Path) -> Option<DynamicImage> {
 	RgbaImage::from_raw(width, height, pixmap.take()).map(DynamicImage::ImageRgba8)
 }
 
-/// Get the global image picker (lazily initialized).
-pub fn get_picker() -> Option<&'static Picker> {
-	PICKER
-		.get_or_init(|| picker_from_env().or_else(|| Picker::from_query_stdio().ok()))
+/// Decode the raster image at `path` after checking from its header that
+/// its pixels fit in [`MAX_DECODE_ALLOC`].
+fn decode(path: &Path) -> Result<DynamicImage, String> {
+	let open = || {
+		image::ImageReader::open(path)
+			.and_then(image::ImageReader::with_guessed_format)
+			.map_err(|err| format!("Failed to read image: {err}"))
+	};
+	let (width, height) = open()?
+		.into_dimensions()
+		.map_err(|err| format!("Failed to load image: {err}"))?;
+	if !fits_decode_budget(width, height) {
+		return Err(format!("Image too large to preview ({width}×{height})"));
+	}
+
+	let mut reader = open()?;
+	let mut limits = image::Limits::default();
+	limits.max_image_width = Some(width);
+	limits.max_image_height = Some(height);
+	limits.max_alloc = Some(MAX_DECODE_ALLOC);
+	reader.limits(limits);
+	reader
+		.decode()
+		.map_err(|err| format!("Failed to load image: {err}"))
+}
+
+/// Whether a `width`×`height` image decoded to RGBA fits in
+/// [`MAX_DECODE_ALLOC`].
+fn fits_decode_budget(width: u32, height: u32) -> bool {
+	(u64::from(width) * u64::from(height)).saturating_mul(4) <= MAX_DECODE_ALLOC
+}
+
+/// Shrink `img` to fit within `max` pixels on either side, keeping its
+/// aspect ratio. Images that already fit are returned unchanged.
+fn downscale(img: DynamicImage, max: u32) -> DynamicImage {
+	if img.width() > max || img.height() > max {
+		img.thumbnail(max, max)
+	} else {
+		img
+	}
+}
+
+/// Get the global image picker, probing the terminal on first use.
+pub fn get_picker() -> Option<Arc<Picker>> {
+	if let Some(picker) = PICKER
+		.read()
+		.unwrap_or_else(PoisonError::into_inner)
 		.as_ref()
+	{
+		return picker.clone();
+	}
+	let mut picker = PICKER.write().unwrap_or_else(PoisonError::into_inner);
+	picker.get_or_insert_with(probe).clone()
+}
+
+/// Probe the terminal's graphics backend again, e.g. after another program
+/// ran in it.
+///
+/// Needs the terminal in raw mode with nothing else reading its input.
+pub fn reprobe() {
+	*PICKER.write().unwrap_or_else(PoisonError::into_inner) = Some(probe());
+}
+
+fn probe() -> Option<Arc<Picker>> {
+	picker_from_env()
+		.or_else(|| Picker::from_query_stdio().ok())
+		.map(Arc::new)
 }
 
 /// Check if image preview is available in the current terminal.
@@ -67,6 +138,13 @@ pub fn is_available() -> bool {
 	get_picker().is_some()
 }
 
+/// Check if the terminal draws images with real pixels, rather than
+/// approximating them with half-block characters.
+#[must_use]
+pub fn has_graphics() -> bool {
+	get_picker().is_some_and(|p| !matches!(p.protocol_type(), ProtocolType::Halfblocks))
+}
+
 /// Get the name of the detected graphics protocol.
 #[must_use]
 pub fn protocol_name() -> &'static str {
@@ -104,24 +182,38 @@ impl std::fmt::Debug for ImagePreview {
 }
 
 impl ImagePreview {
-	/// Load and pre-encode an image from a file path.
+	/// Load and pre-encode an image from a file path, shrinking it to fit
+	/// within `max_dimension` pixels on either side.
 	///
 	/// This performs the expensive encoding work upfront so that
 	/// rendering is instant.
-	pub fn load(path: &Path) -> Option<Self> {
-		let picker = get_picker()?;
+	///
+	/// # Errors
+	///
+	/// Returns a message for the preview pane if the terminal cannot show
+	/// images or the image cannot be read, is too large to decode, or fails
+	/// to decode.
+	pub fn load(path: &Path, max_dimension: u32) -> Result<Self, String> {
+		let picker = get_picker().ok_or("Terminal cannot show images")?;
 		let img = if is_svg_file(path) {
-			render_svg(path)?
+			let (font_width, font_height) = picker.font_size();
+			let area = encode_size();
+			let bounds = (
+				(u32::from(area.width) * u32::from(font_width)).min(max_dimension),
+				(u32::from(area.height) * u32::from(font_height)).min(max_dimension),
+			);
+			let data = std::fs::read(path).map_err(|err| format!("Failed to read image: {err}"))?;
+			render_svg(&data, bounds).ok_or("Failed to load image")?
 		} else {
-			image::ImageReader::open(path).ok()?.decode().ok()?
+			downscale(decode(path)?, max_dimension)
 		};
-		Self::from_image_with_picker(img, picker)
+		Self::from_image_with_picker(img, &picker).ok_or_else(|| "Failed to load image".to_owned())
 	}
 
 	/// Create from an already-decoded image.
 	pub fn from_image(img: DynamicImage) -> Option<Self> {
 		let picker = get_picker()?;
-		Self::from_image_with_picker(img, picker)
+		Self::from_image_with_picker(img, &picker)
 	}
 
 	fn from_image_with_picker(img: DynamicImage, picker: &Picker) -> Option<Self> {
@@ -198,3 +290,46 @@ fn picker_from_env() -> Option<Picker> {
 	picker.set_protocol_type(proto);
 	Some(picker)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CIRCLE: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="8">
+		<circle cx="4" cy="4" r="4" fill="red"/>
+	</svg>"#;
+
+	#[test]
+	fn svgs_are_rasterized_to_fill_the_bounds() {
+		let image = render_svg(CIRCLE, (64, 64)).expect("valid svg");
+		assert_eq!((image.width(), image.height()), (64, 32));
+		assert!(
+			image.to_rgba8().pixels().any(|pixel| pixel.0[3] > 0),
+			"nothing was drawn"
+		);
+
+		let capped = render_svg(CIRCLE, (u32::MAX, u32::MAX)).expect("valid svg");
+		assert_eq!(capped.width(), MAX_SVG_DIMENSION);
+	}
+
+	#[test]
+	fn oversized_images_are_downscaled_to_the_cap() {
+		let large = downscale(DynamicImage::new_rgba8(5000, 1000), 1000);
+		assert_eq!((large.width(), large.height()), (1000, 200));
+
+		let small = downscale(DynamicImage::new_rgba8(640, 480), 1000);
+		assert_eq!((small.width(), small.height()), (640, 480));
+	}
+
+	#[test]
+	fn images_beyond_the_decode_budget_are_refused() {
+		assert!(fits_decode_budget(8192, 8192));
+		assert!(!fits_decode_budget(100_000, 100_000));
+		assert!(!fits_decode_budget(u32::MAX, 1));
+	}
+
+	#[test]
+	fn invalid_svgs_fail_to_decode() {
+		assert!(render_svg(b"<svg", (64, 64)).is_none());
+	}
+}
@@ -0,0 +1,393 @@
+//! Rendering of markdown files as formatted text.
+//!
+//! Headings, emphasis, lists, quotes and code blocks are converted into
+//! styled ratatui lines instead of being shown as highlighted source. Links
+//! keep their destination next to the link text and images show their alt
+//! text, so nothing in the document is silently dropped.
+
+use std::path::Path;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// File extensions rendered as markdown.
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// Width of the line drawn for thematic breaks.
+const RULE_WIDTH: usize = 40;
+
+/// Whether `path` has a markdown extension.
+pub(crate) fn is_markdown_file(path: &Path) -> bool {
+	path.extension()
+		.and_then(|ext| ext.to_str())
+		.is_some_and(|ext| {
+			MARKDOWN_EXTENSIONS
+				.iter()
+				.any(|candidate| ext.eq_ignore_ascii_case(candidate))
+		})
+}
+
+/// Render `source` as formatted lines, keeping at most `max_lines`.
+pub(crate) fn render_markdown(source: &str, max_lines: usize) -> Vec<Line<'static>> {
+	let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+	let mut renderer = Renderer::default();
+	for event in Parser::new_ext(source, options) {
+		renderer.event(event);
+	}
+	let mut lines = renderer.finish();
+	if lines.len() > max_lines {
+		lines.truncate(max_lines);
+		lines.push(Line::from("... (truncated)"));
+	}
+	lines
+}
+
+fn heading_style(level: HeadingLevel) -> Style {
+	let style = Style::new().add_modifier(Modifier::BOLD);
+	match level {
+		HeadingLevel::H1 => style.add_modifier(Modifier::UNDERLINED),
+		HeadingLevel::H2 => style,
+		_ => style.add_modifier(Modifier::ITALIC),
+	}
+}
+
+fn code_style() -> Style {
+	Style::new().fg(Color::Yellow)
+}
+
+fn link_style() -> Style {
+	Style::new()
+		.fg(Color::Blue)
+		.add_modifier(Modifier::UNDERLINED)
+}
+
+fn muted_style() -> Style {
+	Style::new().add_modifier(Modifier::DIM)
+}
+
+/// A list being rendered, innermost last.
+struct List {
+	/// Number of the next item, or `None` for bullet lists.
+	next: Option<u64>,
+	/// Width of the item markers, used to indent continuation lines.
+	indent: usize,
+}
+
+/// A link whose text is being rendered.
+struct OpenLink {
+	destination: String,
+	text: String,
+}
+
+#[derive(Default)]
+struct Renderer {
+	lines: Vec<Line<'static>>,
+	current: Vec<Span<'static>>,
+	/// Inline styles in effect, innermost last.
+	styles: Vec<Style>,
+	lists: Vec<List>,
+	links: Vec<OpenLink>,
+	/// Marker of a list item whose first line has not started yet.
+	item_marker: Option<String>,
+	quote_depth: usize,
+	in_code_block: bool,
+}
+
+impl Renderer {
+	fn event(&mut self, event: Event<'_>) {
+		match event {
+			Event::Start(tag) => self.start(tag),
+			Event::End(tag) => self.end(tag),
+			Event::Text(text) if self.in_code_block => {
+				for line in text.lines() {
+					self.push_span(Span::styled(format!("  {line}"), code_style()));
+					self.flush_line();
+				}
+			}
+			Event::Text(text) => self.push_text(&text),
+			Event::Code(code) => {
+				if let Some(link) = self.links.last_mut() {
+					link.text.push_str(&code);
+				}
+				self.push_span(Span::styled(code.into_string(), code_style()));
+			}
+			Event::Html(html) | Event::InlineHtml(html) => {
+				for (index, line) in html.lines().enumerate() {
+					if index > 0 {
+						self.flush_line();
+					}
+					self.push_span(Span::styled(line.to_owned(), muted_style()));
+				}
+			}
+			Event::SoftBreak => self.push_text(" "),
+			Event::HardBreak => self.flush_line(),
+			Event::Rule => {
+				self.flush_line();
+				self.push_span(Span::styled("─".repeat(RULE_WIDTH), muted_style()));
+				self.end_block();
+			}
+			Event::TaskListMarker(checked) => {
+				self.push_text(if checked { "[x] " } else { "[ ] " });
+			}
+			_ => {}
+		}
+	}
+
+	fn start(&mut self, tag: Tag<'_>) {
+		match tag {
+			Tag::Heading { level, .. } => {
+				self.flush_line();
+				self.styles.push(heading_style(level));
+			}
+			Tag::BlockQuote(_) => {
+				self.flush_line();
+				self.quote_depth += 1;
+			}
+			Tag::CodeBlock(kind) => {
+				self.flush_line();
+				if let CodeBlockKind::Fenced(language) = kind
+					&& !language.is_empty()
+				{
+					self.push_span(Span::styled(language.into_string(), muted_style()));
+					self.flush_line();
+				}
+				self.in_code_block = true;
+			}
+			Tag::List(start) => {
+				self.flush_line();
+				self.lists.push(List {
+					next: start,
+					indent: 0,
+				});
+			}
+			Tag::Item => {
+				self.flush_line();
+				if let Some(list) = self.lists.last_mut() {
+					let marker = match &mut list.next {
+						Some(number) => {
+							*number += 1;
+							format!("{}. ", *number - 1)
+						}
+						None => "• ".to_owned(),
+					};
+					list.indent = marker.chars().count();
+					self.item_marker = Some(marker);
+				}
+			}
+			Tag::Emphasis => self.push_style(Style::new().add_modifier(Modifier::ITALIC)),
+			Tag::Strong => self.push_style(Style::new().add_modifier(Modifier::BOLD)),
+			Tag::Strikethrough => {
+				self.push_style(Style::new().add_modifier(Modifier::CROSSED_OUT));
+			}
+			Tag::Link { dest_url, .. } => {
+				self.push_style(link_style());
+				self.links.push(OpenLink {
+					destination: dest_url.into_string(),
+					text: String::new(),
+				});
+			}
+			Tag::Image { .. } => {
+				self.push_style(link_style());
+				self.push_text("[image: ");
+			}
+			_ => {}
+		}
+	}
+
+	fn end(&mut self, tag: TagEnd) {
+		match tag {
+			TagEnd::Paragraph | TagEnd::HtmlBlock => self.end_block(),
+			TagEnd::Heading(_) => {
+				self.styles.pop();
+				self.end_block();
+			}
+			TagEnd::BlockQuote(_) => {
+				self.flush_line();
+				self.quote_depth = self.quote_depth.saturating_sub(1);
+				self.end_block();
+			}
+			TagEnd::CodeBlock => {
+				self.in_code_block = false;
+				self.end_block();
+			}
+			TagEnd::List(_) => {
+				self.flush_line();
+				self.lists.pop();
+				if self.lists.is_empty() {
+					self.end_block();
+				}
+			}
+			TagEnd::Item => {
+				self.flush_line();
+				self.item_marker = None;
+			}
+			TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+				self.styles.pop();
+			}
+			TagEnd::Link => {
+				self.styles.pop();
+				if let Some(link) = self.links.pop()
+					&& !link.destination.is_empty()
+					&& link.destination != link.text
+				{
+					let destination = format!(" ({})", link.destination);
+					self.push_span(Span::styled(destination, muted_style()));
+				}
+			}
+			TagEnd::Image => {
+				self.push_text("]");
+				self.styles.pop();
+			}
+			_ => {}
+		}
+	}
+
+	fn push_style(&mut self, style: Style) {
+		let base = self.styles.last().copied().unwrap_or_default();
+		self.styles.push(base.patch(style));
+	}
+
+	fn push_text(&mut self, text: &str) {
+		if let Some(link) = self.links.last_mut() {
+			link.text.push_str(text);
+		}
+		let style = self.styles.last().copied().unwrap_or_default();
+		self.push_span(Span::styled(text.to_owned(), style));
+	}
+
+	/// Append `span` to the current line, starting it with the quote and
+	/// list prefix if it is empty.
+	fn push_span(&mut self, span: Span<'static>) {
+		if self.current.is_empty() {
+			let prefix = self.line_prefix();
+			if !prefix.is_empty() {
+				self.current.push(Span::styled(prefix, muted_style()));
+			}
+		}
+		self.current.push(span);
+	}
+
+	fn line_prefix(&mut self) -> String {
+		let mut prefix = "│ ".repeat(self.quote_depth);
+		let depth = self.lists.len();
+		for list in self.lists.iter().take(depth.saturating_sub(1)) {
+			prefix.push_str(&" ".repeat(list.indent));
+		}
+		if let Some(marker) = self.item_marker.take() {
+			prefix.push_str(&marker);
+		} else if let Some(list) = self.lists.last() {
+			prefix.push_str(&" ".repeat(list.indent));
+		}
+		prefix
+	}
+
+	fn flush_line(&mut self) {
+		if !self.current.is_empty() {
+			self.lines
+				.push(Line::from(std::mem::take(&mut self.current)));
+		}
+	}
+
+	/// Finish a block, separating it from the next one by a blank line.
+	fn end_block(&mut self) {
+		self.flush_line();
+		if self.lists.is_empty() && self.lines.last().is_some_and(|line| line.width() > 0) {
+			self.lines.push(Line::default());
+		}
+	}
+
+	fn finish(mut self) -> Vec<Line<'static>> {
+		self.flush_line();
+		while self.lines.last().is_some_and(|line| line.width() == 0) {
+			self.lines.pop();
+		}
+		self.lines
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn texts(lines: &[Line<'_>]) -> Vec<String> {
+		lines.iter().map(|line| line.to_string()).collect()
+	}
+
+	fn span_style(line: &Line<'_>, text: &str) -> Style {
+		line.spans
+			.iter()
+			.find(|span| span.content == text)
+			.unwrap_or_else(|| panic!("no span {text:?} in {line:?}"))
+			.style
+	}
+
+	#[test]
+	fn documents_render_as_styled_lines() {
+		let source = "# Title\n\n\
+			Some **bold** and *italic* text with `code`.\n\n\
+			- one\n\
+			- two\n  \
+			  1. nested\n\n\
+			> quoted\n\n\
+			[docs](https://example.com) ![logo](logo.png)\n\n\
+			```rust\n\
+			fn main() {}\n\
+			```\n";
+		let lines = render_markdown(source, 100);
+		assert_eq!(
+			texts(&lines),
+			[
+				"Title",
+				"",
+				"Some bold and italic text with code.",
+				"",
+				"• one",
+				"• two",
+				"  1. nested",
+				"",
+				"│ quoted",
+				"",
+				"docs (https://example.com) [image: logo]",
+				"",
+				"rust",
+				"  fn main() {}",
+			]
+		);
+
+		let bold = Style::new().add_modifier(Modifier::BOLD);
+		assert_eq!(
+			span_style(&lines[0], "Title"),
+			bold.add_modifier(Modifier::UNDERLINED)
+		);
+		assert_eq!(span_style(&lines[2], "bold"), bold);
+		assert_eq!(
+			span_style(&lines[2], "italic"),
+			Style::new().add_modifier(Modifier::ITALIC)
+		);
+		assert_eq!(span_style(&lines[2], "code"), code_style());
+		assert_eq!(span_style(&lines[10], "docs"), link_style());
+		assert_eq!(span_style(&lines[10], "logo"), link_style());
+		assert_eq!(span_style(&lines[13], "  fn main() {}"), code_style());
+	}
+
+	#[test]
+	fn autolinks_are_not_repeated_and_long_documents_are_truncated() {
+		let lines = render_markdown("<https://example.com>", 10);
+		assert_eq!(texts(&lines), ["https://example.com"]);
+
+		let source = "a\n\nb\n\nc\n";
+		assert_eq!(
+			texts(&render_markdown(source, 3)),
+			["a", "", "b", "... (truncated)"]
+		);
+	}
+
+	#[test]
+	fn markdown_files_are_recognised_by_extension() {
+		assert!(is_markdown_file(Path::new("README.md")));
+		assert!(is_markdown_file(Path::new("docs/guide.MARKDOWN")));
+		assert!(!is_markdown_file(Path::new("notes.txt")));
+		assert!(!is_markdown_file(Path::new("md")));
+	}
+}
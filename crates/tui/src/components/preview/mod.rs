@@ -1,30 +1,42 @@
 //! File preview pane with syntax highlighting and optional media support.
 //!
-//! Uses `bat` for text highlighting. With `media-preview` feature, renders
-//! images and PDFs via terminal graphics protocols (Kitty, Sixel, iTerm2, halfblocks).
+//! Uses `bat` for text highlighting, renders markdown files as formatted
+//! text and can show `git blame` beside highlighted text. With the default
+//! `archive-preview` feature, lists the entries of zip and tar archives.
+//! Embedders can supply their own content through [`PreviewProvider`]. With
+//! `media-preview` feature, renders images and PDFs via terminal graphics
+//! protocols (Kitty, Sixel, iTerm2, halfblocks).
 
+#[cfg(feature = "archive-preview")]
+mod archive;
+mod blame;
 mod content;
+mod find;
 pub(crate) mod highlight;
 #[cfg(feature = "media-preview")]
 pub mod image;
+mod markdown;
 #[cfg(feature = "media-preview")]
 mod media;
 #[cfg(feature = "media-preview")]
 pub mod pdf;
+mod provider;
 mod render;
 pub mod selection;
 mod worker;
 mod wrap;
 
 pub use content::{PreviewContent, PreviewKind};
+pub(crate) use find::{PreviewTerms, highlight_wrapped, wrapped_row};
 #[cfg(feature = "media-preview")]
 pub use image::{ImagePreview, is_available as is_image_available, protocol_name};
 #[cfg(feature = "media-preview")]
 pub use pdf::{PdfPreview, is_pdf_file};
+pub use provider::{PreviewProvider, SharedPreviewProvider};
 pub use render::{PreviewContext, render_preview};
 pub use selection::{
 	TextSelection, apply_selection_to_lines, copy_to_clipboard, extract_selected_text,
 	selection_style,
 };
 pub use worker::PreviewRuntime;
-pub use wrap::wrap_highlighted_lines;
+pub use wrap::{Gutter, wrap_highlighted_lines};
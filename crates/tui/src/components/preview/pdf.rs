@@ -2,7 +2,8 @@
 //!
 //! PDF files are rendered using Poppler (via `poppler-rs`) and converted to images,
 //! then displayed using the same terminal graphics protocols as regular images
-//! (Kitty, Sixel, iTerm2, halfblocks).
+//! (Kitty, Sixel, iTerm2, halfblocks). Without a graphics protocol, or when
+//! configured to, the text of the pages is extracted and previewed instead.
 
 use std::path::Path;
 
@@ -28,12 +29,7 @@ impl PdfPreview {
 		if get_picker().is_none() {
 			return Err("No terminal graphics protocol available".to_string());
 		}
-		let canonical = path
-			.canonicalize()
-			.map_err(|e| format!("Failed to canonicalize path: {}", e))?;
-		let uri = format!("file://{}", canonical.display());
-		let document =
-			Document::from_file(&uri, None).map_err(|e| format!("Failed to open PDF: {}", e))?;
+		let document = open(path)?;
 
 		let page_count = document.n_pages() as u32;
 
@@ -101,10 +97,65 @@ impl PdfPreview {
 	/// Format page count as a human-readable string (e.g., "1 page" or "5 pages").
 	#[must_use]
 	pub fn page_count_string(&self) -> String {
-		if self.page_count == 1 {
-			"1 page".to_string()
-		} else {
-			format!("{} pages", self.page_count)
+		page_count_label(self.page_count)
+	}
+}
+
+/// Extract the text of every page of the PDF at `path` as at most
+/// `max_lines` lines, with a heading before each page after the first,
+/// along with the number of pages.
+pub fn extract_text(path: &Path, max_lines: usize) -> Result<(Vec<String>, u32), String> {
+	let document = open(path)?;
+	let page_count = document.n_pages().max(0) as u32;
+	let mut lines = Vec::new();
+	for index in 0..document.n_pages() {
+		if lines.len() >= max_lines {
+			break;
+		}
+		if index > 0 {
+			lines.push(String::new());
+			lines.push(format!("── Page {} ──", index + 1));
 		}
+		if let Some(text) = document.page(index).and_then(|page| page.text()) {
+			lines.extend(text.lines().map(|line| line.trim_end().to_string()));
+		}
+	}
+	lines.truncate(max_lines);
+	Ok((lines, page_count))
+}
+
+/// Format a page count as a human-readable string (e.g., "1 page" or "5 pages").
+#[must_use]
+pub fn page_count_label(page_count: u32) -> String {
+	if page_count == 1 {
+		"1 page".to_string()
+	} else {
+		format!("{} pages", page_count)
+	}
+}
+
+/// Open the PDF at `path` with Poppler.
+fn open(path: &Path) -> Result<Document, String> {
+	let canonical = path
+		.canonicalize()
+		.map_err(|e| format!("Failed to canonicalize path: {}", e))?;
+	let uri = format!("file://{}", canonical.display());
+	Document::from_file(&uri, None).map_err(|e| format!("Failed to open PDF: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn text_of_every_page_is_extracted() {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+			.join("src/components/preview/fixtures/two-pages.pdf");
+		let (lines, pages) = extract_text(&path, 100).expect("text");
+		assert_eq!(pages, 2);
+		assert_eq!(lines, ["Hello from frz", "", "── Page 2 ──", "Second page"]);
+
+		let (lines, _) = extract_text(&path, 1).expect("text");
+		assert_eq!(lines, ["Hello from frz"]);
 	}
 }
@@ -0,0 +1,143 @@
+//! Pluggable sources of preview content.
+//!
+//! Every preview comes from a [`PreviewProvider`]. Providers registered by
+//! the embedding application are asked first, in the order they were added,
+//! and the first one to return content wins. When none does, the built-in
+//! file provider previews the selected file from disk, covering text,
+//! markdown, archives and, with `media-preview`, images and PDFs.
+
+use std::sync::Arc;
+
+use frz_core::filesystem::search::SearchSelection;
+
+use super::content::PreviewContent;
+
+/// Supplies preview content for selected results.
+///
+/// Implement this for rows that only exist in memory, or to replace how some
+/// files are previewed. Providers run on the preview worker thread, so they
+/// may do blocking work without stalling the UI.
+pub trait PreviewProvider {
+	/// Preview content for `selection`, or `None` to leave it to the next
+	/// provider.
+	///
+	/// The path of the returned content is replaced with the path of the
+	/// selection, so the pane can tell which result the preview belongs to.
+	fn content_for(&self, selection: &SearchSelection) -> Option<PreviewContent>;
+}
+
+/// A registered provider, shared with the preview worker.
+pub type SharedPreviewProvider = Arc<dyn PreviewProvider + Send + Sync>;
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use frz_core::filesystem::search::FileRow;
+	use ratatui::text::Line;
+
+	use super::*;
+	use crate::components::preview::PreviewRuntime;
+
+	/// Serves canned text for paths under `memory/`.
+	struct Canned;
+
+	impl PreviewProvider for Canned {
+		fn content_for(&self, selection: &SearchSelection) -> Option<PreviewContent> {
+			let SearchSelection::File(file) = selection;
+			let name = file.path.strip_prefix("memory/")?;
+			Some(PreviewContent::text(
+				"",
+				vec![Line::from(format!("in-memory {name}"))],
+			))
+		}
+	}
+
+	fn preview(runtime: &mut PreviewRuntime, path: &str, root: &std::path::Path) -> PreviewContent {
+		preview_row(runtime, FileRow::filesystem(path), root)
+	}
+
+	fn preview_row(
+		runtime: &mut PreviewRuntime,
+		row: FileRow,
+		root: &std::path::Path,
+	) -> PreviewContent {
+		let path = row.path.clone();
+		let selection = SearchSelection::File(row);
+		let id = runtime.request(selection, Some(root.to_path_buf()), None, 100);
+		let deadline = Instant::now() + Duration::from_secs(10);
+		while Instant::now() < deadline {
+			if let Ok(result) = runtime.try_recv()
+				&& result.id == id
+			{
+				return result.content;
+			}
+			std::thread::sleep(Duration::from_millis(5));
+		}
+		panic!("no preview for {path}");
+	}
+
+	fn texts(content: &PreviewContent) -> Vec<String> {
+		content
+			.lines()
+			.unwrap_or_default()
+			.iter()
+			.map(|line| line.to_string())
+			.collect()
+	}
+
+	#[test]
+	fn registered_providers_are_asked_before_the_filesystem() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut runtime = PreviewRuntime::new();
+		runtime.add_provider(Arc::new(Canned));
+
+		let content = preview(&mut runtime, "memory/notes", dir.path());
+		assert_eq!(texts(&content), ["in-memory notes"]);
+		assert_eq!(
+			content.path,
+			dir.path().join("memory/notes").display().to_string()
+		);
+	}
+
+	#[test]
+	fn declined_selections_fall_back_to_the_file_provider() {
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::write(dir.path().join("plain.txt"), "from disk\n").unwrap();
+		let mut runtime = PreviewRuntime::new();
+		runtime.add_provider(Arc::new(Canned));
+
+		let content = preview(&mut runtime, "plain.txt", dir.path());
+		assert!(
+			texts(&content)
+				.iter()
+				.any(|line| line.contains("from disk")),
+			"{:?}",
+			texts(&content)
+		);
+
+		let missing = preview(&mut runtime, "missing.txt", dir.path());
+		assert!(missing.error_message().is_some());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_names_are_previewed_from_the_real_path() {
+		use std::ffi::OsStr;
+		use std::os::unix::ffi::OsStrExt;
+
+		let dir = tempfile::tempdir().unwrap();
+		let name = std::path::Path::new(OsStr::from_bytes(b"caf\xe9.txt"));
+		std::fs::write(dir.path().join(name), "latin-1 name\n").unwrap();
+		let mut runtime = PreviewRuntime::new();
+
+		let content = preview_row(&mut runtime, FileRow::from_path(name), dir.path());
+		assert!(
+			texts(&content)
+				.iter()
+				.any(|line| line.contains("latin-1 name")),
+			"{:?}",
+			texts(&content)
+		);
+	}
+}
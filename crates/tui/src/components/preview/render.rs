@@ -24,6 +24,8 @@ pub struct PreviewContext<'a> {
 	pub scrollbar_area: &'a mut Option<Rect>,
 	/// Cached scroll metrics for the current viewport/content.
 	pub scroll_metrics: Option<ScrollMetrics>,
+	/// Search prompt shown under the preview in place of its notice.
+	pub prompt: Option<String>,
 	/// Color theme.
 	pub theme: &'a Theme,
 }
@@ -51,13 +53,16 @@ pub fn render_preview(frame: &mut Frame, area: Rect, ctx: PreviewContext<'_>) {
 		format!(" {} ", ctx.content.path)
 	};
 
-	let block = Block::default()
+	let mut block = Block::default()
 		.borders(Borders::ALL)
 		.border_set(ratatui::symbols::border::ROUNDED)
 		.border_style(
 			Style::default().fg(ctx.theme.header.fg.unwrap_or(ratatui::style::Color::Reset)),
 		)
 		.title(title);
+	if let Some(notice) = ctx.prompt.as_ref().or(ctx.content.notice.as_ref()) {
+		block = block.title_bottom(format!(" {notice} "));
+	}
 
 	let inner = block.inner(area);
 	frame.render_widget(block, area);
@@ -75,7 +80,7 @@ pub fn render_preview(frame: &mut Frame, area: Rect, ctx: PreviewContext<'_>) {
 			};
 			render_centered_placeholder(frame, inner, msg, ctx.theme);
 		}
-		PreviewKind::Text { lines: _ } => {
+		PreviewKind::Text { .. } | PreviewKind::Markdown { .. } | PreviewKind::Archive { .. } => {
 			let metrics = ctx.scroll_metrics.unwrap_or_else(|| {
 				ScrollMetrics::compute(ctx.wrapped_lines.len(), inner.height as usize)
 			});
@@ -1,5 +1,26 @@
 use std::io::Write;
 use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Bytes written to the terminal at a time by the OSC52 path, unless
+/// overridden with `FRZ_OSC52_CHUNK_SIZE`.
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Smallest chunk size accepted from `FRZ_OSC52_CHUNK_SIZE`.
+const MIN_CHUNK_SIZE: usize = 16;
+
+/// Longest base64 payload sent through OSC52 to terminals not known to take
+/// more. Larger copies go to the native clipboard tools, since xterm, hterm
+/// and others drop or truncate sequences beyond about 100 kB.
+const MAX_OSC52_PAYLOAD: usize = 100_000;
+
+/// Longest payload sent to kitty, WezTerm and foot, which accept copies of
+/// many megabytes.
+const LARGE_OSC52_PAYLOAD: usize = 8 * 1024 * 1024;
+
+/// GNU screen cuts device control strings after 768 bytes, so each chunk
+/// passed through it is kept below that, wrapper included.
+const SCREEN_CHUNK_SIZE: usize = 760;
 
 /// Copy text to clipboard using available methods.
 /// Tries OSC52 first (works in tmux/ssh), then falls back to native tools.
@@ -10,18 +31,126 @@ pub fn copy_to_clipboard(text: &str) -> Result<(), String> {
 	try_native_clipboard(text)
 }
 
+/// Terminal multiplexer the OSC52 sequence has to be passed through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Passthrough {
+	/// The sequence goes straight to the terminal.
+	None,
+	/// tmux forwards one device control string wrapping the sequence.
+	Tmux,
+	/// GNU screen reassembles a series of short device control strings.
+	Screen,
+}
+
+impl Passthrough {
+	fn detect() -> Self {
+		if std::env::var_os("TMUX").is_some() {
+			Self::Tmux
+		} else if std::env::var("TERM").is_ok_and(|term| term.starts_with("screen"))
+			|| std::env::var_os("STY").is_some()
+		{
+			Self::Screen
+		} else {
+			Self::None
+		}
+	}
+}
+
+/// Longest base64 payload the terminal described by the environment
+/// variables `var` returns is known to accept over OSC52, or `None` when it
+/// ignores OSC52 altogether and copies should go to the native tools.
+fn osc52_payload_limit(var: impl Fn(&str) -> Option<String>) -> Option<usize> {
+	let term = var("TERM").unwrap_or_default();
+	let program = var("TERM_PROGRAM").unwrap_or_default();
+	// The Linux console and Terminal.app have no OSC52 support.
+	if term == "linux" || term == "dumb" || program == "Apple_Terminal" {
+		return None;
+	}
+	// Multiplexers keep the outer terminal's variables but have limits of
+	// their own.
+	let multiplexed = var("TMUX").is_some() || var("STY").is_some();
+	let large = term == "xterm-kitty"
+		|| term == "foot"
+		|| term.starts_with("foot-")
+		|| program == "WezTerm"
+		|| var("KITTY_WINDOW_ID").is_some();
+	if large && !multiplexed {
+		return Some(LARGE_OSC52_PAYLOAD);
+	}
+	Some(MAX_OSC52_PAYLOAD)
+}
+
+/// Chunk size for OSC52 writes, from `FRZ_OSC52_CHUNK_SIZE` or the default.
+fn chunk_size() -> usize {
+	static OVERRIDE: OnceLock<usize> = OnceLock::new();
+	*OVERRIDE.get_or_init(|| {
+		std::env::var("FRZ_OSC52_CHUNK_SIZE")
+			.ok()
+			.and_then(|v| v.parse::<usize>().ok())
+			.map(|v| v.max(MIN_CHUNK_SIZE))
+			.unwrap_or(DEFAULT_CHUNK_SIZE)
+	})
+}
+
 fn try_osc52_copy(text: &str) -> bool {
+	let Some(max_payload) = osc52_payload_limit(|name| std::env::var(name).ok()) else {
+		return false;
+	};
+	let Some(chunks) = osc52_chunks(text, Passthrough::detect(), chunk_size(), max_payload) else {
+		return false;
+	};
+	let mut stdout = std::io::stdout().lock();
+	chunks
+		.iter()
+		.all(|chunk| stdout.write_all(chunk.as_bytes()).is_ok() && stdout.flush().is_ok())
+}
+
+/// The writes setting the clipboard to `text` over OSC52, each at most
+/// `max_chunk` bytes long.
+///
+/// Together the chunks form one OSC52 sequence, wrapped for `passthrough`;
+/// behind screen every chunk is a device control string of its own.
+/// Returns `None` when the base64 payload is longer than `max_payload`.
+fn osc52_chunks(
+	text: &str,
+	passthrough: Passthrough,
+	max_chunk: usize,
+	max_payload: usize,
+) -> Option<Vec<String>> {
 	use base64::Engine;
 	let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+	if encoded.len() > max_payload {
+		return None;
+	}
 
-	let osc52 = if std::env::var("TMUX").is_ok() {
-		format!("\x1bPtmux;\x1b\x1b]52;c;{}\x07\x1b\\", encoded)
-	} else {
-		format!("\x1b]52;c;{}\x07", encoded)
+	let osc52 = format!("\x1b]52;c;{encoded}\x07");
+	let chunks = match passthrough {
+		Passthrough::None => split_ascii(&osc52, max_chunk),
+		Passthrough::Tmux => split_ascii(
+			&format!("\x1bPtmux;{}\x1b\\", osc52.replace('\x1b', "\x1b\x1b")),
+			max_chunk,
+		),
+		Passthrough::Screen => {
+			let wrapper = "\x1bP\x1b\\".len();
+			let size = max_chunk
+				.min(SCREEN_CHUNK_SIZE)
+				.saturating_sub(wrapper)
+				.max(1);
+			split_ascii(&osc52, size)
+				.into_iter()
+				.map(|chunk| format!("\x1bP{chunk}\x1b\\"))
+				.collect()
+		}
 	};
+	Some(chunks)
+}
 
-	let mut stdout = std::io::stdout().lock();
-	stdout.write_all(osc52.as_bytes()).is_ok() && stdout.flush().is_ok()
+/// Split ASCII `text` into pieces of at most `size` bytes.
+fn split_ascii(text: &str, size: usize) -> Vec<String> {
+	text.as_bytes()
+		.chunks(size.max(1))
+		.map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+		.collect()
 }
 
 fn try_native_clipboard(text: &str) -> Result<(), String> {
@@ -66,3 +195,117 @@ fn try_native_clipboard(text: &str) -> Result<(), String> {
 
 	Err("No clipboard tool available".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+	use base64::Engine;
+
+	use super::*;
+
+	/// The text a complete OSC52 sequence sets the clipboard to.
+	fn decode(osc52: &str) -> String {
+		let payload = osc52
+			.strip_prefix("\x1b]52;c;")
+			.and_then(|rest| rest.strip_suffix('\x07'))
+			.expect("well-formed OSC52 sequence");
+		let bytes = base64::engine::general_purpose::STANDARD
+			.decode(payload)
+			.expect("valid base64");
+		String::from_utf8(bytes).expect("utf-8")
+	}
+
+	fn large_text() -> String {
+		(0..2_000)
+			.map(|i| format!("line {i} — ünïcode\n"))
+			.collect()
+	}
+
+	#[test]
+	fn large_copies_are_written_in_bounded_chunks() {
+		let text = large_text();
+		let chunks = osc52_chunks(&text, Passthrough::None, 1024, MAX_OSC52_PAYLOAD).expect("fits");
+		assert!(chunks.len() > 1);
+		assert!(chunks.iter().all(|chunk| chunk.len() <= 1024));
+		assert_eq!(decode(&chunks.concat()), text);
+
+		let small =
+			osc52_chunks("src/main.rs", Passthrough::None, 1024, MAX_OSC52_PAYLOAD).expect("fits");
+		assert_eq!(small.len(), 1);
+		assert_eq!(decode(&small[0]), "src/main.rs");
+	}
+
+	#[test]
+	fn screen_chunks_are_separate_device_control_strings() {
+		let text = large_text();
+		let chunks = osc52_chunks(
+			&text,
+			Passthrough::Screen,
+			DEFAULT_CHUNK_SIZE,
+			MAX_OSC52_PAYLOAD,
+		)
+		.expect("fits");
+		assert!(chunks.len() > 1);
+		let mut osc52 = String::new();
+		for chunk in &chunks {
+			assert!(chunk.len() <= SCREEN_CHUNK_SIZE, "{}", chunk.len());
+			let inner = chunk
+				.strip_prefix("\x1bP")
+				.and_then(|rest| rest.strip_suffix("\x1b\\"))
+				.expect("wrapped chunk");
+			osc52.push_str(inner);
+		}
+		assert_eq!(decode(&osc52), text);
+	}
+
+	#[test]
+	fn tmux_chunks_reassemble_into_one_passthrough_sequence() {
+		let chunks = osc52_chunks("a.rs", Passthrough::Tmux, MIN_CHUNK_SIZE, MAX_OSC52_PAYLOAD)
+			.expect("fits");
+		assert!(chunks.len() > 1);
+		let sequence = chunks.concat();
+		let inner = sequence
+			.strip_prefix("\x1bPtmux;")
+			.and_then(|rest| rest.strip_suffix("\x1b\\"))
+			.expect("tmux passthrough");
+		assert_eq!(decode(&inner.replace("\x1b\x1b", "\x1b")), "a.rs");
+	}
+
+	#[test]
+	fn oversized_copies_fall_back_to_native_tools() {
+		let text = "x".repeat(MAX_OSC52_PAYLOAD);
+		let chunks =
+			|max_payload| osc52_chunks(&text, Passthrough::None, DEFAULT_CHUNK_SIZE, max_payload);
+		assert!(chunks(MAX_OSC52_PAYLOAD).is_none());
+		assert!(chunks(LARGE_OSC52_PAYLOAD).is_some());
+	}
+
+	#[test]
+	fn payload_limits_follow_the_terminal() {
+		let limit = |vars: &[(&str, &str)]| {
+			osc52_payload_limit(|name| {
+				vars.iter()
+					.find(|(var, _)| *var == name)
+					.map(|(_, value)| (*value).to_owned())
+			})
+		};
+		assert_eq!(
+			limit(&[("TERM", "xterm-256color")]),
+			Some(MAX_OSC52_PAYLOAD)
+		);
+		assert_eq!(limit(&[("TERM", "xterm-kitty")]), Some(LARGE_OSC52_PAYLOAD));
+		assert_eq!(
+			limit(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]),
+			Some(LARGE_OSC52_PAYLOAD)
+		);
+		assert_eq!(
+			limit(&[
+				("TERM", "tmux-256color"),
+				("TMUX", "/tmp/tmux"),
+				("KITTY_WINDOW_ID", "1")
+			]),
+			Some(MAX_OSC52_PAYLOAD)
+		);
+		assert_eq!(limit(&[("TERM", "linux")]), None);
+		assert_eq!(limit(&[("TERM_PROGRAM", "Apple_Terminal")]), None);
+	}
+}
@@ -5,11 +5,13 @@ use super::gutter::compute_gutter_context;
 use super::highlight::selection_bounds_for_line;
 use super::state::TextSelection;
 
-/// Extract plain text from selected lines.
+/// Extract plain text from selected lines, leaving out the first
+/// `gutter_width` columns of each.
 pub fn extract_selected_text(
 	lines: &[Line<'static>],
 	selection: &TextSelection,
 	area: Rect,
+	gutter_width: usize,
 ) -> Option<String> {
 	if !selection.has_selection() {
 		return None;
@@ -17,13 +19,11 @@ pub fn extract_selected_text(
 
 	let (start, end) = selection.normalized_bounds(area)?;
 	let mut result = String::new();
-	let mut prev_gutter_width = 0usize;
 
 	for (line_idx, line) in lines.iter().enumerate() {
 		let content_row = line_idx as u16;
-		let gutter_ctx = compute_gutter_context(line, prev_gutter_width);
+		let gutter_ctx = compute_gutter_context(line, gutter_width);
 		let is_continuation = gutter_ctx.is_continuation;
-		prev_gutter_width = gutter_ctx.next_prev_gutter;
 
 		if content_row < start.1 || content_row > end.1 {
 			continue;
@@ -16,12 +16,14 @@ pub fn selection_style(theme: &Theme) -> Style {
 }
 
 /// Apply selection highlighting to lines for rendering.
-/// Takes wrapped lines and returns new lines with selection styling applied.
+/// Takes wrapped lines and returns new lines with selection styling applied
+/// past their first `gutter_width` columns.
 pub fn apply_selection_to_lines(
 	lines: &[Line<'static>],
 	selection: &TextSelection,
 	area: Rect,
 	theme: &Theme,
+	gutter_width: usize,
 ) -> Vec<Line<'static>> {
 	if !selection.has_selection() {
 		return lines.to_vec();
@@ -32,15 +34,13 @@ pub fn apply_selection_to_lines(
 	};
 
 	let sel_style = selection_style(theme);
-	let mut prev_gutter_width = 0usize;
 
 	lines
 		.iter()
 		.enumerate()
 		.map(|(line_idx, line)| {
 			let content_row = line_idx as u16;
-			let gutter_ctx = compute_gutter_context(line, prev_gutter_width);
-			prev_gutter_width = gutter_ctx.next_prev_gutter;
+			let gutter_ctx = compute_gutter_context(line, gutter_width);
 
 			if content_row < start.1 || content_row > end.1 {
 				return line.clone();
@@ -59,7 +59,7 @@ pub fn apply_selection_to_lines(
 }
 
 /// Apply selection styling to a single line.
-fn apply_selection_to_line(
+pub(crate) fn apply_selection_to_line(
 	line: &Line<'static>,
 	sel_start: usize,
 	sel_end: usize,
@@ -93,7 +93,7 @@ fn gutter_is_not_selected_on_full_line() {
 	];
 
 	let theme = test_theme();
-	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme);
+	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme, 5);
 	let sel_style = selection_style(&theme);
 	for line in highlighted {
 		let first_span = line.spans.first().expect("gutter span");
@@ -116,7 +116,7 @@ fn gutter_without_separator_not_selected() {
 	let lines = vec![Line::from(" 12  hello world")];
 
 	let theme = test_theme();
-	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme);
+	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme, 5);
 	let sel_style = selection_style(&theme);
 	let first_span = highlighted[0].spans.first().expect("gutter span");
 	assert_ne!(first_span.style, sel_style, "gutter should not be selected");
@@ -139,7 +139,7 @@ fn selection_respects_scroll_offset() {
 		.collect::<Vec<_>>();
 
 	let theme = test_theme();
-	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme);
+	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme, 0);
 	let sel_style = selection_style(&theme);
 
 	for (idx, line) in highlighted.iter().enumerate() {
@@ -166,10 +166,32 @@ fn selection_preserves_indentation() {
 	let area = Rect::new(0, 0, 40, 5);
 	let lines = vec![Line::from("    let x = 1;")];
 
-	let text = extract_selected_text(&lines, &selection, area).expect("text");
+	let text = extract_selected_text(&lines, &selection, area, 0).expect("text");
 	assert!(text.starts_with("    "), "indentation should be preserved");
 }
 
+#[test]
+fn leading_digits_are_copied_without_a_gutter() {
+	let selection = TextSelection {
+		anchor: Some((0, 0)),
+		focus: Some((20, 1)),
+		anchor_scroll: 0,
+		focus_scroll: 0,
+		selecting: false,
+		active: true,
+	};
+
+	let area = Rect::new(0, 0, 40, 5);
+	let lines = vec![Line::from("2024 was a year"), Line::from("   42 answers")];
+
+	let text = extract_selected_text(&lines, &selection, area, 0).expect("text");
+	assert_eq!(text, "2024 was a year\n   42 answers");
+
+	let numbered = vec![Line::from("   1 │ 2024 was a year")];
+	let text = extract_selected_text(&numbered, &selection, area, 7).expect("text");
+	assert_eq!(text, "2024 was a year");
+}
+
 #[test]
 fn highlighting_includes_indentation_without_separator() {
 	let selection = TextSelection {
@@ -184,7 +206,7 @@ fn highlighting_includes_indentation_without_separator() {
 	let area = Rect::new(0, 0, 40, 5);
 	let lines = vec![Line::from("12  \t  let x = 1;")];
 	let theme = test_theme();
-	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme);
+	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme, 3);
 	let sel_style = selection_style(&theme);
 
 	let selected_span = highlighted[0].spans.iter().find(|s| s.style == sel_style);
@@ -215,9 +237,9 @@ fn wrapped_continuation_does_not_insert_extra_newline() {
 	};
 
 	let area = Rect::new(0, 0, 40, 5);
-	let lines = vec![Line::from(" 1 │ hello "), Line::from("    world")];
+	let lines = vec![Line::from(" 1 │ hello "), Line::from("     world")];
 
-	let text = extract_selected_text(&lines, &selection, area).expect("text");
+	let text = extract_selected_text(&lines, &selection, area, 5).expect("text");
 	assert_eq!(text, "hello world", "wrapped lines should be unwrapped");
 }
 
@@ -233,9 +255,9 @@ fn continuation_highlight_skips_gutter() {
 	};
 
 	let area = Rect::new(0, 0, 40, 5);
-	let lines = vec![Line::from(" 1 │ hello "), Line::from("    world")];
+	let lines = vec![Line::from(" 1 │ hello "), Line::from("     world")];
 	let theme = test_theme();
-	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme);
+	let highlighted = apply_selection_to_lines(&lines, &selection, area, &theme, 5);
 	let sel_style = selection_style(&theme);
 
 	let first_span = highlighted[1].spans.first().expect("continuation span");
@@ -7,20 +7,30 @@
 //! display when revisiting files without re-reading from disk or re-highlighting.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 use std::thread;
+use std::time::SystemTime;
 
 use bat::assets::HighlightingAssets;
+use frz_core::filesystem::search::SearchSelection;
+#[cfg(feature = "media-preview")]
+use ratatui::text::Line;
 
+#[cfg(feature = "archive-preview")]
+use super::archive::{archive_format, list_archive};
+use super::blame::{blame_file, with_blame_gutter};
 use super::content::PreviewContent;
-use super::highlight::highlight_with_bat;
+use super::highlight::{highlight_with_bat, line_number_width};
 #[cfg(feature = "media-preview")]
-use super::image::ImagePreview;
+use super::image::{ImagePreview, has_graphics};
+use super::markdown::{is_markdown_file, render_markdown};
 #[cfg(feature = "media-preview")]
 use super::media::{MAX_PDF_SIZE, MediaType, detect_media_type, max_image_size};
 #[cfg(feature = "media-preview")]
-use super::pdf::PdfPreview;
+use super::pdf::{PdfPreview, extract_text, page_count_label};
+use super::provider::{PreviewProvider, SharedPreviewProvider};
+use crate::config::{DEFAULT_MAX_IMAGE_DIMENSION, PdfPreviewMode};
 
 /// Maximum number of previews to keep in the LRU cache.
 const CACHE_CAPACITY: usize = 32;
@@ -28,25 +38,51 @@ const CACHE_CAPACITY: usize = 32;
 /// Commands sent to the preview worker thread.
 pub enum PreviewCommand {
 	/// Request a preview for a file.
-	Generate {
-		/// Unique ID for this preview request (for deduplication).
-		id: u64,
-		/// Path to the file to preview.
-		path: PathBuf,
-		/// Optional bat theme name.
-		theme: Option<String>,
-		/// Maximum number of lines to render.
-		max_lines: usize,
-	},
+	Generate(Box<PreviewRequest>),
+	/// Forget every cached preview.
+	ClearCache,
 	/// Shut down the worker thread.
 	Shutdown,
 }
 
-/// Cache key combining path and theme for proper cache invalidation.
+/// A request for the preview of one selected result.
+pub struct PreviewRequest {
+	/// Unique ID for this preview request (for deduplication).
+	pub id: u64,
+	/// The result to preview.
+	pub selection: SearchSelection,
+	/// Directory relative file paths are resolved against.
+	pub root: Option<PathBuf>,
+	/// Optional bat theme name.
+	pub theme: Option<String>,
+	/// Maximum number of lines to render.
+	pub max_lines: usize,
+	/// Render markdown files as formatted text instead of highlighting them.
+	pub render_markdown: bool,
+	/// Show `git blame` beside highlighted text.
+	pub blame: bool,
+	/// Number the lines of highlighted text.
+	pub line_numbers: bool,
+	/// Whether PDFs are rendered as an image or shown as text.
+	pub pdf_mode: PdfPreviewMode,
+	/// Largest width or height decoded images are kept at.
+	pub max_image_dimension: u32,
+	/// Providers asked before the built-in file previews.
+	pub providers: Vec<SharedPreviewProvider>,
+}
+
+/// Cache key combining the path and modification time with the options that
+/// affect its preview, so files changed on disk are previewed again.
 #[derive(Clone, Hash, Eq, PartialEq)]
 struct CacheKey {
 	path: PathBuf,
+	modified: Option<SystemTime>,
 	theme: Option<String>,
+	render_markdown: bool,
+	blame: bool,
+	line_numbers: bool,
+	pdf_mode: PdfPreviewMode,
+	max_image_dimension: u32,
 }
 
 /// Simple LRU cache for preview content.
@@ -128,39 +164,54 @@ fn worker_loop(command_rx: Receiver<PreviewCommand>, result_tx: Sender<PreviewRe
 
 	while let Ok(command) = command_rx.recv() {
 		match command {
-			PreviewCommand::Generate {
-				id,
-				path,
-				theme,
-				max_lines,
-			} => {
+			PreviewCommand::Generate(request) => {
 				// Before doing any work, drain the channel to get the latest request.
-				let (final_id, final_path, final_theme, final_max_lines) =
-					drain_to_latest(&command_rx, id, path, theme, max_lines);
-
-				let cache_key = CacheKey {
-					path: final_path.clone(),
-					theme: final_theme.clone(),
+				let request = drain_to_latest(&command_rx, request, &mut cache);
+				let files = FilePreviewProvider {
+					request: &request,
+					assets: &assets,
 				};
-
-				// Check cache first
-				let content = if let Some(cached) = cache.get(&cache_key) {
-					cached
+				let path = files.path_for(&request.selection);
+
+				// Registered providers come first; their content is not cached
+				// since it need not change with the file on disk.
+				let provided = request
+					.providers
+					.iter()
+					.find_map(|provider| provider.content_for(&request.selection));
+				let content = if let Some(content) = provided {
+					PreviewContent {
+						path: path.display().to_string(),
+						..content
+					}
 				} else {
-					let generated = generate_preview_impl(
-						&final_path,
-						final_theme.as_deref(),
-						final_max_lines,
-						&assets,
-					);
-					cache.insert(cache_key, generated.clone());
-					generated
+					let cache_key = CacheKey {
+						path: path.clone(),
+						modified: std::fs::metadata(&path)
+							.and_then(|metadata| metadata.modified())
+							.ok(),
+						theme: request.theme.clone(),
+						render_markdown: request.render_markdown,
+						blame: request.blame,
+						line_numbers: request.line_numbers,
+						pdf_mode: request.pdf_mode,
+						max_image_dimension: request.max_image_dimension,
+					};
+					if let Some(cached) = cache.get(&cache_key) {
+						cached
+					} else {
+						let generated = files
+							.content_for(&request.selection)
+							.unwrap_or_else(PreviewContent::empty);
+						cache.insert(cache_key, generated.clone());
+						generated
+					}
 				};
 
 				// If the receiver is gone, just exit
 				if result_tx
 					.send(PreviewResult {
-						id: final_id,
+						id: request.id,
 						content,
 					})
 					.is_err()
@@ -168,6 +219,7 @@ fn worker_loop(command_rx: Receiver<PreviewCommand>, result_tx: Sender<PreviewRe
 					break;
 				}
 			}
+			PreviewCommand::ClearCache => cache = PreviewCache::new(CACHE_CAPACITY),
 			PreviewCommand::Shutdown => break,
 		}
 	}
@@ -179,26 +231,17 @@ fn worker_loop(command_rx: Receiver<PreviewCommand>, result_tx: Sender<PreviewRe
 /// avoiding expensive processing of files the user has already moved past.
 fn drain_to_latest(
 	rx: &Receiver<PreviewCommand>,
-	mut id: u64,
-	mut path: PathBuf,
-	mut theme: Option<String>,
-	mut max_lines: usize,
-) -> (u64, PathBuf, Option<String>, usize) {
+	mut request: Box<PreviewRequest>,
+	cache: &mut PreviewCache,
+) -> Box<PreviewRequest> {
 	// Non-blocking drain of any pending requests
 	loop {
 		match rx.try_recv() {
-			Ok(PreviewCommand::Generate {
-				id: new_id,
-				path: new_path,
-				theme: new_theme,
-				max_lines: new_max_lines,
-			}) => {
+			Ok(PreviewCommand::Generate(newer)) => {
 				// Found a newer request, use it instead
-				id = new_id;
-				path = new_path;
-				theme = new_theme;
-				max_lines = new_max_lines;
+				request = newer;
 			}
+			Ok(PreviewCommand::ClearCache) => *cache = PreviewCache::new(CACHE_CAPACITY),
 			Ok(PreviewCommand::Shutdown) => {
 				// Put shutdown back for the main loop to handle
 				// (We can't easily do this with mpsc, so just break)
@@ -209,7 +252,7 @@ fn drain_to_latest(
 			}
 		}
 	}
-	(id, path, theme, max_lines)
+	request
 }
 
 /// Maximum file size for text preview (in bytes). Larger files are skipped.
@@ -219,11 +262,35 @@ const MAX_PREVIEW_SIZE: u64 = 512 * 1024; // 512 KB
 #[cfg(feature = "media-preview")]
 const MAGIC_HEADER_SIZE: usize = 64;
 
+/// Built-in provider previewing the selected file from disk.
+struct FilePreviewProvider<'a> {
+	request: &'a PreviewRequest,
+	assets: &'a HighlightingAssets,
+}
+
+impl FilePreviewProvider<'_> {
+	/// Path of the selected file, resolved against the request root.
+	fn path_for(&self, selection: &SearchSelection) -> PathBuf {
+		let SearchSelection::File(file) = selection;
+		let path = file.os_path();
+		match &self.request.root {
+			Some(root) if path.is_relative() => root.join(path),
+			_ => path.to_path_buf(),
+		}
+	}
+}
+
+impl PreviewProvider for FilePreviewProvider<'_> {
+	fn content_for(&self, selection: &SearchSelection) -> Option<PreviewContent> {
+		let path = self.path_for(selection);
+		Some(generate_preview_impl(&path, self.request, self.assets))
+	}
+}
+
 /// Generate syntax-highlighted preview content for a file.
 fn generate_preview_impl(
-	path: &std::path::Path,
-	bat_theme: Option<&str>,
-	max_lines: usize,
+	path: &Path,
+	request: &PreviewRequest,
 	assets: &HighlightingAssets,
 ) -> PreviewContent {
 	let path_str = path.display().to_string();
@@ -251,6 +318,15 @@ fn generate_preview_impl(
 							&path_str,
 							format!("PDF too large ({} MB)", metadata.len() / (1024 * 1024)),
 						)
+					} else if pdf_as_text(request.pdf_mode) {
+						match extract_text(path, request.max_lines) {
+							Ok((lines, pages)) => PreviewContent::text(
+								&path_str,
+								lines.into_iter().map(Line::from).collect(),
+							)
+							.with_notice(page_count_label(pages)),
+							Err(e) => PreviewContent::error(&path_str, format!("PDF error: {}", e)),
+						}
 					} else {
 						match PdfPreview::load(path) {
 							Ok(pdf) => PreviewContent::pdf(&path_str, pdf),
@@ -270,9 +346,9 @@ fn generate_preview_impl(
 							),
 						)
 					} else {
-						match ImagePreview::load(path) {
-							Some(image) => PreviewContent::image(&path_str, image),
-							None => PreviewContent::error(&path_str, "Failed to load image"),
+						match ImagePreview::load(path, request.max_image_dimension) {
+							Ok(image) => PreviewContent::image(&path_str, image),
+							Err(message) => PreviewContent::error(&path_str, message),
 						}
 					}
 				}
@@ -280,6 +356,16 @@ fn generate_preview_impl(
 		}
 	}
 
+	// Archives are listed from their headers, so they skip the size limit.
+	// Files that only look like archives by name are previewed like any
+	// other file.
+	#[cfg(feature = "archive-preview")]
+	if let Some(format) = archive_format(path)
+		&& let Ok(lines) = list_archive(path, format, request.max_lines)
+	{
+		return PreviewContent::archive(&path_str, lines);
+	}
+
 	if metadata.len() > MAX_PREVIEW_SIZE {
 		return PreviewContent::error(
 			&path_str,
@@ -304,8 +390,47 @@ fn generate_preview_impl(
 		return PreviewContent::empty_file(&path_str);
 	}
 
-	let highlighted = highlight_with_bat(path, &content, bat_theme, max_lines, assets);
-	PreviewContent::text(&path_str, highlighted)
+	if request.render_markdown && is_markdown_file(path) {
+		return PreviewContent::markdown(&path_str, render_markdown(&content, request.max_lines));
+	}
+
+	let highlighted = highlight_with_bat(
+		path,
+		&content,
+		request.theme.as_deref(),
+		request.max_lines,
+		request.line_numbers,
+		assets,
+	);
+	let numbers = if request.line_numbers {
+		line_number_width(request.max_lines)
+	} else {
+		0
+	};
+	if !request.blame {
+		return PreviewContent::text(&path_str, highlighted).with_gutter_width(numbers);
+	}
+	match blame_file(path, content.lines().count().min(request.max_lines)) {
+		Ok(blame) => {
+			let (lines, blame_width) = with_blame_gutter(highlighted, &blame);
+			PreviewContent::text(&path_str, lines).with_gutter_width(blame_width + numbers)
+		}
+		Err(notice) => PreviewContent::text(&path_str, highlighted)
+			.with_gutter_width(numbers)
+			.with_notice(notice),
+	}
+}
+
+/// Whether PDFs are previewed as their text in `mode`: when asked to, or by
+/// default when the terminal cannot show the rendered page. Half blocks
+/// are too coarse to read a page in, so they count as no graphics.
+#[cfg(feature = "media-preview")]
+fn pdf_as_text(mode: PdfPreviewMode) -> bool {
+	match mode {
+		PdfPreviewMode::Auto => !has_graphics(),
+		PdfPreviewMode::Image => false,
+		PdfPreviewMode::Text => true,
+	}
 }
 
 /// Read the first N bytes of a file for magic byte detection.
@@ -331,6 +456,12 @@ pub struct PreviewRuntime {
 	rx: Receiver<PreviewResult>,
 	next_id: u64,
 	current_id: Option<u64>,
+	render_markdown: bool,
+	blame: bool,
+	line_numbers: bool,
+	pdf_mode: PdfPreviewMode,
+	max_image_dimension: u32,
+	providers: Vec<SharedPreviewProvider>,
 }
 
 impl PreviewRuntime {
@@ -342,24 +473,91 @@ impl PreviewRuntime {
 			rx,
 			next_id: 0,
 			current_id: None,
+			render_markdown: true,
+			blame: false,
+			line_numbers: true,
+			pdf_mode: PdfPreviewMode::default(),
+			max_image_dimension: DEFAULT_MAX_IMAGE_DIMENSION,
+			providers: Vec::new(),
 		}
 	}
 
-	/// Request a preview for a file. Returns the request ID.
-	pub fn request(&mut self, path: PathBuf, theme: Option<String>, max_lines: usize) -> u64 {
+	/// Request a preview for a selected result, resolving relative file
+	/// paths against `root`. Returns the request ID.
+	pub fn request(
+		&mut self,
+		selection: SearchSelection,
+		root: Option<PathBuf>,
+		theme: Option<String>,
+		max_lines: usize,
+	) -> u64 {
 		self.next_id = self.next_id.wrapping_add(1);
 		let id = self.next_id;
 		self.current_id = Some(id);
 
-		let _ = self.tx.send(PreviewCommand::Generate {
-			id,
-			path,
-			theme,
-			max_lines,
-		});
+		let _ = self
+			.tx
+			.send(PreviewCommand::Generate(Box::new(PreviewRequest {
+				id,
+				selection,
+				root,
+				theme,
+				max_lines,
+				render_markdown: self.render_markdown,
+				blame: self.blame,
+				line_numbers: self.line_numbers,
+				pdf_mode: self.pdf_mode,
+				max_image_dimension: self.max_image_dimension,
+				providers: self.providers.clone(),
+			})));
 		id
 	}
 
+	/// Choose whether markdown files are rendered as formatted text, the
+	/// default, or highlighted as source like other files.
+	pub fn set_render_markdown(&mut self, enabled: bool) {
+		self.render_markdown = enabled;
+	}
+
+	/// Whether markdown files are rendered as formatted text.
+	#[must_use]
+	pub fn render_markdown(&self) -> bool {
+		self.render_markdown
+	}
+
+	/// Choose whether highlighted text shows `git blame` in a gutter.
+	pub fn set_blame(&mut self, enabled: bool) {
+		self.blame = enabled;
+	}
+
+	/// Choose whether highlighted text starts each line with its number,
+	/// the default.
+	pub fn set_line_numbers(&mut self, enabled: bool) {
+		self.line_numbers = enabled;
+	}
+
+	/// Choose whether PDFs are rendered as an image or shown as text.
+	pub fn set_pdf_mode(&mut self, mode: PdfPreviewMode) {
+		self.pdf_mode = mode;
+	}
+
+	/// Choose the largest width or height decoded images are kept at.
+	pub fn set_max_image_dimension(&mut self, max: u32) {
+		self.max_image_dimension = max;
+	}
+
+	/// Whether highlighted text starts each line with its number.
+	#[must_use]
+	pub fn line_numbers(&self) -> bool {
+		self.line_numbers
+	}
+
+	/// Ask `provider` for previews before the built-in file previews and
+	/// after any provider added earlier.
+	pub fn add_provider(&mut self, provider: SharedPreviewProvider) {
+		self.providers.push(provider);
+	}
+
 	/// Try to receive a completed preview result.
 	pub fn try_recv(&self) -> Result<PreviewResult, TryRecvError> {
 		self.rx.try_recv()
@@ -370,6 +568,12 @@ impl PreviewRuntime {
 		self.current_id == Some(id)
 	}
 
+	/// Forget the previews cached by the worker, e.g. after the terminal's
+	/// graphics backend changed.
+	pub fn clear_cache(&self) {
+		let _ = self.tx.send(PreviewCommand::ClearCache);
+	}
+
 	/// Shut down the preview worker.
 	pub fn shutdown(&self) {
 		let _ = self.tx.send(PreviewCommand::Shutdown);
@@ -387,3 +591,44 @@ impl Drop for PreviewRuntime {
 		self.shutdown();
 	}
 }
+
+#[cfg(all(test, feature = "archive-preview"))]
+mod tests {
+	use frz_core::filesystem::search::FileRow;
+
+	use super::*;
+	use crate::components::preview::PreviewKind;
+
+	fn request_for(path: &Path) -> PreviewRequest {
+		PreviewRequest {
+			id: 0,
+			selection: SearchSelection::File(FileRow::from_path(path)),
+			root: None,
+			theme: None,
+			max_lines: 100,
+			render_markdown: false,
+			blame: false,
+			line_numbers: false,
+			pdf_mode: PdfPreviewMode::default(),
+			max_image_dimension: DEFAULT_MAX_IMAGE_DIMENSION,
+			providers: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn unreadable_archives_are_previewed_as_plain_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let assets = HighlightingAssets::from_binary();
+
+		let text = dir.path().join("notes.zip");
+		std::fs::write(&text, "not a zip\n").unwrap();
+		let content = generate_preview_impl(&text, &request_for(&text), &assets);
+		assert!(matches!(content.kind, PreviewKind::Text { .. }));
+		assert_eq!(content.lines().unwrap()[0].to_string(), "not a zip");
+
+		let binary = dir.path().join("broken.tar.gz");
+		std::fs::write(&binary, [0x1f, 0x8b, 0, 0, 0]).unwrap();
+		let content = generate_preview_impl(&binary, &request_for(&binary), &assets);
+		assert_eq!(content.error_message(), Some("Binary file"));
+	}
+}
@@ -3,10 +3,25 @@ use ratatui::text::{Line, Span};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-/// Soft-wrap highlighted lines while preserving a line number gutter and basic indentation.
+/// Leading columns of preview lines that hold line numbers or blame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Gutter {
+	/// Width in columns, zero when the lines have no gutter.
+	pub width: usize,
+	/// Style patched onto the gutter, such as the theme's muted color.
+	pub style: Style,
+}
+
+/// Soft-wrap highlighted lines while preserving their `gutter` and basic
+/// indentation.
+///
+/// The first `gutter.width` columns of each line are drawn in the gutter
+/// style and left blank on wrapped continuations, which also repeat the
+/// indent of the text after the gutter.
 pub fn wrap_highlighted_lines(
 	lines: &[Line<'static>],
 	available_width: usize,
+	gutter: Gutter,
 ) -> Vec<Line<'static>> {
 	if available_width == 0 {
 		return Vec::new();
@@ -15,38 +30,45 @@ pub fn wrap_highlighted_lines(
 	let mut wrapped = Vec::new();
 
 	for line in lines {
-		let (gutter, mut body, gutter_width) = split_gutter(line);
+		let (gutter_spans, mut body) = split_gutter(line, gutter.width);
+		let gutter_spans: Vec<Span<'static>> = gutter_spans
+			.into_iter()
+			.map(|span| span.patch_style(gutter.style))
+			.collect();
 
 		if body.is_empty() {
-			wrapped.push(Line::from(gutter.clone()));
+			wrapped.push(Line::from(gutter_spans));
 			continue;
 		}
 
 		// Keep space for the gutter; if it would consume the whole line, fall back to the original.
-		if gutter_width >= available_width {
+		if gutter.width >= available_width {
 			wrapped.push(line.clone());
 			continue;
 		}
 
-		let body_width = available_width - gutter_width;
-		let continuation_gutter = if gutter_width > 0 {
-			Span::raw(" ".repeat(gutter_width))
-		} else {
-			Span::raw(String::new())
-		};
-		let continuation_indent = leading_indent_width(&body);
+		let body_width = available_width - gutter.width;
+		let mut continuation_indent = leading_indent_width(&body);
+		if continuation_indent >= body_width {
+			continuation_indent = 0;
+		}
 
 		let mut first = true;
 		while !body.is_empty() {
-			let (chunk, rest) = take_spans_within_width(&body, body_width);
+			let width = if first {
+				body_width
+			} else {
+				body_width - continuation_indent
+			};
+			let (chunk, rest) = take_spans_within_width(&body, width);
 			body = rest;
 
 			let mut line_spans = if first {
-				gutter.clone()
+				gutter_spans.clone()
 			} else {
 				let mut cont = Vec::new();
-				if gutter_width > 0 {
-					cont.push(continuation_gutter.clone());
+				if gutter.width > 0 {
+					cont.push(Span::raw(" ".repeat(gutter.width)));
 				}
 				if continuation_indent > 0 {
 					cont.push(Span::raw(" ".repeat(continuation_indent)));
@@ -63,141 +85,42 @@ pub fn wrap_highlighted_lines(
 	wrapped
 }
 
-fn split_gutter(line: &Line<'static>) -> (Vec<Span<'static>>, Vec<Span<'static>>, usize) {
-	let mut gutter = Vec::new();
-	let mut body = Vec::new();
-	let mut gutter_width = 0;
-	let mut found = false;
-
-	for (i, span) in line.spans.iter().cloned().enumerate() {
-		if found {
-			body.push(span);
-			continue;
-		}
-
-		let mut before = String::new();
-		let mut after = String::new();
-		let mut local_width = 0;
-
-		let mut chars = span.content.chars().peekable();
-		while let Some(ch) = chars.next() {
-			let ch_width = ch.width().unwrap_or(0);
-			if ch == '│' {
-				found = true;
-				before.push(ch);
-				local_width += ch_width;
-
-				if chars.peek().copied() == Some(' ') {
-					chars.next();
-					before.push(' ');
-					local_width += 1;
-				}
-
-				for ch in chars {
-					after.push(ch);
-				}
-				break;
-			} else {
-				before.push(ch);
-				local_width += ch_width;
-			}
-		}
-
-		if found {
-			if !before.is_empty() {
-				gutter.push(Span::styled(before, span.style));
-			}
-			gutter_width += local_width;
-
-			if !after.is_empty() {
-				body.push(Span::styled(after, span.style));
-			}
-
-			body.extend(line.spans.iter().skip(i + 1).cloned());
-		} else {
-			gutter_width += local_width;
-			gutter.push(span);
-		}
-	}
-
-	if !found {
-		// No explicit separator; fall back to leading digits/spaces as a gutter.
-		let leading_chars = count_leading_gutter_chars(line);
-		if leading_chars == 0 {
-			gutter.clear();
-			gutter_width = 0;
-			body = line.spans.clone();
-		} else {
-			let (gutter_spans, body_spans) = split_spans_at_char(line, leading_chars);
-			gutter_width = gutter_spans
-				.iter()
-				.map(|s| {
-					s.content
-						.chars()
-						.map(|c| c.width().unwrap_or(0))
-						.sum::<usize>()
-				})
-				.sum();
-			gutter = gutter_spans;
-			body = body_spans;
-		}
-	}
-
-	(gutter, body, gutter_width)
-}
-
-fn count_leading_gutter_chars(line: &Line<'static>) -> usize {
-	let mut count = 0;
-	for span in &line.spans {
-		for ch in span.content.chars() {
-			if ch.is_ascii_digit() || ch == ' ' {
-				count += 1;
-			} else {
-				return count;
-			}
-		}
-	}
-	count
+/// Number of characters [`wrap_highlighted_lines`] pads the rows continuing
+/// `line` with: the gutter's width followed by the body's indent.
+pub(crate) fn continuation_prefix_len(line: &Line<'static>, gutter_width: usize) -> usize {
+	let (_, body) = split_gutter(line, gutter_width);
+	gutter_width + leading_indent_width(&body)
 }
 
-fn split_spans_at_char(
+/// Split `line` into the spans covering its first `width` columns and the
+/// spans of the text after them.
+pub(crate) fn split_gutter(
 	line: &Line<'static>,
-	char_count: usize,
+	width: usize,
 ) -> (Vec<Span<'static>>, Vec<Span<'static>>) {
+	if width == 0 {
+		return (Vec::new(), line.spans.clone());
+	}
+
 	let mut gutter = Vec::new();
 	let mut body = Vec::new();
-	let mut consumed = 0;
+	let mut used = 0;
 
 	for span in &line.spans {
-		if consumed >= char_count {
+		if used >= width {
 			body.push(span.clone());
 			continue;
 		}
 
-		let mut prefix = String::new();
-		let mut suffix = String::new();
-		let mut prefix_bytes = 0;
-
-		for (i, ch) in span.content.char_indices() {
-			if consumed < char_count {
-				prefix.push(ch);
-				consumed += 1;
-				prefix_bytes = i + ch.len_utf8();
-			} else {
-				suffix.push_str(&span.content[i..]);
-				break;
-			}
-		}
-
-		if consumed >= char_count && suffix.is_empty() && prefix_bytes < span.content.len() {
-			suffix.push_str(&span.content[prefix_bytes..]);
+		let (left, right) = split_text_at_width(&span.content, width - used);
+		used += left.width();
+		if !left.is_empty() {
+			gutter.push(Span::styled(left, span.style));
 		}
-
-		if !prefix.is_empty() {
-			gutter.push(Span::styled(prefix, span.style));
-		}
-		if !suffix.is_empty() {
-			body.push(Span::styled(suffix, span.style));
+		if !right.is_empty() {
+			body.push(Span::styled(right, span.style));
+			// A wide character straddling the gutter's edge ends it early.
+			used = width;
 		}
 	}
 
@@ -232,7 +155,14 @@ fn take_spans_within_width(
 						last_break_index
 					};
 
-					if let Some(break_index) = break_index.filter(|idx| *idx > 0) {
+					// Breaking after nothing but indentation would leave a
+					// blank row, so long words are split instead.
+					let has_text = |idx: &usize| {
+						taken_segments[..*idx]
+							.iter()
+							.any(|(text, _)| !text.trim().is_empty())
+					};
+					if let Some(break_index) = break_index.filter(has_text) {
 						let (head, tail) = taken_segments.split_at(break_index);
 						let mut rest_segments: Vec<(String, Style)> = tail.to_vec();
 						rest_segments.push((part.to_string(), span.style));
@@ -359,6 +289,7 @@ mod tests {
 	use super::*;
 	use crate::App;
 	use crate::components::PreviewContent;
+	use crate::components::preview::highlight::line_number_width;
 
 	#[test]
 	fn preview_wrap_respects_gutter_and_indent_snapshot() {
@@ -380,9 +311,11 @@ println!("still indented");
 			content,
 			None,
 			256,
+			true,
 			&assets,
 		);
-		app.preview.content = PreviewContent::text("wrap_example.rs", highlighted);
+		app.preview.content = PreviewContent::text("wrap_example.rs", highlighted)
+			.with_gutter_width(line_number_width(256));
 
 		let backend = TestBackend::new(80, 12);
 		let mut terminal = Terminal::new(backend).expect("terminal");
@@ -420,7 +353,7 @@ println!("still indented");
 
 			let width = preview
 				.chars()
-				.take_while(|ch| ch.is_ascii_digit() || *ch == ' ')
+				.take_while(|ch| ch.is_ascii_digit() || *ch == ' ' || *ch == '│')
 				.count();
 			if width > 0 {
 				return width;
@@ -509,10 +442,15 @@ println!("still indented");
 			content,
 			None,
 			16,
+			true,
 			&assets,
 		);
 
-		let wrapped = wrap_highlighted_lines(&highlighted, 32);
+		let gutter = Gutter {
+			width: line_number_width(16),
+			style: Style::new().fg(ratatui::style::Color::DarkGray),
+		};
+		let wrapped = wrap_highlighted_lines(&highlighted, 32, gutter);
 		assert!(wrapped.len() > 2);
 		for (idx, line) in wrapped.iter().enumerate() {
 			let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
@@ -522,17 +460,63 @@ println!("still indented");
 					prefix.contains('1'),
 					"first line should include line number gutter"
 				);
+				assert_eq!(line.spans[0].style, gutter.style);
 			} else {
 				assert_eq!(prefix, "       ", "continuation should keep gutter spacing");
 			}
 		}
 	}
 
+	#[test]
+	fn line_numbers_can_be_left_out() {
+		let assets = HighlightingAssets::from_binary();
+		let text = |line_numbers| {
+			crate::components::preview::highlight::highlight_with_bat(
+				Path::new("example.rs"),
+				"fn main() {}\n",
+				None,
+				16,
+				line_numbers,
+				&assets,
+			)
+			.iter()
+			.map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+			.collect::<Vec<String>>()
+		};
+
+		assert_eq!(text(true), ["   1 │ fn main() {}"]);
+		assert_eq!(text(false), ["fn main() {}"]);
+	}
+
+	#[test]
+	fn leading_digits_are_only_a_gutter_when_numbered() {
+		let line = Line::from("2024 was a year of many releases");
+		let rendered = |gutter: Gutter| {
+			wrap_highlighted_lines(std::slice::from_ref(&line), 16, gutter)
+				.iter()
+				.map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+				.collect::<Vec<String>>()
+		};
+
+		assert_eq!(
+			rendered(Gutter::default()),
+			["2024 was a year ", "of many releases"]
+		);
+		let gutter = Gutter {
+			width: 5,
+			style: Style::default(),
+		};
+		assert_eq!(
+			rendered(gutter),
+			["2024 was a year ", "     of many ", "     releases"]
+		);
+	}
+
 	#[test]
 	fn wraps_at_word_boundaries() {
 		let line = Line::from(vec![Span::raw("alpha beta gamma")]);
 
-		let wrapped = wrap_highlighted_lines(&[line], 10);
+		let wrapped = wrap_highlighted_lines(&[line], 10, Gutter::default());
 		let rendered: Vec<String> = wrapped
 			.iter()
 			.map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
@@ -547,7 +531,7 @@ println!("still indented");
 	fn splits_long_words_when_needed() {
 		let line = Line::from(vec![Span::raw("superlong")]);
 
-		let wrapped = wrap_highlighted_lines(&[line], 6);
+		let wrapped = wrap_highlighted_lines(&[line], 6, Gutter::default());
 		let rendered: Vec<String> = wrapped
 			.iter()
 			.map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
@@ -560,7 +544,7 @@ println!("still indented");
 	fn punctuation_counts_as_break_opportunity() {
 		let line = Line::from(vec![Span::raw("alpha.beta.gamma")]);
 
-		let wrapped = wrap_highlighted_lines(&[line], 10);
+		let wrapped = wrap_highlighted_lines(&[line], 10, Gutter::default());
 		let rendered: Vec<String> = wrapped
 			.iter()
 			.map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
@@ -1,7 +1,56 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
+use frz_core::filesystem::indexer::CacheRole;
 use frz_core::filesystem::search::SearchData;
 
+/// Format `value` with a comma between each group of three digits.
+pub(crate) fn format_count(value: usize) -> String {
+	let digits = value.to_string();
+	let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+	for (index, digit) in digits.chars().enumerate() {
+		if index > 0 && (digits.len() - index).is_multiple_of(3) {
+			formatted.push(',');
+		}
+		formatted.push(digit);
+	}
+	formatted
+}
+
+/// Footer text such as `123/4,567 matches • 12 ms`: the matches kept for the
+/// query out of every row searched, with a `+` when more matched than were
+/// kept, and how long the query took to score once it finished.
+pub(crate) fn format_match_footer(
+	matched: usize,
+	total: usize,
+	capped: bool,
+	elapsed: Option<Duration>,
+) -> String {
+	let counts = format!(
+		"{}{}/{} matches",
+		format_count(matched),
+		if capped { "+" } else { "" },
+		format_count(total)
+	);
+	match elapsed {
+		Some(elapsed) => format!("{counts} • {}", format_elapsed(elapsed)),
+		None => counts,
+	}
+}
+
+/// `elapsed` in milliseconds, with a decimal below 10 ms, or in seconds from
+/// one second up.
+pub(crate) fn format_elapsed(elapsed: Duration) -> String {
+	let millis = elapsed.as_secs_f64() * 1000.0;
+	if millis < 10.0 {
+		format!("{millis:.1} ms")
+	} else if millis < 1000.0 {
+		format!("{millis:.0} ms")
+	} else {
+		format!("{:.2} s", elapsed.as_secs_f64())
+	}
+}
+
 #[derive(Default, Clone, Debug)]
 struct ProgressEntry {
 	indexed: usize,
@@ -58,6 +107,8 @@ pub struct IndexProgress {
 	entries: HashMap<&'static str, ProgressEntry>,
 	order: Vec<&'static str>,
 	complete: bool,
+	skipped_duplicates: usize,
+	cache_role: CacheRole,
 }
 
 impl IndexProgress {
@@ -103,11 +154,33 @@ impl IndexProgress {
 		self.update_completion();
 	}
 
+	/// Record how many entries were left out as duplicates of indexed files.
+	pub fn record_skipped_duplicates(&mut self, count: usize) {
+		self.skipped_duplicates = count;
+	}
+
+	/// Record how this instance uses the index cache of the root.
+	pub fn record_cache_role(&mut self, role: CacheRole) {
+		self.cache_role = role;
+	}
+
+	/// How this instance uses the index cache of the root.
+	#[must_use]
+	pub fn cache_role(&self) -> CacheRole {
+		self.cache_role
+	}
+
 	/// Mark indexing as complete regardless of recorded totals.
 	pub fn mark_complete(&mut self) {
 		self.complete = true;
 	}
 
+	/// Whether indexing has finished.
+	#[must_use]
+	pub fn is_complete(&self) -> bool {
+		self.complete
+	}
+
 	/// Return a formatted status label and a completion flag suitable for the UI.
 	#[must_use]
 	pub fn status(&self, labels: &[(&str, String)]) -> (String, bool) {
@@ -129,6 +202,12 @@ impl IndexProgress {
 				.unwrap_or(*key);
 			segments.push(format!("Indexed {}: {}", label, entry.format()));
 		}
+		if self.skipped_duplicates > 0 {
+			segments.push(format!(
+				"{} duplicates skipped",
+				format_count(self.skipped_duplicates)
+			));
+		}
 		let status = segments.join(" • ");
 		(status, self.complete)
 	}
@@ -180,3 +259,25 @@ impl std::fmt::Display for ProgressDisplay {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn match_footer_shows_counts_and_scoring_time() {
+		assert_eq!(
+			format_match_footer(123, 4567, false, Some(Duration::from_millis(12))),
+			"123/4,567 matches • 12 ms"
+		);
+		assert_eq!(
+			format_match_footer(1000, 250_000, true, Some(Duration::from_micros(2340))),
+			"1,000+/250,000 matches • 2.3 ms"
+		);
+		assert_eq!(
+			format_match_footer(0, 10, false, Some(Duration::from_millis(1500))),
+			"0/10 matches • 1.50 s"
+		);
+		assert_eq!(format_match_footer(3, 3, false, None), "3/3 matches");
+	}
+}
@@ -1,28 +1,64 @@
-use frizbee::{Config, match_indices};
-use frz_core::filesystem::search::FileRow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use frizbee::Config;
+use frz_core::filesystem::search::{FileRow, Query};
 use ratatui::style::Style;
+use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{Cell, Row};
 
-use crate::highlight::highlight_cell_with_prefix;
+use crate::highlight::highlight_spans;
+
+/// Glyph shown before the path of a row marked for a multi-selection.
+pub const MARK_SYMBOL: &str = "● ";
 
-/// Create match indices for the provided needle and configuration.
+/// Create match indices for the provided query and configuration.
+///
+/// Fuzzy terms are highlighted at frizbee's match positions, literal terms
+/// (`'exact`, `^prefix`, `suffix$`) at the text they matched, and `|` groups
+/// at whichever alternative matched best.
 #[must_use]
-pub fn highlight_for_refs(needle: &str, config: &Config, text: &str) -> Option<Vec<usize>> {
-	if text.is_empty() || needle.is_empty() {
+pub fn highlight_for_refs(query: &Query, config: &Config, text: &str) -> Option<Vec<usize>> {
+	if text.is_empty() || query.is_empty() {
 		return None;
 	}
-	match_indices(needle, text, config).map(|m| m.indices)
+
+	let indices = query.match_indices(text, config);
+	(!indices.is_empty()).then_some(indices)
+}
+
+/// Format a byte count using binary units, e.g. `1.5 MiB`.
+#[must_use]
+pub fn format_size(bytes: u64) -> String {
+	const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+	if bytes < 1024 {
+		return format!("{bytes} B");
+	}
+	let mut value = bytes as f64 / 1024.0;
+	let mut unit = 0;
+	while value >= 1024.0 && unit + 1 < UNITS.len() {
+		value /= 1024.0;
+		unit += 1;
+	}
+	format!("{value:.1} {}", UNITS[unit])
 }
 
 /// Build table rows for the filtered file results.
+///
+/// When `show_sizes` is set a size column is rendered between the path and
+/// score columns; files without a recorded size show an empty cell.
+/// Directories are rendered with a trailing `/`. Tags are left out; see
+/// [`RowLayout::show_tags`].
 #[must_use]
 pub fn build_file_rows<'a>(
 	filtered_files: &'a [usize],
 	file_scores: &'a [u16],
 	files: &'a [FileRow],
-	highlight_state: Option<(&'a str, Config)>,
+	highlight_state: Option<(&'a Query, Config)>,
 	highlight_style: Style,
 	column_widths: Option<&[u16]>,
+	show_sizes: bool,
 ) -> Vec<Row<'a>> {
 	filtered_files
 		.iter()
@@ -30,24 +66,662 @@ pub fn build_file_rows<'a>(
 		.filter_map(|(idx, &actual_index)| {
 			let entry = files.get(actual_index)?;
 			let score = file_scores.get(idx).copied().unwrap_or_default();
-			let path_highlight = highlight_state
-				.as_ref()
-				.and_then(|(needle, config)| highlight_for_refs(needle, config, &entry.path));
-			// Leave one column of slack so we don't rely on the table drawing right up to the edge.
-			let path_width = column_widths
-				.and_then(|widths| widths.first().copied())
-				.map(|w| w.saturating_sub(1));
-			Some(Row::new([
-				highlight_cell_with_prefix(
-					&entry.path,
-					path_highlight,
-					path_width,
-					entry.truncation_style(),
-					highlight_style,
-					None,
-				),
-				Cell::from(score.to_string()),
-			]))
+			let layout = RowLayout {
+				highlight_style,
+				column_widths,
+				show_sizes,
+				show_tags: false,
+				marked: None,
+				positions: None,
+			};
+			let cells = file_row_cells(
+				entry,
+				actual_index,
+				score,
+				false,
+				highlight_state.as_ref(),
+				&layout,
+			);
+			Some(Row::new(
+				cells
+					.into_iter()
+					.map(|spans| Cell::from(Text::from(Line::from(spans)))),
+			))
 		})
 		.collect()
 }
+
+/// Presentation settings shared by every row of a frame.
+#[derive(Debug, Clone, Copy)]
+pub struct RowLayout<'a> {
+	/// Style applied to matched characters.
+	pub highlight_style: Style,
+	/// Resolved width of each column, if known.
+	pub column_widths: Option<&'a [u16]>,
+	/// Render a size column between the path and score columns.
+	pub show_sizes: bool,
+	/// Render a column with the row's tags before the score column. Tags a
+	/// `tag:` filter of the highlight query asks for get the highlight
+	/// style.
+	pub show_tags: bool,
+	/// [Stable ids](FileRow::stable_id) of rows rendered with
+	/// [`MARK_SYMBOL`].
+	pub marked: Option<&'a HashSet<u64>>,
+	/// Match positions computed while scoring, keyed by dataset index; see
+	/// [`MatchBatch::positions`].
+	///
+	/// Rows with positions are highlighted at them instead of at positions
+	/// recomputed from the highlight query, which can disagree with the
+	/// scorer.
+	///
+	/// [`MatchBatch::positions`]: frz_core::filesystem::search::MatchBatch::positions
+	pub positions: Option<&'a HashMap<usize, Vec<usize>>>,
+}
+
+/// Styled spans for each cell of a file row.
+type RowCells = Vec<Vec<Span<'static>>>;
+
+/// Row content built for a previous frame, reused while its inputs are
+/// unchanged.
+///
+/// Entries are keyed by dataset index, score and mark, and rebuilt when the
+/// row's precomputed match positions change. Everything else that shapes
+/// a row (the highlight query, resolved column widths, highlight style and
+/// the size column) is shared by all rows of a frame, so a change to any of
+/// it drops the whole cache instead. Cached rows are handed out as spans
+/// borrowing the cached text, so redrawing an unchanged result set does not
+/// copy any strings.
+#[derive(Debug, Default)]
+pub struct RowCache {
+	context: Option<RowContext>,
+	rows: HashMap<RowKey, CachedRow>,
+}
+
+#[derive(Debug)]
+struct CachedRow {
+	/// Precomputed match positions the row was highlighted with.
+	positions: Option<Vec<usize>>,
+	cells: RowCells,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RowContext {
+	query: Option<u64>,
+	max_typos: Option<u16>,
+	column_widths: Option<Vec<u16>>,
+	highlight_style: Style,
+	show_sizes: bool,
+	show_tags: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RowKey {
+	index: usize,
+	score: u16,
+	marked: bool,
+}
+
+impl RowCache {
+	/// Drop every cached row, e.g. after the dataset was replaced.
+	pub fn clear(&mut self) {
+		self.context = None;
+		self.rows.clear();
+	}
+
+	/// Number of rows currently cached.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// Whether no rows are cached.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.rows.is_empty()
+	}
+
+	/// Build table rows like [`build_file_rows`], reusing rows cached by
+	/// earlier calls whose inputs have not changed.
+	#[must_use]
+	pub fn build_file_rows(
+		&mut self,
+		filtered_files: &[usize],
+		file_scores: &[u16],
+		files: &[FileRow],
+		highlight_state: Option<(&Query, Config)>,
+		layout: RowLayout<'_>,
+	) -> Vec<Row<'_>> {
+		let context = RowContext {
+			query: highlight_state.as_ref().map(|(query, _)| hash_query(query)),
+			max_typos: highlight_state
+				.as_ref()
+				.and_then(|(_, config)| config.max_typos),
+			column_widths: layout.column_widths.map(<[u16]>::to_vec),
+			highlight_style: layout.highlight_style,
+			show_sizes: layout.show_sizes,
+			show_tags: layout.show_tags,
+		};
+		if self.context.as_ref() != Some(&context) {
+			self.rows.clear();
+			self.context = Some(context);
+		}
+
+		let is_marked = |file: &FileRow| {
+			layout
+				.marked
+				.is_some_and(|marked| marked.contains(&file.stable_id()))
+		};
+		for (idx, &actual_index) in filtered_files.iter().enumerate() {
+			let Some(entry) = files.get(actual_index) else {
+				continue;
+			};
+			let score = file_scores.get(idx).copied().unwrap_or_default();
+			let key = RowKey {
+				index: actual_index,
+				score,
+				marked: is_marked(entry),
+			};
+			let positions = layout
+				.positions
+				.and_then(|positions| positions.get(&actual_index));
+			if self
+				.rows
+				.get(&key)
+				.is_some_and(|cached| cached.positions.as_ref() == positions)
+			{
+				continue;
+			}
+			let cells = file_row_cells(
+				entry,
+				actual_index,
+				score,
+				key.marked,
+				highlight_state.as_ref(),
+				&layout,
+			);
+			self.rows.insert(
+				key,
+				CachedRow {
+					positions: positions.cloned(),
+					cells,
+				},
+			);
+		}
+
+		filtered_files
+			.iter()
+			.enumerate()
+			.filter_map(|(idx, &actual_index)| {
+				let key = RowKey {
+					index: actual_index,
+					score: file_scores.get(idx).copied().unwrap_or_default(),
+					marked: files.get(actual_index).is_some_and(is_marked),
+				};
+				self.rows
+					.get(&key)
+					.map(|cached| borrowed_row(&cached.cells))
+			})
+			.collect()
+	}
+}
+
+fn hash_query(query: &Query) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	query.hash(&mut hasher);
+	hasher.finish()
+}
+
+fn file_row_cells(
+	entry: &FileRow,
+	index: usize,
+	score: u16,
+	marked: bool,
+	highlight_state: Option<&(&Query, Config)>,
+	layout: &RowLayout<'_>,
+) -> RowCells {
+	let path_highlight = match layout.positions.and_then(|positions| positions.get(&index)) {
+		Some(positions) => (!positions.is_empty()).then(|| positions.clone()),
+		None => highlight_state
+			.and_then(|(query, config)| highlight_for_refs(query, config, &entry.path)),
+	};
+	// Leave one column of slack so we don't rely on the table drawing right up to the edge.
+	let path_width = layout
+		.column_widths
+		.and_then(|widths| widths.first().copied())
+		.map(|w| w.saturating_sub(1))
+		.map(|w| {
+			if marked {
+				w.saturating_sub(MARK_SYMBOL.chars().count() as u16)
+			} else {
+				w
+			}
+		});
+	let display = if entry.is_dir() {
+		format!("{}/", entry.path)
+	} else {
+		entry.path.clone()
+	};
+	let mut path = highlight_spans(
+		&display,
+		path_highlight,
+		path_width,
+		entry.truncation_style(),
+		layout.highlight_style,
+	);
+	if marked {
+		path.insert(0, Span::styled(MARK_SYMBOL, layout.highlight_style));
+	}
+	let mut cells = vec![path];
+	if layout.show_sizes {
+		cells.push(
+			entry
+				.size
+				.map(format_size)
+				.map(Span::raw)
+				.into_iter()
+				.collect(),
+		);
+	}
+	if layout.show_tags {
+		let query = highlight_state.map(|(query, _)| query);
+		cells.push(tag_spans(
+			&entry.tags,
+			query.copied(),
+			layout.highlight_style,
+		));
+	}
+	cells.push(vec![Span::raw(score.to_string())]);
+	cells
+}
+
+/// Spans listing `tags` separated by commas, with the tags a `tag:` filter
+/// of `query` asks for in `highlight_style`.
+fn tag_spans(tags: &[String], query: Option<&Query>, highlight_style: Style) -> Vec<Span<'static>> {
+	let filters = query.map_or(&[][..], Query::filters);
+	let mut spans = Vec::with_capacity(tags.len() * 2);
+	for (position, tag) in tags.iter().enumerate() {
+		if position > 0 {
+			spans.push(Span::raw(", "));
+		}
+		if filters.iter().any(|filter| filter.matches_tag(tag)) {
+			spans.push(Span::styled(tag.clone(), highlight_style));
+		} else {
+			spans.push(Span::raw(tag.clone()));
+		}
+	}
+	spans
+}
+
+fn borrowed_row(cells: &RowCells) -> Row<'_> {
+	Row::new(cells.iter().map(|spans| {
+		let spans: Vec<Span<'_>> = spans
+			.iter()
+			.map(|span| Span::styled(span.content.as_ref(), span.style))
+			.collect();
+		Cell::from(Text::from(Line::from(spans)))
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use frz_core::{EntryKind, TruncationStyle};
+	use ratatui::Terminal;
+	use ratatui::backend::TestBackend;
+	use ratatui::buffer::Buffer;
+	use ratatui::layout::Constraint;
+	use ratatui::style::{Modifier, Stylize};
+	use ratatui::widgets::Table;
+
+	use super::*;
+
+	fn sample_files() -> Vec<FileRow> {
+		vec![
+			FileRow::new("src/lib.rs").with_size(Some(2048)),
+			FileRow::new("src/components/rows.rs"),
+			FileRow::new("a/very/long/directory/structure/with/a/deeply/nested/file.rs"),
+		]
+	}
+
+	fn render(rows: Vec<Row<'_>>) -> Buffer {
+		let mut terminal = Terminal::new(TestBackend::new(40, 3)).expect("terminal");
+		terminal
+			.draw(|frame| {
+				let table = Table::new(
+					rows,
+					[
+						Constraint::Length(24),
+						Constraint::Length(8),
+						Constraint::Length(6),
+					],
+				);
+				frame.render_widget(table, frame.area());
+			})
+			.expect("draw rows");
+		terminal.backend().buffer().clone()
+	}
+
+	#[test]
+	fn formats_sizes_with_binary_units() {
+		assert_eq!(format_size(0), "0 B");
+		assert_eq!(format_size(1023), "1023 B");
+		assert_eq!(format_size(1024), "1.0 KiB");
+		assert_eq!(format_size(1536), "1.5 KiB");
+		assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+	}
+
+	#[test]
+	fn cached_rows_render_like_fresh_rows() {
+		let files = sample_files();
+		let query = Query::parse("rs");
+		let config = Config::default();
+		let widths = [24, 8, 6];
+		let style = Style::default().bold();
+
+		let fresh = render(build_file_rows(
+			&[2, 0, 1],
+			&[30, 20, 10],
+			&files,
+			Some((&query, config.clone())),
+			style,
+			Some(&widths),
+			true,
+		));
+
+		let mut cache = RowCache::default();
+		let layout = RowLayout {
+			highlight_style: style,
+			column_widths: Some(&widths),
+			show_sizes: true,
+			show_tags: false,
+			marked: None,
+			positions: None,
+		};
+		for _ in 0..2 {
+			let cached = render(cache.build_file_rows(
+				&[2, 0, 1],
+				&[30, 20, 10],
+				&files,
+				Some((&query, config.clone())),
+				layout,
+			));
+			assert_eq!(cached, fresh);
+		}
+	}
+
+	#[test]
+	fn cache_reuses_rows_until_inputs_change() {
+		let files = sample_files();
+		let query = Query::parse("lib");
+		let mut cache = RowCache::default();
+		let mut layout = RowLayout {
+			highlight_style: Style::default(),
+			column_widths: Some(&[24, 6]),
+			show_sizes: false,
+			show_tags: false,
+			marked: None,
+			positions: None,
+		};
+
+		let _ = cache.build_file_rows(
+			&[0, 1],
+			&[5, 4],
+			&files,
+			Some((&query, Config::default())),
+			layout,
+		);
+		assert_eq!(cache.len(), 2);
+
+		// Scrolling to a different slice of the same results keeps earlier rows.
+		let _ = cache.build_file_rows(
+			&[1, 2],
+			&[4, 3],
+			&files,
+			Some((&query, Config::default())),
+			layout,
+		);
+		assert_eq!(cache.len(), 3);
+
+		// A changed score is a different row.
+		let _ = cache.build_file_rows(
+			&[1],
+			&[9],
+			&files,
+			Some((&query, Config::default())),
+			layout,
+		);
+		assert_eq!(cache.len(), 4);
+
+		layout.column_widths = Some(&[30, 6]);
+		let _ = cache.build_file_rows(
+			&[1],
+			&[9],
+			&files,
+			Some((&query, Config::default())),
+			layout,
+		);
+		assert_eq!(cache.len(), 1);
+
+		let other = Query::parse("rows");
+		let _ = cache.build_file_rows(
+			&[0, 1],
+			&[5, 4],
+			&files,
+			Some((&other, Config::default())),
+			layout,
+		);
+		assert_eq!(cache.len(), 2);
+
+		cache.clear();
+		assert!(cache.is_empty());
+	}
+
+	#[test]
+	fn directories_render_with_a_trailing_slash() {
+		let files = vec![
+			FileRow::filesystem("src/components").with_kind(EntryKind::Dir),
+			FileRow::filesystem("src/lib.rs"),
+		];
+		let buffer = render(build_file_rows(
+			&[0, 1],
+			&[2, 1],
+			&files,
+			None,
+			Style::default(),
+			None,
+			false,
+		));
+
+		let line = |y: u16| -> String {
+			(0..24)
+				.map(|x| buffer[(x, y)].symbol())
+				.collect::<String>()
+				.trim_end()
+				.to_string()
+		};
+		assert_eq!(line(0), "src/components/");
+		assert_eq!(line(1), "src/lib.rs");
+	}
+
+	#[test]
+	fn marked_rows_render_with_a_mark_glyph() {
+		let files = sample_files();
+		let marked = HashSet::from([files[1].stable_id()]);
+		let mut cache = RowCache::default();
+		let mut layout = RowLayout {
+			highlight_style: Style::default(),
+			column_widths: None,
+			show_sizes: false,
+			show_tags: false,
+			marked: Some(&marked),
+			positions: None,
+		};
+
+		let buffer = render(cache.build_file_rows(&[0, 1], &[2, 1], &files, None, layout));
+		let line = |buffer: &Buffer, y: u16| -> String {
+			(0..24)
+				.map(|x| buffer[(x, y)].symbol())
+				.collect::<String>()
+				.trim_end()
+				.to_string()
+		};
+		assert_eq!(line(&buffer, 0), "src/lib.rs");
+		assert_eq!(
+			line(&buffer, 1),
+			format!("{MARK_SYMBOL}src/components/rows.rs")
+		);
+
+		// Unmarking rebuilds the row rather than reusing the marked one.
+		layout.marked = None;
+		let buffer = render(cache.build_file_rows(&[0, 1], &[2, 1], &files, None, layout));
+		assert_eq!(line(&buffer, 1), "src/components/rows.rs");
+	}
+
+	#[test]
+	fn precomputed_positions_win_over_recomputed_highlights() {
+		let files = sample_files();
+		let query = Query::parse("lib");
+		let style = Style::default().bold();
+		let is_bold = |buffer: &Buffer, x: u16| buffer[(x, 0)].modifier.contains(Modifier::BOLD);
+		let mut positions = HashMap::from([(0, vec![0, 1])]);
+		let mut cache = RowCache::default();
+		let build = |cache: &mut RowCache, positions: &HashMap<usize, Vec<usize>>| {
+			let layout = RowLayout {
+				highlight_style: style,
+				column_widths: None,
+				show_sizes: false,
+				show_tags: false,
+				marked: None,
+				positions: Some(positions),
+			};
+			render(cache.build_file_rows(
+				&[0],
+				&[5],
+				&files,
+				Some((&query, Config::default())),
+				layout,
+			))
+		};
+
+		let buffer = build(&mut cache, &positions);
+		assert!(is_bold(&buffer, 0) && is_bold(&buffer, 1));
+		assert!(!is_bold(&buffer, 4), "recomputed `lib` highlight");
+
+		// Changed positions rebuild the cached row.
+		positions.insert(0, vec![4, 5, 6]);
+		let buffer = build(&mut cache, &positions);
+		assert!(!is_bold(&buffer, 0));
+		assert!(is_bold(&buffer, 4) && is_bold(&buffer, 6));
+
+		// Rows without positions fall back to the highlight query.
+		positions.clear();
+		let buffer = build(&mut cache, &positions);
+		assert!(is_bold(&buffer, 4) && !is_bold(&buffer, 0));
+	}
+
+	#[test]
+	fn highlights_follow_the_scorers_positions_for_fuzzy_matches() {
+		use frz_core::filesystem::search::{SearchData, collect_matches};
+
+		let data = SearchData::new().with_files(vec![
+			FileRow::new("src/search/matcher.rs"),
+			FileRow::new("docs/summary.md"),
+			FileRow::new("sm/r.txt"),
+		]);
+		let batch = collect_matches(&data, "smr", 3);
+		let scored = batch.positions.expect("scored matches carry positions");
+		assert_eq!(batch.indices.len(), 3);
+		let positions: HashMap<usize, Vec<usize>> =
+			batch.indices.iter().copied().zip(scored).collect();
+
+		let query = Query::parse("smr");
+		let style = Style::default().bold();
+		let layout = RowLayout {
+			highlight_style: style,
+			column_widths: None,
+			show_sizes: false,
+			show_tags: false,
+			marked: None,
+			positions: Some(&positions),
+		};
+		let buffer = render(RowCache::default().build_file_rows(
+			&batch.indices,
+			&batch.scores,
+			&data.files,
+			Some((&query, Config::default())),
+			layout,
+		));
+		for (y, index) in (0..).zip(&batch.indices) {
+			let bold: Vec<usize> = (0..24)
+				.filter(|&x| buffer[(x, y)].modifier.contains(Modifier::BOLD))
+				.map(usize::from)
+				.collect();
+			assert!(!bold.is_empty(), "{}", data.files[*index].path);
+			assert_eq!(bold, positions[index], "{}", data.files[*index].path);
+		}
+	}
+
+	#[test]
+	fn middle_truncated_rows_keep_the_file_name_and_its_highlight() {
+		let files = vec![
+			FileRow::new("src/very/long/path/file.rs")
+				.with_truncation_style(TruncationStyle::Middle),
+		];
+		let positions = HashMap::from([(0, vec![0, 19])]);
+		let layout = RowLayout {
+			highlight_style: Style::default().bold(),
+			column_widths: Some(&[21, 6]),
+			show_sizes: false,
+			show_tags: false,
+			marked: None,
+			positions: Some(&positions),
+		};
+		let buffer = render(RowCache::default().build_file_rows(&[0], &[1], &files, None, layout));
+		let text: String = (0..20).map(|x| buffer[(x, 0)].symbol()).collect();
+		assert_eq!(text, "src/very/lo…/file.rs");
+		let bold: Vec<u16> = (0..20)
+			.filter(|&x| buffer[(x, 0)].modifier.contains(Modifier::BOLD))
+			.collect();
+		assert_eq!(bold, [0, 13], "`s` and the `f` of the file name");
+	}
+
+	#[test]
+	fn tag_columns_mark_the_tags_filtered_for() {
+		let files = vec![FileRow::new("docs/plan.md").with_tags(["work", "Urgent"])];
+		let query = Query::parse("tag:urgent plan");
+		let layout = RowLayout {
+			highlight_style: Style::default().bold(),
+			column_widths: None,
+			show_sizes: false,
+			show_tags: true,
+			marked: None,
+			positions: None,
+		};
+		let mut cache = RowCache::default();
+		let rows = cache.build_file_rows(
+			&[0],
+			&[1],
+			&files,
+			Some((&query, Config::default())),
+			layout,
+		);
+		let mut terminal = Terminal::new(TestBackend::new(40, 1)).expect("terminal");
+		terminal
+			.draw(|frame| {
+				let widths = [
+					Constraint::Length(14),
+					Constraint::Length(14),
+					Constraint::Length(4),
+				];
+				frame.render_widget(Table::new(rows, widths), frame.area());
+			})
+			.expect("draw rows");
+		let buffer = terminal.backend().buffer();
+
+		let tags: String = (15..29).map(|x| buffer[(x, 0)].symbol()).collect();
+		assert_eq!(tags, "work, Urgent  ");
+		let bold: Vec<u16> = (15..29)
+			.filter(|&x| buffer[(x, 0)].modifier.contains(Modifier::BOLD))
+			.collect();
+		assert_eq!(bold, (21..27).collect::<Vec<_>>(), "only `Urgent`");
+	}
+}
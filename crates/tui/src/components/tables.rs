@@ -21,14 +21,23 @@ pub struct TableSpec<'a> {
 	pub headers: Vec<String>,
 	/// Column width constraints.
 	pub widths: Vec<Constraint>,
-	/// Rendered table rows.
+	/// Rendered table rows: every row, or the window of them in view.
 	pub rows: Vec<Row<'a>>,
+	/// Rows in the whole table, sizing the scrollbar when `rows` is only a
+	/// window of them.
+	pub total_rows: usize,
 	/// Optional title for the bordered table.
 	pub title: Option<String>,
 	/// Spacing to use for row highlighting.
 	pub highlight_spacing: HighlightSpacing,
+	/// Number the first nine rows below the scroll offset in the selection
+	/// column, matching the Alt+1..Alt+9 quick-select keys.
+	pub row_labels: bool,
 }
 
+/// Rows numbered when [`TableSpec::row_labels`] is set.
+const LABELED_ROWS: usize = 9;
+
 /// Render the table using the provided dataset definition.
 pub fn render_table(
 	frame: &mut Frame,
@@ -89,7 +98,9 @@ fn render_configured_table(
 	let header_height = TABLE_HEADER_ROWS;
 	let viewport_height = area.height as usize;
 	let available_rows = viewport_height.saturating_sub(header_height);
-	let total_rows = spec.rows.len();
+	let total_rows = spec.total_rows.max(spec.rows.len());
+	let rendered_rows = spec.rows.len();
+	let row_labels = spec.row_labels;
 
 	// Determine if we need a scrollbar
 	let needs_scrollbar = total_rows > available_rows && available_rows > 0;
@@ -115,6 +126,10 @@ fn render_configured_table(
 		.highlight_symbol(HIGHLIGHT_SYMBOL);
 	frame.render_stateful_widget(table, table_area, table_state);
 
+	if row_labels {
+		render_row_labels(frame, table_area, table_state, rendered_rows, theme);
+	}
+
 	// Render scrollbar if needed
 	if needs_scrollbar {
 		render_scrollbar(frame, area, scrollbar_state, scrollbar_area, theme);
@@ -123,6 +138,40 @@ fn render_configured_table(
 	render_header_separator(frame, table_area, theme, 1);
 }
 
+/// Draw the quick-select digit of each numbered row in the selection
+/// column, leaving the selected row's highlight symbol in place.
+///
+/// The column is only laid out while a row is selected.
+fn render_row_labels(
+	frame: &mut Frame,
+	area: Rect,
+	table_state: &ratatui::widgets::TableState,
+	rendered_rows: usize,
+	theme: &Theme,
+) {
+	let Some(selected) = table_state.selected() else {
+		return;
+	};
+	let offset = table_state.offset();
+	let shown = (area.height as usize)
+		.saturating_sub(TABLE_HEADER_ROWS)
+		.min(rendered_rows.saturating_sub(offset))
+		.min(LABELED_ROWS);
+	for position in 0..shown {
+		if offset + position == selected {
+			continue;
+		}
+		let label = Rect {
+			x: area.x,
+			y: area.y + (TABLE_HEADER_ROWS + position) as u16,
+			width: area.width.min(1),
+			height: 1,
+		};
+		let digit = Span::styled((position + 1).to_string(), theme.empty);
+		frame.render_widget(Paragraph::new(Line::from(digit)), label);
+	}
+}
+
 fn render_header_separator(frame: &mut Frame, area: Rect, theme: &Theme, header_height: u16) {
 	if header_height >= area.height {
 		return;
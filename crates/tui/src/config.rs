@@ -123,3 +123,30 @@ impl UiLabels {
 			.unwrap_or("")
 	}
 }
+
+/// Where the preview pane sits relative to the results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreviewLayout {
+	/// Results on the left, preview on the right.
+	#[default]
+	Horizontal,
+	/// Results on top, preview below.
+	Vertical,
+}
+
+/// Largest width or height, in pixels, an image preview is kept at by
+/// default.
+pub const DEFAULT_MAX_IMAGE_DIMENSION: u32 = 4096;
+
+/// How PDFs are previewed with the `media-preview` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PdfPreviewMode {
+	/// Render the first page when the terminal can show images, and show the
+	/// extracted text otherwise.
+	#[default]
+	Auto,
+	/// Always render the first page as an image.
+	Image,
+	/// Always show the text extracted from every page.
+	Text,
+}
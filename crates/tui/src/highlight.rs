@@ -14,13 +14,13 @@ pub fn highlight_cell(
 	max_width: Option<u16>,
 	truncation: TruncationStyle,
 	highlight_style: Style,
-) -> Cell<'_> {
+) -> Cell<'static> {
 	highlight_cell_with_prefix(text, indices, max_width, truncation, highlight_style, None)
 }
 
 /// Build a table cell with highlighted indices and optional prefix spans.
 pub fn highlight_cell_with_prefix<'a>(
-	text: &'a str,
+	text: &str,
 	indices: Option<Vec<usize>>,
 	max_width: Option<u16>,
 	truncation: TruncationStyle,
@@ -33,29 +33,53 @@ pub fn highlight_cell_with_prefix<'a>(
 		let prefix_width_u16: u16 = prefix_width.try_into().unwrap_or(u16::MAX);
 		width.checked_sub(prefix_width_u16)
 	});
+	// A prefix wider than the column leaves no room for the text at all.
+	let text_width = if max_width.is_some() {
+		Some(adjusted_width.unwrap_or(0))
+	} else {
+		None
+	};
+
+	let mut spans = highlight_spans(text, indices, text_width, truncation, highlight_style);
+	if spans.is_empty() {
+		if prefix_spans.is_empty() {
+			return Cell::from(String::new());
+		}
+		return Cell::from(Text::from(Line::from(prefix_spans)));
+	}
 
-	let (display_text, indices) = if let Some(width) = adjusted_width.map(usize::from) {
+	if prefix_spans.is_empty() {
+		Cell::from(Text::from(Line::from(spans)))
+	} else {
+		prefix_spans.append(&mut spans);
+		Cell::from(Text::from(Line::from(prefix_spans)))
+	}
+}
+
+/// Build the styled spans for `text`, truncated to `max_width` with the
+/// characters at `indices` highlighted.
+///
+/// The spans own their text, so callers can keep them around and render
+/// them again without recomputing the highlight.
+pub fn highlight_spans(
+	text: &str,
+	indices: Option<Vec<usize>>,
+	max_width: Option<u16>,
+	truncation: TruncationStyle,
+	highlight_style: Style,
+) -> Vec<Span<'static>> {
+	let (display_text, indices) = if let Some(width) = max_width.map(usize::from) {
 		truncate_with_highlight(text, indices, width, truncation)
-	} else if max_width.is_some() {
-		(String::new(), None)
 	} else {
 		(text.to_string(), indices)
 	};
 
 	if display_text.is_empty() {
-		if prefix_spans.is_empty() {
-			return Cell::from(display_text);
-		}
-		return Cell::from(Text::from(Line::from(prefix_spans)));
+		return Vec::new();
 	}
 
 	let Some(mut sorted_indices) = indices.filter(|indices| !indices.is_empty()) else {
-		let mut spans = spans_with_dimmed_ellipsis(&display_text);
-		if prefix_spans.is_empty() {
-			return Cell::from(Text::from(Line::from(spans)));
-		}
-		prefix_spans.append(&mut spans);
-		return Cell::from(Text::from(Line::from(prefix_spans)));
+		return spans_with_dimmed_ellipsis(&display_text);
 	};
 	sorted_indices.sort_unstable();
 	let mut next = sorted_indices.into_iter().peekable();
@@ -104,12 +128,7 @@ pub fn highlight_cell_with_prefix<'a>(
 		spans.push(Span::styled(buffer, style));
 	}
 
-	if prefix_spans.is_empty() {
-		Cell::from(Text::from(Line::from(spans)))
-	} else {
-		prefix_spans.extend(spans);
-		Cell::from(Text::from(Line::from(prefix_spans)))
-	}
+	spans
 }
 
 fn truncate_with_highlight(
@@ -164,9 +183,60 @@ fn truncate_with_highlight(
 			});
 			(truncated, indices)
 		}
+		TruncationStyle::Middle => truncate_middle(text, indices, available),
 	}
 }
 
+/// Replace the interior of `text` with an ellipsis, keeping the start and
+/// the whole final path component with the separator before it.
+///
+/// `available` is the width left besides the ellipsis. When the final
+/// component alone does not fit, the path is truncated from the left so the
+/// end of the file name stays visible.
+fn truncate_middle(
+	text: &str,
+	indices: Option<Vec<usize>>,
+	available: usize,
+) -> (String, Option<Vec<usize>>) {
+	let ellipsis = "…";
+	let split = text
+		.trim_end_matches('/')
+		.rfind('/')
+		.filter(|&split| split > 0 && text[split..].width() <= available);
+	let Some(split) = split else {
+		return truncate_with_highlight(
+			text,
+			indices,
+			available + ellipsis.width(),
+			TruncationStyle::Left,
+		);
+	};
+
+	let tail = &text[split..];
+	let (head, _) = text[..split].unicode_truncate(available - tail.width());
+	let head_len = head.chars().count();
+	let tail_start = text[..split].chars().count();
+	let mut truncated = String::with_capacity(head.len() + ellipsis.len() + tail.len());
+	truncated.push_str(head);
+	truncated.push_str(ellipsis);
+	truncated.push_str(tail);
+	let indices = indices.and_then(|indices| {
+		let adjusted: Vec<usize> = indices
+			.into_iter()
+			.filter_map(|idx| {
+				if idx < head_len {
+					Some(idx)
+				} else {
+					idx.checked_sub(tail_start)
+						.map(|offset| head_len + 1 + offset)
+				}
+			})
+			.collect();
+		(!adjusted.is_empty()).then_some(adjusted)
+	});
+	(truncated, indices)
+}
+
 fn spans_with_dimmed_ellipsis(text: &str) -> Vec<Span<'static>> {
 	let mut spans = Vec::new();
 	let mut buffer = String::new();
@@ -218,6 +288,55 @@ mod tests {
 		assert_eq!(indices, Some(vec![3]));
 	}
 
+	#[test]
+	fn middle_truncation_keeps_the_start_and_the_file_name() {
+		let path = "src/very/long/path/file.rs";
+		let middle = |width| truncate_for_test(path, width, TruncationStyle::Middle);
+		assert_eq!(middle(26), path);
+		assert_eq!(middle(25), "src/very/long/pa…/file.rs");
+		assert_eq!(middle(20), "src/very/lo…/file.rs");
+		assert_eq!(middle(10), "s…/file.rs");
+		// Too narrow for both ends: the end of the file name wins.
+		assert_eq!(middle(9), "…/file.rs");
+		assert_eq!(middle(5), "…e.rs");
+		assert_eq!(middle(1), "…");
+		assert_eq!(middle(0), "");
+		// Trailing separators stay with the final component.
+		assert_eq!(
+			truncate_for_test("src/components/", 14, TruncationStyle::Middle),
+			"s…/components/"
+		);
+	}
+
+	#[test]
+	fn middle_truncation_adjusts_indices() {
+		let (text, indices) = truncate_with_highlight(
+			"src/very/long/path/file.rs",
+			Some(vec![0, 4, 15, 19, 25]),
+			20,
+			TruncationStyle::Middle,
+		);
+		assert_eq!(text, "src/very/lo…/file.rs");
+		// `s`, `v`, nothing from the dropped interior, then `f` and `s`.
+		assert_eq!(indices, Some(vec![0, 4, 13, 19]));
+	}
+
+	#[test]
+	fn middle_truncation_measures_display_width() {
+		// Each CJK character is two columns wide.
+		let path = "文書/長い/ディレクトリ/ファイル.md";
+		let middle = |width| truncate_for_test(path, width, TruncationStyle::Middle);
+		assert_eq!(middle(25), "文書/長い/デ…/ファイル.md");
+		// A wide character never straddles the remaining width.
+		assert_eq!(middle(24), "文書/長い/…/ファイル.md");
+		assert_eq!(middle(22), "文書/長い…/ファイル.md");
+		assert_eq!(middle(16), "文…/ファイル.md");
+		for width in 12..=34 {
+			assert!(middle(width).width() <= width, "{width}");
+		}
+		assert_eq!(middle(12), "…ファイル.md");
+	}
+
 	#[test]
 	fn spans_with_dimmed_ellipsis_styles_only_ellipsis() {
 		let spans = spans_with_dimmed_ellipsis("abc…xyz");
@@ -3,24 +3,74 @@
 //! This module provides a wrapper around `TextArea` configured for single-line input,
 //! similar to `<input>` in HTML.
 
+use std::collections::VecDeque;
+
 use ratatui::Frame;
 use ratatui::layout::Rect;
-use tui_textarea::{Input, Key, TextArea};
+use tui_textarea::{CursorMove, Input, Key, TextArea};
 
 use super::style::Theme;
 
+/// Number of queries remembered by [`QueryInput`] unless configured
+/// otherwise.
+pub const HISTORY_LIMIT: usize = 200;
+
+/// Previously committed queries, newest last.
+#[derive(Debug)]
+struct History {
+	entries: VecDeque<String>,
+	/// Index of the entry currently recalled into the prompt.
+	position: Option<usize>,
+	/// Maximum number of entries kept.
+	limit: usize,
+}
+
+impl Default for History {
+	fn default() -> Self {
+		Self {
+			entries: VecDeque::new(),
+			position: None,
+			limit: HISTORY_LIMIT,
+		}
+	}
+}
+
+impl History {
+	/// Append `entry` unless it repeats the newest one, dropping the oldest
+	/// entries beyond the limit.
+	fn push(&mut self, entry: String) {
+		if self.entries.back() == Some(&entry) {
+			return;
+		}
+		self.entries.push_back(entry);
+		self.truncate();
+	}
+
+	fn truncate(&mut self) {
+		let excess = self.entries.len().saturating_sub(self.limit);
+		self.entries.drain(..excess);
+	}
+}
+
 /// A single-line text input widget for entering search queries
 pub struct QueryInput<'a> {
 	textarea: TextArea<'a>,
+	history: History,
 }
 
 impl<'a> QueryInput<'a> {
 	/// Create a new query input with optional initial text
+	///
+	/// The cursor starts after the initial text so typing extends it.
 	pub fn new(initial_text: impl Into<String>) -> Self {
 		let text = initial_text.into().replace(['\n', '\r'], " ");
 		let mut textarea = TextArea::new(vec![text]);
 		textarea.remove_line_number();
-		Self { textarea }
+		textarea.move_cursor(CursorMove::End);
+		Self {
+			textarea,
+			history: History::default(),
+		}
 	}
 
 	/// Handle input events, ignoring Enter and Ctrl+M to keep it single-line.
@@ -63,6 +113,58 @@ impl<'a> QueryInput<'a> {
 		self.text().trim().to_string()
 	}
 
+	/// Insert `text` at the cursor as a single undoable edit.
+	///
+	/// Returns `true` when the normalized query text changes.
+	pub fn insert_str(&mut self, text: &str) -> bool {
+		let before = self.normalized_text();
+		if !self.textarea.insert_str(text.replace(['\n', '\r'], " ")) {
+			return false;
+		}
+		before != self.normalized_text()
+	}
+
+	/// Remove `text` that [`QueryInput::insert_str`] just inserted before the
+	/// cursor.
+	///
+	/// The insertion is undone when that restores the text without it, so a
+	/// later undo does not bring it back; otherwise it is deleted. Nothing
+	/// changes when `text` is not right before the cursor.
+	///
+	/// Returns `true` when the normalized query text changes.
+	pub fn revert_insertion(&mut self, text: &str) -> bool {
+		let text = text.replace(['\n', '\r'], " ");
+		let before = self.normalized_text();
+		let (row, col) = self.textarea.cursor();
+		let line = &self.textarea.lines()[row];
+		let split = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+		let Some(head) = line[..split].strip_suffix(text.as_str()) else {
+			return false;
+		};
+		let expected = format!("{head}{}", &line[split..]);
+
+		if self.textarea.undo() {
+			if self.textarea.lines()[row] == expected {
+				return before != self.normalized_text();
+			}
+			self.textarea.redo();
+		}
+		let chars = text.chars().count();
+		for _ in 0..chars {
+			self.textarea.move_cursor(CursorMove::Back);
+		}
+		self.textarea.delete_str(chars);
+		before != self.normalized_text()
+	}
+
+	/// Revert the most recent edit.
+	///
+	/// Returns `true` when the normalized query text changes.
+	pub fn undo(&mut self) -> bool {
+		let before = self.normalized_text();
+		self.textarea.undo() && before != self.normalized_text()
+	}
+
 	/// Set the input text
 	pub fn set_text(&mut self, text: impl Into<String>) {
 		let text = text.into().replace(['\n', '\r'], " ");
@@ -75,6 +177,101 @@ impl<'a> QueryInput<'a> {
 		self.set_text("");
 	}
 
+	/// Remember the current query so it can be recalled later.
+	///
+	/// Empty queries and repeats of the newest entry are skipped, and the
+	/// oldest entries are dropped beyond the history limit. A recalled entry
+	/// that was edited is added as a new entry; the original is kept as is.
+	pub fn commit_to_history(&mut self) {
+		self.history.position = None;
+		let text = self.normalized_text();
+		if !text.is_empty() {
+			self.history.push(text);
+		}
+	}
+
+	/// Remember at most `limit` queries, [`HISTORY_LIMIT`] by default.
+	///
+	/// Entries beyond a lowered limit are dropped, oldest first.
+	pub fn set_history_limit(&mut self, limit: usize) {
+		self.history.position = None;
+		self.history.limit = limit;
+		self.history.truncate();
+	}
+
+	/// Recall the previous (older) history entry.
+	///
+	/// Only applies while the prompt is empty or still shows an unedited
+	/// recalled entry. Returns `true` when the query text changes.
+	pub fn history_previous(&mut self) -> bool {
+		if !self.can_browse_history() {
+			return false;
+		}
+		let position = match self.history.position {
+			Some(0) => return false,
+			Some(position) => position - 1,
+			None => match self.history.entries.len().checked_sub(1) {
+				Some(newest) => newest,
+				None => return false,
+			},
+		};
+		self.recall(Some(position))
+	}
+
+	/// Recall the next (newer) history entry, or clear the prompt after the
+	/// newest one.
+	///
+	/// Returns `true` when the query text changes.
+	pub fn history_next(&mut self) -> bool {
+		if !self.can_browse_history() {
+			return false;
+		}
+		let Some(position) = self.history.position else {
+			return false;
+		};
+		let next = position + 1;
+		self.recall((next < self.history.entries.len()).then_some(next))
+	}
+
+	/// Seed the history with queries from earlier sessions, oldest first.
+	///
+	/// Consecutive repeats are collapsed and only the newest entries up to
+	/// the history limit are kept.
+	pub fn load_history(&mut self, entries: impl IntoIterator<Item = String>) {
+		self.history.position = None;
+		for entry in entries {
+			let entry = entry.trim();
+			if !entry.is_empty() {
+				self.history.push(entry.to_string());
+			}
+		}
+	}
+
+	/// Previously committed queries, oldest first.
+	pub fn history(&self) -> impl Iterator<Item = &str> {
+		self.history.entries.iter().map(String::as_str)
+	}
+
+	fn can_browse_history(&self) -> bool {
+		match self.history.position {
+			Some(position) => {
+				self.history.entries.get(position).map(String::as_str) == Some(self.text())
+			}
+			None => self.text().is_empty(),
+		}
+	}
+
+	fn recall(&mut self, position: Option<usize>) -> bool {
+		let before = self.normalized_text();
+		let text = position
+			.and_then(|position| self.history.entries.get(position).cloned())
+			.unwrap_or_default();
+		self.set_text(text);
+		self.textarea.move_cursor(CursorMove::End);
+		self.history.position = position;
+		before != self.normalized_text()
+	}
+
 	/// Render the textarea widget directly (shows cursor and proper text editing)
 	pub fn render_textarea(&self, frame: &mut Frame, area: Rect) {
 		frame.render_widget(&self.textarea, area);
@@ -119,6 +316,13 @@ mod tests {
 		assert_eq!(input.text(), "test");
 	}
 
+	#[test]
+	fn initial_text_leaves_the_cursor_at_the_end() {
+		let mut input = QueryInput::new("src");
+		assert!(input.insert_str("/main"));
+		assert_eq!(input.text(), "src/main");
+	}
+
 	#[test]
 	fn test_newlines_replaced() {
 		let input = QueryInput::new("test\nwith\rnewlines");
@@ -182,4 +386,128 @@ mod tests {
 		};
 		assert!(input.input(delete));
 	}
+
+	#[test]
+	fn test_insert_str_at_multibyte_cursor() {
+		let mut input = QueryInput::new("日本");
+		input
+			.textarea_mut()
+			.move_cursor(tui_textarea::CursorMove::End);
+		let left = Input {
+			key: Key::Left,
+			ctrl: false,
+			alt: false,
+			shift: false,
+		};
+		input.input(left);
+		assert!(input.insert_str("語/"));
+		assert_eq!(input.text(), "日語/本");
+
+		assert!(input.undo());
+		assert_eq!(input.text(), "日本");
+	}
+
+	fn typed(text: &str) -> QueryInput<'static> {
+		let mut input = QueryInput::default();
+		input.set_text(text);
+		input
+	}
+
+	fn commit(input: &mut QueryInput<'_>, text: &str) {
+		input.set_text(text);
+		input.commit_to_history();
+		input.clear();
+	}
+
+	#[test]
+	fn history_cycles_from_an_empty_prompt() {
+		let mut input = QueryInput::default();
+		commit(&mut input, "first");
+		commit(&mut input, "second");
+
+		assert!(input.history_previous());
+		assert_eq!(input.text(), "second");
+		assert!(input.history_previous());
+		assert_eq!(input.text(), "first");
+		assert!(!input.history_previous());
+
+		assert!(input.history_next());
+		assert_eq!(input.text(), "second");
+		assert!(input.history_next());
+		assert_eq!(input.text(), "");
+		assert!(!input.history_next());
+	}
+
+	#[test]
+	fn history_is_not_browsed_over_typed_text() {
+		let mut input = typed("draft");
+		input.commit_to_history();
+		input.set_text("typing");
+		assert!(!input.history_previous());
+		assert_eq!(input.text(), "typing");
+	}
+
+	#[test]
+	fn edited_recall_is_committed_as_a_new_entry() {
+		let mut input = QueryInput::default();
+		commit(&mut input, "src lib");
+
+		assert!(input.history_previous());
+		input.insert_str(" rs");
+		assert!(!input.history_previous(), "edited entries stop browsing");
+		input.commit_to_history();
+
+		assert_eq!(
+			input.history().collect::<Vec<_>>(),
+			vec!["src lib", "src lib rs"]
+		);
+	}
+
+	#[test]
+	fn history_is_capped_and_skips_repeats() {
+		let mut input = QueryInput::default();
+		commit(&mut input, "same");
+		commit(&mut input, "same");
+		commit(&mut input, "   ");
+		assert_eq!(input.history().count(), 1);
+
+		for i in 0..HISTORY_LIMIT + 5 {
+			commit(&mut input, &format!("query {i}"));
+		}
+		assert_eq!(input.history().count(), HISTORY_LIMIT);
+		assert_eq!(input.history().next(), Some("query 5"));
+	}
+
+	#[test]
+	fn history_limit_can_be_lowered() {
+		let mut input = QueryInput::default();
+		for query in ["one", "two", "three"] {
+			commit(&mut input, query);
+		}
+		input.set_history_limit(2);
+		assert_eq!(input.history().collect::<Vec<_>>(), vec!["two", "three"]);
+
+		commit(&mut input, "four");
+		assert_eq!(input.history().collect::<Vec<_>>(), vec!["three", "four"]);
+		assert!(input.history_previous());
+		assert_eq!(input.text(), "four");
+		assert!(input.history_previous());
+		assert_eq!(input.text(), "three");
+		assert!(!input.history_previous());
+	}
+
+	#[test]
+	fn loaded_history_is_recalled_before_older_sessions() {
+		let mut input = QueryInput::default();
+		input.load_history(["old".to_string(), "old".to_string(), "recent".to_string()]);
+		commit(&mut input, "now");
+
+		assert_eq!(
+			input.history().collect::<Vec<_>>(),
+			vec!["old", "recent", "now"]
+		);
+		assert!(input.history_previous());
+		assert!(input.history_previous());
+		assert_eq!(input.text(), "recent");
+	}
 }
@@ -0,0 +1,412 @@
+//! Configurable key bindings for the picker's actions.
+//!
+//! Keys without a binding fall through to the remaining built-in shortcuts
+//! and finally to the query input.
+
+use std::fmt;
+
+use frz_core::error::{Error, Result};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// An action that can be bound to one or more keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Action {
+	/// Highlight the previous row, or recall older history on an empty
+	/// prompt.
+	MoveUp,
+	/// Highlight the next row, or recall newer history.
+	MoveDown,
+	/// Highlight the first row.
+	MoveToTop,
+	/// Highlight the last row.
+	MoveToBottom,
+	/// Move the highlight up by the number of rows in view.
+	PageUp,
+	/// Move the highlight down by the number of rows in view.
+	PageDown,
+	/// Confirm the highlighted or marked rows and exit.
+	Accept,
+	/// Exit without a selection.
+	Cancel,
+	/// Show or hide the preview pane.
+	TogglePreview,
+	/// Show or hide the line numbers of highlighted previews.
+	ToggleLineNumbers,
+	/// Switch markdown previews between formatted text and highlighted
+	/// source.
+	ToggleMarkdown,
+	/// Open a prompt searching the previewed text.
+	FindInPreview,
+	/// Mark or unmark the highlighted row, then move down.
+	MarkNext,
+	/// Mark or unmark the highlighted row, then move up.
+	MarkPrevious,
+	/// Mark every row matching the query, including rows scrolled out of
+	/// view.
+	MarkAll,
+	/// Clear every mark.
+	UnmarkAll,
+	/// Mark the unmarked rows matching the query and unmark the marked ones.
+	InvertMarks,
+	/// Copy the highlighted row's path to the clipboard.
+	CopyPath,
+	/// Copy the highlighted row's path, resolved under the search root, to
+	/// the clipboard.
+	CopyAbsolutePath,
+	/// Open the highlighted file in the editor, returning to the picker when
+	/// it exits.
+	OpenInEditor,
+	/// Show or hide an overlay describing the picker's internal state, such
+	/// as whether this instance refreshes the index cache.
+	ToggleDebugOverlay,
+}
+
+impl Action {
+	/// Every action, in the order bindings are listed.
+	pub const ALL: [Self; 21] = [
+		Self::MoveUp,
+		Self::MoveDown,
+		Self::MoveToTop,
+		Self::MoveToBottom,
+		Self::PageUp,
+		Self::PageDown,
+		Self::Accept,
+		Self::Cancel,
+		Self::TogglePreview,
+		Self::ToggleLineNumbers,
+		Self::ToggleMarkdown,
+		Self::FindInPreview,
+		Self::MarkNext,
+		Self::MarkPrevious,
+		Self::MarkAll,
+		Self::UnmarkAll,
+		Self::InvertMarks,
+		Self::CopyPath,
+		Self::CopyAbsolutePath,
+		Self::OpenInEditor,
+		Self::ToggleDebugOverlay,
+	];
+}
+
+/// Keys assigned to each [`Action`].
+///
+/// A key matches when its code and modifiers are equal; press, repeat and
+/// release kinds are not distinguished. The [`Default`] bindings are the
+/// picker's built-in keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+	bindings: Vec<(Action, Vec<KeyEvent>)>,
+}
+
+impl Default for KeyBindings {
+	fn default() -> Self {
+		let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+		let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+		let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+		Self {
+			bindings: vec![
+				(Action::MoveUp, vec![key(KeyCode::Up)]),
+				(Action::MoveDown, vec![key(KeyCode::Down)]),
+				(Action::MoveToTop, vec![key(KeyCode::Home)]),
+				(Action::MoveToBottom, vec![key(KeyCode::End)]),
+				(Action::PageUp, vec![key(KeyCode::PageUp)]),
+				(Action::PageDown, vec![key(KeyCode::PageDown)]),
+				(Action::Accept, vec![key(KeyCode::Enter)]),
+				(Action::Cancel, vec![key(KeyCode::Esc)]),
+				(Action::TogglePreview, vec![ctrl('p')]),
+				(Action::ToggleLineNumbers, vec![alt('l')]),
+				(Action::ToggleMarkdown, vec![alt('m')]),
+				(Action::FindInPreview, vec![alt('/')]),
+				(Action::MarkNext, vec![key(KeyCode::Tab)]),
+				(
+					Action::MarkPrevious,
+					vec![
+						key(KeyCode::BackTab),
+						KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+					],
+				),
+				(Action::MarkAll, vec![ctrl('a')]),
+				(Action::UnmarkAll, vec![ctrl('d'), alt('u')]),
+				(Action::InvertMarks, vec![alt('i')]),
+				(Action::CopyPath, vec![ctrl('y')]),
+				(Action::CopyAbsolutePath, vec![alt('y')]),
+				(Action::OpenInEditor, vec![ctrl('o')]),
+				(Action::ToggleDebugOverlay, vec![key(KeyCode::F(12))]),
+			],
+		}
+	}
+}
+
+impl KeyBindings {
+	/// Replace the keys bound to `action`.
+	///
+	/// An empty list leaves the action without a key.
+	#[must_use]
+	pub fn bind(mut self, action: Action, keys: impl IntoIterator<Item = KeyEvent>) -> Self {
+		let keys = keys.into_iter().collect();
+		match self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+			Some((_, bound_keys)) => *bound_keys = keys,
+			None => self.bindings.push((action, keys)),
+		}
+		self
+	}
+
+	/// Keys currently bound to `action`.
+	#[must_use]
+	pub fn keys(&self, action: Action) -> &[KeyEvent] {
+		self.bindings
+			.iter()
+			.find(|(bound, _)| *bound == action)
+			.map_or(&[], |(_, keys)| keys.as_slice())
+	}
+
+	/// The action bound to `key`, if any.
+	#[must_use]
+	pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+		self.bindings
+			.iter()
+			.find(|(_, keys)| keys.iter().any(|bound| same_key(bound, key)))
+			.map(|(action, _)| *action)
+	}
+
+	/// Check that no key is bound more than once.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Config`] naming the first key that is bound twice.
+	pub fn validate(&self) -> Result<()> {
+		let bound = self
+			.bindings
+			.iter()
+			.flat_map(|(action, keys)| keys.iter().map(move |key| (*action, key)));
+		for (position, (action, key)) in bound.clone().enumerate() {
+			if let Some((other, _)) = bound
+				.clone()
+				.skip(position + 1)
+				.find(|(_, other)| same_key(key, other))
+			{
+				let key = KeyName(key);
+				return Err(Error::config(if other == action {
+					format!("Key {key} is bound to {action:?} twice")
+				} else {
+					format!("Key {key} is bound to both {action:?} and {other:?}")
+				}));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Names of keys without a character, as written by [`parse_key`] and
+/// [`key_name`].
+const NAMED_KEYS: [(&str, KeyCode); 16] = [
+	("enter", KeyCode::Enter),
+	("esc", KeyCode::Esc),
+	("tab", KeyCode::Tab),
+	("btab", KeyCode::BackTab),
+	("space", KeyCode::Char(' ')),
+	("bspace", KeyCode::Backspace),
+	("del", KeyCode::Delete),
+	("ins", KeyCode::Insert),
+	("up", KeyCode::Up),
+	("down", KeyCode::Down),
+	("left", KeyCode::Left),
+	("right", KeyCode::Right),
+	("home", KeyCode::Home),
+	("end", KeyCode::End),
+	("pgup", KeyCode::PageUp),
+	("pgdn", KeyCode::PageDown),
+];
+
+/// Parse a key written the way fzf does, e.g. `ctrl-v`, `alt-enter`, `f2`
+/// or `?`.
+///
+/// Modifiers are `ctrl-`, `alt-` and `shift-`, in any order. Shift is
+/// folded into characters, so `shift-a` is the same key as `A`.
+///
+/// # Errors
+///
+/// Returns [`Error::Config`] for unknown key names.
+pub fn parse_key(spec: &str) -> Result<KeyEvent> {
+	let mut modifiers = KeyModifiers::NONE;
+	let mut rest = spec.trim();
+	loop {
+		let lower = rest.to_ascii_lowercase();
+		let (modifier, len) = if lower.starts_with("ctrl-") {
+			(KeyModifiers::CONTROL, 5)
+		} else if lower.starts_with("alt-") {
+			(KeyModifiers::ALT, 4)
+		} else if lower.starts_with("shift-") {
+			(KeyModifiers::SHIFT, 6)
+		} else {
+			break;
+		};
+		// A lone `-` after a modifier is the key itself, as in `alt--`.
+		if rest.len() == len - 1 {
+			break;
+		}
+		modifiers |= modifier;
+		rest = &rest[len..];
+	}
+
+	let mut chars = rest.chars();
+	let code = match (chars.next(), chars.next()) {
+		(Some(ch), None) => KeyCode::Char(ch),
+		_ => {
+			let lower = rest.to_ascii_lowercase();
+			if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == lower) {
+				*code
+			} else if let Some(number) = lower
+				.strip_prefix('f')
+				.and_then(|number| number.parse::<u8>().ok())
+				.filter(|number| (1..=12).contains(number))
+			{
+				KeyCode::F(number)
+			} else {
+				return Err(Error::config(format!("Unknown key `{spec}`")));
+			}
+		}
+	};
+	let (code, modifiers) = normalize(&KeyEvent::new(code, modifiers));
+	Ok(KeyEvent::new(code, modifiers))
+}
+
+/// The fzf-style name of `key`, which [`parse_key`] reads back.
+#[must_use]
+pub fn key_name(key: &KeyEvent) -> String {
+	let mut name = String::new();
+	for (modifier, prefix) in [
+		(KeyModifiers::CONTROL, "ctrl-"),
+		(KeyModifiers::ALT, "alt-"),
+		(KeyModifiers::SHIFT, "shift-"),
+	] {
+		if key.modifiers.contains(modifier) {
+			name.push_str(prefix);
+		}
+	}
+	match key.code {
+		KeyCode::F(number) => name.push_str(&format!("f{number}")),
+		code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+			Some((named, _)) => name.push_str(named),
+			None => match code {
+				KeyCode::Char(ch) => name.push(ch),
+				other => name.push_str(&other.to_string().to_ascii_lowercase()),
+			},
+		},
+	}
+	name
+}
+
+/// Whether `a` and `b` are the same key, whatever their kind and state.
+pub(crate) fn same_key(a: &KeyEvent, b: &KeyEvent) -> bool {
+	normalize(a) == normalize(b)
+}
+
+/// The code and modifiers of `key` with Shift folded into characters,
+/// since terminals report Shift+A as `A` with or without the modifier.
+fn normalize(key: &KeyEvent) -> (KeyCode, KeyModifiers) {
+	match key.code {
+		KeyCode::Char(ch) => {
+			let ch = if key.modifiers.contains(KeyModifiers::SHIFT) {
+				ch.to_ascii_uppercase()
+			} else {
+				ch
+			};
+			(
+				KeyCode::Char(ch),
+				key.modifiers.difference(KeyModifiers::SHIFT),
+			)
+		}
+		code => (code, key.modifiers),
+	}
+}
+
+/// Formats a key as e.g. `Control+y` for error messages.
+struct KeyName<'a>(&'a KeyEvent);
+
+impl fmt::Display for KeyName<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.0.modifiers.is_empty() {
+			write!(f, "{}+", self.0.modifiers)?;
+		}
+		write!(f, "{}", self.0.code)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ctrl(c: char) -> KeyEvent {
+		KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+	}
+
+	#[test]
+	fn keys_parse_from_fzf_names_and_back() {
+		let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+		for (spec, key) in [
+			("ctrl-v", ctrl('v')),
+			("CTRL-T", ctrl('T')),
+			("alt-enter", alt(KeyCode::Enter)),
+			("alt--", alt(KeyCode::Char('-'))),
+			("f12", KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE)),
+			("?", KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE)),
+			(
+				"ctrl-alt-space",
+				KeyEvent::new(
+					KeyCode::Char(' '),
+					KeyModifiers::CONTROL | KeyModifiers::ALT,
+				),
+			),
+		] {
+			assert_eq!(parse_key(spec).expect(spec), key, "{spec}");
+			assert_eq!(parse_key(&key_name(&key)).unwrap(), key, "{spec}");
+		}
+		assert_eq!(key_name(&ctrl('v')), "ctrl-v");
+		assert_eq!(
+			parse_key("shift-a").unwrap(),
+			KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE)
+		);
+		assert!(parse_key("ctrl-nope").is_err());
+		assert!(parse_key("f13").is_err());
+	}
+
+	#[test]
+	fn defaults_cover_every_action_without_conflicts() {
+		let bindings = KeyBindings::default();
+		for action in Action::ALL {
+			assert!(!bindings.keys(action).is_empty(), "{action:?} is unbound");
+		}
+		bindings.validate().expect("default bindings are valid");
+		assert_eq!(
+			bindings.action_for(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+			Some(Action::Accept)
+		);
+		assert_eq!(bindings.action_for(&ctrl('y')), Some(Action::CopyPath));
+		assert_eq!(bindings.action_for(&ctrl('g')), None);
+	}
+
+	#[test]
+	fn rebinding_replaces_the_previous_keys() {
+		let bindings = KeyBindings::default().bind(Action::Accept, [ctrl('y'), ctrl('m')]);
+		assert_eq!(bindings.keys(Action::Accept), [ctrl('y'), ctrl('m')]);
+		assert_eq!(bindings.action_for(&ctrl('m')), Some(Action::Accept));
+		assert_eq!(
+			bindings.action_for(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+			None
+		);
+	}
+
+	#[test]
+	fn keys_bound_twice_are_rejected() {
+		let conflicting = KeyBindings::default().bind(Action::Cancel, [ctrl('p')]);
+		let err = conflicting.validate().expect_err("conflict");
+		assert!(matches!(err, Error::Config { .. }), "{err:?}");
+		assert!(err.to_string().contains("TogglePreview"), "{err}");
+		assert!(err.to_string().contains("Cancel"), "{err}");
+
+		let repeated = KeyBindings::default().bind(Action::Accept, [ctrl('y'), ctrl('y')]);
+		assert!(repeated.validate().is_err());
+	}
+}
@@ -11,14 +11,19 @@ mod config;
 /// Syntax highlighting and text styling utilities.
 pub mod highlight;
 pub mod input;
+mod keys;
 mod runtime;
 pub mod style;
 
 pub use app::App;
 pub use builder::Picker;
-pub use config::{PaneLabels, TabLabels, UiLabels};
+pub use config::{
+	DEFAULT_MAX_IMAGE_DIMENSION, PaneLabels, PdfPreviewMode, PreviewLayout, TabLabels, UiLabels,
+};
+pub use frz_core::Error;
+pub use keys::{Action, KeyBindings, key_name, parse_key};
 pub use runtime::run;
 
 pub use crate::components::{progress, prompt, rows as utils, tables};
 pub use crate::input::QueryInput;
-pub use crate::style::{StyleConfig, Theme, builtin_themes, default_theme};
+pub use crate::style::{ColorDepth, StyleConfig, Theme, builtin_themes, default_theme};
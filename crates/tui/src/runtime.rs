@@ -1,14 +1,15 @@
 //! Application runtime and event loop.
 
 use std::collections::VecDeque;
-use std::io::stdout;
+use std::io::{self, stdout};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, mpsc};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-use anyhow::{Result, anyhow};
+use frz_core::error::{Error, Result};
 use frz_core::filesystem::search::{SearchData, SearchOutcome};
+use ratatui::DefaultTerminal;
 use ratatui::crossterm::event::{
 	self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind,
 };
@@ -17,6 +18,10 @@ use ratatui::crossterm::execute;
 use crate::App;
 
 /// Construct an [`App`] for the provided data and run it to completion.
+///
+/// # Errors
+///
+/// Returns [`Error::Terminal`] if the terminal cannot be driven.
 pub fn run(data: SearchData) -> Result<SearchOutcome> {
 	let mut app = App::new(data);
 	app.run()
@@ -24,45 +29,35 @@ pub fn run(data: SearchData) -> Result<SearchOutcome> {
 
 impl<'a> App<'a> {
 	/// Pump the terminal event loop until the user exits with a result.
+	///
+	/// # Errors
+	///
+	/// Returns [`Error::Terminal`] if the terminal cannot be set up, read
+	/// from or drawn to.
 	pub fn run(&mut self) -> Result<SearchOutcome> {
-		let mut terminal = ratatui::init();
-		terminal.clear()?;
-		execute!(stdout(), EnableMouseCapture)?;
+		let mut terminal = enter_terminal()?;
 
 		// Auto-enable preview if terminal is wide enough (unless explicitly set)
-		let initial_size = terminal.size()?;
+		let initial_size = terminal.size().map_err(Error::Terminal)?;
 		self.update_preview_responsive(initial_size.width);
 
 		self.hydrate_initial_results();
 
-		let (event_tx, event_rx) = mpsc::channel();
-		let event_loop_running = Arc::new(AtomicBool::new(true));
-		let event_loop_flag = Arc::clone(&event_loop_running);
-
-		let event_thread = thread::spawn(move || -> Result<()> {
-			while event_loop_flag.load(Ordering::Relaxed) {
-				if event::poll(Duration::from_millis(50))? {
-					let event = event::read()?;
-					if event_tx.send(event).is_err() {
-						break;
-					}
-				}
-			}
-			Ok(())
-		});
-
+		let mut events = EventReader::spawn();
 		let mut pending_events = VecDeque::new();
 
 		let result: Result<SearchOutcome> = 'event_loop: loop {
 			loop {
-				match event_rx.try_recv() {
+				match events.receiver.try_recv() {
 					Ok(Event::Resize(width, _)) => {
 						self.update_preview_responsive(width);
 					}
 					Ok(event) => pending_events.push_back(event),
 					Err(mpsc::TryRecvError::Empty) => break,
 					Err(mpsc::TryRecvError::Disconnected) => {
-						break 'event_loop Err(anyhow!("input event channel disconnected"));
+						break 'event_loop Err(Error::Terminal(std::io::Error::other(
+							"input event channel disconnected",
+						)));
 					}
 				}
 			}
@@ -88,28 +83,67 @@ impl<'a> App<'a> {
 				break Ok(outcome);
 			}
 
+			if let Some(path) = self.take_pending_edit() {
+				pending_events.clear();
+				// The terminal is already restored when this fails.
+				(terminal, events) = self.edit(path, terminal, events)?;
+			}
+
+			#[cfg(feature = "theme-watch")]
+			self.pump_theme_reloads();
+			self.flush_debounced_search(false);
 			self.pump_index_updates();
 			self.pump_search_results();
 			self.pump_preview_results();
 			self.throbber_state.calc_next();
 
-			terminal.draw(|frame| self.draw(frame))?;
+			if let Err(err) = terminal.draw(|frame| self.draw(frame)) {
+				break Err(Error::Terminal(err));
+			}
 
 			thread::sleep(Duration::from_millis(16));
 		};
 
-		ratatui::restore();
-		execute!(stdout(), DisableMouseCapture)?;
-
-		event_loop_running.store(false, Ordering::Relaxed);
-		match event_thread.join() {
-			Ok(join_result) => join_result?,
-			Err(err) => std::panic::resume_unwind(err),
-		}
+		leave_terminal()?;
+		events.stop()?;
 
 		result
 	}
 
+	/// Hand the terminal to the editor for `path`, then take it back.
+	///
+	/// Input is no longer read while the editor runs, and the terminal is
+	/// set up again from scratch afterwards, so the next draw repaints the
+	/// whole screen.
+	fn edit(
+		&mut self,
+		path: std::path::PathBuf,
+		terminal: DefaultTerminal,
+		events: EventReader,
+	) -> Result<(DefaultTerminal, EventReader)> {
+		let mut command = match self.editor_command(&path) {
+			Ok(command) => command,
+			Err(message) => {
+				self.show_status(message);
+				return Ok((terminal, events));
+			}
+		};
+		let stopped = events.stop();
+		drop(terminal);
+		leave_terminal()?;
+		stopped?;
+
+		let status = command.status();
+
+		let terminal = enter_terminal()?;
+		// Some graphics protocols stop working once another program used
+		// the terminal, so detect the backend again before input resumes.
+		#[cfg(feature = "media-preview")]
+		crate::components::preview::image::reprobe();
+		self.resume_after_edit(status);
+		Ok((terminal, EventReader::spawn()))
+	}
+
 	fn hydrate_initial_results(&mut self) {
 		if !self.search.has_issued_query() {
 			self.mark_query_dirty();
@@ -117,3 +151,57 @@ impl<'a> App<'a> {
 		}
 	}
 }
+
+/// Switch to the alternate screen in raw mode with mouse capture.
+fn enter_terminal() -> Result<DefaultTerminal> {
+	let mut terminal = ratatui::init();
+	terminal.clear().map_err(Error::Terminal)?;
+	execute!(stdout(), EnableMouseCapture).map_err(Error::Terminal)?;
+	Ok(terminal)
+}
+
+/// Undo [`enter_terminal`].
+fn leave_terminal() -> Result<()> {
+	ratatui::restore();
+	execute!(stdout(), DisableMouseCapture).map_err(Error::Terminal)
+}
+
+/// Thread forwarding terminal events to the UI until it is stopped.
+struct EventReader {
+	receiver: mpsc::Receiver<Event>,
+	running: Arc<AtomicBool>,
+	thread: JoinHandle<io::Result<()>>,
+}
+
+impl EventReader {
+	fn spawn() -> Self {
+		let (sender, receiver) = mpsc::channel();
+		let running = Arc::new(AtomicBool::new(true));
+		let flag = Arc::clone(&running);
+		let thread = thread::spawn(move || -> io::Result<()> {
+			while flag.load(Ordering::Relaxed) {
+				if event::poll(Duration::from_millis(50))? {
+					let event = event::read()?;
+					if sender.send(event).is_err() {
+						break;
+					}
+				}
+			}
+			Ok(())
+		});
+		Self {
+			receiver,
+			running,
+			thread,
+		}
+	}
+
+	/// Stop reading input and wait for the thread to finish.
+	fn stop(self) -> Result<()> {
+		self.running.store(false, Ordering::Relaxed);
+		match self.thread.join() {
+			Ok(result) => result.map_err(Error::Terminal),
+			Err(err) => std::panic::resume_unwind(err),
+		}
+	}
+}
@@ -0,0 +1,150 @@
+//! Terminal color depth detection and palette quantization.
+
+use std::env;
+
+use ratatui::style::Color;
+
+/// How many colors the terminal can display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorDepth {
+	/// 24-bit RGB colors are shown as-is.
+	#[default]
+	TrueColor,
+	/// Only the 256-color palette is available; RGB colors are mapped to
+	/// the nearest palette entry.
+	Ansi256,
+}
+
+impl ColorDepth {
+	/// Detect the color depth from the `COLORTERM` and `TERM` variables.
+	///
+	/// Terminals advertise 24-bit support with `COLORTERM=truecolor` (or
+	/// `24bit`) or a `TERM` ending in `-direct`. Anything else is assumed to
+	/// support the 256-color palette only.
+	#[must_use]
+	pub fn detect() -> Self {
+		let colorterm = env::var("COLORTERM").ok();
+		let term = env::var("TERM").ok();
+		Self::from_env(colorterm.as_deref(), term.as_deref())
+	}
+
+	fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+		let colorterm = colorterm.unwrap_or_default().trim();
+		let truecolor = colorterm.eq_ignore_ascii_case("truecolor")
+			|| colorterm.eq_ignore_ascii_case("24bit")
+			|| term.is_some_and(|term| term.ends_with("-direct"));
+		if truecolor {
+			Self::TrueColor
+		} else {
+			Self::Ansi256
+		}
+	}
+
+	/// Map `color` onto a color this depth can display.
+	#[must_use]
+	pub fn adapt(self, color: Color) -> Color {
+		match (self, color) {
+			(Self::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(ansi256_index(r, g, b)),
+			_ => color,
+		}
+	}
+}
+
+/// Channel values of the 6x6x6 color cube at palette indices 16–231.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Index of the palette entry closest to the given RGB color.
+///
+/// Only the color cube and the grayscale ramp (232–255) are considered: the
+/// first 16 entries are redefined by most terminal themes. Distance is
+/// squared euclidean in RGB space; on a tie the cube entry wins, so the
+/// mapping is deterministic.
+#[must_use]
+pub fn ansi256_index(r: u8, g: u8, b: u8) -> u8 {
+	let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+	let cube_index = 16 + 36 * ri + 6 * gi + bi;
+	let cube_distance = distance(
+		(r, g, b),
+		(
+			CUBE_LEVELS[ri as usize],
+			CUBE_LEVELS[gi as usize],
+			CUBE_LEVELS[bi as usize],
+		),
+	);
+
+	// The ramp runs from 8 to 238 in steps of 10.
+	let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+	let step = (average.saturating_sub(3) / 10).min(23) as u8;
+	let level = 8 + 10 * step;
+	let gray_distance = distance((r, g, b), (level, level, level));
+
+	if gray_distance < cube_distance {
+		232 + step
+	} else {
+		cube_index
+	}
+}
+
+fn nearest_level(value: u8) -> u8 {
+	let mut best = 0;
+	for (index, level) in CUBE_LEVELS.iter().enumerate() {
+		if value.abs_diff(*level) < value.abs_diff(CUBE_LEVELS[best]) {
+			best = index;
+		}
+	}
+	best as u8
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+	let channel = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+	channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rgb_colors_map_to_the_nearest_palette_entry() {
+		assert_eq!(ansi256_index(0, 0, 0), 16);
+		assert_eq!(ansi256_index(255, 255, 255), 231);
+		assert_eq!(ansi256_index(255, 0, 0), 196);
+		assert_eq!(ansi256_index(95, 135, 175), 67);
+		assert_eq!(ansi256_index(128, 128, 128), 244);
+		assert_eq!(ansi256_index(8, 8, 8), 232);
+		assert_eq!(ansi256_index(240, 240, 240), 255);
+		assert_eq!(ansi256_index(0x1e, 0x1e, 0x2e), 235);
+		assert_eq!(ansi256_index(100, 200, 50), 77);
+	}
+
+	#[test]
+	fn only_rgb_colors_are_downgraded() {
+		let depth = ColorDepth::Ansi256;
+		assert_eq!(depth.adapt(Color::Rgb(255, 0, 0)), Color::Indexed(196));
+		assert_eq!(depth.adapt(Color::Indexed(3)), Color::Indexed(3));
+		assert_eq!(depth.adapt(Color::Blue), Color::Blue);
+		let rgb = Color::Rgb(1, 2, 3);
+		assert_eq!(ColorDepth::TrueColor.adapt(rgb), rgb);
+	}
+
+	#[test]
+	fn truecolor_is_detected_from_the_environment() {
+		assert_eq!(
+			ColorDepth::from_env(Some("truecolor"), Some("xterm-256color")),
+			ColorDepth::TrueColor
+		);
+		assert_eq!(
+			ColorDepth::from_env(Some("24bit"), None),
+			ColorDepth::TrueColor
+		);
+		assert_eq!(
+			ColorDepth::from_env(None, Some("xterm-direct")),
+			ColorDepth::TrueColor
+		);
+		assert_eq!(
+			ColorDepth::from_env(None, Some("xterm-256color")),
+			ColorDepth::Ansi256
+		);
+		assert_eq!(ColorDepth::from_env(Some(""), None), ColorDepth::Ansi256);
+	}
+}
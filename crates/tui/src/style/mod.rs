@@ -4,27 +4,56 @@
 //! color schemes applied to the terminal UI, while additional styling options
 //! can be layered alongside themes in the future.
 
+/// Terminal color depth detection and palette quantization.
+pub mod color;
+
 /// The `theme` submodule contains definitions, built-in themes, and
 /// theme registration utilities.
 pub mod theme;
 
 /// Re-export theme types and utilities.
+pub use color::ColorDepth;
 pub use theme::{
 	AliasConflict, Theme, ThemeDescriptor, ThemeRegistration, ThemeRegistrationReport, bat_theme,
-	builtin_themes, by_name, default_theme, descriptors, names, register_additional,
+	builtin_themes, by_name, default_theme, descriptors, load_theme_file, names,
+	register_additional,
 };
+#[cfg(feature = "theme-watch")]
+pub use theme::{THEME_POLL_INTERVAL, THEME_RELOAD_DEBOUNCE, ThemeReload, ThemeWatcher};
 
-/// Aggregate container for styling knobs. Currently only color themes.
+/// Aggregate container for styling knobs: the color theme and the color
+/// depth it is rendered at.
 #[derive(Clone, Debug, Default)]
 pub struct StyleConfig {
 	/// The active theme for the UI.
 	pub theme: Theme,
+	/// Colors the terminal can display. Defaults to truecolor.
+	pub color_depth: ColorDepth,
 }
 
 impl StyleConfig {
 	/// Creates a new style configuration with the given theme.
 	#[must_use]
 	pub fn with_theme(theme: Theme) -> Self {
-		Self { theme }
+		Self {
+			theme,
+			color_depth: ColorDepth::default(),
+		}
+	}
+
+	/// Set the color depth, downgrading the current theme if needed.
+	#[must_use]
+	pub fn with_color_depth(mut self, color_depth: ColorDepth) -> Self {
+		self.color_depth = color_depth;
+		self.set_theme(self.theme);
+		self
+	}
+
+	/// Replace the theme, adapting it to the configured color depth.
+	pub fn set_theme(&mut self, theme: Theme) {
+		self.theme = match self.color_depth {
+			ColorDepth::TrueColor => theme,
+			ColorDepth::Ansi256 => theme.downgraded(),
+		};
 	}
 }
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::path::Path;
 
 use anyhow::{Context, Result, bail};
 use include_dir::{Dir, File};
@@ -14,7 +15,8 @@ pub(super) struct BuiltinThemes {
 
 #[derive(Debug, Deserialize)]
 struct ThemeConfig {
-	name: String,
+	#[serde(default)]
+	name: Option<String>,
 	#[serde(default)]
 	aliases: Vec<String>,
 	#[serde(default)]
@@ -25,18 +27,30 @@ struct ThemeConfig {
 }
 
 impl ThemeConfig {
-	fn into_document(self, context: &str) -> Result<ThemeDocument> {
+	/// Build the registration, naming it `name` when given and otherwise
+	/// after the declared `name`. A declared name that is overridden is kept
+	/// as an alias.
+	fn into_document(self, context: &str, name: Option<&str>) -> Result<ThemeDocument> {
+		let declared = self
+			.name
+			.map(|name| name.trim().to_string())
+			.filter(|name| !name.is_empty());
+		let (name, declared_alias) = match (name, declared) {
+			(Some(name), declared) => (name.to_string(), declared),
+			(None, Some(declared)) => (declared, None),
+			(None, None) => bail!("{context}: theme definitions need a `name`"),
+		};
 		let theme = self.styles.into_theme(&format!("{context}.styles"))?;
 
-		let mut registration = ThemeRegistration::new(self.name.clone(), theme);
+		let mut registration = ThemeRegistration::new(name, theme);
 
 		if let Some(bat_theme) = self.bat_theme {
 			registration = registration.with_bat_theme(bat_theme);
 		}
 
-		let registration = self
-			.aliases
+		let registration = declared_alias
 			.into_iter()
+			.chain(self.aliases)
 			.map(|alias| alias.trim().to_string())
 			.filter(|alias| !alias.is_empty())
 			.fold(registration, |registration, alias| {
@@ -154,6 +168,39 @@ pub(super) fn load_builtin_themes(dir: &Dir) -> Result<BuiltinThemes> {
 	})
 }
 
+/// Syntax of a theme definition file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(in crate::style::theme) enum ThemeFormat {
+	Toml,
+	Json,
+}
+
+impl ThemeFormat {
+	/// Pick the format from a file's extension, defaulting to TOML.
+	pub(in crate::style::theme) fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+			_ => Self::Toml,
+		}
+	}
+}
+
+/// Parse a theme definition with the built-in fields, registering it as
+/// `name` and naming `context` in error messages.
+pub(in crate::style::theme) fn parse_theme_registration(
+	contents: &str,
+	format: ThemeFormat,
+	name: &str,
+	context: &str,
+) -> Result<ThemeRegistration> {
+	let config: ThemeConfig = match format {
+		ThemeFormat::Toml => toml::from_str(contents).map_err(anyhow::Error::from),
+		ThemeFormat::Json => serde_json::from_str(contents).map_err(anyhow::Error::from),
+	}
+	.with_context(|| format!("failed to parse theme definition in {context}"))?;
+	Ok(config.into_document(context, Some(name))?.registration)
+}
+
 fn parse_theme_document(file: &File) -> Result<ThemeDocument> {
 	let path = file.path();
 	let contents = file
@@ -163,7 +210,7 @@ fn parse_theme_document(file: &File) -> Result<ThemeDocument> {
 	let config: ThemeConfig = toml::from_str(contents)
 		.with_context(|| format!("failed to parse built-in theme definition in {path:?}"))?;
 
-	config.into_document(&format!("{path:?}"))
+	config.into_document(&format!("{path:?}"), None)
 }
 
 fn parse_color(input: &str) -> Result<Color> {
@@ -4,6 +4,7 @@ use std::sync::OnceLock;
 
 use include_dir::{Dir, include_dir};
 use loader::{BuiltinThemes, load_builtin_themes};
+pub(super) use loader::{ThemeFormat, parse_theme_registration};
 
 use crate::style::theme::types::{Theme, ThemeRegistration};
 
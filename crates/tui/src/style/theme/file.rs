@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+use frz_core::error::{Error, Result};
+
+use super::builtins::{ThemeFormat, parse_theme_registration};
+use super::types::ThemeRegistration;
+
+/// Load a theme from a file written in the built-in theme format.
+///
+/// Files ending in `.json` are parsed as JSON and everything else as TOML.
+/// The theme is named after the file stem, so `~/themes/dusk.toml` becomes
+/// `dusk`; a `name` declared inside the file is kept as an alias. The theme
+/// is only parsed; pass it to [`register_additional`] to make it available
+/// by name.
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if the file cannot be read and [`Error::Config`] if
+/// it is not a valid theme definition.
+///
+/// [`register_additional`]: super::register_additional
+pub fn load_theme_file(path: impl AsRef<Path>) -> Result<ThemeRegistration> {
+	let path = path.as_ref();
+	let context = path.display().to_string();
+	let name = path
+		.file_stem()
+		.map(|stem| stem.to_string_lossy().into_owned())
+		.filter(|stem| !stem.trim().is_empty())
+		.ok_or_else(|| Error::config(format!("cannot name a theme after {context}")))?;
+	let contents = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+	parse_theme_registration(&contents, ThemeFormat::from_path(path), &name, &context)
+		.map_err(|err| Error::config(format!("{err:#}")))
+}
+
+#[cfg(test)]
+mod tests {
+	use ratatui::style::Color;
+
+	use super::*;
+
+	#[test]
+	fn theme_files_use_the_builtin_format() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("mine.toml");
+		fs::write(
+			&path,
+			"name = \"declared\"\n\
+			 bat_theme = \"Nord\"\n\
+			 [styles.header]\n[styles.row_highlight]\n[styles.prompt]\n[styles.empty]\n\
+			 [styles.highlight]\nfg = \"#ff0000\"\n",
+		)
+		.expect("write theme");
+
+		let registration = load_theme_file(&path).expect("valid theme");
+		assert_eq!(registration.name, "mine");
+		assert_eq!(registration.aliases, ["declared"]);
+		assert_eq!(registration.bat_theme.as_deref(), Some("Nord"));
+		assert_eq!(registration.theme.highlight.fg, Some(Color::Rgb(255, 0, 0)));
+
+		fs::write(&path, "bat_theme = \"Nord\"\n").expect("write theme");
+		let err = load_theme_file(&path).expect_err("missing styles");
+		assert!(matches!(err, Error::Config { .. }), "{err:?}");
+		assert!(matches!(
+			load_theme_file(dir.path().join("missing.toml")),
+			Err(Error::Io { .. })
+		));
+	}
+
+	#[test]
+	fn json_theme_files_are_named_after_the_file() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("dusk.json");
+		fs::write(
+			&path,
+			r##"{"styles": {"header": {}, "row_highlight": {"bg": "#102030"}, "prompt": {},
+			"empty": {}, "highlight": {"modifiers": ["bold"]}}}"##,
+		)
+		.expect("write theme");
+
+		let registration = load_theme_file(&path).expect("valid theme");
+		assert_eq!(registration.name, "dusk");
+		assert!(registration.aliases.is_empty());
+		assert_eq!(
+			registration.theme.row_highlight.bg,
+			Some(Color::Rgb(16, 32, 48))
+		);
+
+		fs::write(&path, "{\"styles\": ").expect("write theme");
+		let err = load_theme_file(&path).expect_err("truncated json");
+		assert!(err.to_string().contains("dusk.json"), "{err}");
+	}
+}
@@ -1,12 +1,18 @@
 mod builtins;
+mod file;
 mod registry;
 mod types;
+#[cfg(feature = "theme-watch")]
+mod watch;
 
 pub use builtins::default_theme;
+pub use file::load_theme_file;
 pub use registry::{bat_theme, by_name, descriptors, names, register_additional};
 pub use types::{
 	AliasConflict, Theme, ThemeDescriptor, ThemeRegistration, ThemeRegistrationReport,
 };
+#[cfg(feature = "theme-watch")]
+pub use watch::{THEME_POLL_INTERVAL, THEME_RELOAD_DEBOUNCE, ThemeReload, ThemeWatcher};
 
 /// Return the built-in themes bundled with the application.
 #[must_use]
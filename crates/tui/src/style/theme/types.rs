@@ -1,5 +1,7 @@
 use ratatui::style::{Color, Style};
 
+use crate::style::color::ColorDepth;
+
 /// A theme containing styles for various UI elements.
 #[derive(Debug, Clone, Copy)]
 pub struct Theme {
@@ -29,6 +31,24 @@ impl Theme {
 	pub fn tab_highlight_style(&self) -> Style {
 		Style::new().bg(self.header.bg.unwrap_or(Color::Reset))
 	}
+
+	/// Returns a copy with every RGB color replaced by the nearest entry of
+	/// the 256-color palette, for terminals without truecolor support.
+	#[must_use]
+	pub fn downgraded(&self) -> Self {
+		let downgrade = |style: Style| Style {
+			fg: style.fg.map(|color| ColorDepth::Ansi256.adapt(color)),
+			bg: style.bg.map(|color| ColorDepth::Ansi256.adapt(color)),
+			..style
+		};
+		Self {
+			header: downgrade(self.header),
+			row_highlight: downgrade(self.row_highlight),
+			prompt: downgrade(self.prompt),
+			empty: downgrade(self.empty),
+			highlight: downgrade(self.highlight),
+		}
+	}
 }
 
 /// Describes a theme instance that can be registered with the UI.
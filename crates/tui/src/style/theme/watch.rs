@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, thread};
+
+use super::file::load_theme_file;
+use super::registry::register_additional;
+
+/// How often the watched file is checked for changes.
+pub const THEME_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the file must stay unchanged before it is reloaded, so that a
+/// burst of saves causes a single reload.
+pub const THEME_RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Result of reloading a watched theme file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeReload {
+	/// The file was parsed and registered; look it up with [`by_name`].
+	///
+	/// [`by_name`]: super::by_name
+	Reloaded {
+		/// Name the theme is registered under, taken from the file stem.
+		name: String,
+	},
+	/// The file could not be loaded. The previously registered theme is
+	/// left untouched.
+	Failed {
+		/// Description of the problem.
+		message: String,
+	},
+}
+
+/// Watches a theme file on a background thread and re-registers it whenever
+/// it changes.
+///
+/// The file is polled rather than subscribed to, so no platform watcher is
+/// required. The thread stops when the watcher is dropped.
+#[derive(Debug)]
+pub struct ThemeWatcher {
+	updates: Receiver<ThemeReload>,
+	running: Arc<AtomicBool>,
+}
+
+impl ThemeWatcher {
+	/// Start watching `path` with the default poll and debounce intervals.
+	#[must_use]
+	pub fn spawn(path: impl Into<PathBuf>) -> Self {
+		Self::with_intervals(path, THEME_POLL_INTERVAL, THEME_RELOAD_DEBOUNCE)
+	}
+
+	/// Start watching `path`, checking it every `poll` and reloading once it
+	/// has been unchanged for `debounce`.
+	#[must_use]
+	pub fn with_intervals(path: impl Into<PathBuf>, poll: Duration, debounce: Duration) -> Self {
+		let (tx, updates) = mpsc::channel();
+		let running = Arc::new(AtomicBool::new(true));
+		let flag = Arc::clone(&running);
+		let path = path.into();
+		thread::spawn(move || watch(&path, poll, debounce, &flag, &tx));
+		Self { updates, running }
+	}
+
+	/// Return the next reload, if one is waiting.
+	#[must_use]
+	pub fn try_recv(&self) -> Option<ThemeReload> {
+		self.updates.try_recv().ok()
+	}
+}
+
+impl Drop for ThemeWatcher {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::Relaxed);
+	}
+}
+
+/// Modification time and length, which change on every save in practice.
+type Fingerprint = Option<(SystemTime, u64)>;
+
+fn fingerprint(path: &Path) -> Fingerprint {
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn watch(
+	path: &Path,
+	poll: Duration,
+	debounce: Duration,
+	running: &AtomicBool,
+	tx: &Sender<ThemeReload>,
+) {
+	let mut seen = fingerprint(path);
+	let mut changed_at: Option<Instant> = None;
+	while running.load(Ordering::Relaxed) {
+		thread::sleep(poll);
+		let current = fingerprint(path);
+		if current != seen {
+			seen = current;
+			changed_at = Some(Instant::now());
+			continue;
+		}
+		let Some(at) = changed_at else {
+			continue;
+		};
+		if at.elapsed() < debounce {
+			continue;
+		}
+		changed_at = None;
+
+		let reload = match load_theme_file(path) {
+			Ok(registration) => {
+				let name = registration.name.clone();
+				let _ = register_additional([registration]);
+				ThemeReload::Reloaded { name }
+			}
+			Err(err) => ThemeReload::Failed {
+				message: err.to_string(),
+			},
+		};
+		if tx.send(reload).is_err() {
+			break;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ratatui::style::Color;
+
+	use super::*;
+	use crate::style::theme::by_name;
+
+	fn theme_source(name: &str, highlight: &str) -> String {
+		format!(
+			"name = \"{name}\"\n\
+			 [styles.header]\n[styles.row_highlight]\n[styles.prompt]\n[styles.empty]\n\
+			 [styles.highlight]\nfg = \"{highlight}\"\n"
+		)
+	}
+
+	fn next_reload(watcher: &ThemeWatcher) -> ThemeReload {
+		let deadline = Instant::now() + Duration::from_secs(5);
+		loop {
+			if let Some(reload) = watcher.try_recv() {
+				return reload;
+			}
+			assert!(Instant::now() < deadline, "no reload arrived");
+			thread::sleep(Duration::from_millis(5));
+		}
+	}
+
+	#[test]
+	fn changed_files_are_reloaded_and_bad_edits_keep_the_last_theme() {
+		let dir = tempfile::tempdir().expect("tempdir");
+		let path = dir.path().join("watch-test.toml");
+		fs::write(&path, theme_source("watch-test", "#000001")).expect("write");
+		let watcher = ThemeWatcher::with_intervals(
+			&path,
+			Duration::from_millis(5),
+			Duration::from_millis(30),
+		);
+		thread::sleep(Duration::from_millis(20));
+
+		// Several quick saves cause a single reload of the final contents.
+		fs::write(&path, theme_source("watch-test", "#00000")).expect("write");
+		fs::write(&path, theme_source("watch-test", "#000002")).expect("write");
+		assert_eq!(
+			next_reload(&watcher),
+			ThemeReload::Reloaded {
+				name: "watch-test".into()
+			}
+		);
+		let highlight = |name| by_name(name).expect("registered").highlight.fg;
+		assert_eq!(highlight("watch-test"), Some(Color::Rgb(0, 0, 2)));
+
+		fs::write(&path, "not a theme").expect("write");
+		assert!(matches!(next_reload(&watcher), ThemeReload::Failed { .. }));
+		assert_eq!(highlight("watch-test"), Some(Color::Rgb(0, 0, 2)));
+		assert!(watcher.try_recv().is_none());
+	}
+}